@@ -0,0 +1,114 @@
+//! Generating everything a distribution package ships besides the binary.
+//!
+//! `generate-assets --output-dir <DIR>` writes, in one pass,
+//!
+//! - the man pages of all commands and subcommands to `man/`,
+//! - the completion scripts for all shells `clap_complete` supports to
+//!   `completions/`, named the way each shell looks them up,
+//! - the reference of the `.cfe` program language to `doc/`.
+//!
+//! The language reference is compiled into the binary, so a package build
+//! only needs the binary itself, not the source tree.
+
+use std::path::{Path, PathBuf};
+
+use clap::ValueEnum as _;
+use clap_complete::aot::Shell;
+
+use crate::error::Error;
+
+/// The name of the binary the assets are generated for.
+const BIN_NAME: &str = "cargo-for-each";
+
+/// The reference of the program language.
+const LANGUAGE_REFERENCE: &str = include_str!("../doc/cfe-language.md");
+
+/// Writes all assets to `output_dir` and returns the paths of the written
+/// files.
+///
+/// # Errors
+///
+/// Returns an error if a directory or a file cannot be written.
+pub fn generate(output_dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    let mut written = Vec::new();
+
+    let man_dir = output_dir.join("man");
+    fs_err::create_dir_all(&man_dir).map_err(Error::GenerateManpageError)?;
+    clap_mangen::generate_to(
+        <crate::Options as clap::CommandFactory>::command(),
+        &man_dir,
+    )
+    .map_err(Error::GenerateManpageError)?;
+    let mut man_pages: Vec<PathBuf> = fs_err::read_dir(&man_dir)
+        .map_err(Error::GenerateManpageError)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<_, _>>()
+        .map_err(Error::GenerateManpageError)?;
+    man_pages.sort();
+    written.extend(man_pages);
+
+    let completion_dir = output_dir.join("completions");
+    fs_err::create_dir_all(&completion_dir).map_err(Error::GenerateShellCompletionError)?;
+    for shell in Shell::value_variants() {
+        let mut command = <crate::Options as clap::CommandFactory>::command();
+        written.push(
+            clap_complete::generate_to(*shell, &mut command, BIN_NAME, &completion_dir)
+                .map_err(Error::GenerateShellCompletionError)?,
+        );
+    }
+
+    let doc_dir = output_dir.join("doc");
+    fs_err::create_dir_all(&doc_dir).map_err(Error::IoError)?;
+    let reference = doc_dir.join("cfe-language.md");
+    fs_err::write(&reference, LANGUAGE_REFERENCE).map_err(Error::IoError)?;
+    written.push(reference);
+
+    Ok(written)
+}
+
+/// Implements the `generate-assets` command.
+///
+/// # Errors
+///
+/// Returns an error if a directory or a file cannot be written.
+#[expect(clippy::print_stdout, reason = "This is part of the UI, not logging")]
+pub fn command(output_dir: &Path) -> Result<(), Error> {
+    let written = generate(output_dir)?;
+    for path in &written {
+        println!("{}", path.display());
+    }
+    println!(
+        "Wrote {} file(s) to {}",
+        written.len(),
+        output_dir.display()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::generate;
+
+    #[test]
+    fn all_assets_are_written() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempfile::tempdir()?;
+        let dir = temp_dir.path();
+        let written = generate(dir)?;
+        for expected in [
+            "man/cargo-for-each.1",
+            "man/cargo-for-each-task-run.1",
+            "completions/cargo-for-each.bash",
+            "completions/_cargo-for-each",
+            "completions/cargo-for-each.fish",
+            "completions/cargo-for-each.elv",
+            "completions/_cargo-for-each.ps1",
+            "doc/cfe-language.md",
+        ] {
+            assert!(
+                written.contains(&dir.join(expected)) && dir.join(expected).is_file(),
+                "{expected} is written"
+            );
+        }
+        Ok(())
+    }
+}
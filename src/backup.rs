@@ -0,0 +1,318 @@
+//! Moving a setup between machines.
+//!
+//! `config export --output <file>` bundles the configuration file (the
+//! registered workspaces and crates, tags and target sets) and the programs
+//! and templates in the configuration directory into one TOML file, which
+//! can be copied to another machine or checked into a dotfiles repository.
+//! With `--include-tasks` the definitions of the tasks (their programs and
+//! resolved targets) are bundled as well; their execution state is not, as it
+//! only makes sense on the machine that ran them.
+//!
+//! `config import --input <file>` writes the bundled files into the
+//! configuration directory.  Files that exist with a different content are
+//! left alone and reported unless `--force` is given, so an import does not
+//! silently replace local changes.
+
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+
+use crate::error::Error;
+
+/// The version of the bundle format written by this version.
+const VERSION: u32 = 1;
+
+/// The files and directories of the configuration directory that are
+/// bundled, relative to it.
+const ENTRIES: [&str; 3] = ["cargo-for-each.toml", "programs", "templates"];
+
+/// The directory of the task definitions, bundled with `--include-tasks`.
+const TASKS_DIR: &str = "tasks";
+
+/// Parameters for `config export`
+#[derive(clap::Parser, Debug, Clone)]
+pub struct ExportParameters {
+    /// The file to write the bundle to.
+    #[clap(long)]
+    pub output: PathBuf,
+    /// Also bundle the definitions of all tasks (not their execution state).
+    #[clap(long)]
+    pub include_tasks: bool,
+}
+
+/// Parameters for `config import`
+#[derive(clap::Parser, Debug, Clone)]
+pub struct ImportParameters {
+    /// The bundle to read.
+    #[clap(long)]
+    pub input: PathBuf,
+    /// Replace existing files that differ from the bundled ones.
+    #[clap(long)]
+    pub force: bool,
+}
+
+/// The `config` subcommand
+#[derive(clap::Parser, Debug, Clone)]
+pub enum SubCommand {
+    /// Bundle the configuration, programs and templates into one file.
+    Export(ExportParameters),
+    /// Write the files of a bundle into the configuration directory.
+    Import(ImportParameters),
+}
+
+/// Parameters for the config subcommand
+#[derive(clap::Parser, Debug, Clone)]
+pub struct Parameters {
+    /// the config subcommand to run
+    #[clap(subcommand)]
+    pub sub_command: SubCommand,
+}
+
+/// The files of a setup.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Bundle {
+    /// The version of the bundle format.
+    pub version: u32,
+    /// The content of each file, by its path relative to the configuration
+    /// directory, with `/` as separator.
+    #[serde(default)]
+    pub files: BTreeMap<String, String>,
+}
+
+/// Adds the files below `path`, named `name` relative to the configuration
+/// directory, to `files`, skipping hidden files.
+///
+/// # Errors
+///
+/// Returns an error if a file or directory cannot be read.
+fn collect(path: &Path, name: &str, files: &mut BTreeMap<String, String>) -> Result<(), Error> {
+    if path.is_file() {
+        let content = fs_err::read_to_string(path)
+            .map_err(|e| Error::CouldNotReadBundledFile(path.to_path_buf(), e))?;
+        files.insert(name.to_owned(), content);
+    } else if path.is_dir() {
+        for entry in fs_err::read_dir(path)
+            .map_err(|e| Error::CouldNotReadBundledFile(path.to_path_buf(), e))?
+        {
+            let entry_path = entry
+                .map_err(|e| Error::CouldNotReadBundledFile(path.to_path_buf(), e))?
+                .path();
+            let Some(file_name) = entry_path.file_name().and_then(|n| n.to_str()) else {
+                continue;
+            };
+            if file_name.starts_with('.') {
+                continue;
+            }
+            collect(&entry_path, &format!("{name}/{file_name}"), files)?;
+        }
+    }
+    Ok(())
+}
+
+/// Bundles the files of the configuration directory `config_dir`.
+///
+/// # Errors
+///
+/// Returns an error if a file cannot be read.
+pub fn export(config_dir: &Path, include_tasks: bool) -> Result<Bundle, Error> {
+    let mut files = BTreeMap::new();
+    let tasks = include_tasks.then_some(TASKS_DIR);
+    for name in ENTRIES.into_iter().chain(tasks) {
+        collect(&config_dir.join(name), name, &mut files)?;
+    }
+    Ok(Bundle {
+        version: VERSION,
+        files,
+    })
+}
+
+/// Returns the path of the bundled file `name` below `config_dir`.
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidBundledPath`] if `name` is not a relative path
+/// staying inside `config_dir`.
+fn target_path(config_dir: &Path, name: &str) -> Result<PathBuf, Error> {
+    let relative = Path::new(name);
+    if name.is_empty()
+        || !relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return Err(Error::InvalidBundledPath(name.to_owned()));
+    }
+    Ok(config_dir.join(relative))
+}
+
+/// How a bundled file was imported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// The file did not exist and was created.
+    Created,
+    /// The file existed with a different content and was replaced.
+    Replaced,
+    /// The file already had the bundled content.
+    Unchanged,
+    /// The file exists with a different content and was left alone.
+    Conflict,
+}
+
+/// Writes the files of `bundle` into `config_dir`, replacing differing files
+/// only if `force` is set, and returns what happened to each file.
+///
+/// # Errors
+///
+/// Returns an error if the bundle has an unsupported version or an invalid
+/// path, or if a file cannot be written.
+pub fn import(
+    bundle: &Bundle,
+    config_dir: &Path,
+    force: bool,
+) -> Result<Vec<(String, Outcome)>, Error> {
+    if bundle.version != VERSION {
+        return Err(Error::UnsupportedBundleVersion(bundle.version));
+    }
+    let mut outcomes = Vec::new();
+    for (name, content) in &bundle.files {
+        let path = target_path(config_dir, name)?;
+        let outcome = match fs_err::read_to_string(&path) {
+            Ok(existing) if &existing == content => Outcome::Unchanged,
+            Ok(_) if !force => Outcome::Conflict,
+            Ok(_) => Outcome::Replaced,
+            Err(_) => Outcome::Created,
+        };
+        if matches!(outcome, Outcome::Created | Outcome::Replaced) {
+            if let Some(parent) = path.parent() {
+                fs_err::create_dir_all(parent)
+                    .map_err(|e| Error::CouldNotWriteBundledFile(parent.to_path_buf(), e))?;
+            }
+            crate::utils::write_atomically(&path, content)
+                .map_err(|e| Error::CouldNotWriteBundledFile(path.clone(), e))?;
+        }
+        outcomes.push((name.clone(), outcome));
+    }
+    Ok(outcomes)
+}
+
+/// Runs the `config` subcommand.
+///
+/// # Errors
+///
+/// Returns an error if the bundle cannot be written, read or imported.
+#[instrument]
+#[expect(clippy::print_stdout, reason = "This is part of the UI, not logging")]
+pub async fn command(parameters: Parameters, environment: crate::Environment) -> Result<(), Error> {
+    let config_dir = crate::config_dir_path(&environment)?;
+    match parameters.sub_command {
+        SubCommand::Export(export_parameters) => {
+            let bundle = export(&config_dir, export_parameters.include_tasks)?;
+            let content = toml::to_string(&bundle).map_err(Error::CouldNotSerializeBundle)?;
+            crate::utils::write_atomically(&export_parameters.output, content)
+                .map_err(|e| Error::CouldNotWriteBundle(export_parameters.output.clone(), e))?;
+            println!(
+                "Exported {} file(s) to {}",
+                bundle.files.len(),
+                export_parameters.output.display()
+            );
+        }
+        SubCommand::Import(import_parameters) => {
+            let path = &import_parameters.input;
+            let content = fs_err::read_to_string(path)
+                .map_err(|e| Error::CouldNotReadBundle(path.clone(), e))?;
+            let bundle: Bundle = toml::from_str(&content)
+                .map_err(|e| Error::CouldNotParseBundle(path.clone(), e))?;
+            let _lock = crate::config_lock::acquire(&environment).await?;
+            let outcomes = import(&bundle, &config_dir, import_parameters.force)?;
+            let mut conflicts = 0_usize;
+            for (name, outcome) in &outcomes {
+                match outcome {
+                    Outcome::Created => println!("created {name}"),
+                    Outcome::Replaced => println!("replaced {name}"),
+                    Outcome::Unchanged => {}
+                    Outcome::Conflict => {
+                        println!("conflict: {name} differs from the bundle");
+                        conflicts = conflicts.saturating_add(1);
+                    }
+                }
+            }
+            if conflicts > 0 {
+                return Err(Error::BundleConflicts(conflicts));
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::{Bundle, Outcome, export, import};
+    use crate::error::Error;
+
+    #[test]
+    fn bundles_round_trip_without_overwriting() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempfile::tempdir()?;
+        let source = temp_dir.path().join("source");
+        fs_err::create_dir_all(source.join("programs"))?;
+        fs_err::create_dir_all(source.join("tasks").join("bump"))?;
+        fs_err::write(source.join("cargo-for-each.toml"), "workspaces = []\n")?;
+        fs_err::write(source.join("programs").join("fmt.cfe"), "select crates;\n")?;
+        fs_err::write(source.join("programs").join(".hidden"), "")?;
+        fs_err::write(
+            source.join("tasks").join("bump").join("program.cfe"),
+            "select workspaces;\n",
+        )?;
+
+        let bundle = export(&source, false)?;
+        assert_eq!(
+            bundle.files.keys().collect::<Vec<_>>(),
+            ["cargo-for-each.toml", "programs/fmt.cfe"]
+        );
+        assert_eq!(
+            export(&source, true)?.files.keys().collect::<Vec<_>>(),
+            [
+                "cargo-for-each.toml",
+                "programs/fmt.cfe",
+                "tasks/bump/program.cfe"
+            ]
+        );
+
+        let target = temp_dir.path().join("target");
+        fs_err::create_dir_all(&target)?;
+        fs_err::write(
+            target.join("cargo-for-each.toml"),
+            "workspaces = [] # mine\n",
+        )?;
+        assert_eq!(
+            import(&bundle, &target, false)?,
+            [
+                ("cargo-for-each.toml".to_owned(), Outcome::Conflict),
+                ("programs/fmt.cfe".to_owned(), Outcome::Created),
+            ]
+        );
+        assert_eq!(
+            import(&bundle, &target, true)?,
+            [
+                ("cargo-for-each.toml".to_owned(), Outcome::Replaced),
+                ("programs/fmt.cfe".to_owned(), Outcome::Unchanged),
+            ]
+        );
+        assert_eq!(export(&target, false)?, bundle);
+
+        let escaping = Bundle {
+            files: [("../outside".to_owned(), String::new())].into(),
+            ..bundle
+        };
+        assert!(
+            matches!(
+                import(&escaping, &target, true),
+                Err(Error::InvalidBundledPath(ref name)) if name == "../outside"
+            ),
+            "paths outside of the configuration directory are refused"
+        );
+        Ok(())
+    }
+}
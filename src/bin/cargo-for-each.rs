@@ -54,7 +54,9 @@ async fn main() -> Result<(), cargo_for_each::error::Error> {
     } else {
         None
     };
-    let registry = registry.with(file_layer);
+    let registry = registry
+        .with(file_layer)
+        .with(cargo_for_each::tasks::target_log::Layer);
     #[cfg(target_os = "linux")]
     let registry = registry.with(
         tracing_journald::layer()
@@ -0,0 +1,575 @@
+//! Boilerplate files kept in sync across all registered targets.
+//!
+//! A template is a file such as a CI workflow, `rustfmt.toml` or a license
+//! that should exist with (nearly) the same content in every crate or
+//! workspace.  Templates are registered once and stored as
+//! `templates/<name>.toml` in the configuration directory.  `templates apply`
+//! renders each template for each target and writes it to its destination
+//! inside the target, and `templates check` reports targets whose file is
+//! missing or differs from the rendered template.
+//!
+//! Per-target values are substituted with `${package.field|filter}` (a field
+//! of the target's package in `cargo metadata`, using the same filters as
+//! interpolations in `.cfe` programs) and `${target.manifest_dir}` /
+//! `${target.workspace_manifest_dir}`.  Any other `${` is left untouched, so
+//! shell variables and GitHub Actions expressions need no escaping.
+
+use std::collections::BTreeMap;
+use std::collections::btree_map::Entry;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+
+use crate::error::Error;
+
+/// The `templates` sub command
+#[derive(clap::Parser, Debug, Clone)]
+pub enum TemplateSubCommand {
+    /// List registered templates.
+    List,
+    /// Register a file as a template.
+    Add(AddTemplateParameters),
+    /// Remove a registered template.
+    Remove(RemoveTemplateParameters),
+    /// Render templates and write them into every target.
+    Apply(SelectTemplatesParameters),
+    /// Report targets whose files differ from the rendered templates.
+    Check(SelectTemplatesParameters),
+}
+
+/// A template in the JSON output of `templates list`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TemplateView {
+    /// The name of the template.
+    pub name: String,
+    /// Whether the template is written into crates or workspaces.
+    pub scope: Scope,
+    /// The path of the file relative to the target's manifest directory.
+    pub destination: PathBuf,
+}
+
+/// Parameters for the `templates` top-level subcommand.
+#[derive(clap::Parser, Debug, Clone)]
+pub struct TemplateParameters {
+    /// The `templates` subcommand to run.
+    #[clap(subcommand)]
+    pub sub_command: TemplateSubCommand,
+}
+
+/// Which targets a template is written into.
+#[derive(
+    clap::ValueEnum,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Default,
+    Serialize,
+    Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    /// Every registered crate.
+    #[default]
+    Crate,
+    /// Every registered workspace.
+    Workspace,
+}
+
+/// Parameters for registering a template.
+#[derive(clap::Parser, Debug, Clone)]
+pub struct AddTemplateParameters {
+    /// The name of the template.
+    #[clap(long)]
+    pub name: String,
+    /// The file whose content becomes the template.
+    #[clap(long)]
+    pub file: PathBuf,
+    /// Path of the rendered file, relative to each target's manifest dir.
+    #[clap(long)]
+    pub destination: PathBuf,
+    /// Whether the template is written into every crate or every workspace.
+    #[clap(long, value_enum, default_value_t)]
+    pub scope: Scope,
+    /// Replace an existing template of the same name.
+    #[clap(long)]
+    pub force: bool,
+}
+
+/// Parameters for removing a template.
+#[derive(clap::Parser, Debug, Clone)]
+pub struct RemoveTemplateParameters {
+    /// The name of the template.
+    #[clap(long)]
+    pub name: String,
+}
+
+/// Parameters for rendering templates into the targets.
+#[derive(clap::Parser, Debug, Clone)]
+pub struct SelectTemplatesParameters {
+    /// Only use these templates (can be repeated); all templates if omitted.
+    #[clap(long = "name", short = 'n')]
+    pub names: Vec<String>,
+}
+
+/// A registered template.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Template {
+    /// Path of the rendered file, relative to the target's manifest dir.
+    pub destination: PathBuf,
+    /// Whether the template is written into every crate or every workspace.
+    #[serde(default)]
+    pub scope: Scope,
+    /// The unrendered content.
+    pub content: String,
+}
+
+/// The values available to a template for one target.
+#[derive(Debug, Clone)]
+pub struct Variables {
+    /// The directory of the target's `Cargo.toml`.
+    pub manifest_dir: PathBuf,
+    /// The directory of the `Cargo.toml` of the target's workspace.
+    pub workspace_manifest_dir: PathBuf,
+    /// The target's package entry from `cargo metadata`, if it has one
+    /// (virtual workspaces do not).
+    pub package: Option<serde_json::Value>,
+}
+
+/// How a target's file relates to the rendered template.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Drift {
+    /// The file has the rendered content.
+    UpToDate,
+    /// The file does not exist.
+    Missing,
+    /// The file exists with different content.
+    Differs,
+}
+
+/// Returns the directory the templates are stored in.
+///
+/// # Errors
+///
+/// Returns an error if the config directory path cannot be determined.
+pub fn dir_path(environment: &crate::Environment) -> Result<PathBuf, Error> {
+    Ok(crate::config_dir_path(environment)?.join("templates"))
+}
+
+/// Returns the path of the file storing the template `name`.
+///
+/// # Errors
+///
+/// Returns an error if the config directory path cannot be determined.
+pub fn file_path(name: &str, environment: &crate::Environment) -> Result<PathBuf, Error> {
+    let mut file_name = name.to_owned();
+    file_name.push_str(".toml");
+    Ok(dir_path(environment)?.join(file_name))
+}
+
+/// Loads all registered templates, by name.
+///
+/// # Errors
+///
+/// Returns an error if the templates directory or a template cannot be read
+/// or parsed.
+pub fn load_all(environment: &crate::Environment) -> Result<BTreeMap<String, Template>, Error> {
+    let dir = dir_path(environment)?;
+    let mut templates = BTreeMap::new();
+    if !dir.exists() {
+        return Ok(templates);
+    }
+    for entry in fs_err::read_dir(&dir).map_err(|e| Error::CouldNotReadTemplate(dir.clone(), e))? {
+        let path = entry
+            .map_err(|e| Error::CouldNotReadTemplate(dir.clone(), e))?
+            .path();
+        let Some(name) = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .and_then(|n| n.strip_suffix(".toml"))
+        else {
+            continue;
+        };
+        let content = fs_err::read_to_string(&path)
+            .map_err(|e| Error::CouldNotReadTemplate(path.clone(), e))?;
+        let template =
+            toml::from_str(&content).map_err(|e| Error::CouldNotParseTemplate(path.clone(), e))?;
+        templates.insert(name.to_owned(), template);
+    }
+    Ok(templates)
+}
+
+/// Replaces the `${package.…}` and `${target.…}` references in `content`
+/// with the values for one target.
+///
+/// # Errors
+///
+/// Returns an error if a reference is not terminated by `}`, if a referenced
+/// value does not exist for the target, or if a filter is unknown or cannot be
+/// applied to the value.
+pub fn render(content: &str, variables: &Variables) -> Result<String, Error> {
+    let mut result = String::with_capacity(content.len());
+    let mut rest = content;
+    while let Some(start) = rest.find("${") {
+        let (before, reference_start) = rest.split_at(start);
+        result.push_str(before);
+        let inner = reference_start.get(2..).unwrap_or_default();
+        if !inner.starts_with("package.") && !inner.starts_with("target.") {
+            result.push_str("${");
+            rest = inner;
+            continue;
+        }
+        let (reference, after) = inner
+            .split_once('}')
+            .ok_or_else(|| Error::InvalidInterpolation(reference_start.to_owned()))?;
+        let mut pipeline = reference.split('|');
+        let lookup = pipeline.next().unwrap_or_default().trim();
+        let mut value = lookup_variable(lookup, variables)?;
+        for filter in pipeline {
+            value = crate::tasks::filters::apply(filter.trim(), &value)?;
+        }
+        result.push_str(&value);
+        rest = after;
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+/// Returns the value of a single `package.…` or `target.…` reference.
+fn lookup_variable(lookup: &str, variables: &Variables) -> Result<String, Error> {
+    let not_found =
+        || Error::TemplateVariableNotFound(lookup.to_owned(), variables.manifest_dir.clone());
+    match lookup {
+        "target.manifest_dir" => return Ok(variables.manifest_dir.display().to_string()),
+        "target.workspace_manifest_dir" => {
+            return Ok(variables.workspace_manifest_dir.display().to_string());
+        }
+        _ => {}
+    }
+    let field_path = lookup.strip_prefix("package.").ok_or_else(not_found)?;
+    let mut current = variables.package.as_ref().ok_or_else(not_found)?;
+    for segment in field_path.split('.') {
+        current = current.get(segment).ok_or_else(not_found)?;
+    }
+    match current {
+        serde_json::Value::String(s) => Ok(s.clone()),
+        serde_json::Value::Null => Err(not_found()),
+        other => Ok(other.to_string()),
+    }
+}
+
+/// Compares the file at `path` with the `rendered` template.
+#[must_use]
+pub fn drift(path: &Path, rendered: &str) -> Drift {
+    match fs_err::read_to_string(path) {
+        Ok(existing) if existing == rendered => Drift::UpToDate,
+        Ok(_) => Drift::Differs,
+        Err(_) if path.exists() => Drift::Differs,
+        Err(_) => Drift::Missing,
+    }
+}
+
+/// Collects the variables of every target of `scope`, by manifest dir.
+///
+/// `cargo metadata` is called once per workspace.
+///
+/// # Errors
+///
+/// Returns an error if `cargo metadata` fails for a workspace or its packages
+/// cannot be serialized.
+fn target_variables(config: &crate::Config, scope: Scope) -> Result<Vec<Variables>, Error> {
+    let mut packages_by_workspace: BTreeMap<PathBuf, BTreeMap<PathBuf, serde_json::Value>> =
+        BTreeMap::new();
+    let targets: Vec<(PathBuf, PathBuf)> = match scope {
+        Scope::Crate => config
+            .crates
+            .iter()
+            .map(|c| (c.manifest_dir.clone(), c.workspace_manifest_dir.clone()))
+            .collect(),
+        Scope::Workspace => config
+            .workspaces
+            .iter()
+            .map(|w| (w.manifest_dir.clone(), w.manifest_dir.clone()))
+            .collect(),
+    };
+    let mut variables = Vec::new();
+    for (manifest_dir, workspace_manifest_dir) in targets {
+        if !packages_by_workspace.contains_key(&workspace_manifest_dir) {
+            let manifest_path = workspace_manifest_dir.join("Cargo.toml");
+            let metadata = cargo_metadata::MetadataCommand::new()
+                .manifest_path(&manifest_path)
+                .no_deps()
+                .exec()
+                .map_err(|e| Error::CargoMetadataError(manifest_path, e))?;
+            let mut packages = BTreeMap::new();
+            for package in &metadata.packages {
+                if let Some(dir) = package.manifest_path.parent() {
+                    packages.insert(
+                        dir.as_std_path().to_path_buf(),
+                        serde_json::to_value(package)
+                            .map_err(Error::CouldNotSerializeMetadataSnapshot)?,
+                    );
+                }
+            }
+            packages_by_workspace.insert(workspace_manifest_dir.clone(), packages);
+        }
+        let package = packages_by_workspace
+            .get(&workspace_manifest_dir)
+            .and_then(|packages| packages.get(&manifest_dir))
+            .cloned();
+        variables.push(Variables {
+            manifest_dir,
+            workspace_manifest_dir,
+            package,
+        });
+    }
+    Ok(variables)
+}
+
+/// Returns the templates named in `names`, or all templates if it is empty.
+///
+/// # Errors
+///
+/// Returns an error if the templates cannot be loaded or a named template does
+/// not exist.
+fn selected_templates(
+    names: &[String],
+    environment: &crate::Environment,
+) -> Result<BTreeMap<String, Template>, Error> {
+    let mut templates = load_all(environment)?;
+    if names.is_empty() {
+        return Ok(templates);
+    }
+    names
+        .iter()
+        .map(|name| {
+            templates
+                .remove_entry(name)
+                .ok_or_else(|| Error::TemplateNotFound(name.clone()))
+        })
+        .collect()
+}
+
+/// implementation of the templates subcommand
+///
+/// # Errors
+///
+/// Propagates errors from the chosen subcommand.
+#[instrument]
+pub async fn template_command(
+    template_parameters: TemplateParameters,
+    environment: crate::Environment,
+    run_options: crate::RunOptions,
+) -> Result<(), Error> {
+    match template_parameters.sub_command {
+        TemplateSubCommand::List => list_command(&environment, &run_options)?,
+        TemplateSubCommand::Add(params) => add_command(params, &environment)?,
+        TemplateSubCommand::Remove(params) => {
+            let path = file_path(&params.name, &environment)?;
+            if !path.exists() {
+                return Err(Error::TemplateNotFound(params.name));
+            }
+            fs_err::remove_file(&path).map_err(|e| Error::CouldNotWriteTemplate(path, e))?;
+        }
+        TemplateSubCommand::Apply(params) => apply_command(&params, &environment, false)?,
+        TemplateSubCommand::Check(params) => apply_command(&params, &environment, true)?,
+    }
+    Ok(())
+}
+
+/// Prints the registered templates.
+///
+/// # Errors
+///
+/// Returns an error if the templates cannot be loaded.
+#[expect(clippy::print_stdout, reason = "This is part of the UI, not logging")]
+fn list_command(
+    environment: &crate::Environment,
+    run_options: &crate::RunOptions,
+) -> Result<(), Error> {
+    let templates = load_all(environment)?;
+    if crate::porcelain::is_json(run_options.format, None, "templates list")? {
+        let views: Vec<TemplateView> = templates
+            .into_iter()
+            .map(|(name, template)| TemplateView {
+                name,
+                scope: template.scope,
+                destination: template.destination,
+            })
+            .collect();
+        println!("{}", crate::porcelain::json(&views)?);
+        return Ok(());
+    }
+    if templates.is_empty() {
+        println!("No templates registered.");
+    }
+    for (name, template) in &templates {
+        let scope = match template.scope {
+            Scope::Crate => "crate",
+            Scope::Workspace => "workspace",
+        };
+        println!("- {name} ({scope}): {}", template.destination.display());
+    }
+    Ok(())
+}
+
+/// Registers the file in `params` as a template.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read, if the destination is not a
+/// relative path, if a template of the same name exists and `--force` is not
+/// given, or if the template cannot be written.
+fn add_command(
+    params: AddTemplateParameters,
+    environment: &crate::Environment,
+) -> Result<(), Error> {
+    if !params.destination.is_relative() {
+        return Err(Error::TemplateDestinationNotRelative(params.destination));
+    }
+    let content = fs_err::read_to_string(&params.file)
+        .map_err(|e| Error::CouldNotReadTemplate(params.file.clone(), e))?;
+    let path = file_path(&params.name, environment)?;
+    if path.exists() && !params.force {
+        return Err(Error::AlreadyExists(format!("template {}", params.name)));
+    }
+    let template = Template {
+        destination: params.destination,
+        scope: params.scope,
+        content,
+    };
+    let serialized = toml::to_string(&template).map_err(Error::CouldNotSerializeTemplate)?;
+    if let Some(dir) = path.parent() {
+        fs_err::create_dir_all(dir)
+            .map_err(|e| Error::CouldNotWriteTemplate(dir.to_path_buf(), e))?;
+    }
+    fs_err::write(&path, serialized).map_err(|e| Error::CouldNotWriteTemplate(path, e))
+}
+
+/// Renders the selected templates for every target and writes files that are
+/// out of date, or with `check_only` only reports them.
+///
+/// # Errors
+///
+/// Returns an error if the templates or the configuration cannot be loaded,
+/// if a template cannot be rendered for a target, if a file cannot be written
+/// or, with `check_only`, if any file is out of date.
+#[expect(clippy::print_stdout, reason = "This is part of the UI, not logging")]
+fn apply_command(
+    params: &SelectTemplatesParameters,
+    environment: &crate::Environment,
+    check_only: bool,
+) -> Result<(), Error> {
+    let templates = selected_templates(&params.names, environment)?;
+    let config = crate::Config::load(environment)?;
+    let mut variables_by_scope = BTreeMap::new();
+    let mut out_of_date = 0_usize;
+    for (name, template) in &templates {
+        let targets = match variables_by_scope.entry(template.scope) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(target_variables(&config, template.scope)?),
+        };
+        for variables in targets.iter() {
+            let rendered = render(&template.content, variables)?;
+            let path = variables.manifest_dir.join(&template.destination);
+            let state = drift(&path, &rendered);
+            if state == Drift::UpToDate {
+                continue;
+            }
+            out_of_date = out_of_date.saturating_add(1);
+            if check_only {
+                let what = if state == Drift::Missing {
+                    "missing"
+                } else {
+                    "differs"
+                };
+                println!("{name}: {} {what}", path.display());
+                continue;
+            }
+            if let Some(dir) = path.parent() {
+                fs_err::create_dir_all(dir)
+                    .map_err(|e| Error::CouldNotWriteRenderedTemplate(dir.to_path_buf(), e))?;
+            }
+            fs_err::write(&path, &rendered)
+                .map_err(|e| Error::CouldNotWriteRenderedTemplate(path.clone(), e))?;
+            println!("{name}: wrote {}", path.display());
+        }
+    }
+    if check_only && out_of_date > 0 {
+        return Err(Error::TemplatesOutOfDate(out_of_date));
+    }
+    if out_of_date == 0 {
+        println!("All templates are up to date.");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use pretty_assertions::assert_eq;
+
+    use super::{Drift, Variables, drift, render};
+
+    fn variables() -> Variables {
+        Variables {
+            manifest_dir: PathBuf::from("/src/ws/my-crate"),
+            workspace_manifest_dir: PathBuf::from("/src/ws"),
+            package: Some(serde_json::json!({
+                "name": "my-crate",
+                "license": "MIT",
+                "metadata": { "ci": { "msrv": "1.85" } },
+                "repository": null,
+            })),
+        }
+    }
+
+    #[test]
+    fn render_substitutes_package_and_target_values() -> Result<(), Box<dyn std::error::Error>> {
+        let content = "# ${package.name|snake_case|uppercase} (${package.license})\n\
+                       msrv: ${package.metadata.ci.msrv}\n\
+                       dir: ${target.manifest_dir|file_name}\n\
+                       key: ${{ secrets.TOKEN }} ${HOME}\n";
+        assert_eq!(
+            render(content, &variables())?,
+            "# MY_CRATE (MIT)\nmsrv: 1.85\ndir: my-crate\nkey: ${{ secrets.TOKEN }} ${HOME}\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn render_fails_for_missing_values() {
+        assert!(
+            render("${package.repository}", &variables()).is_err(),
+            "null fields are missing"
+        );
+        assert!(
+            render("${package.name", &variables()).is_err(),
+            "references need a closing brace"
+        );
+        let virtual_workspace = Variables {
+            package: None,
+            ..variables()
+        };
+        assert!(
+            render("${package.name}", &virtual_workspace).is_err(),
+            "virtual workspaces have no package"
+        );
+    }
+
+    #[test]
+    fn drift_compares_file_content() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempfile::tempdir()?;
+        let path = temp_dir.path().join("rustfmt.toml");
+        assert_eq!(drift(&path, "edition = \"2024\"\n"), Drift::Missing);
+        fs_err::write(&path, "edition = \"2021\"\n")?;
+        assert_eq!(drift(&path, "edition = \"2024\"\n"), Drift::Differs);
+        assert_eq!(drift(&path, "edition = \"2021\"\n"), Drift::UpToDate);
+        Ok(())
+    }
+}
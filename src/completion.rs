@@ -0,0 +1,180 @@
+//! Generating and installing shell completion scripts.
+//!
+//! `generate-shell-completion --output-file <FILE>` writes the completion
+//! script for a shell to any file.  With `--install` it is written to the
+//! location the shell loads per-user completions from instead, and the lines
+//! that have to be added to the shell's startup file, if any, are printed.
+//! Without `--shell` the shell is detected from `$SHELL`.
+
+use std::path::{Path, PathBuf};
+
+use clap_complete::aot::Shell;
+
+use crate::error::Error;
+
+/// The name of the binary the completion is generated for.
+const BIN_NAME: &str = "cargo-for-each";
+
+/// Where the completion script of a shell is installed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    /// The file the completion script is written to.
+    pub path: PathBuf,
+    /// The startup file of the shell and the lines to add to it, if the
+    /// shell does not load completions from `path` by itself.
+    pub rc_hint: Option<(PathBuf, String)>,
+}
+
+/// Returns the conventional per-user location of the completion script for
+/// `shell`, given the user's home, data (`XDG_DATA_HOME`) and configuration
+/// (`XDG_CONFIG_HOME`) directories.
+///
+/// # Errors
+///
+/// Returns an error for shells without a conventional per-user location.
+pub fn location(
+    shell: Shell,
+    home_dir: &Path,
+    data_dir: &Path,
+    config_dir: &Path,
+) -> Result<Location, Error> {
+    match shell {
+        // bash-completion loads completions from here on demand
+        Shell::Bash => Ok(Location {
+            path: data_dir
+                .join("bash-completion")
+                .join("completions")
+                .join(BIN_NAME),
+            rc_hint: None,
+        }),
+        Shell::Fish => Ok(Location {
+            path: config_dir
+                .join("fish")
+                .join("completions")
+                .join(format!("{BIN_NAME}.fish")),
+            rc_hint: None,
+        }),
+        Shell::Zsh => Ok(Location {
+            path: home_dir.join(".zfunc").join(format!("_{BIN_NAME}")),
+            rc_hint: Some((
+                home_dir.join(".zshrc"),
+                "fpath=(~/.zfunc $fpath)\nautoload -Uz compinit && compinit".to_owned(),
+            )),
+        }),
+        Shell::Elvish => Ok(Location {
+            path: config_dir
+                .join("elvish")
+                .join("lib")
+                .join(format!("{BIN_NAME}.elv")),
+            rc_hint: Some((
+                config_dir.join("elvish").join("rc.elv"),
+                format!("use {BIN_NAME}"),
+            )),
+        }),
+        other => Err(Error::CompletionInstallNotSupported(other.to_string())),
+    }
+}
+
+/// Writes the completion script for `shell` to `path`, creating its parent
+/// directory.
+///
+/// # Errors
+///
+/// Returns an error if the directory or the file cannot be created.
+fn write(shell: Shell, path: &Path) -> Result<(), Error> {
+    if let Some(dir) = path.parent() {
+        fs_err::create_dir_all(dir).map_err(Error::GenerateShellCompletionError)?;
+    }
+    let mut f = fs_err::File::create(path).map_err(Error::GenerateShellCompletionError)?;
+    let mut c = <crate::Options as clap::CommandFactory>::command();
+    clap_complete::generate(shell, &mut c, BIN_NAME, &mut f);
+    Ok(())
+}
+
+/// Implements the `generate-shell-completion` command.
+///
+/// # Errors
+///
+/// Returns an error if no shell is given and it cannot be detected, if the
+/// per-user directories cannot be determined, if `--install` is not supported
+/// for the shell or if the script cannot be written.
+#[expect(clippy::print_stdout, reason = "This is part of the UI, not logging")]
+pub fn generate(
+    shell: Option<Shell>,
+    output_file: Option<PathBuf>,
+    install: bool,
+) -> Result<(), Error> {
+    let shell = shell
+        .or_else(Shell::from_env)
+        .ok_or(Error::CouldNotDetectShell)?;
+    let (path, rc_hint) = match output_file {
+        Some(path) if !install => (path, None),
+        _ => {
+            let location = location(
+                shell,
+                &dirs::home_dir().ok_or(Error::CouldNotDetermineHomeDir)?,
+                &dirs::data_dir().ok_or(Error::CouldNotDetermineDataDir)?,
+                &dirs::config_dir().ok_or(Error::CouldNotDetermineUserConfigDir)?,
+            )?;
+            (location.path, location.rc_hint)
+        }
+    };
+    write(shell, &path)?;
+    if install {
+        println!("Installed {shell} completion to {}", path.display());
+        match rc_hint {
+            Some((rc_file, lines)) => {
+                println!(
+                    "Add these lines to {} if they are not there yet:",
+                    rc_file.display()
+                );
+                println!("{lines}");
+            }
+            None => println!("It is loaded automatically in new shells."),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use clap_complete::aot::Shell;
+    use pretty_assertions::assert_eq;
+
+    use super::location;
+
+    #[test]
+    fn completions_are_installed_in_per_user_locations() -> Result<(), Box<dyn std::error::Error>> {
+        let locate = |shell| {
+            location(
+                shell,
+                Path::new("/home/u"),
+                Path::new("/home/u/.local/share"),
+                Path::new("/home/u/.config"),
+            )
+        };
+        let bash = locate(Shell::Bash)?;
+        assert_eq!(
+            bash.path,
+            PathBuf::from("/home/u/.local/share/bash-completion/completions/cargo-for-each")
+        );
+        assert_eq!(bash.rc_hint, None);
+        assert_eq!(
+            locate(Shell::Fish)?.path,
+            PathBuf::from("/home/u/.config/fish/completions/cargo-for-each.fish")
+        );
+        let zsh = locate(Shell::Zsh)?;
+        assert_eq!(zsh.path, PathBuf::from("/home/u/.zfunc/_cargo-for-each"));
+        assert_eq!(
+            zsh.rc_hint.map(|(rc_file, _)| rc_file),
+            Some(PathBuf::from("/home/u/.zshrc"))
+        );
+        assert!(
+            locate(Shell::PowerShell).is_err(),
+            "PowerShell has no per-user completion directory"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,193 @@
+//! Keeping concurrent changes of the configuration apart.
+//!
+//! Commands that change `cargo-for-each.toml` load it, modify it and save it
+//! again.  Two of them running at the same time, e.g. a `target refresh` in
+//! one terminal and a `target add` in another, would each save their own
+//! version and the one finishing last would silently drop the changes of the
+//! other.  Such commands hold an advisory lock on `cargo-for-each.lock` next
+//! to the configuration file from loading to saving.  Another process waits
+//! up to [`TIMEOUT`] for the lock and then fails with
+//! [`Error::ConfigLocked`].  Commands only reading the configuration do not
+//! take the lock.
+//!
+//! Within one process the lock is reentrant, so a command may call others
+//! that take it as well.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::error::Error;
+
+/// How long to wait for another process to release the lock.
+pub const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How often to try to take the lock while waiting.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A lock held by this process.
+#[derive(Debug)]
+struct Held {
+    /// The locked file; closing it releases the lock.
+    _file: std::fs::File,
+    /// The number of guards for the lock.
+    count: usize,
+}
+
+/// The locks held by this process, by the path of their lock file.
+static HELD: LazyLock<Mutex<BTreeMap<PathBuf, Held>>> =
+    LazyLock::new(|| Mutex::new(BTreeMap::new()));
+
+/// Releases the lock when the last guard for it is dropped.
+#[derive(Debug)]
+pub struct Guard {
+    /// The lock file.
+    path: PathBuf,
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        let mut held = HELD
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        if let Some(lock) = held.get_mut(&self.path) {
+            lock.count = lock.count.saturating_sub(1);
+            if lock.count == 0 {
+                held.remove(&self.path);
+            }
+        }
+    }
+}
+
+/// Takes the lock on `path` if this process holds it already or no other
+/// process does.
+///
+/// `file` is the opened lock file, which is moved into [`HELD`] when the lock
+/// is taken.  Returns `None` if another process holds the lock.
+fn try_acquire(path: &Path, file: &mut Option<std::fs::File>) -> Result<Option<Guard>, Error> {
+    let mut held = HELD
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    if let Some(lock) = held.get_mut(path) {
+        lock.count = lock.count.saturating_add(1);
+        return Ok(Some(Guard {
+            path: path.to_path_buf(),
+        }));
+    }
+    let Some(opened) = file.take() else {
+        return Ok(None);
+    };
+    match opened.try_lock() {
+        Ok(()) => {
+            held.insert(
+                path.to_path_buf(),
+                Held {
+                    _file: opened,
+                    count: 1,
+                },
+            );
+            drop(held);
+            Ok(Some(Guard {
+                path: path.to_path_buf(),
+            }))
+        }
+        Err(std::fs::TryLockError::WouldBlock) => {
+            drop(held);
+            *file = Some(opened);
+            Ok(None)
+        }
+        Err(std::fs::TryLockError::Error(e)) => {
+            Err(Error::CouldNotOpenLockFile(path.to_path_buf(), e))
+        }
+    }
+}
+
+/// Takes the lock on the file at `path`, waiting up to `timeout` for another
+/// process holding it.
+///
+/// The wait does not block the thread, so the runtime keeps running other
+/// tasks meanwhile.
+///
+/// # Errors
+///
+/// Returns [`Error::ConfigLocked`] if the lock is still held by another
+/// process after `timeout`, or an error if the lock file cannot be opened.
+pub async fn acquire_file(path: &Path, timeout: Duration) -> Result<Guard, Error> {
+    if let Some(parent) = path.parent() {
+        fs_err::create_dir_all(parent).map_err(Error::CouldNotCreateConfigFileParentDirs)?;
+    }
+    let mut file = Some(
+        std::fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(path)
+            .map_err(|e| Error::CouldNotOpenLockFile(path.to_path_buf(), e))?,
+    );
+    let deadline = Instant::now().checked_add(timeout);
+    loop {
+        if let Some(guard) = try_acquire(path, &mut file)? {
+            return Ok(guard);
+        }
+        if deadline.is_none_or(|deadline| Instant::now() >= deadline) {
+            return Err(Error::ConfigLocked(path.to_path_buf()));
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+/// Takes the lock on the configuration of `environment`.
+///
+/// # Errors
+///
+/// Returns [`Error::ConfigLocked`] if another process holds it for longer
+/// than [`TIMEOUT`], or an error if the lock file cannot be opened.
+pub async fn acquire(environment: &crate::Environment) -> Result<Guard, Error> {
+    acquire_file(
+        &crate::config_dir_path(environment)?.join("cargo-for-each.lock"),
+        TIMEOUT,
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use pretty_assertions::assert_eq;
+
+    use super::{HELD, acquire_file};
+    use crate::error::Error;
+
+    #[tokio::test]
+    async fn locks_are_reentrant_and_exclusive() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempfile::tempdir()?;
+        let path = temp_dir.path().join("cargo-for-each.lock");
+        let outer = acquire_file(&path, Duration::ZERO).await?;
+        let inner = acquire_file(&path, Duration::ZERO).await?;
+        drop(inner);
+        assert_eq!(
+            HELD.lock().map(|held| held.contains_key(&path)).ok(),
+            Some(true),
+            "the outer guard still holds the lock"
+        );
+
+        // another open file stands in for another process
+        let other = std::fs::OpenOptions::new().write(true).open(&path)?;
+        assert!(
+            matches!(other.try_lock(), Err(std::fs::TryLockError::WouldBlock)),
+            "the lock is exclusive"
+        );
+        drop(outer);
+        other.try_lock()?;
+        assert!(
+            matches!(
+                acquire_file(&path, Duration::from_millis(100)).await,
+                Err(Error::ConfigLocked(ref locked)) if locked == &path
+            ),
+            "a lock held elsewhere is reported"
+        );
+        Ok(())
+    }
+}
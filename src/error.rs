@@ -42,9 +42,24 @@ pub enum Error {
     /// error generating shell completion
     #[error("error generating shell completion: {0}")]
     GenerateShellCompletionError(#[source] std::io::Error),
+    /// the recorder chosen for a task is not installed
+    #[error("the recorder {0} is not installed; install it or choose another one with `record`")]
+    RecorderNotAvailable(String),
     /// error determining user config dir
     #[error("error determining user config dir")]
     CouldNotDetermineUserConfigDir,
+    /// error determining the user's home dir
+    #[error("error determining user home dir")]
+    CouldNotDetermineHomeDir,
+    /// error determining user data dir
+    #[error("error determining user data dir")]
+    CouldNotDetermineDataDir,
+    /// no shell was given and none could be detected from `$SHELL`
+    #[error("could not detect the shell from $SHELL; pass --shell")]
+    CouldNotDetectShell,
+    /// `--install` is not supported for the shell
+    #[error("installing completions is not supported for {0}; use --output-file")]
+    CompletionInstallNotSupported(String),
     /// error reading config file
     #[error("error reading config file: {0}")]
     CouldNotReadConfigFile(#[source] std::io::Error),
@@ -91,12 +106,23 @@ pub enum Error {
     /// error turning a absolute manifest path into a canonical one
     #[error("error turning the absolute manifest path {0} into a canonical one: {1}")]
     CouldNotDetermineCanonicalManifestPath(std::path::PathBuf, #[source] std::io::Error),
+    /// the given directory has no Cargo.toml, neither directly nor in a subdirectory
+    #[error("{0} contains no Cargo.toml, neither directly nor in a subdirectory")]
+    NoManifestInDirectory(std::path::PathBuf),
+    /// the given directory has no Cargo.toml but several of its subdirectories do
+    #[error(
+        "{0} contains no Cargo.toml, but several subdirectories do ({1}); pass one of them or use target discover"
+    )]
+    AmbiguousManifestDirectory(std::path::PathBuf, String),
     /// the given manifest path has no parent directory
     #[error("the given manifest path {0} has no parent directory")]
     ManifestPathHasNoParentDir(std::path::PathBuf),
     /// the task of the given name already exists
     #[error("{0} already exists")]
     AlreadyExists(String),
+    /// two workspaces would get the same task name in `task create-per-workspace`
+    #[error("workspaces {1} and {2} would both get the task name {0}")]
+    AmbiguousWorkspaceTaskName(String, std::path::PathBuf, std::path::PathBuf),
     /// we called cargo metadata on a directory with a Cargo.toml
     /// but the output did not contain a package with the manifest_path
     /// pointing to that Cargo.toml
@@ -121,6 +147,9 @@ pub enum Error {
     /// The specified command was not found in PATH
     #[error("command not found: {0}")]
     CommandNotFound(String),
+    /// sandboxing was requested but the sandbox tool is not installed
+    #[error("--sandbox requires {0} (bubblewrap) to be installed")]
+    SandboxUnavailable(String),
     /// error formatting a string
     #[error("error formatting a string: {0}")]
     FmtError(#[from] std::fmt::Error),
@@ -155,9 +184,32 @@ pub enum Error {
     /// some steps failed
     #[error("some steps failed")]
     SomeStepsFailed,
-    /// circular dependency or deadlock detected
-    #[error("circular dependency or deadlock detected")]
-    CircularDependency,
+    /// the limit of `--max-failures` was reached
+    #[error("aborted after {0} targets failed; this many failures usually have a common cause")]
+    TooManyFailures(usize),
+    /// error serializing the invocation of a step
+    #[error("error serializing step invocation: {0}")]
+    CouldNotSerializeInvocation(#[source] toml::ser::Error),
+    /// another process is running the task
+    #[error(
+        "task {0} is already being run by process {1} on {2} since {3}; if it is not, use task unlock --name {0}"
+    )]
+    TaskIsRunning(String, u32, String, String),
+    /// error serializing the lock of a running task
+    #[error("error serializing the run lock: {0}")]
+    CouldNotSerializeRunLock(#[source] toml::ser::Error),
+    /// error removing the lock of a task
+    #[error("error removing the run lock {0}: {1}")]
+    CouldNotRemoveRunLock(std::path::PathBuf, #[source] std::io::Error),
+    /// error serializing the approval of a step
+    #[error("error serializing step approval: {0}")]
+    CouldNotSerializeApproval(#[source] toml::ser::Error),
+    /// the given directory is not a target of the task
+    #[error("{0} is not a target of the task")]
+    TargetNotInTask(std::path::PathBuf),
+    /// the targets of a task depend on each other in a cycle
+    #[error("circular dependency between targets: {0}")]
+    CircularDependency(String),
     /// error serializing cargo metadata snapshot to JSON
     #[error("error serializing cargo metadata snapshot: {0}")]
     CouldNotSerializeMetadataSnapshot(#[source] serde_json::Error),
@@ -174,10 +226,26 @@ pub enum Error {
     #[error("field '{1}' not found in package for snapshot '{0}'")]
     SnapshotFieldNotFound(String, String),
     /// a `${{...}}` interpolation reference is malformed
+    #[error("invalid interpolation reference '{0}': must be '${{name.field}}' or '${{variable}}'")]
+    InvalidInterpolation(String),
+    /// a `${{variable}}` interpolation names an unknown built-in variable
     #[error(
-        "invalid interpolation reference '{0}': must be '${{name.field}}' with at least one field after the name"
+        "unknown interpolation variable '{0}'; supported variables are: {supported}, or use '${{snapshot.field}}'",
+        supported = crate::tasks::variables::NAMES.join(", ")
     )]
-    InvalidInterpolation(String),
+    UnknownVariable(String),
+    /// a package variable was used for a target without a package
+    #[error("variable '{0}' needs a package, but {1} has none (a virtual workspace?)")]
+    NoPackageForVariable(String, std::path::PathBuf),
+    /// a `${{name.field|filter}}` interpolation names an unknown filter
+    #[error(
+        "unknown interpolation filter '{0}'; supported filters are: {supported}",
+        supported = crate::tasks::filters::NAMES.join(", ")
+    )]
+    UnknownInterpolationFilter(String),
+    /// an interpolation filter cannot be applied to the interpolated value
+    #[error("interpolation filter '{0}' cannot be applied to value '{1}'")]
+    InterpolationFilterNotApplicable(String, String),
     /// the env file specified in a `with_env_file` block could not be read
     #[error("could not read env file {0}: {1}")]
     CouldNotReadEnvFile(std::path::PathBuf, #[source] std::io::Error),
@@ -202,7 +270,283 @@ pub enum Error {
     /// error parsing resolved program snapshot file
     #[error("error parsing resolved program snapshot file {0}: {1}")]
     CouldNotParseResolvedProgram(std::path::PathBuf, #[source] toml::de::Error),
+    /// the command of a `wait_until` step did not succeed before the timeout
+    #[error("gave up waiting for {0} to succeed after {1}s")]
+    WaitUntilTimedOut(String, u64),
+    /// the cursor given to `task handoff` does not point at a manual step
+    #[error("cursor {0} does not point at a manual_step in the task's program")]
+    NotAManualStep(String),
     /// a cursor string given to `task continue` could not be parsed
     #[error("invalid cursor string {0:?}: {1}")]
     InvalidCursorString(String, String),
+    /// error serializing a journal entry
+    #[error("error serializing journal entry: {0}")]
+    CouldNotSerializeJournalEntry(#[source] serde_json::Error),
+    /// error writing the run journal
+    #[error("error writing run journal {0}: {1}")]
+    CouldNotWriteJournal(std::path::PathBuf, #[source] std::io::Error),
+    /// error reading the run journal
+    #[error("error reading run journal {0}: {1}")]
+    CouldNotReadJournal(std::path::PathBuf, #[source] std::io::Error),
+    /// error parsing an entry of the run journal
+    #[error("error parsing run journal {0}: {1}")]
+    CouldNotParseJournal(std::path::PathBuf, #[source] serde_json::Error),
+    /// error parsing the JSON output of cargo-outdated
+    #[error("error parsing cargo-outdated report for {0}: {1}")]
+    CouldNotParseOutdatedReport(std::path::PathBuf, #[source] serde_json::Error),
+    /// error serializing the program source record of a task
+    #[error("error serializing program source record: {0}")]
+    CouldNotSerializeProgramSource(#[source] toml::ser::Error),
+    /// error writing the program source record of a task
+    #[error("error writing program source record {0}: {1}")]
+    CouldNotWriteProgramSource(std::path::PathBuf, #[source] std::io::Error),
+    /// error reading the program source record of a task
+    #[error("error reading program source record {0}: {1}")]
+    CouldNotReadProgramSource(std::path::PathBuf, #[source] std::io::Error),
+    /// error parsing the program source record of a task
+    #[error("error parsing program source record {0}: {1}")]
+    CouldNotParseProgramSource(std::path::PathBuf, #[source] toml::de::Error),
+    /// the task does not record which program file it was created from
+    #[error("task {0} does not record its program source; pass --program explicitly")]
+    UnknownProgramSource(String),
+    /// the new program would invalidate the execution state recorded for the task
+    #[error(
+        "the new program for task {0} changes statements that already have execution state; rewind the task or pass --force"
+    )]
+    IncompatibleProgramChange(String),
+    /// error reading a template or the templates directory
+    #[error("error reading template {0}: {1}")]
+    CouldNotReadTemplate(std::path::PathBuf, #[source] std::io::Error),
+    /// error parsing a stored template
+    #[error("error parsing template {0}: {1}")]
+    CouldNotParseTemplate(std::path::PathBuf, #[source] toml::de::Error),
+    /// error serializing a template
+    #[error("error serializing template: {0}")]
+    CouldNotSerializeTemplate(#[source] toml::ser::Error),
+    /// error writing or removing a stored template
+    #[error("error writing template {0}: {1}")]
+    CouldNotWriteTemplate(std::path::PathBuf, #[source] std::io::Error),
+    /// error writing a rendered template into a target
+    #[error("error writing rendered template to {0}: {1}")]
+    CouldNotWriteRenderedTemplate(std::path::PathBuf, #[source] std::io::Error),
+    /// the template of the given name is not registered
+    #[error("template {0} is not registered")]
+    TemplateNotFound(String),
+    /// the destination of a template must be inside the target
+    #[error("template destination {0} must be a relative path")]
+    TemplateDestinationNotRelative(std::path::PathBuf),
+    /// a template references a value that does not exist for a target
+    #[error("template value ${{{0}}} does not exist for {1}")]
+    TemplateVariableNotFound(String, std::path::PathBuf),
+    /// `templates check` found files that differ from the rendered templates
+    #[error("{0} file(s) differ from their templates")]
+    TemplatesOutOfDate(usize),
+    /// a task with `elevate` or `run_as` statements needs cached sudo credentials
+    #[error(
+        "the task runs statements via sudo, but sudo would ask for a password; run `sudo -v` first"
+    )]
+    SudoCredentialsNotCached,
+    /// `elevate` and `run_as` do not work inside the sandbox
+    #[error("statements with elevate or run_as cannot run with --sandbox")]
+    ElevationInSandbox,
+    /// `--until-phase` or `--only-phase` names a phase the program does not have
+    #[error("the program has no phase {0:?} (phases: {1})")]
+    UnknownPhase(String, String),
+    /// `sync` was called without a repository and none is configured
+    #[error("no shared repository is configured; pass --repository")]
+    NoSharedRepository,
+    /// error reading a shared program or template, or the sync baseline
+    #[error("error reading shared file {0}: {1}")]
+    CouldNotReadSharedFile(std::path::PathBuf, #[source] std::io::Error),
+    /// error writing or removing a shared program or template, or the sync baseline
+    #[error("error writing shared file {0}: {1}")]
+    CouldNotWriteSharedFile(std::path::PathBuf, #[source] std::io::Error),
+    /// error parsing the checksums recorded by the last sync
+    #[error("error parsing sync baseline {0}: {1}")]
+    CouldNotParseSharedBaseline(std::path::PathBuf, #[source] toml::de::Error),
+    /// error serializing the checksums recorded by a sync
+    #[error("error serializing sync baseline: {0}")]
+    CouldNotSerializeSharedBaseline(#[source] toml::ser::Error),
+    /// files changed both locally and in the shared repository
+    #[error(
+        "{0} file(s) changed both locally and in the shared repository; resolve them or pass --force"
+    )]
+    SharedFilesConflict(usize),
+    /// error serializing the target statistics to JSON
+    #[error("error serializing target statistics: {0}")]
+    CouldNotSerializeStats(#[source] serde_json::Error),
+    /// error serializing the output of a list command to JSON
+    #[error("error serializing list output: {0}")]
+    CouldNotSerializeListOutput(#[source] serde_json::Error),
+    /// error serializing the description of a task to JSON
+    #[error("error serializing task description: {0}")]
+    CouldNotSerializeTaskDescription(#[source] serde_json::Error),
+    /// the program of a new task uses commands that are not installed
+    #[error(
+        "commands used by the program were not found: {0}; install them or pass --skip-command-check"
+    )]
+    MissingCommands(String),
+    /// error serializing the checksums of a task's resolution inputs
+    #[error("error serializing resolution inputs: {0}")]
+    CouldNotSerializeResolutionInputs(#[source] toml::ser::Error),
+    /// error writing the checksums of a task's resolution inputs
+    #[error("error writing resolution inputs {0}: {1}")]
+    CouldNotWriteResolutionInputs(std::path::PathBuf, #[source] std::io::Error),
+    /// error reading the checksums of a task's resolution inputs
+    #[error("error reading resolution inputs {0}: {1}")]
+    CouldNotReadResolutionInputs(std::path::PathBuf, #[source] std::io::Error),
+    /// error parsing the checksums of a task's resolution inputs
+    #[error("error parsing resolution inputs {0}: {1}")]
+    CouldNotParseResolutionInputs(std::path::PathBuf, #[source] toml::de::Error),
+    /// `task run --strict` found that the targets of the task are likely outdated
+    #[error("the targets of task {0} are likely outdated; review them with `task diff-targets`")]
+    StaleResolution(String),
+    /// more than one input of a command was to be read from standard input
+    #[error("only one input can be read from standard input (`-`)")]
+    StdinUsedTwice,
+    /// error reading a list of target directories
+    #[error("error reading target list {0}: {1}")]
+    CouldNotReadTargetList(std::path::PathBuf, #[source] std::io::Error),
+    /// a cargo command that works on a single package was run in a virtual workspace root
+    #[error(
+        "`cargo {0}` needs a package, but {1} is the root of a virtual workspace; select members with `--package` or run it inside `for crate in workspace`"
+    )]
+    NeedsPackageContext(String, std::path::PathBuf),
+    /// error writing a report file
+    #[error("error writing report {0}: {1}")]
+    CouldNotWriteReport(std::path::PathBuf, #[source] std::io::Error),
+    /// error running the program delivering a notification
+    #[error("error running `{0}` to send a notification: {1}")]
+    CouldNotSendNotification(String, #[source] std::io::Error),
+    /// the program delivering a notification failed
+    #[error("`{0}` failed to send a notification with exit code {1}")]
+    NotificationFailed(String, i32),
+    /// the environment variable holding a notification access token is not set
+    #[error("environment variable {0} with the notification access token is not set")]
+    MissingNotificationToken(String),
+    /// error removing a file from a task's state directory
+    #[error("error removing state file {0}: {1}")]
+    CouldNotRemoveStateFile(std::path::PathBuf, #[source] std::io::Error),
+    /// a target was not run because its git working tree is not clean or its
+    /// environment changed
+    #[error("{0} is blocked: {1}")]
+    TargetBlocked(std::path::PathBuf, String),
+    /// some targets were not run because their git working trees are not
+    /// clean or their environments changed
+    #[error(
+        "{0} target(s) blocked by uncommitted changes or a changed environment; resolve that and run again"
+    )]
+    TargetsBlocked(usize),
+    /// a task that `hygiene` would recreate was not generated by it
+    #[error("task {0} exists and was not created by the hygiene command; choose another --name")]
+    NotAHygieneTask(String),
+    /// some hygiene checks failed for some targets
+    #[error("hygiene checks failed: {0}")]
+    HygieneChecksFailed(String),
+    /// a command was killed because it exceeded the timeout of its step
+    #[error("`{0}` in {1} was killed after running for {2} seconds")]
+    StepTimedOut(String, std::path::PathBuf, u64),
+    /// no output of `run` steps was recorded for a target
+    #[error("no output of run steps was recorded for {0}")]
+    NoStepLogs(std::path::PathBuf),
+    /// another task is running on some of the targets of a task to run
+    #[error("task {0} (process {1} on {2}) is running on {3} as well; wait for it or pass --force")]
+    TaskRunOverlaps(String, u32, String, std::path::PathBuf),
+    /// neither a built-in command nor a plugin of that name exists
+    #[error("no such command: {0}; there is no cargo-for-each-{0} executable on PATH either")]
+    UnknownSubcommand(String),
+    /// error starting the executable of an external subcommand
+    #[error("error running {0}: {1}")]
+    CouldNotRunPlugin(std::path::PathBuf, #[source] std::io::Error),
+    /// an external subcommand exited unsuccessfully
+    #[error("{0} failed with exit code {1}")]
+    PluginFailed(std::path::PathBuf, i32),
+    /// a run was aborted when asked whether to start the next target
+    #[error("run aborted; run the task again to continue with the remaining targets")]
+    RunAborted,
+    /// targets to remove are used by tasks
+    #[error(
+        "tasks {0} run on the targets to remove; pass --force to remove them anyway or --cascade to remove the tasks as well"
+    )]
+    TargetsInUse(String),
+    /// a program or command refers to a target set that does not exist
+    #[error("there is no target set named {0}")]
+    UnknownTargetSet(String),
+    /// a directory given as a target is not registered
+    #[error("{0} is not a registered workspace or crate; add it with target add first")]
+    NotARegisteredTarget(std::path::PathBuf),
+    /// a composite target set refers to itself, directly or through other sets
+    #[error("target set {0} is composed of itself")]
+    TargetSetCycle(String),
+    /// members can only be added to or removed from explicit target sets
+    #[error("target set {0} is composed of other sets; change the members of those instead")]
+    CompositeTargetSet(String),
+    /// a target set that is part of composite sets cannot be removed
+    #[error("target set {0} is used by the composite sets {1}")]
+    TargetSetInUse(String, String),
+    /// the regular expression of `--name-regex` is invalid
+    #[error("invalid regular expression {0} for --name-regex: {1}")]
+    InvalidNameRegex(String, String),
+    /// the requirement of `--version-req` is invalid
+    #[error("invalid version requirement {0} for --version-req: {1}")]
+    InvalidVersionReq(String, String),
+    /// error parsing the explicitly given targets of a task
+    #[error("error parsing the explicit targets of a task in {0}: {1}")]
+    CouldNotParseExplicitTargets(std::path::PathBuf, #[source] toml::de::Error),
+    /// error serializing the environment fingerprint of a step
+    #[error("error serializing the environment fingerprint of a step: {0}")]
+    CouldNotSerializeFingerprint(#[source] toml::ser::Error),
+    /// another process holds the lock on the configuration for too long
+    #[error(
+        "the configuration is locked by another cargo-for-each process (lock file {0}); try again once it finished"
+    )]
+    ConfigLocked(std::path::PathBuf),
+    /// error opening or locking a lock file
+    #[error("error locking {0}: {1}")]
+    CouldNotOpenLockFile(std::path::PathBuf, #[source] std::io::Error),
+    /// error reading a file to bundle with `config export`
+    #[error("error reading {0} to bundle it: {1}")]
+    CouldNotReadBundledFile(std::path::PathBuf, #[source] std::io::Error),
+    /// error writing a file of a bundle with `config import`
+    #[error("error writing {0} from the bundle: {1}")]
+    CouldNotWriteBundledFile(std::path::PathBuf, #[source] std::io::Error),
+    /// a bundle contains a path outside of the configuration directory
+    #[error("the bundle contains the invalid path {0}")]
+    InvalidBundledPath(String),
+    /// a bundle was written by an incompatible version
+    #[error("the bundle has the unsupported format version {0}")]
+    UnsupportedBundleVersion(u32),
+    /// error serializing a bundle
+    #[error("error serializing the bundle: {0}")]
+    CouldNotSerializeBundle(#[source] toml::ser::Error),
+    /// error writing a bundle
+    #[error("error writing the bundle {0}: {1}")]
+    CouldNotWriteBundle(std::path::PathBuf, #[source] std::io::Error),
+    /// error reading a bundle
+    #[error("error reading the bundle {0}: {1}")]
+    CouldNotReadBundle(std::path::PathBuf, #[source] std::io::Error),
+    /// error parsing a bundle
+    #[error("error parsing the bundle {0}: {1}")]
+    CouldNotParseBundle(std::path::PathBuf, #[source] toml::de::Error),
+    /// files to import differ from the existing ones
+    #[error("{0} file(s) differ from the bundle; pass --force to replace them")]
+    BundleConflicts(usize),
+    /// a command was killed because the run was cancelled
+    #[error("`{0}` in {1} was interrupted because the run was cancelled")]
+    StepInterrupted(String, std::path::PathBuf),
+    /// a step was not started because the run was cancelled
+    #[error("not starting `{0}` in {1} because the run was cancelled")]
+    RunCancelled(String, std::path::PathBuf),
+    /// the run was interrupted with Ctrl+C
+    #[error("the run was interrupted")]
+    RunInterrupted,
+    /// an ad-hoc command failed in some of the targets
+    #[error("the command failed in {0} target(s)")]
+    ExecFailed(usize),
+    /// a command does not support the output format given with `--format`
+    #[error("`{1}` cannot print --format {0}")]
+    UnsupportedOutputFormat(crate::porcelain::OutputFormat, String),
+    /// `--porcelain` was combined with a `--format` other than text
+    #[error("--porcelain cannot be combined with --format {0}")]
+    PorcelainWithFormat(crate::porcelain::OutputFormat),
 }
@@ -0,0 +1,382 @@
+//! Running an ad-hoc command in each registered target.
+//!
+//! Not every change is worth a task: `exec -- <command> ...` runs a command
+//! once in the manifest directory of each registered crate (or workspace with
+//! `--kind workspaces`), without a program, state or resuming.  The targets
+//! can be narrowed down with `--standalone`, the tag and git filters of
+//! `target list` and `--target-set`, so the sets saved for tasks can be
+//! reused for quick one-off commands.  Like `task run`, targets with a
+//! `.cargo-for-each-skip` marker are skipped (see
+//! [`crate::tasks::skip_marker`]).
+//!
+//! With `--ordered` the targets run in dependency order, dependencies first,
+//! using the same graph as `graph fleet` (without dev-dependencies, which may
+//! form cycles).  A workspace depends on another one if any of its crates
+//! depends on a crate of the other one.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+
+use tracing::instrument;
+
+use crate::error::Error;
+use crate::graph::Edge;
+use crate::metadata::{Cached, Subprocess};
+
+/// Which registered targets the command runs in.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Kind {
+    /// the manifest directories of the registered crates
+    #[default]
+    Crates,
+    /// the manifest directories of the registered workspaces
+    Workspaces,
+}
+
+/// Which of the registered targets the command runs in
+#[derive(clap::Parser, Debug, Clone, Default)]
+pub struct Selection {
+    /// run the command in the registered crates or workspaces
+    #[clap(long, value_enum, default_value_t)]
+    pub kind: Kind,
+    /// only run in targets that are standalone (or part of a standalone
+    /// workspace) or not
+    #[clap(long)]
+    pub standalone: Option<bool>,
+    /// only run in the members of this target set
+    #[clap(long)]
+    pub target_set: Option<String>,
+    /// filters on the git checkout of the target
+    #[clap(flatten)]
+    pub git: crate::targets::git::FilterParameters,
+    /// filters on the tags of the target
+    #[clap(flatten)]
+    pub tags: crate::targets::tags::TagFilterParameters,
+}
+
+/// Parameters for the exec subcommand
+#[derive(clap::Parser, Debug, Clone)]
+pub struct Parameters {
+    /// which targets to run the command in
+    #[clap(flatten)]
+    pub selection: Selection,
+    /// run in dependency order, dependencies first
+    #[clap(long)]
+    pub ordered: bool,
+    /// keep running in the remaining targets after the command failed
+    #[clap(long, short = 'k')]
+    pub keep_going: bool,
+    /// the command and its arguments
+    #[clap(last = true, required = true)]
+    pub command: Vec<String>,
+}
+
+/// Returns the manifest directories of the targets in `config` picked by
+/// `selection`.
+///
+/// # Errors
+///
+/// Returns an error if the target set cannot be resolved.
+pub fn select(config: &crate::Config, selection: &Selection) -> Result<Vec<PathBuf>, Error> {
+    let members = selection
+        .target_set
+        .as_deref()
+        .map(|name| crate::targets::sets::members(config, name))
+        .transpose()?;
+    let workspace_standalone: BTreeMap<&PathBuf, bool> = config
+        .workspaces
+        .iter()
+        .map(|workspace| (&workspace.manifest_dir, workspace.is_standalone))
+        .collect();
+    let candidates: Vec<(&PathBuf, &PathBuf)> = match selection.kind {
+        Kind::Crates => config
+            .crates
+            .iter()
+            .map(|krate| (&krate.manifest_dir, &krate.workspace_manifest_dir))
+            .collect(),
+        Kind::Workspaces => config
+            .workspaces
+            .iter()
+            .map(|workspace| (&workspace.manifest_dir, &workspace.manifest_dir))
+            .collect(),
+    };
+    Ok(candidates
+        .into_iter()
+        .filter(|(_, workspace_dir)| {
+            selection.standalone.is_none_or(|standalone| {
+                workspace_standalone.get(workspace_dir) == Some(&standalone)
+            })
+        })
+        .filter(|(manifest_dir, _)| {
+            members
+                .as_ref()
+                .is_none_or(|members| members.contains(*manifest_dir))
+        })
+        .filter(|(manifest_dir, _)| selection.tags.matches(config, manifest_dir))
+        .filter(|(manifest_dir, _)| selection.git.matches(manifest_dir))
+        .map(|(manifest_dir, _)| manifest_dir.clone())
+        .collect())
+}
+
+/// Sorts `targets` of `kind` so that each comes after the targets it
+/// depends on according to the crate dependencies `edges`, also through
+/// registered targets that are not among `targets`.
+///
+/// # Errors
+///
+/// Returns [`Error::CircularDependency`] if the targets depend on each other
+/// in a cycle.
+pub fn order(
+    config: &crate::Config,
+    kind: Kind,
+    targets: Vec<PathBuf>,
+    edges: &[Edge],
+) -> Result<Vec<PathBuf>, Error> {
+    let workspace_of: BTreeMap<&PathBuf, &PathBuf> = config
+        .crates
+        .iter()
+        .map(|krate| (&krate.manifest_dir, &krate.workspace_manifest_dir))
+        .collect();
+    let target_of = |manifest_dir: &PathBuf| match kind {
+        Kind::Crates => Some(manifest_dir.clone()),
+        Kind::Workspaces => workspace_of.get(manifest_dir).map(|dir| (*dir).clone()),
+    };
+    let mut dependencies: BTreeMap<PathBuf, BTreeSet<PathBuf>> = BTreeMap::new();
+    for edge in edges {
+        if let (Some(from), Some(to)) = (target_of(&edge.from), target_of(&edge.to))
+            && from != to
+        {
+            dependencies.entry(from).or_default().insert(to);
+        }
+    }
+    let all_targets = select(
+        config,
+        &Selection {
+            kind,
+            ..Selection::default()
+        },
+    )?;
+    let graph: Vec<(PathBuf, Vec<PathBuf>)> = all_targets
+        .into_iter()
+        .map(|target| {
+            let depends_on = dependencies
+                .remove(&target)
+                .unwrap_or_default()
+                .into_iter()
+                .collect();
+            (target, depends_on)
+        })
+        .collect();
+    let order = crate::tasks::scheduling::topological_order(&graph)
+        .map_err(|stuck| crate::tasks::scheduling::cycle_error(&graph, &stuck))?;
+    Ok(order
+        .into_iter()
+        .filter_map(|idx| graph.get(idx).map(|(dir, _)| dir.clone()))
+        .filter(|dir| targets.contains(dir))
+        .collect())
+}
+
+/// Runs `command` (the program and its arguments) in each of `targets`,
+/// skipping those with a skip marker, and returns the number of targets it
+/// failed in.  Without `keep_going` it stops at the first failure.
+///
+/// # Errors
+///
+/// Returns an error if the command cannot be executed.
+#[expect(clippy::print_stdout, reason = "This is part of the UI, not logging")]
+pub fn run(
+    targets: &[PathBuf],
+    command: &[String],
+    keep_going: bool,
+    environment: &crate::Environment,
+) -> Result<usize, Error> {
+    let Some((program, args)) = command.split_first() else {
+        return Ok(0);
+    };
+    let mut failures = 0_usize;
+    for target in targets {
+        if let Some(reason) = crate::tasks::skip_marker::reason(target) {
+            tracing::warn!("Skipping {}: {reason}", target.display());
+            println!("==> {} ({reason})", target.display());
+            continue;
+        }
+        println!("==> {}", target.display());
+        let mut cmd = std::process::Command::new(program);
+        cmd.args(args).current_dir(target);
+        let output = crate::utils::execute_command(&mut cmd, environment, target)?;
+        if !output.status.success() {
+            tracing::error!(
+                "`{}` failed in {} with {}",
+                command.join(" "),
+                target.display(),
+                output.status
+            );
+            failures = failures.saturating_add(1);
+            if !keep_going {
+                break;
+            }
+        }
+    }
+    Ok(failures)
+}
+
+/// Runs the exec subcommand.
+///
+/// # Errors
+///
+/// Returns an error if the targets cannot be selected or ordered, if the
+/// command is not found, or [`Error::ExecFailed`] if it failed in any target.
+#[instrument]
+pub async fn command(parameters: Parameters, environment: crate::Environment) -> Result<(), Error> {
+    let config = crate::Config::load(&environment)?;
+    if let Some(program) = parameters.command.first()
+        && !crate::utils::command_is_executable(program, &environment)
+    {
+        return Err(Error::CommandNotFound(program.to_owned()));
+    }
+    let mut targets = select(&config, &parameters.selection)?;
+    if parameters.ordered {
+        let graph = crate::graph::fleet(&config, &Cached::new(Subprocess), false);
+        targets = order(&config, parameters.selection.kind, targets, &graph.edges)?;
+    }
+    let failures = run(
+        &targets,
+        &parameters.command,
+        parameters.keep_going,
+        &environment,
+    )?;
+    if failures > 0 {
+        return Err(Error::ExecFailed(failures));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use pretty_assertions::assert_eq;
+
+    use super::{Kind, Selection, order, run, select};
+    use crate::graph::{Edge, EdgeKind};
+    use crate::targets::sets::TargetSet;
+
+    #[test]
+    fn target_sets_are_selected_in_dependency_order() -> Result<(), Box<dyn std::error::Error>> {
+        let mut config = crate::Config::default();
+        for (dir, is_standalone) in [("/src/app", false), ("/src/util", true)] {
+            config.workspaces.push(crate::Workspace {
+                manifest_dir: PathBuf::from(dir),
+                is_standalone,
+                repository: None,
+            });
+        }
+        for (dir, workspace) in [
+            ("/src/app/cli", "/src/app"),
+            ("/src/app/core", "/src/app"),
+            ("/src/util", "/src/util"),
+        ] {
+            config.crates.push(crate::Crate {
+                manifest_dir: PathBuf::from(dir),
+                workspace_manifest_dir: PathBuf::from(workspace),
+                types: std::collections::BTreeSet::new(),
+                metadata: crate::targets::PackageMetadata::default(),
+                name: None,
+                version: None,
+            });
+        }
+        config.target_sets.insert(
+            "app-and-util".to_owned(),
+            TargetSet::Explicit {
+                manifest_dirs: vec![PathBuf::from("/src/app/cli"), PathBuf::from("/src/util")],
+            },
+        );
+        let edges = [
+            Edge {
+                from: PathBuf::from("/src/app/cli"),
+                to: PathBuf::from("/src/app/core"),
+                kind: EdgeKind::Normal,
+            },
+            Edge {
+                from: PathBuf::from("/src/app/core"),
+                to: PathBuf::from("/src/util"),
+                kind: EdgeKind::Build,
+            },
+        ];
+
+        config.tags.insert(
+            PathBuf::from("/src/app/core"),
+            ["internal".to_owned()].into(),
+        );
+
+        let members = select(
+            &config,
+            &Selection {
+                target_set: Some("app-and-util".to_owned()),
+                ..Selection::default()
+            },
+        )?;
+        assert_eq!(
+            members,
+            [PathBuf::from("/src/app/cli"), PathBuf::from("/src/util")]
+        );
+        assert_eq!(
+            select(
+                &config,
+                &Selection {
+                    standalone: Some(true),
+                    ..Selection::default()
+                }
+            )?,
+            [PathBuf::from("/src/util")]
+        );
+        let mut untagged = Selection::default();
+        untagged.tags.not_tag.push("internal".to_owned());
+        assert_eq!(
+            select(&config, &untagged)?,
+            [PathBuf::from("/src/app/cli"), PathBuf::from("/src/util")]
+        );
+        assert_eq!(
+            order(&config, Kind::Crates, members, &edges)?,
+            [PathBuf::from("/src/util"), PathBuf::from("/src/app/cli")]
+        );
+        let workspaces = select(
+            &config,
+            &Selection {
+                kind: Kind::Workspaces,
+                ..Selection::default()
+            },
+        )?;
+        assert_eq!(
+            order(&config, Kind::Workspaces, workspaces, &edges)?,
+            [PathBuf::from("/src/util"), PathBuf::from("/src/app")]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn targets_with_a_skip_marker_are_not_run() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempfile::tempdir()?;
+        let environment = crate::Environment::mock(&temp_dir)?;
+        let marked = temp_dir.path().join("marked");
+        let unmarked = temp_dir.path().join("unmarked");
+        for dir in [&marked, &unmarked] {
+            fs_err::create_dir_all(dir)?;
+        }
+        fs_err::write(
+            marked.join(crate::tasks::skip_marker::FILE_NAME),
+            "broken\n",
+        )?;
+        let command = ["sh", "-c", "touch ran"].map(str::to_owned);
+        let failures = run(
+            &[marked.clone(), unmarked.clone()],
+            &command,
+            false,
+            &environment,
+        )?;
+        assert_eq!(failures, 0);
+        assert!(!marked.join("ran").exists(), "the marked target is skipped");
+        assert!(unmarked.join("ran").exists(), "the other target is run");
+        Ok(())
+    }
+}
@@ -0,0 +1,221 @@
+//! Durations, timestamps and sizes as shown to people and scripts.
+//!
+//! `task report`, `task status` and `history` format these values the same
+//! way.  By default timestamps are shown in the local time zone and
+//! durations rounded to the second (e.g. `1m 5s`).  `--utc` shows timestamps
+//! in UTC, and `--iso8601` switches to the ISO 8601 forms scripts can parse,
+//! e.g. `2026-01-01T12:00:00+01:00` and `PT65.432S`.
+//!
+//! Timestamps are stored in RFC 3339 format in UTC.  The local time zone is
+//! taken from `date +%z` once per process, so timestamps from the other side
+//! of a daylight saving time change are off by its difference; where that
+//! matters, use `--utc`.
+
+use std::fmt::Write as _;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use time::format_description::well_known::Rfc3339;
+use time::{OffsetDateTime, UtcOffset};
+
+/// How durations and timestamps are formatted.
+#[derive(clap::Args, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Style {
+    /// Show timestamps in UTC instead of the local time zone.
+    #[clap(long)]
+    pub utc: bool,
+    /// Show timestamps and durations in ISO 8601 format, for scripts.
+    #[clap(long)]
+    pub iso8601: bool,
+}
+
+impl Style {
+    /// Formats `duration`, e.g. `1m 5s`, or `250ms` under a second; in ISO
+    /// 8601 format to the millisecond, e.g. `PT65.432S`.
+    #[must_use]
+    pub fn duration(self, duration: Duration) -> String {
+        if self.iso8601 {
+            return match duration.subsec_millis() {
+                0 => format!("PT{}S", duration.as_secs()),
+                millis => format!("PT{}.{millis:03}S", duration.as_secs()),
+            };
+        }
+        if duration.as_secs() == 0 {
+            return format!("{}ms", duration.as_millis());
+        }
+        humantime::format_duration(Duration::from_secs(duration.as_secs())).to_string()
+    }
+
+    /// Formats the RFC 3339 timestamp `rfc3339`; timestamps that cannot be
+    /// parsed are returned unchanged.
+    #[must_use]
+    pub fn timestamp(self, rfc3339: &str) -> String {
+        let offset = if self.utc {
+            UtcOffset::UTC
+        } else {
+            local_offset()
+        };
+        self.timestamp_at_offset(rfc3339, offset)
+    }
+
+    /// Formats the RFC 3339 timestamp `rfc3339` in the time zone `offset`.
+    #[must_use]
+    pub fn timestamp_at_offset(self, rfc3339: &str, offset: UtcOffset) -> String {
+        let Ok(parsed) = OffsetDateTime::parse(rfc3339, &Rfc3339) else {
+            return rfc3339.to_owned();
+        };
+        let at = parsed.to_offset(offset);
+        let mut out = format!(
+            "{:04}-{:02}-{:02}{}{:02}:{:02}:{:02}",
+            at.year(),
+            u8::from(at.month()),
+            at.day(),
+            if self.iso8601 { 'T' } else { ' ' },
+            at.hour(),
+            at.minute(),
+            at.second()
+        );
+        if offset.is_utc() {
+            out.push_str(if self.iso8601 { "Z" } else { " UTC" });
+        } else if self.iso8601 {
+            let (hours, minutes, _seconds) = offset.as_hms();
+            let _ignored = write!(
+                out,
+                "{}{:02}:{:02}",
+                if offset.is_negative() { '-' } else { '+' },
+                hours.unsigned_abs(),
+                minutes.unsigned_abs()
+            );
+        }
+        out
+    }
+}
+
+/// Returns the offset of the local time zone, or UTC if it cannot be
+/// determined.
+fn local_offset() -> UtcOffset {
+    static OFFSET: OnceLock<UtcOffset> = OnceLock::new();
+    *OFFSET.get_or_init(|| {
+        std::process::Command::new("date")
+            .arg("+%z")
+            .output()
+            .ok()
+            .and_then(|output| parse_offset(&String::from_utf8_lossy(&output.stdout)))
+            .unwrap_or(UtcOffset::UTC)
+    })
+}
+
+/// Parses an offset in the format of `date +%z`, e.g. `+0130`.
+fn parse_offset(text: &str) -> Option<UtcOffset> {
+    let text = text.trim();
+    let (sign, digits) = match text.split_at_checked(1)? {
+        ("+", digits) => (1, digits),
+        ("-", digits) => (-1, digits),
+        _ => return None,
+    };
+    if digits.len() != 4 {
+        return None;
+    }
+    let hours: i8 = digits.get(..2)?.parse().ok()?;
+    let minutes: i8 = digits.get(2..)?.parse().ok()?;
+    UtcOffset::from_hms(hours.checked_mul(sign)?, minutes.checked_mul(sign)?, 0).ok()
+}
+
+/// Formats a size of `bytes`, e.g. `512 B` or `1.5 KiB`.
+#[must_use]
+pub fn bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["KiB", "MiB", "GiB", "TiB", "PiB"];
+    let mut unit_size: u128 = 1;
+    let mut unit_name = "B";
+    for name in UNITS {
+        let next = unit_size.saturating_mul(1024);
+        if u128::from(bytes) < next {
+            break;
+        }
+        unit_size = next;
+        unit_name = name;
+    }
+    if unit_size == 1 {
+        return format!("{bytes} {unit_name}");
+    }
+    let tenths = u128::from(bytes)
+        .saturating_mul(10)
+        .saturating_add(unit_size / 2)
+        .checked_div(unit_size)
+        .unwrap_or_default();
+    format!("{}.{} {unit_name}", tenths / 10, tenths % 10)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use pretty_assertions::assert_eq;
+    use time::UtcOffset;
+
+    use super::{Style, bytes, parse_offset};
+
+    #[test]
+    fn durations_are_formatted() {
+        let human = Style::default();
+        let iso = Style {
+            utc: false,
+            iso8601: true,
+        };
+        assert_eq!(human.duration(Duration::from_millis(65_432)), "1m 5s");
+        assert_eq!(human.duration(Duration::from_millis(250)), "250ms");
+        assert_eq!(iso.duration(Duration::from_millis(65_432)), "PT65.432S");
+        assert_eq!(iso.duration(Duration::from_secs(3)), "PT3S");
+    }
+
+    #[test]
+    fn timestamps_are_formatted() -> Result<(), Box<dyn std::error::Error>> {
+        let stored = "2026-01-01T23:30:00Z";
+        let plus_one = UtcOffset::from_hms(1, 0, 0)?;
+        let human = Style::default();
+        let iso = Style {
+            utc: false,
+            iso8601: true,
+        };
+        assert_eq!(
+            human.timestamp_at_offset(stored, UtcOffset::UTC),
+            "2026-01-01 23:30:00 UTC"
+        );
+        assert_eq!(
+            human.timestamp_at_offset(stored, plus_one),
+            "2026-01-02 00:30:00"
+        );
+        assert_eq!(
+            iso.timestamp_at_offset(stored, UtcOffset::UTC),
+            "2026-01-01T23:30:00Z"
+        );
+        assert_eq!(
+            iso.timestamp_at_offset(stored, UtcOffset::from_hms(-5, -30, 0)?),
+            "2026-01-01T18:00:00-05:30"
+        );
+        assert_eq!(
+            Style {
+                utc: true,
+                iso8601: false
+            }
+            .timestamp(stored),
+            "2026-01-01 23:30:00 UTC"
+        );
+        assert_eq!(human.timestamp("yesterday"), "yesterday");
+        assert_eq!(
+            parse_offset("+0130\n"),
+            Some(UtcOffset::from_hms(1, 30, 0)?)
+        );
+        assert_eq!(parse_offset("-0800"), Some(UtcOffset::from_hms(-8, 0, 0)?));
+        assert_eq!(parse_offset("UTC"), None);
+        Ok(())
+    }
+
+    #[test]
+    fn sizes_are_formatted() {
+        assert_eq!(bytes(0), "0 B");
+        assert_eq!(bytes(1023), "1023 B");
+        assert_eq!(bytes(1536), "1.5 KiB");
+        assert_eq!(bytes(5 * 1024 * 1024), "5.0 MiB");
+    }
+}
@@ -0,0 +1,384 @@
+//! Dependency freshness across all registered workspaces.
+//!
+//! The `outdated` command runs
+//! [cargo-outdated](https://github.com/kbknapp/cargo-outdated) in every
+//! registered workspace and merges the per-crate reports into one table keyed
+//! by dependency, so questions like "which crates are still on an old tokio"
+//! can be answered with a single command.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+
+use crate::error::Error;
+
+/// The cargo subcommand executable that produces the per-workspace reports.
+const CARGO_OUTDATED: &str = "cargo-outdated";
+
+/// Parameters for the `outdated` command.
+#[derive(clap::Parser, Debug, Clone)]
+pub struct OutdatedParameters {
+    /// Only show these dependencies (repeatable).
+    #[clap(long = "dependency", short = 'd')]
+    pub dependencies: Vec<String>,
+    /// Only show crates that may be published to this registry according to
+    /// the `publish` field of their manifest (`crates-io` for crates.io).
+    #[clap(long)]
+    pub registry: Option<String>,
+    /// print stable tab-separated output for scripts (see the porcelain module docs)
+    #[clap(
+        long,
+        value_enum,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "v1"
+    )]
+    pub porcelain: Option<crate::porcelain::Version>,
+}
+
+/// The report cargo-outdated prints for a single crate with `--format json`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct CrateReport {
+    /// The name of the crate the report is for.
+    pub crate_name: String,
+    /// The dependencies of the crate that are not on their latest version.
+    pub dependencies: Vec<DependencyReport>,
+}
+
+/// A single outdated dependency in a [`CrateReport`].
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct DependencyReport {
+    /// The name of the dependency.
+    pub name: String,
+    /// The version currently locked in the workspace.
+    pub project: String,
+    /// The latest version available in the registry.
+    pub latest: String,
+}
+
+/// One crate depending on a specific version of a dependency.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Usage {
+    /// The version the crate currently uses.
+    pub version: String,
+    /// The name of the depending crate.
+    pub crate_name: String,
+    /// The manifest directory of the workspace the crate belongs to.
+    pub workspace_manifest_dir: PathBuf,
+}
+
+/// All registered crates using an outdated version of one dependency.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dependency {
+    /// The latest version available in the registry.
+    pub latest: String,
+    /// The crates that are not on `latest`, sorted by version and crate name.
+    pub usages: Vec<Usage>,
+}
+
+/// An outdated dependency in the JSON output of `outdated`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DependencyView {
+    /// The name of the dependency.
+    pub name: String,
+    /// The latest version available in the registry.
+    pub latest: String,
+    /// The crates that are not on `latest`.
+    pub usages: Vec<Usage>,
+}
+
+/// Returns the views of the `merged` dependencies, sorted by name.
+#[must_use]
+pub fn views(merged: &BTreeMap<String, Dependency>) -> Vec<DependencyView> {
+    merged
+        .iter()
+        .map(|(name, dependency)| DependencyView {
+            name: name.clone(),
+            latest: dependency.latest.clone(),
+            usages: dependency.usages.clone(),
+        })
+        .collect()
+}
+
+/// Parses the output of `cargo outdated --format json`.
+///
+/// With `--workspace`, cargo-outdated prints one JSON document per line, one
+/// for each member crate.
+///
+/// # Errors
+///
+/// Returns an error if a line is not a valid crate report.
+pub fn parse_reports(
+    output: &str,
+    workspace_manifest_dir: &Path,
+) -> Result<Vec<CrateReport>, Error> {
+    output
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            serde_json::from_str(line).map_err(|e| {
+                Error::CouldNotParseOutdatedReport(workspace_manifest_dir.to_path_buf(), e)
+            })
+        })
+        .collect()
+}
+
+/// Merges the reports of several workspaces into one entry per dependency.
+///
+/// `only` restricts the result to the named dependencies unless it is empty.
+/// Dependencies cargo-outdated could not resolve (shown as `---` or
+/// `Removed`) are skipped.
+#[must_use]
+pub fn merge_reports(
+    reports: &[(PathBuf, Vec<CrateReport>)],
+    only: &[String],
+) -> BTreeMap<String, Dependency> {
+    let mut merged: BTreeMap<String, Dependency> = BTreeMap::new();
+    for (workspace_manifest_dir, crate_reports) in reports {
+        for report in crate_reports {
+            for dependency in &report.dependencies {
+                if !only.is_empty() && !only.contains(&dependency.name) {
+                    continue;
+                }
+                if !is_version(&dependency.project) || !is_version(&dependency.latest) {
+                    continue;
+                }
+                let entry = merged
+                    .entry(dependency.name.clone())
+                    .or_insert_with(|| Dependency {
+                        latest: dependency.latest.clone(),
+                        usages: Vec::new(),
+                    });
+                let usage = Usage {
+                    version: dependency.project.clone(),
+                    crate_name: report.crate_name.clone(),
+                    workspace_manifest_dir: workspace_manifest_dir.clone(),
+                };
+                if !entry.usages.contains(&usage) {
+                    entry.usages.push(usage);
+                }
+            }
+        }
+    }
+    for dependency in merged.values_mut() {
+        dependency.usages.sort_by_cached_key(|usage| {
+            (
+                cargo_metadata::semver::Version::parse(&usage.version).ok(),
+                usage.crate_name.clone(),
+                usage.workspace_manifest_dir.clone(),
+            )
+        });
+    }
+    merged
+}
+
+/// Returns true if `value` is a version rather than one of cargo-outdated's
+/// placeholders.
+fn is_version(value: &str) -> bool {
+    value.starts_with(|c: char| c.is_ascii_digit())
+}
+
+/// Returns the names of the packages in the workspace in `manifest_dir` that
+/// may be published to `registry`.
+///
+/// # Errors
+///
+/// Returns an error if `cargo metadata` fails.
+fn publishing_crates(manifest_dir: &Path, registry: &str) -> Result<BTreeSet<String>, Error> {
+    let metadata = cargo_metadata::MetadataCommand::new()
+        .manifest_path(manifest_dir.join("Cargo.toml"))
+        .no_deps()
+        .exec()
+        .map_err(|e| Error::CargoMetadataError(manifest_dir.to_path_buf(), e))?;
+    Ok(metadata
+        .packages
+        .iter()
+        .filter(|package| crate::program::resolve::publishes_to(package, registry))
+        .map(|package| package.name.to_string())
+        .collect())
+}
+
+/// Runs cargo-outdated for the workspace in `manifest_dir`, keeping only the
+/// reports of crates that may be published to `registry` if one is given.
+///
+/// # Errors
+///
+/// Returns an error if cargo-outdated cannot be started, fails, or prints
+/// output that cannot be parsed, or if `cargo metadata` fails.
+fn workspace_reports(
+    manifest_dir: &Path,
+    registry: Option<&str>,
+) -> Result<Vec<CrateReport>, Error> {
+    let mut command = Command::new("cargo");
+    command
+        .args([
+            "outdated",
+            "--workspace",
+            "--root-deps-only",
+            "--format",
+            "json",
+        ])
+        .current_dir(manifest_dir)
+        .stdin(Stdio::null())
+        .stderr(Stdio::inherit());
+    let output = command.output().map_err(|e| {
+        Error::CommandExecutionFailed(format!("{command:?}"), manifest_dir.to_path_buf(), e)
+    })?;
+    if !output.status.success() {
+        return Err(Error::CommandFailed(
+            "cargo outdated".to_owned(),
+            manifest_dir.to_path_buf(),
+            output.status.code().unwrap_or(-1),
+        ));
+    }
+    let mut reports = parse_reports(&String::from_utf8_lossy(&output.stdout), manifest_dir)?;
+    if let Some(registry) = registry {
+        let publishing = publishing_crates(manifest_dir, registry)?;
+        reports.retain(|report| publishing.contains(&report.crate_name));
+    }
+    Ok(reports)
+}
+
+/// Prints the merged outdated dependencies of all registered workspaces.
+///
+/// Workspaces for which cargo-outdated fails are reported on stderr and
+/// skipped.
+///
+/// # Errors
+///
+/// Returns an error if the configuration cannot be loaded, cargo-outdated
+/// is not installed or the result cannot be printed in the chosen format.
+#[instrument]
+#[expect(clippy::print_stdout, reason = "This is part of the UI, not logging")]
+#[expect(clippy::print_stderr, reason = "This is part of the UI, not logging")]
+pub async fn outdated_command(
+    params: OutdatedParameters,
+    environment: crate::Environment,
+    run_options: crate::RunOptions,
+) -> Result<(), Error> {
+    let json = crate::porcelain::is_json(run_options.format, params.porcelain, "outdated")?;
+    if !crate::utils::command_is_executable(CARGO_OUTDATED, &environment) {
+        return Err(Error::CommandNotFound(CARGO_OUTDATED.to_owned()));
+    }
+    let config = crate::Config::load(&environment)?;
+    let mut reports = Vec::new();
+    for workspace in &config.workspaces {
+        match workspace_reports(&workspace.manifest_dir, params.registry.as_deref()) {
+            Ok(workspace_reports) => {
+                reports.push((workspace.manifest_dir.clone(), workspace_reports));
+            }
+            Err(e) => eprintln!("Skipping {}: {e}", workspace.manifest_dir.display()),
+        }
+    }
+    let merged = merge_reports(&reports, &params.dependencies);
+    if json {
+        println!("{}", crate::porcelain::json(&views(&merged))?);
+        return Ok(());
+    }
+    for (name, dependency) in &merged {
+        if params.porcelain.is_some() {
+            for usage in &dependency.usages {
+                println!(
+                    "{}",
+                    crate::porcelain::record(&[
+                        "outdated",
+                        name,
+                        &dependency.latest,
+                        &usage.version,
+                        &usage.crate_name,
+                        &usage.workspace_manifest_dir.to_string_lossy(),
+                    ])
+                );
+            }
+            continue;
+        }
+        println!("{name} (latest {})", dependency.latest);
+        for usage in &dependency.usages {
+            println!(
+                "  {:<12} {} ({})",
+                usage.version,
+                usage.crate_name,
+                usage.workspace_manifest_dir.display()
+            );
+        }
+    }
+    if merged.is_empty() && params.porcelain.is_none() {
+        println!("All dependencies are up to date.");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use pretty_assertions::assert_eq;
+
+    use super::{CrateReport, Usage, merge_reports, parse_reports, views};
+
+    /// Two lines of `cargo outdated --workspace --format json` output.
+    const OUTPUT: &str = r#"{"crate_name":"foo","dependencies":[{"name":"tokio","project":"1.0.0","compat":"1.47.1","latest":"1.47.1","kind":"Normal","platform":null},{"name":"gone","project":"0.1.0","compat":"---","latest":"Removed","kind":"Normal","platform":null}]}
+{"crate_name":"bar","dependencies":[]}
+"#;
+
+    #[test]
+    fn parses_one_report_per_line() -> Result<(), Box<dyn std::error::Error>> {
+        let reports = parse_reports(OUTPUT, Path::new("/src/ws"))?;
+        assert_eq!(
+            reports
+                .iter()
+                .map(|r| r.crate_name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["foo", "bar"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn merges_reports_by_dependency() -> Result<(), Box<dyn std::error::Error>> {
+        let reports: Vec<(PathBuf, Vec<CrateReport>)> = vec![
+            (
+                PathBuf::from("/src/ws"),
+                parse_reports(OUTPUT, Path::new("/src/ws"))?,
+            ),
+            (
+                PathBuf::from("/src/other"),
+                parse_reports(
+                    r#"{"crate_name":"baz","dependencies":[{"name":"tokio","project":"0.2.25","compat":"0.2.25","latest":"1.47.1","kind":"Normal","platform":null}]}"#,
+                    Path::new("/src/other"),
+                )?,
+            ),
+        ];
+        let merged = merge_reports(&reports, &[]);
+        assert_eq!(merged.keys().collect::<Vec<_>>(), vec!["tokio"]);
+        let tokio = merged.get("tokio").ok_or("tokio missing")?;
+        assert_eq!(tokio.latest, "1.47.1");
+        assert_eq!(
+            tokio.usages,
+            vec![
+                Usage {
+                    version: "0.2.25".to_owned(),
+                    crate_name: "baz".to_owned(),
+                    workspace_manifest_dir: PathBuf::from("/src/other"),
+                },
+                Usage {
+                    version: "1.0.0".to_owned(),
+                    crate_name: "foo".to_owned(),
+                    workspace_manifest_dir: PathBuf::from("/src/ws"),
+                },
+            ]
+        );
+        assert_eq!(
+            serde_json::to_value(views(&merged))?.pointer("/0/usages/0/crate_name"),
+            Some(&serde_json::json!("baz"))
+        );
+        assert!(
+            merge_reports(&reports, &["serde".to_owned()]).is_empty(),
+            "filtering by dependency should drop all others"
+        );
+        Ok(())
+    }
+}
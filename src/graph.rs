@@ -0,0 +1,352 @@
+//! The dependency graph of all registered crates.
+//!
+//! Task resolution only looks at the dependencies between the targets a
+//! program selected.  Planning to split up a workspace or estimating what a
+//! change ripples into needs the whole picture: `graph fleet` builds the graph
+//! of dependencies between all registered crates, with one edge per
+//! dependency kind (normal, dev or build), and prints it as Graphviz `dot`
+//! or as JSON.
+//!
+//! Dependencies are matched by package name, so a crate depending on the
+//! crates.io release of another registered crate is connected to it as well.
+//! Nodes are identified by manifest directory, since forks may share a name.
+
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+
+use cargo_metadata::DependencyKind;
+use tracing::instrument;
+
+use crate::error::Error;
+use crate::metadata::{Cached, MetadataProvider, Subprocess};
+use crate::porcelain::OutputFormat;
+
+/// The graph subcommand
+#[derive(clap::Parser, Debug, Clone)]
+pub enum SubCommand {
+    /// Print the dependencies between all registered crates.
+    Fleet(FleetParameters),
+}
+
+/// Parameters for the graph subcommand
+#[derive(clap::Parser, Debug, Clone)]
+pub struct Parameters {
+    /// The graph subcommand
+    #[clap(subcommand)]
+    pub sub_command: SubCommand,
+}
+
+/// Parameters for `graph fleet`
+#[derive(clap::Parser, Debug, Clone)]
+pub struct FleetParameters {
+    /// leave out dev-dependencies
+    #[clap(long)]
+    pub no_dev: bool,
+}
+
+/// A registered crate in the graph.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, serde::Serialize)]
+pub struct Node {
+    /// the directory that contains the crate Cargo.toml file
+    pub manifest_dir: PathBuf,
+    /// the package name
+    pub name: String,
+    /// the package version
+    pub version: String,
+}
+
+/// The kind of a dependency edge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EdgeKind {
+    /// a `[dependencies]` entry
+    Normal,
+    /// a `[dev-dependencies]` entry
+    Dev,
+    /// a `[build-dependencies]` entry
+    Build,
+}
+
+impl std::fmt::Display for EdgeKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Normal => write!(f, "normal"),
+            Self::Dev => write!(f, "dev"),
+            Self::Build => write!(f, "build"),
+        }
+    }
+}
+
+/// A dependency of one registered crate on another.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, serde::Serialize)]
+pub struct Edge {
+    /// the manifest directory of the dependent crate
+    pub from: PathBuf,
+    /// the manifest directory of the crate depended on
+    pub to: PathBuf,
+    /// the kind of dependency
+    pub kind: EdgeKind,
+}
+
+/// The dependency graph of the registered crates.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+pub struct Fleet {
+    /// the registered crates, sorted by manifest directory
+    pub nodes: Vec<Node>,
+    /// the dependencies between them, sorted
+    pub edges: Vec<Edge>,
+}
+
+/// Builds the graph of the crates registered in `config`.  Crates whose
+/// workspace `cargo metadata` fails for are left out with a warning.
+#[must_use]
+pub fn fleet(config: &crate::Config, provider: &dyn MetadataProvider, include_dev: bool) -> Fleet {
+    let mut nodes = BTreeSet::new();
+    let mut packages = Vec::new();
+    for krate in &config.crates {
+        let metadata = match provider.metadata(&krate.workspace_manifest_dir) {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                tracing::warn!("Leaving out {}: {e}", krate.manifest_dir.display());
+                continue;
+            }
+        };
+        let manifest_path = krate.manifest_dir.join("Cargo.toml");
+        let Some(package) = metadata
+            .packages
+            .iter()
+            .find(|package| package.manifest_path.as_std_path() == manifest_path)
+        else {
+            tracing::warn!(
+                "Leaving out {}: not a package of its workspace",
+                krate.manifest_dir.display()
+            );
+            continue;
+        };
+        nodes.insert(Node {
+            manifest_dir: krate.manifest_dir.clone(),
+            name: package.name.to_string(),
+            version: package.version.to_string(),
+        });
+        packages.push((krate.manifest_dir.clone(), package.dependencies.clone()));
+    }
+    let mut edges = BTreeSet::new();
+    for (from, dependencies) in packages {
+        for dependency in dependencies {
+            let kind = match dependency.kind {
+                DependencyKind::Development if !include_dev => continue,
+                DependencyKind::Development => EdgeKind::Dev,
+                DependencyKind::Build => EdgeKind::Build,
+                _ => EdgeKind::Normal,
+            };
+            for node in nodes
+                .iter()
+                .filter(|node| node.name == dependency.name && node.manifest_dir != from)
+            {
+                edges.insert(Edge {
+                    from: from.clone(),
+                    to: node.manifest_dir.clone(),
+                    kind,
+                });
+            }
+        }
+    }
+    Fleet {
+        nodes: nodes.into_iter().collect(),
+        edges: edges.into_iter().collect(),
+    }
+}
+
+/// Returns `graph` in Graphviz `dot` syntax; nodes are labelled with their
+/// name and version, dev-dependencies are dashed and build-dependencies
+/// dotted.
+#[must_use]
+pub fn to_dot(graph: &Fleet) -> String {
+    let quote = |s: &str| format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""));
+    let nodes = graph.nodes.iter().map(|node| {
+        format!(
+            "    {} [label={}];\n",
+            quote(&node.manifest_dir.display().to_string()),
+            quote(&format!("{} {}", node.name, node.version))
+        )
+    });
+    let edges = graph.edges.iter().map(|edge| {
+        let style = match edge.kind {
+            EdgeKind::Normal => "solid",
+            EdgeKind::Dev => "dashed",
+            EdgeKind::Build => "dotted",
+        };
+        format!(
+            "    {} -> {} [label={}, style={style}];\n",
+            quote(&edge.from.display().to_string()),
+            quote(&edge.to.display().to_string()),
+            quote(&edge.kind.to_string())
+        )
+    });
+    format!(
+        "digraph fleet {{\n    rankdir=LR;\n{}}}\n",
+        nodes.chain(edges).collect::<String>()
+    )
+}
+
+/// implementation of the graph subcommand
+///
+/// # Errors
+///
+/// Returns an error if the configuration cannot be loaded or the graph
+/// cannot be serialized.
+#[instrument]
+#[expect(clippy::print_stdout, reason = "This is part of the UI, not logging")]
+pub async fn command(
+    parameters: Parameters,
+    environment: crate::Environment,
+    run_options: crate::RunOptions,
+) -> Result<(), Error> {
+    let config = crate::Config::load(&environment)?;
+    match parameters.sub_command {
+        SubCommand::Fleet(fleet_parameters) => {
+            let graph = fleet(&config, &Cached::new(Subprocess), !fleet_parameters.no_dev);
+            print!("{}", render(&graph, run_options.format)?);
+        }
+    }
+    Ok(())
+}
+
+/// Formats `graph` in `format`: `dot` (also for `text`) or JSON.
+///
+/// # Errors
+///
+/// Returns [`Error::UnsupportedOutputFormat`] for the report formats, or an
+/// error if the graph cannot be serialized.
+fn render(graph: &Fleet, format: OutputFormat) -> Result<String, Error> {
+    match format {
+        OutputFormat::Text | OutputFormat::Dot => Ok(to_dot(graph)),
+        OutputFormat::Json => serde_json::to_string_pretty(graph)
+            .map(|json| format!("{json}\n"))
+            .map_err(Error::CouldNotSerializeListOutput),
+        OutputFormat::Markdown | OutputFormat::Html => Err(Error::UnsupportedOutputFormat(
+            format,
+            "graph fleet".to_owned(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+    use std::path::{Path, PathBuf};
+
+    use pretty_assertions::assert_eq;
+
+    use super::{Edge, EdgeKind, fleet, render, to_dot};
+    use crate::metadata::Fixtures;
+
+    /// Metadata of the standalone crate `name` in `dir` with `dependencies`
+    /// given as name and kind.
+    fn crate_metadata(
+        dir: &str,
+        name: &str,
+        dependencies: &[(&str, Option<&str>)],
+    ) -> Result<cargo_metadata::Metadata, serde_json::Error> {
+        let id = format!("{name}@0.1.0");
+        serde_json::from_value(serde_json::json!({
+            "packages": [{
+                "name": name,
+                "version": "0.1.0",
+                "id": id,
+                "dependencies": dependencies.iter().map(|(dep, kind)| serde_json::json!({
+                    "name": dep,
+                    "req": "*",
+                    "kind": kind,
+                    "optional": false,
+                    "uses_default_features": true,
+                    "features": [],
+                    "target": null,
+                })).collect::<Vec<_>>(),
+                "targets": [],
+                "features": {},
+                "manifest_path": format!("{dir}/Cargo.toml"),
+            }],
+            "workspace_members": [id],
+            "resolve": null,
+            "workspace_root": dir,
+            "target_directory": format!("{dir}/target"),
+            "version": 1,
+        }))
+    }
+
+    #[test]
+    fn edges_connect_registered_crates_by_kind() -> Result<(), Box<dyn std::error::Error>> {
+        let krate = |dir: &str| crate::Crate {
+            manifest_dir: PathBuf::from(dir),
+            workspace_manifest_dir: PathBuf::from(dir),
+            types: BTreeSet::new(),
+            metadata: crate::targets::PackageMetadata::default(),
+            name: None,
+            version: None,
+        };
+        let config = crate::Config {
+            crates: vec![krate("/g/app"), krate("/g/lib"), krate("/g/macros")],
+            ..crate::Config::default()
+        };
+        let fixtures = Fixtures::default()
+            .with(
+                Path::new("/g/app"),
+                crate_metadata(
+                    "/g/app",
+                    "app",
+                    &[("lib", None), ("macros", Some("dev")), ("serde", None)],
+                )?,
+            )
+            .with(
+                Path::new("/g/lib"),
+                crate_metadata("/g/lib", "lib", &[("macros", Some("build"))])?,
+            )
+            .with(
+                Path::new("/g/macros"),
+                crate_metadata("/g/macros", "macros", &[])?,
+            );
+        let edge = |from: &str, to: &str, kind| Edge {
+            from: PathBuf::from(from),
+            to: PathBuf::from(to),
+            kind,
+        };
+        let graph = fleet(&config, &fixtures, true);
+        assert_eq!(graph.nodes.len(), 3);
+        assert_eq!(
+            graph.edges,
+            [
+                edge("/g/app", "/g/lib", EdgeKind::Normal),
+                edge("/g/app", "/g/macros", EdgeKind::Dev),
+                edge("/g/lib", "/g/macros", EdgeKind::Build),
+            ]
+        );
+        assert!(
+            to_dot(&graph).contains("\"/g/app\" -> \"/g/macros\" [label=\"dev\", style=dashed];"),
+            "dev-dependencies are dashed"
+        );
+        assert_eq!(fleet(&config, &fixtures, false).edges.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn dot_is_accepted_as_a_format() -> Result<(), Box<dyn std::error::Error>> {
+        use clap::Parser as _;
+
+        let options = crate::Options::try_parse_from([
+            "cargo-for-each",
+            "--format",
+            "dot",
+            "graph",
+            "fleet",
+        ])?;
+        assert_eq!(options.format, crate::porcelain::OutputFormat::Dot);
+        let graph = fleet(&crate::Config::default(), &Fixtures::default(), true);
+        assert_eq!(render(&graph, options.format)?, to_dot(&graph));
+        assert!(
+            render(&graph, crate::porcelain::OutputFormat::Html).is_err(),
+            "graphs cannot be printed as HTML"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,342 @@
+//! Formatting, lint and documentation checks across all registered targets.
+//!
+//! `hygiene` is a ready-made entry point for the most common fleet-wide job:
+//! checking that every workspace is formatted, passes clippy and builds its
+//! documentation.  Each check becomes its own task (`hygiene-fmt`,
+//! `hygiene-clippy` and `hygiene-doc` by default) with a generated program,
+//! so a failing check does not keep the other checks from running in a
+//! workspace and every run, log and recording can be inspected with the usual
+//! `task` subcommands afterwards.  The tasks are recreated from scratch on
+//! every invocation; tasks of the same name that were not generated by
+//! `hygiene` are never replaced.
+//!
+//! The checks do not depend on each other across workspaces, so targets run
+//! without dependency ordering and a failure in one workspace does not hold
+//! back its dependents.  After all checks ran, the status of every target in
+//! every check is printed as a table, as `task report` would print it.
+
+use std::path::PathBuf;
+
+use tracing::instrument;
+
+use crate::error::Error;
+use crate::metadata::{Cached, Subprocess};
+use crate::program::resolve::{ResolvedProgram, resolve_explicit_workspace_targets};
+use crate::tasks::report;
+use crate::tasks::scheduling::ScheduleStrategy;
+use crate::tasks::{
+    RunAllTargetsParameters, TaskRunParameters, TaskRunSubCommand, named_dir_path, parse_program,
+    remove_task, resolve_from_config, state_dir_for_task, store_task, task_run_command,
+};
+
+/// The first line of every generated program, used to recognize the tasks
+/// `hygiene` may replace.
+pub const MARKER: &str = "// Generated by `cargo for-each hygiene`; recreated on every run.";
+
+/// How strictly warnings are treated by clippy and rustdoc.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LintLevel {
+    /// Warnings fail the check.
+    #[default]
+    Deny,
+    /// Warnings are reported but do not fail the check.
+    Warn,
+}
+
+impl LintLevel {
+    /// Returns the compiler flag setting the level of the `warnings` lint
+    /// group.
+    #[must_use]
+    pub const fn flag(self) -> &'static str {
+        match self {
+            Self::Deny => "-D",
+            Self::Warn => "-W",
+        }
+    }
+}
+
+/// One of the checks run by `hygiene`.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Check {
+    /// `cargo fmt --check`
+    Fmt,
+    /// `cargo clippy` on all targets
+    Clippy,
+    /// `cargo doc` without dependencies
+    Doc,
+}
+
+impl Check {
+    /// All checks, in the order they run.
+    pub const ALL: [Self; 3] = [Self::Fmt, Self::Clippy, Self::Doc];
+
+    /// Returns the name of the check, used as task name suffix and column
+    /// header.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Fmt => "fmt",
+            Self::Clippy => "clippy",
+            Self::Doc => "doc",
+        }
+    }
+
+    /// Returns the `run` statement performing the check in a workspace.
+    #[must_use]
+    pub fn statement(self, lint_level: LintLevel) -> String {
+        let flag = lint_level.flag();
+        match self {
+            Self::Fmt => r#"run "cargo" "fmt" "--all" "--check";"#.to_owned(),
+            Self::Clippy => format!(
+                r#"run "cargo" "clippy" "--workspace" "--all-targets" "--" "{flag}" "warnings";"#
+            ),
+            Self::Doc => format!(
+                r#"run "cargo" "doc" "--workspace" "--no-deps" env "RUSTDOCFLAGS={flag} warnings";"#
+            ),
+        }
+    }
+}
+
+/// Parameters for the `hygiene` command.
+#[derive(clap::Parser, Debug, Clone)]
+pub struct Parameters {
+    /// Prefix of the generated task names; each task is named after its check,
+    /// e.g. `hygiene-fmt`.
+    #[clap(long, default_value = "hygiene")]
+    pub name: String,
+    /// Only run these checks (repeatable); all checks run by default.
+    #[clap(long = "check", value_enum)]
+    pub checks: Vec<Check>,
+    /// How warnings of clippy and rustdoc are treated.
+    #[clap(long, value_enum, default_value_t = LintLevel::Deny)]
+    pub lint_level: LintLevel,
+    /// Only check these workspace directories (repeatable); all registered
+    /// workspaces are checked by default.
+    #[clap(long = "workspace", value_name = "PATH")]
+    pub workspaces: Vec<PathBuf>,
+    /// Number of parallel jobs. Defaults to 1.
+    #[clap(short = 'j', long)]
+    pub jobs: Option<usize>,
+}
+
+/// Returns the program of the task running `check` in every selected
+/// workspace.
+#[must_use]
+pub fn program_source(check: Check, lint_level: LintLevel) -> String {
+    format!(
+        "{MARKER}\nselect workspaces;\nfor workspace {{\n    {}\n}}\n",
+        check.statement(lint_level)
+    )
+}
+
+/// Removes the task `name` and its state if it was generated by `hygiene`.
+///
+/// # Errors
+///
+/// Returns [`Error::NotAHygieneTask`] if a task of that name exists but was
+/// not generated by `hygiene`, or an error if it cannot be removed.
+pub fn remove_generated_task(name: &str, environment: &crate::Environment) -> Result<(), Error> {
+    let task_dir = named_dir_path(name, environment)?;
+    if !task_dir.exists() {
+        return Ok(());
+    }
+    let generated = fs_err::read_to_string(task_dir.join("program.cfe"))
+        .is_ok_and(|source| source.starts_with(MARKER));
+    if !generated {
+        return Err(Error::NotAHygieneTask(name.to_owned()));
+    }
+    remove_task(name, environment)?;
+    let state_dir = state_dir_for_task(name, environment)?;
+    if state_dir.exists() {
+        fs_err::remove_dir_all(&state_dir)
+            .map_err(|e| Error::CouldNotRemoveTaskStateDir(state_dir.clone(), e))?;
+        crate::tasks::completion_cache::invalidate(&state_dir);
+    }
+    Ok(())
+}
+
+/// Resolves the workspaces to check, without dependencies between them.
+///
+/// # Errors
+///
+/// Returns an error if the targets cannot be resolved.
+fn resolve_workspaces(
+    program_source: &str,
+    workspaces: &[PathBuf],
+    environment: &crate::Environment,
+) -> Result<ResolvedProgram, Error> {
+    let program = parse_program(program_source, "<hygiene>")?;
+    let mut resolved = if workspaces.is_empty() {
+        resolve_from_config(&program, environment, false)?
+    } else {
+        ResolvedProgram {
+            workspace_executions: resolve_explicit_workspace_targets(
+                workspaces,
+                &[],
+                &crate::Config::load(environment)?,
+                &Cached::new(Subprocess),
+            )?,
+            crate_executions: vec![],
+        }
+    };
+    for workspace in &mut resolved.workspace_executions {
+        workspace.dependencies.clear();
+    }
+    Ok(resolved)
+}
+
+/// implementation of the `hygiene` command
+///
+/// # Errors
+///
+/// Returns [`Error::HygieneChecksFailed`] if a check failed for some
+/// target, or an error if the targets cannot be resolved or a task cannot be
+/// created or replaced.
+#[instrument]
+#[expect(clippy::print_stdout, reason = "This is part of the UI, not logging")]
+#[expect(clippy::print_stderr, reason = "This is part of the UI, not logging")]
+pub async fn command(
+    params: Parameters,
+    environment: crate::Environment,
+    run_options: crate::RunOptions,
+) -> Result<(), Error> {
+    let format = report::Format::for_command(run_options.format, "hygiene")?;
+    let mut checks = if params.checks.is_empty() {
+        Check::ALL.to_vec()
+    } else {
+        params.checks.clone()
+    };
+    checks.sort();
+    checks.dedup();
+
+    let sources: Vec<(Check, String)> = checks
+        .iter()
+        .map(|check| (*check, program_source(*check, params.lint_level)))
+        .collect();
+    let Some((_, first_source)) = sources.first() else {
+        return Ok(());
+    };
+    let resolved = resolve_workspaces(first_source, &params.workspaces, &environment)?;
+
+    let mut task_names = Vec::new();
+    let mut failed = Vec::new();
+    for (check, source) in &sources {
+        let task_name = format!("{}-{}", params.name, check.name());
+        remove_generated_task(&task_name, &environment)?;
+        store_task(&task_name, None, source, &resolved, &environment)?;
+        println!("Running {} in task {task_name}", check.name());
+        let run = TaskRunParameters {
+            sandbox: false,
+            strict: false,
+            require_clean_git: false,
+            accept_environment_change: false,
+            no_record: false,
+            force: false,
+            dry_run: false,
+            refresh_targets: false,
+            explain_blocking: false,
+            sub_command: TaskRunSubCommand::AllTargets(RunAllTargetsParameters {
+                name: task_name.clone(),
+                jobs: params.jobs,
+                keep_going: true,
+                max_failures: None,
+                schedule: ScheduleStrategy::default(),
+                fail_fast_order: false,
+                until_phase: None,
+                only_phase: None,
+                interactive: false,
+                prefix_output: false,
+            }),
+        };
+        if let Err(e) = task_run_command(run, environment.clone(), run_options.clone()).await {
+            if !matches!(e, Error::SomeStepsFailed) {
+                eprintln!("{task_name}: {e}");
+            }
+            failed.push(check.name());
+        }
+        task_names.push(task_name);
+    }
+
+    let statuses = task_names
+        .iter()
+        .map(|task| report::target_statuses(task, &environment))
+        .collect::<Result<Vec<_>, _>>()?;
+    let headers: Vec<String> = checks.iter().map(|c| c.name().to_owned()).collect();
+    print!(
+        "\n{}",
+        report::render(
+            &headers,
+            &report::rows(&statuses),
+            &[],
+            format,
+            crate::formatting::Style::default(),
+        )
+    );
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::HygieneChecksFailed(failed.join(", ")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::{Check, LintLevel, MARKER, program_source, remove_generated_task};
+    use crate::error::Error;
+    use crate::tasks::{named_dir_path, parse_program, state_dir_for_task};
+
+    #[test]
+    fn generated_programs_parse() -> Result<(), Box<dyn std::error::Error>> {
+        for check in Check::ALL {
+            for lint_level in [LintLevel::Deny, LintLevel::Warn] {
+                let source = program_source(check, lint_level);
+                parse_program(&source, "<hygiene>")?;
+                assert!(source.starts_with(MARKER), "generated programs are marked");
+            }
+        }
+        assert!(
+            program_source(Check::Clippy, LintLevel::Warn).contains(r#""-W" "warnings""#),
+            "the lint level is passed to clippy"
+        );
+        assert!(
+            program_source(Check::Doc, LintLevel::Deny).contains("RUSTDOCFLAGS=-D warnings"),
+            "the lint level is passed to rustdoc"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn only_generated_tasks_are_replaced() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempfile::tempdir()?;
+        let environment = crate::Environment::mock(&temp_dir)?;
+        remove_generated_task("hygiene-fmt", &environment)?;
+
+        let generated = named_dir_path("hygiene-fmt", &environment)?;
+        fs_err::create_dir_all(&generated)?;
+        fs_err::write(
+            generated.join("program.cfe"),
+            program_source(Check::Fmt, LintLevel::Deny),
+        )?;
+        let state_dir = state_dir_for_task("hygiene-fmt", &environment)?;
+        fs_err::create_dir_all(state_dir.join("w0"))?;
+        remove_generated_task("hygiene-fmt", &environment)?;
+        assert!(!generated.exists(), "the generated task is removed");
+        assert!(!state_dir.exists(), "its state is removed as well");
+
+        let own = named_dir_path("hygiene-doc", &environment)?;
+        fs_err::create_dir_all(&own)?;
+        fs_err::write(own.join("program.cfe"), "select workspaces;\n")?;
+        let result = remove_generated_task("hygiene-doc", &environment);
+        assert!(
+            matches!(result, Err(Error::NotAHygieneTask(_))),
+            "other tasks are not replaced: {result:?}"
+        );
+        assert_eq!(
+            fs_err::read_to_string(own.join("program.cfe"))?,
+            "select workspaces;\n"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,280 @@
+//! Persistent run journal.
+//!
+//! Every task invocation appends one JSON line to `journal.jsonl` in the
+//! cargo-for-each state directory, recording when it ran, who ran it on which
+//! host, the command line, and whether it succeeded.  The `history` command
+//! reads the journal back so fleet operations leave an audit trail that does
+//! not depend on the tracing log configuration.
+
+use std::io::Write as _;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+
+use crate::error::Error;
+
+/// A single entry in the run journal.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entry {
+    /// When the invocation finished, in RFC 3339 format (UTC).
+    pub timestamp: String,
+    /// The user who ran the invocation.
+    pub user: String,
+    /// The host the invocation ran on.
+    pub host: String,
+    /// The name of the task the invocation operated on, if any.
+    pub task: Option<String>,
+    /// The command line arguments of the invocation.
+    pub arguments: Vec<String>,
+    /// `success`, or `failed: <error>` if the invocation returned an error.
+    pub outcome: String,
+    /// Manifest directories of the task's targets with a failed statement
+    /// after a failed invocation.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub failed_targets: Vec<PathBuf>,
+}
+
+impl Entry {
+    /// Creates an entry for an invocation of the current process that just
+    /// finished with `result`.
+    #[must_use]
+    pub fn for_current_process(task: Option<String>, result: &Result<(), Error>) -> Self {
+        Self {
+            timestamp: humantime::format_rfc3339_seconds(std::time::SystemTime::now()).to_string(),
+            user: current_user(),
+            host: current_host(),
+            task,
+            arguments: std::env::args().collect(),
+            outcome: match result {
+                Ok(()) => "success".to_string(),
+                Err(e) => format!("failed: {e}"),
+            },
+            failed_targets: Vec::new(),
+        }
+    }
+
+    /// Returns true if the invocation succeeded.
+    #[must_use]
+    pub fn succeeded(&self) -> bool {
+        self.outcome == "success"
+    }
+}
+
+/// Parameters for the `history` command.
+#[derive(clap::Parser, Debug, Clone)]
+pub struct HistoryParameters {
+    /// Only show entries for the task with this name.
+    #[clap(long)]
+    pub task: Option<String>,
+    /// Only show the most recent N entries.
+    #[clap(short = 'n', long)]
+    pub limit: Option<usize>,
+    /// Only show entries whose invocation failed.
+    #[clap(long)]
+    pub failed: bool,
+    /// How timestamps are shown.
+    #[clap(flatten)]
+    pub style: crate::formatting::Style,
+}
+
+/// Returns the path of the journal file.
+#[must_use]
+pub fn file_path(environment: &crate::Environment) -> PathBuf {
+    environment
+        .state_dir
+        .join("cargo-for-each")
+        .join("journal.jsonl")
+}
+
+/// Appends `entry` to the journal, creating the file if necessary.
+///
+/// # Errors
+///
+/// Returns an error if the state directory cannot be created or the journal
+/// cannot be written.
+pub fn append_entry(entry: &Entry, environment: &crate::Environment) -> Result<(), Error> {
+    let path = file_path(environment);
+    if let Some(parent) = path.parent() {
+        fs_err::create_dir_all(parent)
+            .map_err(|e| Error::CouldNotCreateStateDir(parent.to_path_buf(), e))?;
+    }
+    let mut line = serde_json::to_string(entry).map_err(Error::CouldNotSerializeJournalEntry)?;
+    line.push('\n');
+    let mut file = fs_err::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|e| Error::CouldNotWriteJournal(path.clone(), e))?;
+    file.write_all(line.as_bytes())
+        .map_err(|e| Error::CouldNotWriteJournal(path.clone(), e))?;
+    Ok(())
+}
+
+/// Reads all journal entries, oldest first.
+///
+/// A missing journal file yields an empty list.
+///
+/// # Errors
+///
+/// Returns an error if the journal cannot be read or a line cannot be parsed.
+pub fn read_entries(environment: &crate::Environment) -> Result<Vec<Entry>, Error> {
+    let path = file_path(environment);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content =
+        fs_err::read_to_string(&path).map_err(|e| Error::CouldNotReadJournal(path.clone(), e))?;
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            serde_json::from_str(line).map_err(|e| Error::CouldNotParseJournal(path.clone(), e))
+        })
+        .collect()
+}
+
+/// Selects the entries matching `params`, oldest first.
+#[must_use]
+pub fn filter_entries(entries: Vec<Entry>, params: &HistoryParameters) -> Vec<Entry> {
+    let mut selected: Vec<Entry> = entries
+        .into_iter()
+        .filter(|e| {
+            params
+                .task
+                .as_ref()
+                .is_none_or(|task| e.task.as_ref() == Some(task))
+        })
+        .filter(|e| !params.failed || !e.succeeded())
+        .collect();
+    if let Some(limit) = params.limit {
+        let skip = selected.len().saturating_sub(limit);
+        selected.drain(..skip);
+    }
+    selected
+}
+
+/// Prints the journal entries matching `params`, as a JSON array of the
+/// entries as they are stored with `--format json`.
+///
+/// # Errors
+///
+/// Returns an error if the journal cannot be read, or the entries cannot be
+/// printed in the chosen format.
+#[instrument]
+#[expect(clippy::print_stdout, reason = "This is part of the UI, not logging")]
+pub async fn history_command(
+    params: HistoryParameters,
+    environment: crate::Environment,
+    run_options: crate::RunOptions,
+) -> Result<(), Error> {
+    let entries = filter_entries(read_entries(&environment)?, &params);
+    if crate::porcelain::is_json(run_options.format, None, "history")? {
+        println!("{}", crate::porcelain::json(&entries)?);
+        return Ok(());
+    }
+    for entry in entries {
+        println!(
+            "{}  {}@{}  {}  {}",
+            params.style.timestamp(&entry.timestamp),
+            entry.user,
+            entry.host,
+            entry.outcome,
+            entry.arguments.join(" ")
+        );
+    }
+    Ok(())
+}
+
+/// Determines the name of the current user from the environment.
+fn current_user() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("LOGNAME"))
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+/// Determines the host name from the environment or `/etc/hostname`.
+fn current_host() -> String {
+    std::env::var("HOSTNAME")
+        .ok()
+        .or_else(|| {
+            fs_err::read_to_string("/etc/hostname")
+                .ok()
+                .map(|s| s.trim().to_string())
+        })
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use tempfile::tempdir;
+
+    use super::{Entry, HistoryParameters, append_entry, filter_entries, read_entries};
+    use crate::Environment;
+
+    /// Builds a journal entry for `task` with the given outcome.
+    fn entry(task: &str, outcome: &str) -> Entry {
+        Entry {
+            timestamp: "2026-01-01T00:00:00Z".to_string(),
+            user: "alice".to_string(),
+            host: "box".to_string(),
+            task: Some(task.to_string()),
+            arguments: vec!["cargo-for-each".to_string(), "task".to_string()],
+            outcome: outcome.to_string(),
+            failed_targets: vec![],
+        }
+    }
+
+    #[test]
+    fn append_and_read_round_trip() -> Result<(), Box<dyn std::error::Error>> {
+        let temp = tempdir()?;
+        let environment = Environment::mock(&temp)?;
+        assert_eq!(read_entries(&environment)?, vec![]);
+        append_entry(&entry("a", "success"), &environment)?;
+        append_entry(&entry("b", "failed: boom"), &environment)?;
+        assert_eq!(
+            read_entries(&environment)?,
+            vec![entry("a", "success"), entry("b", "failed: boom")]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn filter_by_task_failed_and_limit() {
+        let entries = vec![
+            entry("a", "success"),
+            entry("b", "failed: x"),
+            entry("a", "failed: y"),
+            entry("a", "success"),
+        ];
+        let params = HistoryParameters {
+            task: Some("a".to_string()),
+            limit: None,
+            failed: false,
+            style: crate::formatting::Style::default(),
+        };
+        assert_eq!(filter_entries(entries.clone(), &params).len(), 3);
+        let params = HistoryParameters {
+            task: None,
+            limit: Some(2),
+            failed: false,
+            style: crate::formatting::Style::default(),
+        };
+        assert_eq!(
+            filter_entries(entries.clone(), &params),
+            vec![entry("a", "failed: y"), entry("a", "success")]
+        );
+        let params = HistoryParameters {
+            task: None,
+            limit: None,
+            failed: true,
+            style: crate::formatting::Style::default(),
+        };
+        assert_eq!(
+            filter_entries(entries, &params),
+            vec![entry("b", "failed: x"), entry("a", "failed: y")]
+        );
+    }
+}
@@ -5,10 +5,44 @@
 //! (cargo-for-each) text files and executed against registered target
 //! workspaces and crates.
 
+/// Implements generating man pages, completions and documentation for packaging.
+pub mod assets;
+/// Implements the `config` command exporting and importing the whole setup.
+pub mod backup;
+/// Implements templates for boilerplate files kept in sync across targets.
+pub mod boilerplate;
+/// Implements generating and installing shell completion scripts.
+pub mod completion;
+/// Implements the lock keeping concurrent changes of the configuration apart.
+pub mod config_lock;
 /// Handles application-specific errors.
 pub mod error;
+/// Implements the `exec` command that runs an ad-hoc command in each target.
+pub mod exec;
+/// Formats durations, timestamps and sizes consistently across reports.
+pub mod formatting;
+/// Implements the `outdated` command that aggregates cargo-outdated reports.
+pub mod freshness;
+/// Implements the `graph` command printing the dependencies between all registered crates.
+pub mod graph;
+/// Implements the `hygiene` command running fmt, clippy and doc checks on all targets.
+pub mod hygiene;
+/// Implements the persistent run journal and the `history` command.
+pub mod journal;
+/// Implements project-local configuration directories.
+pub mod local;
+/// Provides `cargo metadata` output to the resolver.
+pub mod metadata;
+/// Implements notifications about finished task runs.
+pub mod notify;
+/// Implements external subcommands run from `cargo-for-each-<name>` executables.
+pub mod plugins;
+/// Defines the stable `--porcelain` output format for scripting.
+pub mod porcelain;
 /// Implements the `.cfe` program language: AST, parser, evaluation, and resolution.
 pub mod program;
+/// Implements the `sync` command that mirrors programs and templates from a shared git repository.
+pub mod shared;
 /// Defines target-related structures and resolution logic.
 pub mod targets;
 /// Implements functionality for managing tasks.
@@ -16,7 +50,8 @@ pub mod tasks;
 /// Implements utility functions.
 pub mod utils;
 
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
+use std::ffi::OsString;
 use std::path::PathBuf;
 
 use serde::{Deserialize, Serialize};
@@ -28,6 +63,24 @@ pub enum Command {
     Target(crate::targets::TargetParameters),
     /// manage tasks
     Task(crate::tasks::TaskParameters),
+    /// Show the run journal of past task invocations.
+    History(crate::journal::HistoryParameters),
+    /// Show outdated dependencies across all registered workspaces.
+    Outdated(crate::freshness::OutdatedParameters),
+    /// Keep boilerplate files in sync across targets using templates.
+    Templates(crate::boilerplate::TemplateParameters),
+    /// Mirror programs and templates from a shared git repository.
+    Sync(crate::shared::SyncParameters),
+    /// Check formatting, clippy lints and documentation of all workspaces.
+    Hygiene(crate::hygiene::Parameters),
+    /// Print the dependency graph of all registered crates.
+    Graph(crate::graph::Parameters),
+    /// Export the configuration, programs and templates to a file or import
+    /// them from one.
+    Config(crate::backup::Parameters),
+    /// Run a command in the manifest directory of each registered crate or
+    /// workspace.
+    Exec(crate::exec::Parameters),
 
     /// Generate man page
     GenerateManpage {
@@ -38,12 +91,26 @@ pub enum Command {
     /// Generate shell completion
     GenerateShellCompletion {
         /// output file for shell completion generation
+        #[clap(long, required_unless_present = "install", conflicts_with = "install")]
+        output_file: Option<PathBuf>,
+        /// which shell (detected from $SHELL if not given)
         #[clap(long)]
-        output_file: PathBuf,
-        /// which shell
+        shell: Option<clap_complete::aot::Shell>,
+        /// install the completion where the shell loads per-user completions from
+        /// and print what to add to the shell's startup file
         #[clap(long)]
-        shell: clap_complete::aot::Shell,
+        install: bool,
     },
+    /// Generate man pages, shell completions for all shells and the program
+    /// language reference, e.g. for distribution packages
+    GenerateAssets {
+        /// target dir for the generated files
+        #[clap(long)]
+        output_dir: PathBuf,
+    },
+    /// Run the external subcommand `cargo-for-each-<name>` found on PATH.
+    #[clap(external_subcommand)]
+    External(Vec<OsString>),
 }
 
 /// The Clap type for all the commandline parameters
@@ -54,6 +121,17 @@ pub enum Command {
        version = clap::crate_version!(),
        )]
 pub struct Options {
+    /// use the project-local configuration in `.cargo-for-each/`, creating it
+    /// in the current directory if there is none above it
+    #[clap(long, global = true, conflicts_with = "no_local")]
+    local: bool,
+    /// use the user configuration even inside a project with a local one
+    #[clap(long, global = true)]
+    no_local: bool,
+    /// the output format of listing and reporting commands (not every
+    /// command supports every format)
+    #[clap(long, global = true, value_enum, default_value_t)]
+    format: crate::porcelain::OutputFormat,
     /// which subcommand to use
     #[clap(subcommand)]
     command: Command,
@@ -71,6 +149,38 @@ pub struct Environment {
     pub paths: Vec<std::path::PathBuf>,
     /// if true, sub-processes stdout and stderr are suppressed and traced
     pub suppress_subprocess_output: bool,
+    /// the directory containing the project-local configuration directory
+    /// used instead of the user configuration, if any
+    pub local_root: Option<std::path::PathBuf>,
+}
+
+/// the options of a single invocation, passed along with the [`Environment`]
+/// it runs in
+#[derive(Debug, Clone, Default)]
+pub struct RunOptions {
+    /// the output format of listing and reporting commands
+    pub format: crate::porcelain::OutputFormat,
+    /// if true, `run` steps are executed in a sandbox that only allows writes
+    /// to the target's own directories
+    pub sandbox: bool,
+    /// if true, waits for exclusive resources are printed with what blocks
+    /// them
+    pub explain_blocking: bool,
+    /// if true, targets whose git working tree is not clean are blocked
+    /// instead of run
+    pub require_clean_git: bool,
+    /// what happens to targets whose environment changed since their earlier
+    /// steps ran
+    pub environment_change: crate::tasks::fingerprint::OnChange,
+    /// checksum of the program of the running task, recorded in the
+    /// environment fingerprints of its steps
+    pub program_checksum: Option<String>,
+    /// how the output of `run` and `manual_step` steps is recorded
+    pub recorder: crate::tasks::recorder::Recorder,
+    /// if set, only statements in these phases are run
+    pub phases: Option<crate::tasks::phases::Selection>,
+    /// cancels the running steps once the run fails
+    pub cancellation: crate::tasks::cancellation::Token,
 }
 
 impl Environment {
@@ -89,6 +199,9 @@ impl Environment {
                 .map(std::path::PathBuf::from)
                 .collect(),
             suppress_subprocess_output: false,
+            local_root: std::env::current_dir()
+                .ok()
+                .and_then(|dir| crate::local::discover(&dir)),
         })
     }
 
@@ -125,6 +238,7 @@ impl Environment {
             state_dir,
             paths,
             suppress_subprocess_output: true,
+            local_root: None,
         })
     }
 }
@@ -138,12 +252,80 @@ pub async fn run_app(
     options: Options,
     environment: Environment,
 ) -> Result<(), crate::error::Error> {
+    let environment = crate::local::select(environment, options.local, options.no_local)?;
+    let run_options = RunOptions {
+        format: options.format,
+        ..RunOptions::default()
+    };
     match options.command {
         Command::Target(target_parameters) => {
-            crate::targets::target_command(target_parameters, environment).await?;
+            crate::targets::target_command(target_parameters, environment, run_options).await?;
         }
         Command::Task(task_parameters) => {
-            crate::tasks::task_command(task_parameters, environment).await?;
+            let task = task_parameters.sub_command.task_name().map(str::to_string);
+            let is_run = matches!(
+                task_parameters.sub_command,
+                crate::tasks::TaskSubCommand::Run(ref run) if !run.dry_run
+            );
+            if !task_parameters.sub_command.is_journalled() {
+                return crate::tasks::task_command(task_parameters, environment, run_options).await;
+            }
+            let journal_environment = environment.clone();
+            let result =
+                crate::tasks::task_command(task_parameters, environment, run_options).await;
+            let mut entry = crate::journal::Entry::for_current_process(task, &result);
+            if result.is_err()
+                && let Some(task) = &entry.task
+            {
+                entry.failed_targets =
+                    crate::tasks::failure_history::failed_targets(task, &journal_environment);
+            }
+            if let Err(e) = crate::journal::append_entry(&entry, &journal_environment) {
+                tracing::warn!("Could not record invocation in the run journal: {e}");
+            }
+            if is_run {
+                match Config::load(&journal_environment) {
+                    Ok(config) => crate::notify::send_all(&config.notifications, &entry).await,
+                    Err(e) => tracing::warn!("Could not load notification settings: {e}"),
+                }
+            }
+            result?;
+        }
+        Command::History(history_parameters) => {
+            crate::journal::history_command(history_parameters, environment, run_options).await?;
+        }
+        Command::Outdated(outdated_parameters) => {
+            crate::freshness::outdated_command(outdated_parameters, environment, run_options)
+                .await?;
+        }
+        Command::Templates(template_parameters) => {
+            crate::boilerplate::template_command(template_parameters, environment, run_options)
+                .await?;
+        }
+        Command::Sync(sync_parameters) => {
+            crate::shared::sync_command(sync_parameters, environment).await?;
+        }
+        Command::Hygiene(hygiene_parameters) => {
+            crate::hygiene::command(hygiene_parameters, environment, run_options).await?;
+        }
+        Command::Graph(graph_parameters) => {
+            crate::graph::command(graph_parameters, environment, run_options).await?;
+        }
+        Command::Config(config_parameters) => {
+            crate::backup::command(config_parameters, environment).await?;
+        }
+        Command::Exec(exec_parameters) => {
+            let journal_environment = environment.clone();
+            let result = crate::exec::command(exec_parameters, environment).await;
+            let entry = crate::journal::Entry::for_current_process(None, &result);
+            if let Err(e) = crate::journal::append_entry(&entry, &journal_environment) {
+                tracing::warn!("Could not record invocation in the run journal: {e}");
+            }
+            match Config::load(&journal_environment) {
+                Ok(config) => crate::notify::send_all(&config.notifications, &entry).await,
+                Err(e) => tracing::warn!("Could not load notification settings: {e}"),
+            }
+            result?;
         }
 
         Command::GenerateManpage { output_dir } => {
@@ -151,11 +333,18 @@ pub async fn run_app(
             clap_mangen::generate_to(<Options as clap::CommandFactory>::command(), output_dir)
                 .map_err(crate::error::Error::GenerateManpageError)?;
         }
-        Command::GenerateShellCompletion { output_file, shell } => {
-            let mut f = std::fs::File::create(output_file)
-                .map_err(crate::error::Error::GenerateShellCompletionError)?;
-            let mut c = <Options as clap::CommandFactory>::command();
-            clap_complete::generate(shell, &mut c, "cargo-for-each", &mut f);
+        Command::GenerateShellCompletion {
+            output_file,
+            shell,
+            install,
+        } => {
+            crate::completion::generate(shell, output_file, install)?;
+        }
+        Command::GenerateAssets { output_dir } => {
+            crate::assets::command(&output_dir)?;
+        }
+        Command::External(args) => {
+            crate::plugins::command(args, environment).await?;
         }
     }
 
@@ -169,6 +358,9 @@ pub struct Workspace {
     pub manifest_dir: PathBuf,
     /// is this a standalone crate workspace
     pub is_standalone: bool,
+    /// the git checkout the workspace lives in, recorded when it was added or refreshed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repository: Option<crate::targets::git::Repository>,
 }
 
 /// represents a Rust crate
@@ -180,11 +372,39 @@ pub struct Crate {
     pub workspace_manifest_dir: PathBuf,
     /// the types of this crate (only bin and lib can be combined so this should have at most two members)
     pub types: BTreeSet<crate::targets::CrateType>,
+    /// the settings from the `[package.metadata.cargo-for-each]` table of the crate's Cargo.toml
+    #[serde(
+        default,
+        skip_serializing_if = "crate::targets::PackageMetadata::is_empty"
+    )]
+    pub metadata: crate::targets::PackageMetadata,
+    /// the package name, recorded when the crate was added or refreshed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// the package version, recorded when the crate was added or refreshed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
 }
 
 /// represents the cargo-for-each configuration file
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Config {
+    /// if true, workspaces and crates are sorted by path and deduplicated
+    /// when the config is saved, so the file diffs cleanly under version control
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub sort_on_save: bool,
+    /// URL of the git repository `sync` mirrors programs and templates from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shared_repository: Option<String>,
+    /// transports that are told when a `task run` invocation finishes
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub notifications: Vec<crate::notify::Transport>,
+    /// named sets of targets programs can select with `in_set`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub target_sets: BTreeMap<String, crate::targets::sets::TargetSet>,
+    /// free-form tags of workspaces and crates, by manifest directory
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tags: BTreeMap<PathBuf, BTreeSet<String>>,
     /// represents all the workspaces we know about
     pub workspaces: Vec<Workspace>,
     /// presents all the crates we know about
@@ -240,7 +460,12 @@ impl Config {
         if fs_err::exists(&config_file_path).map_err(crate::error::Error::CouldNotReadConfigFile)? {
             let file_content = fs_err::read_to_string(&config_file_path)
                 .map_err(crate::error::Error::CouldNotReadConfigFile)?;
-            toml::from_str(&file_content).map_err(crate::error::Error::CouldNotParseConfigFile)
+            let config: Self = toml::from_str(&file_content)
+                .map_err(crate::error::Error::CouldNotParseConfigFile)?;
+            Ok(match &environment.local_root {
+                Some(root) => config.map_paths(&|path| crate::local::resolve(root, path)),
+                None => config,
+            })
         } else {
             Ok(Self::default())
         }
@@ -248,6 +473,9 @@ impl Config {
 
     /// Save the config file
     ///
+    /// The file is replaced atomically, so a crash while saving does not lose
+    /// the registered targets.
+    ///
     /// # Errors
     ///
     /// Returns an error if the config file path cannot be determined,
@@ -259,21 +487,80 @@ impl Config {
             fs_err::create_dir_all(config_dir_path)
                 .map_err(crate::error::Error::CouldNotCreateConfigFileParentDirs)?;
         }
-        fs_err::write(
-            &config_file_path,
-            toml::to_string(self).map_err(crate::error::Error::CouldNotSerializeConfigFile)?,
-        )
-        .map_err(crate::error::Error::CouldNotWriteConfigFile)
+        let config = match &environment.local_root {
+            Some(root) => self
+                .clone()
+                .map_paths(&|path| crate::local::relative(root, path)),
+            None => self.clone(),
+        };
+        let content = if config.sort_on_save {
+            toml::to_string(&config.normalized())
+        } else {
+            toml::to_string(&config)
+        }
+        .map_err(crate::error::Error::CouldNotSerializeConfigFile)?;
+        crate::utils::write_atomically(&config_file_path, content)
+            .map_err(crate::error::Error::CouldNotWriteConfigFile)
+    }
+
+    /// returns the config with `map` applied to every path in it
+    #[must_use]
+    pub fn map_paths(mut self, map: &dyn Fn(&std::path::Path) -> PathBuf) -> Self {
+        for workspace in &mut self.workspaces {
+            workspace.manifest_dir = map(&workspace.manifest_dir);
+            if let Some(repository) = &mut workspace.repository {
+                repository.root = map(&repository.root);
+            }
+        }
+        for krate in &mut self.crates {
+            krate.manifest_dir = map(&krate.manifest_dir);
+            krate.workspace_manifest_dir = map(&krate.workspace_manifest_dir);
+        }
+        self.tags = std::mem::take(&mut self.tags)
+            .into_iter()
+            .map(|(dir, tags)| (map(&dir), tags))
+            .collect();
+        for set in self.target_sets.values_mut() {
+            if let crate::targets::sets::TargetSet::Explicit { manifest_dirs } = set {
+                for dir in manifest_dirs {
+                    *dir = map(dir);
+                }
+            }
+        }
+        self
+    }
+
+    /// returns a copy with workspaces and crates sorted by manifest directory,
+    /// keeping only the first entry for each directory
+    #[must_use]
+    pub fn normalized(&self) -> Self {
+        let mut config = self.clone();
+        config
+            .workspaces
+            .sort_by(|a, b| a.manifest_dir.cmp(&b.manifest_dir));
+        config
+            .workspaces
+            .dedup_by(|a, b| a.manifest_dir == b.manifest_dir);
+        config
+            .crates
+            .sort_by(|a, b| a.manifest_dir.cmp(&b.manifest_dir));
+        config
+            .crates
+            .dedup_by(|a, b| a.manifest_dir == b.manifest_dir);
+        config
     }
 }
 
-/// returns the config dir path
+/// returns the config dir path, the project-local one if it is used
 ///
 /// # Errors
 ///
 /// Returns an error if the user's config directory cannot be determined.
 pub fn config_dir_path(environment: &Environment) -> Result<PathBuf, crate::error::Error> {
-    Ok(environment.config_dir.join("cargo-for-each"))
+    Ok(match &environment.local_root {
+        Some(root) => root.join(crate::local::DIR_NAME),
+        None => environment.config_dir.join("cargo-for-each"),
+    })
 }
 
 /// returns the config file path
@@ -287,6 +574,8 @@ pub fn config_file(environment: &Environment) -> Result<PathBuf, crate::error::E
 
 #[cfg(test)]
 mod tests {
+    use pretty_assertions::assert_eq;
+
     use super::*;
     use crate::{
         targets::{
@@ -295,7 +584,7 @@ mod tests {
         },
         tasks::{
             CreateTaskParameters, RunAllTargetsParameters, TaskParameters, TaskRunParameters,
-            TaskRunSubCommand, TaskSubCommand,
+            TaskRunSubCommand, TaskSubCommand, scheduling::ScheduleStrategy,
         },
         utils::execute_command,
     };
@@ -311,8 +600,12 @@ mod tests {
 
         // Create Options for the "targets list" command
         let options = Options {
+            local: false,
+            no_local: false,
+            format: crate::porcelain::OutputFormat::Text,
             command: Command::Target(TargetParameters {
                 sub_command: TargetSubCommand::List(ListParameters {
+                    porcelain: None,
                     target_filter: TargetFilter::Workspaces(WorkspaceFilterParameters::default()),
                 }),
             }),
@@ -361,9 +654,13 @@ mod tests {
         tracing::debug!("Adding test1 as a target");
 
         let options = Options {
+            local: false,
+            no_local: false,
+            format: crate::porcelain::OutputFormat::Text,
             command: Command::Target(TargetParameters {
                 sub_command: TargetSubCommand::Add(AddParameters {
-                    manifest_path: workspaces_dir.join("test1").join("Cargo.toml"),
+                    manifest_path: vec![workspaces_dir.join("test1").join("Cargo.toml")],
+                    nested: None,
                 }),
             }),
         };
@@ -396,9 +693,13 @@ mod tests {
         tracing::debug!("Adding test2 as a target");
 
         let options = Options {
+            local: false,
+            no_local: false,
+            format: crate::porcelain::OutputFormat::Text,
             command: Command::Target(TargetParameters {
                 sub_command: TargetSubCommand::Add(AddParameters {
-                    manifest_path: workspaces_dir.join("test2").join("Cargo.toml"),
+                    manifest_path: vec![workspaces_dir.join("test2").join("Cargo.toml")],
+                    nested: None,
                 }),
             }),
         };
@@ -422,12 +723,20 @@ mod tests {
         tracing::debug!("Creating task test-task from test.cfe");
 
         let options = Options {
+            local: false,
+            no_local: false,
+            format: crate::porcelain::OutputFormat::Text,
             command: Command::Task(TaskParameters {
                 sub_command: TaskSubCommand::Create(CreateTaskParameters {
                     name: "test-task".to_string(),
                     program: cfe_path,
                     workspaces: vec![],
                     crates: vec![],
+                    workspaces_from: None,
+                    crates_from: None,
+                    skip_command_check: false,
+                    register_path_dependencies: false,
+                    include_dependents: false,
                 }),
             }),
         };
@@ -443,12 +752,31 @@ mod tests {
         tracing::debug!("Running task test-task");
 
         let options = Options {
+            local: false,
+            no_local: false,
+            format: crate::porcelain::OutputFormat::Text,
             command: Command::Task(TaskParameters {
                 sub_command: TaskSubCommand::Run(TaskRunParameters {
+                    sandbox: false,
+                    explain_blocking: false,
+                    strict: false,
+                    require_clean_git: false,
+                    accept_environment_change: false,
+                    no_record: false,
+                    force: false,
+                    dry_run: false,
+                    refresh_targets: false,
                     sub_command: TaskRunSubCommand::AllTargets(RunAllTargetsParameters {
                         name: "test-task".to_string(),
                         jobs: None,
                         keep_going: false,
+                        max_failures: None,
+                        schedule: ScheduleStrategy::default(),
+                        fail_fast_order: false,
+                        until_phase: None,
+                        only_phase: None,
+                        interactive: false,
+                        prefix_output: false,
                     }),
                 }),
             }),
@@ -523,9 +851,13 @@ mod tests {
         tracing::debug!("Adding workspace1 as a target");
 
         let options = Options {
+            local: false,
+            no_local: false,
+            format: crate::porcelain::OutputFormat::Text,
             command: Command::Target(TargetParameters {
                 sub_command: TargetSubCommand::Add(AddParameters {
-                    manifest_path: workspace1_dir.join("Cargo.toml"),
+                    manifest_path: vec![workspace1_dir.join("Cargo.toml")],
+                    nested: None,
                 }),
             }),
         };
@@ -584,9 +916,13 @@ mod tests {
         tracing::debug!("Adding workspace2 as a target");
 
         let options = Options {
+            local: false,
+            no_local: false,
+            format: crate::porcelain::OutputFormat::Text,
             command: Command::Target(TargetParameters {
                 sub_command: TargetSubCommand::Add(AddParameters {
-                    manifest_path: workspace2_dir.join("Cargo.toml"),
+                    manifest_path: vec![workspace2_dir.join("Cargo.toml")],
+                    nested: None,
                 }),
             }),
         };
@@ -610,12 +946,20 @@ mod tests {
         tracing::debug!("Creating task test-task from test.cfe");
 
         let options = Options {
+            local: false,
+            no_local: false,
+            format: crate::porcelain::OutputFormat::Text,
             command: Command::Task(TaskParameters {
                 sub_command: TaskSubCommand::Create(CreateTaskParameters {
                     name: "test-task".to_string(),
                     program: cfe_path,
                     workspaces: vec![],
                     crates: vec![],
+                    workspaces_from: None,
+                    crates_from: None,
+                    skip_command_check: false,
+                    register_path_dependencies: false,
+                    include_dependents: false,
                 }),
             }),
         };
@@ -631,12 +975,31 @@ mod tests {
         tracing::debug!("Running task test-task");
 
         let options = Options {
+            local: false,
+            no_local: false,
+            format: crate::porcelain::OutputFormat::Text,
             command: Command::Task(TaskParameters {
                 sub_command: TaskSubCommand::Run(TaskRunParameters {
+                    sandbox: false,
+                    explain_blocking: false,
+                    strict: false,
+                    require_clean_git: false,
+                    accept_environment_change: false,
+                    no_record: false,
+                    force: false,
+                    dry_run: false,
+                    refresh_targets: false,
                     sub_command: TaskRunSubCommand::AllTargets(RunAllTargetsParameters {
                         name: "test-task".to_string(),
                         jobs: None,
                         keep_going: false,
+                        max_failures: None,
+                        schedule: ScheduleStrategy::default(),
+                        fail_fast_order: false,
+                        until_phase: None,
+                        only_phase: None,
+                        interactive: false,
+                        prefix_output: false,
                     }),
                 }),
             }),
@@ -679,9 +1042,13 @@ mod tests {
         execute_command(&mut cmd, &environment, &workspaces_dir)?;
 
         let options = Options {
+            local: false,
+            no_local: false,
+            format: crate::porcelain::OutputFormat::Text,
             command: Command::Target(TargetParameters {
                 sub_command: TargetSubCommand::Add(AddParameters {
-                    manifest_path: workspaces_dir.join("failing_target").join("Cargo.toml"),
+                    manifest_path: vec![workspaces_dir.join("failing_target").join("Cargo.toml")],
+                    nested: None,
                 }),
             }),
         };
@@ -696,12 +1063,20 @@ mod tests {
         )?;
 
         let options = Options {
+            local: false,
+            no_local: false,
+            format: crate::porcelain::OutputFormat::Text,
             command: Command::Task(TaskParameters {
                 sub_command: TaskSubCommand::Create(CreateTaskParameters {
                     name: "failing-task".to_string(),
                     program: cfe_path,
                     workspaces: vec![],
                     crates: vec![],
+                    workspaces_from: None,
+                    crates_from: None,
+                    skip_command_check: true,
+                    register_path_dependencies: false,
+                    include_dependents: false,
                 }),
             }),
         };
@@ -710,23 +1085,203 @@ mod tests {
         // Run with keep_going=true — must terminate and report SomeStepsFailed,
         // not loop forever (Bug 1) and not return CircularDependency (Bug 3).
         let options = Options {
+            local: false,
+            no_local: false,
+            format: crate::porcelain::OutputFormat::Text,
             command: Command::Task(TaskParameters {
                 sub_command: TaskSubCommand::Run(TaskRunParameters {
+                    sandbox: false,
+                    explain_blocking: false,
+                    strict: false,
+                    require_clean_git: false,
+                    accept_environment_change: false,
+                    no_record: false,
+                    force: false,
+                    dry_run: false,
+                    refresh_targets: false,
                     sub_command: TaskRunSubCommand::AllTargets(RunAllTargetsParameters {
                         name: "failing-task".to_string(),
                         jobs: None,
                         keep_going: true,
+                        max_failures: None,
+                        schedule: ScheduleStrategy::default(),
+                        fail_fast_order: false,
+                        until_phase: None,
+                        only_phase: None,
+                        interactive: false,
+                        prefix_output: false,
                     }),
                 }),
             }),
         };
-        let result = run_app(options, environment).await;
+        let result = run_app(options, environment.clone()).await;
 
         assert!(
             matches!(result, Err(crate::error::Error::SomeStepsFailed)),
             "expected SomeStepsFailed with keep_going=true on a failing step, got {result:?}"
         );
 
+        // With --max-failures 1 the first failed target aborts the run.
+        let options = Options {
+            local: false,
+            no_local: false,
+            format: crate::porcelain::OutputFormat::Text,
+            command: Command::Task(TaskParameters {
+                sub_command: TaskSubCommand::Run(TaskRunParameters {
+                    sandbox: false,
+                    explain_blocking: false,
+                    strict: false,
+                    require_clean_git: false,
+                    accept_environment_change: false,
+                    no_record: false,
+                    force: false,
+                    dry_run: false,
+                    refresh_targets: false,
+                    sub_command: TaskRunSubCommand::AllTargets(RunAllTargetsParameters {
+                        name: "failing-task".to_string(),
+                        jobs: None,
+                        keep_going: true,
+                        max_failures: Some(1),
+                        schedule: ScheduleStrategy::default(),
+                        fail_fast_order: false,
+                        until_phase: None,
+                        only_phase: None,
+                        interactive: false,
+                        prefix_output: false,
+                    }),
+                }),
+            }),
+        };
+        let result = run_app(options, environment.clone()).await;
+        assert!(
+            matches!(result, Err(crate::error::Error::TooManyFailures(1))),
+            "expected TooManyFailures with max_failures=1, got {result:?}"
+        );
+
+        // A run interrupted with Ctrl+C does not start any further targets.
+        let interrupted = RunOptions::default();
+        interrupted.cancellation.cancel();
+        let task_parameters = TaskParameters {
+            sub_command: TaskSubCommand::Run(TaskRunParameters {
+                sandbox: false,
+                explain_blocking: false,
+                strict: false,
+                require_clean_git: false,
+                accept_environment_change: false,
+                no_record: false,
+                force: false,
+                dry_run: false,
+                refresh_targets: false,
+                sub_command: TaskRunSubCommand::AllTargets(RunAllTargetsParameters {
+                    name: "failing-task".to_string(),
+                    jobs: None,
+                    keep_going: false,
+                    max_failures: None,
+                    schedule: ScheduleStrategy::default(),
+                    fail_fast_order: false,
+                    until_phase: None,
+                    only_phase: None,
+                    interactive: false,
+                    prefix_output: false,
+                }),
+            }),
+        };
+        let result = crate::tasks::task_command(task_parameters, environment, interrupted).await;
+        assert!(
+            matches!(result, Err(crate::error::Error::RunInterrupted)),
+            "expected RunInterrupted after Ctrl+C, got {result:?}"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_config_sorted_and_deduplicated_on_save() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempfile::tempdir()?;
+        let environment = Environment::mock(&temp_dir)?;
+        let workspace = |dir: &str| Workspace {
+            manifest_dir: PathBuf::from(dir),
+            is_standalone: true,
+            repository: None,
+        };
+        let mut config = Config {
+            sort_on_save: false,
+            shared_repository: None,
+            notifications: vec![],
+            target_sets: BTreeMap::new(),
+            tags: BTreeMap::new(),
+            workspaces: vec![workspace("/b"), workspace("/a"), workspace("/b")],
+            crates: vec![],
+        };
+        config.save(&environment)?;
+        let dirs = |config: &Config| {
+            config
+                .workspaces
+                .iter()
+                .map(|w| w.manifest_dir.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            dirs(&Config::load(&environment)?),
+            vec![
+                PathBuf::from("/b"),
+                PathBuf::from("/a"),
+                PathBuf::from("/b")
+            ]
+        );
+
+        config.sort_on_save = true;
+        config.save(&environment)?;
+        let loaded = Config::load(&environment)?;
+        assert!(loaded.sort_on_save, "the setting should be persisted");
+        assert_eq!(
+            dirs(&loaded),
+            vec![PathBuf::from("/a"), PathBuf::from("/b")]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_local_config_stores_project_paths_relative() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempfile::tempdir()?;
+        let root = temp_dir.path().join("monorepo");
+        let environment = Environment {
+            local_root: Some(root.clone()),
+            ..Environment::mock(&temp_dir)?
+        };
+        let config = Config {
+            workspaces: vec![
+                Workspace {
+                    manifest_dir: root.join("api"),
+                    is_standalone: true,
+                    repository: None,
+                },
+                Workspace {
+                    manifest_dir: PathBuf::from("/elsewhere"),
+                    is_standalone: true,
+                    repository: None,
+                },
+            ],
+            ..Config::default()
+        };
+        config.save(&environment)?;
+        assert!(
+            config_file(&environment)?.starts_with(root.join(crate::local::DIR_NAME)),
+            "the config lives in the project"
+        );
+        let saved = fs_err::read_to_string(config_file(&environment)?)?;
+        assert!(
+            saved.contains("manifest_dir = \"api\""),
+            "project paths are stored relative: {saved}"
+        );
+        assert_eq!(
+            Config::load(&environment)?
+                .workspaces
+                .iter()
+                .map(|w| w.manifest_dir.clone())
+                .collect::<Vec<_>>(),
+            vec![root.join("api"), PathBuf::from("/elsewhere")]
+        );
         Ok(())
     }
 }
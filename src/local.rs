@@ -0,0 +1,116 @@
+//! Project-local configuration.
+//!
+//! Teams working in a monorepo want the registered targets, target sets,
+//! programs and templates versioned with the code rather than in every
+//! developer's own configuration.  A `.cargo-for-each/` directory in the
+//! repository takes the place of `~/.config/cargo-for-each/`: it is found by
+//! walking up from the current directory and then used instead of the user
+//! configuration, with the same layout (`cargo-for-each.toml`, `programs/`,
+//! `templates/`, `tasks/`).  `--local` requires it, creating it in the current
+//! directory if there is none yet, and `--no-local` ignores it.
+//!
+//! Paths inside the project are stored relative to the directory containing
+//! `.cargo-for-each/`, so the file works in every checkout.  The execution
+//! state of the tasks is kept in the user's state directory, separately for
+//! each project.
+
+use std::path::{Path, PathBuf};
+
+use crate::error::Error;
+use crate::tasks::program_source::checksum;
+
+/// Name of the project-local configuration directory.
+pub const DIR_NAME: &str = ".cargo-for-each";
+
+/// Returns the closest directory at or above `start` that contains a
+/// project-local configuration directory.
+#[must_use]
+pub fn discover(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .find(|dir| dir.join(DIR_NAME).is_dir())
+        .map(Path::to_path_buf)
+}
+
+/// Returns `path` relative to the project `root` if it is inside of it.
+#[must_use]
+pub fn relative(root: &Path, path: &Path) -> PathBuf {
+    match path.strip_prefix(root) {
+        Ok(relative) if relative.as_os_str().is_empty() => PathBuf::from("."),
+        Ok(relative) => relative.to_path_buf(),
+        Err(_) => path.to_path_buf(),
+    }
+}
+
+/// Returns the absolute path for `path` stored relative to the project
+/// `root`; absolute paths are kept.
+#[must_use]
+pub fn resolve(root: &Path, path: &Path) -> PathBuf {
+    if path == Path::new(".") {
+        root.to_path_buf()
+    } else {
+        root.join(path)
+    }
+}
+
+/// Applies `--local` and `--no-local` to `environment`, whose project was
+/// auto-detected, and gives the project its own state directory.
+///
+/// # Errors
+///
+/// Returns an error if `--local` is given outside of a project and the
+/// current directory cannot be determined.
+pub fn select(
+    mut environment: crate::Environment,
+    local: bool,
+    no_local: bool,
+) -> Result<crate::Environment, Error> {
+    if no_local {
+        environment.local_root = None;
+    } else if local && environment.local_root.is_none() {
+        environment.local_root = Some(std::env::current_dir().map_err(Error::IoError)?);
+    }
+    if let Some(root) = &environment.local_root {
+        let project = checksum(&root.to_string_lossy());
+        environment.state_dir = environment
+            .state_dir
+            .join("cargo-for-each")
+            .join("projects")
+            .join(project.get(..16).unwrap_or(&project));
+    }
+    Ok(environment)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use pretty_assertions::assert_eq;
+
+    use super::{DIR_NAME, discover, relative, resolve};
+
+    #[test]
+    fn projects_are_found_and_paths_stored_relative() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempfile::tempdir()?;
+        let root = temp_dir.path().join("monorepo");
+        let member = root.join("crates").join("api");
+        fs_err::create_dir_all(&member)?;
+        assert_eq!(discover(&member), None);
+        fs_err::create_dir_all(root.join(DIR_NAME))?;
+        assert_eq!(discover(&member), Some(root.clone()));
+
+        assert_eq!(relative(&root, &member), PathBuf::from("crates/api"));
+        assert_eq!(relative(&root, &root), PathBuf::from("."));
+        assert_eq!(
+            relative(&root, Path::new("/elsewhere")),
+            Path::new("/elsewhere")
+        );
+        assert_eq!(resolve(&root, Path::new("crates/api")), member);
+        assert_eq!(resolve(&root, Path::new(".")), root);
+        assert_eq!(
+            resolve(&root, Path::new("/elsewhere")),
+            Path::new("/elsewhere")
+        );
+        Ok(())
+    }
+}
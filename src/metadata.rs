@@ -0,0 +1,207 @@
+//! Access to `cargo metadata` behind a replaceable provider.
+//!
+//! Resolving a program asks for the metadata of the same manifests several
+//! times: once per selected crate to find its workspace root, once per
+//! workspace root for the packages, and again when looking for unregistered
+//! path dependencies.  Each request spawns `cargo metadata`, which dominates
+//! the time `task create` takes for larger fleets.
+//!
+//! The resolver therefore asks a [`MetadataProvider`] instead of running
+//! `cargo metadata` itself.  [`Subprocess`] runs `cargo metadata`,
+//! [`Cached`] wraps another provider and keeps the result per manifest
+//! directory for as long as it lives (one command), and [`Fixtures`] serves
+//! prepared metadata so tests can resolve programs without `cargo`.
+//!
+//! The resolver only uses `--no-deps` metadata; it does not need the resolved
+//! dependency graph, which requires reading the lock file and possibly the
+//! network.  [`WithDependencies`] provides the graph for the few commands
+//! that do, like `target list crates --depends-on`.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use cargo_metadata::Metadata;
+
+use crate::error::Error;
+
+/// A source of `cargo metadata` output.
+#[expect(
+    clippy::module_name_repetitions,
+    reason = "the name is used on its own outside of this module"
+)]
+pub trait MetadataProvider: std::fmt::Debug + Send + Sync {
+    /// Returns the metadata of the manifest in `manifest_dir`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::CargoMetadataError`] if the metadata cannot be
+    /// obtained.
+    fn metadata(&self, manifest_dir: &Path) -> Result<Arc<Metadata>, Error>;
+}
+
+/// Runs `cargo metadata` for every request.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Subprocess;
+
+impl MetadataProvider for Subprocess {
+    fn metadata(&self, manifest_dir: &Path) -> Result<Arc<Metadata>, Error> {
+        cargo_metadata::MetadataCommand::new()
+            .manifest_path(manifest_dir.join("Cargo.toml"))
+            .no_deps()
+            .exec()
+            .map(Arc::new)
+            .map_err(|e| Error::CargoMetadataError(manifest_dir.to_path_buf(), e))
+    }
+}
+
+/// Runs `cargo metadata` with the resolved dependency graph for every
+/// request.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WithDependencies;
+
+impl MetadataProvider for WithDependencies {
+    fn metadata(&self, manifest_dir: &Path) -> Result<Arc<Metadata>, Error> {
+        cargo_metadata::MetadataCommand::new()
+            .manifest_path(manifest_dir.join("Cargo.toml"))
+            .exec()
+            .map(Arc::new)
+            .map_err(|e| Error::CargoMetadataError(manifest_dir.to_path_buf(), e))
+    }
+}
+
+/// Asks the inner provider once per manifest directory and remembers the
+/// result.  Errors are not remembered.
+#[derive(Debug, Default)]
+pub struct Cached<P> {
+    /// The provider asked on a cache miss.
+    inner: P,
+    /// The metadata obtained so far, by manifest directory.
+    cache: Mutex<HashMap<PathBuf, Arc<Metadata>>>,
+}
+
+impl<P: MetadataProvider> Cached<P> {
+    /// Creates an empty cache in front of `inner`.
+    #[must_use]
+    pub fn new(inner: P) -> Self {
+        Self {
+            inner,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<P: MetadataProvider> MetadataProvider for Cached<P> {
+    fn metadata(&self, manifest_dir: &Path) -> Result<Arc<Metadata>, Error> {
+        if let Ok(cache) = self.cache.lock()
+            && let Some(metadata) = cache.get(manifest_dir)
+        {
+            return Ok(Arc::clone(metadata));
+        }
+        let metadata = self.inner.metadata(manifest_dir)?;
+        if let Ok(mut cache) = self.cache.lock() {
+            cache.insert(manifest_dir.to_path_buf(), Arc::clone(&metadata));
+        }
+        Ok(metadata)
+    }
+}
+
+/// Serves prepared metadata by manifest directory.
+#[derive(Debug, Clone, Default)]
+pub struct Fixtures {
+    /// The metadata of each known manifest directory.
+    by_dir: HashMap<PathBuf, Arc<Metadata>>,
+}
+
+impl Fixtures {
+    /// Returns the fixtures with `metadata` added for `manifest_dir`.
+    #[must_use]
+    pub fn with(mut self, manifest_dir: &Path, metadata: Metadata) -> Self {
+        self.by_dir
+            .insert(manifest_dir.to_path_buf(), Arc::new(metadata));
+        self
+    }
+}
+
+impl MetadataProvider for Fixtures {
+    fn metadata(&self, manifest_dir: &Path) -> Result<Arc<Metadata>, Error> {
+        self.by_dir.get(manifest_dir).cloned().ok_or_else(|| {
+            Error::CargoMetadataError(
+                manifest_dir.to_path_buf(),
+                cargo_metadata::Error::CargoMetadata {
+                    stderr: "no metadata fixture for this directory".to_owned(),
+                },
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use cargo_metadata::Metadata;
+    use pretty_assertions::assert_eq;
+
+    use super::{Cached, Fixtures, MetadataProvider};
+    use crate::error::Error;
+
+    /// Metadata of a workspace in `/src/a` without packages.
+    fn empty_metadata() -> Result<Metadata, serde_json::Error> {
+        serde_json::from_str(
+            r#"{
+                "packages": [],
+                "workspace_members": [],
+                "resolve": null,
+                "workspace_root": "/src/a",
+                "target_directory": "/src/a/target",
+                "version": 1
+            }"#,
+        )
+    }
+
+    /// Counts how often it is asked.
+    #[derive(Debug, Default)]
+    struct Counting {
+        /// The number of requests so far.
+        calls: AtomicUsize,
+        /// Serves the metadata.
+        fixtures: Fixtures,
+    }
+
+    impl MetadataProvider for Counting {
+        fn metadata(&self, manifest_dir: &Path) -> Result<Arc<Metadata>, Error> {
+            self.calls.fetch_add(1, Ordering::Relaxed);
+            self.fixtures.metadata(manifest_dir)
+        }
+    }
+
+    #[test]
+    fn metadata_is_fetched_once_per_directory() -> Result<(), Box<dyn std::error::Error>> {
+        let cached = Cached::new(Counting {
+            calls: AtomicUsize::new(0),
+            fixtures: Fixtures::default().with(Path::new("/src/a"), empty_metadata()?),
+        });
+        for _ in 0..3 {
+            assert_eq!(
+                cached
+                    .metadata(Path::new("/src/a"))?
+                    .workspace_root
+                    .as_str(),
+                "/src/a"
+            );
+        }
+        assert!(
+            cached.metadata(Path::new("/src/b")).is_err(),
+            "unknown directories have no metadata"
+        );
+        assert!(
+            cached.metadata(Path::new("/src/b")).is_err(),
+            "errors are not cached"
+        );
+        assert_eq!(cached.inner.calls.load(Ordering::Relaxed), 3);
+        Ok(())
+    }
+}
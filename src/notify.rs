@@ -0,0 +1,346 @@
+//! Notifications about finished task runs.
+//!
+//! The `notifications` list in the configuration file names transports that
+//! are told when a `task run` invocation finishes, so a long fleet operation
+//! does not have to be watched.  Transports use programs that are usually
+//! already set up on hosts that send alerts instead of talking to the network
+//! themselves: email is handed to a sendmail-compatible program (e.g. `msmtp`,
+//! which submits it to an SMTP server), and Matrix room messages are sent
+//! with `curl`.  A notification that cannot be delivered is logged as a
+//! warning and does not change the outcome of the run.
+
+use std::io::Write as _;
+use std::process::{Command, Stdio};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+use crate::journal::Entry;
+
+/// Which finished runs a transport is told about.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum When {
+    /// Only runs that failed.
+    #[default]
+    Failure,
+    /// Every run.
+    Always,
+}
+
+/// A way to deliver notifications, configured in the `notifications` list of
+/// the configuration file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "transport", rename_all = "kebab-case")]
+pub enum Transport {
+    /// An email handed to a sendmail-compatible program.
+    Email {
+        /// The recipients.
+        to: Vec<String>,
+        /// The sender, if the program should not choose one.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        from: Option<String>,
+        /// The sendmail-compatible program, `sendmail` by default.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        sendmail: Option<String>,
+        /// Which runs to report.
+        #[serde(default)]
+        on: When,
+    },
+    /// A message in a Matrix room, sent with `curl`.
+    Matrix {
+        /// The base URL of the homeserver, e.g. `https://matrix.example.org`.
+        homeserver: String,
+        /// The ID of the room, e.g. `!abcdef:example.org`.
+        room: String,
+        /// The environment variable holding the access token, so the token
+        /// does not have to be stored in the configuration file.
+        access_token_env: String,
+        /// Which runs to report.
+        #[serde(default)]
+        on: When,
+    },
+}
+
+impl Transport {
+    /// Returns `true` if the transport should be told about `entry`.
+    #[must_use]
+    pub fn wants(&self, entry: &Entry) -> bool {
+        let on = match self {
+            Self::Email { on, .. } | Self::Matrix { on, .. } => on,
+        };
+        *on == When::Always || !entry.succeeded()
+    }
+}
+
+/// Returns the subject and the body of the notification about `entry`.
+#[must_use]
+pub fn message(entry: &Entry) -> (String, String) {
+    let task = entry.task.as_deref().unwrap_or("(none)");
+    let result = if entry.succeeded() {
+        "succeeded"
+    } else {
+        "failed"
+    };
+    let subject = format!("cargo-for-each: task {task} {result}");
+    let mut body = format!(
+        "{subject} on {} (run by {}) at {}.\n\nCommand: {}\nOutcome: {}\n",
+        entry.host,
+        entry.user,
+        entry.timestamp,
+        entry.arguments.join(" "),
+        entry.outcome
+    );
+    if !entry.failed_targets.is_empty() {
+        body.push_str("\nFailed targets:\n");
+        for target in &entry.failed_targets {
+            body.push_str("  ");
+            body.push_str(&target.display().to_string());
+            body.push('\n');
+        }
+    }
+    (subject, body)
+}
+
+/// Returns the email to hand to sendmail with `-t`.
+#[must_use]
+pub fn email(to: &[String], from: Option<&str>, subject: &str, body: &str) -> String {
+    let mut mail = format!("To: {}\n", to.join(", "));
+    if let Some(from) = from {
+        mail.push_str("From: ");
+        mail.push_str(from);
+        mail.push('\n');
+    }
+    mail.push_str("Subject: ");
+    mail.push_str(subject);
+    mail.push_str("\nContent-Type: text/plain; charset=utf-8\n\n");
+    mail.push_str(body);
+    mail
+}
+
+/// Percent-encodes `segment` for use in a URL path.
+fn encode_path_segment(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|b| {
+            if b.is_ascii_alphanumeric() || b"-._~".contains(&b) {
+                char::from(b).to_string()
+            } else {
+                format!("%{b:02X}")
+            }
+        })
+        .collect()
+}
+
+/// Returns the URL to send a message with transaction ID `txn_id` to `room`.
+#[must_use]
+pub fn matrix_url(homeserver: &str, room: &str, txn_id: &str) -> String {
+    format!(
+        "{}/_matrix/client/v3/rooms/{}/send/m.room.message/{}",
+        homeserver.trim_end_matches('/'),
+        encode_path_segment(room),
+        encode_path_segment(txn_id)
+    )
+}
+
+/// Runs `command`, writing `input` to its standard input.
+///
+/// # Errors
+///
+/// Returns an error if the command cannot be started or fails.
+fn run_with_input(mut command: Command, input: &str) -> Result<(), Error> {
+    let description = format!("{command:?}");
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .map_err(|e| Error::CouldNotSendNotification(description.clone(), e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(input.as_bytes())
+            .map_err(|e| Error::CouldNotSendNotification(description.clone(), e))?;
+    }
+    let status = child
+        .wait()
+        .map_err(|e| Error::CouldNotSendNotification(description.clone(), e))?;
+    if !status.success() {
+        return Err(Error::NotificationFailed(
+            description,
+            status.code().unwrap_or(-1),
+        ));
+    }
+    Ok(())
+}
+
+/// Delivers the notification about `entry` with `transport`.
+///
+/// # Errors
+///
+/// Returns an error if the access token of a Matrix transport is not set or
+/// the program delivering the notification cannot be run or fails.
+pub fn send(transport: &Transport, entry: &Entry) -> Result<(), Error> {
+    let (subject, body) = message(entry);
+    match transport {
+        Transport::Email {
+            to, from, sendmail, ..
+        } => {
+            let mut command = Command::new(sendmail.as_deref().unwrap_or("sendmail"));
+            command.arg("-t");
+            run_with_input(command, &email(to, from.as_deref(), &subject, &body))
+        }
+        Transport::Matrix {
+            homeserver,
+            room,
+            access_token_env,
+            ..
+        } => {
+            let token = std::env::var(access_token_env)
+                .map_err(|_err| Error::MissingNotificationToken(access_token_env.clone()))?;
+            let txn_id = format!(
+                "cargo-for-each-{}-{}",
+                std::process::id(),
+                std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map(|d| d.as_nanos())
+                    .unwrap_or_default()
+            );
+            let content = serde_json::json!({ "msgtype": "m.text", "body": body });
+            let mut command = Command::new("curl");
+            // the token is passed on standard input so it does not show up in
+            // the process list
+            command.args([
+                "--silent",
+                "--show-error",
+                "--fail",
+                "--request",
+                "PUT",
+                "--header",
+                "@-",
+                "--header",
+                "Content-Type: application/json",
+                "--data",
+                &content.to_string(),
+                &matrix_url(homeserver, room, &txn_id),
+            ]);
+            run_with_input(command, &format!("Authorization: Bearer {token}\n"))
+        }
+    }
+}
+
+/// Delivers the notification about `entry` with every transport in
+/// `transports` that wants it, logging failures as warnings.  `sendmail` and
+/// `curl` run on the blocking thread pool, so they do not hold up a runtime
+/// worker.
+pub async fn send_all(transports: &[Transport], entry: &Entry) {
+    let transports: Vec<Transport> = transports
+        .iter()
+        .filter(|t| t.wants(entry))
+        .cloned()
+        .collect();
+    if transports.is_empty() {
+        return;
+    }
+    let entry = entry.clone();
+    let sent = tokio::task::spawn_blocking(move || {
+        for transport in &transports {
+            if let Err(e) = send(transport, &entry) {
+                tracing::warn!("Could not send notification: {e}");
+            }
+        }
+    })
+    .await;
+    if let Err(e) = sent {
+        tracing::warn!("Could not send notifications: {e}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use pretty_assertions::assert_eq;
+
+    use super::{Transport, When, email, matrix_url, message};
+    use crate::journal::Entry;
+
+    /// Builds a journal entry with `outcome`.
+    fn entry(outcome: &str) -> Entry {
+        Entry {
+            timestamp: "2026-01-01T00:00:00Z".to_owned(),
+            user: "alice".to_owned(),
+            host: "box".to_owned(),
+            task: Some("release".to_owned()),
+            arguments: vec!["cargo-for-each".to_owned(), "task".to_owned()],
+            outcome: outcome.to_owned(),
+            failed_targets: vec![PathBuf::from("/src/a")],
+        }
+    }
+
+    #[test]
+    fn transports_are_configured_in_toml() -> Result<(), Box<dyn std::error::Error>> {
+        #[derive(serde::Deserialize)]
+        struct Settings {
+            notifications: Vec<Transport>,
+        }
+        let settings: Settings = toml::from_str(
+            r#"
+            [[notifications]]
+            transport = "email"
+            to = ["ops@example.org"]
+
+            [[notifications]]
+            transport = "matrix"
+            homeserver = "https://matrix.example.org"
+            room = "!abc:example.org"
+            access_token_env = "MATRIX_TOKEN"
+            on = "always"
+            "#,
+        )?;
+        let [email, matrix] = settings.notifications.as_slice() else {
+            return Err("expected two transports".into());
+        };
+        assert_eq!(
+            email,
+            &Transport::Email {
+                to: vec!["ops@example.org".to_owned()],
+                from: None,
+                sendmail: None,
+                on: When::Failure,
+            }
+        );
+        assert!(
+            !email.wants(&entry("success")) && email.wants(&entry("failed: boom")),
+            "email is only sent for failures"
+        );
+        assert!(
+            matrix.wants(&entry("success")),
+            "matrix is sent for every run"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn messages_describe_the_run() {
+        let (subject, body) = message(&entry("failed: some steps failed"));
+        assert_eq!(subject, "cargo-for-each: task release failed");
+        assert_eq!(
+            body,
+            "cargo-for-each: task release failed on box (run by alice) at 2026-01-01T00:00:00Z.\n\n\
+             Command: cargo-for-each task\nOutcome: failed: some steps failed\n\n\
+             Failed targets:\n  /src/a\n"
+        );
+        assert_eq!(
+            email(
+                &["a@x".to_owned(), "b@x".to_owned()],
+                Some("cfe@x"),
+                "hi",
+                "body\n"
+            ),
+            "To: a@x, b@x\nFrom: cfe@x\nSubject: hi\nContent-Type: text/plain; charset=utf-8\n\nbody\n"
+        );
+        assert_eq!(
+            matrix_url("https://m.example.org/", "!abc:example.org", "t1"),
+            "https://m.example.org/_matrix/client/v3/rooms/%21abc%3Aexample.org/send/m.room.message/t1"
+        );
+    }
+}
@@ -0,0 +1,196 @@
+//! External subcommands.
+//!
+//! Like git and cargo, `cargo-for-each <name> [args...]` runs an executable
+//! called `cargo-for-each-<name>` from `PATH` for any `<name>` that is not a
+//! built-in command, so teams can add their own fleet operations without
+//! forking the tool.  The arguments after `<name>` are passed on unchanged,
+//! and the plugin inherits the terminal.  Where cargo-for-each keeps its data
+//! is passed in environment variables, so plugins do not have to duplicate
+//! the lookup of the XDG directories:
+//!
+//! * `CARGO_FOR_EACH_EXE`: the running `cargo-for-each` executable, to call
+//!   back into it (e.g. `task describe --format json`)
+//! * `CARGO_FOR_EACH_CONFIG_DIR` and `CARGO_FOR_EACH_CONFIG_FILE`: the
+//!   configuration directory and the file listing the registered targets
+//! * `CARGO_FOR_EACH_TASKS_DIR`: where tasks are stored
+//! * `CARGO_FOR_EACH_STATE_DIR`: where the execution state of tasks is kept
+//! * `CARGO_FOR_EACH_WORKSPACE_DIR` and `CARGO_FOR_EACH_CRATE_DIR`: the
+//!   registered workspace and crate the current directory is in, if any
+
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use tracing::instrument;
+
+use crate::error::Error;
+
+/// Prefix of the executable names of plugins.
+pub const PREFIX: &str = "cargo-for-each-";
+
+/// Returns the executable of the plugin `name` on the `PATH` of
+/// `environment`, if there is one.
+#[must_use]
+pub fn find(name: &str, environment: &crate::Environment) -> Option<PathBuf> {
+    let file_name = format!("{PREFIX}{name}");
+    environment
+        .paths
+        .iter()
+        .map(|dir| dir.join(&file_name))
+        .find(|path| crate::utils::is_executable(path))
+}
+
+/// Returns the innermost of `dirs` containing `cwd`.
+fn innermost(dirs: impl Iterator<Item = PathBuf>, cwd: &Path) -> Option<PathBuf> {
+    dirs.filter(|dir| cwd.starts_with(dir))
+        .max_by_key(|dir| dir.components().count())
+}
+
+/// Returns the environment variables passed to plugins started in `cwd`.
+///
+/// # Errors
+///
+/// Returns an error if the directories of cargo-for-each cannot be
+/// determined or the configuration cannot be loaded.
+pub fn context(
+    environment: &crate::Environment,
+    cwd: &Path,
+) -> Result<Vec<(&'static str, PathBuf)>, Error> {
+    let mut vars = vec![
+        (
+            "CARGO_FOR_EACH_CONFIG_DIR",
+            crate::config_dir_path(environment)?,
+        ),
+        (
+            "CARGO_FOR_EACH_CONFIG_FILE",
+            crate::config_file(environment)?,
+        ),
+        (
+            "CARGO_FOR_EACH_TASKS_DIR",
+            crate::tasks::dir_path(environment)?,
+        ),
+        (
+            "CARGO_FOR_EACH_STATE_DIR",
+            environment.state_dir.join("cargo-for-each"),
+        ),
+    ];
+    if let Ok(exe) = std::env::current_exe() {
+        vars.push(("CARGO_FOR_EACH_EXE", exe));
+    }
+    let cwd = fs_err::canonicalize(cwd).unwrap_or_else(|_| cwd.to_path_buf());
+    let config = crate::Config::load(environment)?;
+    if let Some(dir) = innermost(
+        config.workspaces.iter().map(|w| w.manifest_dir.clone()),
+        &cwd,
+    ) {
+        vars.push(("CARGO_FOR_EACH_WORKSPACE_DIR", dir));
+    }
+    if let Some(dir) = innermost(config.crates.iter().map(|c| c.manifest_dir.clone()), &cwd) {
+        vars.push(("CARGO_FOR_EACH_CRATE_DIR", dir));
+    }
+    Ok(vars)
+}
+
+/// Runs the plugin named by the first of `args` with the remaining ones.
+///
+/// # Errors
+///
+/// Returns [`Error::UnknownSubcommand`] if there is no such plugin,
+/// [`Error::PluginFailed`] if it exits unsuccessfully, or an error if it
+/// cannot be started.
+#[instrument]
+pub async fn command(args: Vec<OsString>, environment: crate::Environment) -> Result<(), Error> {
+    let mut args = args.into_iter();
+    let name = args
+        .next()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned();
+    let executable =
+        find(&name, &environment).ok_or_else(|| Error::UnknownSubcommand(name.clone()))?;
+    let cwd = std::env::current_dir().map_err(Error::IoError)?;
+    let status = std::process::Command::new(&executable)
+        .args(args)
+        .envs(context(&environment, &cwd)?)
+        .status()
+        .map_err(|e| Error::CouldNotRunPlugin(executable.clone(), e))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(Error::PluginFailed(executable, status.code().unwrap_or(-1)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use pretty_assertions::assert_eq;
+
+    use super::{context, find};
+
+    #[test]
+    fn plugins_are_found_on_the_path() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempfile::tempdir()?;
+        let mut environment = crate::Environment::mock(&temp_dir)?;
+        let bin_dir = temp_dir.path().join("bin");
+        environment.paths = vec![bin_dir.clone()];
+        assert_eq!(find("release", &environment), None);
+
+        let plugin = bin_dir.join("cargo-for-each-release");
+        fs_err::write(&plugin, "#!/bin/sh\n")?;
+        assert_eq!(find("release", &environment), None, "not executable");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt as _;
+            fs_err::set_permissions(&plugin, std::fs::Permissions::from_mode(0o755))?;
+        }
+        assert_eq!(find("release", &environment), Some(plugin));
+        Ok(())
+    }
+
+    #[test]
+    fn plugins_learn_the_selected_targets() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempfile::tempdir()?;
+        let environment = crate::Environment::mock(&temp_dir)?;
+        let root = fs_err::canonicalize(temp_dir.path())?;
+        let workspace = root.join("ws");
+        let member = workspace.join("crates").join("a");
+        fs_err::create_dir_all(member.join("src"))?;
+        let mut config = crate::Config::default();
+        config.add_workspace(crate::Workspace {
+            manifest_dir: workspace.clone(),
+            is_standalone: false,
+            repository: None,
+        });
+        config.crates.push(crate::Crate {
+            manifest_dir: member.clone(),
+            workspace_manifest_dir: workspace.clone(),
+            types: BTreeSet::new(),
+            metadata: crate::targets::PackageMetadata::default(),
+            name: None,
+            version: None,
+        });
+        config.save(&environment)?;
+
+        let vars = context(&environment, &member.join("src"))?;
+        let get = |name: &str| {
+            vars.iter()
+                .find(|(var, _)| *var == name)
+                .map(|(_, value)| value.clone())
+        };
+        assert_eq!(get("CARGO_FOR_EACH_WORKSPACE_DIR"), Some(workspace.clone()));
+        assert_eq!(get("CARGO_FOR_EACH_CRATE_DIR"), Some(member));
+        assert_eq!(
+            get("CARGO_FOR_EACH_TASKS_DIR"),
+            Some(crate::tasks::dir_path(&environment)?)
+        );
+
+        let vars = context(&environment, &workspace)?;
+        assert!(
+            vars.iter()
+                .all(|(var, _)| *var != "CARGO_FOR_EACH_CRATE_DIR"),
+            "the workspace root is in no member crate"
+        );
+        Ok(())
+    }
+}
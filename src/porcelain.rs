@@ -0,0 +1,215 @@
+//! Stable, machine-readable output for scripting.
+//!
+//! Commands that support `--porcelain` print one record per line with fields
+//! separated by a single tab and no header, colors or icons.  The format is
+//! versioned: `--porcelain` is the same as `--porcelain=v1`, and a format
+//! version, once released, never changes.  New fields or record kinds are only
+//! added in a new version.  Tabs and newlines inside a field are replaced by
+//! spaces.
+//!
+//! # Version 1
+//!
+//! The first field of every record names its kind.
+//!
+//! `target list workspaces`:
+//!
+//! ```text
+//! workspace <manifest-dir> <standalone: true|false>
+//! ```
+//!
+//! `target list crates`:
+//!
+//! ```text
+//! crate <manifest-dir> <workspace-manifest-dir> <types, comma-separated>
+//! ```
+//!
+//! `task list`:
+//!
+//! ```text
+//! task <name>
+//! ```
+//!
+//! `task describe`, in program order:
+//!
+//! ```text
+//! task <name>
+//! program <source-path> <unchanged|changed|missing>
+//! workspace <cursor> <state> <manifest-dir>
+//! crate <cursor> <state> <manifest-dir>
+//! step <cursor> <state> <label>
+//! ```
+//!
+//! where `<state>` is one of `pending`, `done`, `failed` or `waiting`.  The
+//! `program` record is omitted for tasks that do not record their source.
+//! `step` records belong to the closest preceding `workspace` or `crate`
+//! record.
+//!
+//! `task diff-targets`, sorted by manifest dir within each kind and change:
+//!
+//! ```text
+//! workspace <added|removed> <manifest-dir>
+//! crate <added|removed> <manifest-dir>
+//! dependency <added|removed> <dependent-manifest-dir> <dependency-manifest-dir>
+//! ```
+//!
+//! `outdated`, sorted by dependency name:
+//!
+//! ```text
+//! outdated <dependency> <latest> <version> <crate> <workspace-manifest-dir>
+//! ```
+//!
+//! # JSON
+//!
+//! The global `--format` option selects the output format of all listing and
+//! reporting commands, so it is the same for every subcommand.  With `--format
+//! json`, `target list`, `target set list`, `task list`, `task logs`,
+//! `templates list`, `history` and `outdated` print a single JSON array with
+//! one object per listed item, and `task status` and `task diff-targets` a
+//! single object.
+//! Each module defines the serializable view of its items, so the objects
+//! only contain the listed fields and not the internal representation.  New
+//! fields may be added to the objects, existing ones are not renamed or
+//! removed.
+//!
+//! `task describe --format json` prints a single object containing the
+//! program statements themselves.  Since those follow the language, the
+//! object carries a `schema_version` that is increased on incompatible
+//! changes, see `tasks::DESCRIPTION_SCHEMA_VERSION`.
+
+use serde::Serialize;
+
+use crate::error::Error;
+
+/// Format of the output of listing and reporting commands, chosen with the
+/// global `--format` option.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Human-readable text (Graphviz `dot` for graphs).
+    #[default]
+    Text,
+    /// JSON, e.g. an array with one object per listed item.
+    Json,
+    /// A Markdown table, for reports.
+    Markdown,
+    /// A standalone HTML document, for reports.
+    Html,
+    /// Graphviz `dot`, for graphs.
+    Dot,
+}
+
+impl std::fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Text => write!(f, "text"),
+            Self::Json => write!(f, "json"),
+            Self::Markdown => write!(f, "markdown"),
+            Self::Html => write!(f, "html"),
+            Self::Dot => write!(f, "dot"),
+        }
+    }
+}
+
+/// Returns whether the listing command `command`, which prints text (or
+/// porcelain records if `porcelain` is set) or JSON, prints JSON in `format`.
+///
+/// # Errors
+///
+/// Returns [`Error::PorcelainWithFormat`] if both `--porcelain` and `--format
+/// json` are given, or [`Error::UnsupportedOutputFormat`] for the report and
+/// graph formats.
+pub fn is_json(
+    format: OutputFormat,
+    porcelain: Option<Version>,
+    command: &str,
+) -> Result<bool, Error> {
+    match (format, porcelain) {
+        (OutputFormat::Text, _) => Ok(false),
+        (OutputFormat::Json, None) => Ok(true),
+        (OutputFormat::Json, Some(_)) => Err(Error::PorcelainWithFormat(format)),
+        (OutputFormat::Markdown | OutputFormat::Html | OutputFormat::Dot, _) => {
+            Err(Error::UnsupportedOutputFormat(format, command.to_owned()))
+        }
+    }
+}
+
+/// Returns `items` as a pretty-printed JSON array.
+///
+/// # Errors
+///
+/// Returns an error if an item cannot be serialized.
+pub fn json<T: Serialize>(items: &[T]) -> Result<String, Error> {
+    serde_json::to_string_pretty(items).map_err(Error::CouldNotSerializeListOutput)
+}
+
+/// A version of the porcelain output format.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Version {
+    /// The first version of the format.
+    V1,
+}
+
+/// Formats `fields` as a single tab-separated record (without a newline).
+#[must_use]
+pub fn record(fields: &[&str]) -> String {
+    fields
+        .iter()
+        .map(|field| field.replace(['\t', '\n', '\r'], " "))
+        .collect::<Vec<_>>()
+        .join("\t")
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::{OutputFormat, Version, is_json, json, record};
+    use crate::error::Error;
+
+    #[test]
+    fn record_separates_fields_with_tabs() {
+        assert_eq!(
+            record(&["step", "w0/s1/", "done", "run \"a\tb\"\nc"]),
+            "step\tw0/s1/\tdone\trun \"a b\" c"
+        );
+    }
+
+    #[test]
+    fn json_lists_are_arrays() -> Result<(), Box<dyn std::error::Error>> {
+        #[derive(serde::Serialize)]
+        struct Item {
+            name: &'static str,
+        }
+        assert_eq!(json::<Item>(&[])?, "[]");
+        assert_eq!(
+            json(&[Item { name: "a" }])?,
+            "[\n  {\n    \"name\": \"a\"\n  }\n]"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn listings_print_text_or_json() -> Result<(), Box<dyn std::error::Error>> {
+        assert!(!is_json(
+            OutputFormat::Text,
+            Some(Version::V1),
+            "task list"
+        )?);
+        assert!(is_json(OutputFormat::Json, None, "task list")?);
+        assert!(
+            matches!(
+                is_json(OutputFormat::Json, Some(Version::V1), "task list"),
+                Err(Error::PorcelainWithFormat(OutputFormat::Json))
+            ),
+            "--porcelain and --format json conflict"
+        );
+        assert!(
+            matches!(
+                is_json(OutputFormat::Html, None, "task list"),
+                Err(Error::UnsupportedOutputFormat(OutputFormat::Html, ref command))
+                    if command == "task list"
+            ),
+            "listings are not rendered as reports"
+        );
+        Ok(())
+    }
+}
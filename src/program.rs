@@ -18,14 +18,15 @@ pub use ast::workspace_ctx::{ForWorkspaceBlock, WorkspaceFilter};
 ///
 /// A program is a sequence of top-level statements that together describe which
 /// workspaces and crates to operate on and what to do with each.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
 pub struct Program {
     /// The top-level statements of the program, in the order they appear in the source.
     pub statements: Vec<GlobalStatement>,
 }
 
 /// A single top-level statement in a `.cfe` program.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum GlobalStatement {
     /// Selects workspaces to operate on (`select workspaces [where <condition>];`).
     ///
@@ -38,8 +39,20 @@ pub enum GlobalStatement {
     /// multi-crate workspaces are accessed through
     /// `for workspace { for crate in workspace { ... } }`.
     SelectCrates(CrateFilter),
+    /// Restricts which member crates of the selected workspaces are iterated
+    /// over by `for crate in workspace` (`select members [where <condition>];`).
+    ///
+    /// Multiple `SelectMembers` statements accumulate: a member is kept if it
+    /// matches at least one of them.  Without any `SelectMembers` statement all
+    /// members are kept.
+    SelectMembers(CrateFilter),
     /// Iterates over all selected workspaces in inter-workspace dependency order.
     ForWorkspace(ForWorkspaceBlock),
     /// Iterates over all selected standalone crates in dependency order.
     ForCrate(ForCrateBlock),
+    /// Requires the git working tree of each target to be clean before its
+    /// first statement runs (`require_clean_git;`).
+    RequireCleanGit,
+    /// Chooses how the output of steps is recorded (`record <recorder>;`).
+    Record(crate::tasks::recorder::Recorder),
 }
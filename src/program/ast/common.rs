@@ -1,28 +1,159 @@
 //! AST node types shared across all execution contexts.
 
+use std::collections::BTreeMap;
+
 /// A step that executes an external command in the target's directory.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
 pub struct RunStep {
     /// The command to execute.
     pub command: String,
     /// The arguments to pass to the command.
     pub args: Vec<String>,
+    /// What to feed to the command's standard input, if anything.
+    ///
+    /// Without it the command reads from the terminal of the runner.
+    pub stdin: Option<StdinSource>,
+    /// How often this statement may start across all targets, if limited.
+    pub rate_limit: Option<RateLimit>,
+    /// Names of resources this statement needs exclusively; statements of
+    /// parallel targets sharing one of them do not run at the same time.
+    pub exclusive: Vec<String>,
+    /// The user to run the command as via `sudo`, if not the current user.
+    pub elevation: Option<Elevation>,
+    /// Environment variables set for this command only, e.g. `RUSTFLAGS`;
+    /// they take precedence over those of `with_env_file`.
+    pub env: BTreeMap<String, String>,
+    /// The directory to run the command in, relative to the target's
+    /// directory, if not the target's directory itself.  Interpolations like
+    /// `${workspace_dir}` may make it absolute.
+    pub cwd_relative: Option<String>,
+    /// How long the command may run before it is killed, if limited.
+    pub timeout_seconds: Option<u64>,
+    /// How often the command is run again after failing or timing out, if
+    /// at all.
+    pub retry: Option<Retry>,
+}
+
+/// A step that runs a cargo subcommand described by its options instead of
+/// a raw command line.
+///
+/// It runs exactly like the [`RunStep`] returned by [`CargoStep::to_run_step`];
+/// describing the options separately keeps them free of quoting mistakes and
+/// lets later features inspect them.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct CargoStep {
+    /// The cargo subcommand, e.g. `build` or `test`.
+    pub subcommand: String,
+    /// Features to enable (`--features`).
+    pub features: Vec<String>,
+    /// The profile to build with (`--profile`), if not the subcommand's default.
+    pub profile: Option<String>,
+    /// Whether to require an up-to-date lock file (`--locked`).
+    pub locked: bool,
+    /// Whether to run without network access (`--offline`).
+    pub offline: bool,
+    /// Further arguments passed after the options, e.g. `--` and test arguments.
+    pub extra_args: Vec<String>,
+}
+
+impl CargoStep {
+    /// Returns the `run` statement executing this step.
+    #[must_use]
+    pub fn to_run_step(&self) -> RunStep {
+        let mut args = vec![self.subcommand.clone()];
+        if !self.features.is_empty() {
+            args.push("--features".to_owned());
+            args.push(self.features.join(","));
+        }
+        if let Some(profile) = &self.profile {
+            args.push("--profile".to_owned());
+            args.push(profile.clone());
+        }
+        if self.locked {
+            args.push("--locked".to_owned());
+        }
+        if self.offline {
+            args.push("--offline".to_owned());
+        }
+        args.extend(self.extra_args.iter().cloned());
+        RunStep {
+            command: "cargo".to_owned(),
+            args,
+            stdin: None,
+            rate_limit: None,
+            exclusive: vec![],
+            elevation: None,
+            env: BTreeMap::new(),
+            cwd_relative: None,
+            timeout_seconds: None,
+            retry: None,
+        }
+    }
+}
+
+/// The user a [`RunStep`] is run as via `sudo`, e.g. to install built
+/// binaries into system paths.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Elevation {
+    /// `elevate`: run as root.
+    Root,
+    /// `run_as "name"`: run as the named user.
+    User(String),
+}
+
+/// A limit on how many targets may start a statement within a period, e.g. to
+/// stay below the rate limits of crates.io when publishing a whole fleet.
+///
+/// The limit applies to the statement as a whole, independently of the
+/// number of parallel jobs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub struct RateLimit {
+    /// The maximum number of starts within one period.
+    pub max: u64,
+    /// The length of the period in seconds.
+    pub period_seconds: u64,
+}
+
+/// How a [`RunStep`] is retried after its command failed or timed out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub struct Retry {
+    /// The number of additional attempts after the first one.
+    pub retries: u64,
+    /// The number of seconds to wait before each additional attempt.
+    pub delay_seconds: u64,
+}
+
+/// The standard input of a [`RunStep`].
+///
+/// Both variants support `${name.field}` interpolation.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StdinSource {
+    /// Literal content, given as a string literal or a heredoc.
+    Content(String),
+    /// A file whose content is used, relative to the target's directory.
+    File(String),
 }
 
 /// A step that pauses for manual user intervention.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
 pub struct ManualStepNode {
     /// A short title displayed to the user.
     pub title: String,
     /// Detailed instructions for the manual step.
     pub instructions: String,
+    /// The person responsible for carrying out the step, if any.
+    ///
+    /// This can be overridden per target with `task handoff`.
+    pub assignee: Option<String>,
 }
 
 /// A conditional if/else-if/else block parameterized over condition and statement types.
 ///
 /// The type parameter `C` is the condition type for the context (e.g. `WorkspaceCondition`
 /// or `CrateCondition`), and `S` is the statement type for the body of each branch.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
 pub struct IfBlock<C, S> {
     /// The ordered list of if/else-if branches. At least one is always present.
     pub branches: Vec<Branch<C, S>>,
@@ -31,7 +162,7 @@ pub struct IfBlock<C, S> {
 }
 
 /// A single conditional branch (if or else-if arm) in an [`IfBlock`].
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
 pub struct Branch<C, S> {
     /// The condition that must be true for this branch to execute.
     pub condition: C,
@@ -43,7 +174,7 @@ pub struct Branch<C, S> {
 ///
 /// The captured metadata can be referenced in later steps using `${name.field}` syntax
 /// in command arguments and manual step text.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
 pub struct SnapshotMetadataNode {
     /// The name under which the captured metadata is stored.
     ///
@@ -52,18 +183,44 @@ pub struct SnapshotMetadataNode {
 }
 
 /// A barrier that pauses execution of this target until the user releases it.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
 pub struct WaitForContinueNode {
     /// Human-readable description shown when the barrier is reached.
     pub description: String,
 }
 
+/// A marker that starts a named phase of the surrounding block, e.g.
+/// `prepare`, `verify` or `release`.
+///
+/// The phase lasts until the next marker in the same block or the end of the
+/// block; nested blocks inherit it.  Markers have no execution state.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct PhaseNode {
+    /// The name of the phase.
+    pub name: String,
+}
+
+/// A step that polls a command until it succeeds, e.g. until a published
+/// crate version is visible on crates.io or a CI pipeline is green.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct WaitUntilNode {
+    /// The command to poll, executed in the target's directory.
+    pub command: String,
+    /// The arguments to pass to the command.
+    pub args: Vec<String>,
+    /// Seconds to sleep between two attempts.
+    pub interval_seconds: u64,
+    /// Seconds after which the step fails if the command has not succeeded;
+    /// `None` waits indefinitely.
+    pub timeout_seconds: Option<u64>,
+}
+
 /// A block that applies environment variables from a file to all nested statements.
 ///
 /// The env file is read at execution time, relative to the target's manifest directory.
 /// Variables from nested `with_env_file` blocks extend (and override, for duplicate keys)
 /// variables from outer blocks.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
 pub struct WithEnvFileBlock<S> {
     /// Path to the env file, relative to the target's manifest directory.
     pub env_file: String,
@@ -120,7 +277,8 @@ impl std::fmt::Display for CommonCondition {
     clippy::module_name_repetitions,
     reason = "The 'Common' prefix is semantically meaningful as it distinguishes this from WorkspaceCondition and CrateCondition; renaming would lose that clarity"
 )]
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum CommonCondition {
     /// Ask the user a yes/no question. Evaluates to true if the user answers yes/y.
     AskUser(String),
@@ -1,12 +1,13 @@
 //! AST node types for the crate execution context.
 
 use super::common::{
-    Branch, CommonCondition, IfBlock, ManualStepNode, RunStep, SnapshotMetadataNode,
-    WaitForContinueNode, WithEnvFileBlock,
+    Branch, CargoStep, CommonCondition, IfBlock, ManualStepNode, PhaseNode, RunStep,
+    SnapshotMetadataNode, WaitForContinueNode, WaitUntilNode, WithEnvFileBlock,
 };
 
 /// The type of a Rust crate, used as a filter in crate-context conditions.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum CrateTypeFilter {
     /// A binary crate (produces an executable).
     Bin,
@@ -52,17 +53,20 @@ impl std::fmt::Display for CrateTypeFilter {
 
 /// A block that iterates over all selected crates (standalone or within a workspace)
 /// in dependency order.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
 pub struct ForCrateBlock {
     /// The statements to execute for each crate.
     pub statements: Vec<CrateStatement>,
 }
 
 /// A single statement in the crate execution context.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum CrateStatement {
     /// Execute a command in the crate's manifest directory.
     Run(RunStep),
+    /// Run a cargo subcommand in the crate's manifest directory.
+    Cargo(CargoStep),
     /// Pause for a manual step with instructions for the user.
     ManualStep(ManualStepNode),
     /// Conditional branching using crate-level conditions.
@@ -73,6 +77,23 @@ pub enum CrateStatement {
     WithEnvFile(WithEnvFileBlock<Self>),
     /// Pause execution until the user releases this barrier.
     WaitForContinue(WaitForContinueNode),
+    /// Poll a command until it succeeds or times out.
+    WaitUntil(WaitUntilNode),
+    /// Start a named phase.
+    Phase(PhaseNode),
+    /// Check every combination of the crate's features with `cargo hack`.
+    FeaturePowerset(FeaturePowersetNode),
+}
+
+/// A step that runs `cargo hack check --feature-powerset` in the crate's
+/// directory and records the result of every feature combination.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct FeaturePowersetNode {
+    /// The maximum number of features combined in one check (`--depth`);
+    /// `None` checks all combinations.
+    pub depth: Option<u64>,
+    /// Features that are left out of all combinations (`--exclude-features`).
+    pub exclude_features: Vec<String>,
 }
 
 /// A boolean condition available in the crate execution context.
@@ -80,7 +101,8 @@ pub enum CrateStatement {
 /// Extends [`CommonCondition`] with conditions that inspect crate-specific properties
 /// such as crate type. These conditions are only meaningful when operating on an
 /// individual crate and are therefore unavailable at the workspace level.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum CrateCondition {
     /// A condition from the common set available in all contexts.
     Common(CommonCondition),
@@ -88,6 +110,16 @@ pub enum CrateCondition {
     CrateType(CrateTypeFilter),
     /// True if this crate lives in a standalone (single-crate) workspace.
     Standalone,
+    /// True if this crate is one of its workspace's `default-members`, as
+    /// reported by `cargo metadata`.
+    DefaultMember,
+    /// True if the crate may be published to the named registry according to
+    /// the `publish` field of its manifest (`crates-io` for crates.io).
+    PublishesTo(String),
+    /// True if the crate lists the tag in `[package.metadata.cargo-for-each]`.
+    Tagged(String),
+    /// True if the crate is a member of the named target set.
+    InSet(String),
     /// True if the inner condition evaluates to false.
     Not(Box<Self>),
     /// True if all inner conditions evaluate to true (short-circuits on first false).
@@ -96,17 +128,26 @@ pub enum CrateCondition {
     Or(Vec<Self>),
 }
 
-/// A condition allowed inside `select crates where ...` filters.
+/// A condition allowed inside `select crates where ...` and `select members where ...` filters.
 ///
 /// This is a restricted subset of [`CrateCondition`] that can be evaluated
 /// statically against the registered configuration at task-creation time.
 /// The `ask_user` and `run` variants are excluded.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum CrateSelectCondition {
     /// True if the crate lives in a standalone (single-crate) workspace.
     Standalone,
     /// True if the crate matches the given type filter.
     CrateType(CrateTypeFilter),
+    /// True if the crate is one of its workspace's `default-members`.
+    ///
+    /// Standalone crates are always their workspace's default member.
+    DefaultMember,
+    /// True if the crate lists the tag in `[package.metadata.cargo-for-each]`.
+    Tagged(String),
+    /// True if the crate is a member of the named target set.
+    InSet(String),
     /// True if the inner condition evaluates to false.
     Not(Box<Self>),
     /// True if all inner conditions evaluate to true (short-circuits on first false).
@@ -121,6 +162,9 @@ impl From<CrateSelectCondition> for CrateCondition {
         match cond {
             CrateSelectCondition::Standalone => Self::Standalone,
             CrateSelectCondition::CrateType(t) => Self::CrateType(t),
+            CrateSelectCondition::DefaultMember => Self::DefaultMember,
+            CrateSelectCondition::Tagged(tag) => Self::Tagged(tag),
+            CrateSelectCondition::InSet(name) => Self::InSet(name),
             CrateSelectCondition::Not(inner) => Self::Not(Box::new(Self::from(*inner))),
             CrateSelectCondition::And(conditions) => {
                 Self::And(conditions.into_iter().map(Self::from).collect())
@@ -138,6 +182,10 @@ impl std::fmt::Display for CrateCondition {
             Self::Common(inner) => write!(f, "{inner}"),
             Self::CrateType(filter) => write!(f, "type == {filter}"),
             Self::Standalone => write!(f, "standalone"),
+            Self::DefaultMember => write!(f, "default_member"),
+            Self::PublishesTo(registry) => write!(f, "publishes_to \"{registry}\""),
+            Self::Tagged(tag) => write!(f, "tagged \"{tag}\""),
+            Self::InSet(name) => write!(f, "in_set \"{name}\""),
             Self::Not(inner) => write!(f, "!{inner}"),
             Self::And(conditions) => {
                 write!(f, "(")?;
@@ -163,8 +211,9 @@ impl std::fmt::Display for CrateCondition {
     }
 }
 
-/// A filter applied to the set of crates selected by a `select crates` statement.
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// A filter applied to the set of crates selected by a `select crates` or
+/// `select members` statement.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
 pub struct CrateFilter {
     /// Optional condition; if `None`, all registered standalone crates are selected.
     pub condition: Option<CrateSelectCondition>,
@@ -1,14 +1,14 @@
 //! AST node types for the workspace execution context.
 
 use super::common::{
-    Branch, CommonCondition, IfBlock, ManualStepNode, RunStep, SnapshotMetadataNode,
-    WaitForContinueNode, WithEnvFileBlock,
+    Branch, CargoStep, CommonCondition, IfBlock, ManualStepNode, PhaseNode, RunStep,
+    SnapshotMetadataNode, WaitForContinueNode, WaitUntilNode, WithEnvFileBlock,
 };
 use super::crate_ctx::CrateStatement;
 
 /// A block that iterates over all member crates of the current workspace in
 /// intra-workspace dependency order.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
 pub struct ForCrateInWorkspaceBlock {
     /// The statements to execute for each member crate.
     pub statements: Vec<CrateStatement>,
@@ -16,17 +16,20 @@ pub struct ForCrateInWorkspaceBlock {
 
 /// A block that runs its body once for each selected workspace in inter-workspace
 /// dependency order.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
 pub struct ForWorkspaceBlock {
     /// The statements to execute for each workspace.
     pub statements: Vec<WorkspaceStatement>,
 }
 
 /// A single statement in the workspace execution context.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum WorkspaceStatement {
     /// Execute a command in the workspace root directory.
     Run(RunStep),
+    /// Run a cargo subcommand in the workspace root directory.
+    Cargo(CargoStep),
     /// Pause for a manual step with instructions for the user.
     ManualStep(ManualStepNode),
     /// Conditional branching using workspace-level conditions.
@@ -39,12 +42,17 @@ pub enum WorkspaceStatement {
     WithEnvFile(WithEnvFileBlock<Self>),
     /// Pause execution until the user releases this barrier.
     WaitForContinue(WaitForContinueNode),
+    /// Poll a command until it succeeds or times out.
+    WaitUntil(WaitUntilNode),
+    /// Start a named phase.
+    Phase(PhaseNode),
 }
 
 /// A boolean condition available in the workspace execution context.
 ///
 /// Extends [`CommonCondition`] with conditions that inspect workspace-level properties.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum WorkspaceCondition {
     /// A condition from the common set available in all contexts.
     Common(CommonCondition),
@@ -52,6 +60,12 @@ pub enum WorkspaceCondition {
     Standalone,
     /// True if this workspace has multiple member crates.
     HasMembers,
+    /// True if the workspace root has a virtual manifest (no `[package]`).
+    Virtual,
+    /// True if the workspace is a member of the named target set.
+    InSet(String),
+    /// True if the workspace is tagged with the given tag.
+    Tagged(String),
     /// True if the inner condition evaluates to false.
     Not(Box<Self>),
     /// True if all inner conditions evaluate to true (short-circuits on first false).
@@ -66,6 +80,9 @@ impl std::fmt::Display for WorkspaceCondition {
             Self::Common(inner) => write!(f, "{inner}"),
             Self::Standalone => write!(f, "standalone"),
             Self::HasMembers => write!(f, "has_members"),
+            Self::Virtual => write!(f, "virtual"),
+            Self::InSet(name) => write!(f, "in_set \"{name}\""),
+            Self::Tagged(tag) => write!(f, "tagged \"{tag}\""),
             Self::Not(inner) => write!(f, "!{inner}"),
             Self::And(conditions) => {
                 write!(f, "(")?;
@@ -97,12 +114,19 @@ impl std::fmt::Display for WorkspaceCondition {
 /// statically against the registered configuration at task-creation time. The
 /// `ask_user` and `run` variants are excluded because they require interactive
 /// evaluation which is not appropriate during target resolution.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum WorkspaceSelectCondition {
     /// True if the workspace is a standalone (single-crate) workspace.
     Standalone,
     /// True if the workspace has multiple member crates.
     HasMembers,
+    /// True if the workspace root has a virtual manifest (no `[package]`).
+    Virtual,
+    /// True if the workspace is a member of the named target set.
+    InSet(String),
+    /// True if the workspace is tagged with the given tag.
+    Tagged(String),
     /// True if the inner condition evaluates to false.
     Not(Box<Self>),
     /// True if all inner conditions evaluate to true (short-circuits on first false).
@@ -117,6 +141,9 @@ impl From<WorkspaceSelectCondition> for WorkspaceCondition {
         match cond {
             WorkspaceSelectCondition::Standalone => Self::Standalone,
             WorkspaceSelectCondition::HasMembers => Self::HasMembers,
+            WorkspaceSelectCondition::Virtual => Self::Virtual,
+            WorkspaceSelectCondition::InSet(name) => Self::InSet(name),
+            WorkspaceSelectCondition::Tagged(tag) => Self::Tagged(tag),
             WorkspaceSelectCondition::Not(inner) => Self::Not(Box::new(Self::from(*inner))),
             WorkspaceSelectCondition::And(conditions) => {
                 Self::And(conditions.into_iter().map(Self::from).collect())
@@ -129,7 +156,7 @@ impl From<WorkspaceSelectCondition> for WorkspaceCondition {
 }
 
 /// A filter applied to the set of workspaces selected by a `select workspaces` statement.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
 pub struct WorkspaceFilter {
     /// Optional condition; if `None`, all registered workspaces are selected.
     pub condition: Option<WorkspaceSelectCondition>,
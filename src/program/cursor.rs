@@ -224,6 +224,12 @@ impl ProgramCursor {
     pub const fn len(&self) -> usize {
         self.segments.len()
     }
+
+    /// Returns `true` if `prefix` is this cursor or one of its ancestors.
+    #[must_use]
+    pub fn starts_with(&self, prefix: &Self) -> bool {
+        self.segments.starts_with(&prefix.segments)
+    }
 }
 
 impl fmt::Display for ProgramCursor {
@@ -407,4 +413,14 @@ mod tests {
         ]);
         assert_eq!(cursor.len(), 2);
     }
+
+    #[test]
+    fn cursor_starts_with() {
+        let target = ProgramCursor::new().with(CursorSegment::WorkspaceIteration(0));
+        let step = target.clone().with(CursorSegment::Statement(1));
+        assert!(step.starts_with(&target));
+        assert!(step.starts_with(&step));
+        assert!(!target.starts_with(&step));
+        assert!(!step.starts_with(&ProgramCursor::new().with(CursorSegment::CrateIteration(0))));
+    }
 }
@@ -242,6 +242,13 @@ pub fn evaluate_workspace_condition(
             .workspaces
             .iter()
             .any(|w| w.manifest_dir == manifest_dir && !w.is_standalone)),
+        WorkspaceCondition::Virtual => Ok(crate::targets::is_virtual_manifest(manifest_dir)),
+        WorkspaceCondition::InSet(name) => {
+            Ok(crate::targets::sets::members(config, name)?.contains(manifest_dir))
+        }
+        WorkspaceCondition::Tagged(tag) => {
+            Ok(crate::targets::tags::has_tag(config, manifest_dir, tag))
+        }
         WorkspaceCondition::Not(inner) => Ok(!evaluate_workspace_condition(
             inner,
             manifest_dir,
@@ -268,6 +275,46 @@ pub fn evaluate_workspace_condition(
     }
 }
 
+/// Returns `true` if the crate at `manifest_dir` may be published to
+/// `registry`, as reported by `cargo metadata`.
+///
+/// # Errors
+///
+/// Returns an error if `cargo metadata` fails.
+fn crate_publishes_to(manifest_dir: &Path, registry: &str) -> Result<bool, Error> {
+    let manifest_path = manifest_dir.join("Cargo.toml");
+    let metadata = cargo_metadata::MetadataCommand::new()
+        .manifest_path(&manifest_path)
+        .no_deps()
+        .exec()
+        .map_err(|e| Error::CargoMetadataError(manifest_dir.to_path_buf(), e))?;
+    Ok(metadata
+        .packages
+        .iter()
+        .find(|p| p.manifest_path.as_std_path() == manifest_path)
+        .is_some_and(|p| crate::program::resolve::publishes_to(p, registry)))
+}
+
+/// Returns `true` if the crate at `manifest_dir` is one of its workspace's
+/// `default-members`, as reported by `cargo metadata`.
+///
+/// # Errors
+///
+/// Returns an error if `cargo metadata` fails.
+fn is_default_workspace_member(manifest_dir: &Path) -> Result<bool, Error> {
+    let manifest_path = manifest_dir.join("Cargo.toml");
+    let metadata = cargo_metadata::MetadataCommand::new()
+        .manifest_path(&manifest_path)
+        .no_deps()
+        .exec()
+        .map_err(|e| Error::CargoMetadataError(manifest_dir.to_path_buf(), e))?;
+    Ok(metadata
+        .packages
+        .iter()
+        .find(|p| p.manifest_path.as_std_path() == manifest_path)
+        .is_some_and(|p| crate::program::resolve::is_default_member(&metadata, &p.id)))
+}
+
 /// Evaluates a [`CrateCondition`] for the given crate target.
 ///
 /// # Errors
@@ -322,6 +369,12 @@ pub fn evaluate_crate_condition(
                     .any(|w| w.manifest_dir == ws && w.is_standalone)),
             }
         }
+        CrateCondition::DefaultMember => is_default_workspace_member(manifest_dir),
+        CrateCondition::PublishesTo(registry) => crate_publishes_to(manifest_dir, registry),
+        CrateCondition::Tagged(tag) => Ok(crate::targets::tags::has_tag(config, manifest_dir, tag)),
+        CrateCondition::InSet(name) => {
+            Ok(crate::targets::sets::members(config, name)?.contains(manifest_dir))
+        }
         CrateCondition::Not(inner) => Ok(!evaluate_crate_condition(
             inner,
             manifest_dir,
@@ -361,6 +414,7 @@ mod tests {
     use tempfile::tempdir;
 
     use super::*;
+    use crate::targets::PackageMetadata;
     use crate::{Crate, Workspace};
 
     fn mock_env(temp: &tempfile::TempDir) -> crate::Environment {
@@ -369,20 +423,37 @@ mod tests {
 
     fn config_with_bin_crate(dir: &Path) -> crate::Config {
         crate::Config {
+            sort_on_save: false,
+            shared_repository: None,
+            notifications: vec![],
+            target_sets: std::collections::BTreeMap::new(),
+            tags: std::collections::BTreeMap::new(),
             workspaces: vec![Workspace {
                 manifest_dir: dir.to_path_buf(),
                 is_standalone: true,
+                repository: None,
             }],
             crates: vec![Crate {
                 manifest_dir: dir.to_path_buf(),
                 workspace_manifest_dir: dir.to_path_buf(),
                 types: BTreeSet::from([CrateType::Bin]),
+                metadata: PackageMetadata {
+                    tags: BTreeSet::from(["cli".to_owned()]),
+                    ..PackageMetadata::default()
+                },
+                name: None,
+                version: None,
             }],
         }
     }
 
     fn empty_config() -> crate::Config {
         crate::Config {
+            sort_on_save: false,
+            shared_repository: None,
+            notifications: vec![],
+            target_sets: std::collections::BTreeMap::new(),
+            tags: std::collections::BTreeMap::new(),
             workspaces: vec![],
             crates: vec![],
         }
@@ -577,9 +648,15 @@ mod tests {
         let dir = temp.path();
         let env = mock_env(&temp);
         let config = crate::Config {
+            sort_on_save: false,
+            shared_repository: None,
+            notifications: vec![],
+            target_sets: std::collections::BTreeMap::new(),
+            tags: std::collections::BTreeMap::new(),
             workspaces: vec![Workspace {
                 manifest_dir: dir.to_path_buf(),
                 is_standalone: true,
+                repository: None,
             }],
             crates: vec![],
         };
@@ -594,9 +671,15 @@ mod tests {
         let dir = temp.path();
         let env = mock_env(&temp);
         let config = crate::Config {
+            sort_on_save: false,
+            shared_repository: None,
+            notifications: vec![],
+            target_sets: std::collections::BTreeMap::new(),
+            tags: std::collections::BTreeMap::new(),
             workspaces: vec![Workspace {
                 manifest_dir: dir.to_path_buf(),
                 is_standalone: false,
+                repository: None,
             }],
             crates: vec![],
         };
@@ -639,6 +722,53 @@ mod tests {
         assert_eq!(result.unwrap_or_else(|e| panic!("{e}")), false);
     }
 
+    #[test]
+    fn tagged_checks_package_metadata_tags() {
+        let temp = tempdir().unwrap_or_else(|e| panic!("{e}"));
+        let dir = temp.path();
+        let env = mock_env(&temp);
+        let config = config_with_bin_crate(dir);
+        let tagged = |tag: &str| {
+            evaluate_crate_condition(
+                &CrateCondition::Tagged(tag.to_owned()),
+                dir,
+                &env,
+                &config,
+                &[],
+            )
+            .unwrap_or_else(|e| panic!("{e}"))
+        };
+        assert_eq!(tagged("cli"), true);
+        assert_eq!(tagged("server"), false);
+    }
+
+    #[test]
+    fn crate_publishes_to_registry_from_manifest() {
+        let temp = tempdir().unwrap_or_else(|e| panic!("{e}"));
+        let dir = fs_err::canonicalize(temp.path()).unwrap_or_else(|e| panic!("{e}"));
+        fs_err::create_dir_all(dir.join("src")).unwrap_or_else(|e| panic!("{e}"));
+        fs_err::write(dir.join("src").join("lib.rs"), "").unwrap_or_else(|e| panic!("{e}"));
+        fs_err::write(
+            dir.join("Cargo.toml"),
+            "[package]\nname = \"private\"\nversion = \"0.1.0\"\nedition = \"2021\"\npublish = [\"kellnr\"]\n",
+        )
+        .unwrap_or_else(|e| panic!("{e}"));
+        let env = mock_env(&temp);
+        let config = empty_config();
+        let publishes_to = |registry: &str| {
+            evaluate_crate_condition(
+                &CrateCondition::PublishesTo(registry.to_owned()),
+                &dir,
+                &env,
+                &config,
+                &[],
+            )
+            .unwrap_or_else(|e| panic!("{e}"))
+        };
+        assert_eq!(publishes_to("kellnr"), true);
+        assert_eq!(publishes_to("crates-io"), false);
+    }
+
     #[test]
     fn crate_standalone_true() {
         let temp = tempdir().unwrap_or_else(|e| panic!("{e}"));
@@ -3,21 +3,25 @@
 //! The entry point is [`parse`], which takes source text and a filename and returns
 //! either a [`Program`] or a human-readable error string produced by ariadne.
 
+use std::collections::BTreeMap;
+
 use chumsky::prelude::*;
 
 use super::ast::common::{
-    Branch, CommonCondition, IfBlock, ManualStepNode, RunStep, SnapshotMetadataNode,
-    WaitForContinueNode, WithEnvFileBlock,
+    Branch, CargoStep, CommonCondition, Elevation, IfBlock, ManualStepNode, PhaseNode, RateLimit,
+    Retry, RunStep, SnapshotMetadataNode, StdinSource, WaitForContinueNode, WaitUntilNode,
+    WithEnvFileBlock,
 };
 use super::ast::crate_ctx::{
     CrateCondition, CrateFilter, CrateSelectCondition, CrateStatement, CrateTypeFilter,
-    ForCrateBlock,
+    FeaturePowersetNode, ForCrateBlock,
 };
 use super::ast::workspace_ctx::{
     ForCrateInWorkspaceBlock, ForWorkspaceBlock, WorkspaceCondition, WorkspaceFilter,
     WorkspaceSelectCondition, WorkspaceStatement,
 };
 use super::{GlobalStatement, Program};
+use crate::tasks::recorder::Recorder;
 
 /// Errors returned by the parser, rendered via ariadne.
 ///
@@ -188,10 +192,25 @@ fn workspace_condition_parser<'src>()
         // Workspace-specific leaf conditions ──────────────────────────────────
         let standalone = kw("standalone").to(WorkspaceCondition::Standalone);
         let has_members = kw("has_members").to(WorkspaceCondition::HasMembers);
+        let virtual_manifest = kw("virtual").to(WorkspaceCondition::Virtual);
+        let in_set = kw("in_set")
+            .ignore_then(string_literal())
+            .map(WorkspaceCondition::InSet);
+        let tagged = kw("tagged")
+            .ignore_then(string_literal())
+            .map(WorkspaceCondition::Tagged);
 
         let paren = cond.clone().delimited_by(sym("("), sym(")"));
 
-        let atom = choice((common_leaf, standalone, has_members, paren));
+        let atom = choice((
+            common_leaf,
+            standalone,
+            has_members,
+            virtual_manifest,
+            in_set,
+            tagged,
+            paren,
+        ));
 
         // `!` prefix ──────────────────────────────────────────────────────────
         let not_expr = sym("!")
@@ -228,8 +247,8 @@ fn workspace_condition_parser<'src>()
 
 /// Parses a [`CrateCondition`] expression.
 ///
-/// Includes everything from [`common_condition_parser`] plus `type == bin|lib|proc_macro`
-/// and `standalone`.
+/// Includes everything from [`common_condition_parser`] plus `type == bin|lib|proc_macro`,
+/// `standalone`, `default_member`, `publishes_to "registry"` and `tagged "tag"`.
 fn crate_condition_parser<'src>()
 -> impl Parser<'src, &'src str, CrateCondition, extra::Err<Rich<'src, char>>> + Clone {
     recursive(|cond| {
@@ -282,6 +301,16 @@ fn crate_condition_parser<'src>()
             .map(CrateCondition::CrateType);
 
         let standalone = kw("standalone").to(CrateCondition::Standalone);
+        let default_member = kw("default_member").to(CrateCondition::DefaultMember);
+        let publishes_to = kw("publishes_to")
+            .ignore_then(str_lit.clone())
+            .map(CrateCondition::PublishesTo);
+        let tagged = kw("tagged")
+            .ignore_then(str_lit.clone())
+            .map(CrateCondition::Tagged);
+        let in_set = kw("in_set")
+            .ignore_then(str_lit.clone())
+            .map(CrateCondition::InSet);
 
         let paren = cond.clone().delimited_by(sym("("), sym(")"));
 
@@ -293,6 +322,10 @@ fn crate_condition_parser<'src>()
             git_config_equals,
             crate_type,
             standalone,
+            default_member,
+            publishes_to,
+            tagged,
+            in_set,
             paren,
         ));
 
@@ -335,8 +368,22 @@ fn workspace_select_condition_parser<'src>()
     recursive(|cond| {
         let standalone = kw("standalone").to(WorkspaceSelectCondition::Standalone);
         let has_members = kw("has_members").to(WorkspaceSelectCondition::HasMembers);
+        let virtual_manifest = kw("virtual").to(WorkspaceSelectCondition::Virtual);
+        let in_set = kw("in_set")
+            .ignore_then(string_literal())
+            .map(WorkspaceSelectCondition::InSet);
+        let tagged = kw("tagged")
+            .ignore_then(string_literal())
+            .map(WorkspaceSelectCondition::Tagged);
         let paren = cond.clone().delimited_by(sym("("), sym(")"));
-        let atom = choice((standalone, has_members, paren));
+        let atom = choice((
+            standalone,
+            has_members,
+            virtual_manifest,
+            in_set,
+            tagged,
+            paren,
+        ));
 
         let not_expr = sym("!").repeated().foldr(atom, |_, inner| {
             WorkspaceSelectCondition::Not(Box::new(inner))
@@ -387,8 +434,22 @@ fn crate_select_condition_parser<'src>()
                 kw("custom_build").to(CrateTypeFilter::CustomBuild),
             )))
             .map(CrateSelectCondition::CrateType);
+        let default_member = kw("default_member").to(CrateSelectCondition::DefaultMember);
+        let tagged = kw("tagged")
+            .ignore_then(string_literal())
+            .map(CrateSelectCondition::Tagged);
+        let in_set = kw("in_set")
+            .ignore_then(string_literal())
+            .map(CrateSelectCondition::InSet);
         let paren = cond.clone().delimited_by(sym("("), sym(")"));
-        let atom = choice((standalone, crate_type, paren));
+        let atom = choice((
+            standalone,
+            crate_type,
+            default_member,
+            tagged,
+            in_set,
+            paren,
+        ));
 
         let not_expr = sym("!")
             .repeated()
@@ -429,28 +490,246 @@ fn snapshot_metadata_parser<'src>()
         .map(|name| SnapshotMetadataNode { name })
 }
 
-/// Parses a `run "cmd" "args"...;` statement into a [`RunStep`].
+/// Parses a heredoc: `<<MARKER`, the rest of that line, then every following
+/// line up to a line containing only `MARKER` (optionally followed by the `;`
+/// that ends the statement, which is left unconsumed).
+///
+/// The result contains the lines in between, each terminated by a newline.
+fn heredoc<'src>() -> impl Parser<'src, &'src str, String, extra::Err<Rich<'src, char>>> + Clone {
+    custom(|inp| {
+        let start = inp.cursor();
+        let marker: &str = inp.parse(
+            just("<<")
+                .ignore_then(text::ident())
+                .then_ignore(text::inline_whitespace())
+                .then_ignore(just('\n')),
+        )?;
+        let mut content = String::new();
+        loop {
+            let line_start = inp.save();
+            let line: &str = inp.parse(none_of('\n').repeated().to_slice())?;
+            let trimmed = line.trim();
+            if trimmed
+                .strip_prefix(marker)
+                .is_some_and(|rest| rest.is_empty() || rest.trim_start() == ";")
+            {
+                inp.rewind(line_start);
+                inp.parse(text::inline_whitespace().then(just(marker)))?;
+                return Ok(content);
+            }
+            content.push_str(line);
+            content.push('\n');
+            if inp.parse(just('\n')).is_err() {
+                return Err(Rich::custom(
+                    inp.span_since(&start),
+                    format!("unterminated heredoc, expected a line containing only `{marker}`"),
+                ));
+            }
+        }
+    })
+    .padded_by(padding())
+}
+
+/// Parses the optional `stdin "content"`, `stdin <<MARKER ... MARKER` or
+/// `stdin_file "path"` clause of a `run` statement.
+fn stdin_parser<'src>()
+-> impl Parser<'src, &'src str, StdinSource, extra::Err<Rich<'src, char>>> + Clone {
+    choice((
+        kw("stdin_file")
+            .ignore_then(string_literal())
+            .map(StdinSource::File),
+        kw("stdin")
+            .ignore_then(string_literal().or(heredoc()))
+            .map(StdinSource::Content),
+    ))
+}
+
+/// Parses a `rate_limit N per second|minute|hour` clause into a [`RateLimit`].
+fn rate_limit_parser<'src>()
+-> impl Parser<'src, &'src str, RateLimit, extra::Err<Rich<'src, char>>> + Clone {
+    kw("rate_limit")
+        .ignore_then(integer_literal().try_map(|max, span| {
+            if max == 0 {
+                Err(Rich::custom(span, "rate limit must be at least 1"))
+            } else {
+                Ok(max)
+            }
+        }))
+        .then_ignore(kw("per"))
+        .then(choice((
+            kw("second").to(1),
+            kw("minute").to(60),
+            kw("hour").to(3600),
+        )))
+        .map(|(max, period_seconds)| RateLimit {
+            max,
+            period_seconds,
+        })
+}
+
+/// Parses an `elevate` or `run_as "user"` clause into an [`Elevation`].
+fn elevation_parser<'src>()
+-> impl Parser<'src, &'src str, Elevation, extra::Err<Rich<'src, char>>> + Clone {
+    choice((
+        kw("elevate").to(Elevation::Root),
+        kw("run_as")
+            .ignore_then(string_literal())
+            .map(Elevation::User),
+    ))
+}
+
+/// Parses an `exclusive "resource"...` clause into the names of the
+/// resources.
+fn exclusive_parser<'src>()
+-> impl Parser<'src, &'src str, Vec<String>, extra::Err<Rich<'src, char>>> + Clone {
+    kw("exclusive").ignore_then(string_literal().repeated().at_least(1).collect::<Vec<_>>())
+}
+
+/// Parses an `env "NAME=value"...` clause into the variables to set.
+fn env_parser<'src>()
+-> impl Parser<'src, &'src str, BTreeMap<String, String>, extra::Err<Rich<'src, char>>> + Clone {
+    let assignment =
+        string_literal().try_map(
+            |assignment: String, span| match assignment.split_once('=') {
+                Some((name, value)) if !name.is_empty() => Ok((name.to_owned(), value.to_owned())),
+                _ => Err(Rich::custom(
+                    span,
+                    format!("expected \"NAME=value\", found \"{assignment}\""),
+                )),
+            },
+        );
+    kw("env").ignore_then(
+        assignment
+            .repeated()
+            .at_least(1)
+            .collect::<Vec<_>>()
+            .map(|assignments| assignments.into_iter().collect()),
+    )
+}
+
+/// Parses a `cwd "dir"` clause into the directory relative to the target.
+fn cwd_parser<'src>() -> impl Parser<'src, &'src str, String, extra::Err<Rich<'src, char>>> + Clone
+{
+    kw("cwd").ignore_then(string_literal().try_map(|dir: String, span| {
+        if std::path::Path::new(&dir).is_absolute() {
+            Err(Rich::custom(
+                span,
+                format!("cwd must be relative to the target, found \"{dir}\""),
+            ))
+        } else {
+            Ok(dir)
+        }
+    }))
+}
+
+/// Default number of seconds between two attempts of a `run` statement with
+/// `retries`.
+const DEFAULT_RETRY_DELAY_SECONDS: u64 = 10;
+
+/// Parses a `timeout N` clause into the number of seconds.
+fn timeout_parser<'src>() -> impl Parser<'src, &'src str, u64, extra::Err<Rich<'src, char>>> + Clone
+{
+    kw("timeout").ignore_then(integer_literal().try_map(|seconds, span| {
+        if seconds == 0 {
+            Err(Rich::custom(span, "timeout must be at least 1 second"))
+        } else {
+            Ok(seconds)
+        }
+    }))
+}
+
+/// Parses a `retries N [delay N]` clause into a [`Retry`].
+fn retry_parser<'src>() -> impl Parser<'src, &'src str, Retry, extra::Err<Rich<'src, char>>> + Clone
+{
+    kw("retries")
+        .ignore_then(integer_literal())
+        .then(kw("delay").ignore_then(integer_literal()).or_not())
+        .map(|(retries, delay_seconds)| Retry {
+            retries,
+            delay_seconds: delay_seconds.unwrap_or(DEFAULT_RETRY_DELAY_SECONDS),
+        })
+}
+
+/// Parses a `run "cmd" "args"... [elevate | run_as "user"] [rate_limit ...]
+/// [exclusive ...] [env ...] [cwd ...] [timeout N] [retries N [delay N]]
+/// [stdin ...];` statement into a [`RunStep`].
 fn run_step_parser<'src>()
 -> impl Parser<'src, &'src str, RunStep, extra::Err<Rich<'src, char>>> + Clone {
     let str_lit = string_literal();
     kw("run")
         .ignore_then(str_lit.clone())
         .then(str_lit.repeated().collect::<Vec<_>>())
+        .then(elevation_parser().or_not())
+        .then(rate_limit_parser().or_not())
+        .then(exclusive_parser().or_not())
+        .then(env_parser().or_not())
+        .then(cwd_parser().or_not())
+        .then(timeout_parser().or_not().then(retry_parser().or_not()))
+        .then(stdin_parser().or_not())
+        .then_ignore(sym(";"))
+        .map(
+            |(
+                (
+                    ((((((command, args), elevation), rate_limit), exclusive), env), cwd_relative),
+                    (timeout_seconds, retry),
+                ),
+                stdin,
+            )| {
+                RunStep {
+                    command,
+                    args,
+                    stdin,
+                    rate_limit,
+                    exclusive: exclusive.unwrap_or_default(),
+                    elevation,
+                    env: env.unwrap_or_default(),
+                    cwd_relative,
+                    timeout_seconds,
+                    retry,
+                }
+            },
+        )
+}
+
+/// Parses a `cargo "subcommand" [features "name"...] [profile "name"] [locked]
+/// [offline] [args "arg"...];` statement into a [`CargoStep`].
+fn cargo_step_parser<'src>()
+-> impl Parser<'src, &'src str, CargoStep, extra::Err<Rich<'src, char>>> + Clone {
+    let strings = || string_literal().repeated().at_least(1).collect::<Vec<_>>();
+    kw("cargo")
+        .ignore_then(string_literal())
+        .then(kw("features").ignore_then(strings()).or_not())
+        .then(kw("profile").ignore_then(string_literal()).or_not())
+        .then(kw("locked").or_not())
+        .then(kw("offline").or_not())
+        .then(kw("args").ignore_then(strings()).or_not())
         .then_ignore(sym(";"))
-        .map(|(command, args)| RunStep { command, args })
+        .map(
+            |(((((subcommand, features), profile), locked), offline), extra_args)| CargoStep {
+                subcommand,
+                features: features.unwrap_or_default(),
+                profile,
+                locked: locked.is_some(),
+                offline: offline.is_some(),
+                extra_args: extra_args.unwrap_or_default(),
+            },
+        )
 }
 
-/// Parses a `manual_step "title" "instructions";` statement into a [`ManualStepNode`].
+/// Parses a `manual_step "title" "instructions" [assignee "name"];` statement
+/// into a [`ManualStepNode`].
 fn manual_step_parser<'src>()
 -> impl Parser<'src, &'src str, ManualStepNode, extra::Err<Rich<'src, char>>> + Clone {
     let str_lit = string_literal();
     kw("manual_step")
         .ignore_then(str_lit.clone())
-        .then(str_lit)
+        .then(str_lit.clone())
+        .then(kw("assignee").ignore_then(str_lit).or_not())
         .then_ignore(sym(";"))
-        .map(|(title, instructions)| ManualStepNode {
+        .map(|((title, instructions), assignee)| ManualStepNode {
             title,
             instructions,
+            assignee,
         })
 }
 
@@ -463,6 +742,69 @@ fn wait_for_continue_parser<'src>()
         .map(|description| WaitForContinueNode { description })
 }
 
+/// Parses a [`PhaseNode`] from `phase "name";`.
+fn phase_parser<'src>()
+-> impl Parser<'src, &'src str, PhaseNode, extra::Err<Rich<'src, char>>> + Clone {
+    kw("phase")
+        .ignore_then(string_literal())
+        .then_ignore(sym(";"))
+        .map(|name| PhaseNode { name })
+}
+
+/// Parses a non-negative decimal integer.
+fn integer_literal<'src>() -> impl Parser<'src, &'src str, u64, extra::Err<Rich<'src, char>>> + Clone
+{
+    text::int(10)
+        .try_map(|digits: &str, span| {
+            digits
+                .parse::<u64>()
+                .map_err(|e| Rich::custom(span, format!("invalid number: {e}")))
+        })
+        .padded_by(padding())
+}
+
+/// Default number of seconds between two attempts of a `wait_until` statement.
+const DEFAULT_WAIT_UNTIL_INTERVAL_SECONDS: u64 = 10;
+
+/// Parses a `wait_until "cmd" "args"... [every N] [timeout N];` statement into a
+/// [`WaitUntilNode`].
+fn wait_until_parser<'src>()
+-> impl Parser<'src, &'src str, WaitUntilNode, extra::Err<Rich<'src, char>>> + Clone {
+    let str_lit = string_literal();
+    kw("wait_until")
+        .ignore_then(str_lit.clone())
+        .then(str_lit.repeated().collect::<Vec<_>>())
+        .then(kw("every").ignore_then(integer_literal()).or_not())
+        .then(kw("timeout").ignore_then(integer_literal()).or_not())
+        .then_ignore(sym(";"))
+        .map(
+            |(((command, args), interval_seconds), timeout_seconds)| WaitUntilNode {
+                command,
+                args,
+                interval_seconds: interval_seconds.unwrap_or(DEFAULT_WAIT_UNTIL_INTERVAL_SECONDS),
+                timeout_seconds,
+            },
+        )
+}
+
+/// Parses a `feature_powerset [depth N] [exclude "feature"...];` statement into
+/// a [`FeaturePowersetNode`].
+fn feature_powerset_parser<'src>()
+-> impl Parser<'src, &'src str, FeaturePowersetNode, extra::Err<Rich<'src, char>>> + Clone {
+    kw("feature_powerset")
+        .ignore_then(kw("depth").ignore_then(integer_literal()).or_not())
+        .then(
+            kw("exclude")
+                .ignore_then(string_literal().repeated().at_least(1).collect::<Vec<_>>())
+                .or_not(),
+        )
+        .then_ignore(sym(";"))
+        .map(|(depth, exclude_features)| FeaturePowersetNode {
+            depth,
+            exclude_features: exclude_features.unwrap_or_default(),
+        })
+}
+
 // ─── CrateStatement parser ────────────────────────────────────────────────────
 
 /// Parses a [`CrateStatement`].
@@ -470,13 +812,18 @@ fn crate_statement_parser<'src>()
 -> impl Parser<'src, &'src str, CrateStatement, extra::Err<Rich<'src, char>>> + Clone {
     recursive(|stmt| {
         let run = run_step_parser().map(CrateStatement::Run);
+        let cargo = cargo_step_parser().map(CrateStatement::Cargo);
         let manual = manual_step_parser().map(CrateStatement::ManualStep);
         let snapshot_metadata = snapshot_metadata_parser().map(CrateStatement::SnapshotMetadata);
         let wait_for_continue = wait_for_continue_parser().map(CrateStatement::WaitForContinue);
+        let wait_until = wait_until_parser().map(CrateStatement::WaitUntil);
+        let phase = phase_parser().map(CrateStatement::Phase);
+        let feature_powerset = feature_powerset_parser().map(CrateStatement::FeaturePowerset);
 
         let crate_cond = crate_condition_parser();
         let body = stmt.clone().repeated().collect::<Vec<_>>();
 
+        let only_if = only_if_parser(crate_cond.clone(), stmt.clone()).map(CrateStatement::If);
         let if_stmt = crate_if_parser(crate_cond, body.clone()).map(CrateStatement::If);
 
         let with_env_file = kw("with_env_file")
@@ -495,15 +842,38 @@ fn crate_statement_parser<'src>()
 
         choice((
             run,
+            cargo,
             manual,
             if_stmt,
+            only_if,
             with_env_file,
             snapshot_metadata,
             wait_for_continue,
+            wait_until,
+            phase,
+            feature_powerset,
         ))
     })
 }
 
+/// Builds a parser for `only_if <condition> <statement>`, a shorthand for an
+/// `if` block guarding a single statement.
+fn only_if_parser<'src, C, S>(
+    cond_parser: impl Parser<'src, &'src str, C, extra::Err<Rich<'src, char>>> + Clone,
+    stmt_parser: impl Parser<'src, &'src str, S, extra::Err<Rich<'src, char>>> + Clone,
+) -> impl Parser<'src, &'src str, IfBlock<C, S>, extra::Err<Rich<'src, char>>> + Clone {
+    kw("only_if")
+        .ignore_then(cond_parser)
+        .then(stmt_parser)
+        .map(|(condition, statement)| IfBlock {
+            branches: vec![Branch {
+                condition,
+                statements: vec![statement],
+            }],
+            else_statements: vec![],
+        })
+}
+
 /// Builds an if/else-if/else parser for the crate context.
 fn crate_if_parser<'src>(
     cond_parser: impl Parser<'src, &'src str, CrateCondition, extra::Err<Rich<'src, char>>> + Clone,
@@ -549,14 +919,18 @@ fn workspace_statement_parser<'src>()
 -> impl Parser<'src, &'src str, WorkspaceStatement, extra::Err<Rich<'src, char>>> + Clone {
     recursive(|stmt| {
         let run = run_step_parser().map(WorkspaceStatement::Run);
+        let cargo = cargo_step_parser().map(WorkspaceStatement::Cargo);
         let manual = manual_step_parser().map(WorkspaceStatement::ManualStep);
         let snapshot_metadata =
             snapshot_metadata_parser().map(WorkspaceStatement::SnapshotMetadata);
         let wait_for_continue = wait_for_continue_parser().map(WorkspaceStatement::WaitForContinue);
+        let wait_until = wait_until_parser().map(WorkspaceStatement::WaitUntil);
+        let phase = phase_parser().map(WorkspaceStatement::Phase);
 
         let ws_cond = workspace_condition_parser();
         let ws_body = stmt.clone().repeated().collect::<Vec<_>>();
 
+        let only_if = only_if_parser(ws_cond.clone(), stmt.clone()).map(WorkspaceStatement::If);
         let if_stmt = workspace_if_parser(ws_cond, ws_body.clone()).map(WorkspaceStatement::If);
 
         let for_crate_in_ws = kw("for")
@@ -589,12 +963,16 @@ fn workspace_statement_parser<'src>()
 
         choice((
             run,
+            cargo,
             manual,
             if_stmt,
+            only_if,
             for_crate_in_ws,
             with_env_file,
             snapshot_metadata,
             wait_for_continue,
+            wait_until,
+            phase,
         ))
     })
 }
@@ -670,6 +1048,33 @@ fn program_parser<'src>() -> impl Parser<'src, &'src str, Program, extra::Err<Ri
         .then_ignore(sym(";"))
         .map(|condition| GlobalStatement::SelectCrates(CrateFilter { condition }));
 
+    // `select members [where <cond>];`
+    let select_members = kw("select")
+        .ignore_then(kw("members"))
+        .ignore_then(
+            kw("where")
+                .ignore_then(crate_select_condition_parser())
+                .or_not(),
+        )
+        .then_ignore(sym(";"))
+        .map(|condition| GlobalStatement::SelectMembers(CrateFilter { condition }));
+
+    // `require_clean_git;`
+    let require_clean_git = kw("require_clean_git")
+        .then_ignore(sym(";"))
+        .to(GlobalStatement::RequireCleanGit);
+
+    // `record auto|asciinema|script|none;`
+    let record = kw("record")
+        .ignore_then(choice((
+            kw("auto").to(Recorder::Auto),
+            kw("asciinema").to(Recorder::Asciinema),
+            kw("script").to(Recorder::Script),
+            kw("none").to(Recorder::None),
+        )))
+        .then_ignore(sym(";"))
+        .map(GlobalStatement::Record);
+
     // `for workspace { ... }`
     let for_workspace = kw("for")
         .ignore_then(kw("workspace"))
@@ -699,12 +1104,20 @@ fn program_parser<'src>() -> impl Parser<'src, &'src str, Program, extra::Err<Ri
     // so inter-statement whitespace and comments are consumed by those keyword parsers.
     // Trailing padding (after the last statement, including a trailing comment or newline) is
     // consumed by the explicit `padding()` before `end()`.
-    choice((select_workspaces, select_crates, for_workspace, for_crate))
-        .repeated()
-        .collect::<Vec<_>>()
-        .then_ignore(padding())
-        .then_ignore(end())
-        .map(|statements| Program { statements })
+    choice((
+        select_workspaces,
+        select_crates,
+        select_members,
+        require_clean_git,
+        record,
+        for_workspace,
+        for_crate,
+    ))
+    .repeated()
+    .collect::<Vec<_>>()
+    .then_ignore(padding())
+    .then_ignore(end())
+    .map(|statements| Program { statements })
 }
 
 // ─── Tests ────────────────────────────────────────────────────────────────────
@@ -777,6 +1190,37 @@ mod tests {
         );
     }
 
+    #[test]
+    fn require_clean_git_statement() {
+        let prog = parse_ok("require_clean_git;\nselect crates;");
+        assert_eq!(
+            prog.statements.first(),
+            Some(&GlobalStatement::RequireCleanGit)
+        );
+    }
+
+    #[test]
+    fn record_statement() {
+        let prog = parse_ok("record script;\nselect crates;");
+        assert_eq!(
+            prog.statements.first(),
+            Some(&GlobalStatement::Record(Recorder::Script))
+        );
+    }
+
+    #[test]
+    fn select_workspaces_where_not_virtual() {
+        let prog = parse_ok("select workspaces where !virtual;");
+        assert_eq!(
+            prog.statements,
+            vec![GlobalStatement::SelectWorkspaces(WorkspaceFilter {
+                condition: Some(WorkspaceSelectCondition::Not(Box::new(
+                    WorkspaceSelectCondition::Virtual
+                )))
+            })]
+        );
+    }
+
     #[test]
     fn select_workspaces_where_not_standalone() {
         let prog = parse_ok("select workspaces where !standalone;");
@@ -812,6 +1256,41 @@ mod tests {
         );
     }
 
+    #[test]
+    fn select_crates_where_in_set() {
+        let prog = parse_ok(r#"select crates where in_set "team-a";"#);
+        assert_eq!(
+            prog.statements,
+            vec![GlobalStatement::SelectCrates(CrateFilter {
+                condition: Some(CrateSelectCondition::InSet("team-a".to_owned()))
+            })]
+        );
+    }
+
+    #[test]
+    fn select_workspaces_where_tagged() {
+        let prog = parse_ok(r#"select workspaces where tagged "client";"#);
+        assert_eq!(
+            prog.statements,
+            vec![GlobalStatement::SelectWorkspaces(WorkspaceFilter {
+                condition: Some(WorkspaceSelectCondition::Tagged("client".to_owned()))
+            })]
+        );
+    }
+
+    #[test]
+    fn select_members_where_not_default_member() {
+        let prog = parse_ok("select members where !default_member;");
+        assert_eq!(
+            prog.statements,
+            vec![GlobalStatement::SelectMembers(CrateFilter {
+                condition: Some(CrateSelectCondition::Not(Box::new(
+                    CrateSelectCondition::DefaultMember
+                )))
+            })]
+        );
+    }
+
     #[test]
     fn for_workspace_with_run() {
         let prog = parse_ok(r#"for workspace { run "cargo" "check"; }"#);
@@ -821,6 +1300,14 @@ mod tests {
                 statements: vec![WorkspaceStatement::Run(RunStep {
                     command: "cargo".to_owned(),
                     args: vec!["check".to_owned()],
+                    stdin: None,
+                    rate_limit: None,
+                    exclusive: vec![],
+                    elevation: None,
+                    env: BTreeMap::new(),
+                    cwd_relative: None,
+                    timeout_seconds: None,
+                    retry: None,
                 })]
             })]
         );
@@ -835,6 +1322,24 @@ mod tests {
                 statements: vec![WorkspaceStatement::ManualStep(ManualStepNode {
                     title: "Review".to_owned(),
                     instructions: "Check the output.".to_owned(),
+                    assignee: None,
+                })]
+            })]
+        );
+    }
+
+    #[test]
+    fn manual_step_with_assignee() {
+        let prog = parse_ok(
+            r#"for crate { manual_step "Announce" "Post the release notes." assignee "alice"; }"#,
+        );
+        assert_eq!(
+            prog.statements,
+            vec![GlobalStatement::ForCrate(ForCrateBlock {
+                statements: vec![CrateStatement::ManualStep(ManualStepNode {
+                    title: "Announce".to_owned(),
+                    instructions: "Post the release notes.".to_owned(),
+                    assignee: Some("alice".to_owned()),
                 })]
             })]
         );
@@ -852,6 +1357,14 @@ mod tests {
                         statements: vec![CrateStatement::Run(RunStep {
                             command: "cargo".to_owned(),
                             args: vec!["publish".to_owned()],
+                            stdin: None,
+                            rate_limit: None,
+                            exclusive: vec![],
+                            elevation: None,
+                            env: BTreeMap::new(),
+                            cwd_relative: None,
+                            timeout_seconds: None,
+                            retry: None,
                         })]
                     }
                 )]
@@ -868,6 +1381,14 @@ mod tests {
                 statements: vec![CrateStatement::Run(RunStep {
                     command: "cargo".to_owned(),
                     args: vec!["clippy".to_owned()],
+                    stdin: None,
+                    rate_limit: None,
+                    exclusive: vec![],
+                    elevation: None,
+                    env: BTreeMap::new(),
+                    cwd_relative: None,
+                    timeout_seconds: None,
+                    retry: None,
                 })]
             })]
         );
@@ -885,6 +1406,14 @@ mod tests {
                         statements: vec![CrateStatement::Run(RunStep {
                             command: "cargo".to_owned(),
                             args: vec!["publish".to_owned()],
+                            stdin: None,
+                            rate_limit: None,
+                            exclusive: vec![],
+                            elevation: None,
+                            env: BTreeMap::new(),
+                            cwd_relative: None,
+                            timeout_seconds: None,
+                            retry: None,
                         })],
                     }],
                     else_statements: vec![],
@@ -893,6 +1422,31 @@ mod tests {
         );
     }
 
+    #[test]
+    fn only_if_guards_one_statement() {
+        assert_eq!(
+            parse_ok(
+                r#"for crate { only_if type == bin run "cargo" "install" "--path" "."; run "cargo" "test"; }"#
+            ),
+            parse_ok(
+                r#"for crate { if type == bin { run "cargo" "install" "--path" "."; } run "cargo" "test"; }"#
+            )
+        );
+        assert_eq!(
+            parse_ok(
+                r#"for workspace { only_if run "grep" "-q" "bench" "Cargo.toml" run "cargo" "bench"; }"#
+            ),
+            parse_ok(
+                r#"for workspace { if run "grep" "-q" "bench" "Cargo.toml" { run "cargo" "bench"; } }"#
+            ),
+            "a run condition ends at the guarded statement"
+        );
+        assert!(
+            parse(r#"for crate { only_if file_exists "benches"; }"#, "<test>").is_err(),
+            "only_if needs a statement"
+        );
+    }
+
     #[test]
     fn workspace_if_with_else() {
         let prog = parse_ok(
@@ -915,11 +1469,20 @@ mod tests {
                         statements: vec![WorkspaceStatement::Run(RunStep {
                             command: "cargo".to_owned(),
                             args: vec!["release".to_owned()],
+                            stdin: None,
+                            rate_limit: None,
+                            exclusive: vec![],
+                            elevation: None,
+                            env: BTreeMap::new(),
+                            cwd_relative: None,
+                            timeout_seconds: None,
+                            retry: None,
                         })],
                     }],
                     else_statements: vec![WorkspaceStatement::ManualStep(ManualStepNode {
                         title: "Fix it".to_owned(),
                         instructions: "Commit your changes first.".to_owned(),
+                        assignee: None,
                     })],
                 })]
             })]
@@ -1125,4 +1688,360 @@ for crate {
         assert_eq!(env_block.statements.len(), 1);
         assert!(matches!(env_block.statements[0], CrateStatement::If(_)));
     }
+
+    #[test]
+    fn wait_until_with_interval_and_timeout() {
+        let prog = parse_ok(
+            r#"for workspace { wait_until "curl" "-sf" "https://example.com" every 30 timeout 600; }"#,
+        );
+        assert_eq!(
+            prog.statements,
+            vec![GlobalStatement::ForWorkspace(ForWorkspaceBlock {
+                statements: vec![WorkspaceStatement::WaitUntil(WaitUntilNode {
+                    command: "curl".to_owned(),
+                    args: vec!["-sf".to_owned(), "https://example.com".to_owned()],
+                    interval_seconds: 30,
+                    timeout_seconds: Some(600),
+                })]
+            })]
+        );
+    }
+
+    #[test]
+    fn wait_until_defaults() {
+        let prog = parse_ok(r#"for crate { wait_until "true"; }"#);
+        assert_eq!(
+            prog.statements,
+            vec![GlobalStatement::ForCrate(ForCrateBlock {
+                statements: vec![CrateStatement::WaitUntil(WaitUntilNode {
+                    command: "true".to_owned(),
+                    args: vec![],
+                    interval_seconds: DEFAULT_WAIT_UNTIL_INTERVAL_SECONDS,
+                    timeout_seconds: None,
+                })]
+            })]
+        );
+    }
+
+    /// Returns the stdin of the single `run` statement in a `for crate` block.
+    fn run_stdin(body: &str) -> Option<StdinSource> {
+        let prog = parse_ok(&format!("for crate {{\n{body}\n}}"));
+        match prog.statements.as_slice() {
+            [GlobalStatement::ForCrate(ForCrateBlock { statements })] => {
+                match statements.as_slice() {
+                    [CrateStatement::Run(step)] => step.stdin.clone(),
+                    other => panic!("expected a single run statement, got {other:?}"),
+                }
+            }
+            other => panic!("expected a single for crate block, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn run_with_stdin() {
+        assert_eq!(run_stdin(r#"run "cargo" "build";"#), None);
+        assert_eq!(
+            run_stdin(r#"run "cargo" "login" stdin "${secrets.token}";"#),
+            Some(StdinSource::Content("${secrets.token}".to_owned()))
+        );
+        assert_eq!(
+            run_stdin(r#"run "installer" stdin_file "answers.txt";"#),
+            Some(StdinSource::File("answers.txt".to_owned()))
+        );
+    }
+
+    #[test]
+    fn run_with_stdin_heredoc() {
+        assert_eq!(
+            run_stdin("run \"installer\" stdin <<END\n  yes\nno\n    END;"),
+            Some(StdinSource::Content("  yes\nno\n".to_owned()))
+        );
+        assert_eq!(
+            run_stdin("run \"installer\" stdin <<EOF\nEND\nEOF\n;"),
+            Some(StdinSource::Content("END\n".to_owned()))
+        );
+        assert!(
+            parse("for crate { run \"x\" stdin <<END\nyes\n }", "<test>").is_err(),
+            "an unterminated heredoc should be rejected"
+        );
+    }
+
+    #[test]
+    fn run_with_rate_limit() {
+        let prog = parse_ok(
+            r#"for crate { run "cargo" "publish" rate_limit 5 per minute stdin_file "t"; }"#,
+        );
+        assert_eq!(
+            prog.statements,
+            vec![GlobalStatement::ForCrate(ForCrateBlock {
+                statements: vec![CrateStatement::Run(RunStep {
+                    command: "cargo".to_owned(),
+                    args: vec!["publish".to_owned()],
+                    stdin: Some(StdinSource::File("t".to_owned())),
+                    rate_limit: Some(RateLimit {
+                        max: 5,
+                        period_seconds: 60,
+                    }),
+                    exclusive: vec![],
+                    elevation: None,
+                    env: BTreeMap::new(),
+                    cwd_relative: None,
+                    timeout_seconds: None,
+                    retry: None,
+                })]
+            })]
+        );
+        assert!(
+            parse(r#"for crate { run "x" rate_limit 0 per hour; }"#, "<test>").is_err(),
+            "a rate limit of zero should be rejected"
+        );
+    }
+
+    #[test]
+    fn run_with_exclusive_resources() {
+        let exclusive = |src: &str| match parse_ok(src).statements.as_slice() {
+            [GlobalStatement::ForCrate(ForCrateBlock { statements })] => {
+                match statements.as_slice() {
+                    [CrateStatement::Run(step)] => step.exclusive.clone(),
+                    _ => vec![],
+                }
+            }
+            _ => vec![],
+        };
+        assert_eq!(
+            exclusive(
+                r#"for crate { run "cargo" "login" rate_limit 1 per minute exclusive "registry-token" stdin "t"; }"#
+            ),
+            ["registry-token"]
+        );
+        assert_eq!(
+            exclusive(
+                r#"for crate { run "apt-get" "install" "x" elevate exclusive "apt" "dpkg"; }"#
+            ),
+            ["apt", "dpkg"]
+        );
+        assert!(
+            parse(r#"for crate { run "x" exclusive; }"#, "<test>").is_err(),
+            "exclusive needs at least one resource"
+        );
+    }
+
+    #[test]
+    fn run_with_env_and_cwd() {
+        let prog = parse_ok(
+            r#"for crate { run "cargo" "build" env "RUSTFLAGS=-D warnings" "CARGO_TARGET_DIR=/tmp/t" cwd "sub/dir"; }"#,
+        );
+        let step = match prog.statements.as_slice() {
+            [GlobalStatement::ForCrate(block)] => match block.statements.as_slice() {
+                [CrateStatement::Run(step)] => step.clone(),
+                other => panic!("expected one run statement, got {other:?}"),
+            },
+            other => panic!("expected one for crate block, got {other:?}"),
+        };
+        assert_eq!(
+            step.env,
+            BTreeMap::from([
+                ("CARGO_TARGET_DIR".to_owned(), "/tmp/t".to_owned()),
+                ("RUSTFLAGS".to_owned(), "-D warnings".to_owned()),
+            ])
+        );
+        assert_eq!(step.cwd_relative.as_deref(), Some("sub/dir"));
+        assert!(
+            parse(r#"for crate { run "x" env "NOVALUE"; }"#, "<test>").is_err(),
+            "env assignments need a ="
+        );
+        assert!(
+            parse(r#"for crate { run "x" cwd "/tmp"; }"#, "<test>").is_err(),
+            "cwd must be relative"
+        );
+    }
+
+    #[test]
+    fn run_with_timeout_and_retries() {
+        let run_step = |src: &str| match parse_ok(src).statements.as_slice() {
+            [GlobalStatement::ForWorkspace(ForWorkspaceBlock { statements })] => {
+                match statements.as_slice() {
+                    [WorkspaceStatement::Run(step)] => Some(step.clone()),
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+        let step = run_step(
+            r#"for workspace { run "cargo" "test" cwd "sub" timeout 1800 retries 2 delay 30 stdin "y"; }"#,
+        );
+        assert_eq!(step.as_ref().and_then(|s| s.timeout_seconds), Some(1800));
+        assert_eq!(
+            step.and_then(|s| s.retry),
+            Some(Retry {
+                retries: 2,
+                delay_seconds: 30
+            })
+        );
+        assert_eq!(
+            run_step(r#"for workspace { run "cargo" "test" retries 1; }"#).and_then(|s| s.retry),
+            Some(Retry {
+                retries: 1,
+                delay_seconds: DEFAULT_RETRY_DELAY_SECONDS
+            })
+        );
+        assert!(
+            parse(r#"for crate { run "x" timeout 0; }"#, "<test>").is_err(),
+            "a timeout of zero seconds is rejected"
+        );
+    }
+
+    #[test]
+    fn run_with_elevation() {
+        let elevation = |src: &str| match parse_ok(src).statements.as_slice() {
+            [GlobalStatement::ForWorkspace(ForWorkspaceBlock { statements })] => {
+                match statements.as_slice() {
+                    [WorkspaceStatement::Run(step)] => step.elevation.clone(),
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+        assert_eq!(
+            elevation(r#"for workspace { run "install" "-m755" "bin" "/usr/local/bin" elevate; }"#),
+            Some(Elevation::Root)
+        );
+        assert_eq!(
+            elevation(
+                r#"for workspace { run "systemctl" "restart" "x" run_as "deploy" rate_limit 1 per second; }"#
+            ),
+            Some(Elevation::User("deploy".to_owned()))
+        );
+        assert!(
+            parse(
+                r#"for workspace { run "x" rate_limit 1 per second elevate; }"#,
+                "<test>"
+            )
+            .is_err(),
+            "elevation comes before the rate limit"
+        );
+    }
+
+    #[test]
+    fn cargo_statement() {
+        let program = parse_ok(
+            r#"for crate { cargo "test" features "a" "b" profile "ci" locked offline args "--" "-q"; cargo "build"; }"#,
+        );
+        let [GlobalStatement::ForCrate(ForCrateBlock { statements })] =
+            program.statements.as_slice()
+        else {
+            panic!("expected a single for crate block");
+        };
+        let [CrateStatement::Cargo(full), CrateStatement::Cargo(plain)] = statements.as_slice()
+        else {
+            panic!("expected two cargo statements");
+        };
+        assert_eq!(
+            full,
+            &CargoStep {
+                subcommand: "test".to_owned(),
+                features: vec!["a".to_owned(), "b".to_owned()],
+                profile: Some("ci".to_owned()),
+                locked: true,
+                offline: true,
+                extra_args: vec!["--".to_owned(), "-q".to_owned()],
+            }
+        );
+        assert_eq!(
+            full.to_run_step().args,
+            [
+                "test",
+                "--features",
+                "a,b",
+                "--profile",
+                "ci",
+                "--locked",
+                "--offline",
+                "--",
+                "-q"
+            ]
+        );
+        assert_eq!(plain.to_run_step().args, ["build"]);
+        assert!(
+            parse_ok(r#"for workspace { cargo "check" locked; }"#)
+                .statements
+                .len()
+                == 1,
+            "cargo is also a workspace statement"
+        );
+        assert!(
+            parse(
+                r#"for crate { cargo "test" locked profile "ci"; }"#,
+                "<test>"
+            )
+            .is_err(),
+            "clauses appear in a fixed order"
+        );
+    }
+
+    #[test]
+    fn phase_markers() {
+        let program = parse_ok(r#"for crate { phase "verify"; run "cargo" "test"; }"#);
+        let [GlobalStatement::ForCrate(ForCrateBlock { statements })] =
+            program.statements.as_slice()
+        else {
+            panic!("expected a single for crate block");
+        };
+        assert!(
+            matches!(
+                statements.as_slice(),
+                [CrateStatement::Phase(PhaseNode { name }), CrateStatement::Run(_)] if name == "verify"
+            ),
+            "phase marker followed by a run step"
+        );
+        assert!(
+            parse("for crate { phase verify; }", "<test>").is_err(),
+            "phase names are string literals"
+        );
+    }
+
+    #[test]
+    fn feature_powerset_statement() {
+        let program = parse_ok(
+            r#"for crate { feature_powerset; feature_powerset depth 2 exclude "a" "b"; }"#,
+        );
+        let [GlobalStatement::ForCrate(ForCrateBlock { statements })] =
+            program.statements.as_slice()
+        else {
+            panic!("expected a single for crate block");
+        };
+        assert_eq!(
+            statements,
+            &[
+                CrateStatement::FeaturePowerset(FeaturePowersetNode {
+                    depth: None,
+                    exclude_features: vec![],
+                }),
+                CrateStatement::FeaturePowerset(FeaturePowersetNode {
+                    depth: Some(2),
+                    exclude_features: vec!["a".to_owned(), "b".to_owned()],
+                }),
+            ]
+        );
+        assert!(
+            parse("for workspace { feature_powerset; }", "<test>").is_err(),
+            "feature_powerset is a crate statement"
+        );
+    }
+
+    #[test]
+    fn publishes_to_condition() {
+        let prog = parse_ok(r#"for crate { if publishes_to "kellnr" { run "cargo" "publish"; } }"#);
+        let [GlobalStatement::ForCrate(ForCrateBlock { statements })] = prog.statements.as_slice()
+        else {
+            panic!("expected a single for crate block");
+        };
+        let [CrateStatement::If(block)] = statements.as_slice() else {
+            panic!("expected an if block");
+        };
+        assert_eq!(
+            block.branches[0].condition,
+            CrateCondition::PublishesTo("kellnr".to_owned())
+        );
+    }
 }
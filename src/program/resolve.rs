@@ -3,18 +3,21 @@
 
 pub mod snapshot;
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::path::{Path, PathBuf};
 
 use cargo_metadata::{DependencyKind, PackageId};
 
 use crate::error::Error;
+use crate::metadata::MetadataProvider;
 use crate::program::ast::crate_ctx::{CrateFilter, CrateSelectCondition, CrateTypeFilter};
 use crate::program::ast::workspace_ctx::{WorkspaceFilter, WorkspaceSelectCondition};
 use crate::program::{GlobalStatement, Program};
-use crate::targets::CrateType;
+use crate::targets::{CrateType, PackageMetadata};
 
-pub use snapshot::{ResolvedCrateExecution, ResolvedProgram, ResolvedWorkspaceExecution};
+pub use snapshot::{
+    ResolvedCrateExecution, ResolvedPackage, ResolvedProgram, ResolvedWorkspaceExecution,
+};
 
 /// Resolves a parsed program against the current configuration.
 ///
@@ -34,6 +37,7 @@ pub use snapshot::{ResolvedCrateExecution, ResolvedProgram, ResolvedWorkspaceExe
 pub fn resolve_program(
     program: &Program,
     config: &crate::Config,
+    provider: &dyn MetadataProvider,
 ) -> Result<ResolvedProgram, Error> {
     // ── Collect filters from the program ─────────────────────────────────────
     let workspace_filters: Vec<&WorkspaceFilter> = program
@@ -60,18 +64,36 @@ pub fn resolve_program(
         })
         .collect();
 
+    let member_filters = collect_member_filters(program);
+
+    // ── Check that the target sets used by the filters exist ─────────────────
+    let mut set_names = BTreeSet::new();
+    for filter in &workspace_filters {
+        if let Some(cond) = &filter.condition {
+            workspace_select_set_names(cond, &mut set_names);
+        }
+    }
+    for filter in crate_filters.iter().chain(&member_filters) {
+        if let Some(cond) = &filter.condition {
+            crate_select_set_names(cond, &mut set_names);
+        }
+    }
+    for name in set_names {
+        crate::targets::sets::members(config, name)?;
+    }
+
     // ── Resolve workspaces ────────────────────────────────────────────────────
     let workspace_executions = if workspace_filters.is_empty() {
         Vec::new()
     } else {
-        resolve_workspaces(&workspace_filters, config)?
+        resolve_workspaces(&workspace_filters, &member_filters, config, provider)?
     };
 
     // ── Resolve standalone crates ─────────────────────────────────────────────
     let crate_executions = if crate_filters.is_empty() {
         Vec::new()
     } else {
-        resolve_standalone_crates(&crate_filters, config)?
+        resolve_standalone_crates(&crate_filters, config, provider)?
     };
 
     Ok(ResolvedProgram {
@@ -80,11 +102,184 @@ pub fn resolve_program(
     })
 }
 
+/// Collects the filters of all `select members` statements in the program.
+///
+/// An empty result means that all member crates of the selected workspaces are
+/// iterated over.
+#[must_use]
+pub fn collect_member_filters(program: &Program) -> Vec<&CrateFilter> {
+    program
+        .statements
+        .iter()
+        .filter_map(|s| {
+            if let GlobalStatement::SelectMembers(f) = s {
+                Some(f)
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Returns `true` if the package is one of the workspace's `default-members`.
+///
+/// Cargo versions that do not report default members are treated as if every
+/// member was a default member.
+#[must_use]
+pub fn is_default_member(metadata: &cargo_metadata::Metadata, package_id: &PackageId) -> bool {
+    metadata.workspace_default_members.is_missing()
+        || metadata.workspace_default_members.contains(package_id)
+}
+
+/// Returns `true` if the `publish` field of the package's manifest allows
+/// publishing it to `registry` (`crates-io` for crates.io).
+///
+/// A package without a `publish` field may be published to any registry;
+/// `publish = false` allows none.
+#[must_use]
+pub fn publishes_to(package: &cargo_metadata::Package, registry: &str) -> bool {
+    package
+        .publish
+        .as_ref()
+        .is_none_or(|registries| registries.iter().any(|r| r == registry))
+}
+
+/// Returns the directories of crates that a selected crate depends on by
+/// `path` but that are not registered, sorted and without duplicates.
+///
+/// Such a dependency is missing from the resolved program, so the ordering
+/// computed for it cannot take the dependency into account.  Dev-dependencies
+/// are ignored, like for the ordering itself.
+///
+/// # Errors
+///
+/// Returns an error if `cargo metadata` fails for a selected target or if a
+/// manifest path cannot be canonicalized.
+pub fn unregistered_path_dependencies(
+    resolved: &ResolvedProgram,
+    config: &crate::Config,
+    provider: &dyn MetadataProvider,
+) -> Result<Vec<PathBuf>, Error> {
+    let registered: HashSet<PathBuf> = config
+        .crates
+        .iter()
+        .map(|c| {
+            fs_err::canonicalize(&c.manifest_dir).unwrap_or_else(|_err| c.manifest_dir.clone())
+        })
+        .collect();
+    let selected: HashSet<&PathBuf> = resolved
+        .workspace_executions
+        .iter()
+        .flat_map(|w| w.member_crates.iter())
+        .chain(resolved.crate_executions.iter())
+        .map(|c| &c.manifest_dir)
+        .collect();
+    let roots: BTreeSet<&PathBuf> = resolved
+        .workspace_executions
+        .iter()
+        .map(|w| &w.manifest_dir)
+        .chain(resolved.crate_executions.iter().map(|c| &c.manifest_dir))
+        .collect();
+
+    let mut unregistered = BTreeSet::new();
+    for root in roots {
+        let metadata = provider.metadata(root)?;
+        for package in &metadata.packages {
+            let Some(package_dir) = package.manifest_path.parent() else {
+                continue;
+            };
+            let package_dir = fs_err::canonicalize(package_dir).map_err(|e| {
+                Error::CouldNotDetermineCanonicalManifestPath(package_dir.into(), e)
+            })?;
+            if !selected.contains(&package_dir) {
+                continue;
+            }
+            for dep in &package.dependencies {
+                if dep.kind == DependencyKind::Development {
+                    continue;
+                }
+                let Some(dep_dir) = &dep.path else {
+                    continue;
+                };
+                let Ok(dep_dir) = fs_err::canonicalize(dep_dir) else {
+                    continue;
+                };
+                if dep_dir.join("Cargo.toml").exists() && !registered.contains(&dep_dir) {
+                    unregistered.insert(dep_dir);
+                }
+            }
+        }
+    }
+    Ok(unregistered.into_iter().collect())
+}
+
+/// Collects the names of the target sets used by `cond` into `names`.
+fn workspace_select_set_names<'a>(
+    cond: &'a WorkspaceSelectCondition,
+    names: &mut BTreeSet<&'a str>,
+) {
+    match cond {
+        WorkspaceSelectCondition::InSet(name) => {
+            names.insert(name);
+        }
+        WorkspaceSelectCondition::Not(inner) => workspace_select_set_names(inner, names),
+        WorkspaceSelectCondition::And(conditions) | WorkspaceSelectCondition::Or(conditions) => {
+            for c in conditions {
+                workspace_select_set_names(c, names);
+            }
+        }
+        WorkspaceSelectCondition::Standalone
+        | WorkspaceSelectCondition::HasMembers
+        | WorkspaceSelectCondition::Virtual
+        | WorkspaceSelectCondition::Tagged(_) => {}
+    }
+}
+
+/// Collects the names of the target sets used by `cond` into `names`.
+fn crate_select_set_names<'a>(cond: &'a CrateSelectCondition, names: &mut BTreeSet<&'a str>) {
+    match cond {
+        CrateSelectCondition::InSet(name) => {
+            names.insert(name);
+        }
+        CrateSelectCondition::Not(inner) => crate_select_set_names(inner, names),
+        CrateSelectCondition::And(conditions) | CrateSelectCondition::Or(conditions) => {
+            for c in conditions {
+                crate_select_set_names(c, names);
+            }
+        }
+        CrateSelectCondition::Standalone
+        | CrateSelectCondition::CrateType(_)
+        | CrateSelectCondition::DefaultMember
+        | CrateSelectCondition::Tagged(_) => {}
+    }
+}
+
+/// The properties of a crate that `select crates` and `select members`
+/// conditions are evaluated against.
+struct CrateSelectFacts<'a> {
+    /// The manifest directory of the crate.
+    manifest_dir: &'a Path,
+    /// The configuration, for the target sets.
+    config: &'a crate::Config,
+    /// The target types of the crate.
+    types: &'a BTreeSet<CrateType>,
+    /// Whether the crate lives in a standalone (single-crate) workspace.
+    standalone: bool,
+    /// Whether the crate is one of its workspace's `default-members`.
+    default_member: bool,
+    /// The settings from the crate's `[package.metadata.cargo-for-each]`.
+    metadata: &'a PackageMetadata,
+}
+
 /// Returns `true` if the workspace satisfies the filter.
-fn workspace_matches_filter(workspace: &crate::Workspace, filter: &WorkspaceFilter) -> bool {
+fn workspace_matches_filter(
+    workspace: &crate::Workspace,
+    filter: &WorkspaceFilter,
+    config: &crate::Config,
+) -> bool {
     match &filter.condition {
         None => true,
-        Some(cond) => evaluate_workspace_select_condition(cond, workspace),
+        Some(cond) => evaluate_workspace_select_condition(cond, workspace, config),
     }
 }
 
@@ -92,67 +287,80 @@ fn workspace_matches_filter(workspace: &crate::Workspace, filter: &WorkspaceFilt
 fn evaluate_workspace_select_condition(
     cond: &WorkspaceSelectCondition,
     workspace: &crate::Workspace,
+    config: &crate::Config,
 ) -> bool {
     match cond {
         WorkspaceSelectCondition::Standalone => workspace.is_standalone,
         WorkspaceSelectCondition::HasMembers => !workspace.is_standalone,
+        WorkspaceSelectCondition::Virtual => {
+            crate::targets::is_virtual_manifest(&workspace.manifest_dir)
+        }
+        WorkspaceSelectCondition::InSet(name) => {
+            crate::targets::sets::contains(config, name, &workspace.manifest_dir)
+        }
+        WorkspaceSelectCondition::Tagged(tag) => {
+            crate::targets::tags::has_tag(config, &workspace.manifest_dir, tag)
+        }
         WorkspaceSelectCondition::Not(inner) => {
-            !evaluate_workspace_select_condition(inner, workspace)
+            !evaluate_workspace_select_condition(inner, workspace, config)
         }
         WorkspaceSelectCondition::And(conditions) => conditions
             .iter()
-            .all(|c| evaluate_workspace_select_condition(c, workspace)),
+            .all(|c| evaluate_workspace_select_condition(c, workspace, config)),
         WorkspaceSelectCondition::Or(conditions) => conditions
             .iter()
-            .any(|c| evaluate_workspace_select_condition(c, workspace)),
+            .any(|c| evaluate_workspace_select_condition(c, workspace, config)),
     }
 }
 
 /// Returns `true` if the crate satisfies the filter.
-fn crate_matches_filter(
-    krate: &crate::Crate,
-    filter: &CrateFilter,
-    workspace_standalone_map: &HashMap<PathBuf, bool>,
-) -> bool {
+///
+/// Crates that set `skip` in their metadata never match.
+fn crate_matches_filter(facts: &CrateSelectFacts<'_>, filter: &CrateFilter) -> bool {
+    if facts.metadata.skip {
+        return false;
+    }
     match &filter.condition {
         None => true,
-        Some(cond) => evaluate_crate_select_condition(cond, krate, workspace_standalone_map),
+        Some(cond) => evaluate_crate_select_condition(cond, facts),
     }
 }
 
 /// Evaluates a [`CrateSelectCondition`] against a single crate.
 fn evaluate_crate_select_condition(
     cond: &CrateSelectCondition,
-    krate: &crate::Crate,
-    workspace_standalone_map: &HashMap<PathBuf, bool>,
+    facts: &CrateSelectFacts<'_>,
 ) -> bool {
     match cond {
-        CrateSelectCondition::Standalone => workspace_standalone_map
-            .get(&krate.workspace_manifest_dir)
-            .copied()
-            .unwrap_or(false),
+        CrateSelectCondition::Standalone => facts.standalone,
+        CrateSelectCondition::DefaultMember => facts.default_member,
+        CrateSelectCondition::Tagged(tag) => {
+            facts.metadata.tags.contains(tag)
+                || crate::targets::tags::has_tag(facts.config, facts.manifest_dir, tag)
+        }
+        CrateSelectCondition::InSet(name) => {
+            crate::targets::sets::contains(facts.config, name, facts.manifest_dir)
+        }
         CrateSelectCondition::CrateType(filter) => match filter {
-            CrateTypeFilter::Bin => krate.types.contains(&CrateType::Bin),
-            CrateTypeFilter::Lib => krate.types.contains(&CrateType::Lib),
-            CrateTypeFilter::ProcMacro => krate.types.contains(&CrateType::ProcMacro),
-            CrateTypeFilter::CDyLib => krate.types.contains(&CrateType::CDyLib),
-            CrateTypeFilter::DyLib => krate.types.contains(&CrateType::DyLib),
-            CrateTypeFilter::RLib => krate.types.contains(&CrateType::RLib),
-            CrateTypeFilter::StaticLib => krate.types.contains(&CrateType::StaticLib),
-            CrateTypeFilter::Bench => krate.types.contains(&CrateType::Bench),
-            CrateTypeFilter::Test => krate.types.contains(&CrateType::Test),
-            CrateTypeFilter::Example => krate.types.contains(&CrateType::Example),
-            CrateTypeFilter::CustomBuild => krate.types.contains(&CrateType::CustomBuild),
+            CrateTypeFilter::Bin => facts.types.contains(&CrateType::Bin),
+            CrateTypeFilter::Lib => facts.types.contains(&CrateType::Lib),
+            CrateTypeFilter::ProcMacro => facts.types.contains(&CrateType::ProcMacro),
+            CrateTypeFilter::CDyLib => facts.types.contains(&CrateType::CDyLib),
+            CrateTypeFilter::DyLib => facts.types.contains(&CrateType::DyLib),
+            CrateTypeFilter::RLib => facts.types.contains(&CrateType::RLib),
+            CrateTypeFilter::StaticLib => facts.types.contains(&CrateType::StaticLib),
+            CrateTypeFilter::Bench => facts.types.contains(&CrateType::Bench),
+            CrateTypeFilter::Test => facts.types.contains(&CrateType::Test),
+            CrateTypeFilter::Example => facts.types.contains(&CrateType::Example),
+            CrateTypeFilter::CustomBuild => facts.types.contains(&CrateType::CustomBuild),
         },
-        CrateSelectCondition::Not(inner) => {
-            !evaluate_crate_select_condition(inner, krate, workspace_standalone_map)
-        }
+        CrateSelectCondition::Not(inner) => !evaluate_crate_select_condition(inner, facts),
         CrateSelectCondition::And(conditions) => conditions
             .iter()
-            .all(|c| evaluate_crate_select_condition(c, krate, workspace_standalone_map)),
+            .all(|c| evaluate_crate_select_condition(c, facts)),
         CrateSelectCondition::Or(conditions) => conditions
             .iter()
-            .any(|c| evaluate_crate_select_condition(c, krate, workspace_standalone_map)),
+            .any(|c| evaluate_crate_select_condition(c, facts)),
     }
 }
 
@@ -160,13 +368,19 @@ fn evaluate_crate_select_condition(
 /// together with their member crates and dependency information.
 fn resolve_workspaces(
     filters: &[&WorkspaceFilter],
+    member_filters: &[&CrateFilter],
     config: &crate::Config,
+    provider: &dyn MetadataProvider,
 ) -> Result<Vec<ResolvedWorkspaceExecution>, Error> {
     // Deduplicate: a workspace is selected if it matches at least one filter.
     let selected_manifest_dirs: Vec<PathBuf> = config
         .workspaces
         .iter()
-        .filter(|w| filters.iter().any(|f| workspace_matches_filter(w, f)))
+        .filter(|w| {
+            filters
+                .iter()
+                .any(|f| workspace_matches_filter(w, f, config))
+        })
         .map(|w| w.manifest_dir.clone())
         .collect::<Vec<_>>();
 
@@ -179,16 +393,20 @@ fn resolve_workspaces(
         })
         .collect::<Result<Vec<_>, _>>()?;
 
-    resolve_workspaces_from_canonical_dirs(canonical_selected)
+    resolve_workspaces_from_canonical_dirs(canonical_selected, member_filters, config, provider)
 }
 
 /// Resolves workspace executions from an explicit list of canonical workspace
 /// directory paths, loading `cargo metadata` directly.
 ///
 /// This is the shared implementation used by both the filter-based and
-/// explicit-path-based workspace resolution paths.
+/// explicit-path-based workspace resolution paths.  Member crates that do not
+/// match any of `member_filters` (if given) are left out of the executions.
 fn resolve_workspaces_from_canonical_dirs(
     canonical_selected: Vec<PathBuf>,
+    member_filters: &[&CrateFilter],
+    config: &crate::Config,
+    provider: &dyn MetadataProvider,
 ) -> Result<Vec<ResolvedWorkspaceExecution>, Error> {
     if canonical_selected.is_empty() {
         return Ok(Vec::new());
@@ -203,26 +421,38 @@ fn resolve_workspaces_from_canonical_dirs(
     let mut package_name_to_id: HashMap<String, PackageId> = HashMap::new();
 
     for canonical_ws_dir in &canonical_selected {
-        let metadata = cargo_metadata::MetadataCommand::new()
-            .manifest_path(canonical_ws_dir.join("Cargo.toml"))
-            .no_deps()
-            .exec()
-            .map_err(|e| Error::CargoMetadataError(canonical_ws_dir.clone(), e))?;
+        let metadata = provider.metadata(canonical_ws_dir)?;
 
+        let standalone = metadata.workspace_members.len() == 1;
         let mut members: Vec<WorkspaceMemberInfo> = Vec::new();
-        for package in metadata.packages {
+        for package in &metadata.packages {
             let pkg_dir = package.manifest_path.parent().ok_or_else(|| {
                 Error::ManifestPathHasNoParentDir(package.manifest_path.clone().into_std_path_buf())
             })?;
             let canonical_pkg_dir = fs_err::canonicalize(pkg_dir).map_err(|e| {
                 Error::CouldNotDetermineCanonicalManifestPath(pkg_dir.to_path_buf().into(), e)
             })?;
+            let types = CrateType::from_package(package);
+            let package_metadata = PackageMetadata::from_package(package);
+            let facts = CrateSelectFacts {
+                manifest_dir: &canonical_pkg_dir,
+                config,
+                types: &types,
+                standalone,
+                default_member: is_default_member(&metadata, &package.id),
+                metadata: &package_metadata,
+            };
             members.push(WorkspaceMemberInfo {
                 package_id: package.id.clone(),
                 manifest_dir: canonical_pkg_dir.clone(),
+                selected: !package_metadata.skip
+                    && (member_filters.is_empty()
+                        || member_filters
+                            .iter()
+                            .any(|f| crate_matches_filter(&facts, f))),
             });
             package_name_to_id.insert(package.name.to_string(), package.id.clone());
-            all_packages.insert(package.id.clone(), package);
+            all_packages.insert(package.id.clone(), package.clone());
         }
         workspace_packages.insert(canonical_ws_dir.clone(), members);
     }
@@ -277,6 +507,9 @@ fn resolve_workspaces_from_canonical_dirs(
 )]
 pub fn resolve_explicit_workspace_targets(
     workspace_dirs: &[PathBuf],
+    member_filters: &[&CrateFilter],
+    config: &crate::Config,
+    provider: &dyn MetadataProvider,
 ) -> Result<Vec<ResolvedWorkspaceExecution>, Error> {
     let canonical: Vec<PathBuf> = workspace_dirs
         .iter()
@@ -286,7 +519,7 @@ pub fn resolve_explicit_workspace_targets(
         })
         .collect::<Result<Vec<_>, _>>()?;
 
-    resolve_workspaces_from_canonical_dirs(canonical)
+    resolve_workspaces_from_canonical_dirs(canonical, member_filters, config, provider)
 }
 
 /// Resolves crate executions from an explicit list of crate directory paths
@@ -306,6 +539,7 @@ pub fn resolve_explicit_workspace_targets(
 )]
 pub fn resolve_explicit_crate_targets(
     crate_dirs: &[PathBuf],
+    provider: &dyn MetadataProvider,
 ) -> Result<Vec<ResolvedCrateExecution>, Error> {
     if crate_dirs.is_empty() {
         return Ok(Vec::new());
@@ -328,24 +562,16 @@ pub fn resolve_explicit_crate_targets(
     let mut seen_workspace_roots: HashSet<PathBuf> = HashSet::new();
 
     for canonical_dir in &canonical_dirs {
-        let metadata = cargo_metadata::MetadataCommand::new()
-            .manifest_path(canonical_dir.join("Cargo.toml"))
-            .no_deps()
-            .exec()
-            .map_err(|e| Error::CargoMetadataError(canonical_dir.clone(), e))?;
+        let metadata = provider.metadata(canonical_dir)?;
 
-        let ws_root = metadata.workspace_root.into_std_path_buf();
+        let ws_root = metadata.workspace_root.clone().into_std_path_buf();
         let canonical_ws_root = fs_err::canonicalize(&ws_root)
             .map_err(|e| Error::CouldNotDetermineCanonicalManifestPath(ws_root.clone(), e))?;
 
         if seen_workspace_roots.insert(canonical_ws_root.clone()) {
-            let ws_metadata = cargo_metadata::MetadataCommand::new()
-                .manifest_path(canonical_ws_root.join("Cargo.toml"))
-                .no_deps()
-                .exec()
-                .map_err(|e| Error::CargoMetadataError(canonical_ws_root.clone(), e))?;
+            let ws_metadata = provider.metadata(&canonical_ws_root)?;
 
-            for package in ws_metadata.packages {
+            for package in ws_metadata.packages.iter().cloned() {
                 package_name_to_id.insert(package.name.to_string(), package.id.clone());
                 all_packages.insert(package.id.clone(), package);
             }
@@ -366,6 +592,8 @@ struct WorkspaceMemberInfo {
     package_id: PackageId,
     /// Canonical manifest directory of the member.
     manifest_dir: PathBuf,
+    /// Whether the member matches the program's `select members` filters.
+    selected: bool,
 }
 
 /// Resolves the member crates of a single workspace with their intra-workspace
@@ -380,11 +608,15 @@ fn resolve_workspace_member_crates(
         return Ok(Vec::new());
     };
 
-    let member_dirs: HashSet<&PathBuf> = members.iter().map(|m| &m.manifest_dir).collect();
+    let member_dirs: HashSet<&PathBuf> = members
+        .iter()
+        .filter(|m| m.selected)
+        .map(|m| &m.manifest_dir)
+        .collect();
 
     let mut crates: Vec<ResolvedCrateExecution> = Vec::new();
 
-    for member in members {
+    for member in members.iter().filter(|m| m.selected) {
         let package = all_packages.get(&member.package_id).ok_or_else(|| {
             Error::FoundNoPackageInCargoMetadataWithGivenManifestPath(member.manifest_dir.clone())
         })?;
@@ -416,6 +648,7 @@ fn resolve_workspace_member_crates(
         crates.push(ResolvedCrateExecution {
             manifest_dir: member.manifest_dir.clone(),
             dependencies,
+            package: Some(ResolvedPackage::from_package(package)),
         });
     }
 
@@ -490,6 +723,7 @@ fn compute_inter_workspace_deps(
 fn resolve_standalone_crates(
     filters: &[&CrateFilter],
     config: &crate::Config,
+    provider: &dyn MetadataProvider,
 ) -> Result<Vec<ResolvedCrateExecution>, Error> {
     // Build a map from workspace manifest_dir → is_standalone for filter evaluation.
     let workspace_standalone_map: HashMap<PathBuf, bool> = config
@@ -509,9 +743,18 @@ fn resolve_standalone_crates(
                 .unwrap_or(false)
         })
         .filter(|c| {
-            filters
-                .iter()
-                .any(|f| crate_matches_filter(c, f, &workspace_standalone_map))
+            let facts = CrateSelectFacts {
+                manifest_dir: &c.manifest_dir,
+                config,
+                types: &c.types,
+                standalone: workspace_standalone_map
+                    .get(&c.workspace_manifest_dir)
+                    .copied()
+                    .unwrap_or(false),
+                default_member: true,
+                metadata: &c.metadata,
+            };
+            filters.iter().any(|f| crate_matches_filter(&facts, f))
         })
         .map(|c| c.manifest_dir.clone())
         .collect();
@@ -543,13 +786,9 @@ fn resolve_standalone_crates(
         .collect();
 
     for ws_root in &unique_workspace_roots {
-        let metadata = cargo_metadata::MetadataCommand::new()
-            .manifest_path(ws_root.join("Cargo.toml"))
-            .no_deps()
-            .exec()
-            .map_err(|e| Error::CargoMetadataError(ws_root.clone(), e))?;
+        let metadata = provider.metadata(ws_root)?;
 
-        for package in metadata.packages {
+        for package in metadata.packages.iter().cloned() {
             package_name_to_id.insert(package.name.to_string(), package.id.clone());
             all_packages.insert(package.id.clone(), package);
         }
@@ -622,6 +861,7 @@ fn crate_executions_from_dirs(
         results.push(ResolvedCrateExecution {
             manifest_dir: canonical_dir.clone(),
             dependencies,
+            package: Some(ResolvedPackage::from_package(package)),
         });
     }
 
@@ -642,6 +882,7 @@ mod tests {
     use pretty_assertions::assert_eq;
 
     use super::*;
+    use crate::metadata::Subprocess;
     use crate::program::parser::parse;
     use crate::utils::execute_command;
     use tempfile::tempdir;
@@ -657,13 +898,18 @@ mod tests {
                     .join("\n")
             )
         });
-        resolve_program(&program, config).unwrap_or_else(|e| {
+        resolve_program(&program, config, &Subprocess).unwrap_or_else(|e| {
             panic!("resolve error: {e}");
         })
     }
 
     fn empty_config() -> crate::Config {
         crate::Config {
+            sort_on_save: false,
+            shared_repository: None,
+            notifications: vec![],
+            target_sets: std::collections::BTreeMap::new(),
+            tags: std::collections::BTreeMap::new(),
             workspaces: vec![],
             crates: vec![],
         }
@@ -698,9 +944,13 @@ mod tests {
 
         // Register it.
         let options = crate::Options {
+            local: false,
+            no_local: false,
+            format: crate::porcelain::OutputFormat::Text,
             command: crate::Command::Target(crate::targets::TargetParameters {
                 sub_command: crate::targets::TargetSubCommand::Add(crate::targets::AddParameters {
-                    manifest_path: ws_dir.join("Cargo.toml"),
+                    manifest_path: vec![ws_dir.join("Cargo.toml")],
+                    nested: None,
                 }),
             }),
         };
@@ -745,10 +995,14 @@ mod tests {
         // Register both.
         for manifest in [standalone_dir.join("Cargo.toml"), ws_dir.join("Cargo.toml")] {
             let options = crate::Options {
+                local: false,
+                no_local: false,
+                format: crate::porcelain::OutputFormat::Text,
                 command: crate::Command::Target(crate::targets::TargetParameters {
                     sub_command: crate::targets::TargetSubCommand::Add(
                         crate::targets::AddParameters {
-                            manifest_path: manifest,
+                            manifest_path: vec![manifest],
+                            nested: None,
                         },
                     ),
                 }),
@@ -796,9 +1050,13 @@ mod tests {
         }
 
         let options = crate::Options {
+            local: false,
+            no_local: false,
+            format: crate::porcelain::OutputFormat::Text,
             command: crate::Command::Target(crate::targets::TargetParameters {
                 sub_command: crate::targets::TargetSubCommand::Add(crate::targets::AddParameters {
-                    manifest_path: ws_dir.join("Cargo.toml"),
+                    manifest_path: vec![ws_dir.join("Cargo.toml")],
+                    nested: None,
                 }),
             }),
         };
@@ -816,6 +1074,245 @@ mod tests {
             let canonical = fs_err::canonicalize(&member.manifest_dir)?;
             assert_eq!(member.manifest_dir, canonical);
         }
+        let mut packages: Vec<String> = resolved.workspace_executions[0]
+            .member_crates
+            .iter()
+            .filter_map(|member| member.package.as_ref().map(ToString::to_string))
+            .collect();
+        packages.sort();
+        assert_eq!(packages, ["crate_a 0.1.0", "crate_b 0.1.0"]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn select_members_where_default_member() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let environment = crate::Environment::mock(&temp_dir)?;
+        let ws_dir = temp_dir.path().join("multi");
+        fs_err::create_dir_all(&ws_dir)?;
+        fs_err::write(
+            ws_dir.join("Cargo.toml"),
+            "[workspace]\nmembers = [\"core\", \"extra\"]\ndefault-members = [\"core\"]\nresolver = \"2\"\n",
+        )?;
+        for name in ["core", "extra"] {
+            let mut cmd = std::process::Command::new("cargo");
+            cmd.current_dir(&ws_dir).args(["new", "--lib", name]);
+            execute_command(&mut cmd, &environment, &ws_dir)?;
+        }
+        let options = crate::Options {
+            local: false,
+            no_local: false,
+            format: crate::porcelain::OutputFormat::Text,
+            command: crate::Command::Target(crate::targets::TargetParameters {
+                sub_command: crate::targets::TargetSubCommand::Add(crate::targets::AddParameters {
+                    manifest_path: vec![ws_dir.join("Cargo.toml")],
+                    nested: None,
+                }),
+            }),
+        };
+        crate::run_app(options, environment.clone()).await?;
+        let config = crate::Config::load(&environment)?;
+
+        let all = resolve_ok("select workspaces;", &config);
+        assert_eq!(all.workspace_executions[0].member_crates.len(), 2);
+
+        let default = resolve_ok(
+            "select workspaces; select members where default_member;",
+            &config,
+        );
+        let members = &default.workspace_executions[0].member_crates;
+        assert_eq!(members.len(), 1);
+        assert_eq!(
+            members[0].manifest_dir,
+            fs_err::canonicalize(ws_dir.join("core"))?
+        );
+
+        let non_default = resolve_ok(
+            "select workspaces; select members where !default_member;",
+            &config,
+        );
+        let members = &non_default.workspace_executions[0].member_crates;
+        assert_eq!(members.len(), 1);
+        assert_eq!(
+            members[0].manifest_dir,
+            fs_err::canonicalize(ws_dir.join("extra"))?
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn package_metadata_tags_and_skip_members() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let environment = crate::Environment::mock(&temp_dir)?;
+        let ws_dir = temp_dir.path().join("multi");
+        fs_err::create_dir_all(&ws_dir)?;
+        fs_err::write(
+            ws_dir.join("Cargo.toml"),
+            "[workspace]\nmembers = [\"core\", \"tool\", \"legacy\"]\nresolver = \"2\"\n",
+        )?;
+        let metadata = [
+            ("core", ""),
+            (
+                "tool",
+                "tags = [\"cli\"]\ntoolchain = \"nightly\"\nenv = { RUST_LOG = \"debug\" }\n",
+            ),
+            ("legacy", "skip = true\n"),
+        ];
+        for (name, table) in metadata {
+            let mut cmd = std::process::Command::new("cargo");
+            cmd.current_dir(&ws_dir).args(["new", "--lib", name]);
+            execute_command(&mut cmd, &environment, &ws_dir)?;
+            let manifest = ws_dir.join(name).join("Cargo.toml");
+            let content = fs_err::read_to_string(&manifest)?;
+            fs_err::write(
+                &manifest,
+                format!("{content}\n[package.metadata.cargo-for-each]\n{table}"),
+            )?;
+        }
+        let mut config = crate::Config::default();
+        crate::targets::add_manifest(&mut config, &ws_dir.join("Cargo.toml"))?;
+        let tool = config
+            .crates
+            .iter()
+            .find(|c| c.manifest_dir.ends_with("tool"))
+            .ok_or("tool crate was not registered")?;
+        assert_eq!(
+            tool.metadata.env_vars(),
+            [
+                ("RUST_LOG".to_owned(), "debug".to_owned()),
+                ("RUSTUP_TOOLCHAIN".to_owned(), "nightly".to_owned())
+            ]
+        );
+
+        let member_names = |src: &str| -> Vec<String> {
+            resolve_ok(src, &config).workspace_executions[0]
+                .member_crates
+                .iter()
+                .filter_map(|c| c.manifest_dir.file_name())
+                .map(|name| name.to_string_lossy().into_owned())
+                .collect()
+        };
+        assert_eq!(member_names("select workspaces;"), ["core", "tool"]);
+        assert_eq!(
+            member_names(r#"select workspaces; select members where tagged "cli";"#),
+            ["tool"]
+        );
+        assert_eq!(
+            member_names("select workspaces; select members where !tagged \"cli\";"),
+            ["core"]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn unregistered_path_dependencies_are_found() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let environment = crate::Environment::mock(&temp_dir)?;
+        for name in ["app", "helper"] {
+            let dir = temp_dir.path().join(name);
+            fs_err::create_dir_all(&dir)?;
+            let mut cmd = std::process::Command::new("cargo");
+            cmd.current_dir(&dir)
+                .args(["init", "--name", name, "--lib"]);
+            execute_command(&mut cmd, &environment, &dir)?;
+        }
+        let app_dir = temp_dir.path().join("app");
+        let manifest = fs_err::read_to_string(app_dir.join("Cargo.toml"))?;
+        fs_err::write(
+            app_dir.join("Cargo.toml"),
+            manifest.replace(
+                "[dependencies]",
+                "[dependencies]\nhelper = { path = \"../helper\" }",
+            ),
+        )?;
+
+        let mut config = empty_config();
+        crate::targets::add_manifest(&mut config, &app_dir.join("Cargo.toml"))?;
+        let resolved = resolve_ok("select crates;", &config);
+        assert_eq!(resolved.crate_executions.len(), 1);
+        let helper_dir = fs_err::canonicalize(temp_dir.path().join("helper"))?;
+        assert_eq!(
+            unregistered_path_dependencies(&resolved, &config, &Subprocess)?,
+            std::slice::from_ref(&helper_dir)
+        );
+
+        crate::targets::add_manifest(&mut config, &helper_dir.join("Cargo.toml"))?;
+        let resolved = resolve_ok("select crates;", &config);
+        assert_eq!(resolved.crate_executions.len(), 2);
+        assert!(
+            unregistered_path_dependencies(&resolved, &config, &Subprocess)?.is_empty(),
+            "all path dependencies are registered"
+        );
+        let app = resolved
+            .crate_executions
+            .iter()
+            .find(|c| c.manifest_dir == fs_err::canonicalize(&app_dir).unwrap_or_default());
+        assert_eq!(app.map(|c| c.dependencies.clone()), Some(vec![helper_dir]));
+        Ok(())
+    }
+
+    /// `cargo metadata --no-deps` output for a standalone crate `name` in
+    /// `dir` with path dependencies on `dependencies`.
+    fn crate_metadata(
+        dir: &Path,
+        name: &str,
+        dependencies: &[&str],
+    ) -> Result<cargo_metadata::Metadata, serde_json::Error> {
+        let id = format!("path+file://{}#{name}@0.1.0", dir.display());
+        serde_json::from_value(serde_json::json!({
+            "packages": [{
+                "name": name,
+                "version": "0.1.0",
+                "id": id,
+                "dependencies": dependencies.iter().map(|dep| serde_json::json!({
+                    "name": dep,
+                    "req": "*",
+                    "kind": null,
+                    "optional": false,
+                    "uses_default_features": true,
+                    "features": [],
+                    "target": null,
+                })).collect::<Vec<_>>(),
+                "targets": [],
+                "features": {},
+                "manifest_path": dir.join("Cargo.toml"),
+            }],
+            "workspace_members": [id],
+            "resolve": null,
+            "workspace_root": dir,
+            "target_directory": dir.join("target"),
+            "version": 1,
+        }))
+    }
+
+    #[test]
+    fn explicit_crates_resolve_from_injected_metadata() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempdir()?;
+        let app = temp_dir.path().join("app");
+        let helper = temp_dir.path().join("helper");
+        fs_err::create_dir_all(&app)?;
+        fs_err::create_dir_all(&helper)?;
+        let app = fs_err::canonicalize(app)?;
+        let helper = fs_err::canonicalize(helper)?;
+        let fixtures = crate::metadata::Fixtures::default()
+            .with(&app, crate_metadata(&app, "app", &["helper"])?)
+            .with(&helper, crate_metadata(&helper, "helper", &[])?);
+
+        let resolved = resolve_explicit_crate_targets(&[app.clone(), helper.clone()], &fixtures)?;
+        assert_eq!(
+            resolved
+                .iter()
+                .map(|c| (c.manifest_dir.clone(), c.dependencies.clone()))
+                .collect::<Vec<_>>(),
+            [(app, vec![helper.clone()]), (helper, vec![])]
+        );
+        assert_eq!(
+            resolved.first().and_then(|c| c.package.clone()),
+            Some(ResolvedPackage {
+                name: "app".to_owned(),
+                version: "0.1.0".to_owned(),
+            })
+        );
         Ok(())
     }
 }
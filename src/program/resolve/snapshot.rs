@@ -46,4 +46,39 @@ pub struct ResolvedCrateExecution {
     /// Other crates (by their canonical manifest dir) in the same set that must
     /// complete before this one.  An empty vec means no tracked dependencies.
     pub dependencies: Vec<PathBuf>,
+    /// Name and version of the crate's package at task-creation time.
+    ///
+    /// `None` for tasks created before this was recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub package: Option<ResolvedPackage>,
+}
+
+/// The package of a crate as `cargo metadata` reported it during resolution.
+///
+/// Recorded so that labels and reports can name crates without running
+/// `cargo metadata` again.  The version may be outdated once a step bumped it;
+/// the `crate_version` variable looks it up when a step runs instead.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ResolvedPackage {
+    /// The package name.
+    pub name: String,
+    /// The package version.
+    pub version: String,
+}
+
+impl ResolvedPackage {
+    /// Returns the name and version of `package`.
+    #[must_use]
+    pub fn from_package(package: &cargo_metadata::Package) -> Self {
+        Self {
+            name: package.name.to_string(),
+            version: package.version.to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for ResolvedPackage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.name, self.version)
+    }
 }
@@ -0,0 +1,339 @@
+//! Programs and templates mirrored from a shared git repository.
+//!
+//! A team keeps its `.cfe` programs and its templates in one git repository,
+//! in the directories `programs/` and `templates/`.  `sync` clones that
+//! repository into the state directory and mirrors both directories into the
+//! configuration directory, so `task create --program` and `templates` work
+//! with the same files on every machine.  `sync --push` additionally commits
+//! local changes to the repository and pushes them.
+//!
+//! The checksum of every file as of the last sync is stored in `shared.toml`.
+//! A file that only changed on one side since then is taken from that side;
+//! a file that changed on both sides is a conflict and is left alone unless
+//! `--force` is given, in which case the repository wins.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+
+use crate::error::Error;
+use crate::tasks::program_source::checksum;
+
+/// Directories mirrored between the repository and the configuration directory.
+const SHARED_DIRS: [&str; 2] = ["programs", "templates"];
+
+/// Name of the file recording the checksums as of the last sync.
+const BASELINE_FILE_NAME: &str = "shared.toml";
+
+/// Parameters for the `sync` subcommand.
+#[derive(clap::Parser, Debug, Clone)]
+pub struct SyncParameters {
+    /// URL of the shared git repository; remembered for later calls.
+    #[clap(long)]
+    pub repository: Option<String>,
+    /// Also commit local changes to the repository and push them.
+    #[clap(long)]
+    pub push: bool,
+    /// Replace conflicting local files with the version from the repository.
+    #[clap(long)]
+    pub force: bool,
+}
+
+/// Checksums of the shared files as of the last sync, keyed by their path
+/// relative to the repository root.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct Baseline {
+    /// Checksum of each file.
+    #[serde(default)]
+    files: BTreeMap<String, String>,
+}
+
+/// How a shared file changed since the last sync.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Change {
+    /// The local file and the repository agree.
+    Same,
+    /// Only the repository changed; the local file is replaced or removed.
+    Pull,
+    /// Only the local file changed; it is published with `--push`.
+    Push,
+    /// Both sides changed.
+    Conflict,
+}
+
+/// Classifies a file from its checksums locally, in the repository and as of
+/// the last sync; `None` means the file does not exist there.
+fn classify(local: Option<&str>, remote: Option<&str>, base: Option<&str>) -> Change {
+    if local == remote {
+        Change::Same
+    } else if local == base {
+        Change::Pull
+    } else if remote == base {
+        Change::Push
+    } else {
+        Change::Conflict
+    }
+}
+
+/// Returns the directory the shared repository is cloned into.
+fn clone_dir(environment: &crate::Environment) -> PathBuf {
+    environment
+        .state_dir
+        .join("cargo-for-each")
+        .join("shared-repository")
+}
+
+/// Runs `git` with `args` in `dir`.
+///
+/// # Errors
+///
+/// Returns an error if git cannot be started or fails.
+fn git(dir: &Path, args: &[&str]) -> Result<(), Error> {
+    let mut command = Command::new("git");
+    command.args(args).current_dir(dir).stdin(Stdio::null());
+    let status = command
+        .status()
+        .map_err(|e| Error::CommandExecutionFailed(format!("{command:?}"), dir.to_path_buf(), e))?;
+    if !status.success() {
+        return Err(Error::CommandFailed(
+            format!("git {}", args.join(" ")),
+            dir.to_path_buf(),
+            status.code().unwrap_or(-1),
+        ));
+    }
+    Ok(())
+}
+
+/// Clones `repository` into `dir` or brings an existing clone up to date.
+///
+/// The clone is private to this tool, so leftovers of an earlier sync that
+/// failed halfway, such as a commit that could not be pushed, are discarded.
+///
+/// # Errors
+///
+/// Returns an error if a git command fails.
+fn update_clone(repository: &str, dir: &Path) -> Result<(), Error> {
+    if dir.join(".git").exists() {
+        git(dir, &["remote", "set-url", "origin", repository])?;
+        git(dir, &["fetch", "--quiet", "origin"])?;
+        git(dir, &["reset", "--quiet", "--hard", "@{upstream}"])?;
+        git(dir, &["clean", "--quiet", "--force", "-d"])
+    } else {
+        let parent = dir.parent().unwrap_or(dir);
+        fs_err::create_dir_all(parent)
+            .map_err(|e| Error::CouldNotWriteSharedFile(parent.to_path_buf(), e))?;
+        let dir_arg = dir.to_string_lossy();
+        git(parent, &["clone", "--quiet", repository, &dir_arg])
+    }
+}
+
+/// Reads the shared files below `root`, keyed by their relative path.
+///
+/// # Errors
+///
+/// Returns an error if a directory or file cannot be read.
+fn read_files(root: &Path) -> Result<BTreeMap<String, String>, Error> {
+    let mut files = BTreeMap::new();
+    for shared_dir in SHARED_DIRS {
+        let dir = root.join(shared_dir);
+        if !dir.exists() {
+            continue;
+        }
+        for entry in
+            fs_err::read_dir(&dir).map_err(|e| Error::CouldNotReadSharedFile(dir.clone(), e))?
+        {
+            let path = entry
+                .map_err(|e| Error::CouldNotReadSharedFile(dir.clone(), e))?
+                .path();
+            let Some(file_name) = path.file_name().and_then(|n| n.to_str()) else {
+                continue;
+            };
+            if !path.is_file() || file_name.starts_with('.') {
+                continue;
+            }
+            let content = fs_err::read_to_string(&path)
+                .map_err(|e| Error::CouldNotReadSharedFile(path.clone(), e))?;
+            files.insert(format!("{shared_dir}/{file_name}"), content);
+        }
+    }
+    Ok(files)
+}
+
+/// Writes `content` to `name` below `root`, or removes the file if `content`
+/// is `None`.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be written or removed.
+fn write_file(root: &Path, name: &str, content: Option<&String>) -> Result<(), Error> {
+    let path = root.join(name);
+    match content {
+        Some(content) => {
+            if let Some(parent) = path.parent() {
+                fs_err::create_dir_all(parent)
+                    .map_err(|e| Error::CouldNotWriteSharedFile(parent.to_path_buf(), e))?;
+            }
+            fs_err::write(&path, content).map_err(|e| Error::CouldNotWriteSharedFile(path, e))
+        }
+        None if path.exists() => {
+            fs_err::remove_file(&path).map_err(|e| Error::CouldNotWriteSharedFile(path, e))
+        }
+        None => Ok(()),
+    }
+}
+
+/// Loads the baseline from the configuration directory.
+///
+/// # Errors
+///
+/// Returns an error if the file exists but cannot be read or parsed.
+fn load_baseline(path: &Path) -> Result<Baseline, Error> {
+    if !path.exists() {
+        return Ok(Baseline::default());
+    }
+    let content = fs_err::read_to_string(path)
+        .map_err(|e| Error::CouldNotReadSharedFile(path.to_path_buf(), e))?;
+    toml::from_str(&content).map_err(|e| Error::CouldNotParseSharedBaseline(path.to_path_buf(), e))
+}
+
+/// Stores the baseline in the configuration directory.
+///
+/// # Errors
+///
+/// Returns an error if the baseline cannot be serialized or written.
+fn save_baseline(path: &Path, baseline: &Baseline) -> Result<(), Error> {
+    let content = toml::to_string(baseline).map_err(Error::CouldNotSerializeSharedBaseline)?;
+    fs_err::write(path, content).map_err(|e| Error::CouldNotWriteSharedFile(path.to_path_buf(), e))
+}
+
+/// Mirrors the shared programs and templates, and publishes local changes
+/// with `--push`.
+///
+/// # Errors
+///
+/// Returns an error if no repository is configured, if a git command fails,
+/// if files cannot be read or written, or if conflicts remain.
+#[instrument]
+#[expect(clippy::print_stdout, reason = "This is part of the UI, not logging")]
+pub async fn sync_command(
+    params: SyncParameters,
+    environment: crate::Environment,
+) -> Result<(), Error> {
+    let _lock = crate::config_lock::acquire(&environment).await?;
+    let mut config = crate::Config::load(&environment)?;
+    if let Some(repository) = &params.repository
+        && config.shared_repository.as_ref() != Some(repository)
+    {
+        config.shared_repository = Some(repository.clone());
+        config.save(&environment)?;
+    }
+    let repository = config.shared_repository.ok_or(Error::NoSharedRepository)?;
+
+    let repo_dir = clone_dir(&environment);
+    update_clone(&repository, &repo_dir)?;
+
+    let local_dir = crate::config_dir_path(&environment)?;
+    let baseline_path = local_dir.join(BASELINE_FILE_NAME);
+    let baseline = load_baseline(&baseline_path)?;
+    let local = read_files(&local_dir)?;
+    let remote = read_files(&repo_dir)?;
+
+    let names: BTreeSet<&String> = local
+        .keys()
+        .chain(remote.keys())
+        .chain(baseline.files.keys())
+        .collect();
+    let mut new_baseline = Baseline::default();
+    let mut conflicts = 0_usize;
+    let mut pushed = false;
+    for name in names {
+        let local_content = local.get(name);
+        let remote_content = remote.get(name);
+        let local_sum = local_content.map(|c| checksum(c));
+        let remote_sum = remote_content.map(|c| checksum(c));
+        let base_sum = baseline.files.get(name);
+        let change = match classify(
+            local_sum.as_deref(),
+            remote_sum.as_deref(),
+            base_sum.map(String::as_str),
+        ) {
+            Change::Conflict if params.force => Change::Pull,
+            change => change,
+        };
+        let synced_sum = match change {
+            Change::Same => remote_sum,
+            Change::Pull => {
+                write_file(&local_dir, name, remote_content)?;
+                let action = if remote_content.is_some() {
+                    "updated"
+                } else {
+                    "removed"
+                };
+                println!("{action} {name}");
+                remote_sum
+            }
+            Change::Push if params.push => {
+                write_file(&repo_dir, name, local_content)?;
+                println!("publishing {name}");
+                pushed = true;
+                local_sum
+            }
+            Change::Push => {
+                println!("changed locally: {name} (publish with --push)");
+                base_sum.cloned()
+            }
+            Change::Conflict => {
+                println!("conflict: {name} changed locally and in the repository");
+                conflicts = conflicts.saturating_add(1);
+                base_sum.cloned()
+            }
+        };
+        if let Some(sum) = synced_sum {
+            new_baseline.files.insert(name.clone(), sum);
+        }
+    }
+
+    if pushed {
+        git(&repo_dir, &["add", "--all"])?;
+        git(
+            &repo_dir,
+            &[
+                "commit",
+                "--quiet",
+                "-m",
+                "Update shared programs and templates",
+            ],
+        )?;
+        git(&repo_dir, &["push", "--quiet"])?;
+    }
+    save_baseline(&baseline_path, &new_baseline)?;
+
+    if conflicts > 0 {
+        return Err(Error::SharedFilesConflict(conflicts));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::{Change, classify};
+
+    #[test]
+    fn classify_by_side_that_changed() {
+        assert_eq!(classify(Some("a"), Some("a"), None), Change::Same);
+        assert_eq!(classify(None, None, Some("a")), Change::Same);
+        assert_eq!(classify(Some("a"), Some("b"), Some("a")), Change::Pull);
+        assert_eq!(classify(None, Some("b"), None), Change::Pull);
+        assert_eq!(classify(Some("a"), None, Some("a")), Change::Pull);
+        assert_eq!(classify(Some("b"), Some("a"), Some("a")), Change::Push);
+        assert_eq!(classify(Some("b"), None, None), Change::Push);
+        assert_eq!(classify(None, Some("a"), Some("a")), Change::Push);
+        assert_eq!(classify(Some("b"), Some("c"), Some("a")), Change::Conflict);
+        assert_eq!(classify(Some("b"), Some("c"), None), Change::Conflict);
+    }
+}
@@ -1,6 +1,6 @@
 //! This module defines the core data structures and traits related to targets (workspaces and crates).
 //! It includes extensions for `cargo_metadata` and the `Target` struct itself.
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::path::{Path, PathBuf};
 
 use cargo_metadata::PackageId;
@@ -10,6 +10,12 @@ use std::collections::HashMap;
 use crate::{Crate, Workspace};
 use tracing::instrument;
 
+pub mod dependents;
+pub mod discover;
+pub mod git;
+pub mod sets;
+pub mod tags;
+
 /// The target sub command
 #[derive(clap::Parser, Debug, Clone)]
 pub enum TargetSubCommand {
@@ -20,7 +26,15 @@ pub enum TargetSubCommand {
     /// Remove a workspace or crate managed by cargo-for-each.
     Remove(RemoveParameters),
     /// Refresh the list of workspaces and crates managed by cargo-for-each, removing deleted entries and adding new ones.
-    Refresh,
+    Refresh(RefreshParameters),
+    /// Print counts of the registered workspaces and crates.
+    Stats,
+    /// Add all workspaces and crates whose manifests are found below a directory.
+    Discover(discover::Parameters),
+    /// Manage named sets of hand-picked workspaces and crates.
+    Set(sets::Parameters),
+    /// Manage free-form tags of workspaces and crates.
+    Tag(tags::Parameters),
 }
 
 /// Parameters for target subcommand
@@ -40,10 +54,11 @@ pub struct TargetParameters {
 pub async fn target_command(
     target_parameters: TargetParameters,
     environment: crate::Environment,
+    run_options: crate::RunOptions,
 ) -> Result<(), crate::error::Error> {
     match target_parameters.sub_command {
         TargetSubCommand::List(list_parameters) => {
-            list_command(list_parameters, environment).await?;
+            list_command(list_parameters, environment, run_options).await?;
         }
         TargetSubCommand::Add(add_parameters) => {
             add_command(add_parameters, environment).await?;
@@ -51,8 +66,20 @@ pub async fn target_command(
         TargetSubCommand::Remove(remove_parameters) => {
             remove_command(remove_parameters, environment).await?;
         }
-        TargetSubCommand::Refresh => {
-            refresh_command(environment).await?;
+        TargetSubCommand::Refresh(refresh_parameters) => {
+            refresh_command(refresh_parameters, environment).await?;
+        }
+        TargetSubCommand::Stats => {
+            stats_command(environment, run_options).await?;
+        }
+        TargetSubCommand::Discover(discover_parameters) => {
+            discover::command(discover_parameters, environment).await?;
+        }
+        TargetSubCommand::Set(set_parameters) => {
+            sets::command(set_parameters, environment, run_options).await?;
+        }
+        TargetSubCommand::Tag(tag_parameters) => {
+            tags::command(tag_parameters, environment).await?;
         }
     }
     Ok(())
@@ -67,6 +94,115 @@ pub struct CrateFilterParameters {
     /// only list crates that are standalone or not
     #[clap(long)]
     pub standalone: Option<bool>,
+    /// only list crates whose package name matches this regular expression
+    /// (anywhere in the name, unless anchored with `^` and `$`)
+    #[clap(long)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name_regex: Option<String>,
+    /// only list crates in this directory or below it (repeatable, any of them)
+    #[clap(long)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub path_prefix: Vec<PathBuf>,
+    /// do not list crates in this directory or below it (repeatable)
+    #[clap(long)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude_path: Vec<PathBuf>,
+    /// only list crates depending on the package with this name
+    #[clap(long, value_name = "PACKAGE")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub depends_on: Option<String>,
+    /// with --depends-on, only count versions of the package matching this
+    /// requirement, e.g. `^1.0`
+    #[clap(long, requires = "depends_on")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version_req: Option<String>,
+    /// with --depends-on, also list crates depending on the package through
+    /// other packages
+    #[clap(long, requires = "depends_on")]
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub transitive: bool,
+    /// filters on the git checkout of the crate
+    #[clap(flatten)]
+    #[serde(flatten)]
+    pub git: git::FilterParameters,
+    /// filters on the tags of the crate
+    #[clap(flatten)]
+    #[serde(flatten)]
+    pub tags: tags::TagFilterParameters,
+}
+
+/// The name and path filters of [`CrateFilterParameters`], prepared for
+/// matching
+#[derive(Debug, Clone)]
+pub struct NameAndPathFilter {
+    /// the compiled `--name-regex`
+    name: Option<regex_automata::meta::Regex>,
+    /// the absolute `--path-prefix` directories
+    prefixes: Vec<PathBuf>,
+    /// the absolute `--exclude-path` directories
+    excluded: Vec<PathBuf>,
+}
+
+impl NameAndPathFilter {
+    /// prepares the filters of `params`; relative paths are relative to the
+    /// current directory
+    ///
+    /// # Errors
+    ///
+    /// Returns [`crate::error::Error::InvalidNameRegex`] if the regular
+    /// expression is invalid, or an error if a relative path cannot be made
+    /// absolute.
+    pub fn new(params: &CrateFilterParameters) -> Result<Self, crate::error::Error> {
+        let name = params
+            .name_regex
+            .as_deref()
+            .map(|pattern| {
+                regex_automata::meta::Regex::new(pattern).map_err(|e| {
+                    crate::error::Error::InvalidNameRegex(pattern.to_owned(), e.to_string())
+                })
+            })
+            .transpose()?;
+        let absolute = |dirs: &[PathBuf]| {
+            dirs.iter()
+                .map(|dir| {
+                    let dir = std::path::absolute(dir).map_err(crate::error::Error::IoError)?;
+                    Ok(fs_err::canonicalize(&dir).unwrap_or(dir))
+                })
+                .collect::<Result<Vec<_>, crate::error::Error>>()
+        };
+        Ok(Self {
+            name,
+            prefixes: absolute(&params.path_prefix)?,
+            excluded: absolute(&params.exclude_path)?,
+        })
+    }
+
+    /// returns `true` if the crate in `manifest_dir` with the recorded
+    /// package name `name` passes the filters; without a recorded name it is
+    /// read from the manifest with `--name-regex`, and crates whose name
+    /// cannot be read do not match it
+    #[must_use]
+    pub fn matches(&self, manifest_dir: &Path, name: Option<&str>) -> bool {
+        (self.prefixes.is_empty() || self.prefixes.iter().any(|p| manifest_dir.starts_with(p)))
+            && !self.excluded.iter().any(|p| manifest_dir.starts_with(p))
+            && self.name.as_ref().is_none_or(|regex| match name {
+                Some(name) => regex.is_match(name),
+                None => package_name(manifest_dir).is_some_and(|name| regex.is_match(&name)),
+            })
+    }
+}
+
+/// Returns the package name from the `Cargo.toml` in `manifest_dir`, if it
+/// can be read.
+#[must_use]
+pub fn package_name(manifest_dir: &Path) -> Option<String> {
+    let content = fs_err::read_to_string(manifest_dir.join("Cargo.toml")).ok()?;
+    let manifest: toml::Table = toml::from_str(&content).ok()?;
+    manifest
+        .get("package")?
+        .get("name")?
+        .as_str()
+        .map(str::to_owned)
 }
 
 /// Parameters for filtering workspaces
@@ -75,6 +211,14 @@ pub struct WorkspaceFilterParameters {
     /// only list multi-crate workspaces
     #[clap(long)]
     pub no_standalone: bool,
+    /// filters on the git checkout of the workspace
+    #[clap(flatten)]
+    #[serde(flatten)]
+    pub git: git::FilterParameters,
+    /// filters on the tags of the workspace
+    #[clap(flatten)]
+    #[serde(flatten)]
+    pub tags: tags::TagFilterParameters,
 }
 
 /// The type of object to filter
@@ -89,38 +233,135 @@ pub enum TargetFilter {
 /// Parameters for list subcommand
 #[derive(clap::Parser, Debug, Clone)]
 pub struct ListParameters {
+    /// print stable tab-separated output for scripts (see the porcelain module docs)
+    #[clap(
+        long,
+        global = true,
+        value_enum,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "v1"
+    )]
+    pub porcelain: Option<crate::porcelain::Version>,
     /// the type of object to list
     #[clap(subcommand)]
     pub target_filter: TargetFilter,
 }
 
+/// a registered workspace in the JSON output of `target list workspaces`
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct WorkspaceView {
+    /// the directory that contains the workspace Cargo.toml file
+    pub manifest_dir: PathBuf,
+    /// is this a standalone crate workspace
+    pub standalone: bool,
+    /// the git checkout the workspace lives in, if known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repository: Option<git::Repository>,
+}
+
+/// a registered crate in the JSON output of `target list crates`
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct CrateView {
+    /// the directory that contains the crate Cargo.toml file
+    pub manifest_dir: PathBuf,
+    /// the directory that contains the workspace Cargo.toml file for this crate
+    pub workspace_manifest_dir: PathBuf,
+    /// the types of this crate, as accepted by `--type`
+    pub types: Vec<String>,
+    /// the package name, if recorded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// the package version, if recorded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+}
+
+impl From<&crate::Crate> for CrateView {
+    fn from(krate: &crate::Crate) -> Self {
+        Self {
+            manifest_dir: krate.manifest_dir.clone(),
+            workspace_manifest_dir: krate.workspace_manifest_dir.clone(),
+            types: krate
+                .types
+                .iter()
+                .filter_map(clap::ValueEnum::to_possible_value)
+                .map(|value| value.get_name().to_owned())
+                .collect(),
+            name: krate.name.clone(),
+            version: krate.version.clone(),
+        }
+    }
+}
+
 /// implementation of the list subcommand
 ///
 /// # Errors
 ///
 /// This command can fail if the configuration file cannot be loaded or parsed.
 #[instrument]
+#[expect(clippy::print_stdout, reason = "This is part of the UI, not logging")]
 pub async fn list_command(
     list_parameters: ListParameters,
     environment: crate::Environment,
+    run_options: crate::RunOptions,
 ) -> Result<(), crate::error::Error> {
+    let json =
+        crate::porcelain::is_json(run_options.format, list_parameters.porcelain, "target list")?;
     #[expect(clippy::print_stderr, reason = "This is part of the UI, not logging")]
     let Ok(config) = crate::Config::load(&environment) else {
-        eprintln!("No config file found, nothing to list");
+        if json {
+            println!("[]");
+        } else {
+            eprintln!("No config file found, nothing to list");
+        }
         return Ok(());
     };
-    #[expect(clippy::print_stdout, reason = "This is part of the UI, not logging")]
     match list_parameters.target_filter {
         TargetFilter::Workspaces(params) => {
-            for workspace in config.workspaces {
-                if params.no_standalone && workspace.is_standalone {
+            let workspaces = config
+                .workspaces
+                .iter()
+                .filter(|workspace| !(params.no_standalone && workspace.is_standalone))
+                .filter(|workspace| params.git.matches(&workspace.manifest_dir))
+                .filter(|workspace| params.tags.matches(&config, &workspace.manifest_dir))
+                .cloned();
+            if json {
+                let views: Vec<WorkspaceView> = workspaces
+                    .map(|workspace| WorkspaceView {
+                        manifest_dir: workspace.manifest_dir,
+                        standalone: workspace.is_standalone,
+                        repository: workspace.repository,
+                    })
+                    .collect();
+                println!("{}", crate::porcelain::json(&views)?);
+                return Ok(());
+            }
+            for workspace in workspaces {
+                if list_parameters.porcelain.is_some() {
+                    println!(
+                        "{}",
+                        crate::porcelain::record(&[
+                            "workspace",
+                            &workspace.manifest_dir.to_string_lossy(),
+                            &workspace.is_standalone.to_string(),
+                        ])
+                    );
                     continue;
                 }
-                println!(
-                    "{} (standalone: {})",
-                    workspace.manifest_dir.display(),
-                    workspace.is_standalone
-                );
+                match &workspace.repository {
+                    Some(repository) => println!(
+                        "{} (standalone: {}, repository: {})",
+                        workspace.manifest_dir.display(),
+                        workspace.is_standalone,
+                        repository.root.display()
+                    ),
+                    None => println!(
+                        "{} (standalone: {})",
+                        workspace.manifest_dir.display(),
+                        workspace.is_standalone
+                    ),
+                }
             }
         }
         TargetFilter::Crates(params) => {
@@ -129,29 +370,79 @@ pub async fn list_command(
                 .iter()
                 .map(|w| (w.manifest_dir.clone(), w.is_standalone))
                 .collect();
+            let name_and_path = NameAndPathFilter::new(&params)?;
+            let dependency_filter = params
+                .depends_on
+                .as_deref()
+                .map(|name| {
+                    dependents::DependencyFilter::new(
+                        name,
+                        params.version_req.as_deref(),
+                        params.transitive,
+                    )
+                })
+                .transpose()?;
+            let metadata_provider = crate::metadata::Cached::new(crate::metadata::WithDependencies);
 
-            for krate in config.crates {
-                if let Some(crate_type) = &params.r#type
-                    && !krate.types.contains(crate_type)
-                {
-                    continue;
-                }
-                if let Some(standalone) = params.standalone
-                    && workspace_standalone_map
-                        .get(&krate.workspace_manifest_dir)
-                        .is_none_or(|&is_standalone| is_standalone != standalone)
-                {
+            let crates = config.crates.iter().filter(|krate| {
+                params
+                    .r#type
+                    .as_ref()
+                    .is_none_or(|crate_type| krate.types.contains(crate_type))
+                    && params.standalone.is_none_or(|standalone| {
+                        workspace_standalone_map
+                            .get(&krate.workspace_manifest_dir)
+                            .is_some_and(|&is_standalone| is_standalone == standalone)
+                    })
+                    && name_and_path.matches(&krate.manifest_dir, krate.name.as_deref())
+                    && params.git.matches(&krate.manifest_dir)
+                    && params.tags.matches(&config, &krate.manifest_dir)
+                    && dependency_filter.as_ref().is_none_or(|filter| {
+                        match crate::metadata::MetadataProvider::metadata(
+                            &metadata_provider,
+                            &krate.workspace_manifest_dir,
+                        ) {
+                            Ok(metadata) => filter.matches(&metadata, &krate.manifest_dir),
+                            Err(e) => {
+                                tracing::warn!("Skipping {}: {e}", krate.manifest_dir.display());
+                                false
+                            }
+                        }
+                    })
+            });
+            if json {
+                let views: Vec<CrateView> = crates.map(CrateView::from).collect();
+                println!("{}", crate::porcelain::json(&views)?);
+                return Ok(());
+            }
+            for krate in crates {
+                if list_parameters.porcelain.is_some() {
+                    let types = CrateView::from(krate).types.join(",");
+                    println!(
+                        "{}",
+                        crate::porcelain::record(&[
+                            "crate",
+                            &krate.manifest_dir.to_string_lossy(),
+                            &krate.workspace_manifest_dir.to_string_lossy(),
+                            &types,
+                        ])
+                    );
                     continue;
                 }
+                let package = match (&krate.name, &krate.version) {
+                    (Some(name), Some(version)) => format!("{name} {version}, "),
+                    (Some(name), None) => format!("{name}, "),
+                    _ => String::new(),
+                };
                 if krate.manifest_dir == krate.workspace_manifest_dir {
                     println!(
-                        "{} (types: {:?})",
+                        "{} ({package}types: {:?})",
                         krate.manifest_dir.display(),
                         krate.types
                     );
                 } else {
                     println!(
-                        "{} (workspace: {}, types: {:?})",
+                        "{} ({package}workspace: {}, types: {:?})",
                         krate.manifest_dir.display(),
                         krate.workspace_manifest_dir.display(),
                         krate.types
@@ -163,12 +454,135 @@ pub async fn list_command(
     Ok(())
 }
 
+/// counts of the registered workspaces and crates
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+pub struct Stats {
+    /// number of workspaces, including those of standalone crates
+    pub workspaces: usize,
+    /// number of workspaces with more than one member or a virtual manifest
+    pub multi_crate_workspaces: usize,
+    /// number of crates
+    pub crates: usize,
+    /// number of crates that are members of a multi-crate workspace
+    pub member_crates: usize,
+    /// number of standalone crates
+    pub standalone_crates: usize,
+    /// number of crates of each type; a crate with several types is counted for each
+    pub crates_by_type: BTreeMap<String, usize>,
+    /// number of member crates of each multi-crate workspace
+    pub members_per_workspace: BTreeMap<PathBuf, usize>,
+}
+
+impl Stats {
+    /// counts the workspaces and crates registered in `config`
+    #[must_use]
+    pub fn from_config(config: &crate::Config) -> Self {
+        let mut stats = Self {
+            workspaces: config.workspaces.len(),
+            crates: config.crates.len(),
+            ..Self::default()
+        };
+        for workspace in config.workspaces.iter().filter(|w| !w.is_standalone) {
+            stats.multi_crate_workspaces = stats.multi_crate_workspaces.saturating_add(1);
+            stats
+                .members_per_workspace
+                .insert(workspace.manifest_dir.clone(), 0);
+        }
+        for krate in &config.crates {
+            if let Some(members) = stats
+                .members_per_workspace
+                .get_mut(&krate.workspace_manifest_dir)
+            {
+                *members = members.saturating_add(1);
+                stats.member_crates = stats.member_crates.saturating_add(1);
+            } else {
+                stats.standalone_crates = stats.standalone_crates.saturating_add(1);
+            }
+            for crate_type in krate
+                .types
+                .iter()
+                .filter_map(clap::ValueEnum::to_possible_value)
+            {
+                let count = stats
+                    .crates_by_type
+                    .entry(crate_type.get_name().to_owned())
+                    .or_insert(0);
+                *count = count.saturating_add(1);
+            }
+        }
+        stats
+    }
+}
+
+/// implementation of the stats subcommand
+///
+/// # Errors
+///
+/// This command can fail if the configuration file cannot be loaded or parsed,
+/// or if the statistics cannot be serialized to JSON.
+#[instrument]
+#[expect(clippy::print_stdout, reason = "This is part of the UI, not logging")]
+pub async fn stats_command(
+    environment: crate::Environment,
+    run_options: crate::RunOptions,
+) -> Result<(), crate::error::Error> {
+    let config = crate::Config::load(&environment)?;
+    let stats = Stats::from_config(&config);
+    if crate::porcelain::is_json(run_options.format, None, "target stats")? {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&stats)
+                .map_err(crate::error::Error::CouldNotSerializeStats)?
+        );
+        return Ok(());
+    }
+    println!(
+        "Workspaces: {} ({} multi-crate, {} standalone)",
+        stats.workspaces,
+        stats.multi_crate_workspaces,
+        stats
+            .workspaces
+            .saturating_sub(stats.multi_crate_workspaces)
+    );
+    println!(
+        "Crates: {} ({} workspace members, {} standalone)",
+        stats.crates, stats.member_crates, stats.standalone_crates
+    );
+    if !stats.crates_by_type.is_empty() {
+        println!("Crates by type:");
+        for (crate_type, count) in &stats.crates_by_type {
+            println!("  {crate_type:<14} {count}");
+        }
+    }
+    if !stats.members_per_workspace.is_empty() {
+        println!("Members per workspace:");
+        for (manifest_dir, count) in &stats.members_per_workspace {
+            println!("  {count:>5}  {}", manifest_dir.display());
+        }
+    }
+    Ok(())
+}
+
 /// Parameters for add subcommand
 #[derive(clap::Parser, Debug, Clone)]
 pub struct AddParameters {
-    /// the manifest file to add, if it refers to a workspace manifest all crates in the workspace are added too
-    #[clap(long)]
-    pub manifest_path: PathBuf,
+    /// the manifest file (or its directory) to add, if it refers to a workspace manifest all crates in the workspace are added too (repeatable)
+    #[clap(long, required = true)]
+    pub manifest_path: Vec<PathBuf>,
+    /// what to do with crates inside the workspace directory that are not workspace members
+    /// (excluded crates or nested independent workspaces); without this they are only listed
+    #[clap(long, value_enum)]
+    pub nested: Option<NestedMode>,
+}
+
+/// How `target add` treats manifests inside the workspace directory that are
+/// not members of the workspace
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NestedMode {
+    /// register them as separate workspaces and crates
+    Include,
+    /// ignore them silently
+    Skip,
 }
 
 /// implementation of the add subcommand
@@ -177,27 +591,82 @@ pub struct AddParameters {
 ///
 /// This command can fail due to issues with loading or saving the configuration, resolving or canonicalizing manifest paths, errors during cargo metadata execution, inability to determine parent directories of manifest paths, or if expected packages are not found in cargo metadata output.
 #[instrument]
+#[expect(clippy::print_stdout, reason = "This is part of the UI, not logging")]
+#[expect(clippy::print_stderr, reason = "This is part of the UI, not logging")]
 pub async fn add_command(
     add_parameters: AddParameters,
     environment: crate::Environment,
 ) -> Result<(), crate::error::Error> {
+    let _lock = crate::config_lock::acquire(&environment).await?;
     let mut config = crate::Config::load(&environment)?;
-    let manifest_path =
-        std::path::absolute(add_parameters.manifest_path.clone()).map_err(|err| {
-            crate::error::Error::CouldNotDetermineAbsoluteManifestPath(
-                add_parameters.manifest_path,
-                err,
-            )
-        })?;
-    let manifest_path = fs_err::canonicalize(manifest_path.clone()).map_err(|err| {
-        crate::error::Error::CouldNotDetermineCanonicalManifestPath(manifest_path, err)
-    })?;
+    // resolve every path first, so a typo does not leave half of them registered
+    let manifest_paths = add_parameters
+        .manifest_path
+        .into_iter()
+        .map(resolve_manifest_path)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for manifest_path in manifest_paths {
+        let workspace_manifest_dir = add_manifest(&mut config, &manifest_path)?;
+        let member_dirs: Vec<PathBuf> = config
+            .crates
+            .iter()
+            .filter(|c| c.workspace_manifest_dir == workspace_manifest_dir)
+            .map(|c| c.manifest_dir.clone())
+            .collect();
+        let nested = find_nested_manifest_dirs(&workspace_manifest_dir, &member_dirs)?;
+        match add_parameters.nested {
+            None => {
+                if !nested.is_empty() {
+                    println!(
+                        "Found crates in {} that are not workspace members (excluded or nested workspaces):",
+                        workspace_manifest_dir.display()
+                    );
+                    for dir in &nested {
+                        println!("  {}", dir.display());
+                    }
+                    println!(
+                        "Re-run with --nested include to register them, or --nested skip to silence this."
+                    );
+                }
+            }
+            Some(NestedMode::Skip) => {
+                tracing::debug!("Skipping {} nested manifests", nested.len());
+            }
+            Some(NestedMode::Include) => {
+                for dir in &nested {
+                    if config.crates.iter().any(|c| &c.manifest_dir == dir) {
+                        continue;
+                    }
+                    match add_manifest(&mut config, &dir.join("Cargo.toml")) {
+                        Ok(_) => println!("Added nested {}", dir.display()),
+                        Err(e) => eprintln!("Could not add nested {}: {e}", dir.display()),
+                    }
+                }
+            }
+        }
+    }
+
+    config.save(&environment)?;
 
+    Ok(())
+}
+
+/// adds the workspace containing `manifest_path` and all its members to `config`
+/// and returns the workspace's manifest directory
+///
+/// # Errors
+///
+/// This can fail due to errors during cargo metadata execution, inability to determine parent directories of manifest paths, or if expected packages are not found in cargo metadata output.
+pub fn add_manifest(
+    config: &mut crate::Config,
+    manifest_path: &Path,
+) -> Result<PathBuf, crate::error::Error> {
     // first call to metadata to find the workspace root
     let initial_metadata = cargo_metadata::MetadataCommand::new()
-        .manifest_path(&manifest_path)
+        .manifest_path(manifest_path)
         .exec()
-        .map_err(|err| crate::error::Error::CargoMetadataError(manifest_path.clone(), err))?; // manifest_path here is already std::path::PathBuf
+        .map_err(|err| crate::error::Error::CargoMetadataError(manifest_path.to_path_buf(), err))?;
     let workspace_manifest_path_camino = initial_metadata.workspace_root.join("Cargo.toml");
 
     let Some(workspace_manifest_dir_camino) = workspace_manifest_path_camino.parent() else {
@@ -230,20 +699,26 @@ pub async fn add_command(
         let package = workspace_metadata
             .get_package_by_manifest_path(&workspace_manifest_path_camino.into_std_path_buf())?; // Convert for comparison
         let crate_types = CrateType::from_package(package);
+        let metadata = PackageMetadata::from_package(package);
         config.add_workspace(Workspace {
             manifest_dir: workspace_manifest_dir_camino.clone().into_std_path_buf(),
             is_standalone: true,
+            repository: git::Repository::discover(workspace_manifest_dir_camino.as_std_path()),
         });
         config.add_crate(Crate {
             manifest_dir: workspace_manifest_dir_camino.clone().into_std_path_buf(),
-            workspace_manifest_dir: workspace_manifest_dir_camino.into_std_path_buf(),
+            workspace_manifest_dir: workspace_manifest_dir_camino.clone().into_std_path_buf(),
             types: crate_types,
+            metadata,
+            name: Some(package.name.to_string()),
+            version: Some(package.version.to_string()),
         });
     } else {
         tracing::debug!("Identified Cargo.toml as workspace");
         config.add_workspace(Workspace {
             manifest_dir: workspace_manifest_dir_camino.clone().into_std_path_buf(),
             is_standalone: false,
+            repository: git::Repository::discover(workspace_manifest_dir_camino.as_std_path()),
         });
         for package_id in workspace_metadata.workspace_members.clone() {
             let package = workspace_metadata.get_package_by_id(&package_id)?;
@@ -253,26 +728,169 @@ pub async fn add_command(
                     package_manifest_path,
                 ));
             };
-            let crate_types = CrateType::from_package(package);
             config.add_crate(Crate {
                 manifest_dir: package_manifest_dir.to_path_buf(),
                 workspace_manifest_dir: workspace_manifest_dir_camino.clone().into_std_path_buf(),
-                types: crate_types,
+                types: CrateType::from_package(package),
+                metadata: PackageMetadata::from_package(package),
+                name: Some(package.name.to_string()),
+                version: Some(package.version.to_string()),
             });
         }
     }
 
-    config.save(&environment)?;
+    Ok(workspace_manifest_dir_camino.into_std_path_buf())
+}
 
-    Ok(())
+/// finds directories below `workspace_dir` that contain a `Cargo.toml` but
+/// are neither the workspace itself nor one of `member_dirs`, e.g. crates
+/// listed in `workspace.exclude` or nested independent workspaces
+///
+/// `target` directories and hidden directories are not searched.  The result
+/// is sorted, so enclosing directories come before the ones nested in them.
+///
+/// # Errors
+///
+/// Returns an error if a directory cannot be read.
+pub fn find_nested_manifest_dirs(
+    workspace_dir: &Path,
+    member_dirs: &[PathBuf],
+) -> Result<Vec<PathBuf>, crate::error::Error> {
+    let mut found = Vec::new();
+    let mut pending = vec![workspace_dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        for entry in fs_err::read_dir(&dir).map_err(crate::error::Error::IoError)? {
+            let entry = entry.map_err(crate::error::Error::IoError)?;
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            if name.starts_with('.') || name == "target" {
+                continue;
+            }
+            let path = entry.path();
+            if entry
+                .file_type()
+                .map_err(crate::error::Error::IoError)?
+                .is_dir()
+            {
+                if path.join("Cargo.toml").is_file() && !member_dirs.contains(&path) {
+                    found.push(path.clone());
+                }
+                pending.push(path);
+            }
+        }
+    }
+    found.sort();
+    Ok(found)
+}
+
+/// returns `true` if the `Cargo.toml` in `manifest_dir` is a virtual
+/// manifest, i.e. it has a `[workspace]` table but no `[package]` table, so
+/// cargo commands that work on a single package need to be told which member
+/// to use
+///
+/// a manifest that cannot be read or parsed is not considered virtual
+#[must_use]
+pub fn is_virtual_manifest(manifest_dir: &Path) -> bool {
+    fs_err::read_to_string(manifest_dir.join("Cargo.toml"))
+        .ok()
+        .and_then(|content| content.parse::<toml::Table>().ok())
+        .is_some_and(|table| table.contains_key("workspace") && !table.contains_key("package"))
 }
 
 /// Parameters for remove subcommand
 #[derive(clap::Parser, Debug, Clone)]
+#[expect(
+    clippy::struct_excessive_bools,
+    reason = "these are independent command line flags"
+)]
 pub struct RemoveParameters {
-    /// the manifest file to remove
+    /// the manifest file (or its directory) to remove (repeatable)
+    #[clap(long, required = true)]
+    pub manifest_path: Vec<PathBuf>,
+    /// if the manifest belongs to a workspace member, remove the whole workspace and all its members
+    #[clap(long, conflicts_with = "members_only")]
+    pub with_workspace: bool,
+    /// if the manifest is a workspace, remove its member crates but keep the workspace itself
     #[clap(long)]
-    pub manifest_path: PathBuf,
+    pub members_only: bool,
+    /// remove the targets even if tasks run on them, without asking
+    #[clap(long, conflicts_with = "cascade")]
+    pub force: bool,
+    /// also remove the tasks running on the removed targets
+    #[clap(long)]
+    pub cascade: bool,
+}
+
+/// Which entries `target remove` removes for a manifest directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RemoveMode {
+    /// a workspace is removed with all its members, a member crate on its own
+    Default,
+    /// a member crate is removed together with its workspace and all siblings
+    WithWorkspace,
+    /// only the member crates of a workspace are removed
+    MembersOnly,
+}
+
+impl From<&RemoveParameters> for RemoveMode {
+    fn from(remove_parameters: &RemoveParameters) -> Self {
+        if remove_parameters.with_workspace {
+            Self::WithWorkspace
+        } else if remove_parameters.members_only {
+            Self::MembersOnly
+        } else {
+            Self::Default
+        }
+    }
+}
+
+/// The entries removed from the config by [`remove_entries`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RemovedEntries {
+    /// manifest directories of the removed workspaces
+    pub workspaces: Vec<PathBuf>,
+    /// manifest directories of the removed crates
+    pub crates: Vec<PathBuf>,
+}
+
+/// removes the entries for `manifest_dir` from `config` according to `mode`
+/// and returns what was removed
+pub fn remove_entries(
+    config: &mut crate::Config,
+    manifest_dir: &Path,
+    mode: RemoveMode,
+) -> RemovedEntries {
+    let workspace_dir = match mode {
+        RemoveMode::WithWorkspace => config
+            .crates
+            .iter()
+            .find(|c| c.manifest_dir == manifest_dir)
+            .map_or_else(
+                || manifest_dir.to_path_buf(),
+                |c| c.workspace_manifest_dir.clone(),
+            ),
+        RemoveMode::Default | RemoveMode::MembersOnly => manifest_dir.to_path_buf(),
+    };
+    let mut removed = RemovedEntries::default();
+    if mode != RemoveMode::MembersOnly {
+        let (gone, kept): (Vec<_>, Vec<_>) = std::mem::take(&mut config.workspaces)
+            .into_iter()
+            .partition(|w| w.manifest_dir == workspace_dir);
+        config.workspaces = kept;
+        removed.workspaces = gone.into_iter().map(|w| w.manifest_dir).collect();
+    }
+    let (gone, kept): (Vec<_>, Vec<_>) =
+        std::mem::take(&mut config.crates)
+            .into_iter()
+            .partition(|c| match mode {
+                RemoveMode::MembersOnly => c.workspace_manifest_dir == workspace_dir,
+                RemoveMode::Default | RemoveMode::WithWorkspace => {
+                    c.manifest_dir == manifest_dir || c.workspace_manifest_dir == workspace_dir
+                }
+            });
+    config.crates = kept;
+    removed.crates = gone.into_iter().map(|c| c.manifest_dir).collect();
+    removed
 }
 
 /// implementation of the remove subcommand
@@ -280,50 +898,160 @@ pub struct RemoveParameters {
 /// # Errors
 ///
 /// This command can fail due to issues with loading or saving the configuration, resolving or canonicalizing manifest paths, or other file system errors during config saving.
+/// It fails with [`crate::error::Error::TargetsInUse`] if tasks run on the removed targets and neither `--force` nor `--cascade` was given nor the removal confirmed.
 #[instrument]
+#[expect(clippy::print_stdout, reason = "This is part of the UI, not logging")]
 pub async fn remove_command(
     remove_parameters: RemoveParameters,
     environment: crate::Environment,
 ) -> Result<(), crate::error::Error> {
+    let _lock = crate::config_lock::acquire(&environment).await?;
     let mut config = crate::Config::load(&environment)?;
-    let manifest_path =
-        std::path::absolute(remove_parameters.manifest_path.clone()).map_err(|err| {
-            crate::error::Error::CouldNotDetermineAbsoluteManifestPath(
-                remove_parameters.manifest_path,
-                err,
-            )
-        })?;
+    let mode = RemoveMode::from(&remove_parameters);
+    let manifest_dirs = remove_parameters
+        .manifest_path
+        .into_iter()
+        .map(canonical_manifest_dir)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut removed_dirs = Vec::new();
+    let mut messages = Vec::new();
+    for manifest_dir in manifest_dirs {
+        let removed = remove_entries(&mut config, &manifest_dir, mode);
+        if removed == RemovedEntries::default() {
+            println!("Nothing registered at {}", manifest_dir.display());
+            continue;
+        }
+        for workspace_dir in &removed.workspaces {
+            messages.push(format!("Removed workspace {}", workspace_dir.display()));
+        }
+        for crate_dir in &removed.crates {
+            messages.push(format!("Removed crate {}", crate_dir.display()));
+        }
+        removed_dirs.extend(removed.workspaces);
+        removed_dirs.extend(removed.crates);
+    }
+    if removed_dirs.is_empty() {
+        return Ok(());
+    }
+
+    let references = crate::tasks::references::tasks_using(&removed_dirs, &environment)?;
+    if !references.is_empty() {
+        println!("These tasks run on targets to be removed:");
+        for reference in &references {
+            println!("  {}", reference.task);
+            for dir in &reference.targets {
+                println!("    {}", dir.display());
+            }
+        }
+        if remove_parameters.cascade {
+            for reference in &references {
+                crate::tasks::remove_task(&reference.task, &environment)?;
+                println!("Removed task {}", reference.task);
+            }
+        } else if !remove_parameters.force
+            && !crate::tasks::references::confirm(
+                "Remove the targets anyway? The tasks keep running on them.",
+            )?
+        {
+            return Err(crate::error::Error::TargetsInUse(
+                references
+                    .into_iter()
+                    .map(|reference| reference.task)
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ));
+        }
+    }
+
+    config.save(&environment)?;
+    for message in messages {
+        println!("{message}");
+    }
+    Ok(())
+}
+
+/// returns the canonical directory of a manifest path given on the command
+/// line, which may be a `Cargo.toml` file or the directory containing it
+///
+/// # Errors
+///
+/// Returns an error if the path cannot be made absolute or canonical.
+pub fn canonical_manifest_dir(manifest_path: PathBuf) -> Result<PathBuf, crate::error::Error> {
+    let manifest_path = std::path::absolute(manifest_path.clone()).map_err(|err| {
+        crate::error::Error::CouldNotDetermineAbsoluteManifestPath(manifest_path, err)
+    })?;
     let manifest_path = fs_err::canonicalize(manifest_path.clone()).map_err(|err| {
         crate::error::Error::CouldNotDetermineCanonicalManifestPath(manifest_path, err)
     })?;
-
-    // Filter out the workspace if it matches the manifest_path
-    let initial_workspace_count = config.workspaces.len();
-    config
-        .workspaces
-        .retain(|w| w.manifest_dir != manifest_path);
-    if config.workspaces.len() < initial_workspace_count {
-        tracing::debug!("Removed workspace at {}", manifest_path.display());
+    if manifest_path.is_file() {
+        Ok(manifest_path
+            .parent()
+            .ok_or_else(|| crate::error::Error::ManifestPathHasNoParentDir(manifest_path.clone()))?
+            .to_path_buf())
     } else {
-        tracing::warn!("No workspace found at {}", manifest_path.display());
+        Ok(manifest_path)
     }
+}
 
-    // Filter out crates that match the manifest_path or belong to the removed workspace
-    let initial_crate_count = config.crates.len();
-    config
-        .crates
-        .retain(|c| c.manifest_dir != manifest_path && c.workspace_manifest_dir != manifest_path);
-    if config.crates.len() < initial_crate_count {
-        tracing::debug!("Removed crates associated with {}", manifest_path.display());
-    } else {
-        tracing::warn!(
-            "No crates found associated with {}",
-            manifest_path.display()
-        );
+/// returns the canonical `Cargo.toml` of a manifest path given on the command
+/// line; for a directory without one, the only `Cargo.toml` in its immediate
+/// subdirectories is used
+///
+/// # Errors
+///
+/// Returns an error if the path cannot be made absolute or canonical, or if
+/// a directory contains no `Cargo.toml` or several subdirectories do.
+pub fn resolve_manifest_path(manifest_path: PathBuf) -> Result<PathBuf, crate::error::Error> {
+    let manifest_path = std::path::absolute(manifest_path.clone()).map_err(|err| {
+        crate::error::Error::CouldNotDetermineAbsoluteManifestPath(manifest_path, err)
+    })?;
+    let manifest_path = fs_err::canonicalize(manifest_path.clone()).map_err(|err| {
+        crate::error::Error::CouldNotDetermineCanonicalManifestPath(manifest_path, err)
+    })?;
+    if !manifest_path.is_dir() {
+        return Ok(manifest_path);
+    }
+    let direct = manifest_path.join("Cargo.toml");
+    if direct.is_file() {
+        return Ok(direct);
     }
+    let mut candidates: Vec<PathBuf> = fs_err::read_dir(&manifest_path)
+        .map_err(crate::error::Error::IoError)?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path().join("Cargo.toml"))
+        .filter(|candidate| candidate.is_file())
+        .collect();
+    candidates.sort();
+    match candidates.as_slice() {
+        [] => Err(crate::error::Error::NoManifestInDirectory(manifest_path)),
+        [only] => Ok(only.clone()),
+        several => Err(crate::error::Error::AmbiguousManifestDirectory(
+            manifest_path.clone(),
+            several
+                .iter()
+                .filter_map(|candidate| candidate.parent()?.file_name())
+                .map(|name| name.to_string_lossy().into_owned())
+                .collect::<Vec<_>>()
+                .join(", "),
+        )),
+    }
+}
 
-    config.save(&environment)?;
-    Ok(())
+/// Parameters for refresh subcommand
+#[derive(clap::Parser, Debug, Clone)]
+pub struct RefreshParameters {
+    /// only remove entries whose Cargo.toml no longer exists, without running cargo metadata
+    #[clap(long, conflicts_with = "deep")]
+    pub shallow: bool,
+    /// also run cargo metadata to add new member crates and re-detect standalone workspaces,
+    /// crate types and package metadata (the default)
+    #[clap(long)]
+    pub deep: bool,
+    /// only refresh this workspace (its directory or Cargo.toml) and its crates, may be given multiple times;
+    /// without it all registered workspaces are refreshed
+    #[clap(long = "workspace", value_name = "PATH")]
+    pub workspaces: Vec<PathBuf>,
 }
 
 /// implementation of the refresh subcommand
@@ -332,14 +1060,35 @@ pub async fn remove_command(
 ///
 /// This command can fail due to issues with loading or saving the configuration, errors during cargo metadata execution, if expected packages are not found in cargo metadata output, or other file system errors during config saving.
 #[instrument]
-pub async fn refresh_command(environment: crate::Environment) -> Result<(), crate::error::Error> {
+pub async fn refresh_command(
+    refresh_parameters: RefreshParameters,
+    environment: crate::Environment,
+) -> Result<(), crate::error::Error> {
+    let _lock = crate::config_lock::acquire(&environment).await?;
     let mut config = crate::Config::load(&environment)?;
+    let selected = refresh_parameters
+        .workspaces
+        .into_iter()
+        .map(|path| {
+            if path.exists() {
+                canonical_manifest_dir(path)
+            } else {
+                // a workspace that was deleted entirely can still be selected to prune it
+                std::path::absolute(&path).map_err(|err| {
+                    crate::error::Error::CouldNotDetermineAbsoluteManifestPath(path, err)
+                })
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let in_scope = |workspace_manifest_dir: &Path| {
+        selected.is_empty() || selected.iter().any(|dir| dir == workspace_manifest_dir)
+    };
 
     // 1. Remove workspaces that no longer exist.
     let (retained_workspaces, removed_workspaces): (Vec<_>, Vec<_>) = config
         .workspaces
         .drain(..)
-        .partition(|w| w.manifest_dir.join("Cargo.toml").is_file());
+        .partition(|w| !in_scope(&w.manifest_dir) || w.manifest_dir.join("Cargo.toml").is_file());
     for r in &removed_workspaces {
         tracing::debug!(
             "Removing workspace at {} because Cargo.toml is gone.",
@@ -349,10 +1098,10 @@ pub async fn refresh_command(environment: crate::Environment) -> Result<(), crat
     config.workspaces = retained_workspaces;
 
     // 2. Remove crates that no longer exist.
-    let (retained_crates, removed_crates): (Vec<_>, Vec<_>) = config
-        .crates
-        .drain(..)
-        .partition(|c| c.manifest_dir.join("Cargo.toml").is_file());
+    let (retained_crates, removed_crates): (Vec<_>, Vec<_>) =
+        config.crates.drain(..).partition(|c| {
+            !in_scope(&c.workspace_manifest_dir) || c.manifest_dir.join("Cargo.toml").is_file()
+        });
     for r in &removed_crates {
         tracing::debug!(
             "Removing crate at {} because Cargo.toml is gone.",
@@ -361,15 +1110,43 @@ pub async fn refresh_command(environment: crate::Environment) -> Result<(), crat
     }
     config.crates = retained_crates;
 
+    if refresh_parameters.shallow {
+        config.save(&environment)?;
+        return Ok(());
+    }
+
     // 3. For all existing workspaces, discover and add new member crates.
     //    We don't need to update existing crates found here, as the next step will do it.
-    let workspaces_to_scan = config.workspaces.clone();
-    for workspace in &workspaces_to_scan {
+    for workspace_index in 0..config.workspaces.len() {
+        let Some(workspace) = config.workspaces.get(workspace_index).cloned() else {
+            continue;
+        };
+        if !in_scope(&workspace.manifest_dir) {
+            continue;
+        }
         let manifest_path = workspace.manifest_dir.join("Cargo.toml");
         let cargo_metadata = cargo_metadata::MetadataCommand::new()
             .manifest_path(&manifest_path)
             .exec()
-            .map_err(|err| crate::error::Error::CargoMetadataError(manifest_path, err))?;
+            .map_err(|err| crate::error::Error::CargoMetadataError(manifest_path.clone(), err))?;
+
+        let is_standalone = if let [package_id] = cargo_metadata.workspace_members.as_slice() {
+            cargo_metadata.get_package_by_id(package_id)?.manifest_path == manifest_path
+        } else {
+            false
+        };
+        if let Some(registered) = config.workspaces.get_mut(workspace_index)
+            && registered.is_standalone != is_standalone
+        {
+            tracing::debug!(
+                "Updating standalone status of {} to {is_standalone}",
+                registered.manifest_dir.display()
+            );
+            registered.is_standalone = is_standalone;
+        }
+        if let Some(registered) = config.workspaces.get_mut(workspace_index) {
+            registered.repository = git::Repository::discover(&registered.manifest_dir);
+        }
 
         for package_id in &cargo_metadata.workspace_members {
             let package = cargo_metadata.get_package_by_id(package_id)?;
@@ -379,11 +1156,13 @@ pub async fn refresh_command(environment: crate::Environment) -> Result<(), crat
 
                 // Only add if it doesn't exist. `add_crate` does this.
                 if !config.crates.iter().any(|c| c.manifest_dir == manifest_dir) {
-                    let crate_types = CrateType::from_package(package);
                     config.add_crate(Crate {
                         manifest_dir,
                         workspace_manifest_dir: workspace.manifest_dir.clone(),
-                        types: crate_types,
+                        types: CrateType::from_package(package),
+                        metadata: PackageMetadata::from_package(package),
+                        name: Some(package.name.to_string()),
+                        version: Some(package.version.to_string()),
                     });
                 }
             }
@@ -392,6 +1171,9 @@ pub async fn refresh_command(environment: crate::Environment) -> Result<(), crat
 
     // 4. Update crate_types for all existing crates.
     for krate in &mut config.crates {
+        if !in_scope(&krate.workspace_manifest_dir) {
+            continue;
+        }
         let manifest_path = krate.manifest_dir.join("Cargo.toml");
 
         let cargo_metadata = cargo_metadata::MetadataCommand::new()
@@ -413,6 +1195,18 @@ pub async fn refresh_command(environment: crate::Environment) -> Result<(), crat
                 );
                 krate.types = new_crate_types;
             }
+            let new_metadata = PackageMetadata::from_package(package);
+            if krate.metadata != new_metadata {
+                tracing::debug!(
+                    "Updating metadata for {} from {:?} to {:?}",
+                    krate.manifest_dir.display(),
+                    krate.metadata,
+                    new_metadata
+                );
+                krate.metadata = new_metadata;
+            }
+            krate.name = Some(package.name.to_string());
+            krate.version = Some(package.version.to_string());
         } else {
             tracing::warn!(
                 "Could not find package for manifest path {} during refresh.",
@@ -583,6 +1377,70 @@ impl CrateType {
     }
 }
 
+/// the settings a crate declares for cargo-for-each in the
+/// `[package.metadata.cargo-for-each]` table of its Cargo.toml
+///
+/// they are read when the crate is added or refreshed, so crate authors can
+/// annotate their own crates without touching the operator's config
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct PackageMetadata {
+    /// free-form tags, matched by the `tagged "name"` condition
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub tags: BTreeSet<String>,
+    /// if true, the crate is never selected by `select crates` or `select members`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub skip: bool,
+    /// environment variables set for every statement run for the crate
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    /// the rustup toolchain statements for the crate run with (as `RUSTUP_TOOLCHAIN`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub toolchain: Option<String>,
+}
+
+impl PackageMetadata {
+    /// reads the `cargo-for-each` table from the metadata of `package`
+    ///
+    /// a missing table yields the default; an invalid one is reported as a
+    /// warning and ignored, so one broken crate does not keep a whole fleet
+    /// from being added or refreshed
+    #[must_use]
+    pub fn from_package(package: &cargo_metadata::Package) -> Self {
+        let Some(table) = package.metadata.get("cargo-for-each") else {
+            return Self::default();
+        };
+        serde_json::from_value(table.clone()).unwrap_or_else(|err| {
+            tracing::warn!(
+                "Ignoring invalid [package.metadata.cargo-for-each] in {}: {err}",
+                package.manifest_path
+            );
+            Self::default()
+        })
+    }
+
+    /// returns true if the crate declares nothing
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// returns the environment variables statements for the crate run with,
+    /// including `RUSTUP_TOOLCHAIN` for the toolchain
+    #[must_use]
+    pub fn env_vars(&self) -> Vec<(String, String)> {
+        let mut vars: Vec<(String, String)> = self
+            .env
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        if let Some(toolchain) = &self.toolchain {
+            vars.push(("RUSTUP_TOOLCHAIN".to_owned(), toolchain.clone()));
+        }
+        vars
+    }
+}
+
 /// represents a target within a resolved target set
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct Target {
@@ -591,3 +1449,371 @@ pub struct Target {
     /// the manifest directories of the targets that this target depends on
     pub dependencies: Vec<PathBuf>,
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+    use std::path::{Path, PathBuf};
+
+    use pretty_assertions::assert_eq;
+
+    use super::{
+        AddParameters, CrateFilterParameters, CrateType, CrateView, NameAndPathFilter, NestedMode,
+        PackageMetadata, RefreshParameters, RemoveMode, RemovedEntries, Stats, add_command,
+        find_nested_manifest_dirs, refresh_command, remove_entries, resolve_manifest_path,
+    };
+    use crate::{Config, Crate, Environment, Workspace};
+
+    /// A config with one workspace `/ws` containing `/ws/a` and `/ws/b` and a
+    /// standalone crate `/solo`.
+    fn config() -> Config {
+        let krate = |dir: &str, workspace_dir: &str| Crate {
+            manifest_dir: PathBuf::from(dir),
+            workspace_manifest_dir: PathBuf::from(workspace_dir),
+            types: BTreeSet::new(),
+            metadata: PackageMetadata::default(),
+            name: None,
+            version: None,
+        };
+        Config {
+            sort_on_save: false,
+            shared_repository: None,
+            notifications: vec![],
+            target_sets: std::collections::BTreeMap::new(),
+            tags: std::collections::BTreeMap::new(),
+            workspaces: vec![
+                Workspace {
+                    manifest_dir: PathBuf::from("/ws"),
+                    is_standalone: false,
+                    repository: None,
+                },
+                Workspace {
+                    manifest_dir: PathBuf::from("/solo"),
+                    is_standalone: true,
+                    repository: None,
+                },
+            ],
+            crates: vec![
+                krate("/ws/a", "/ws"),
+                krate("/ws/b", "/ws"),
+                krate("/solo", "/solo"),
+            ],
+        }
+    }
+
+    /// Builds the expected result from directory names.
+    fn removed(workspaces: &[&str], crates: &[&str]) -> RemovedEntries {
+        RemovedEntries {
+            workspaces: workspaces.iter().map(PathBuf::from).collect(),
+            crates: crates.iter().map(PathBuf::from).collect(),
+        }
+    }
+
+    #[test]
+    fn list_json_output() -> Result<(), Box<dyn std::error::Error>> {
+        use clap::Parser as _;
+
+        let options = crate::Options::try_parse_from([
+            "cargo-for-each",
+            "target",
+            "list",
+            "crates",
+            "--format",
+            "json",
+        ])?;
+        assert_eq!(options.format, crate::porcelain::OutputFormat::Json);
+
+        let krate = Crate {
+            manifest_dir: PathBuf::from("/ws/a"),
+            workspace_manifest_dir: PathBuf::from("/ws"),
+            types: BTreeSet::from([CrateType::Bin, CrateType::Lib]),
+            metadata: PackageMetadata::default(),
+            name: Some("a".to_owned()),
+            version: Some("0.1.0".to_owned()),
+        };
+        assert_eq!(
+            crate::porcelain::json(&[CrateView::from(&krate)])?,
+            "[\n  {\n    \"manifest_dir\": \"/ws/a\",\n    \"workspace_manifest_dir\": \"/ws\",\n    \
+             \"types\": [\n      \"bin\",\n      \"lib\"\n    ],\n    \"name\": \"a\",\n    \
+             \"version\": \"0.1.0\"\n  }\n]"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn remove_member_crate_only() {
+        let mut config = config();
+        assert_eq!(
+            remove_entries(&mut config, Path::new("/ws/a"), RemoveMode::Default),
+            removed(&[], &["/ws/a"])
+        );
+        assert_eq!(config.workspaces.len(), 2);
+        assert_eq!(config.crates.len(), 2);
+    }
+
+    #[test]
+    fn remove_member_crate_with_workspace() {
+        let mut config = config();
+        assert_eq!(
+            remove_entries(&mut config, Path::new("/ws/a"), RemoveMode::WithWorkspace),
+            removed(&["/ws"], &["/ws/a", "/ws/b"])
+        );
+        assert_eq!(config.crates.len(), 1);
+    }
+
+    #[test]
+    fn remove_workspace_members_only() {
+        let mut config = config();
+        assert_eq!(
+            remove_entries(&mut config, Path::new("/ws"), RemoveMode::MembersOnly),
+            removed(&[], &["/ws/a", "/ws/b"])
+        );
+        assert_eq!(config.workspaces.len(), 2);
+    }
+
+    #[test]
+    fn remove_standalone_crate() {
+        let mut config = config();
+        assert_eq!(
+            remove_entries(&mut config, Path::new("/solo"), RemoveMode::Default),
+            removed(&["/solo"], &["/solo"])
+        );
+        assert_eq!(
+            remove_entries(&mut config, Path::new("/nowhere"), RemoveMode::Default),
+            RemovedEntries::default()
+        );
+    }
+
+    #[test]
+    fn stats_counts_members_standalone_crates_and_types() {
+        let mut config = config();
+        for krate in &mut config.crates {
+            krate.types.insert(CrateType::Lib);
+        }
+        if let Some(krate) = config.crates.first_mut() {
+            krate.types.insert(CrateType::Bin);
+        }
+        assert_eq!(
+            Stats::from_config(&config),
+            Stats {
+                workspaces: 2,
+                multi_crate_workspaces: 1,
+                crates: 3,
+                member_crates: 2,
+                standalone_crates: 1,
+                crates_by_type: [("bin".to_owned(), 1), ("lib".to_owned(), 3)].into(),
+                members_per_workspace: [(PathBuf::from("/ws"), 2)].into(),
+            }
+        );
+    }
+
+    /// Writes a minimal library crate called `name` into `dir`.
+    fn write_crate(dir: &Path, name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        fs_err::create_dir_all(dir.join("src"))?;
+        fs_err::write(
+            dir.join("Cargo.toml"),
+            format!("[package]\nname = \"{name}\"\nversion = \"0.1.0\"\nedition = \"2021\"\n"),
+        )?;
+        fs_err::write(dir.join("src").join("lib.rs"), "")?;
+        Ok(())
+    }
+
+    #[test]
+    fn crates_are_filtered_by_name_and_path() -> Result<(), Box<dyn std::error::Error>> {
+        let temp = tempfile::tempdir()?;
+        let root = fs_err::canonicalize(temp.path())?;
+        let api = root.join("services").join("api");
+        let worker = root.join("services").join("worker");
+        let legacy = root.join("services-old").join("api");
+        write_crate(&api, "svc-api")?;
+        write_crate(&worker, "svc-worker")?;
+        write_crate(&legacy, "svc-api-old")?;
+        let filter = |name_regex: Option<&str>, prefixes: &[PathBuf], excluded: &[PathBuf]| {
+            NameAndPathFilter::new(&CrateFilterParameters {
+                r#type: None,
+                standalone: None,
+                name_regex: name_regex.map(str::to_owned),
+                path_prefix: prefixes.to_vec(),
+                exclude_path: excluded.to_vec(),
+                depends_on: None,
+                version_req: None,
+                transitive: false,
+                git: super::git::FilterParameters::default(),
+                tags: super::tags::TagFilterParameters::default(),
+            })
+        };
+        let matching = |filter: &NameAndPathFilter| {
+            [&api, &worker, &legacy]
+                .into_iter()
+                .filter(|dir| filter.matches(dir, None))
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+        let services = root.join("services");
+        assert_eq!(
+            matching(&filter(None, std::slice::from_ref(&services), &[])?),
+            [api.clone(), worker.clone()],
+            "prefixes match whole path components"
+        );
+        assert_eq!(
+            matching(&filter(None, &[services], std::slice::from_ref(&worker))?),
+            std::slice::from_ref(&api)
+        );
+        assert_eq!(
+            matching(&filter(Some("^svc-api"), &[], &[])?),
+            [api, legacy]
+        );
+        assert!(
+            filter(Some("("), &[], &[]).is_err(),
+            "invalid regular expressions are reported"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn find_nested_skips_members_target_and_hidden() -> Result<(), Box<dyn std::error::Error>> {
+        let temp = tempfile::tempdir()?;
+        let root = temp.path();
+        for dir in ["a", "tools/b", "tools/b/c", "target/d", ".git/e"] {
+            fs_err::create_dir_all(root.join(dir))?;
+            fs_err::write(root.join(dir).join("Cargo.toml"), "")?;
+        }
+        assert_eq!(
+            find_nested_manifest_dirs(root, &[root.join("a")])?,
+            vec![root.join("tools/b"), root.join("tools/b/c")]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn manifest_paths_may_be_directories() -> Result<(), Box<dyn std::error::Error>> {
+        let temp = tempfile::tempdir()?;
+        let root = fs_err::canonicalize(temp.path())?;
+        write_crate(&root.join("ws"), "ws")?;
+        write_crate(&root.join("checkout").join("inner"), "inner")?;
+        write_crate(&root.join("several").join("x"), "x")?;
+        write_crate(&root.join("several").join("y"), "y")?;
+        fs_err::create_dir_all(root.join("empty"))?;
+
+        let manifest = root.join("ws").join("Cargo.toml");
+        assert_eq!(resolve_manifest_path(manifest.clone())?, manifest);
+        assert_eq!(resolve_manifest_path(root.join("ws"))?, manifest);
+        assert_eq!(
+            resolve_manifest_path(root.join("checkout"))?,
+            root.join("checkout").join("inner").join("Cargo.toml"),
+            "the only manifest in a subdirectory is used"
+        );
+        assert!(
+            matches!(
+                resolve_manifest_path(root.join("several")),
+                Err(crate::error::Error::AmbiguousManifestDirectory(_, ref names)) if names == "x, y"
+            ),
+            "several subdirectories with manifests are ambiguous"
+        );
+        assert!(
+            matches!(
+                resolve_manifest_path(root.join("empty")),
+                Err(crate::error::Error::NoManifestInDirectory(_))
+            ),
+            "a directory without manifests is an error"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn add_includes_excluded_crates() -> Result<(), Box<dyn std::error::Error>> {
+        let temp = tempfile::tempdir()?;
+        let environment = Environment::mock(&temp)?;
+        let root = fs_err::canonicalize(temp.path())?.join("ws");
+        fs_err::create_dir_all(&root)?;
+        fs_err::write(
+            root.join("Cargo.toml"),
+            "[workspace]\nmembers = [\"a\"]\nexclude = [\"b\"]\nresolver = \"2\"\n",
+        )?;
+        write_crate(&root.join("a"), "a")?;
+        write_crate(&root.join("b"), "b")?;
+
+        let add = |nested| AddParameters {
+            manifest_path: vec![root.join("Cargo.toml")],
+            nested,
+        };
+        add_command(add(None), environment.clone()).await?;
+        let config = Config::load(&environment)?;
+        assert_eq!(
+            config
+                .crates
+                .iter()
+                .map(|c| c.manifest_dir.clone())
+                .collect::<Vec<_>>(),
+            vec![root.join("a")]
+        );
+        assert_eq!(
+            config
+                .crates
+                .first()
+                .map(|c| (c.name.as_deref(), c.version.as_deref())),
+            Some((Some("a"), Some("0.1.0"))),
+            "the package name and version are recorded"
+        );
+
+        add_command(add(Some(NestedMode::Include)), environment.clone()).await?;
+        let config = Config::load(&environment)?;
+        let b = config
+            .workspaces
+            .iter()
+            .find(|w| w.manifest_dir == root.join("b"))
+            .ok_or("excluded crate b was not registered")?;
+        assert!(b.is_standalone, "an excluded crate is its own workspace");
+        assert_eq!(config.crates.len(), 2);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn shallow_refresh_prunes_selected_workspaces() -> Result<(), Box<dyn std::error::Error>>
+    {
+        let temp = tempfile::tempdir()?;
+        let environment = Environment::mock(&temp)?;
+        let root = fs_err::canonicalize(temp.path())?;
+        let mut config = Config::default();
+        for name in ["kept", "gone", "other"] {
+            let dir = root.join(name);
+            // not a valid manifest, so a deep refresh would fail in cargo metadata
+            fs_err::create_dir_all(&dir)?;
+            fs_err::write(dir.join("Cargo.toml"), "")?;
+            config.add_workspace(Workspace {
+                manifest_dir: dir.clone(),
+                is_standalone: true,
+                repository: None,
+            });
+            config.add_crate(Crate {
+                manifest_dir: dir.clone(),
+                workspace_manifest_dir: dir,
+                types: [CrateType::Lib].into(),
+                metadata: PackageMetadata::default(),
+                name: None,
+                version: None,
+            });
+        }
+        config.save(&environment)?;
+        fs_err::remove_dir_all(root.join("gone"))?;
+        fs_err::remove_file(root.join("other").join("Cargo.toml"))?;
+
+        let refresh = |workspaces| RefreshParameters {
+            shallow: true,
+            deep: false,
+            workspaces,
+        };
+        let registered = || -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+            Ok(Config::load(&environment)?
+                .crates
+                .into_iter()
+                .map(|c| c.manifest_dir)
+                .collect())
+        };
+        refresh_command(refresh(vec![root.join("gone")]), environment.clone()).await?;
+        assert_eq!(registered()?, [root.join("kept"), root.join("other")]);
+        refresh_command(refresh(vec![]), environment.clone()).await?;
+        assert_eq!(registered()?, [root.join("kept")]);
+        Ok(())
+    }
+}
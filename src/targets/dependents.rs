@@ -0,0 +1,357 @@
+//! Finding the crates that depend on a package.
+//!
+//! Before bumping a widely used dependency, say `serde`, one wants to run the
+//! tests of everything using it.  `target list crates --depends-on serde`
+//! lists the registered crates depending on the package, optionally only on
+//! versions matching `--version-req`, and with `--transitive` also those
+//! depending on it through other packages.  All kinds of dependencies count,
+//! including dev and build dependencies.  The list can be passed to `task
+//! create --crates-from -`.
+//!
+//! The versions are the ones in the dependency graph `cargo metadata`
+//! resolves for the workspace of each crate, so its lock file is used.
+//!
+//! `task create --include-dependents` goes the other way: starting from the
+//! crates a task selected, it adds the registered targets with a crate
+//! depending on one of them, and then those depending on the added ones, so
+//! a change to a library fans out to its consumers.
+
+use std::collections::{BTreeSet, VecDeque};
+use std::path::{Path, PathBuf};
+
+use cargo_metadata::semver::VersionReq;
+use cargo_metadata::{Metadata, PackageId};
+
+use crate::error::Error;
+use crate::metadata::MetadataProvider;
+use crate::program::resolve::ResolvedProgram;
+
+/// A prepared `--depends-on` filter.
+#[derive(Debug, Clone)]
+pub struct DependencyFilter {
+    /// the name of the package depended on
+    name: String,
+    /// the versions of it that count
+    version_req: Option<VersionReq>,
+    /// whether dependencies of dependencies count
+    transitive: bool,
+}
+
+impl DependencyFilter {
+    /// Returns the filter on dependencies on the package `name`, if its version
+    /// matches `version_req`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidVersionReq`] if `version_req` is not a valid
+    /// version requirement.
+    pub fn new(name: &str, version_req: Option<&str>, transitive: bool) -> Result<Self, Error> {
+        let version_req = version_req
+            .map(|req| {
+                VersionReq::parse(req)
+                    .map_err(|e| Error::InvalidVersionReq(req.to_owned(), e.to_string()))
+            })
+            .transpose()?;
+        Ok(Self {
+            name: name.to_owned(),
+            version_req,
+            transitive,
+        })
+    }
+
+    /// Returns `true` if the package with the id `id` is the one depended on.
+    fn is_wanted(&self, metadata: &Metadata, id: &PackageId) -> bool {
+        metadata.packages.iter().any(|package| {
+            &package.id == id
+                && *package.name == self.name
+                && self
+                    .version_req
+                    .as_ref()
+                    .is_none_or(|req| req.matches(&package.version))
+        })
+    }
+
+    /// Returns `true` if the crate in `manifest_dir` depends on the package,
+    /// according to the resolved dependency graph in `metadata`.
+    #[must_use]
+    pub fn matches(&self, metadata: &Metadata, manifest_dir: &Path) -> bool {
+        let Some(resolve) = &metadata.resolve else {
+            return false;
+        };
+        let manifest_path = manifest_dir.join("Cargo.toml");
+        let Some(root) = metadata
+            .packages
+            .iter()
+            .find(|package| package.manifest_path.as_std_path() == manifest_path)
+        else {
+            return false;
+        };
+        let mut seen = BTreeSet::from([&root.id]);
+        let mut queue = VecDeque::from([&root.id]);
+        while let Some(id) = queue.pop_front() {
+            let Some(node) = resolve.nodes.iter().find(|node| &node.id == id) else {
+                continue;
+            };
+            for dep in &node.deps {
+                if self.is_wanted(metadata, &dep.pkg) {
+                    return true;
+                }
+                if self.transitive && seen.insert(&dep.pkg) {
+                    queue.push_back(&dep.pkg);
+                }
+            }
+        }
+        false
+    }
+}
+
+/// The registered targets added by `--include-dependents`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Dependents {
+    /// The added multi-crate workspaces.
+    pub workspaces: Vec<PathBuf>,
+    /// The added standalone crates.
+    pub crates: Vec<PathBuf>,
+}
+
+/// Returns the registered targets outside of `resolved` that have a crate
+/// depending on one of its crates, directly or through other added targets.
+/// All kinds of dependencies count; packages are matched by name.
+///
+/// # Errors
+///
+/// Returns an error if `cargo metadata` fails for a registered workspace.
+pub fn fan_out(
+    resolved: &ResolvedProgram,
+    config: &crate::Config,
+    provider: &dyn MetadataProvider,
+) -> Result<Dependents, Error> {
+    let canonical = |dir: &Path| fs_err::canonicalize(dir).unwrap_or_else(|_err| dir.to_owned());
+    let mut names: BTreeSet<String> = resolved
+        .workspace_executions
+        .iter()
+        .flat_map(|w| w.member_crates.iter())
+        .chain(resolved.crate_executions.iter())
+        .filter_map(|c| match &c.package {
+            Some(package) => Some(package.name.clone()),
+            None => super::package_name(&c.manifest_dir),
+        })
+        .collect();
+    let mut included: BTreeSet<PathBuf> = resolved
+        .workspace_executions
+        .iter()
+        .map(|w| w.manifest_dir.clone())
+        .chain(
+            resolved
+                .crate_executions
+                .iter()
+                .map(|c| c.manifest_dir.clone()),
+        )
+        .collect();
+    let mut dependents = Dependents::default();
+    loop {
+        let mut added = false;
+        for workspace in &config.workspaces {
+            let dir = canonical(&workspace.manifest_dir);
+            if included.contains(&dir) {
+                continue;
+            }
+            let metadata = provider.metadata(&dir)?;
+            let members: Vec<_> = metadata
+                .packages
+                .iter()
+                .filter(|package| metadata.workspace_members.contains(&package.id))
+                .collect();
+            let depends = members.iter().any(|package| {
+                package
+                    .dependencies
+                    .iter()
+                    .any(|dependency| names.contains(&dependency.name))
+            });
+            if !depends {
+                continue;
+            }
+            names.extend(members.iter().map(|package| package.name.to_string()));
+            if workspace.is_standalone {
+                dependents.crates.push(dir.clone());
+            } else {
+                dependents.workspaces.push(dir.clone());
+            }
+            included.insert(dir);
+            added = true;
+        }
+        if !added {
+            return Ok(dependents);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use pretty_assertions::assert_eq;
+
+    use super::{DependencyFilter, Dependents, fan_out};
+    use crate::metadata::Fixtures;
+    use crate::program::resolve::{ResolvedCrateExecution, ResolvedPackage, ResolvedProgram};
+
+    /// Metadata of a workspace in `/ws` with the members `app` and `lib`,
+    /// where `app` depends on `lib` and `lib` on `serde` 1.0.200.
+    fn metadata() -> Result<cargo_metadata::Metadata, serde_json::Error> {
+        let package = |name: &str, version: &str, dir: &str| {
+            serde_json::json!({
+                "name": name,
+                "version": version,
+                "id": format!("{name}@{version}"),
+                "dependencies": [],
+                "targets": [],
+                "features": {},
+                "manifest_path": format!("{dir}/Cargo.toml"),
+            })
+        };
+        let node = |id: &str, deps: &[&str]| {
+            serde_json::json!({
+                "id": id,
+                "dependencies": deps,
+                "deps": deps.iter().map(|dep| serde_json::json!({
+                    "name": dep.split('@').next(),
+                    "pkg": dep,
+                    "dep_kinds": [{ "kind": null, "target": null }],
+                })).collect::<Vec<_>>(),
+                "features": [],
+            })
+        };
+        serde_json::from_value(serde_json::json!({
+            "packages": [
+                package("app", "0.1.0", "/ws/app"),
+                package("lib", "0.1.0", "/ws/lib"),
+                package("serde", "1.0.200", "/registry/serde-1.0.200"),
+            ],
+            "workspace_members": ["app@0.1.0", "lib@0.1.0"],
+            "resolve": {
+                "nodes": [
+                    node("app@0.1.0", &["lib@0.1.0"]),
+                    node("lib@0.1.0", &["serde@1.0.200"]),
+                    node("serde@1.0.200", &[]),
+                ],
+                "root": null,
+            },
+            "workspace_root": "/ws",
+            "target_directory": "/ws/target",
+            "version": 1,
+        }))
+    }
+
+    #[test]
+    fn dependents_are_found_directly_or_transitively() -> Result<(), Box<dyn std::error::Error>> {
+        let metadata = metadata()?;
+        let app = Path::new("/ws/app");
+        let lib = Path::new("/ws/lib");
+        let direct = DependencyFilter::new("serde", None, false)?;
+        assert!(direct.matches(&metadata, lib), "lib uses serde");
+        assert!(
+            !direct.matches(&metadata, app),
+            "app uses serde only through lib"
+        );
+        let transitive = DependencyFilter::new("serde", Some("^1.0.100"), true)?;
+        assert!(
+            transitive.matches(&metadata, app),
+            "app uses serde through lib"
+        );
+        let newer = DependencyFilter::new("serde", Some(">=1.0.210"), true)?;
+        assert!(!newer.matches(&metadata, app), "the version does not match");
+        assert!(
+            DependencyFilter::new("serde", Some("one"), false).is_err(),
+            "invalid requirements are reported"
+        );
+        Ok(())
+    }
+
+    /// Metadata of the standalone crate `name` in `dir` depending on
+    /// `dependencies`.
+    fn crate_metadata(
+        dir: &str,
+        name: &str,
+        dependencies: &[&str],
+    ) -> Result<cargo_metadata::Metadata, serde_json::Error> {
+        let id = format!("{name}@0.1.0");
+        serde_json::from_value(serde_json::json!({
+            "packages": [{
+                "name": name,
+                "version": "0.1.0",
+                "id": id,
+                "dependencies": dependencies.iter().map(|dep| serde_json::json!({
+                    "name": dep,
+                    "req": "*",
+                    "kind": "dev",
+                    "optional": false,
+                    "uses_default_features": true,
+                    "features": [],
+                    "target": null,
+                })).collect::<Vec<_>>(),
+                "targets": [],
+                "features": {},
+                "manifest_path": format!("{dir}/Cargo.toml"),
+            }],
+            "workspace_members": [id],
+            "resolve": null,
+            "workspace_root": dir,
+            "target_directory": format!("{dir}/target"),
+            "version": 1,
+        }))
+    }
+
+    #[test]
+    fn dependents_are_added_until_none_are_left() -> Result<(), Box<dyn std::error::Error>> {
+        let workspace = |dir: &str| crate::Workspace {
+            manifest_dir: PathBuf::from(dir),
+            is_standalone: true,
+            repository: None,
+        };
+        let config = crate::Config {
+            workspaces: vec![
+                workspace("/cfe-test/lib"),
+                workspace("/cfe-test/cli"),
+                workspace("/cfe-test/app"),
+                workspace("/cfe-test/other"),
+            ],
+            ..crate::Config::default()
+        };
+        let fixtures = Fixtures::default()
+            .with(
+                Path::new("/cfe-test/cli"),
+                crate_metadata("/cfe-test/cli", "cli", &["app"])?,
+            )
+            .with(
+                Path::new("/cfe-test/app"),
+                crate_metadata("/cfe-test/app", "app", &["lib"])?,
+            )
+            .with(
+                Path::new("/cfe-test/other"),
+                crate_metadata("/cfe-test/other", "other", &["serde"])?,
+            );
+        let resolved = ResolvedProgram {
+            workspace_executions: vec![],
+            crate_executions: vec![ResolvedCrateExecution {
+                manifest_dir: PathBuf::from("/cfe-test/lib"),
+                dependencies: vec![],
+                package: Some(ResolvedPackage {
+                    name: "lib".to_owned(),
+                    version: "0.1.0".to_owned(),
+                }),
+            }],
+        };
+        assert_eq!(
+            fan_out(&resolved, &config, &fixtures)?,
+            Dependents {
+                workspaces: vec![],
+                crates: vec![
+                    PathBuf::from("/cfe-test/app"),
+                    PathBuf::from("/cfe-test/cli")
+                ],
+            }
+        );
+        Ok(())
+    }
+}
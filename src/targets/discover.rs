@@ -0,0 +1,255 @@
+//! Registering all workspaces and crates below a directory at once.
+//!
+//! `target discover --root <dir>` walks the directory tree, collects every
+//! `Cargo.toml` whose path relative to the root matches `--glob` (by default
+//! `**/Cargo.toml`) and registers it like `target add` would, which also
+//! registers the members of a workspace root.  Manifests in directories that
+//! are already registered are skipped, so discovery can be repeated to pick up
+//! new repositories.  Like for nested manifests, `target` and hidden
+//! directories are not searched, and directories or manifests matching an
+//! `--exclude` pattern are left out.
+//!
+//! Patterns use `/` as separator and are matched against the whole relative
+//! path: `*` matches any characters except `/`, `?` one such character, and a
+//! `**` segment any number of directories, including none.
+
+use std::path::{Path, PathBuf};
+
+use tracing::instrument;
+
+use crate::error::Error;
+
+/// Parameters for the discover subcommand
+#[derive(clap::Parser, Debug, Clone)]
+pub struct Parameters {
+    /// the directory to search for manifests
+    #[clap(long)]
+    pub root: PathBuf,
+    /// only register manifests whose path relative to the root matches this pattern
+    #[clap(long, default_value = "**/Cargo.toml")]
+    pub glob: String,
+    /// skip directories and manifests whose path relative to the root matches this pattern (repeatable)
+    #[clap(long)]
+    pub exclude: Vec<String>,
+}
+
+/// Returns `true` if the `/`-separated relative `path` matches `pattern`.
+#[must_use]
+pub fn glob_matches(pattern: &str, path: &str) -> bool {
+    let pattern: Vec<&str> = pattern.split('/').filter(|s| !s.is_empty()).collect();
+    let path: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    segments_match(&pattern, &path)
+}
+
+/// Matches path segments against pattern segments, where `**` matches any
+/// number of segments.
+fn segments_match(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => {
+            (0..=path.len()).any(|skip| path.get(skip..).is_some_and(|p| segments_match(rest, p)))
+        }
+        Some((first, rest)) => path.split_first().is_some_and(|(segment, path_rest)| {
+            segment_matches(first, segment) && segments_match(rest, path_rest)
+        }),
+    }
+}
+
+/// Matches one path segment against a pattern segment with `*` and `?`.
+fn segment_matches(pattern: &str, segment: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let segment: Vec<char> = segment.chars().collect();
+    chars_match(&pattern, &segment)
+}
+
+/// Matches characters against a pattern with `*` and `?`.
+fn chars_match(pattern: &[char], text: &[char]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some(('*', rest)) => {
+            (0..=text.len()).any(|skip| text.get(skip..).is_some_and(|t| chars_match(rest, t)))
+        }
+        Some(('?', rest)) => text
+            .split_first()
+            .is_some_and(|(_, text_rest)| chars_match(rest, text_rest)),
+        Some((c, rest)) => text
+            .split_first()
+            .is_some_and(|(t, text_rest)| t == c && chars_match(rest, text_rest)),
+    }
+}
+
+/// Returns `path` relative to `root` with `/` separators.
+fn relative(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Finds the `Cargo.toml` files below `root` matching `glob` and none of
+/// `excludes`, sorted so that workspace roots come before their members.
+///
+/// # Errors
+///
+/// Returns an error if a directory cannot be read.
+pub fn find_manifests(root: &Path, glob: &str, excludes: &[String]) -> Result<Vec<PathBuf>, Error> {
+    let excluded = |path: &Path| {
+        let relative = relative(root, path);
+        excludes
+            .iter()
+            .any(|pattern| glob_matches(pattern, &relative))
+    };
+    let mut found = Vec::new();
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        for entry in fs_err::read_dir(&dir).map_err(Error::IoError)? {
+            let entry = entry.map_err(Error::IoError)?;
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            let path = entry.path();
+            let file_type = entry.file_type().map_err(Error::IoError)?;
+            if file_type.is_dir() {
+                if !name.starts_with('.') && name != "target" && !excluded(&path) {
+                    pending.push(path);
+                }
+            } else if name == "Cargo.toml"
+                && glob_matches(glob, &relative(root, &path))
+                && !excluded(&path)
+            {
+                found.push(path);
+            }
+        }
+    }
+    found.sort();
+    Ok(found)
+}
+
+/// implementation of the discover subcommand
+///
+/// # Errors
+///
+/// This command can fail if the root cannot be canonicalized, a directory
+/// cannot be read or the configuration cannot be loaded or saved.  Manifests
+/// that cannot be registered are reported and skipped.
+#[instrument]
+#[expect(clippy::print_stdout, reason = "This is part of the UI, not logging")]
+#[expect(clippy::print_stderr, reason = "This is part of the UI, not logging")]
+pub async fn command(parameters: Parameters, environment: crate::Environment) -> Result<(), Error> {
+    let _lock = crate::config_lock::acquire(&environment).await?;
+    let mut config = crate::Config::load(&environment)?;
+    let root = fs_err::canonicalize(&parameters.root).map_err(|err| {
+        Error::CouldNotDetermineCanonicalManifestPath(parameters.root.clone(), err)
+    })?;
+    let manifests = find_manifests(&root, &parameters.glob, &parameters.exclude)?;
+
+    let mut skipped: usize = 0;
+    let mut failed: usize = 0;
+    let workspaces_before = config.workspaces.len();
+    let crates_before = config.crates.len();
+    for manifest_path in &manifests {
+        let Some(manifest_dir) = manifest_path.parent() else {
+            continue;
+        };
+        let registered = config.crates.iter().any(|c| c.manifest_dir == manifest_dir)
+            || config
+                .workspaces
+                .iter()
+                .any(|w| w.manifest_dir == manifest_dir);
+        if registered {
+            skipped = skipped.saturating_add(1);
+            continue;
+        }
+        match super::add_manifest(&mut config, manifest_path) {
+            Ok(workspace_dir) => println!("Added {}", workspace_dir.display()),
+            Err(e) => {
+                eprintln!("Could not add {}: {e}", manifest_dir.display());
+                failed = failed.saturating_add(1);
+            }
+        }
+    }
+    config.save(&environment)?;
+
+    println!(
+        "Found {} manifests: added {} workspaces and {} crates, {skipped} already registered, {failed} failed",
+        manifests.len(),
+        config.workspaces.len().saturating_sub(workspaces_before),
+        config.crates.len().saturating_sub(crates_before),
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::{find_manifests, glob_matches};
+
+    #[test]
+    fn globs_match_relative_paths() {
+        assert!(
+            glob_matches("**/Cargo.toml", "Cargo.toml"),
+            "** matches no directory"
+        );
+        assert!(
+            glob_matches("**/Cargo.toml", "a/b/Cargo.toml"),
+            "** matches several directories"
+        );
+        assert!(
+            glob_matches("crates/*/Cargo.toml", "crates/foo/Cargo.toml"),
+            "* matches a name"
+        );
+        assert!(
+            !glob_matches("crates/*/Cargo.toml", "crates/foo/bar/Cargo.toml"),
+            "* does not match /"
+        );
+        assert!(glob_matches("repo-?", "repo-1"), "? matches one character");
+        assert!(
+            !glob_matches("repo-?", "repo-10"),
+            "? matches only one character"
+        );
+        assert!(
+            glob_matches("vendor/**", "vendor"),
+            "a trailing ** matches the directory"
+        );
+        assert!(
+            glob_matches("**/fixtures", "a/tests/fixtures"),
+            "a leading ** matches any depth"
+        );
+    }
+
+    #[test]
+    fn manifests_are_found_below_the_root() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempfile::tempdir()?;
+        let root = temp_dir.path();
+        for dir in [
+            "a",
+            "a/member",
+            "a/target/debug",
+            "b/.hidden",
+            "vendor/c",
+            "d/tests/fixtures/e",
+        ] {
+            fs_err::create_dir_all(root.join(dir))?;
+            fs_err::write(root.join(dir).join("Cargo.toml"), "")?;
+        }
+        fs_err::write(root.join("a").join("Other.toml"), "")?;
+
+        let found = find_manifests(
+            root,
+            "**/Cargo.toml",
+            &["vendor".to_owned(), "**/fixtures".to_owned()],
+        )?;
+        assert_eq!(
+            found,
+            [root.join("a/Cargo.toml"), root.join("a/member/Cargo.toml"),]
+        );
+        assert_eq!(
+            find_manifests(root, "*/Cargo.toml", &[])?,
+            [root.join("a/Cargo.toml")],
+            "* does not descend into subdirectories"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,141 @@
+//! The git repositories registered workspaces live in.
+//!
+//! Many operations are really "for each repository" rather than "for each
+//! crate", e.g. committing and pushing.  When a workspace is added (or
+//! refreshed), the root of the git checkout containing it and the URL of its
+//! `origin` remote are recorded, so lists and tools can group targets by
+//! repository.  Whether a checkout has uncommitted changes and which branch
+//! is checked out change all the time and are therefore looked up when
+//! filtering instead of being stored.
+
+use std::path::{Path, PathBuf};
+
+use crate::tasks::revision::git_output;
+
+/// The git checkout a workspace lives in.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Repository {
+    /// The top-level directory of the checkout.
+    pub root: PathBuf,
+    /// The URL of the `origin` remote, if there is one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remote_url: Option<String>,
+}
+
+impl Repository {
+    /// Returns the checkout containing `dir`, or `None` if it is not in a git
+    /// repository.
+    #[must_use]
+    pub fn discover(dir: &Path) -> Option<Self> {
+        let root = git_output(dir, &["rev-parse", "--show-toplevel"])?;
+        Some(Self {
+            root: PathBuf::from(root),
+            remote_url: git_output(dir, &["config", "--get", "remote.origin.url"])
+                .filter(|url| !url.is_empty()),
+        })
+    }
+}
+
+/// Returns the branch checked out in the repository containing `dir`, or
+/// `None` for a detached `HEAD` or outside of a repository.
+#[must_use]
+pub fn branch(dir: &Path) -> Option<String> {
+    git_output(dir, &["symbolic-ref", "--quiet", "--short", "HEAD"])
+}
+
+/// Returns `true` if `dir` has uncommitted changes; directories outside of a
+/// repository are never dirty.
+#[must_use]
+pub fn is_dirty(dir: &Path) -> bool {
+    git_output(dir, &["status", "--porcelain", "--", "."]).is_some_and(|status| !status.is_empty())
+}
+
+/// Parameters restricting targets by the state of their git checkout
+#[derive(
+    clap::Parser, Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize,
+)]
+pub struct FilterParameters {
+    /// only list targets with uncommitted changes
+    #[clap(long)]
+    #[serde(default)]
+    pub git_dirty: bool,
+    /// only list targets whose checkout is on this branch
+    #[clap(long, value_name = "NAME")]
+    #[serde(default)]
+    pub git_branch: Option<String>,
+}
+
+impl FilterParameters {
+    /// Returns `true` if the target in `dir` passes the filters.  Without
+    /// filters no git command is run.
+    #[must_use]
+    pub fn matches(&self, dir: &Path) -> bool {
+        (!self.git_dirty || is_dirty(dir))
+            && self
+                .git_branch
+                .as_ref()
+                .is_none_or(|wanted| branch(dir).as_ref() == Some(wanted))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+
+    use pretty_assertions::assert_eq;
+
+    use super::{FilterParameters, Repository, branch, is_dirty};
+
+    /// Runs `git` with `args` in `dir`.
+    fn git(dir: &std::path::Path, args: &[&str]) -> Result<(), Box<dyn std::error::Error>> {
+        let status = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .status()?;
+        assert!(status.success(), "git {args:?} failed");
+        Ok(())
+    }
+
+    #[test]
+    fn repositories_and_their_state_are_detected() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempfile::tempdir()?;
+        let root = fs_err::canonicalize(temp_dir.path())?;
+        let member = root.join("crates").join("a");
+        fs_err::create_dir_all(&member)?;
+        assert_eq!(Repository::discover(&member), None);
+
+        git(&root, &["init", "-q", "-b", "main"])?;
+        git(
+            &root,
+            &["remote", "add", "origin", "https://example.com/r.git"],
+        )?;
+        fs_err::write(member.join("Cargo.toml"), "")?;
+        git(&root, &["add", "."])?;
+        git(&root, &["commit", "-q", "-m", "init"])?;
+        assert_eq!(
+            Repository::discover(&member),
+            Some(Repository {
+                root: root.clone(),
+                remote_url: Some("https://example.com/r.git".to_owned()),
+            })
+        );
+        assert_eq!(branch(&member).as_deref(), Some("main"));
+        assert!(!is_dirty(&member), "a fresh commit is clean");
+
+        fs_err::write(member.join("Cargo.toml"), "[package]\n")?;
+        assert!(is_dirty(&member), "modified files make the checkout dirty");
+        let filters = FilterParameters {
+            git_dirty: true,
+            git_branch: Some("main".to_owned()),
+        };
+        assert!(filters.matches(&member), "dirty checkout on main");
+        let other_branch = FilterParameters {
+            git_dirty: false,
+            git_branch: Some("release".to_owned()),
+        };
+        assert!(!other_branch.matches(&member), "checkout is not on release");
+        Ok(())
+    }
+}
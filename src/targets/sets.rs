@@ -0,0 +1,587 @@
+//! Named sets of hand-picked targets.
+//!
+//! Select filters describe targets by their properties, which does not help
+//! when the targets of an operation are an arbitrary choice, e.g. the crates
+//! one team maintains.  A target set names such a choice: `target set
+//! add-member --name <set> --manifest-path <path>` adds registered workspaces
+//! or crates to the set (creating it), and `select crates where in_set
+//! "<set>"` (or `select workspaces`, `select members` and `if`) uses it.
+//! Sets are stored in the configuration file, so they are shared like the
+//! registered targets themselves.
+//!
+//! `target set compose` defines a set from other sets instead, as their
+//! union, their intersection or the first of them minus the others, e.g. the
+//! crates of a team except those in a `legacy` set.  Composite sets are
+//! resolved whenever they are used, so they follow changes to the sets they
+//! are made of.
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use tracing::instrument;
+
+use crate::error::Error;
+
+/// A named set of targets.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TargetSet {
+    /// The workspaces and crates with these manifest directories.
+    Explicit {
+        /// the manifest directories of the members, in the order they were added
+        manifest_dirs: Vec<PathBuf>,
+    },
+    /// The result of a set operation on other named sets.
+    Composite {
+        /// how the sets are combined
+        operation: SetOperation,
+        /// the names of the sets
+        sets: Vec<String>,
+    },
+}
+
+/// How a composite set combines its sets.
+#[derive(
+    clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum SetOperation {
+    /// the targets in any of the sets
+    Union,
+    /// the targets in all of the sets
+    Intersection,
+    /// the targets in the first set but in none of the others
+    Difference,
+}
+
+impl std::fmt::Display for SetOperation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Union => write!(f, "union"),
+            Self::Intersection => write!(f, "intersection"),
+            Self::Difference => write!(f, "difference"),
+        }
+    }
+}
+
+/// Returns the manifest directories of the members of the set `name`,
+/// resolving composite sets.
+///
+/// # Errors
+///
+/// Returns [`Error::UnknownTargetSet`] if there is no such set or a
+/// composite set refers to one that does not exist, or
+/// [`Error::TargetSetCycle`] if a composite set is composed of itself.
+pub fn members(config: &crate::Config, name: &str) -> Result<BTreeSet<PathBuf>, Error> {
+    resolve_target_set(config, name, &mut Vec::new())
+}
+
+/// Resolves the set `name`; `stack` holds the composite sets being resolved.
+fn resolve_target_set(
+    config: &crate::Config,
+    name: &str,
+    stack: &mut Vec<String>,
+) -> Result<BTreeSet<PathBuf>, Error> {
+    let (operation, sets) = match config.target_sets.get(name) {
+        Some(TargetSet::Explicit { manifest_dirs }) => {
+            return Ok(manifest_dirs.iter().cloned().collect());
+        }
+        Some(TargetSet::Composite { operation, sets }) => (*operation, sets),
+        None => return Err(Error::UnknownTargetSet(name.to_owned())),
+    };
+    if stack.iter().any(|outer| outer == name) {
+        return Err(Error::TargetSetCycle(name.to_owned()));
+    }
+    stack.push(name.to_owned());
+    let mut parts = Vec::new();
+    for set in sets {
+        parts.push(resolve_target_set(config, set, stack)?);
+    }
+    stack.pop();
+    let mut parts = parts.into_iter();
+    let first = parts.next().unwrap_or_default();
+    Ok(parts.fold(first, |result, part| match operation {
+        SetOperation::Union => result.union(&part).cloned().collect(),
+        SetOperation::Intersection => result.intersection(&part).cloned().collect(),
+        SetOperation::Difference => result.difference(&part).cloned().collect(),
+    }))
+}
+
+/// Returns `true` if the target in `manifest_dir` is a member of the set
+/// `name`; unknown sets have no members.
+#[must_use]
+pub fn contains(config: &crate::Config, name: &str, manifest_dir: &Path) -> bool {
+    members(config, name).is_ok_and(|dirs| dirs.contains(manifest_dir))
+}
+
+/// a target set in the JSON output of `target set list`
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct SetView {
+    /// the name of the set
+    pub name: String,
+    /// how a composite set combines its sets
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub operation: Option<SetOperation>,
+    /// the names of the sets a composite set is made of
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub sets: Vec<String>,
+    /// the manifest directories of the members, with composite sets resolved
+    pub members: Vec<PathBuf>,
+    /// why the members of a composite set could not be resolved
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Returns the views of all target sets of `config`, sorted by name.
+#[must_use]
+pub fn views(config: &crate::Config) -> Vec<SetView> {
+    config
+        .target_sets
+        .iter()
+        .map(|(name, set)| {
+            let (operation, sets) = match set {
+                TargetSet::Explicit { .. } => (None, Vec::new()),
+                TargetSet::Composite { operation, sets } => (Some(*operation), sets.clone()),
+            };
+            let (members, error) = match members(config, name) {
+                Ok(dirs) => (dirs.into_iter().collect(), None),
+                Err(e) => (Vec::new(), Some(e.to_string())),
+            };
+            SetView {
+                name: name.clone(),
+                operation,
+                sets,
+                members,
+                error,
+            }
+        })
+        .collect()
+}
+
+/// The target set sub command
+#[derive(clap::Parser, Debug, Clone)]
+pub enum SetSubCommand {
+    /// List the target sets and their members.
+    List,
+    /// Add registered workspaces or crates to a set, creating it if necessary.
+    AddMember(MemberParameters),
+    /// Define a set as the union, intersection or difference of other sets.
+    Compose(ComposeParameters),
+    /// Remove workspaces or crates from a set.
+    RemoveMember(MemberParameters),
+    /// Remove a set.
+    Remove(RemoveParameters),
+}
+
+/// Parameters for the target set subcommand
+#[derive(clap::Parser, Debug, Clone)]
+pub struct Parameters {
+    /// The target set subcommand
+    #[clap(subcommand)]
+    pub sub_command: SetSubCommand,
+}
+
+/// Parameters for adding members to or removing them from a set
+#[derive(clap::Parser, Debug, Clone)]
+pub struct MemberParameters {
+    /// the name of the set
+    #[clap(long)]
+    pub name: String,
+    /// the manifest file (or its directory) of the target (repeatable)
+    #[clap(long, required_unless_present = "tag")]
+    pub manifest_path: Vec<PathBuf>,
+    /// also the registered targets with this tag (repeatable, all of them)
+    #[clap(long)]
+    pub tag: Vec<String>,
+}
+
+/// Parameters for composing a set of other sets
+#[derive(clap::Parser, Debug, Clone)]
+pub struct ComposeParameters {
+    /// the name of the set, which replaces an existing set of that name
+    #[clap(long)]
+    pub name: String,
+    /// how the sets are combined; `difference` removes the members of the
+    /// other sets from the first one
+    #[clap(long)]
+    pub operation: SetOperation,
+    /// a set the new set is composed of (repeatable, in order)
+    #[clap(long = "set", required = true)]
+    pub sets: Vec<String>,
+}
+
+/// Parameters for removing a set
+#[derive(clap::Parser, Debug, Clone)]
+pub struct RemoveParameters {
+    /// the name of the set
+    #[clap(long)]
+    pub name: String,
+}
+
+/// Adds the registered targets in `manifest_dirs` to the explicit set
+/// `name`, creating it if necessary, and returns the ones that were not
+/// members yet.
+///
+/// # Errors
+///
+/// Returns [`Error::NotARegisteredTarget`] if one of the directories is not
+/// registered, or [`Error::CompositeTargetSet`] if the set is composite.
+pub fn add_members(
+    config: &mut crate::Config,
+    name: &str,
+    manifest_dirs: &[PathBuf],
+) -> Result<Vec<PathBuf>, Error> {
+    for dir in manifest_dirs {
+        let registered = config.workspaces.iter().any(|w| &w.manifest_dir == dir)
+            || config.crates.iter().any(|c| &c.manifest_dir == dir);
+        if !registered {
+            return Err(Error::NotARegisteredTarget(dir.clone()));
+        }
+    }
+    let TargetSet::Explicit {
+        manifest_dirs: members,
+    } = config
+        .target_sets
+        .entry(name.to_owned())
+        .or_insert_with(|| TargetSet::Explicit {
+            manifest_dirs: Vec::new(),
+        })
+    else {
+        return Err(Error::CompositeTargetSet(name.to_owned()));
+    };
+    let mut added = Vec::new();
+    for dir in manifest_dirs {
+        if !members.contains(dir) {
+            members.push(dir.clone());
+            added.push(dir.clone());
+        }
+    }
+    Ok(added)
+}
+
+/// Removes `manifest_dirs` from the explicit set `name` and returns the
+/// ones that were members.
+///
+/// # Errors
+///
+/// Returns [`Error::UnknownTargetSet`] if there is no such set, or
+/// [`Error::CompositeTargetSet`] if it is composite.
+pub fn remove_members(
+    config: &mut crate::Config,
+    name: &str,
+    manifest_dirs: &[PathBuf],
+) -> Result<Vec<PathBuf>, Error> {
+    let members = match config.target_sets.get_mut(name) {
+        Some(TargetSet::Explicit { manifest_dirs }) => manifest_dirs,
+        Some(TargetSet::Composite { .. }) => {
+            return Err(Error::CompositeTargetSet(name.to_owned()));
+        }
+        None => return Err(Error::UnknownTargetSet(name.to_owned())),
+    };
+    let removed = manifest_dirs
+        .iter()
+        .filter(|dir| members.contains(dir))
+        .cloned()
+        .collect();
+    members.retain(|dir| !manifest_dirs.contains(dir));
+    Ok(removed)
+}
+
+/// Defines the set `name` as the composition of `sets` by `operation`,
+/// replacing an existing set of that name.
+///
+/// # Errors
+///
+/// Returns [`Error::UnknownTargetSet`] if one of `sets` does not exist, or
+/// [`Error::TargetSetCycle`] if the set would be composed of itself; the
+/// configuration is left unchanged then.
+pub fn compose(
+    config: &mut crate::Config,
+    name: &str,
+    operation: SetOperation,
+    sets: Vec<String>,
+) -> Result<(), Error> {
+    let previous = config
+        .target_sets
+        .insert(name.to_owned(), TargetSet::Composite { operation, sets });
+    if let Err(e) = members(config, name) {
+        match previous {
+            Some(previous) => config.target_sets.insert(name.to_owned(), previous),
+            None => config.target_sets.remove(name),
+        };
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// Returns the names of the composite sets composed of the set `name`.
+#[must_use]
+pub fn composites_using(config: &crate::Config, name: &str) -> Vec<String> {
+    config
+        .target_sets
+        .iter()
+        .filter(|(_, set)| {
+            matches!(set, TargetSet::Composite { sets, .. } if sets.iter().any(|s| s == name))
+        })
+        .map(|(composite, _)| composite.clone())
+        .collect()
+}
+
+/// implementation of the target set subcommand
+///
+/// # Errors
+///
+/// This command can fail if the configuration cannot be loaded or saved, a
+/// manifest path cannot be canonicalized, a target is not registered or a set
+/// does not exist.
+#[instrument]
+#[expect(clippy::print_stdout, reason = "This is part of the UI, not logging")]
+pub async fn command(
+    parameters: Parameters,
+    environment: crate::Environment,
+    run_options: crate::RunOptions,
+) -> Result<(), Error> {
+    // listing only reads the configuration
+    let _lock = if matches!(parameters.sub_command, SetSubCommand::List) {
+        None
+    } else {
+        Some(crate::config_lock::acquire(&environment).await?)
+    };
+    let mut config = crate::Config::load(&environment)?;
+    match parameters.sub_command {
+        SetSubCommand::List => {
+            if crate::porcelain::is_json(run_options.format, None, "target set list")? {
+                println!("{}", crate::porcelain::json(&views(&config))?);
+                return Ok(());
+            }
+            for (name, set) in &config.target_sets {
+                match set {
+                    TargetSet::Explicit { .. } => println!("{name}"),
+                    TargetSet::Composite { operation, sets } => {
+                        println!("{name} ({operation} of {})", sets.join(", "));
+                    }
+                }
+                match members(&config, name) {
+                    Ok(dirs) => {
+                        for dir in dirs {
+                            println!("  {}", dir.display());
+                        }
+                    }
+                    Err(e) => println!("  {e}"),
+                }
+            }
+        }
+        SetSubCommand::AddMember(member_parameters) => {
+            let mut dirs = member_parameters
+                .manifest_path
+                .into_iter()
+                .map(super::canonical_manifest_dir)
+                .collect::<Result<Vec<_>, _>>()?;
+            if !member_parameters.tag.is_empty() {
+                dirs.extend(super::tags::tagged(&config, &member_parameters.tag));
+            }
+            for dir in add_members(&mut config, &member_parameters.name, &dirs)? {
+                println!("Added {} to {}", dir.display(), member_parameters.name);
+            }
+            config.save(&environment)?;
+        }
+        SetSubCommand::Compose(compose_parameters) => {
+            compose(
+                &mut config,
+                &compose_parameters.name,
+                compose_parameters.operation,
+                compose_parameters.sets,
+            )?;
+            config.save(&environment)?;
+        }
+        SetSubCommand::RemoveMember(member_parameters) => {
+            let mut dirs = member_parameters
+                .manifest_path
+                .into_iter()
+                .map(super::canonical_manifest_dir)
+                .collect::<Result<Vec<_>, _>>()?;
+            if !member_parameters.tag.is_empty() {
+                dirs.extend(super::tags::tagged(&config, &member_parameters.tag));
+            }
+            for dir in remove_members(&mut config, &member_parameters.name, &dirs)? {
+                println!("Removed {} from {}", dir.display(), member_parameters.name);
+            }
+            config.save(&environment)?;
+        }
+        SetSubCommand::Remove(remove_parameters) => {
+            let composites = composites_using(&config, &remove_parameters.name);
+            if !composites.is_empty() {
+                return Err(Error::TargetSetInUse(
+                    remove_parameters.name,
+                    composites.join(", "),
+                ));
+            }
+            if config.target_sets.remove(&remove_parameters.name).is_none() {
+                return Err(Error::UnknownTargetSet(remove_parameters.name));
+            }
+            config.save(&environment)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+    use std::path::PathBuf;
+
+    use pretty_assertions::assert_eq;
+
+    use super::{
+        SetOperation, SetView, add_members, compose, contains, members, remove_members, views,
+    };
+    use crate::error::Error;
+
+    #[test]
+    fn explicit_sets_hold_registered_targets() -> Result<(), Box<dyn std::error::Error>> {
+        let mut config = crate::Config::default();
+        config.add_workspace(crate::Workspace {
+            manifest_dir: PathBuf::from("/ws"),
+            is_standalone: false,
+            repository: None,
+        });
+        config.crates.push(crate::Crate {
+            manifest_dir: PathBuf::from("/ws/a"),
+            workspace_manifest_dir: PathBuf::from("/ws"),
+            types: BTreeSet::new(),
+            metadata: crate::targets::PackageMetadata::default(),
+            name: None,
+            version: None,
+        });
+        let dirs = [PathBuf::from("/ws/a"), PathBuf::from("/ws")];
+        assert_eq!(add_members(&mut config, "team", &dirs)?, dirs);
+        assert!(
+            add_members(&mut config, "team", &[PathBuf::from("/ws")])?.is_empty(),
+            "members are only added once"
+        );
+        assert!(
+            matches!(
+                add_members(&mut config, "team", &[PathBuf::from("/elsewhere")]),
+                Err(Error::NotARegisteredTarget(_))
+            ),
+            "only registered targets can be added"
+        );
+        assert!(
+            contains(&config, "team", &PathBuf::from("/ws/a")),
+            "members are in the set"
+        );
+        assert_eq!(
+            remove_members(&mut config, "team", &[PathBuf::from("/ws/a")])?,
+            [PathBuf::from("/ws/a")]
+        );
+        assert_eq!(
+            members(&config, "team")?,
+            BTreeSet::from([PathBuf::from("/ws")])
+        );
+        assert!(
+            matches!(members(&config, "other"), Err(Error::UnknownTargetSet(_))),
+            "unknown sets are reported"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn composite_sets_combine_other_sets() -> Result<(), Box<dyn std::error::Error>> {
+        let mut config = crate::Config::default();
+        let explicit = |dirs: &[&str]| super::TargetSet::Explicit {
+            manifest_dirs: dirs.iter().map(PathBuf::from).collect(),
+        };
+        config
+            .target_sets
+            .insert("team".to_owned(), explicit(&["/a", "/b", "/c"]));
+        config
+            .target_sets
+            .insert("legacy".to_owned(), explicit(&["/b", "/d"]));
+        let set = |dirs: &[&str]| dirs.iter().map(PathBuf::from).collect::<BTreeSet<_>>();
+        let names = |names: &[&str]| names.iter().map(|n| (*n).to_owned()).collect::<Vec<_>>();
+
+        compose(
+            &mut config,
+            "current",
+            SetOperation::Difference,
+            names(&["team", "legacy"]),
+        )?;
+        assert_eq!(members(&config, "current")?, set(&["/a", "/c"]));
+        compose(
+            &mut config,
+            "both",
+            SetOperation::Intersection,
+            names(&["team", "legacy"]),
+        )?;
+        assert_eq!(members(&config, "both")?, set(&["/b"]));
+        compose(
+            &mut config,
+            "all",
+            SetOperation::Union,
+            names(&["current", "legacy"]),
+        )?;
+        assert_eq!(members(&config, "all")?, set(&["/a", "/b", "/c", "/d"]));
+
+        assert!(
+            matches!(
+                compose(&mut config, "current", SetOperation::Union, names(&["all"])),
+                Err(Error::TargetSetCycle(_))
+            ),
+            "cycles are refused"
+        );
+        assert_eq!(
+            members(&config, "current")?,
+            set(&["/a", "/c"]),
+            "a refused composition keeps the previous set"
+        );
+        assert!(
+            matches!(
+                add_members(&mut config, "all", &[]),
+                Err(Error::CompositeTargetSet(_))
+            ),
+            "composite sets have no members of their own"
+        );
+        assert_eq!(
+            super::composites_using(&config, "legacy"),
+            names(&["all", "both", "current"])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn views_resolve_composite_sets() -> Result<(), Box<dyn std::error::Error>> {
+        let mut config = crate::Config::default();
+        config.target_sets.insert(
+            "team".to_owned(),
+            super::TargetSet::Explicit {
+                manifest_dirs: vec![PathBuf::from("/b"), PathBuf::from("/a")],
+            },
+        );
+        compose(
+            &mut config,
+            "everyone",
+            SetOperation::Union,
+            vec!["team".to_owned()],
+        )?;
+        assert_eq!(
+            views(&config),
+            [
+                SetView {
+                    name: "everyone".to_owned(),
+                    operation: Some(SetOperation::Union),
+                    sets: vec!["team".to_owned()],
+                    members: vec![PathBuf::from("/a"), PathBuf::from("/b")],
+                    error: None,
+                },
+                SetView {
+                    name: "team".to_owned(),
+                    operation: None,
+                    sets: vec![],
+                    members: vec![PathBuf::from("/a"), PathBuf::from("/b")],
+                    error: None,
+                },
+            ]
+        );
+        Ok(())
+    }
+}
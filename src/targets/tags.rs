@@ -0,0 +1,300 @@
+//! Free-form tags on registered workspaces and crates.
+//!
+//! Whether a target is standalone or which crate types it has does not say
+//! what it is for, e.g. "client projects" versus "internal tools".  `target
+//! tag add --manifest-path <path> --tag <tag>` attaches such labels to
+//! registered workspaces and crates without touching their manifests; they
+//! are stored in the configuration file next to the targets.
+//!
+//! Crates can also carry tags in the `tags` of their
+//! `[package.metadata.cargo-for-each]` table.  Both kinds count the same:
+//! `target list --tag`/`--not-tag`, `target set add-member --tag` and the
+//! `tagged "tag"` condition of programs match a target if either lists the
+//! tag.
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use tracing::instrument;
+
+use crate::error::Error;
+
+/// Returns `true` if the target in `manifest_dir` is tagged with `tag`, in
+/// the configuration or in its package metadata.
+#[must_use]
+pub fn has_tag(config: &crate::Config, manifest_dir: &Path, tag: &str) -> bool {
+    config
+        .tags
+        .get(manifest_dir)
+        .is_some_and(|tags| tags.contains(tag))
+        || config
+            .crates
+            .iter()
+            .any(|c| c.manifest_dir == manifest_dir && c.metadata.tags.contains(tag))
+}
+
+/// The `--tag` and `--not-tag` filters of the list commands
+#[derive(
+    clap::Parser, Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize,
+)]
+pub struct TagFilterParameters {
+    /// only list targets with this tag (repeatable, all of them)
+    #[clap(long)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tag: Vec<String>,
+    /// do not list targets with this tag (repeatable)
+    #[clap(long)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub not_tag: Vec<String>,
+}
+
+impl TagFilterParameters {
+    /// returns `true` if the target in `manifest_dir` has all of the `--tag`
+    /// tags and none of the `--not-tag` ones
+    #[must_use]
+    pub fn matches(&self, config: &crate::Config, manifest_dir: &Path) -> bool {
+        self.tag
+            .iter()
+            .all(|tag| has_tag(config, manifest_dir, tag))
+            && !self
+                .not_tag
+                .iter()
+                .any(|tag| has_tag(config, manifest_dir, tag))
+    }
+}
+
+/// Returns the registered workspaces and crates with all of `tags`.
+#[must_use]
+pub fn tagged(config: &crate::Config, tags: &[String]) -> Vec<PathBuf> {
+    let filter = TagFilterParameters {
+        tag: tags.to_vec(),
+        not_tag: Vec::new(),
+    };
+    config
+        .workspaces
+        .iter()
+        .map(|w| &w.manifest_dir)
+        .chain(config.crates.iter().map(|c| &c.manifest_dir))
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .filter(|dir| filter.matches(config, dir))
+        .cloned()
+        .collect()
+}
+
+/// Tags the registered targets in `manifest_dirs` with `tags` and returns
+/// the tags each of them did not have yet.
+///
+/// # Errors
+///
+/// Returns [`Error::NotARegisteredTarget`] if one of the directories is not
+/// registered.
+pub fn add(
+    config: &mut crate::Config,
+    manifest_dirs: &[PathBuf],
+    tags: &[String],
+) -> Result<Vec<(PathBuf, String)>, Error> {
+    for dir in manifest_dirs {
+        let registered = config.workspaces.iter().any(|w| &w.manifest_dir == dir)
+            || config.crates.iter().any(|c| &c.manifest_dir == dir);
+        if !registered {
+            return Err(Error::NotARegisteredTarget(dir.clone()));
+        }
+    }
+    let mut added = Vec::new();
+    for dir in manifest_dirs {
+        let target_tags = config.tags.entry(dir.clone()).or_default();
+        for tag in tags {
+            if target_tags.insert(tag.clone()) {
+                added.push((dir.clone(), tag.clone()));
+            }
+        }
+    }
+    Ok(added)
+}
+
+/// Removes `tags` from the targets in `manifest_dirs` and returns the ones
+/// that were removed.  Tags from package metadata cannot be removed here.
+pub fn remove(
+    config: &mut crate::Config,
+    manifest_dirs: &[PathBuf],
+    tags: &[String],
+) -> Vec<(PathBuf, String)> {
+    let mut removed = Vec::new();
+    for dir in manifest_dirs {
+        let Some(target_tags) = config.tags.get_mut(dir) else {
+            continue;
+        };
+        for tag in tags {
+            if target_tags.remove(tag) {
+                removed.push((dir.clone(), tag.clone()));
+            }
+        }
+        if target_tags.is_empty() {
+            config.tags.remove(dir);
+        }
+    }
+    removed
+}
+
+/// The target tag sub command
+#[derive(clap::Parser, Debug, Clone)]
+pub enum TagSubCommand {
+    /// List the tags of all tagged targets.
+    List,
+    /// Tag registered workspaces or crates.
+    Add(TagParameters),
+    /// Remove tags from workspaces or crates.
+    Remove(TagParameters),
+}
+
+/// Parameters for the target tag subcommand
+#[derive(clap::Parser, Debug, Clone)]
+pub struct Parameters {
+    /// The target tag subcommand
+    #[clap(subcommand)]
+    pub sub_command: TagSubCommand,
+}
+
+/// Parameters for adding tags to or removing them from targets
+#[derive(clap::Parser, Debug, Clone)]
+pub struct TagParameters {
+    /// the manifest file (or its directory) of the target (repeatable)
+    #[clap(long, required = true)]
+    pub manifest_path: Vec<PathBuf>,
+    /// the tag (repeatable)
+    #[clap(long, required = true)]
+    pub tag: Vec<String>,
+}
+
+/// implementation of the target tag subcommand
+///
+/// # Errors
+///
+/// This command can fail if the configuration cannot be loaded or saved, a
+/// manifest path cannot be canonicalized or a target is not registered.
+#[instrument]
+#[expect(clippy::print_stdout, reason = "This is part of the UI, not logging")]
+pub async fn command(parameters: Parameters, environment: crate::Environment) -> Result<(), Error> {
+    let _lock = crate::config_lock::acquire(&environment).await?;
+    let mut config = crate::Config::load(&environment)?;
+    match parameters.sub_command {
+        TagSubCommand::List => {
+            let dirs: BTreeSet<&PathBuf> = config
+                .tags
+                .keys()
+                .chain(
+                    config
+                        .crates
+                        .iter()
+                        .filter(|c| !c.metadata.tags.is_empty())
+                        .map(|c| &c.manifest_dir),
+                )
+                .collect();
+            for dir in dirs {
+                let mut tags: BTreeSet<&String> =
+                    config.tags.get(dir).into_iter().flatten().collect();
+                tags.extend(
+                    config
+                        .crates
+                        .iter()
+                        .filter(|c| &c.manifest_dir == dir)
+                        .flat_map(|c| &c.metadata.tags),
+                );
+                let tags: Vec<&str> = tags.into_iter().map(String::as_str).collect();
+                println!("{}: {}", dir.display(), tags.join(", "));
+            }
+        }
+        TagSubCommand::Add(tag_parameters) => {
+            let dirs = tag_parameters
+                .manifest_path
+                .into_iter()
+                .map(super::canonical_manifest_dir)
+                .collect::<Result<Vec<_>, _>>()?;
+            for (dir, tag) in add(&mut config, &dirs, &tag_parameters.tag)? {
+                println!("Tagged {} with {tag}", dir.display());
+            }
+            config.save(&environment)?;
+        }
+        TagSubCommand::Remove(tag_parameters) => {
+            let dirs = tag_parameters
+                .manifest_path
+                .into_iter()
+                .map(super::canonical_manifest_dir)
+                .collect::<Result<Vec<_>, _>>()?;
+            for (dir, tag) in remove(&mut config, &dirs, &tag_parameters.tag) {
+                println!("Removed tag {tag} from {}", dir.display());
+            }
+            config.save(&environment)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+    use std::path::PathBuf;
+
+    use pretty_assertions::assert_eq;
+
+    use super::{TagFilterParameters, add, remove, tagged};
+    use crate::error::Error;
+
+    #[test]
+    fn tags_come_from_the_config_and_package_metadata() -> Result<(), Box<dyn std::error::Error>> {
+        let mut config = crate::Config::default();
+        config.add_workspace(crate::Workspace {
+            manifest_dir: PathBuf::from("/ws"),
+            is_standalone: false,
+            repository: None,
+        });
+        config.crates.push(crate::Crate {
+            manifest_dir: PathBuf::from("/ws/a"),
+            workspace_manifest_dir: PathBuf::from("/ws"),
+            types: BTreeSet::new(),
+            metadata: crate::targets::PackageMetadata {
+                tags: BTreeSet::from(["cli".to_owned()]),
+                ..crate::targets::PackageMetadata::default()
+            },
+            name: None,
+            version: None,
+        });
+        let client = ["client".to_owned()];
+        assert_eq!(
+            add(&mut config, &[PathBuf::from("/ws")], &client)?,
+            [(PathBuf::from("/ws"), "client".to_owned())]
+        );
+        assert!(
+            add(&mut config, &[PathBuf::from("/ws")], &client)?.is_empty(),
+            "tags are only added once"
+        );
+        assert!(
+            matches!(
+                add(&mut config, &[PathBuf::from("/elsewhere")], &client),
+                Err(Error::NotARegisteredTarget(_))
+            ),
+            "only registered targets can be tagged"
+        );
+        assert_eq!(tagged(&config, &client), [PathBuf::from("/ws")]);
+        assert_eq!(
+            tagged(&config, &["cli".to_owned()]),
+            [PathBuf::from("/ws/a")]
+        );
+        let filter = TagFilterParameters {
+            tag: vec![],
+            not_tag: client.to_vec(),
+        };
+        assert!(
+            !filter.matches(&config, &PathBuf::from("/ws"))
+                && filter.matches(&config, &PathBuf::from("/ws/a")),
+            "--not-tag leaves out tagged targets"
+        );
+        assert_eq!(
+            remove(&mut config, &[PathBuf::from("/ws")], &client).len(),
+            1
+        );
+        assert!(config.tags.is_empty(), "targets without tags are dropped");
+        Ok(())
+    }
+}
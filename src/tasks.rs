@@ -4,21 +4,66 @@
 //! run for each workspace and crate.  This module handles task creation,
 //! execution (sequential and parallel), rewinding, and status display.
 
-use std::collections::HashMap;
-use std::io::{self, Write as _};
+pub mod approval;
+pub mod cancellation;
+pub mod clean_git;
+pub mod completion_cache;
+pub mod dry_run;
+pub mod elevation;
+pub mod export;
+pub mod failure_history;
+pub mod failure_output;
+pub mod feature_powerset;
+pub mod filters;
+pub mod fingerprint;
+pub mod invocation;
+pub mod overlap;
+pub mod pending;
+pub mod phases;
+pub mod prerequisites;
+pub mod program_source;
+pub mod progress;
+pub mod rate_limit;
+pub mod recorder;
+pub mod references;
+pub mod report;
+pub mod reset;
+pub mod resolution_inputs;
+pub mod resources;
+pub mod revision;
+pub mod run_lock;
+pub mod sandbox;
+pub mod scheduling;
+pub mod skip_marker;
+pub mod status;
+pub mod step_duration;
+pub mod step_logs;
+pub mod step_timeout;
+pub mod target_changes;
+pub mod target_gate;
+pub mod target_log;
+pub mod target_refresh;
+pub mod variables;
+pub mod virtual_workspace;
+pub mod worktree_diff;
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{self, Read as _, Write as _};
 use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::sync::Arc;
 
 use cargo_metadata::MetadataCommand;
-use futures::stream::{self, StreamExt as _};
-use tracing::instrument;
+use futures::stream::{FuturesUnordered, StreamExt as _};
+use tracing::{Instrument as _, instrument};
 
 use crate::error::Error;
+use crate::metadata::{Cached, Subprocess};
 use crate::program::ast::common::{
-    ManualStepNode, RunStep, SnapshotMetadataNode, WaitForContinueNode,
+    CargoStep, Elevation, ManualStepNode, RunStep, SnapshotMetadataNode, StdinSource,
+    WaitForContinueNode, WaitUntilNode,
 };
-use crate::program::ast::crate_ctx::{CrateIfBlock, CrateStatement};
+use crate::program::ast::crate_ctx::{CrateIfBlock, CrateStatement, FeaturePowersetNode};
 use crate::program::ast::workspace_ctx::{WorkspaceIfBlock, WorkspaceStatement};
 use crate::program::cursor::{CursorSegment, ProgramCursor};
 use crate::program::evaluate::{
@@ -26,11 +71,12 @@ use crate::program::evaluate::{
     workspace_condition_runtime_detail,
 };
 use crate::program::resolve::{
-    ResolvedCrateExecution, ResolvedProgram, ResolvedWorkspaceExecution,
+    ResolvedCrateExecution, ResolvedPackage, ResolvedProgram, ResolvedWorkspaceExecution,
 };
 use crate::program::{GlobalStatement, Program};
-use crate::{Config, Environment};
+use crate::{Config, Environment, porcelain};
 use clap::Parser;
+use scheduling::{ScheduleStrategy, Scheduler, sort_by_priority, target_priorities};
 
 // ── Path helpers ───────────────────────────────────────────────────────────────
 
@@ -133,6 +179,67 @@ fn load_env_vars_from_files(
     Ok(vars)
 }
 
+/// Returns `true` if `path` is `-`, which stands for standard input.
+fn is_stdin(path: &Path) -> bool {
+    path.as_os_str() == "-"
+}
+
+/// Reads all of standard input.
+///
+/// # Errors
+///
+/// Returns an error if standard input cannot be read or is not UTF-8.
+fn read_stdin() -> Result<String, io::Error> {
+    let mut content = String::new();
+    io::stdin().read_to_string(&mut content)?;
+    Ok(content)
+}
+
+/// Parses a list of target directories, one per line; empty lines and lines
+/// starting with `#` are ignored.
+fn parse_target_list(content: &str) -> Vec<PathBuf> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(PathBuf::from)
+        .collect()
+}
+
+/// Reads a list of target directories from the file at `path`, or from
+/// standard input if `path` is `-`.
+///
+/// # Errors
+///
+/// Returns an error if the list cannot be read.
+fn read_target_list(path: &Path) -> Result<Vec<PathBuf>, Error> {
+    let content = if is_stdin(path) {
+        read_stdin()
+    } else {
+        fs_err::read_to_string(path)
+    }
+    .map_err(|e| Error::CouldNotReadTargetList(path.to_path_buf(), e))?;
+    Ok(parse_target_list(&content))
+}
+
+/// Returns the environment variables for statements run for the crate at
+/// `manifest_dir`: those declared in its `[package.metadata.cargo-for-each]`,
+/// followed by `extra_env`, which takes precedence.
+fn crate_env(
+    config: &Config,
+    manifest_dir: &Path,
+    extra_env: &[(String, String)],
+) -> Vec<(String, String)> {
+    let mut vars = config
+        .crates
+        .iter()
+        .find(|c| c.manifest_dir == manifest_dir)
+        .map(|c| c.metadata.env_vars())
+        .unwrap_or_default();
+    vars.extend_from_slice(extra_env);
+    vars
+}
+
 // ── CLI parameter structs ──────────────────────────────────────────────────────
 
 /// Parameters for creating a new task.
@@ -141,7 +248,8 @@ pub struct CreateTaskParameters {
     /// The name of the task.
     #[clap(long)]
     pub name: String,
-    /// Path to the `.cfe` program file that defines the task steps.
+    /// Path to the `.cfe` program file that defines the task steps, or `-`
+    /// to read the program from standard input.
     #[clap(long)]
     pub program: PathBuf,
     /// Explicit workspace directory paths to run the task against.
@@ -158,6 +266,43 @@ pub struct CreateTaskParameters {
     /// computed automatically.  May be specified multiple times.
     #[clap(long = "crate", value_name = "PATH")]
     pub crates: Vec<PathBuf>,
+    /// File listing explicit workspace directories, one per line, or `-` to
+    /// read the list from standard input; added to `--workspace`.
+    #[clap(long, value_name = "FILE")]
+    pub workspaces_from: Option<PathBuf>,
+    /// File listing explicit crate directories, one per line, or `-` to
+    /// read the list from standard input; added to `--crate`.
+    #[clap(long, value_name = "FILE")]
+    pub crates_from: Option<PathBuf>,
+    /// Create the task even if commands used by the program are not installed,
+    /// e.g. when it will run on another machine.
+    #[clap(long)]
+    pub skip_command_check: bool,
+    /// Register crates that selected crates depend on by `path` but that are
+    /// not registered yet, and resolve the program again with them.
+    #[clap(long)]
+    pub register_path_dependencies: bool,
+    /// Also run the task on registered workspaces and crates depending on
+    /// the selected crates, and on those depending on them in turn.
+    #[clap(long)]
+    pub include_dependents: bool,
+}
+
+/// Parameters for creating one task per registered multi-crate workspace.
+#[derive(Parser, Debug, Clone)]
+pub struct CreatePerWorkspaceParameters {
+    /// Path to the `.cfe` program file that defines the task steps, or `-`
+    /// to read the program from standard input.
+    #[clap(long)]
+    pub program: PathBuf,
+    /// Prefix of the task names; each task is named after the directory of
+    /// its workspace.
+    #[clap(long, default_value = "ws-")]
+    pub prefix: String,
+    /// Create the tasks even if commands used by the program are not
+    /// installed, e.g. when they will run on another machine.
+    #[clap(long)]
+    pub skip_command_check: bool,
 }
 
 /// Parameters for running the next single uncompleted statement of a task.
@@ -178,6 +323,10 @@ pub struct RunSingleTargetParameters {
 
 /// Parameters for running a task across all targets in dependency order.
 #[derive(Parser, Debug, Clone)]
+#[expect(
+    clippy::struct_excessive_bools,
+    reason = "these are independent command line flags"
+)]
 pub struct RunAllTargetsParameters {
     /// The name of the task.
     #[clap(long)]
@@ -188,6 +337,34 @@ pub struct RunAllTargetsParameters {
     /// Continue running even when some targets fail (similar to `make -k`).
     #[clap(short = 'k', long)]
     pub keep_going: bool,
+    /// With `--keep-going`, abort the whole run once this many targets
+    /// failed, since that many failures usually have a common cause.
+    #[clap(long, value_name = "N", requires = "keep_going")]
+    pub max_failures: Option<usize>,
+    /// Order in which targets that are ready at the same time are started.
+    #[clap(long, value_enum, default_value_t = ScheduleStrategy::Fifo)]
+    pub schedule: ScheduleStrategy,
+    /// Start ready targets that failed in earlier runs (according to the run
+    /// journal) first, most recent failures first; `--schedule` orders the
+    /// rest.
+    #[clap(long)]
+    pub fail_fast_order: bool,
+    /// Stop every target at the end of the named phase.
+    #[clap(long, conflicts_with = "only_phase")]
+    pub until_phase: Option<String>,
+    /// Only run the statements of the named phase; a target stops at its
+    /// first uncompleted statement outside of that phase.
+    #[clap(long)]
+    pub only_phase: Option<String>,
+    /// Ask before starting each target whether to continue with it, skip it
+    /// or abort the run.
+    #[clap(long, visible_alias = "confirm-each-target", conflicts_with = "jobs")]
+    pub interactive: bool,
+    /// With more than one job, show the output of the targets as it happens,
+    /// each line prefixed with the name of its target, instead of writing it
+    /// to their log files.
+    #[clap(long)]
+    pub prefix_output: bool,
 }
 
 /// The `task run` subcommand.
@@ -203,7 +380,47 @@ pub enum TaskRunSubCommand {
 
 /// Parameters for the `task run` subcommand.
 #[derive(Parser, Debug, Clone)]
+#[expect(
+    clippy::struct_excessive_bools,
+    reason = "these are independent command line flags"
+)]
 pub struct TaskRunParameters {
+    /// Run each `run` step in a bubblewrap sandbox that only allows writes to
+    /// the target directory, its cargo target directory and `CARGO_HOME`.
+    #[clap(long, global = true)]
+    pub sandbox: bool,
+    /// Fail instead of warning if the configuration or the manifests and
+    /// lockfiles of the targets changed since the task was created.
+    #[clap(long, global = true)]
+    pub strict: bool,
+    /// Block targets with uncommitted changes in their git working tree
+    /// instead of running them, as with `require_clean_git;` in the program.
+    #[clap(long, global = true)]
+    pub require_clean_git: bool,
+    /// Run steps without recording their output, regardless of the
+    /// program's `record` statement.
+    #[clap(long, global = true)]
+    pub no_record: bool,
+    /// Run even if another task is running on some of the same targets,
+    /// only printing a warning.
+    #[clap(long, global = true)]
+    pub force: bool,
+    /// Only print which statements would run where, with their command
+    /// lines and environment, without running anything.
+    #[clap(long, global = true)]
+    pub dry_run: bool,
+    /// Refresh the registered workspaces of the task and resolve its
+    /// program again before running, e.g. after pulling new commits.
+    #[clap(long, global = true, conflicts_with = "dry_run")]
+    pub refresh_targets: bool,
+    /// Print which target waits for which exclusive resource, who holds it
+    /// and who else is queued for it.
+    #[clap(long, global = true)]
+    pub explain_blocking: bool,
+    /// Continue targets whose toolchain, program or build variables changed
+    /// since their earlier steps ran, instead of blocking them.
+    #[clap(long, global = true)]
+    pub accept_environment_change: bool,
     /// The `task run` subcommand to run.
     #[clap(subcommand)]
     pub sub_command: TaskRunSubCommand,
@@ -233,6 +450,24 @@ pub struct RewindAllTargetsParameters {
     pub name: String,
 }
 
+/// Parameters for clearing the state of selected targets or steps of a task.
+#[derive(Parser, Debug, Clone)]
+pub struct ResetTaskParameters {
+    /// The name of the task.
+    #[clap(long)]
+    pub name: String,
+    /// Only reset the target in this directory (or with this `Cargo.toml`).
+    #[clap(long)]
+    pub target: Option<PathBuf>,
+    /// Only reset the top-level statement with this index (the `s<n>` of its
+    /// cursor) of each target.
+    #[clap(long)]
+    pub step: Option<usize>,
+    /// Only reset steps that failed or timed out.
+    #[clap(long)]
+    pub only_failed: bool,
+}
+
 /// The `task rewind` subcommand.
 #[derive(Parser, Debug, Clone)]
 pub enum TaskRewindSubCommand {
@@ -263,13 +498,81 @@ pub struct ContinueBarrierParameters {
     pub cursor: String,
 }
 
+/// Parameters for handing a manual step off to another person.
+#[derive(Parser, Debug, Clone)]
+pub struct HandoffParameters {
+    /// The name of the task.
+    #[clap(long)]
+    pub name: String,
+    /// Cursor path of the manual step (e.g. `w0/s2/`), as shown by `task describe`.
+    #[clap(long)]
+    pub cursor: String,
+    /// The person who is now responsible for the manual step.
+    #[clap(long)]
+    pub to: String,
+}
+
+/// Parameters for printing the output of the steps of a target.
+#[derive(Parser, Debug, Clone)]
+pub struct LogsParameters {
+    /// The name of the task.
+    #[clap(long)]
+    pub name: String,
+    /// The directory (or `Cargo.toml`) of the target.
+    #[clap(long)]
+    pub target: PathBuf,
+    /// Only show the top-level statement with this index (the `s<n>` of its
+    /// cursor) and the steps nested in it.
+    #[clap(long)]
+    pub step: Option<usize>,
+    /// Only show the last N lines of each log.
+    #[clap(long, value_name = "N")]
+    pub tail: Option<usize>,
+    /// Which output to show.
+    #[clap(long, value_enum, default_value_t = step_logs::Stream::Both)]
+    pub stream: step_logs::Stream,
+}
+
+/// Parameters for showing the changes in the targets of a task.
+#[derive(Parser, Debug, Clone)]
+pub struct DiffParameters {
+    /// The name of the task.
+    #[clap(long)]
+    pub name: String,
+    /// Show the complete diffs instead of the changed files.
+    #[clap(long)]
+    pub full: bool,
+}
+
+/// Parameters for resolving the targets of a task again.
+#[derive(Parser, Debug, Clone)]
+pub struct RefreshTaskParameters {
+    /// The name of the task.
+    #[clap(long)]
+    pub name: String,
+    /// Also refresh the registered workspaces the task runs on first, e.g.
+    /// after pulling new commits.
+    #[clap(long)]
+    pub refresh_workspaces: bool,
+}
+
+/// Parameters for removing the lock of a task.
+#[derive(Parser, Debug, Clone)]
+pub struct UnlockParameters {
+    /// The name of the task.
+    #[clap(long)]
+    pub name: String,
+}
+
 /// The `task` subcommand.
 #[derive(Parser, Debug, Clone)]
 pub enum TaskSubCommand {
     /// List all tasks.
-    List,
+    List(ListTasksParameters),
     /// Create a new task.
     Create(CreateTaskParameters),
+    /// Create one task per registered multi-crate workspace from the same program.
+    CreatePerWorkspace(CreatePerWorkspaceParameters),
     /// Remove a task.
     Remove(RemoveTaskParameters),
     /// Describe a task and its current execution status.
@@ -278,8 +581,98 @@ pub enum TaskSubCommand {
     Run(TaskRunParameters),
     /// Rewind a task.
     Rewind(TaskRewindParameters),
+    /// Clear the state of selected targets or steps so they run again.
+    Reset(ResetTaskParameters),
     /// Release a wait barrier so execution can continue past it.
     Continue(ContinueBarrierParameters),
+    /// Export the task as a standalone shell script.
+    ExportScript(ExportScriptParameters),
+    /// Replace the task's copy of its program with the current source file.
+    SyncProgram(SyncProgramParameters),
+    /// Assign a manual step of one target to another person.
+    Handoff(HandoffParameters),
+    /// Remove the lock of a `task run` that is no longer running.
+    Unlock(UnlockParameters),
+    /// Show how resolving the program again would change the task's targets.
+    DiffTargets(DiffTargetsParameters),
+    /// Copy a task under a new name, e.g. to reuse a finished release task.
+    Clone(CloneTaskParameters),
+    /// Summarize the status of the targets of several tasks in one table.
+    Report(ReportParameters),
+    /// Show a matrix of the task's targets and statements with their progress.
+    Status(StatusTaskParameters),
+    /// Print the standard output and error of the `run` steps of a target.
+    Logs(LogsParameters),
+    /// Show the uncommitted changes in the targets of a task, e.g. before
+    /// they are committed.
+    Diff(DiffParameters),
+    /// Resolve the task's program again and store the new targets, keeping
+    /// the progress of targets that are still selected.
+    Refresh(RefreshTaskParameters),
+}
+
+impl TaskSubCommand {
+    /// Returns the name of the task this subcommand operates on, if any.
+    #[must_use]
+    pub fn task_name(&self) -> Option<&str> {
+        match self {
+            Self::List(_) | Self::CreatePerWorkspace(_) => None,
+            Self::Report(p) => p.name.as_deref(),
+            Self::Create(p) => Some(&p.name),
+            Self::Remove(p) => Some(&p.name),
+            Self::Describe(p) => Some(&p.name),
+            Self::Status(p) => Some(&p.name),
+            Self::Logs(p) => Some(&p.name),
+            Self::Diff(p) => Some(&p.name),
+            Self::Refresh(p) => Some(&p.name),
+            Self::Run(p) => Some(match &p.sub_command {
+                TaskRunSubCommand::SingleStep(p) => &p.name,
+                TaskRunSubCommand::SingleTarget(p) => &p.name,
+                TaskRunSubCommand::AllTargets(p) => &p.name,
+            }),
+            Self::Reset(p) => Some(&p.name),
+            Self::Rewind(p) => Some(match &p.sub_command {
+                TaskRewindSubCommand::SingleStep(p) => &p.name,
+                TaskRewindSubCommand::SingleTarget(p) => &p.name,
+                TaskRewindSubCommand::AllTargets(p) => &p.name,
+            }),
+            Self::Continue(p) => Some(&p.name),
+            Self::ExportScript(p) => Some(&p.name),
+            Self::SyncProgram(p) => Some(&p.name),
+            Self::Handoff(p) => Some(&p.name),
+            Self::Unlock(p) => Some(&p.name),
+            Self::DiffTargets(p) => Some(&p.name),
+            Self::Clone(p) => Some(&p.to),
+        }
+    }
+
+    /// Returns whether this subcommand changes the state of a task or runs
+    /// its steps, i.e. whether it is recorded in the run journal.
+    #[must_use]
+    pub const fn is_journalled(&self) -> bool {
+        match self {
+            Self::List(_)
+            | Self::Describe(_)
+            | Self::Report(_)
+            | Self::Status(_)
+            | Self::Logs(_)
+            | Self::Diff(_)
+            | Self::DiffTargets(_)
+            | Self::ExportScript(_) => false,
+            Self::Run(p) => !p.dry_run,
+            Self::Create(_)
+            | Self::CreatePerWorkspace(_)
+            | Self::Remove(_)
+            | Self::Rewind(_)
+            | Self::Reset(_)
+            | Self::Continue(_)
+            | Self::SyncProgram(_)
+            | Self::Handoff(_)
+            | Self::Unlock(_)
+            | Self::Clone(_)
+            | Self::Refresh(_) => true,
+        }
+    }
 }
 
 /// Parameters for removing a task.
@@ -288,6 +681,59 @@ pub struct RemoveTaskParameters {
     /// The name of the task.
     #[clap(long)]
     pub name: String,
+    /// Remove the task even while it is being run.
+    #[clap(long)]
+    pub force: bool,
+}
+
+/// Parameters for listing tasks.
+#[derive(Parser, Debug, Clone)]
+pub struct ListTasksParameters {
+    /// Print stable tab-separated output for scripts (see the porcelain module docs).
+    #[clap(
+        long,
+        value_enum,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "v1"
+    )]
+    pub porcelain: Option<porcelain::Version>,
+}
+
+/// A task in the JSON output of `task list`.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct TaskView {
+    /// The name of the task.
+    pub name: String,
+    /// The program file the task was created from, if it was recorded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub program: Option<PathBuf>,
+    /// The number of targets in each status (see `task report`); empty if
+    /// the task cannot be loaded.
+    pub targets: std::collections::BTreeMap<&'static str, usize>,
+}
+
+impl TaskView {
+    /// Summarizes the task `name`, ignoring parts that cannot be loaded.
+    fn summarize(name: String, environment: &Environment) -> Self {
+        let program = named_dir_path(&name, environment)
+            .ok()
+            .and_then(|dir| program_source::read(&dir).ok().flatten())
+            .map(|record| record.path);
+        let targets = report::target_statuses(&name, environment)
+            .map(|statuses| {
+                report::counts(&statuses)
+                    .into_iter()
+                    .map(|(status, count)| (status.label(), count))
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self {
+            name,
+            program,
+            targets,
+        }
+    }
 }
 
 /// Parameters for describing a task and its current execution status.
@@ -296,6 +742,147 @@ pub struct DescribeTaskParameters {
     /// The name of the task.
     #[clap(long)]
     pub name: String,
+    /// Print stable tab-separated output for scripts (see the porcelain module docs).
+    #[clap(
+        long,
+        value_enum,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "v1"
+    )]
+    pub porcelain: Option<porcelain::Version>,
+}
+
+/// The version of the JSON document printed by `task describe --format json`.
+///
+/// It is increased when a field is renamed or removed or changes its meaning,
+/// including the fields of program statements; adding fields keeps it.
+pub const DESCRIPTION_SCHEMA_VERSION: u32 = 1;
+
+/// The JSON output of `task describe`: the parsed program with every field of
+/// every statement and the targets it was resolved to.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct TaskDescription<'a> {
+    /// Always [`DESCRIPTION_SCHEMA_VERSION`].
+    pub schema_version: u32,
+    /// The name of the task.
+    pub name: &'a str,
+    /// The program file the task was created from, if it was recorded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<ProgramSourceView<'a>>,
+    /// The task's copy of the program.
+    pub program: &'a Program,
+    /// The workspaces and crates the program runs on, in execution order.
+    pub targets: &'a ResolvedProgram,
+}
+
+/// The program file of a task in the JSON output of `task describe`.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ProgramSourceView<'a> {
+    /// Absolute path of the `.cfe` file.
+    pub path: &'a Path,
+    /// Whether the file changed since the task was created.
+    pub status: program_source::Status,
+}
+
+/// Parameters for exporting a task as a standalone shell script.
+#[derive(Parser, Debug, Clone)]
+pub struct ExportScriptParameters {
+    /// The name of the task.
+    #[clap(long)]
+    pub name: String,
+    /// File to write the script to; printed to stdout if omitted.
+    #[clap(long)]
+    pub output: Option<PathBuf>,
+}
+
+/// Parameters for updating a task's program from its source file.
+#[derive(Parser, Debug, Clone)]
+pub struct SyncProgramParameters {
+    /// The name of the task.
+    #[clap(long)]
+    pub name: String,
+    /// Program file to copy; defaults to the file the task was created from.
+    #[clap(long)]
+    pub program: Option<PathBuf>,
+    /// Replace the program even if it invalidates recorded execution state.
+    #[clap(long)]
+    pub force: bool,
+}
+
+/// Parameters for copying a task under a new name.
+#[derive(Parser, Debug, Clone)]
+pub struct CloneTaskParameters {
+    /// The name of the task to copy.
+    #[clap(long)]
+    pub from: String,
+    /// The name of the new task.
+    #[clap(long)]
+    pub to: String,
+    /// Start the new task without any completed steps.
+    #[clap(long)]
+    pub reset_state: bool,
+    /// Resolve the program against the current configuration instead of
+    /// copying the targets; the state refers to the old targets, so this
+    /// requires `--reset-state`.
+    #[clap(long, requires = "reset_state")]
+    pub resolve_again: bool,
+}
+
+/// Parameters for comparing a task's targets with a fresh resolution.
+#[derive(Parser, Debug, Clone)]
+pub struct DiffTargetsParameters {
+    /// The name of the task.
+    #[clap(long)]
+    pub name: String,
+    /// Print stable tab-separated output for scripts (see the porcelain module docs).
+    #[clap(
+        long,
+        value_enum,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "v1"
+    )]
+    pub porcelain: Option<porcelain::Version>,
+}
+
+/// Parameters for showing the progress matrix of a task.
+#[derive(Parser, Debug, Clone)]
+pub struct StatusTaskParameters {
+    /// The name of the task.
+    #[clap(long)]
+    pub name: String,
+    /// Also print the command line, working directory and environment each
+    /// executed `run` step was started with, how long it ran and the size
+    /// of its output.
+    #[clap(long)]
+    pub verbose: bool,
+    /// How durations and timestamps are shown.
+    #[clap(flatten)]
+    pub style: crate::formatting::Style,
+}
+
+/// Parameters for summarizing several tasks in one report.
+#[derive(Parser, Debug, Clone)]
+pub struct ReportParameters {
+    /// The names of the tasks, separated by commas; each gets a column.
+    #[clap(
+        long,
+        value_delimiter = ',',
+        required_unless_present = "name",
+        conflicts_with = "name"
+    )]
+    pub tasks: Vec<String>,
+    /// Report every executed step of this task instead, with its result,
+    /// duration and output files.
+    #[clap(long)]
+    pub name: Option<String>,
+    /// Write the report to this file instead of standard output.
+    #[clap(long)]
+    pub output: Option<PathBuf>,
+    /// How durations and timestamps are shown.
+    #[clap(flatten)]
+    pub style: crate::formatting::Style,
 }
 
 /// Parameters for the `task` top-level subcommand.
@@ -371,6 +958,18 @@ fn is_run_failed(state_dir: &Path) -> bool {
     }
 }
 
+/// Returns the person responsible for the `manual_step` at `state_dir`.
+///
+/// An assignment recorded with `task handoff` takes precedence over the
+/// `assignee` given in the program.
+fn manual_step_assignee(step: &ManualStepNode, state_dir: &Path) -> Option<String> {
+    fs_err::read_to_string(state_dir.join("assignee"))
+        .ok()
+        .map(|s| s.trim().to_owned())
+        .filter(|s| !s.is_empty())
+        .or_else(|| step.assignee.clone())
+}
+
 /// Returns `true` if the `manual_step` at `state_dir` was confirmed by the user.
 fn is_manual_completed(state_dir: &Path) -> bool {
     if !state_dir.exists() {
@@ -388,6 +987,16 @@ fn is_snapshot_metadata_completed(state_dir: &Path) -> bool {
     state_dir.exists() && state_dir.join("snapshot_metadata_completed").exists()
 }
 
+/// Returns `true` if the command of the `wait_until` step at `state_dir` has succeeded.
+fn is_wait_until_completed(state_dir: &Path) -> bool {
+    state_dir.join("wait_until_completed").exists()
+}
+
+/// Returns `true` if the `wait_until` step at `state_dir` gave up after its timeout.
+fn is_wait_until_timed_out(state_dir: &Path) -> bool {
+    !is_wait_until_completed(state_dir) && state_dir.join("wait_until_timed_out").exists()
+}
+
 /// Returns `true` if the `wait_for_continue` barrier at `state_dir` is in the waiting state
 /// (state_dir exists but no `barrier_released` file).
 fn is_wait_barrier_waiting(state_dir: &Path) -> bool {
@@ -419,7 +1028,9 @@ fn is_crate_stmt_completed(
 ) -> bool {
     let state_dir = state_base.join(cursor.to_path());
     match stmt {
-        CrateStatement::Run(_) => is_run_completed(&state_dir),
+        CrateStatement::Run(_) | CrateStatement::Cargo(_) | CrateStatement::FeaturePowerset(_) => {
+            is_run_completed(&state_dir)
+        }
         CrateStatement::ManualStep(_) => is_manual_completed(&state_dir),
         CrateStatement::SnapshotMetadata(_) => is_snapshot_metadata_completed(&state_dir),
         CrateStatement::If(block) => {
@@ -445,6 +1056,8 @@ fn is_crate_stmt_completed(
             is_crate_stmts_completed(&block.statements, &p, state_base)
         }
         CrateStatement::WaitForContinue(_) => is_wait_barrier_released(&state_dir),
+        CrateStatement::WaitUntil(_) => is_wait_until_completed(&state_dir),
+        CrateStatement::Phase(_) => true,
     }
 }
 
@@ -472,7 +1085,7 @@ fn is_workspace_stmt_completed(
 ) -> bool {
     let state_dir = state_base.join(cursor.to_path());
     match stmt {
-        WorkspaceStatement::Run(_) => is_run_completed(&state_dir),
+        WorkspaceStatement::Run(_) | WorkspaceStatement::Cargo(_) => is_run_completed(&state_dir),
         WorkspaceStatement::ManualStep(_) => is_manual_completed(&state_dir),
         WorkspaceStatement::SnapshotMetadata(_) => is_snapshot_metadata_completed(&state_dir),
         WorkspaceStatement::If(block) => {
@@ -514,6 +1127,8 @@ fn is_workspace_stmt_completed(
             })
         }
         WorkspaceStatement::WaitForContinue(_) => is_wait_barrier_released(&state_dir),
+        WorkspaceStatement::WaitUntil(_) => is_wait_until_completed(&state_dir),
+        WorkspaceStatement::Phase(_) => true,
     }
 }
 
@@ -525,7 +1140,16 @@ fn is_workspace_completed(
     state_base: &Path,
 ) -> bool {
     let prefix = ProgramCursor::new().with(CursorSegment::WorkspaceIteration(ws_idx));
-    is_workspace_stmts_completed(ws_stmts, &prefix, &ws_exec.member_crates, state_base)
+    let target_dir = state_base.join(prefix.to_path());
+    if completion_cache::contains(&target_dir) {
+        return true;
+    }
+    let completed =
+        is_workspace_stmts_completed(ws_stmts, &prefix, &ws_exec.member_crates, state_base);
+    if completed {
+        completion_cache::insert(target_dir);
+    }
+    completed
 }
 
 /// Returns `true` if all statements for standalone crate `c_idx` are completed.
@@ -535,7 +1159,15 @@ fn is_standalone_crate_completed(
     state_base: &Path,
 ) -> bool {
     let prefix = ProgramCursor::new().with(CursorSegment::CrateIteration(c_idx));
-    is_crate_stmts_completed(crate_stmts, &prefix, state_base)
+    let target_dir = state_base.join(prefix.to_path());
+    if completion_cache::contains(&target_dir) {
+        return true;
+    }
+    let completed = is_crate_stmts_completed(crate_stmts, &prefix, state_base);
+    if completed {
+        completion_cache::insert(target_dir);
+    }
+    completed
 }
 
 /// Returns `true` if all inter-workspace dependencies of `ws_exec` are completed.
@@ -599,6 +1231,8 @@ fn are_member_crate_deps_completed(
 pub enum StatementAction<'a> {
     /// Execute a command in the target directory.
     RunCommand(&'a RunStep),
+    /// Run a cargo subcommand in the target directory.
+    Cargo(&'a CargoStep),
     /// Pause for a manual user action and confirm completion.
     ManualStep(&'a ManualStepNode),
     /// Evaluate the branch conditions of a workspace `if` block.
@@ -609,6 +1243,10 @@ pub enum StatementAction<'a> {
     SnapshotMetadata(&'a SnapshotMetadataNode),
     /// A wait barrier: pending → create state_dir and print message; released → skip.
     WaitForContinue(&'a WaitForContinueNode),
+    /// Poll a command until it succeeds or the timeout expires.
+    WaitUntil(&'a WaitUntilNode),
+    /// Check every feature combination of a crate with `cargo hack`.
+    FeaturePowerset(&'a FeaturePowersetNode),
 }
 
 /// The next statement that should be executed in a running task.
@@ -650,6 +1288,26 @@ fn find_next_in_crate_stmts<'a>(
                     });
                 }
             }
+            CrateStatement::Cargo(step) => {
+                if !is_run_completed(&state_dir) {
+                    return Some(NextStatement {
+                        cursor,
+                        manifest_dir,
+                        action: StatementAction::Cargo(step),
+                        env_file_paths: env_file_paths.to_vec(),
+                    });
+                }
+            }
+            CrateStatement::FeaturePowerset(node) => {
+                if !is_run_completed(&state_dir) {
+                    return Some(NextStatement {
+                        cursor,
+                        manifest_dir,
+                        action: StatementAction::FeaturePowerset(node),
+                        env_file_paths: env_file_paths.to_vec(),
+                    });
+                }
+            }
             CrateStatement::ManualStep(step) => {
                 if !is_manual_completed(&state_dir) {
                     return Some(NextStatement {
@@ -670,6 +1328,16 @@ fn find_next_in_crate_stmts<'a>(
                     });
                 }
             }
+            CrateStatement::WaitUntil(step) => {
+                if !is_wait_until_completed(&state_dir) {
+                    return Some(NextStatement {
+                        cursor,
+                        manifest_dir,
+                        action: StatementAction::WaitUntil(step),
+                        env_file_paths: env_file_paths.to_vec(),
+                    });
+                }
+            }
             CrateStatement::If(block) => {
                 match fs_err::read_to_string(state_dir.join("chosen_branch")) {
                     Err(_) => {
@@ -727,6 +1395,9 @@ fn find_next_in_crate_stmts<'a>(
                     return nested;
                 }
             }
+            CrateStatement::Phase(_) => {
+                // Phase markers have no execution state — skip.
+            }
             CrateStatement::WaitForContinue(node) => {
                 if is_wait_barrier_released(&state_dir) {
                     // Already released — skip.
@@ -774,6 +1445,16 @@ fn find_next_in_workspace_stmts<'a>(
                     });
                 }
             }
+            WorkspaceStatement::Cargo(step) => {
+                if !is_run_completed(&state_dir) {
+                    return Some(NextStatement {
+                        cursor,
+                        manifest_dir,
+                        action: StatementAction::Cargo(step),
+                        env_file_paths: env_file_paths.to_vec(),
+                    });
+                }
+            }
             WorkspaceStatement::ManualStep(step) => {
                 if !is_manual_completed(&state_dir) {
                     return Some(NextStatement {
@@ -794,6 +1475,16 @@ fn find_next_in_workspace_stmts<'a>(
                     });
                 }
             }
+            WorkspaceStatement::WaitUntil(step) => {
+                if !is_wait_until_completed(&state_dir) {
+                    return Some(NextStatement {
+                        cursor,
+                        manifest_dir,
+                        action: StatementAction::WaitUntil(step),
+                        env_file_paths: env_file_paths.to_vec(),
+                    });
+                }
+            }
             WorkspaceStatement::If(block) => {
                 match fs_err::read_to_string(state_dir.join("chosen_branch")) {
                     Err(_) => {
@@ -861,6 +1552,8 @@ fn find_next_in_workspace_stmts<'a>(
                     .map(|(ci, c)| (c.manifest_dir.clone(), ci))
                     .collect();
 
+                // A member skipped by marker holds the workspace after the loop.
+                let mut held = false;
                 for (c_idx, crate_exec) in member_crates.iter().enumerate() {
                     if !are_member_crate_deps_completed(
                         crate_exec,
@@ -872,6 +1565,10 @@ fn find_next_in_workspace_stmts<'a>(
                         continue;
                     }
                     let c_prefix = cursor.clone().with(CursorSegment::CrateIteration(c_idx));
+                    if skip_marker::reason(&crate_exec.manifest_dir).is_some() {
+                        held |= !is_crate_stmts_completed(&block.statements, &c_prefix, state_base);
+                        continue;
+                    }
                     let nested = find_next_in_crate_stmts(
                         &block.statements,
                         &c_prefix,
@@ -883,8 +1580,14 @@ fn find_next_in_workspace_stmts<'a>(
                         return nested;
                     }
                 }
+                if held {
+                    return None;
+                }
                 // All member crates done — continue to next workspace statement.
             }
+            WorkspaceStatement::Phase(_) => {
+                // Phase markers have no execution state — skip.
+            }
             WorkspaceStatement::WaitForContinue(node) => {
                 if is_wait_barrier_released(&state_dir) {
                     // Already released — skip.
@@ -925,7 +1628,10 @@ pub fn find_next_statement<'a>(
         .collect();
 
     for (ws_idx, ws_exec) in resolved.workspace_executions.iter().enumerate() {
-        if !are_workspace_deps_completed(ws_exec, &ws_map, ws_stmts, resolved, state_base) {
+        if !are_workspace_deps_completed(ws_exec, &ws_map, ws_stmts, resolved, state_base)
+            || is_workspace_completed(ws_idx, ws_exec, ws_stmts, state_base)
+            || skip_marker::reason(&ws_exec.manifest_dir).is_some()
+        {
             continue;
         }
         let prefix = ProgramCursor::new().with(CursorSegment::WorkspaceIteration(ws_idx));
@@ -951,7 +1657,10 @@ pub fn find_next_statement<'a>(
         .collect();
 
     for (c_idx, crate_exec) in resolved.crate_executions.iter().enumerate() {
-        if !are_standalone_crate_deps_completed(crate_exec, &crate_map, crate_stmts, state_base) {
+        if !are_standalone_crate_deps_completed(crate_exec, &crate_map, crate_stmts, state_base)
+            || is_standalone_crate_completed(c_idx, crate_stmts, state_base)
+            || skip_marker::reason(&crate_exec.manifest_dir).is_some()
+        {
             continue;
         }
         let prefix = ProgramCursor::new().with(CursorSegment::CrateIteration(c_idx));
@@ -976,20 +1685,26 @@ pub fn find_next_statement<'a>(
 ///
 /// Each `${name.field1.field2...}` reference is replaced with the value of the
 /// given field path in the current crate's package entry within the named snapshot.
+/// A reference without a field, like `${crate_name}`, is one of the built-in
+/// [`variables`] of the target.
+/// A reference may end in one or more `|filter` suffixes, which are applied to
+/// the value left to right (see [`filters`]).
 /// If `s` contains no `${` sequences, it is returned unchanged without any
 /// filesystem access.
 ///
 /// # Errors
 ///
 /// Returns an error if any interpolation reference is malformed (e.g. missing
-/// the closing `}` or the dot-separated field), if the named snapshot does not
-/// exist, if the current crate's package cannot be found in the snapshot, or if
-/// the given field path does not exist in the package.
+/// the closing `}`), names an unknown variable, if the named snapshot does not
+/// exist, if the current crate's package cannot be found in the snapshot, if
+/// the given field path does not exist in the package, or if a filter is
+/// unknown or cannot be applied to the value.
 fn expand_interpolations(s: &str, manifest_dir: &Path, state_base: &Path) -> Result<String, Error> {
     if !s.contains("${") {
         return Ok(s.to_owned());
     }
     let mut result = String::with_capacity(s.len());
+    let mut target = variables::Lookup::new(manifest_dir);
     let mut parts = s.split("${");
     if let Some(first) = parts.next() {
         result.push_str(first);
@@ -998,10 +1713,20 @@ fn expand_interpolations(s: &str, manifest_dir: &Path, state_base: &Path) -> Res
         let (reference, rest) = part
             .split_once('}')
             .ok_or_else(|| Error::InvalidInterpolation(format!("${{{part}")))?;
-        let (name, field_path) = reference
-            .split_once('.')
-            .ok_or_else(|| Error::InvalidInterpolation(reference.to_owned()))?;
-        let value = resolve_interpolation(name, field_path, manifest_dir, state_base)?;
+        let mut pipeline = reference.split('|');
+        let lookup = pipeline.next().unwrap_or_default().trim();
+        let mut value = match lookup.split_once('.') {
+            Some((name, field_path)) => {
+                resolve_interpolation(name, field_path, manifest_dir, state_base)?
+            }
+            None if lookup.is_empty() => {
+                return Err(Error::InvalidInterpolation(reference.to_owned()));
+            }
+            None => target.get(lookup)?,
+        };
+        for filter in pipeline {
+            value = filters::apply(filter.trim(), &value)?;
+        }
         result.push_str(&value);
         result.push_str(rest);
     }
@@ -1091,17 +1816,13 @@ fn resolve_interpolation(
 ///
 /// Returns an error if `cargo metadata` fails, if the JSON cannot be serialized,
 /// or if any filesystem operation fails.
-#[expect(
-    clippy::print_stdout,
-    reason = "snapshot step announcement is part of the UI"
-)]
 async fn execute_snapshot_metadata_step(
     step: &SnapshotMetadataNode,
     cursor: &ProgramCursor,
     manifest_dir: &Path,
     state_base: &Path,
 ) -> Result<(), Error> {
-    println!("Snapshot metadata: {:?}", step.name);
+    progress::say(format_args!("Snapshot metadata: {:?}", step.name));
     let state_dir = state_base.join(cursor.to_path());
     fs_err::create_dir_all(&state_dir)
         .map_err(|e| Error::CouldNotCreateStateDir(state_dir.clone(), e))?;
@@ -1135,22 +1856,89 @@ async fn execute_snapshot_metadata_step(
     Ok(())
 }
 
-/// Executes a `run` step using asciinema for recording.
+/// Formats a command and its arguments as a line for the `run` wrapper script.
+///
+/// Arguments are wrapped in double quotes, so environment variable references
+/// inside them are expanded by the shell.
+fn shell_command_line(command: &str, args: &[String]) -> String {
+    format!(
+        "{} {}",
+        command,
+        args.iter()
+            .map(|a| format!("\"{}\"", a.replace('"', "\\\"")))
+            .collect::<Vec<_>>()
+            .join(" ")
+    )
+}
+
+/// Executes a `run` step, running it again after a failure or a timeout as
+/// often as its `retries` allow.  The number of attempts made is written to
+/// `state_dir/attempts` for steps with `retries`.
 ///
 /// # Errors
 ///
-/// Returns an error if the command is not found, if asciinema fails to launch,
-/// or if the exit-status file cannot be written.
-#[expect(
-    clippy::print_stdout,
-    reason = "printing the command is part of the UI"
-)]
+/// Returns the error of the last attempt, see [`execute_run_attempt`].
 async fn execute_run_step(
     step: &RunStep,
     cursor: &ProgramCursor,
     manifest_dir: &Path,
     state_base: &Path,
     environment: &Environment,
+    run_options: &crate::RunOptions,
+    extra_env: &[(String, String)],
+) -> Result<(), Error> {
+    let mut attempts: u64 = 0;
+    loop {
+        let result = execute_run_attempt(
+            step,
+            cursor,
+            manifest_dir,
+            state_base,
+            environment,
+            run_options,
+            extra_env,
+        )
+        .await;
+        attempts = attempts.saturating_add(1);
+        let Some(retry) = step.retry else {
+            return result;
+        };
+        let attempts_path = state_base.join(cursor.to_path()).join("attempts");
+        if let Err(e) = fs_err::write(&attempts_path, attempts.to_string()) {
+            tracing::warn!("Could not record the number of attempts: {e}");
+        }
+        match result {
+            Err(e @ (Error::CommandFailed(..) | Error::StepTimedOut(..)))
+                if attempts <= retry.retries =>
+            {
+                progress::say(format_args!(
+                    "{e}; retrying in {}s (attempt {} of {})",
+                    retry.delay_seconds,
+                    attempts.saturating_add(1),
+                    retry.retries.saturating_add(1)
+                ));
+                tokio::time::sleep(std::time::Duration::from_secs(retry.delay_seconds)).await;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Executes a `run` step once, recording its output with the task's
+/// recorder and killing it after its timeout.
+///
+/// # Errors
+///
+/// Returns an error if the command or the recorder is not found, if the
+/// recorder fails to launch, if the command is killed after its timeout, or
+/// if the exit-status file cannot be written.
+async fn execute_run_attempt(
+    step: &RunStep,
+    cursor: &ProgramCursor,
+    manifest_dir: &Path,
+    state_base: &Path,
+    environment: &Environment,
+    run_options: &crate::RunOptions,
     extra_env: &[(String, String)],
 ) -> Result<(), Error> {
     let state_dir = state_base.join(cursor.to_path());
@@ -1164,25 +1952,91 @@ async fn execute_run_step(
         .map(|a| expand_interpolations(a, manifest_dir, state_base))
         .collect::<Result<Vec<_>, _>>()?;
 
+    let working_dir = match &step.cwd_relative {
+        Some(dir) => manifest_dir.join(expand_interpolations(dir, manifest_dir, state_base)?),
+        None => manifest_dir.to_path_buf(),
+    };
+    // the step's own variables come last, so they override `with_env_file`
+    let mut env = extra_env.to_vec();
+    for (name, value) in &step.env {
+        env.push((
+            name.clone(),
+            expand_interpolations(value, manifest_dir, state_base)?,
+        ));
+    }
+
     if !crate::utils::command_is_executable(&command, environment) {
         return Err(Error::CommandNotFound(command.clone()));
     }
+    virtual_workspace::check(&command, &args, manifest_dir)?;
+    let (command, args) = match &step.elevation {
+        Some(elevation) => elevation::wrap(elevation, &command, &args),
+        None => (command, args),
+    };
 
-    let command_str = format!(
-        "{} {}",
-        command,
-        args.iter()
-            .map(|a| format!("\"{}\"", a.replace('"', "\\\"")))
-            .collect::<Vec<_>>()
-            .join(" ")
-    );
+    let command_str = shell_command_line(&command, &args);
+    let script_line = if run_options.sandbox {
+        sandbox::wrap_command_line(&command_str, manifest_dir, environment)?
+    } else {
+        command_str.clone()
+    };
+
+    let stdin_path = match &step.stdin {
+        None => None,
+        Some(StdinSource::Content(content)) => {
+            let path = state_dir.join("stdin");
+            let content = expand_interpolations(content, manifest_dir, state_base)?;
+            fs_err::write(&path, content)
+                .map_err(|e| Error::CouldNotWriteStateFile(path.clone(), e))?;
+            Some(path)
+        }
+        Some(StdinSource::File(file)) => {
+            Some(manifest_dir.join(expand_interpolations(file, manifest_dir, state_base)?))
+        }
+    };
+    let script_line = if stdin_path.is_some() {
+        format!("{script_line} < \"$CARGO_FOR_EACH_STDIN_PATH\"")
+    } else {
+        script_line
+    };
+
+    // taken before the rate limit, so the recorded start is the real one
+    let held =
+        resources::acquire(&step.exclusive, manifest_dir, run_options.explain_blocking).await;
+    if let Some(limit) = step.rate_limit {
+        rate_limit::acquire(limit, cursor, state_base).await?;
+    }
+
+    let target_dir = revision::target_dir(state_base, cursor);
+    if let Some(revision) = revision::current(manifest_dir) {
+        if let Some(earlier) = revision::read(&target_dir)
+            && earlier.commit != revision.commit
+        {
+            progress::say(format_args!(
+                "Warning: {} is now at commit {revision}, but earlier steps ran at {earlier}",
+                manifest_dir.display()
+            ));
+        }
+        revision::write(&state_dir, &revision)?;
+    }
 
-    println!("Running: {command_str}");
+    if run_options.cancellation.is_cancelled() {
+        return Err(Error::RunCancelled(command_str, manifest_dir.to_path_buf()));
+    }
+    progress::step(&command_str);
+    progress::say(format_args!("Running: {command_str}"));
+    step_timeout::clear(&state_dir)?;
+    cancellation::clear(&state_dir)?;
+    invocation::write(
+        &state_dir,
+        &invocation::Invocation::new(&command, &args, &working_dir, run_options.sandbox, &env),
+    )?;
 
     let wrapper_path = state_dir.join("run_wrapper.sh");
     let exit_status_path = state_dir.join("exit_status");
     let script = format!(
-        "#!/bin/sh\n{command_str}\nrc=$?\nprintf '%d' \"$rc\" > \"$CARGO_FOR_EACH_EXIT_STATUS_PATH\"\nexit \"$rc\"\n"
+        "#!/bin/sh\n{}\nprintf '%d' \"$rc\" > \"$CARGO_FOR_EACH_EXIT_STATUS_PATH\"\nexit \"$rc\"\n",
+        step_logs::capture(&script_line)
     );
     fs_err::write(&wrapper_path, &script)
         .map_err(|e| Error::CouldNotWriteStateFile(wrapper_path.clone(), e))?;
@@ -1193,26 +2047,90 @@ async fn execute_run_step(
         fs_err::set_permissions(&wrapper_path, perms).map_err(Error::IoError)?;
     }
 
-    let cast_path = state_dir.join("asciinema.cast");
-    let mut cmd = Command::new("asciinema");
-    cmd.arg("record").arg("--overwrite");
-    if environment.suppress_subprocess_output {
-        cmd.arg("--headless");
-    }
-    cmd.arg("-q")
-        .arg("-c")
-        .arg(wrapper_path.to_string_lossy().as_ref())
-        .arg(&cast_path);
+    let recorder = run_options.recorder.detect(environment)?;
+    let mut cmd = recorder.run_command(
+        &wrapper_path,
+        &state_dir,
+        environment.suppress_subprocess_output,
+    );
     cmd.env("CARGO_FOR_EACH_EXIT_STATUS_PATH", &exit_status_path);
-    for (k, v) in extra_env {
+    cmd.env(
+        step_logs::STDOUT_VARIABLE,
+        state_dir.join(step_logs::STDOUT_FILE),
+    );
+    cmd.env(
+        step_logs::STDERR_VARIABLE,
+        state_dir.join(step_logs::STDERR_FILE),
+    );
+    if let Some(stdin_path) = &stdin_path {
+        cmd.env("CARGO_FOR_EACH_STDIN_PATH", stdin_path);
+    }
+    for (k, v) in &env {
         cmd.env(k, v);
     }
-    cmd.current_dir(manifest_dir);
-
-    match crate::utils::execute_command(&mut cmd, environment, manifest_dir) {
+    cmd.current_dir(&working_dir);
+
+    let started = std::time::Instant::now();
+    let started_at = std::time::SystemTime::now();
+    let result = step_timeout::execute(
+        cmd,
+        environment,
+        &run_options.cancellation,
+        &working_dir,
+        step.timeout_seconds,
+    )
+    .await
+    .and_then(|outcome| match outcome {
+        step_timeout::Outcome::Exited(output) => Ok(output),
+        step_timeout::Outcome::TimedOut(seconds) => Err(Error::StepTimedOut(
+            command_str.clone(),
+            manifest_dir.to_path_buf(),
+            seconds,
+        )),
+        step_timeout::Outcome::Interrupted => Err(Error::StepInterrupted(
+            command_str.clone(),
+            manifest_dir.to_path_buf(),
+        )),
+    });
+    drop(held);
+    step_duration::write(&state_dir, started.elapsed())?;
+    step_duration::write_times(&state_dir, started_at, std::time::SystemTime::now())?;
+    fingerprint::write(
+        &state_dir,
+        &fingerprint::current(&working_dir, &env, run_options.program_checksum.as_deref()),
+    )?;
+    if let Some(revision) = revision::current(manifest_dir)
+        && let Err(e) = revision::write(&target_dir, &revision)
+    {
+        tracing::warn!(
+            "Could not record the commit of {}: {e}",
+            manifest_dir.display()
+        );
+    }
+    // Inline stdin content may contain secrets (e.g. for `cargo login`), so it
+    // is not kept around after the command has consumed it.
+    if matches!(step.stdin, Some(StdinSource::Content(_))) {
+        let stdin_file = state_dir.join("stdin");
+        if let Err(e) = fs_err::remove_file(&stdin_file) {
+            tracing::warn!("Could not remove {}: {e}", stdin_file.display());
+        }
+    }
+    match result {
         Err(e) => {
             fs_err::write(&exit_status_path, "")
                 .map_err(|we| Error::CouldNotWriteStateFile(exit_status_path, we))?;
+            match &e {
+                Error::StepTimedOut(_, _, seconds) => step_timeout::mark(&state_dir, *seconds)?,
+                Error::StepInterrupted(..) => cancellation::mark(&state_dir)?,
+                _ => {}
+            }
+            if matches!(e, Error::StepTimedOut(..) | Error::StepInterrupted(..)) {
+                let tail =
+                    recorder::output_tail(&state_dir, failure_output::LINES).unwrap_or_default();
+                if let Err(e) = failure_output::write(&state_dir, &tail) {
+                    tracing::warn!("Could not store the output of the killed step: {e}");
+                }
+            }
             Err(e)
         }
         Ok(_) => {
@@ -1229,6 +2147,11 @@ async fn execute_run_step(
             }
 
             if exit_code != 0 {
+                let tail =
+                    recorder::output_tail(&state_dir, failure_output::LINES).unwrap_or_default();
+                if let Err(e) = failure_output::write(&state_dir, &tail) {
+                    tracing::warn!("Could not store the output of the failed step: {e}");
+                }
                 return Err(Error::CommandFailed(
                     command_str,
                     manifest_dir.to_path_buf(),
@@ -1240,11 +2163,48 @@ async fn execute_run_step(
     }
 }
 
-/// Executes a `manual_step` by launching an interactive asciinema recording session.
+/// Executes a `feature_powerset` step like a `run` step and records the
+/// feature combinations cargo hack checked, whether it succeeded or not.
+///
+/// # Errors
+///
+/// Returns the errors of [`execute_run_step`].
+async fn execute_feature_powerset_step(
+    node: &FeaturePowersetNode,
+    cursor: &ProgramCursor,
+    manifest_dir: &Path,
+    state_base: &Path,
+    environment: &Environment,
+    run_options: &crate::RunOptions,
+    extra_env: &[(String, String)],
+) -> Result<(), Error> {
+    let step = feature_powerset::run_step(node);
+    let result = execute_run_step(
+        &step,
+        cursor,
+        manifest_dir,
+        state_base,
+        environment,
+        run_options,
+        extra_env,
+    )
+    .await;
+    let state_dir = state_base.join(cursor.to_path());
+    if let Some(output) = recorder::output_tail(&state_dir, usize::MAX) {
+        let sets = feature_powerset::parse(&output, result.is_ok());
+        if let Err(e) = feature_powerset::write(&state_dir, &sets) {
+            tracing::warn!("Could not store the checked feature combinations: {e}");
+        }
+    }
+    result
+}
+
+/// Executes a `manual_step` by launching an interactive shell, recorded with
+/// the task's recorder.
 ///
 /// # Errors
 ///
-/// Returns an error if asciinema fails, if I/O fails, if the confirmation file
+/// Returns an error if the recorder is not found or fails, if I/O fails, if the confirmation file
 /// cannot be written, or if the user does not confirm completion.
 #[expect(
     clippy::print_stdout,
@@ -1256,6 +2216,7 @@ async fn execute_manual_step(
     manifest_dir: &Path,
     state_base: &Path,
     environment: &Environment,
+    run_options: &crate::RunOptions,
     extra_env: &[(String, String)],
 ) -> Result<(), Error> {
     let state_dir = state_base.join(cursor.to_path());
@@ -1265,19 +2226,22 @@ async fn execute_manual_step(
     let title = expand_interpolations(&step.title, manifest_dir, state_base)?;
     let instructions = expand_interpolations(&step.instructions, manifest_dir, state_base)?;
     println!("--- Manual Step: {title} ---");
+    if let Some(assignee) = manual_step_assignee(step, &state_dir) {
+        println!("Assigned to: {assignee}");
+    }
     println!("{instructions}");
+    let recorder = run_options.recorder.detect(environment)?;
     println!(
-        "Starting a recording shell in {}. Press Ctrl+D or type `exit` to continue.",
+        "Starting a {}shell in {}. Press Ctrl+D or type `exit` to continue.",
+        if recorder == recorder::Recorder::None {
+            ""
+        } else {
+            "recording "
+        },
         manifest_dir.display()
     );
 
-    let cast_path = state_dir.join("asciinema.cast");
-    let mut cmd = Command::new("asciinema");
-    cmd.arg("record");
-    if environment.suppress_subprocess_output {
-        cmd.arg("--headless");
-    }
-    cmd.arg("-q").arg(&cast_path);
+    let mut cmd = recorder.shell_command(&state_dir, environment.suppress_subprocess_output);
     for (k, v) in extra_env {
         cmd.env(k, v);
     }
@@ -1303,6 +2267,7 @@ async fn execute_manual_step(
         if confirmed { "y" } else { "n" },
     )
     .map_err(|e| Error::CouldNotWriteStateFile(manual_step_confirmed_path, e))?;
+    approval::write(&state_dir, &approval::Approval::now(confirmed))?;
 
     if !confirmed {
         return Err(Error::ManualStepNotConfirmed);
@@ -1310,6 +2275,83 @@ async fn execute_manual_step(
     Ok(())
 }
 
+/// Executes a `wait_until` step by running its command every
+/// `interval_seconds` until it exits successfully.
+///
+/// On success the number of seconds waited is written to
+/// `state_dir/wait_until_completed`.  If the timeout expires first, the
+/// seconds waited are written to `state_dir/wait_until_timed_out` instead and
+/// the step fails; running the task again starts a new round of polling.
+///
+/// # Errors
+///
+/// Returns an error if the command is not found or cannot be started, if the
+/// timeout expires, or if a state file cannot be written.
+async fn execute_wait_until_step(
+    step: &WaitUntilNode,
+    cursor: &ProgramCursor,
+    manifest_dir: &Path,
+    state_base: &Path,
+    environment: &Environment,
+    run_options: &crate::RunOptions,
+    extra_env: &[(String, String)],
+) -> Result<(), Error> {
+    let state_dir = state_base.join(cursor.to_path());
+    fs_err::create_dir_all(&state_dir)
+        .map_err(|e| Error::CouldNotCreateStateDir(state_dir.clone(), e))?;
+
+    let command = expand_interpolations(&step.command, manifest_dir, state_base)?;
+    let args = step
+        .args
+        .iter()
+        .map(|a| expand_interpolations(a, manifest_dir, state_base))
+        .collect::<Result<Vec<_>, _>>()?;
+    if !crate::utils::command_is_executable(&command, environment) {
+        return Err(Error::CommandNotFound(command.clone()));
+    }
+    let command_str = shell_command_line(&command, &args);
+    progress::say(format_args!(
+        "Waiting until {command_str} succeeds (every {}s)",
+        step.interval_seconds
+    ));
+
+    let start = std::time::Instant::now();
+    let interval = std::time::Duration::from_secs(step.interval_seconds);
+    let timeout = step.timeout_seconds.map(std::time::Duration::from_secs);
+    loop {
+        let mut cmd = Command::new(&command);
+        cmd.args(&args).current_dir(manifest_dir);
+        for (k, v) in extra_env {
+            cmd.env(k, v);
+        }
+        let status = crate::utils::execute_command(&mut cmd, environment, manifest_dir)?.status;
+        let waited = start.elapsed();
+        if status.success() {
+            let completed_path = state_dir.join("wait_until_completed");
+            fs_err::write(&completed_path, waited.as_secs().to_string())
+                .map_err(|e| Error::CouldNotWriteStateFile(completed_path, e))?;
+            progress::say(format_args!(
+                "{command_str} succeeded after {}s",
+                waited.as_secs()
+            ));
+            return Ok(());
+        }
+        let remaining = timeout.map(|t| t.saturating_sub(waited));
+        if remaining.is_some_and(|r| r.is_zero()) {
+            let timed_out_path = state_dir.join("wait_until_timed_out");
+            fs_err::write(&timed_out_path, waited.as_secs().to_string())
+                .map_err(|e| Error::CouldNotWriteStateFile(timed_out_path, e))?;
+            return Err(Error::WaitUntilTimedOut(command_str, waited.as_secs()));
+        }
+        tokio::select! {
+            () = tokio::time::sleep(remaining.map_or(interval, |r| r.min(interval))) => {}
+            () = run_options.cancellation.cancelled() => {
+                return Err(Error::RunCancelled(command_str, manifest_dir.to_path_buf()));
+            }
+        }
+    }
+}
+
 /// Evaluates the branch conditions of a workspace `if` block and writes `chosen_branch`.
 ///
 /// The branch index written is 0-based; `"none"` means no branch matched and there
@@ -1318,7 +2360,6 @@ async fn execute_manual_step(
 /// # Errors
 ///
 /// Returns an error if condition evaluation fails or the state file cannot be written.
-#[expect(clippy::print_stdout, reason = "if-block evaluation is part of the UI")]
 fn evaluate_workspace_if_block(
     block: &WorkspaceIfBlock,
     cursor: &ProgramCursor,
@@ -1332,7 +2373,7 @@ fn evaluate_workspace_if_block(
     fs_err::create_dir_all(&state_dir)
         .map_err(|e| Error::CouldNotCreateStateDir(state_dir.clone(), e))?;
 
-    println!("Evaluating if at {cursor}:");
+    progress::say(format_args!("Evaluating if at {cursor}:"));
     let mut chosen: Option<usize> = None;
     for (i, branch) in block.branches.iter().enumerate() {
         let result = evaluate_workspace_condition(
@@ -1345,7 +2386,10 @@ fn evaluate_workspace_if_block(
         let detail = workspace_condition_runtime_detail(&branch.condition, manifest_dir)
             .map(|d| format!(" [{d}]"))
             .unwrap_or_default();
-        println!("  branch {i}: {}{detail} → {result}", branch.condition);
+        progress::say(format_args!(
+            "  branch {i}: {}{detail} → {result}",
+            branch.condition
+        ));
         if result && chosen.is_none() {
             chosen = Some(i);
         }
@@ -1362,9 +2406,9 @@ fn evaluate_workspace_if_block(
         |n| n.to_string(),
     );
     match chosen_str.as_str() {
-        "none" => println!("  → no branch taken"),
-        "else" => println!("  → else branch taken"),
-        n => println!("  → branch {n} taken"),
+        "none" => progress::say(format_args!("  → no branch taken")),
+        "else" => progress::say(format_args!("  → else branch taken")),
+        n => progress::say(format_args!("  → branch {n} taken")),
     }
     let chosen_branch_path = state_dir.join("chosen_branch");
     fs_err::write(&chosen_branch_path, &chosen_str)
@@ -1377,7 +2421,6 @@ fn evaluate_workspace_if_block(
 /// # Errors
 ///
 /// Returns an error if condition evaluation fails or the state file cannot be written.
-#[expect(clippy::print_stdout, reason = "if-block evaluation is part of the UI")]
 fn evaluate_crate_if_block(
     block: &CrateIfBlock,
     cursor: &ProgramCursor,
@@ -1391,7 +2434,7 @@ fn evaluate_crate_if_block(
     fs_err::create_dir_all(&state_dir)
         .map_err(|e| Error::CouldNotCreateStateDir(state_dir.clone(), e))?;
 
-    println!("Evaluating if at {cursor}:");
+    progress::say(format_args!("Evaluating if at {cursor}:"));
     let mut chosen: Option<usize> = None;
     for (i, branch) in block.branches.iter().enumerate() {
         let result = evaluate_crate_condition(
@@ -1404,7 +2447,10 @@ fn evaluate_crate_if_block(
         let detail = crate_condition_runtime_detail(&branch.condition, manifest_dir)
             .map(|d| format!(" [{d}]"))
             .unwrap_or_default();
-        println!("  branch {i}: {}{detail} → {result}", branch.condition);
+        progress::say(format_args!(
+            "  branch {i}: {}{detail} → {result}",
+            branch.condition
+        ));
         if result && chosen.is_none() {
             chosen = Some(i);
         }
@@ -1421,9 +2467,9 @@ fn evaluate_crate_if_block(
         |n| n.to_string(),
     );
     match chosen_str.as_str() {
-        "none" => println!("  → no branch taken"),
-        "else" => println!("  → else branch taken"),
-        n => println!("  → branch {n} taken"),
+        "none" => progress::say(format_args!("  → no branch taken")),
+        "else" => progress::say(format_args!("  → else branch taken")),
+        n => progress::say(format_args!("  → branch {n} taken")),
     }
     let chosen_branch_path = state_dir.join("chosen_branch");
     fs_err::write(&chosen_branch_path, &chosen_str)
@@ -1431,34 +2477,70 @@ fn evaluate_crate_if_block(
     Ok(())
 }
 
+/// Returns `true` if statements in `phase` may run in this run.
+fn is_phase_selected(run_options: &crate::RunOptions, phase: Option<&str>) -> bool {
+    run_options
+        .phases
+        .as_ref()
+        .is_none_or(|selection| selection.contains(phase))
+}
+
+/// Prints why a target stopped before the statement at `cursor`.
+fn print_phase_stop(cursor: &ProgramCursor, phase: Option<&str>) {
+    match phase {
+        Some(name) => progress::say(format_args!(
+            "Stopping at {cursor}: phase \"{name}\" is not selected."
+        )),
+        None => {
+            progress::say(format_args!(
+                "Stopping at {cursor}: statements before the first phase are not selected."
+            ));
+        }
+    }
+}
+
 /// Runs all crate statements to completion, skipping already-completed ones.
 ///
 /// Handles `if` blocks by evaluating conditions if not yet done, then running
-/// the chosen branch's statements recursively.
+/// the chosen branch's statements recursively.  `phase` is the phase the
+/// enclosing block is in.
+///
+/// Returns `false` if the target stopped early at a wait barrier or at a
+/// statement outside the selected phases.
 ///
 /// # Errors
 ///
 /// Returns an error if any statement fails.
-#[expect(clippy::print_stdout, reason = "barrier message is part of the UI")]
 #[expect(
     clippy::too_many_arguments,
     reason = "all parameters are needed; the task_name threading adds one more than clippy's default limit"
 )]
-async fn run_crate_stmts_to_completion(
-    stmts: &[CrateStatement],
+async fn run_crate_stmts_to_completion<'stmts>(
+    stmts: &'stmts [CrateStatement],
     prefix: &ProgramCursor,
     manifest_dir: &Path,
     state_base: &Path,
     environment: &Environment,
+    run_options: &crate::RunOptions,
     config: &Config,
     extra_env: &[(String, String)],
     task_name: &str,
-) -> Result<(), Error> {
+    phase: Option<&'stmts str>,
+) -> Result<bool, Error> {
+    let mut phase = phase;
     for (i, stmt) in stmts.iter().enumerate() {
         let cursor = prefix.clone().with(CursorSegment::Statement(i));
         let state_dir = state_base.join(cursor.to_path());
 
+        if !is_phase_selected(run_options, phase)
+            && !is_crate_stmt_completed(stmt, &cursor, state_base)
+        {
+            print_phase_stop(&cursor, phase);
+            return Ok(false);
+        }
+
         match stmt {
+            CrateStatement::Phase(node) => phase = Some(&node.name),
             CrateStatement::Run(step) => {
                 if !is_run_completed(&state_dir) {
                     execute_run_step(
@@ -1467,6 +2549,35 @@ async fn run_crate_stmts_to_completion(
                         manifest_dir,
                         state_base,
                         environment,
+                        run_options,
+                        extra_env,
+                    )
+                    .await?;
+                }
+            }
+            CrateStatement::Cargo(step) => {
+                if !is_run_completed(&state_dir) {
+                    execute_run_step(
+                        &step.to_run_step(),
+                        &cursor,
+                        manifest_dir,
+                        state_base,
+                        environment,
+                        run_options,
+                        extra_env,
+                    )
+                    .await?;
+                }
+            }
+            CrateStatement::FeaturePowerset(node) => {
+                if !is_run_completed(&state_dir) {
+                    execute_feature_powerset_step(
+                        node,
+                        &cursor,
+                        manifest_dir,
+                        state_base,
+                        environment,
+                        run_options,
                         extra_env,
                     )
                     .await?;
@@ -1480,6 +2591,7 @@ async fn run_crate_stmts_to_completion(
                         manifest_dir,
                         state_base,
                         environment,
+                        run_options,
                         extra_env,
                     )
                     .await?;
@@ -1490,6 +2602,20 @@ async fn run_crate_stmts_to_completion(
                     execute_snapshot_metadata_step(step, &cursor, manifest_dir, state_base).await?;
                 }
             }
+            CrateStatement::WaitUntil(step) => {
+                if !is_wait_until_completed(&state_dir) {
+                    execute_wait_until_step(
+                        step,
+                        &cursor,
+                        manifest_dir,
+                        state_base,
+                        environment,
+                        run_options,
+                        extra_env,
+                    )
+                    .await?;
+                }
+            }
             CrateStatement::If(block) => {
                 let chosen_branch_path = state_dir.join("chosen_branch");
                 if !chosen_branch_path.exists() {
@@ -1509,34 +2635,44 @@ async fn run_crate_stmts_to_completion(
                     "none" => {}
                     "else" => {
                         let p = cursor.clone().with(CursorSegment::ElseBranch);
-                        Box::pin(run_crate_stmts_to_completion(
+                        if !Box::pin(run_crate_stmts_to_completion(
                             &block.else_statements,
                             &p,
                             manifest_dir,
                             state_base,
                             environment,
+                            run_options,
                             config,
                             extra_env,
                             task_name,
+                            phase,
                         ))
-                        .await?;
+                        .await?
+                        {
+                            return Ok(false);
+                        }
                     }
                     s => {
                         if let Ok(n) = s.trim().parse::<usize>()
                             && let Some(branch) = block.branches.get(n)
                         {
                             let p = cursor.clone().with(CursorSegment::IfBranch(n));
-                            Box::pin(run_crate_stmts_to_completion(
+                            if !Box::pin(run_crate_stmts_to_completion(
                                 &branch.statements,
                                 &p,
                                 manifest_dir,
                                 state_base,
                                 environment,
+                                run_options,
                                 config,
                                 extra_env,
                                 task_name,
+                                phase,
                             ))
-                            .await?;
+                            .await?
+                            {
+                                return Ok(false);
+                            }
                         }
                     }
                 }
@@ -1546,17 +2682,22 @@ async fn run_crate_stmts_to_completion(
                 let mut combined = extra_env.to_vec();
                 combined.extend(file_vars);
                 let inner_prefix = cursor.clone().with(CursorSegment::WithEnvFile);
-                Box::pin(run_crate_stmts_to_completion(
+                if !Box::pin(run_crate_stmts_to_completion(
                     &block.statements,
                     &inner_prefix,
                     manifest_dir,
                     state_base,
                     environment,
+                    run_options,
                     config,
                     &combined,
                     task_name,
+                    phase,
                 ))
-                .await?;
+                .await?
+                {
+                    return Ok(false);
+                }
             }
             CrateStatement::WaitForContinue(node) => {
                 if is_wait_barrier_released(&state_dir) {
@@ -1567,49 +2708,63 @@ async fn run_crate_stmts_to_completion(
                         fs_err::create_dir_all(&state_dir)
                             .map_err(|e| Error::CouldNotCreateStateDir(state_dir.clone(), e))?;
                     }
-                    println!(
+                    progress::say(format_args!(
                         "Wait barrier reached at {}: \"{}\". Release with `cargo-for-each task continue --name {} --cursor {}`.",
                         cursor,
                         node.description,
                         task_name,
                         cursor.to_path_string()
-                    );
-                    return Ok(());
+                    ));
+                    return Ok(false);
                 }
             }
         }
     }
-    Ok(())
+    Ok(true)
 }
 
 /// Runs all workspace statements to completion, including nested `for crate in workspace`.
 ///
-/// Already-completed statements are skipped.
+/// Already-completed statements are skipped.  `phase` is the phase the
+/// enclosing block is in.
+///
+/// Returns `false` if the target stopped early at a wait barrier or at a
+/// statement outside the selected phases.
 ///
 /// # Errors
 ///
 /// Returns an error if any statement fails.
-#[expect(clippy::print_stdout, reason = "barrier message is part of the UI")]
 #[expect(
     clippy::too_many_arguments,
     reason = "all parameters are needed; the env-file threading adds one more than clippy's default limit"
 )]
-async fn run_workspace_stmts_to_completion(
-    stmts: &[WorkspaceStatement],
+async fn run_workspace_stmts_to_completion<'stmts>(
+    stmts: &'stmts [WorkspaceStatement],
     prefix: &ProgramCursor,
     manifest_dir: &Path,
     member_crates: &[ResolvedCrateExecution],
     state_base: &Path,
     environment: &Environment,
+    run_options: &crate::RunOptions,
     config: &Config,
     extra_env: &[(String, String)],
     task_name: &str,
-) -> Result<(), Error> {
+    phase: Option<&'stmts str>,
+) -> Result<bool, Error> {
+    let mut phase = phase;
     for (i, stmt) in stmts.iter().enumerate() {
         let cursor = prefix.clone().with(CursorSegment::Statement(i));
         let state_dir = state_base.join(cursor.to_path());
 
+        if !is_phase_selected(run_options, phase)
+            && !is_workspace_stmt_completed(stmt, &cursor, member_crates, state_base)
+        {
+            print_phase_stop(&cursor, phase);
+            return Ok(false);
+        }
+
         match stmt {
+            WorkspaceStatement::Phase(node) => phase = Some(&node.name),
             WorkspaceStatement::Run(step) => {
                 if !is_run_completed(&state_dir) {
                     execute_run_step(
@@ -1618,6 +2773,21 @@ async fn run_workspace_stmts_to_completion(
                         manifest_dir,
                         state_base,
                         environment,
+                        run_options,
+                        extra_env,
+                    )
+                    .await?;
+                }
+            }
+            WorkspaceStatement::Cargo(step) => {
+                if !is_run_completed(&state_dir) {
+                    execute_run_step(
+                        &step.to_run_step(),
+                        &cursor,
+                        manifest_dir,
+                        state_base,
+                        environment,
+                        run_options,
                         extra_env,
                     )
                     .await?;
@@ -1631,6 +2801,7 @@ async fn run_workspace_stmts_to_completion(
                         manifest_dir,
                         state_base,
                         environment,
+                        run_options,
                         extra_env,
                     )
                     .await?;
@@ -1641,6 +2812,20 @@ async fn run_workspace_stmts_to_completion(
                     execute_snapshot_metadata_step(step, &cursor, manifest_dir, state_base).await?;
                 }
             }
+            WorkspaceStatement::WaitUntil(step) => {
+                if !is_wait_until_completed(&state_dir) {
+                    execute_wait_until_step(
+                        step,
+                        &cursor,
+                        manifest_dir,
+                        state_base,
+                        environment,
+                        run_options,
+                        extra_env,
+                    )
+                    .await?;
+                }
+            }
             WorkspaceStatement::If(block) => {
                 let chosen_branch_path = state_dir.join("chosen_branch");
                 if !chosen_branch_path.exists() {
@@ -1660,36 +2845,46 @@ async fn run_workspace_stmts_to_completion(
                     "none" => {}
                     "else" => {
                         let p = cursor.clone().with(CursorSegment::ElseBranch);
-                        Box::pin(run_workspace_stmts_to_completion(
+                        if !Box::pin(run_workspace_stmts_to_completion(
                             &block.else_statements,
                             &p,
                             manifest_dir,
                             member_crates,
                             state_base,
                             environment,
+                            run_options,
                             config,
                             extra_env,
                             task_name,
+                            phase,
                         ))
-                        .await?;
+                        .await?
+                        {
+                            return Ok(false);
+                        }
                     }
                     s => {
                         if let Ok(n) = s.trim().parse::<usize>()
                             && let Some(branch) = block.branches.get(n)
                         {
                             let p = cursor.clone().with(CursorSegment::IfBranch(n));
-                            Box::pin(run_workspace_stmts_to_completion(
+                            if !Box::pin(run_workspace_stmts_to_completion(
                                 &branch.statements,
                                 &p,
                                 manifest_dir,
                                 member_crates,
                                 state_base,
                                 environment,
+                                run_options,
                                 config,
                                 extra_env,
                                 task_name,
+                                phase,
                             ))
-                            .await?;
+                            .await?
+                            {
+                                return Ok(false);
+                            }
                         }
                     }
                 }
@@ -1699,35 +2894,58 @@ async fn run_workspace_stmts_to_completion(
                 let mut combined = extra_env.to_vec();
                 combined.extend(file_vars);
                 let inner_prefix = cursor.clone().with(CursorSegment::WithEnvFile);
-                Box::pin(run_workspace_stmts_to_completion(
+                if !Box::pin(run_workspace_stmts_to_completion(
                     &block.statements,
                     &inner_prefix,
                     manifest_dir,
                     member_crates,
                     state_base,
                     environment,
+                    run_options,
                     config,
                     &combined,
                     task_name,
+                    phase,
                 ))
-                .await?;
+                .await?
+                {
+                    return Ok(false);
+                }
             }
             WorkspaceStatement::ForCrateInWorkspace(block) => {
                 // Member crates are already in intra-workspace dependency order.
+                // A crate that stops early does not keep the others from
+                // running, but the workspace stops after the loop.
+                let mut finished = true;
                 for (c_idx, crate_exec) in member_crates.iter().enumerate() {
                     let c_prefix = cursor.clone().with(CursorSegment::CrateIteration(c_idx));
-                    run_crate_stmts_to_completion(
+                    if let Some(reason) = skip_marker::reason(&crate_exec.manifest_dir) {
+                        if !is_crate_stmts_completed(&block.statements, &c_prefix, state_base) {
+                            tracing::warn!(
+                                "Skipping {}: {reason}",
+                                crate_exec.manifest_dir.display()
+                            );
+                            finished = false;
+                        }
+                        continue;
+                    }
+                    finished &= run_crate_stmts_to_completion(
                         &block.statements,
                         &c_prefix,
                         &crate_exec.manifest_dir,
                         state_base,
                         environment,
+                        run_options,
                         config,
-                        extra_env,
+                        &crate_env(config, &crate_exec.manifest_dir, extra_env),
                         task_name,
+                        phase,
                     )
                     .await?;
                 }
+                if !finished {
+                    return Ok(false);
+                }
             }
             WorkspaceStatement::WaitForContinue(node) => {
                 if is_wait_barrier_released(&state_dir) {
@@ -1738,23 +2956,42 @@ async fn run_workspace_stmts_to_completion(
                         fs_err::create_dir_all(&state_dir)
                             .map_err(|e| Error::CouldNotCreateStateDir(state_dir.clone(), e))?;
                     }
-                    println!(
+                    progress::say(format_args!(
                         "Wait barrier reached at {}: \"{}\". Release with `cargo-for-each task continue --name {} --cursor {}`.",
                         cursor,
                         node.description,
                         task_name,
                         cursor.to_path_string()
-                    );
-                    return Ok(());
+                    ));
+                    return Ok(false);
                 }
             }
         }
     }
-    Ok(())
+    Ok(true)
 }
 
 // ── Load helpers ───────────────────────────────────────────────────────────────
 
+/// Parses `source`, collecting all parse errors into a single error.
+///
+/// # Errors
+///
+/// Returns [`Error::ProgramParseErrors`] if `source` is not a valid program,
+/// or [`Error::UnknownInterpolationFilter`] if it uses an unknown filter.
+pub fn parse_program(source: &str, file_name: &str) -> Result<Program, Error> {
+    let program = crate::program::parser::parse(source, file_name).map_err(|errors| {
+        let msgs = errors
+            .iter()
+            .map(|e| e.as_str().to_owned())
+            .collect::<Vec<_>>()
+            .join("\n");
+        Error::ProgramParseErrors(msgs)
+    })?;
+    filters::check_names(source)?;
+    Ok(program)
+}
+
 /// Loads the parsed program and resolved snapshot for the given task.
 ///
 /// # Errors
@@ -1774,14 +3011,7 @@ fn load_task_data(
     let program_source_path = task_dir.join("program.cfe");
     let source =
         fs_err::read_to_string(&program_source_path).map_err(Error::CouldNotReadProgramFile)?;
-    let program = crate::program::parser::parse(&source, "program.cfe").map_err(|errors| {
-        let msgs = errors
-            .iter()
-            .map(|e| e.as_str().to_owned())
-            .collect::<Vec<_>>()
-            .join("\n");
-        Error::ProgramParseErrors(msgs)
-    })?;
+    let program = parse_program(&source, "program.cfe")?;
 
     let resolved_path = task_dir.join("resolved-program.toml");
     let resolved_src = fs_err::read_to_string(&resolved_path)
@@ -1832,9 +3062,14 @@ fn find_last_completed_crate_stmt(
                 }
             }
             CrateStatement::Run(_)
+            | CrateStatement::Cargo(_)
+            | CrateStatement::FeaturePowerset(_)
             | CrateStatement::ManualStep(_)
             | CrateStatement::SnapshotMetadata(_)
-            | CrateStatement::WaitForContinue(_) => {}
+            | CrateStatement::WaitForContinue(_)
+            | CrateStatement::WaitUntil(_) => {}
+            // Phase markers have no state to rewind.
+            CrateStatement::Phase(_) => continue,
         }
         if is_crate_stmt_completed(stmt, &cursor, state_base) {
             return Some(cursor);
@@ -1906,9 +3141,13 @@ fn find_last_completed_workspace_stmt(
                 }
             }
             WorkspaceStatement::Run(_)
+            | WorkspaceStatement::Cargo(_)
             | WorkspaceStatement::ManualStep(_)
             | WorkspaceStatement::SnapshotMetadata(_)
-            | WorkspaceStatement::WaitForContinue(_) => {}
+            | WorkspaceStatement::WaitForContinue(_)
+            | WorkspaceStatement::WaitUntil(_) => {}
+            // Phase markers have no state to rewind.
+            WorkspaceStatement::Phase(_) => continue,
         }
         if is_workspace_stmt_completed(stmt, &cursor, member_crates, state_base) {
             return Some(cursor);
@@ -1919,86 +3158,415 @@ fn find_last_completed_workspace_stmt(
 
 // ── Command implementations ────────────────────────────────────────────────────
 
+/// Resolves `program` against the registered targets.
+///
+/// Crates that selected crates depend on by `path` but that are not
+/// registered are reported, or with `register` added to the configuration,
+/// after which the program is resolved again.  This repeats until the path
+/// dependencies of the newly selected crates are registered as well.  With
+/// `register` the caller holds the configuration lock.
+///
+/// # Errors
+///
+/// Returns an error if the configuration cannot be loaded or saved, if
+/// resolving fails or if a path dependency cannot be registered.
+#[expect(clippy::print_stdout, reason = "This is part of the UI, not logging")]
+#[expect(clippy::print_stderr, reason = "This is part of the UI, not logging")]
+pub fn resolve_from_config(
+    program: &Program,
+    environment: &Environment,
+    register: bool,
+) -> Result<ResolvedProgram, Error> {
+    let mut config = Config::load(environment)?;
+    let mut attempted: HashSet<PathBuf> = HashSet::new();
+    let provider = Cached::new(Subprocess);
+    loop {
+        let resolved = crate::program::resolve::resolve_program(program, &config, &provider)?;
+        let unregistered: Vec<PathBuf> =
+            crate::program::resolve::unregistered_path_dependencies(&resolved, &config, &provider)?
+                .into_iter()
+                .filter(|dir| !attempted.contains(dir))
+                .collect();
+        if unregistered.is_empty() {
+            return Ok(resolved);
+        }
+        if !register {
+            for dir in &unregistered {
+                eprintln!(
+                    "warning: {} is a path dependency of a selected crate but not registered; \
+                     its order relative to the other targets is unknown \
+                     (register it with `target add` or use --register-path-dependencies)",
+                    dir.display()
+                );
+            }
+            return Ok(resolved);
+        }
+        for dir in unregistered {
+            crate::targets::add_manifest(&mut config, &dir.join("Cargo.toml"))?;
+            println!("Registered path dependency {}", dir.display());
+            attempted.insert(dir);
+        }
+        config.save(environment)?;
+    }
+}
+
 /// Creates a new task by parsing and resolving the given `.cfe` program file.
 ///
 /// # Errors
 ///
-/// Returns an error if the program file cannot be read or parsed, if the
-/// configuration cannot be loaded, if the program cannot be resolved, if the
+/// Returns an error if the program file cannot be read or parsed, if commands
+/// used by the program are not installed, if the configuration cannot be
+/// loaded, if the program cannot be resolved, if the
 /// task directory already exists or cannot be created, or if the task files
 /// cannot be written.
 #[instrument]
 pub async fn task_create_command(
-    params: CreateTaskParameters,
+    mut params: CreateTaskParameters,
     environment: crate::Environment,
 ) -> Result<(), Error> {
-    if !params.program.exists() {
-        return Err(Error::ProgramNotFound(params.program.clone()));
+    let stdin_inputs = [
+        Some(&params.program),
+        params.workspaces_from.as_ref(),
+        params.crates_from.as_ref(),
+    ]
+    .into_iter()
+    .flatten()
+    .filter(|path| is_stdin(path))
+    .count();
+    if stdin_inputs > 1 {
+        return Err(Error::StdinUsedTwice);
+    }
+    let source = if is_stdin(&params.program) {
+        read_stdin().map_err(Error::CouldNotReadProgramFile)?
+    } else {
+        if !params.program.exists() {
+            return Err(Error::ProgramNotFound(params.program.clone()));
+        }
+        fs_err::read_to_string(&params.program).map_err(Error::CouldNotReadProgramFile)?
+    };
+    let program_name = if is_stdin(&params.program) {
+        "<stdin>".to_owned()
+    } else {
+        params.program.to_string_lossy().into_owned()
+    };
+    let program = parse_program(&source, &program_name)?;
+    if let Some(list) = &params.workspaces_from {
+        let listed = read_target_list(list)?;
+        params.workspaces.extend(listed);
+    }
+    if let Some(list) = &params.crates_from {
+        let listed = read_target_list(list)?;
+        params.crates.extend(listed);
+    }
+    if !params.skip_command_check {
+        let missing = prerequisites::missing_commands(&program, &environment);
+        if !missing.is_empty() {
+            return Err(Error::MissingCommands(missing.join(", ")));
+        }
     }
-    let source = fs_err::read_to_string(&params.program).map_err(Error::CouldNotReadProgramFile)?;
-    let program = crate::program::parser::parse(&source, &params.program.to_string_lossy())
-        .map_err(|errors| {
-            let msgs = errors
+
+    let selection = target_refresh::Selection {
+        workspaces: (!params.workspaces.is_empty()).then(|| params.workspaces.clone()),
+        crates: (!params.crates.is_empty()).then(|| params.crates.clone()),
+    };
+    let _lock = if params.register_path_dependencies {
+        Some(crate::config_lock::acquire(&environment).await?)
+    } else {
+        None
+    };
+    let resolved = resolve_targets(
+        &program,
+        &selection,
+        &environment,
+        params.register_path_dependencies,
+    )?;
+    let (selection, resolved) = if params.include_dependents {
+        include_dependents(&program, resolved, &environment)?
+    } else {
+        (selection, resolved)
+    };
+
+    store_task(
+        &params.name,
+        Some(params.program.as_path()).filter(|path| !is_stdin(path)),
+        &source,
+        &resolved,
+        &environment,
+    )?;
+    // Record the explicit targets as resolved, i.e. canonical workspace roots.
+    target_refresh::Selection {
+        workspaces: selection.workspaces.map(|_| {
+            resolved
+                .workspace_executions
                 .iter()
-                .map(|e| e.as_str().to_owned())
-                .collect::<Vec<_>>()
-                .join("\n");
-            Error::ProgramParseErrors(msgs)
-        })?;
+                .map(|w| w.manifest_dir.clone())
+                .collect()
+        }),
+        crates: selection.crates.map(|_| {
+            resolved
+                .crate_executions
+                .iter()
+                .map(|c| c.manifest_dir.clone())
+                .collect()
+        }),
+    }
+    .write(&named_dir_path(&params.name, &environment)?)
+}
 
+/// Resolves the targets of `program`: the explicitly given workspaces and
+/// standalone crates of `selection`, and the ones the program selects for
+/// the other kind.  `register` is passed to [`resolve_from_config`].
+///
+/// # Errors
+///
+/// Returns an error if the configuration cannot be loaded or the targets
+/// cannot be resolved.
+pub fn resolve_targets(
+    program: &Program,
+    selection: &target_refresh::Selection,
+    environment: &Environment,
+    register: bool,
+) -> Result<ResolvedProgram, Error> {
     use crate::program::resolve::{
-        ResolvedProgram, resolve_explicit_crate_targets, resolve_explicit_workspace_targets,
+        collect_member_filters, resolve_explicit_crate_targets, resolve_explicit_workspace_targets,
     };
-    let resolved = if params.workspaces.is_empty() && params.crates.is_empty() {
-        let config = Config::load(&environment)?;
-        crate::program::resolve::resolve_program(&program, &config)?
-    } else if params.workspaces.is_empty() || params.crates.is_empty() {
-        // One side uses explicit paths; the other still needs the program selection.
-        let config = Config::load(&environment)?;
-        let from_program = crate::program::resolve::resolve_program(&program, &config)?;
-        let workspace_executions = if params.workspaces.is_empty() {
-            from_program.workspace_executions
-        } else {
-            resolve_explicit_workspace_targets(&params.workspaces)?
-        };
-        let crate_executions = if params.crates.is_empty() {
-            from_program.crate_executions
-        } else {
-            resolve_explicit_crate_targets(&params.crates)?
-        };
-        ResolvedProgram {
-            workspace_executions,
-            crate_executions,
-        }
+    let member_filters = collect_member_filters(program);
+    let provider = Cached::new(Subprocess);
+    let config = Config::load(environment)?;
+    let from_program = if selection.workspaces.is_none() || selection.crates.is_none() {
+        resolve_from_config(program, environment, register)?
     } else {
-        // Both sides are explicit — no config or program selection needed.
         ResolvedProgram {
-            workspace_executions: resolve_explicit_workspace_targets(&params.workspaces)?,
-            crate_executions: resolve_explicit_crate_targets(&params.crates)?,
+            workspace_executions: vec![],
+            crate_executions: vec![],
         }
     };
+    Ok(ResolvedProgram {
+        workspace_executions: match &selection.workspaces {
+            Some(dirs) => {
+                resolve_explicit_workspace_targets(dirs, &member_filters, &config, &provider)?
+            }
+            None => from_program.workspace_executions,
+        },
+        crate_executions: match &selection.crates {
+            Some(dirs) => resolve_explicit_crate_targets(dirs, &provider)?,
+            None => from_program.crate_executions,
+        },
+    })
+}
 
-    let task_dir = named_dir_path(&params.name, &environment)?;
+/// Adds the registered targets depending on crates in `resolved` to it, for
+/// `task create --include-dependents`.  Returns the targets as an explicit
+/// selection, so refreshing the task keeps the added ones.
+///
+/// # Errors
+///
+/// Returns an error if the configuration cannot be loaded or if the targets
+/// cannot be resolved.
+#[expect(clippy::print_stdout, reason = "This is part of the UI, not logging")]
+fn include_dependents(
+    program: &Program,
+    resolved: ResolvedProgram,
+    environment: &Environment,
+) -> Result<(target_refresh::Selection, ResolvedProgram), Error> {
+    let config = Config::load(environment)?;
+    let dependents =
+        crate::targets::dependents::fan_out(&resolved, &config, &Cached::new(Subprocess))?;
+    for dir in dependents.workspaces.iter().chain(&dependents.crates) {
+        println!("Including dependent {}", dir.display());
+    }
+    let selection = target_refresh::Selection {
+        workspaces: Some(
+            resolved
+                .workspace_executions
+                .iter()
+                .map(|w| w.manifest_dir.clone())
+                .chain(dependents.workspaces)
+                .collect(),
+        ),
+        crates: Some(
+            resolved
+                .crate_executions
+                .iter()
+                .map(|c| c.manifest_dir.clone())
+                .chain(dependents.crates)
+                .collect(),
+        ),
+    };
+    let resolved = resolve_targets(program, &selection, environment, false)?;
+    Ok((selection, resolved))
+}
+
+/// Creates the directory of the task `name` with the program `source` read
+/// from the file `program` and the targets in `resolved`.  Without a file
+/// (e.g. for standard input) the program cannot be compared against its
+/// source later.
+///
+/// # Errors
+///
+/// Returns an error if the task already exists or its files cannot be
+/// written.
+pub fn store_task(
+    name: &str,
+    program: Option<&Path>,
+    source: &str,
+    resolved: &ResolvedProgram,
+    environment: &Environment,
+) -> Result<(), Error> {
+    let task_dir = named_dir_path(name, environment)?;
     if task_dir.exists() {
-        return Err(Error::AlreadyExists(format!("task {}", params.name)));
+        return Err(Error::AlreadyExists(format!("task {name}")));
     }
     fs_err::create_dir_all(&task_dir)
         .map_err(|e| Error::CouldNotCreateTaskDir(task_dir.clone(), e))?;
 
-    fs_err::copy(&params.program, task_dir.join("program.cfe")).map_err(|e| {
-        Error::CouldNotCopyFile(params.program.clone(), task_dir.join("program.cfe"), e)
+    let program_path = task_dir.join("program.cfe");
+    crate::utils::write_atomically(&program_path, source).map_err(|e| {
+        Error::CouldNotCopyFile(
+            program.map_or_else(|| PathBuf::from("-"), Path::to_path_buf),
+            program_path,
+            e,
+        )
     })?;
+    if let Some(program) = program {
+        program_source::write(
+            &task_dir,
+            &program_source::Record {
+                path: fs_err::canonicalize(program).map_err(Error::IoError)?,
+                checksum: program_source::checksum(source),
+            },
+        )?;
+    }
 
-    let resolved_path = task_dir.join("resolved-program.toml");
-    fs_err::write(
-        &resolved_path,
-        toml::to_string(&resolved).map_err(Error::CouldNotSerializeResolvedProgram)?,
+    write_resolved_program(&task_dir, resolved)?;
+    resolution_inputs::write(
+        &task_dir,
+        &resolution_inputs::collect(resolved, environment)?,
     )
-    .map_err(Error::CouldNotWriteResolvedProgram)?;
+}
 
+/// Returns the name of the task for each registered multi-crate workspace,
+/// `prefix` followed by the name of the workspace directory, sorted by name.
+///
+/// # Errors
+///
+/// Returns [`Error::AmbiguousWorkspaceTaskName`] if two workspaces would get
+/// the same task name.
+fn per_workspace_task_names(
+    workspaces: &[crate::Workspace],
+    prefix: &str,
+) -> Result<BTreeMap<String, PathBuf>, Error> {
+    let mut names = BTreeMap::new();
+    for workspace in workspaces.iter().filter(|w| !w.is_standalone) {
+        let dir_name = workspace
+            .manifest_dir
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let name = format!("{prefix}{dir_name}");
+        if let Some(other) = names.insert(name.clone(), workspace.manifest_dir.clone()) {
+            return Err(Error::AmbiguousWorkspaceTaskName(
+                name,
+                other,
+                workspace.manifest_dir.clone(),
+            ));
+        }
+    }
+    Ok(names)
+}
+
+/// Creates one task per registered multi-crate workspace, each running the
+/// program on that workspace only.
+///
+/// Tasks that already exist are skipped, so the command can be repeated
+/// after registering more workspaces.
+///
+/// # Errors
+///
+/// Returns an error if the program cannot be read or parsed, if commands used
+/// by the program are not installed, if the configuration cannot be loaded,
+/// if two workspaces would get the same task name, or if a task cannot be
+/// resolved or written.
+#[instrument]
+#[expect(clippy::print_stdout, reason = "This is part of the UI, not logging")]
+pub async fn task_create_per_workspace_command(
+    params: CreatePerWorkspaceParameters,
+    environment: crate::Environment,
+) -> Result<(), Error> {
+    let (source, program_name) = if is_stdin(&params.program) {
+        (
+            read_stdin().map_err(Error::CouldNotReadProgramFile)?,
+            "<stdin>".to_owned(),
+        )
+    } else {
+        if !params.program.exists() {
+            return Err(Error::ProgramNotFound(params.program.clone()));
+        }
+        (
+            fs_err::read_to_string(&params.program).map_err(Error::CouldNotReadProgramFile)?,
+            params.program.to_string_lossy().into_owned(),
+        )
+    };
+    let program = parse_program(&source, &program_name)?;
+    if !params.skip_command_check {
+        let missing = prerequisites::missing_commands(&program, &environment);
+        if !missing.is_empty() {
+            return Err(Error::MissingCommands(missing.join(", ")));
+        }
+    }
+
+    let config = Config::load(&environment)?;
+    let names = per_workspace_task_names(&config.workspaces, &params.prefix)?;
+    let member_filters = crate::program::resolve::collect_member_filters(&program);
+    let provider = Cached::new(Subprocess);
+    let mut created: usize = 0;
+    for (name, manifest_dir) in &names {
+        if named_dir_path(name, &environment)?.exists() {
+            println!("Skipped {name}: the task already exists");
+            continue;
+        }
+        let resolved = ResolvedProgram {
+            workspace_executions: crate::program::resolve::resolve_explicit_workspace_targets(
+                std::slice::from_ref(manifest_dir),
+                &member_filters,
+                &config,
+                &provider,
+            )?,
+            crate_executions: vec![],
+        };
+        store_task(
+            name,
+            Some(params.program.as_path()).filter(|path| !is_stdin(path)),
+            &source,
+            &resolved,
+            &environment,
+        )?;
+        target_refresh::Selection {
+            workspaces: Some(vec![manifest_dir.clone()]),
+            crates: Some(vec![]),
+        }
+        .write(&named_dir_path(name, &environment)?)?;
+        println!("Created {name} for {}", manifest_dir.display());
+        created = created.saturating_add(1);
+    }
+    println!("Created {created} of {} workspace tasks", names.len());
     Ok(())
 }
 
+/// Stores the resolved targets of the task in `task_dir`.
+///
+/// # Errors
+///
+/// Returns an error if the resolved program cannot be serialized or written.
+fn write_resolved_program(task_dir: &Path, resolved: &ResolvedProgram) -> Result<(), Error> {
+    let resolved_path = task_dir.join("resolved-program.toml");
+    crate::utils::write_atomically(
+        &resolved_path,
+        toml::to_string(resolved).map_err(Error::CouldNotSerializeResolvedProgram)?,
+    )
+    .map_err(Error::CouldNotWriteResolvedProgram)
+}
+
 /// Finds and executes the next uncompleted statement in a task.
 ///
 /// # Errors
@@ -2009,18 +3577,41 @@ pub async fn task_create_command(
 pub async fn run_single_step_command(
     params: RunSingleStepParameters,
     environment: crate::Environment,
+    run_options: crate::RunOptions,
 ) -> Result<(), Error> {
     let (program, resolved) = load_task_data(&params.name, &environment)?;
     let config = Config::load(&environment)?;
     let state_base = state_dir_for_task(&params.name, &environment)?;
 
     if let Some(next) = find_next_statement(&program, &resolved, &state_base) {
+        if let Some(target) = next.cursor.segments().first() {
+            clean_git::check(
+                next.manifest_dir,
+                &state_base.join(ProgramCursor::new().with(*target).to_path()),
+                run_options.require_clean_git,
+            )?;
+            fingerprint::check(
+                next.manifest_dir,
+                &state_base.join(ProgramCursor::new().with(*target).to_path()),
+                &run_options,
+            )?;
+        }
         println!(
             "Running statement at {} for {}",
             next.cursor,
             next.manifest_dir.display()
         );
         let extra_env = load_env_vars_from_files(&next.env_file_paths, next.manifest_dir)?;
+        let in_crate = next
+            .cursor
+            .segments()
+            .iter()
+            .any(|segment| matches!(segment, CursorSegment::CrateIteration(_)));
+        let extra_env = if in_crate {
+            crate_env(&config, next.manifest_dir, &extra_env)
+        } else {
+            extra_env
+        };
         match next.action {
             StatementAction::RunCommand(step) => {
                 execute_run_step(
@@ -2029,6 +3620,31 @@ pub async fn run_single_step_command(
                     next.manifest_dir,
                     &state_base,
                     &environment,
+                    &run_options,
+                    &extra_env,
+                )
+                .await?;
+            }
+            StatementAction::Cargo(step) => {
+                execute_run_step(
+                    &step.to_run_step(),
+                    &next.cursor,
+                    next.manifest_dir,
+                    &state_base,
+                    &environment,
+                    &run_options,
+                    &extra_env,
+                )
+                .await?;
+            }
+            StatementAction::FeaturePowerset(node) => {
+                execute_feature_powerset_step(
+                    node,
+                    &next.cursor,
+                    next.manifest_dir,
+                    &state_base,
+                    &environment,
+                    &run_options,
                     &extra_env,
                 )
                 .await?;
@@ -2040,6 +3656,7 @@ pub async fn run_single_step_command(
                     next.manifest_dir,
                     &state_base,
                     &environment,
+                    &run_options,
                     &extra_env,
                 )
                 .await?;
@@ -2070,6 +3687,18 @@ pub async fn run_single_step_command(
                 execute_snapshot_metadata_step(step, &next.cursor, next.manifest_dir, &state_base)
                     .await?;
             }
+            StatementAction::WaitUntil(step) => {
+                execute_wait_until_step(
+                    step,
+                    &next.cursor,
+                    next.manifest_dir,
+                    &state_base,
+                    &environment,
+                    &run_options,
+                    &extra_env,
+                )
+                .await?;
+            }
             StatementAction::WaitForContinue(node) => {
                 let state_dir = state_base.join(next.cursor.to_path());
                 fs_err::create_dir_all(&state_dir)
@@ -2099,6 +3728,7 @@ pub async fn run_single_step_command(
 pub async fn run_single_target_command(
     params: RunSingleTargetParameters,
     environment: crate::Environment,
+    run_options: crate::RunOptions,
 ) -> Result<(), Error> {
     let (program, resolved) = load_task_data(&params.name, &environment)?;
     let config = Config::load(&environment)?;
@@ -2119,11 +3749,25 @@ pub async fn run_single_target_command(
         if is_workspace_completed(ws_idx, ws_exec, ws_stmts, &state_base) {
             continue;
         }
+        if let Some(reason) = skip_marker::reason(&ws_exec.manifest_dir) {
+            tracing::warn!("Skipping {}: {reason}", ws_exec.manifest_dir.display());
+            continue;
+        }
         println!(
             "Running all statements for workspace {}.",
             ws_exec.manifest_dir.display()
         );
         let prefix = ProgramCursor::new().with(CursorSegment::WorkspaceIteration(ws_idx));
+        clean_git::check(
+            &ws_exec.manifest_dir,
+            &state_base.join(prefix.to_path()),
+            run_options.require_clean_git,
+        )?;
+        fingerprint::check(
+            &ws_exec.manifest_dir,
+            &state_base.join(prefix.to_path()),
+            &run_options,
+        )?;
         run_workspace_stmts_to_completion(
             ws_stmts,
             &prefix,
@@ -2131,9 +3775,11 @@ pub async fn run_single_target_command(
             &ws_exec.member_crates,
             &state_base,
             &environment,
+            &run_options,
             &config,
             &[],
             &params.name,
+            None,
         )
         .await?;
         return Ok(());
@@ -2154,20 +3800,36 @@ pub async fn run_single_target_command(
         if is_standalone_crate_completed(c_idx, crate_stmts, &state_base) {
             continue;
         }
+        if let Some(reason) = skip_marker::reason(&crate_exec.manifest_dir) {
+            tracing::warn!("Skipping {}: {reason}", crate_exec.manifest_dir.display());
+            continue;
+        }
         println!(
             "Running all statements for crate {}.",
             crate_exec.manifest_dir.display()
         );
         let prefix = ProgramCursor::new().with(CursorSegment::CrateIteration(c_idx));
+        clean_git::check(
+            &crate_exec.manifest_dir,
+            &state_base.join(prefix.to_path()),
+            run_options.require_clean_git,
+        )?;
+        fingerprint::check(
+            &crate_exec.manifest_dir,
+            &state_base.join(prefix.to_path()),
+            &run_options,
+        )?;
         run_crate_stmts_to_completion(
             crate_stmts,
             &prefix,
             &crate_exec.manifest_dir,
             &state_base,
             &environment,
+            &run_options,
             &config,
-            &[],
+            &crate_env(&config, &crate_exec.manifest_dir, &[]),
             &params.name,
+            None,
         )
         .await?;
         return Ok(());
@@ -2177,242 +3839,638 @@ pub async fn run_single_target_command(
     Ok(())
 }
 
-/// Runs all targets in dependency order with optional parallelism.
-///
-/// Workspaces are executed first (in dependency order), followed by standalone
-/// crates.
-///
-/// # Errors
-///
-/// Returns an error if the task cannot be loaded, if a statement fails (unless
-/// `keep_going` is set), if some steps failed with `keep_going`, or if a
-/// circular dependency is detected.
-#[instrument]
-pub async fn run_all_targets_command(
-    params: RunAllTargetsParameters,
-    environment: crate::Environment,
-) -> Result<(), Error> {
-    let (program, resolved) = load_task_data(&params.name, &environment)?;
-    let config = Arc::new(Config::load(&environment)?);
-    let state_base = Arc::new(state_dir_for_task(&params.name, &environment)?);
-    let keep_going = params.keep_going;
-    let jobs = params.jobs.unwrap_or(1);
-    let resolved = Arc::new(resolved);
+/// Returns the name of the target in `manifest_dir` used to prefix its output.
+fn target_name(manifest_dir: &Path) -> String {
+    manifest_dir.file_name().map_or_else(
+        || manifest_dir.display().to_string(),
+        |name| name.to_string_lossy().into_owned(),
+    )
+}
 
-    let ws_stmts: Arc<Vec<WorkspaceStatement>> = Arc::new(first_workspace_stmts(&program).to_vec());
-    let crate_stmts: Arc<Vec<CrateStatement>> = Arc::new(first_crate_stmts(&program).to_vec());
+/// Returns how the output of the target in `manifest_dir` is shown in a
+/// parallel run, with its name padded to `name_width` if it is prefixed and
+/// on its progress bar `bar` otherwise.
+fn target_output(
+    parallel: bool,
+    prefix_output: bool,
+    manifest_dir: &Path,
+    name_width: usize,
+    bar: Option<indicatif::ProgressBar>,
+) -> Option<progress::Target> {
+    if !parallel {
+        None
+    } else if prefix_output {
+        Some(progress::Target::Prefixed(format!(
+            "{:name_width$}",
+            target_name(manifest_dir)
+        )))
+    } else {
+        Some(progress::Target::Logged(bar))
+    }
+}
 
-    // Phase 1: workspaces
-    {
-        let n = resolved.workspace_executions.len();
-        let mut completed = vec![false; n];
-        let mut failed = vec![false; n];
-        let mut has_errors = false;
+/// Reports that the target in `manifest_dir` starts, if its output goes to
+/// `log_file` instead of the terminal: with `bars` by adding a progress bar
+/// for it, which is returned, otherwise by printing a line.
+#[expect(clippy::print_stdout, reason = "This is part of the UI, not logging")]
+fn report_target_start(
+    manifest_dir: &Path,
+    log_file: Option<&Path>,
+    bars: Option<&progress::Bars>,
+) -> Option<indicatif::ProgressBar> {
+    let log_file = log_file?;
+    if let Some(bars) = bars {
+        return Some(bars.start(manifest_dir));
+    }
+    println!(
+        "Started {} (log: {})",
+        manifest_dir.display(),
+        log_file.display()
+    );
+    None
+}
 
-        loop {
-            let ws_map: HashMap<PathBuf, usize> = resolved
-                .workspace_executions
-                .iter()
-                .enumerate()
-                .map(|(i, w)| (w.manifest_dir.clone(), i))
-                .collect();
+/// Reports how the target in `manifest_dir` ended, if its output goes to
+/// `log_file` instead of the terminal, replacing its progress bar `bar`.
+#[expect(clippy::print_stdout, reason = "This is part of the UI, not logging")]
+fn report_target_end(
+    manifest_dir: &Path,
+    log_file: Option<&Path>,
+    result: &Result<bool, Error>,
+    bars: Option<&progress::Bars>,
+    bar: Option<&indicatif::ProgressBar>,
+) {
+    let Some(log_file) = log_file else {
+        return;
+    };
+    let line = match result {
+        Ok(true) => format!("Finished {}", manifest_dir.display()),
+        Ok(false) => format!("Stopped {}", manifest_dir.display()),
+        Err(e) => format!(
+            "Failed {}: {e} (log: {})",
+            manifest_dir.display(),
+            log_file.display()
+        ),
+    };
+    match (bars, bar) {
+        (Some(bars), Some(bar)) => bars.finish(bar, &line),
+        _ => println!("{line}"),
+    }
+}
 
-            let ready: Vec<(usize, PathBuf, Vec<ResolvedCrateExecution>)> = resolved
-                .workspace_executions
+/// Prints which targets of the task `task_name` are not completed yet after
+/// the run was interrupted with Ctrl+C (`error`), and how to resume it.
+#[expect(clippy::print_stdout, reason = "This is part of the UI, not logging")]
+fn print_remaining_work(
+    error: &Error,
+    task_name: &str,
+    resolved: &ResolvedProgram,
+    ws_stmts: &[WorkspaceStatement],
+    crate_stmts: &[CrateStatement],
+    state_base: &Path,
+) {
+    if !matches!(error, Error::RunInterrupted) {
+        return;
+    }
+    let remaining: Vec<&Path> = resolved
+        .workspace_executions
+        .iter()
+        .enumerate()
+        .filter(|(ws_idx, ws_exec)| !is_workspace_completed(*ws_idx, ws_exec, ws_stmts, state_base))
+        .map(|(_, ws_exec)| ws_exec.manifest_dir.as_path())
+        .chain(
+            resolved
+                .crate_executions
                 .iter()
                 .enumerate()
-                .filter(|(idx, ws_exec)| {
-                    !completed.get(*idx).copied().unwrap_or(false)
-                        && !failed.get(*idx).copied().unwrap_or(false)
-                        && ws_exec.dependencies.iter().all(|dep| {
-                            ws_map.get(dep).is_none_or(|&dep_idx| {
-                                completed.get(dep_idx).copied().unwrap_or(false)
-                            })
-                        })
+                .filter(|(c_idx, _)| {
+                    !is_standalone_crate_completed(*c_idx, crate_stmts, state_base)
                 })
-                .map(|(idx, ws_exec)| {
-                    (
-                        idx,
-                        ws_exec.manifest_dir.clone(),
-                        ws_exec.member_crates.clone(),
-                    )
-                })
-                .collect();
+                .map(|(_, c_exec)| c_exec.manifest_dir.as_path()),
+        )
+        .collect();
+    println!(
+        "Interrupted; {} target(s) are not completed yet:",
+        remaining.len()
+    );
+    for manifest_dir in remaining {
+        println!("  {}", manifest_dir.display());
+    }
+    println!(
+        "Resume with `cargo-for-each task run all-targets --name {task_name}`; interrupted steps start over."
+    );
+}
 
-            if ready.is_empty() {
-                break;
-            }
-
-            let results: Vec<(usize, Result<(), Error>)> = stream::iter(ready)
-                .map(|(ws_idx, manifest_dir, member_crates)| {
-                    let ws_stmts = Arc::clone(&ws_stmts);
-                    let config = Arc::clone(&config);
-                    let state_base = Arc::clone(&state_base);
-                    let environment = environment.clone();
-                    let task_name = params.name.clone();
-                    async move {
-                        let prefix =
-                            ProgramCursor::new().with(CursorSegment::WorkspaceIteration(ws_idx));
-                        let result = run_workspace_stmts_to_completion(
-                            &ws_stmts,
-                            &prefix,
-                            &manifest_dir,
-                            &member_crates,
-                            &state_base,
-                            &environment,
-                            &config,
-                            &[],
-                            &task_name,
-                        )
-                        .await;
-                        (ws_idx, result)
-                    }
-                })
-                .buffer_unordered(jobs)
-                .collect()
-                .await;
+/// Cancels the run with `cancellation` after a failure, interrupting the
+/// `running` other targets.
+fn cancel_running(cancellation: &cancellation::Token, running: usize) {
+    if running > 0 && !cancellation.is_cancelled() {
+        tracing::warn!("Interrupting {running} running target(s) because the run failed");
+    }
+    cancellation.cancel();
+}
 
-            for (idx, result) in results {
-                match result {
-                    Ok(()) => {
-                        if let Some(slot) = completed.get_mut(idx) {
-                            *slot = true;
-                        }
-                    }
-                    Err(e) => {
-                        if keep_going {
-                            tracing::error!("Workspace failed: {}", e);
-                            if let Some(slot) = failed.get_mut(idx) {
-                                *slot = true;
-                            }
-                            has_errors = true;
-                        } else {
-                            return Err(e);
-                        }
-                    }
-                }
-            }
-        }
+/// The targets [`run_in_dependency_order`] did not run.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct NotRun {
+    /// Targets blocked by uncommitted changes or a changed environment, and
+    /// the targets depending on them.
+    blocked: Vec<PathBuf>,
+    /// Skipped targets and the targets depending on them or on failed ones.
+    skipped: Vec<PathBuf>,
+}
 
-        if has_errors {
-            return Err(Error::SomeStepsFailed);
-        }
-        if !completed.iter().all(|&c| c) {
-            return Err(Error::CircularDependency);
+/// Abandons the target `idx` in `scheduler`, logging each target that
+/// depends on it as skipped and adding it to `not_run`.
+fn abandon_dependents(
+    scheduler: &mut Scheduler,
+    targets: &[(PathBuf, Vec<PathBuf>)],
+    idx: usize,
+    not_run: &mut Vec<PathBuf>,
+) {
+    let Some((manifest_dir, _)) = targets.get(idx) else {
+        return;
+    };
+    for dependent in scheduler.abandon(idx) {
+        if let Some((dependent_dir, _)) = targets.get(dependent) {
+            tracing::warn!(
+                "Skipping {}: it depends on {}",
+                dependent_dir.display(),
+                manifest_dir.display()
+            );
+            not_run.push(dependent_dir.clone());
         }
     }
+}
 
-    // Phase 2: standalone crates
-    {
-        let n = resolved.crate_executions.len();
-        let mut completed = vec![false; n];
-        let mut failed = vec![false; n];
-        let mut has_errors = false;
-
-        loop {
-            let crate_map: HashMap<PathBuf, usize> = resolved
-                .crate_executions
-                .iter()
-                .enumerate()
-                .map(|(i, c)| (c.manifest_dir.clone(), i))
-                .collect();
-
-            let ready: Vec<(usize, PathBuf)> = resolved
-                .crate_executions
-                .iter()
-                .enumerate()
-                .filter(|(idx, crate_exec)| {
-                    !completed.get(*idx).copied().unwrap_or(false)
-                        && !failed.get(*idx).copied().unwrap_or(false)
-                        && crate_exec.dependencies.iter().all(|dep| {
-                            crate_map.get(dep).is_none_or(|&dep_idx| {
-                                completed.get(dep_idx).copied().unwrap_or(false)
-                            })
-                        })
-                })
-                .map(|(idx, crate_exec)| (idx, crate_exec.manifest_dir.clone()))
-                .collect();
-
-            if ready.is_empty() {
-                break;
-            }
-
-            let results: Vec<(usize, Result<(), Error>)> = stream::iter(ready)
-                .map(|(c_idx, manifest_dir)| {
-                    let crate_stmts = Arc::clone(&crate_stmts);
-                    let config = Arc::clone(&config);
-                    let state_base = Arc::clone(&state_base);
-                    let environment = environment.clone();
-                    let task_name = params.name.clone();
-                    async move {
-                        let prefix =
-                            ProgramCursor::new().with(CursorSegment::CrateIteration(c_idx));
-                        let result = run_crate_stmts_to_completion(
-                            &crate_stmts,
-                            &prefix,
-                            &manifest_dir,
-                            &state_base,
-                            &environment,
-                            &config,
-                            &[],
-                            &task_name,
-                        )
-                        .await;
-                        (c_idx, result)
-                    }
-                })
-                .buffer_unordered(jobs)
-                .collect()
-                .await;
-
-            for (idx, result) in results {
-                match result {
-                    Ok(()) => {
-                        if let Some(slot) = completed.get_mut(idx) {
-                            *slot = true;
+/// Runs `targets` (manifest directories with their dependencies) with up to
+/// `--jobs` of them at a time, starting each one as soon as its dependencies
+/// completed and ordering simultaneously ready targets by `--schedule` and
+/// `--fail-fast-order`.  Targets depending on a failed, blocked or skipped
+/// target, directly or transitively, do not run and are logged as skipped.
+/// `kind` names the targets in error messages.
+///
+/// After a failure without `--keep-going`, or once `--max-failures` targets
+/// failed, no further targets are started and `cancellation` is cancelled,
+/// which interrupts the running ones (see [`cancellation`]).  Once it is
+/// cancelled otherwise, i.e. by Ctrl+C, no further targets are started
+/// either.  With `--interactive` each target is confirmed before it starts
+/// (see [`target_gate`]); targets with a [`skip_marker`] are not started.
+///
+/// Returns the targets that did not run.
+///
+/// # Errors
+///
+/// Returns [`Error::CircularDependency`] if the targets cannot be ordered,
+/// the first error of a target without `keep_going`,
+/// [`Error::TooManyFailures`], [`Error::RunAborted`] if the run was aborted
+/// interactively, [`Error::RunInterrupted`] if it was interrupted with
+/// Ctrl+C, or [`Error::SomeStepsFailed`] if targets failed with `keep_going`.
+async fn run_in_dependency_order<F, Fut>(
+    targets: &[(PathBuf, Vec<PathBuf>)],
+    params: &RunAllTargetsParameters,
+    history: &[crate::journal::Entry],
+    failure_count: &mut usize,
+    kind: &str,
+    cancellation: &cancellation::Token,
+    mut run: F,
+) -> Result<NotRun, Error>
+where
+    F: FnMut(usize, PathBuf) -> Fut,
+    Fut: Future<Output = Result<(), Error>>,
+{
+    let mut scheduler = Scheduler::new(targets)?;
+    let priorities = target_priorities(targets, params.schedule);
+    let failure_priorities = failure_history::priorities(targets, history);
+    let jobs = params.jobs.unwrap_or(1).max(1);
+    let mut queue: Vec<usize> = Vec::new();
+    let mut running = FuturesUnordered::new();
+    let mut not_run = NotRun::default();
+    let mut has_errors = false;
+    let mut abort: Option<Error> = None;
+
+    loop {
+        if abort.is_none() && cancellation.is_cancelled() {
+            abort = Some(Error::RunInterrupted);
+        }
+        if abort.is_none() {
+            queue.extend(scheduler.take_ready());
+            sort_by_priority(&mut queue, &priorities, |idx| *idx);
+            sort_by_priority(&mut queue, &failure_priorities, |idx| *idx);
+            let mut free = jobs.saturating_sub(running.len());
+            let mut ready = std::mem::take(&mut queue).into_iter();
+            while free > 0 {
+                let Some(idx) = ready.next() else {
+                    break;
+                };
+                let Some((manifest_dir, _)) = targets.get(idx) else {
+                    continue;
+                };
+                if let Some(reason) = skip_marker::reason(manifest_dir) {
+                    tracing::warn!("Skipping {}: {reason}", manifest_dir.display());
+                    not_run.skipped.push(manifest_dir.clone());
+                    abandon_dependents(&mut scheduler, targets, idx, &mut not_run.skipped);
+                    continue;
+                }
+                if params.interactive {
+                    match target_gate::ask(manifest_dir) {
+                        Ok(target_gate::Answer::Continue) => {}
+                        Ok(target_gate::Answer::Skip) => {
+                            tracing::warn!(
+                                "Skipping {} and the targets depending on it",
+                                manifest_dir.display()
+                            );
+                            not_run.skipped.push(manifest_dir.clone());
+                            abandon_dependents(&mut scheduler, targets, idx, &mut not_run.skipped);
+                            continue;
                         }
-                    }
-                    Err(e) => {
-                        if keep_going {
-                            tracing::error!("Crate execution failed: {}", e);
-                            if let Some(slot) = failed.get_mut(idx) {
-                                *slot = true;
-                            }
-                            has_errors = true;
-                        } else {
-                            return Err(e);
+                        Ok(target_gate::Answer::Abort) => {
+                            abort.get_or_insert(Error::RunAborted);
+                            break;
+                        }
+                        Err(e) => {
+                            abort.get_or_insert(e);
+                            break;
                         }
                     }
                 }
+                let target = run(idx, manifest_dir.clone());
+                running.push(async move { (idx, target.await) });
+                free = free.saturating_sub(1);
             }
+            queue.extend(ready);
         }
-
-        if has_errors {
-            return Err(Error::SomeStepsFailed);
+        let Some((idx, result)) = running.next().await else {
+            break;
+        };
+        if abort.is_none() && cancellation.is_cancelled() {
+            abort = Some(Error::RunInterrupted);
         }
-        if !completed.iter().all(|&c| c) {
-            return Err(Error::CircularDependency);
+        match result {
+            Ok(()) => scheduler.complete(idx),
+            Err(Error::TargetBlocked(manifest_dir, reason)) => {
+                tracing::warn!("Skipping {}: {reason}", manifest_dir.display());
+                not_run.blocked.push(manifest_dir);
+                abandon_dependents(&mut scheduler, targets, idx, &mut not_run.blocked);
+            }
+            Err(e) if params.keep_going => {
+                tracing::error!("{kind} failed: {}", e);
+                abandon_dependents(&mut scheduler, targets, idx, &mut not_run.skipped);
+                has_errors = true;
+                *failure_count = failure_count.saturating_add(1);
+                if params.max_failures.is_some_and(|max| *failure_count >= max) {
+                    abort.get_or_insert(Error::TooManyFailures(*failure_count));
+                    cancel_running(cancellation, running.len());
+                }
+            }
+            Err(e) => {
+                if abort.is_none() {
+                    cancel_running(cancellation, running.len());
+                }
+                abort.get_or_insert(e);
+            }
         }
     }
 
-    Ok(())
+    if let Some(e) = abort {
+        return Err(e);
+    }
+    if !not_run.skipped.is_empty() {
+        tracing::warn!(
+            "{} target(s) skipped; run the task again to run them",
+            not_run.skipped.len()
+        );
+    }
+    if has_errors {
+        return Err(Error::SomeStepsFailed);
+    }
+    Ok(not_run)
 }
 
-/// Dispatches the `task run` subcommand.
+/// Runs all targets in dependency order with optional parallelism.
 ///
-/// # Errors
+/// With more than one job, the output of each target goes to its own log file
+/// (see [`target_log`]) and only progress is printed.
+///
+/// Workspaces are executed first (in dependency order), followed by standalone
+/// crates.
+///
+/// # Errors
+///
+/// Returns an error if the task cannot be loaded, if a statement fails (unless
+/// `keep_going` is set), if some steps failed with `keep_going`, if
+/// `max_failures` targets failed, if targets
+/// were blocked by uncommitted changes, or if a circular dependency is
+/// detected.
+#[instrument]
+pub async fn run_all_targets_command(
+    params: RunAllTargetsParameters,
+    environment: crate::Environment,
+    run_options: crate::RunOptions,
+) -> Result<(), Error> {
+    let (program, resolved) = load_task_data(&params.name, &environment)?;
+    let config = Arc::new(Config::load(&environment)?);
+    let state_base = Arc::new(state_dir_for_task(&params.name, &environment)?);
+    let mut failure_count: usize = 0;
+    let jobs = params.jobs.unwrap_or(1);
+    let resolved = Arc::new(resolved);
+
+    let ws_stmts: Arc<Vec<WorkspaceStatement>> = Arc::new(first_workspace_stmts(&program).to_vec());
+    let crate_stmts: Arc<Vec<CrateStatement>> = Arc::new(first_crate_stmts(&program).to_vec());
+
+    let phase_names = phases::names(&ws_stmts, &crate_stmts);
+    let selection = match (&params.until_phase, &params.only_phase) {
+        (Some(last), _) => Some(phases::Selection::until(&phase_names, last)?),
+        (None, Some(only)) => Some(phases::Selection::only(&phase_names, only)?),
+        (None, None) => None,
+    };
+    // Without `--fail-fast-order` all failure priorities are 0 and sorting by
+    // them keeps the order of `--schedule`.
+    let history = if params.fail_fast_order {
+        crate::journal::read_entries(&environment)?
+    } else {
+        Vec::new()
+    };
+    let parallel = jobs > 1;
+    let environment = crate::Environment {
+        suppress_subprocess_output: environment.suppress_subprocess_output || parallel,
+        ..environment
+    };
+    let run_options = crate::RunOptions {
+        phases: selection,
+        ..run_options
+    };
+    let _interrupts = cancellation::InterruptHandler::install(&run_options.cancellation);
+    let prefix_output = parallel && params.prefix_output;
+    let bars = if parallel && !prefix_output {
+        progress::Bars::new()
+    } else {
+        None
+    };
+    let name_width = resolved
+        .workspace_executions
+        .iter()
+        .map(|w| target_name(&w.manifest_dir).chars().count())
+        .chain(
+            resolved
+                .crate_executions
+                .iter()
+                .map(|c| target_name(&c.manifest_dir).chars().count()),
+        )
+        .max()
+        .unwrap_or_default();
+
+    // Phase 1: workspaces
+    let ws_targets: Vec<(PathBuf, Vec<PathBuf>)> = resolved
+        .workspace_executions
+        .iter()
+        .map(|w| (w.manifest_dir.clone(), w.dependencies.clone()))
+        .collect();
+    let ws_not_run = run_in_dependency_order(
+        &ws_targets,
+        &params,
+        &history,
+        &mut failure_count,
+        "Workspace",
+        &run_options.cancellation,
+        |ws_idx, manifest_dir| {
+            let ws_stmts = Arc::clone(&ws_stmts);
+            let resolved = Arc::clone(&resolved);
+            let config = Arc::clone(&config);
+            let state_base = Arc::clone(&state_base);
+            let environment = environment.clone();
+            let run_options = run_options.clone();
+            let task_name = params.name.clone();
+            let bars = bars.clone();
+            async move {
+                let prefix = ProgramCursor::new().with(CursorSegment::WorkspaceIteration(ws_idx));
+                clean_git::check(
+                    &manifest_dir,
+                    &state_base.join(prefix.to_path()),
+                    run_options.require_clean_git,
+                )?;
+                fingerprint::check(
+                    &manifest_dir,
+                    &state_base.join(prefix.to_path()),
+                    &run_options,
+                )?;
+                let member_crates = resolved
+                    .workspace_executions
+                    .get(ws_idx)
+                    .map(|w| w.member_crates.as_slice())
+                    .unwrap_or_default();
+                let log_file =
+                    (parallel && !prefix_output).then(|| target_log::path(&state_base, &prefix));
+                let bar = report_target_start(&manifest_dir, log_file.as_deref(), bars.as_ref());
+                let run = run_workspace_stmts_to_completion(
+                    &ws_stmts,
+                    &prefix,
+                    &manifest_dir,
+                    member_crates,
+                    &state_base,
+                    &environment,
+                    &run_options,
+                    &config,
+                    &[],
+                    &task_name,
+                    None,
+                )
+                .instrument(target_log::span(&manifest_dir, log_file.as_deref()));
+                let result = match target_output(
+                    parallel,
+                    prefix_output,
+                    &manifest_dir,
+                    name_width,
+                    bar.clone(),
+                ) {
+                    Some(output) => progress::scope(output, run).await,
+                    None => run.await,
+                };
+                report_target_end(
+                    &manifest_dir,
+                    log_file.as_deref(),
+                    &result,
+                    bars.as_ref(),
+                    bar.as_ref(),
+                );
+                result.map(|_finished| ())
+            }
+        },
+    )
+    .await
+    .inspect_err(|e| {
+        print_remaining_work(
+            e,
+            &params.name,
+            &resolved,
+            &ws_stmts,
+            &crate_stmts,
+            &state_base,
+        );
+    })?;
+
+    // Phase 2: standalone crates
+    let crate_targets: Vec<(PathBuf, Vec<PathBuf>)> = resolved
+        .crate_executions
+        .iter()
+        .map(|c| (c.manifest_dir.clone(), c.dependencies.clone()))
+        .collect();
+    let crate_not_run = run_in_dependency_order(
+        &crate_targets,
+        &params,
+        &history,
+        &mut failure_count,
+        "Crate execution",
+        &run_options.cancellation,
+        |c_idx, manifest_dir| {
+            let crate_stmts = Arc::clone(&crate_stmts);
+            let config = Arc::clone(&config);
+            let state_base = Arc::clone(&state_base);
+            let environment = environment.clone();
+            let run_options = run_options.clone();
+            let task_name = params.name.clone();
+            let bars = bars.clone();
+            async move {
+                let prefix = ProgramCursor::new().with(CursorSegment::CrateIteration(c_idx));
+                clean_git::check(
+                    &manifest_dir,
+                    &state_base.join(prefix.to_path()),
+                    run_options.require_clean_git,
+                )?;
+                fingerprint::check(
+                    &manifest_dir,
+                    &state_base.join(prefix.to_path()),
+                    &run_options,
+                )?;
+                let log_file =
+                    (parallel && !prefix_output).then(|| target_log::path(&state_base, &prefix));
+                let bar = report_target_start(&manifest_dir, log_file.as_deref(), bars.as_ref());
+                let env = crate_env(&config, &manifest_dir, &[]);
+                let run = run_crate_stmts_to_completion(
+                    &crate_stmts,
+                    &prefix,
+                    &manifest_dir,
+                    &state_base,
+                    &environment,
+                    &run_options,
+                    &config,
+                    &env,
+                    &task_name,
+                    None,
+                )
+                .instrument(target_log::span(&manifest_dir, log_file.as_deref()));
+                let result = match target_output(
+                    parallel,
+                    prefix_output,
+                    &manifest_dir,
+                    name_width,
+                    bar.clone(),
+                ) {
+                    Some(output) => progress::scope(output, run).await,
+                    None => run.await,
+                };
+                report_target_end(
+                    &manifest_dir,
+                    log_file.as_deref(),
+                    &result,
+                    bars.as_ref(),
+                    bar.as_ref(),
+                );
+                result.map(|_finished| ())
+            }
+        },
+    )
+    .await
+    .inspect_err(|e| {
+        print_remaining_work(
+            e,
+            &params.name,
+            &resolved,
+            &ws_stmts,
+            &crate_stmts,
+            &state_base,
+        );
+    })?;
+
+    let blocked_count = ws_not_run
+        .blocked
+        .len()
+        .saturating_add(crate_not_run.blocked.len());
+    if blocked_count > 0 {
+        return Err(Error::TargetsBlocked(blocked_count));
+    }
+    Ok(())
+}
+
+/// Dispatches the `task run` subcommand.
+///
+/// # Errors
 ///
 /// Propagates errors from the chosen subcommand.
 #[instrument]
 pub async fn task_run_command(
     params: TaskRunParameters,
     environment: crate::Environment,
+    run_options: crate::RunOptions,
 ) -> Result<(), Error> {
-    match params.sub_command {
-        TaskRunSubCommand::SingleStep(p) => run_single_step_command(p, environment).await,
-        TaskRunSubCommand::SingleTarget(p) => run_single_target_command(p, environment).await,
-        TaskRunSubCommand::AllTargets(p) => run_all_targets_command(p, environment).await,
+    let task_name = match &params.sub_command {
+        TaskRunSubCommand::SingleStep(p) => &p.name,
+        TaskRunSubCommand::SingleTarget(p) => &p.name,
+        TaskRunSubCommand::AllTargets(p) => &p.name,
+    };
+    if params.dry_run {
+        return dry_run::command(&params.sub_command, &environment);
+    }
+    warn_if_program_source_changed(task_name, &environment)?;
+    let (program, mut resolved) = load_task_data(task_name, &environment)?;
+    let _lock = run_lock::acquire(task_name, &state_dir_for_task(task_name, &environment)?)?;
+    if params.refresh_targets {
+        resolved =
+            target_refresh::refresh(task_name, &program, &resolved, &environment, true).await?;
+    }
+    overlap::check(task_name, &resolved, &environment, params.force)?;
+    check_resolution_inputs(task_name, &resolved, &environment, params.strict)?;
+    let keep_alive = if elevation::is_used(&program) {
+        if params.sandbox {
+            return Err(Error::ElevationInSandbox);
+        }
+        elevation::preflight(&environment)?;
+        Some(elevation::keep_alive())
+    } else {
+        None
+    };
+    let run_options = crate::RunOptions {
+        sandbox: params.sandbox,
+        explain_blocking: params.explain_blocking,
+        environment_change: if params.accept_environment_change {
+            fingerprint::OnChange::Continue
+        } else {
+            fingerprint::OnChange::Block
+        },
+        program_checksum: Some(fingerprint::program_checksum(task_name, &environment)?),
+        require_clean_git: params.require_clean_git || clean_git::is_required(&program),
+        recorder: if params.no_record {
+            recorder::Recorder::None
+        } else {
+            recorder::from_program(&program)
+        },
+        ..run_options
+    };
+    let result = match params.sub_command {
+        TaskRunSubCommand::SingleStep(p) => {
+            run_single_step_command(p, environment, run_options).await
+        }
+        TaskRunSubCommand::SingleTarget(p) => {
+            run_single_target_command(p, environment, run_options).await
+        }
+        TaskRunSubCommand::AllTargets(p) => {
+            run_all_targets_command(p, environment, run_options).await
+        }
+    };
+    if let Some(keep_alive) = keep_alive {
+        keep_alive.abort();
     }
+    result
 }
 
 // ── Rewind commands ────────────────────────────────────────────────────────────
@@ -2428,9 +4486,11 @@ pub async fn rewind_all_targets_command(
     environment: crate::Environment,
 ) -> Result<(), Error> {
     let state_dir = state_dir_for_task(&params.name, &environment)?;
+    run_lock::ensure_not_running(&params.name, &state_dir)?;
     if state_dir.exists() {
         fs_err::remove_dir_all(&state_dir)
             .map_err(|e| Error::CouldNotRemoveTaskStateDir(state_dir.clone(), e))?;
+        completion_cache::invalidate(&state_dir);
         tracing::info!("Removed all state for task '{}'.", params.name);
     } else {
         tracing::info!(
@@ -2455,6 +4515,7 @@ pub async fn rewind_single_target_command(
 ) -> Result<(), Error> {
     let (program, resolved) = load_task_data(&params.name, &environment)?;
     let state_base = state_dir_for_task(&params.name, &environment)?;
+    let _lock = run_lock::acquire(&params.name, &state_base)?;
 
     let ws_stmts = first_workspace_stmts(&program);
     let crate_stmts = first_crate_stmts(&program);
@@ -2468,6 +4529,7 @@ pub async fn rewind_single_target_command(
                 fs_err::remove_dir_all(&target_state_dir)
                     .map_err(|e| Error::CouldNotRemoveTaskStateDir(target_state_dir.clone(), e))?;
             }
+            completion_cache::invalidate(&state_base);
             tracing::info!(
                 "Rewound standalone crate {} in task '{}'.",
                 c_idx,
@@ -2485,6 +4547,7 @@ pub async fn rewind_single_target_command(
                 fs_err::remove_dir_all(&target_state_dir)
                     .map_err(|e| Error::CouldNotRemoveTaskStateDir(target_state_dir.clone(), e))?;
             }
+            completion_cache::invalidate(&state_base);
             tracing::info!("Rewound workspace {} in task '{}'.", ws_idx, params.name);
             return Ok(());
         }
@@ -2513,6 +4576,7 @@ pub async fn rewind_single_step_command(
     }
     let (program, resolved) = load_task_data(&params.name, &environment)?;
     let state_base = state_dir_for_task(&params.name, &environment)?;
+    let _lock = run_lock::acquire(&params.name, &state_base)?;
 
     let crate_stmts = first_crate_stmts(&program);
     let ws_stmts = first_workspace_stmts(&program);
@@ -2526,6 +4590,7 @@ pub async fn rewind_single_step_command(
                 fs_err::remove_dir_all(&step_state_dir)
                     .map_err(|e| Error::CouldNotRemoveTaskStateDir(step_state_dir.clone(), e))?;
             }
+            completion_cache::invalidate(&state_base);
             tracing::info!("Rewound statement {} in task '{}'.", cursor, params.name);
             return Ok(());
         }
@@ -2544,6 +4609,7 @@ pub async fn rewind_single_step_command(
                 fs_err::remove_dir_all(&step_state_dir)
                     .map_err(|e| Error::CouldNotRemoveTaskStateDir(step_state_dir.clone(), e))?;
             }
+            completion_cache::invalidate(&state_base);
             tracing::info!("Rewound statement {} in task '{}'.", cursor, params.name);
             return Ok(());
         }
@@ -2573,16 +4639,155 @@ pub async fn task_rewind_command(
     }
 }
 
+/// Clears the state of the selected targets and steps of a task.
+///
+/// # Errors
+///
+/// Returns an error if the task cannot be loaded, if the target is not part
+/// of the task or if the state cannot be removed.
+#[instrument]
+pub async fn task_reset_command(
+    params: ResetTaskParameters,
+    environment: crate::Environment,
+) -> Result<(), Error> {
+    let (_program, resolved) = load_task_data(&params.name, &environment)?;
+    let state_base = state_dir_for_task(&params.name, &environment)?;
+    let _lock = run_lock::acquire(&params.name, &state_base)?;
+    let target = params
+        .target
+        .map(crate::targets::canonical_manifest_dir)
+        .transpose()?;
+    let mut removed = 0_usize;
+    for prefix in reset::targets(&resolved, target.as_deref())? {
+        let dirs = reset::clear(
+            &state_base.join(prefix.to_path()),
+            params.step,
+            params.only_failed,
+        )?;
+        for dir in &dirs {
+            tracing::info!("Reset {}", dir.display());
+        }
+        removed = removed.saturating_add(dirs.len());
+    }
+    completion_cache::invalidate(&state_base);
+    tracing::info!(
+        "Reset {removed} state directories in task '{}'.",
+        params.name
+    );
+    Ok(())
+}
+
 // ── Describe and list commands ─────────────────────────────────────────────────
 
+/// Builds the label string for a `run` statement (raw AST, no interpolation).
+///
+/// Inline stdin content is not shown since it may contain secrets.
+fn run_label(step: &RunStep) -> String {
+    let mut parts = vec![format!("\"{}\"", step.command)];
+    parts.extend(step.args.iter().map(|a| format!("\"{a}\"")));
+    match &step.elevation {
+        None => {}
+        Some(Elevation::Root) => parts.push("elevate".to_owned()),
+        Some(Elevation::User(user)) => parts.push(format!("run_as \"{user}\"")),
+    }
+    if let Some(limit) = step.rate_limit {
+        let period = match limit.period_seconds {
+            1 => "second",
+            60 => "minute",
+            _ => "hour",
+        };
+        parts.push(format!("rate_limit {} per {period}", limit.max));
+    }
+    if !step.exclusive.is_empty() {
+        let names: Vec<String> = step.exclusive.iter().map(|r| format!("\"{r}\"")).collect();
+        parts.push(format!("exclusive {}", names.join(" ")));
+    }
+    if !step.env.is_empty() {
+        let assignments: Vec<String> = step
+            .env
+            .iter()
+            .map(|(name, value)| format!("\"{name}={value}\""))
+            .collect();
+        parts.push(format!("env {}", assignments.join(" ")));
+    }
+    if let Some(dir) = &step.cwd_relative {
+        parts.push(format!("cwd \"{dir}\""));
+    }
+    if let Some(seconds) = step.timeout_seconds {
+        parts.push(format!("timeout {seconds}"));
+    }
+    if let Some(retry) = step.retry {
+        parts.push(format!(
+            "retries {} delay {}",
+            retry.retries, retry.delay_seconds
+        ));
+    }
+    match &step.stdin {
+        None => {}
+        Some(StdinSource::Content(_)) => parts.push("stdin ...".to_owned()),
+        Some(StdinSource::File(file)) => parts.push(format!("stdin_file \"{file}\"")),
+    }
+    format!("run {}", parts.join(" "))
+}
+
+/// Builds the label string for a `wait_until` statement (raw AST, no interpolation).
+fn wait_until_label(node: &WaitUntilNode) -> String {
+    let mut parts = vec![format!("\"{}\"", node.command)];
+    parts.extend(node.args.iter().map(|a| format!("\"{a}\"")));
+    parts.push(format!("every {}", node.interval_seconds));
+    if let Some(timeout) = node.timeout_seconds {
+        parts.push(format!("timeout {timeout}"));
+    }
+    format!("wait_until {}", parts.join(" "))
+}
+
+/// Builds the label of a `cargo` statement in program syntax.
+fn cargo_label(step: &CargoStep) -> String {
+    let quoted = |values: &[String]| {
+        values
+            .iter()
+            .map(|value| format!("\"{value}\""))
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    let mut parts = vec![format!("cargo \"{}\"", step.subcommand)];
+    if !step.features.is_empty() {
+        parts.push(format!("features {}", quoted(&step.features)));
+    }
+    if let Some(profile) = &step.profile {
+        parts.push(format!("profile \"{profile}\""));
+    }
+    if step.locked {
+        parts.push(String::from("locked"));
+    }
+    if step.offline {
+        parts.push(String::from("offline"));
+    }
+    if !step.extra_args.is_empty() {
+        parts.push(format!("args {}", quoted(&step.extra_args)));
+    }
+    parts.join(" ")
+}
+
+/// Builds the label of a `feature_powerset` statement in program syntax.
+fn feature_powerset_label(node: &FeaturePowersetNode) -> String {
+    let mut parts = vec![String::from("feature_powerset")];
+    if let Some(depth) = node.depth {
+        parts.push(format!("depth {depth}"));
+    }
+    if !node.exclude_features.is_empty() {
+        parts.push(String::from("exclude"));
+        parts.extend(node.exclude_features.iter().map(|f| format!("\"{f}\"")));
+    }
+    parts.join(" ")
+}
+
 /// Builds the label string for a crate statement (raw AST, no interpolation).
 fn crate_stmt_label(stmt: &CrateStatement) -> String {
     match stmt {
-        CrateStatement::Run(step) => {
-            let mut parts = vec![format!("\"{}\"", step.command)];
-            parts.extend(step.args.iter().map(|a| format!("\"{a}\"")));
-            format!("run {}", parts.join(" "))
-        }
+        CrateStatement::Run(step) => run_label(step),
+        CrateStatement::Cargo(step) => cargo_label(step),
+        CrateStatement::FeaturePowerset(node) => feature_powerset_label(node),
         CrateStatement::ManualStep(node) => format!("manual_step \"{}\"", node.title),
         CrateStatement::SnapshotMetadata(node) => {
             format!("snapshot_metadata \"{}\"", node.name)
@@ -2594,17 +4799,16 @@ fn crate_stmt_label(stmt: &CrateStatement) -> String {
         CrateStatement::WaitForContinue(node) => {
             format!("wait_for_continue \"{}\"", node.description)
         }
+        CrateStatement::WaitUntil(node) => wait_until_label(node),
+        CrateStatement::Phase(node) => format!("phase \"{}\"", node.name),
     }
 }
 
 /// Builds the label string for a workspace statement (raw AST, no interpolation).
 fn workspace_stmt_label(stmt: &WorkspaceStatement) -> String {
     match stmt {
-        WorkspaceStatement::Run(step) => {
-            let mut parts = vec![format!("\"{}\"", step.command)];
-            parts.extend(step.args.iter().map(|a| format!("\"{a}\"")));
-            format!("run {}", parts.join(" "))
-        }
+        WorkspaceStatement::Run(step) => run_label(step),
+        WorkspaceStatement::Cargo(step) => cargo_label(step),
         WorkspaceStatement::ManualStep(node) => format!("manual_step \"{}\"", node.title),
         WorkspaceStatement::SnapshotMetadata(node) => {
             format!("snapshot_metadata \"{}\"", node.name)
@@ -2617,16 +4821,188 @@ fn workspace_stmt_label(stmt: &WorkspaceStatement) -> String {
         WorkspaceStatement::WaitForContinue(node) => {
             format!("wait_for_continue \"{}\"", node.description)
         }
+        WorkspaceStatement::WaitUntil(node) => wait_until_label(node),
+        WorkspaceStatement::Phase(node) => format!("phase \"{}\"", node.name),
+    }
+}
+
+/// The execution state of a statement or target as shown by `task describe`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StepState {
+    /// Not yet started.
+    Pending,
+    /// Completed successfully.
+    Done,
+    /// Failed with a non-zero exit code.
+    Failed,
+    /// Waiting at a `wait_for_continue` barrier.
+    Waiting,
+}
+
+impl StepState {
+    /// The icon used in the human-readable output.
+    const fn icon(self) -> &'static str {
+        match self {
+            Self::Pending => "\u{2B1C}",
+            Self::Done => "\u{2705}",
+            Self::Failed => "\u{274C}",
+            Self::Waiting => "\u{23F3}",
+        }
+    }
+
+    /// The name used in the porcelain output.
+    const fn name(self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Done => "done",
+            Self::Failed => "failed",
+            Self::Waiting => "waiting",
+        }
+    }
+}
+
+/// Prints one statement line of `task describe`.
+#[expect(clippy::print_stdout, reason = "part of the describe UI")]
+fn print_describe_step(
+    porcelain: Option<porcelain::Version>,
+    indent: &str,
+    cursor_str: &str,
+    state: StepState,
+    label: &str,
+) {
+    match porcelain {
+        Some(porcelain::Version::V1) => {
+            println!(
+                "{}",
+                porcelain::record(&["step", cursor_str, state.name(), label])
+            );
+        }
+        None => println!("{indent}{cursor_str:<20}  {}  {label}", state.icon()),
+    }
+}
+
+/// Prints a phase marker in `task describe` as a separator line, or as a
+/// `step` record that is always done in porcelain output.
+#[expect(clippy::print_stdout, reason = "part of the describe UI")]
+fn print_describe_phase(
+    porcelain: Option<porcelain::Version>,
+    indent: &str,
+    cursor_str: &str,
+    name: &str,
+) {
+    match porcelain {
+        Some(_) => print_describe_step(
+            porcelain,
+            indent,
+            cursor_str,
+            StepState::Done,
+            &format!("phase \"{name}\""),
+        ),
+        None => println!("{indent}── phase \"{name}\" ──"),
+    }
+}
+
+/// Prints the exit code and the last lines of output of a failed `run` step
+/// below its line in `task describe`.
+#[expect(clippy::print_stdout, reason = "part of the describe UI")]
+fn print_describe_failure(indent: &str, state_dir: &Path) {
+    let exit_code = fs_err::read_to_string(state_dir.join("exit_status")).unwrap_or_default();
+    let outcome = match step_timeout::read(state_dir) {
+        Some(seconds) => format!("killed after {seconds}s"),
+        None if cancellation::was_interrupted(state_dir) => "interrupted".to_owned(),
+        None => format!("exit code {}", exit_code.trim()),
+    };
+    let outcome = match fs_err::read_to_string(state_dir.join("attempts")) {
+        Ok(attempts) => format!("{outcome} in attempt {}", attempts.trim()),
+        Err(_) => outcome,
+    };
+    let pad = " ".repeat(24);
+    match failure_output::read(state_dir) {
+        Some(lines) if !lines.is_empty() => {
+            println!("{indent}{pad}{outcome}, last output:");
+            for line in lines {
+                println!("{indent}{pad}│ {line}");
+            }
+        }
+        _ => println!("{indent}{pad}{outcome}"),
+    }
+}
+
+/// Prints who answered a `manual_step` or released a barrier, and when,
+/// below its line in `task describe`.
+#[expect(clippy::print_stdout, reason = "part of the describe UI")]
+fn print_describe_approval(indent: &str, state_dir: &Path) {
+    if let Some(approval) = approval::read(state_dir) {
+        let pad = " ".repeat(24);
+        println!(
+            "{indent}{pad}{}",
+            approval.summary(crate::formatting::Style::default())
+        );
+    }
+}
+
+/// Prints how many feature combinations a `feature_powerset` step checked
+/// and which of them failed below its line in `task describe`.
+#[expect(clippy::print_stdout, reason = "part of the describe UI")]
+fn print_describe_feature_sets(indent: &str, state_dir: &Path) {
+    let Some(sets) = feature_powerset::read(state_dir) else {
+        return;
+    };
+    let pad = " ".repeat(24);
+    let passed = sets.iter().filter(|set| set.passed).count();
+    println!(
+        "{indent}{pad}{passed} of {} feature combinations passed",
+        sets.len()
+    );
+    for set in sets.iter().filter(|set| !set.passed) {
+        println!("{indent}{pad}failed: {}", set.features);
     }
 }
 
-/// Recursively prints crate statements with their cursor, completion icon, and label.
+/// Prints the line introducing a member crate of a `for crate in workspace`
+/// block in `task describe`.
 #[expect(clippy::print_stdout, reason = "part of the describe UI")]
+fn print_describe_member_crate(
+    porcelain: Option<porcelain::Version>,
+    indent: &str,
+    cursor_str: &str,
+    state: StepState,
+    crate_exec: &ResolvedCrateExecution,
+) {
+    match porcelain {
+        Some(porcelain::Version::V1) => {
+            println!(
+                "{}",
+                porcelain::record(&[
+                    "crate",
+                    cursor_str,
+                    state.name(),
+                    &crate_exec.manifest_dir.to_string_lossy(),
+                ])
+            );
+        }
+        None => println!(
+            "{indent}{cursor_str:<20}  {}  crate {}{}",
+            state.icon(),
+            crate_exec.manifest_dir.display(),
+            package_suffix(crate_exec.package.as_ref())
+        ),
+    }
+}
+
+/// Returns the name and version of a crate's package in parentheses, for
+/// the human-readable `task describe` output.
+fn package_suffix(package: Option<&ResolvedPackage>) -> String {
+    package.map_or_else(String::new, |package| format!(" ({package})"))
+}
+
+/// Recursively prints crate statements with their cursor, completion state, and label.
 fn print_crate_stmts_describe(
     stmts: &[CrateStatement],
     prefix: &ProgramCursor,
     state_base: &Path,
     indent: &str,
+    porcelain: Option<porcelain::Version>,
 ) {
     for (i, stmt) in stmts.iter().enumerate() {
         let cursor = prefix.clone().with(CursorSegment::Statement(i));
@@ -2639,16 +5015,16 @@ fn print_crate_stmts_describe(
                     .ok()
                     .unwrap_or_default();
                 let chosen = chosen.trim();
-                let (icon, label) = if chosen.is_empty() {
-                    ("\u{2B1C}", "if [not yet evaluated]")
+                let (state, label) = if chosen.is_empty() {
+                    (StepState::Pending, "if [not yet evaluated]")
                 } else if chosen == "none" {
-                    ("\u{2705}", "if [no branch matched]")
+                    (StepState::Done, "if [no branch matched]")
                 } else if chosen == "else" {
-                    ("\u{2705}", "if [else branch taken]")
+                    (StepState::Done, "if [else branch taken]")
                 } else {
-                    ("\u{2705}", "if [branch taken]")
+                    (StepState::Done, "if [branch taken]")
                 };
-                println!("{indent}{cursor_str:<20}  {icon}  {label}");
+                print_describe_step(porcelain, indent, &cursor_str, state, label);
                 if chosen == "else" {
                     let nested_indent = format!("{indent}  ");
                     print_crate_stmts_describe(
@@ -2656,6 +5032,7 @@ fn print_crate_stmts_describe(
                         &cursor.with(CursorSegment::ElseBranch),
                         state_base,
                         &nested_indent,
+                        porcelain,
                     );
                 } else if let Ok(n) = chosen.parse::<usize>()
                     && let Some(branch) = block.branches.get(n)
@@ -2666,70 +5043,120 @@ fn print_crate_stmts_describe(
                         &cursor.with(CursorSegment::IfBranch(n)),
                         state_base,
                         &nested_indent,
+                        porcelain,
                     );
                 }
             }
             CrateStatement::WithEnvFile(block) => {
                 let env_prefix = cursor.clone().with(CursorSegment::WithEnvFile);
-                let icon = if is_crate_stmts_completed(&block.statements, &env_prefix, state_base) {
-                    "\u{2705}"
+                let state = if is_crate_stmts_completed(&block.statements, &env_prefix, state_base)
+                {
+                    StepState::Done
                 } else {
-                    "\u{2B1C}"
+                    StepState::Pending
                 };
                 let label = format!("with_env_file \"{}\"", block.env_file);
-                println!("{indent}{cursor_str:<20}  {icon}  {label}");
+                print_describe_step(porcelain, indent, &cursor_str, state, &label);
                 let nested_indent = format!("{indent}  ");
                 print_crate_stmts_describe(
                     &block.statements,
                     &env_prefix,
                     state_base,
                     &nested_indent,
+                    porcelain,
                 );
             }
-            CrateStatement::Run(_) => {
+            CrateStatement::Run(_)
+            | CrateStatement::Cargo(_)
+            | CrateStatement::FeaturePowerset(_) => {
                 let state_dir = state_base.join(cursor.to_path());
-                let icon = if is_run_completed(&state_dir) {
-                    "\u{2705}"
+                let state = if is_run_completed(&state_dir) {
+                    StepState::Done
                 } else if is_run_failed(&state_dir) {
-                    "\u{274C}"
+                    StepState::Failed
                 } else {
-                    "\u{2B1C}"
+                    StepState::Pending
                 };
                 let label = crate_stmt_label(stmt);
-                println!("{indent}{cursor_str:<20}  {icon}  {label}");
+                print_describe_step(porcelain, indent, &cursor_str, state, &label);
+                if porcelain.is_none() {
+                    if let CrateStatement::FeaturePowerset(_) = stmt {
+                        print_describe_feature_sets(indent, &state_dir);
+                    }
+                    if state == StepState::Failed {
+                        print_describe_failure(indent, &state_dir);
+                    }
+                }
+            }
+            CrateStatement::Phase(node) => {
+                print_describe_phase(porcelain, indent, &cursor_str, &node.name);
             }
             CrateStatement::WaitForContinue(node) => {
-                let icon = if is_wait_barrier_released(&state_dir) {
-                    "\u{2705}"
+                let state = if is_wait_barrier_released(&state_dir) {
+                    StepState::Done
                 } else if is_wait_barrier_waiting(&state_dir) {
-                    "\u{23F3}"
+                    StepState::Waiting
                 } else {
-                    "\u{2B1C}"
+                    StepState::Pending
                 };
                 let label = format!("wait_for_continue \"{}\"", node.description);
-                println!("{indent}{cursor_str:<20}  {icon}  {label}");
+                print_describe_step(porcelain, indent, &cursor_str, state, &label);
+                if porcelain.is_none() {
+                    print_describe_approval(indent, &state_dir);
+                }
+            }
+            CrateStatement::ManualStep(step) => {
+                let state = if is_manual_completed(&state_dir) {
+                    StepState::Done
+                } else {
+                    StepState::Pending
+                };
+                let label = match manual_step_assignee(step, &state_dir) {
+                    Some(assignee) => {
+                        format!("{} (assigned to {assignee})", crate_stmt_label(stmt))
+                    }
+                    None => crate_stmt_label(stmt),
+                };
+                print_describe_step(porcelain, indent, &cursor_str, state, &label);
+                if porcelain.is_none() {
+                    print_describe_approval(indent, &state_dir);
+                }
+            }
+            CrateStatement::WaitUntil(_) => {
+                let state = if is_wait_until_completed(&state_dir) {
+                    StepState::Done
+                } else if is_wait_until_timed_out(&state_dir) {
+                    StepState::Failed
+                } else {
+                    StepState::Pending
+                };
+                let label = match fs_err::read_to_string(state_dir.join("wait_until_completed")) {
+                    Ok(waited) => format!("{} (waited {}s)", crate_stmt_label(stmt), waited.trim()),
+                    Err(_) => crate_stmt_label(stmt),
+                };
+                print_describe_step(porcelain, indent, &cursor_str, state, &label);
             }
-            CrateStatement::ManualStep(_) | CrateStatement::SnapshotMetadata(_) => {
-                let icon = if is_crate_stmt_completed(stmt, &cursor, state_base) {
-                    "\u{2705}"
+            CrateStatement::SnapshotMetadata(_) => {
+                let state = if is_crate_stmt_completed(stmt, &cursor, state_base) {
+                    StepState::Done
                 } else {
-                    "\u{2B1C}"
+                    StepState::Pending
                 };
                 let label = crate_stmt_label(stmt);
-                println!("{indent}{cursor_str:<20}  {icon}  {label}");
+                print_describe_step(porcelain, indent, &cursor_str, state, &label);
             }
         }
     }
 }
 
-/// Recursively prints workspace statements with their cursor, completion icon, and label.
-#[expect(clippy::print_stdout, reason = "part of the describe UI")]
+/// Recursively prints workspace statements with their cursor, completion state, and label.
 fn print_workspace_stmts_describe(
     stmts: &[WorkspaceStatement],
     prefix: &ProgramCursor,
     member_crates: &[ResolvedCrateExecution],
     state_base: &Path,
     indent: &str,
+    porcelain: Option<porcelain::Version>,
 ) {
     for (i, stmt) in stmts.iter().enumerate() {
         let cursor = prefix.clone().with(CursorSegment::Statement(i));
@@ -2742,16 +5169,16 @@ fn print_workspace_stmts_describe(
                     .ok()
                     .unwrap_or_default();
                 let chosen = chosen.trim();
-                let (icon, label) = if chosen.is_empty() {
-                    ("\u{2B1C}", "if [not yet evaluated]")
+                let (state, label) = if chosen.is_empty() {
+                    (StepState::Pending, "if [not yet evaluated]")
                 } else if chosen == "none" {
-                    ("\u{2705}", "if [no branch matched]")
+                    (StepState::Done, "if [no branch matched]")
                 } else if chosen == "else" {
-                    ("\u{2705}", "if [else branch taken]")
+                    (StepState::Done, "if [else branch taken]")
                 } else {
-                    ("\u{2705}", "if [branch taken]")
+                    (StepState::Done, "if [branch taken]")
                 };
-                println!("{indent}{cursor_str:<20}  {icon}  {label}");
+                print_describe_step(porcelain, indent, &cursor_str, state, label);
                 if chosen == "else" {
                     let nested_indent = format!("{indent}  ");
                     print_workspace_stmts_describe(
@@ -2760,6 +5187,7 @@ fn print_workspace_stmts_describe(
                         member_crates,
                         state_base,
                         &nested_indent,
+                        porcelain,
                     );
                 } else if let Ok(n) = chosen.parse::<usize>()
                     && let Some(branch) = block.branches.get(n)
@@ -2771,54 +5199,65 @@ fn print_workspace_stmts_describe(
                         member_crates,
                         state_base,
                         &nested_indent,
+                        porcelain,
                     );
                 }
             }
             WorkspaceStatement::ForCrateInWorkspace(block) => {
-                let icon = if is_workspace_stmt_completed(stmt, &cursor, member_crates, state_base)
+                let state = if is_workspace_stmt_completed(stmt, &cursor, member_crates, state_base)
                 {
-                    "\u{2705}"
+                    StepState::Done
                 } else {
-                    "\u{2B1C}"
+                    StepState::Pending
                 };
-                println!("{indent}{cursor_str:<20}  {icon}  for crate in workspace");
+                print_describe_step(
+                    porcelain,
+                    indent,
+                    &cursor_str,
+                    state,
+                    "for crate in workspace",
+                );
                 let crate_indent = format!("{indent}  ");
                 let nested_indent = format!("{indent}    ");
                 for (c_idx, crate_exec) in member_crates.iter().enumerate() {
                     let c_prefix = cursor.clone().with(CursorSegment::CrateIteration(c_idx));
                     let c_prefix_str = c_prefix.to_path_string();
-                    let crate_icon =
+                    let crate_state =
                         if is_crate_stmts_completed(&block.statements, &c_prefix, state_base) {
-                            "\u{2705}"
+                            StepState::Done
                         } else {
-                            "\u{2B1C}"
+                            StepState::Pending
                         };
-                    println!(
-                        "{crate_indent}{c_prefix_str:<20}  {crate_icon}  crate {}",
-                        crate_exec.manifest_dir.display()
+                    print_describe_member_crate(
+                        porcelain,
+                        &crate_indent,
+                        &c_prefix_str,
+                        crate_state,
+                        crate_exec,
                     );
                     print_crate_stmts_describe(
                         &block.statements,
                         &c_prefix,
                         state_base,
                         &nested_indent,
+                        porcelain,
                     );
                 }
             }
             WorkspaceStatement::WithEnvFile(block) => {
                 let env_prefix = cursor.clone().with(CursorSegment::WithEnvFile);
-                let icon = if is_workspace_stmts_completed(
+                let state = if is_workspace_stmts_completed(
                     &block.statements,
                     &env_prefix,
                     member_crates,
                     state_base,
                 ) {
-                    "\u{2705}"
+                    StepState::Done
                 } else {
-                    "\u{2B1C}"
+                    StepState::Pending
                 };
                 let label = format!("with_env_file \"{}\"", block.env_file);
-                println!("{indent}{cursor_str:<20}  {icon}  {label}");
+                print_describe_step(porcelain, indent, &cursor_str, state, &label);
                 let nested_indent = format!("{indent}  ");
                 print_workspace_stmts_describe(
                     &block.statements,
@@ -2826,40 +5265,83 @@ fn print_workspace_stmts_describe(
                     member_crates,
                     state_base,
                     &nested_indent,
+                    porcelain,
                 );
             }
-            WorkspaceStatement::Run(_) => {
+            WorkspaceStatement::Run(_) | WorkspaceStatement::Cargo(_) => {
                 let state_dir = state_base.join(cursor.to_path());
-                let icon = if is_run_completed(&state_dir) {
-                    "\u{2705}"
+                let state = if is_run_completed(&state_dir) {
+                    StepState::Done
                 } else if is_run_failed(&state_dir) {
-                    "\u{274C}"
+                    StepState::Failed
                 } else {
-                    "\u{2B1C}"
+                    StepState::Pending
                 };
                 let label = workspace_stmt_label(stmt);
-                println!("{indent}{cursor_str:<20}  {icon}  {label}");
+                print_describe_step(porcelain, indent, &cursor_str, state, &label);
+                if state == StepState::Failed && porcelain.is_none() {
+                    print_describe_failure(indent, &state_dir);
+                }
+            }
+            WorkspaceStatement::Phase(node) => {
+                print_describe_phase(porcelain, indent, &cursor_str, &node.name);
             }
             WorkspaceStatement::WaitForContinue(node) => {
-                let icon = if is_wait_barrier_released(&state_dir) {
-                    "\u{2705}"
+                let state = if is_wait_barrier_released(&state_dir) {
+                    StepState::Done
                 } else if is_wait_barrier_waiting(&state_dir) {
-                    "\u{23F3}"
+                    StepState::Waiting
                 } else {
-                    "\u{2B1C}"
+                    StepState::Pending
                 };
                 let label = format!("wait_for_continue \"{}\"", node.description);
-                println!("{indent}{cursor_str:<20}  {icon}  {label}");
+                print_describe_step(porcelain, indent, &cursor_str, state, &label);
+                if porcelain.is_none() {
+                    print_describe_approval(indent, &state_dir);
+                }
+            }
+            WorkspaceStatement::ManualStep(step) => {
+                let state = if is_manual_completed(&state_dir) {
+                    StepState::Done
+                } else {
+                    StepState::Pending
+                };
+                let label = match manual_step_assignee(step, &state_dir) {
+                    Some(assignee) => {
+                        format!("{} (assigned to {assignee})", workspace_stmt_label(stmt))
+                    }
+                    None => workspace_stmt_label(stmt),
+                };
+                print_describe_step(porcelain, indent, &cursor_str, state, &label);
+                if porcelain.is_none() {
+                    print_describe_approval(indent, &state_dir);
+                }
+            }
+            WorkspaceStatement::WaitUntil(_) => {
+                let state = if is_wait_until_completed(&state_dir) {
+                    StepState::Done
+                } else if is_wait_until_timed_out(&state_dir) {
+                    StepState::Failed
+                } else {
+                    StepState::Pending
+                };
+                let label = match fs_err::read_to_string(state_dir.join("wait_until_completed")) {
+                    Ok(waited) => {
+                        format!("{} (waited {}s)", workspace_stmt_label(stmt), waited.trim())
+                    }
+                    Err(_) => workspace_stmt_label(stmt),
+                };
+                print_describe_step(porcelain, indent, &cursor_str, state, &label);
             }
-            WorkspaceStatement::ManualStep(_) | WorkspaceStatement::SnapshotMetadata(_) => {
-                let icon = if is_workspace_stmt_completed(stmt, &cursor, member_crates, state_base)
+            WorkspaceStatement::SnapshotMetadata(_) => {
+                let state = if is_workspace_stmt_completed(stmt, &cursor, member_crates, state_base)
                 {
-                    "\u{2705}"
+                    StepState::Done
                 } else {
-                    "\u{2B1C}"
+                    StepState::Pending
                 };
                 let label = workspace_stmt_label(stmt);
-                println!("{indent}{cursor_str:<20}  {icon}  {label}");
+                print_describe_step(porcelain, indent, &cursor_str, state, &label);
             }
         }
     }
@@ -2875,157 +5357,1111 @@ fn print_workspace_stmts_describe(
 pub async fn task_describe_command(
     params: DescribeTaskParameters,
     environment: crate::Environment,
+    run_options: crate::RunOptions,
 ) -> Result<(), Error> {
     let (program, resolved) = load_task_data(&params.name, &environment)?;
     let state_base = state_dir_for_task(&params.name, &environment)?;
+    let porcelain = params.porcelain;
 
-    println!("Task: {}", params.name);
+    let task_dir = named_dir_path(&params.name, &environment)?;
+    let source = program_source::read(&task_dir)?.map(|record| {
+        let status = program_source::status(&record);
+        (record, status)
+    });
+    if porcelain::is_json(run_options.format, porcelain, "task describe")? {
+        let description = TaskDescription {
+            schema_version: DESCRIPTION_SCHEMA_VERSION,
+            name: &params.name,
+            source: source.as_ref().map(|(record, status)| ProgramSourceView {
+                path: &record.path,
+                status: *status,
+            }),
+            program: &program,
+            targets: &resolved,
+        };
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&description)
+                .map_err(Error::CouldNotSerializeTaskDescription)?
+        );
+        return Ok(());
+    }
+    if porcelain.is_some() {
+        println!("{}", porcelain::record(&["task", &params.name]));
+        if let Some((record, status)) = &source {
+            let status = match status {
+                program_source::Status::Unchanged => "unchanged",
+                program_source::Status::Changed => "changed",
+                program_source::Status::Missing => "missing",
+            };
+            println!(
+                "{}",
+                porcelain::record(&["program", &record.path.to_string_lossy(), status])
+            );
+        }
+    } else {
+        println!("Task: {}", params.name);
+        if let Some((record, status)) = &source {
+            let note = match status {
+                program_source::Status::Unchanged => "",
+                program_source::Status::Changed => {
+                    " (changed since the task was created; see `task sync-program`)"
+                }
+                program_source::Status::Missing => " (no longer exists)",
+            };
+            println!("Program: {}{}", record.path.display(), note);
+        }
+    }
 
     let ws_stmts = first_workspace_stmts(&program);
     if !resolved.workspace_executions.is_empty() {
-        println!("Workspaces:");
+        if porcelain.is_none() {
+            println!("Workspaces:");
+        }
         for (ws_idx, ws_exec) in resolved.workspace_executions.iter().enumerate() {
             let done = is_workspace_completed(ws_idx, ws_exec, ws_stmts, &state_base);
-            let icon = if done { "\u{2705}" } else { "\u{2B1C}" };
-            println!("  {} {}", icon, ws_exec.manifest_dir.display());
+            let prefix = ProgramCursor::new().with(CursorSegment::WorkspaceIteration(ws_idx));
+            print_describe_target(
+                porcelain,
+                "workspace",
+                &prefix,
+                done,
+                &ws_exec.manifest_dir,
+                None,
+                &state_base,
+            );
             print_workspace_stmts_describe(
                 ws_stmts,
-                &ProgramCursor::new().with(CursorSegment::WorkspaceIteration(ws_idx)),
+                &prefix,
                 &ws_exec.member_crates,
                 &state_base,
                 "    ",
+                porcelain,
             );
         }
     }
 
     let crate_stmts = first_crate_stmts(&program);
     if !resolved.crate_executions.is_empty() {
-        println!("Standalone crates:");
+        if porcelain.is_none() {
+            println!("Standalone crates:");
+        }
         for (c_idx, crate_exec) in resolved.crate_executions.iter().enumerate() {
             let done = is_standalone_crate_completed(c_idx, crate_stmts, &state_base);
-            let icon = if done { "\u{2705}" } else { "\u{2B1C}" };
-            println!("  {} {}", icon, crate_exec.manifest_dir.display());
-            print_crate_stmts_describe(
-                crate_stmts,
-                &ProgramCursor::new().with(CursorSegment::CrateIteration(c_idx)),
+            let prefix = ProgramCursor::new().with(CursorSegment::CrateIteration(c_idx));
+            print_describe_target(
+                porcelain,
+                "crate",
+                &prefix,
+                done,
+                &crate_exec.manifest_dir,
+                crate_exec.package.as_ref(),
                 &state_base,
-                "    ",
             );
+            print_crate_stmts_describe(crate_stmts, &prefix, &state_base, "    ", porcelain);
+        }
+    }
+
+    if porcelain.is_none() {
+        let work = pending::compute(&program, &resolved, &state_base);
+        if !work.is_empty() {
+            println!(
+                "Pending: {} steps, {} of them ready to run",
+                work.steps().count(),
+                work.ready().count()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Prints the line introducing a workspace or standalone crate in `task describe`.
+///
+/// The human-readable form includes the package of a standalone crate and
+/// the git commit the target's last step ran at, if one was recorded.
+#[expect(clippy::print_stdout, reason = "part of the describe UI")]
+fn print_describe_target(
+    porcelain: Option<porcelain::Version>,
+    kind: &str,
+    prefix: &ProgramCursor,
+    done: bool,
+    manifest_dir: &Path,
+    package: Option<&ResolvedPackage>,
+    state_base: &Path,
+) {
+    let state = if done {
+        StepState::Done
+    } else {
+        StepState::Pending
+    };
+    match porcelain {
+        Some(porcelain::Version::V1) => println!(
+            "{}",
+            porcelain::record(&[
+                kind,
+                &prefix.to_path_string(),
+                state.name(),
+                &manifest_dir.to_string_lossy(),
+            ])
+        ),
+        None => {
+            let target_dir = state_base.join(prefix.to_path());
+            let package = package_suffix(package);
+            match revision::read(&target_dir) {
+                Some(revision) => println!(
+                    "  {} {}{package}  at {revision}",
+                    state.icon(),
+                    manifest_dir.display()
+                ),
+                None => println!("  {} {}{package}", state.icon(), manifest_dir.display()),
+            }
+            if let Some(reason) = clean_git::blocked_reason(&target_dir) {
+                println!("      blocked: {reason}");
+            }
+        }
+    }
+}
+
+/// Returns the names of all tasks, sorted.
+///
+/// # Errors
+///
+/// Returns an error if the tasks directory cannot be read.
+pub fn task_names(environment: &crate::Environment) -> Result<Vec<String>, Error> {
+    let tasks_dir = dir_path(environment)?;
+    if !tasks_dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut names = Vec::new();
+    for entry in fs_err::read_dir(&tasks_dir)
+        .map_err(|e| Error::CouldNotReadTasksDir(tasks_dir.clone(), e))?
+    {
+        let entry = entry.map_err(|e| Error::CouldNotReadTasksDir(tasks_dir.clone(), e))?;
+        let path = entry.path();
+        if path.is_dir()
+            && let Some(task_name) = path.file_name().and_then(|s| s.to_str())
+        {
+            names.push(task_name.to_owned());
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+/// Lists all tasks found in the tasks configuration directory.
+///
+/// # Errors
+///
+/// Returns an error if the tasks directory cannot be read.
+#[instrument]
+#[expect(clippy::print_stdout, reason = "This is part of the UI, not logging")]
+pub async fn task_list_command(
+    params: ListTasksParameters,
+    environment: crate::Environment,
+    run_options: crate::RunOptions,
+) -> Result<(), Error> {
+    let tasks_dir = dir_path(&environment)?;
+    let json = porcelain::is_json(run_options.format, params.porcelain, "task list")?;
+
+    if !tasks_dir.exists() {
+        if json {
+            println!("{}", porcelain::json::<TaskView>(&[])?);
+        } else if params.porcelain.is_none() {
+            println!("No tasks found.");
+        }
+        return Ok(());
+    }
+
+    let names = task_names(&environment)?;
+
+    if params.porcelain.is_some() {
+        for task_name in &names {
+            println!("{}", porcelain::record(&["task", task_name]));
+        }
+        return Ok(());
+    }
+    let views: Vec<TaskView> = names
+        .into_iter()
+        .map(|name| TaskView::summarize(name, &environment))
+        .collect();
+    if json {
+        println!("{}", porcelain::json(&views)?);
+        return Ok(());
+    }
+    println!("Existing tasks:");
+    for view in &views {
+        let total: usize = view.targets.values().sum();
+        let progress = report::Status::ALL
+            .iter()
+            .filter_map(|status| {
+                view.targets
+                    .get(status.label())
+                    .map(|count| format!("{count} {}", status.label()))
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        let targets = if total == 0 {
+            "no targets".to_owned()
+        } else {
+            format!("{total} targets: {progress}")
+        };
+        match &view.program {
+            Some(program) => println!("- {} (program {}; {targets})", view.name, program.display()),
+            None => println!("- {} ({targets})", view.name),
+        }
+    }
+    Ok(())
+}
+
+/// Removes the directory of the task `name`; its state is kept.
+///
+/// # Errors
+///
+/// Returns an error if the task directory cannot be removed.
+pub fn remove_task(name: &str, environment: &crate::Environment) -> Result<(), Error> {
+    let task_dir = named_dir_path(name, environment)?;
+    fs_err::remove_dir_all(&task_dir).map_err(|e| Error::CouldNotRemoveTaskDir(task_dir, e))?;
+    completion_cache::invalidate(&state_dir_for_task(name, environment)?);
+    Ok(())
+}
+
+/// Shows what removing a task removes and removes it, unless it is being
+/// run and `--force` was not given.
+///
+/// # Errors
+///
+/// Returns [`Error::TaskNotFound`] if there is no such task,
+/// [`Error::TaskIsRunning`] if it is being run, or an error if it cannot be
+/// removed.
+#[instrument]
+#[expect(clippy::print_stdout, reason = "This is part of the UI, not logging")]
+pub async fn task_remove_command(
+    params: RemoveTaskParameters,
+    environment: crate::Environment,
+) -> Result<(), Error> {
+    let task_dir = named_dir_path(&params.name, &environment)?;
+    if !task_dir.exists() {
+        return Err(Error::TaskNotFound(params.name));
+    }
+    let state_base = state_dir_for_task(&params.name, &environment)?;
+    if let Some(holder) = run_lock::read(&state_base).filter(run_lock::Holder::may_be_alive) {
+        if !params.force {
+            return Err(Error::TaskIsRunning(
+                params.name,
+                holder.pid,
+                holder.host,
+                holder.started,
+            ));
+        }
+        println!(
+            "Warning: task {} is being run by process {} on {}",
+            params.name, holder.pid, holder.host
+        );
+    }
+    println!("Removing task {}:", params.name);
+    match load_task_data(&params.name, &environment) {
+        Ok((_program, resolved)) => println!(
+            "  its program and {} workspace(s) and {} crate(s) in {}",
+            resolved.workspace_executions.len(),
+            resolved.crate_executions.len(),
+            task_dir.display()
+        ),
+        Err(_) => println!("  {}", task_dir.display()),
+    }
+    if state_base.exists() {
+        println!("  its execution state in {} is kept", state_base.display());
+    }
+    remove_task(&params.name, &environment)
+}
+
+/// Dispatches the `task` subcommand.
+///
+/// # Errors
+///
+/// Propagates errors from the chosen subcommand.
+#[instrument]
+pub async fn task_command(
+    task_parameters: TaskParameters,
+    environment: crate::Environment,
+    run_options: crate::RunOptions,
+) -> Result<(), Error> {
+    match task_parameters.sub_command {
+        TaskSubCommand::Create(params) => {
+            task_create_command(params, environment).await?;
+        }
+        TaskSubCommand::CreatePerWorkspace(params) => {
+            task_create_per_workspace_command(params, environment).await?;
+        }
+        TaskSubCommand::Logs(params) => {
+            task_logs_command(params, environment, run_options).await?;
+        }
+        TaskSubCommand::Diff(params) => {
+            worktree_diff::task_diff_command(params, environment).await?;
+        }
+        TaskSubCommand::Refresh(params) => {
+            target_refresh::task_refresh_command(params, environment).await?;
+        }
+        TaskSubCommand::Remove(params) => {
+            task_remove_command(params, environment).await?;
+        }
+        TaskSubCommand::Run(params) => {
+            task_run_command(params, environment, run_options).await?;
+        }
+        TaskSubCommand::List(params) => {
+            task_list_command(params, environment, run_options).await?;
+        }
+        TaskSubCommand::Describe(params) => {
+            task_describe_command(params, environment, run_options).await?;
+        }
+        TaskSubCommand::Rewind(params) => {
+            task_rewind_command(params, environment).await?;
+        }
+        TaskSubCommand::Reset(params) => {
+            task_reset_command(params, environment).await?;
+        }
+        TaskSubCommand::Continue(params) => {
+            release_wait_barrier_command(params, environment).await?;
+        }
+        TaskSubCommand::ExportScript(params) => {
+            export::task_export_script_command(params, environment).await?;
+        }
+        TaskSubCommand::SyncProgram(params) => {
+            task_sync_program_command(params, environment).await?;
+        }
+        TaskSubCommand::Handoff(params) => {
+            task_handoff_command(params, environment).await?;
+        }
+        TaskSubCommand::Unlock(params) => {
+            task_unlock_command(params, environment).await?;
+        }
+        TaskSubCommand::DiffTargets(params) => {
+            task_diff_targets_command(params, environment, run_options).await?;
+        }
+        TaskSubCommand::Clone(params) => {
+            task_clone_command(params, environment).await?;
+        }
+        TaskSubCommand::Report(params) => {
+            task_report_command(params, environment, run_options).await?;
+        }
+        TaskSubCommand::Status(params) => {
+            task_status_command(params, environment, run_options).await?;
         }
     }
+    Ok(())
+}
 
+/// Prints a warning if the program file a task was created from has been
+/// edited since, so the task no longer runs what the file says.
+///
+/// # Errors
+///
+/// Returns an error if the task's program source record cannot be read.
+#[expect(clippy::print_stderr, reason = "This is part of the UI, not logging")]
+fn warn_if_program_source_changed(task_name: &str, environment: &Environment) -> Result<(), Error> {
+    let task_dir = named_dir_path(task_name, environment)?;
+    if let Some(record) = program_source::read(&task_dir)?
+        && program_source::status(&record) == program_source::Status::Changed
+    {
+        eprintln!(
+            "warning: {} changed since task {task_name} was created; \
+             run `cargo-for-each task sync-program --name {task_name}` to use the new version",
+            record.path.display()
+        );
+    }
+    Ok(())
+}
+
+/// Prints a warning, or fails if `strict` is set, if inputs of the task's
+/// target resolution changed since it was created, so the targets are likely
+/// outdated.
+///
+/// # Errors
+///
+/// Returns an error if the recorded inputs cannot be read, or if they changed
+/// and `strict` is set.
+#[expect(clippy::print_stderr, reason = "This is part of the UI, not logging")]
+fn check_resolution_inputs(
+    task_name: &str,
+    resolved: &ResolvedProgram,
+    environment: &Environment,
+    strict: bool,
+) -> Result<(), Error> {
+    let task_dir = named_dir_path(task_name, environment)?;
+    let Some(recorded) = resolution_inputs::read(&task_dir)? else {
+        return Ok(());
+    };
+    let changes = resolution_inputs::changes(
+        &recorded,
+        &resolution_inputs::collect(resolved, environment)?,
+    );
+    if changes.is_empty() {
+        return Ok(());
+    }
+    eprintln!("warning: the targets of task {task_name} may be outdated:");
+    for change in &changes {
+        eprintln!("  {change}");
+    }
+    if strict {
+        return Err(Error::StaleResolution(task_name.to_owned()));
+    }
+    eprintln!(
+        "run `cargo-for-each task diff-targets --name {task_name}` to review the differences \
+         and `cargo-for-each task refresh --name {task_name}` to select the targets again"
+    );
+    Ok(())
+}
+
+/// Replaces a task's copy of its program with the current source file.
+///
+/// If the task already has execution state, the new program must keep all
+/// existing statements in place (see [`program_source::is_state_compatible`])
+/// unless `--force` is given.  The resolved targets are not changed.
+///
+/// # Errors
+///
+/// Returns an error if the task does not exist, if no program source is known,
+/// if the new program cannot be read or parsed, if it is incompatible with the
+/// recorded state, or if the task files cannot be written.
+#[instrument]
+#[expect(clippy::print_stdout, reason = "This is part of the UI, not logging")]
+pub async fn task_sync_program_command(
+    params: SyncProgramParameters,
+    environment: crate::Environment,
+) -> Result<(), Error> {
+    let (old_program, _resolved) = load_task_data(&params.name, &environment)?;
+    let task_dir = named_dir_path(&params.name, &environment)?;
+    let source_path = match params.program {
+        Some(path) => path,
+        None => {
+            program_source::read(&task_dir)?
+                .ok_or_else(|| Error::UnknownProgramSource(params.name.clone()))?
+                .path
+        }
+    };
+    if !source_path.exists() {
+        return Err(Error::ProgramNotFound(source_path));
+    }
+    let source = fs_err::read_to_string(&source_path).map_err(Error::CouldNotReadProgramFile)?;
+    let new_program = parse_program(&source, &source_path.to_string_lossy())?;
+
+    let state_base = state_dir_for_task(&params.name, &environment)?;
+    let _lock = run_lock::acquire(&params.name, &state_base)?;
+    let has_state = fs_err::read_dir(&state_base).is_ok_and(|mut entries| entries.next().is_some());
+    if has_state
+        && !params.force
+        && !program_source::is_state_compatible(&old_program, &new_program)
+    {
+        return Err(Error::IncompatibleProgramChange(params.name));
+    }
+
+    let program_path = task_dir.join("program.cfe");
+    crate::utils::write_atomically(&program_path, &source)
+        .map_err(|e| Error::CouldNotCopyFile(source_path.clone(), program_path, e))?;
+    program_source::write(
+        &task_dir,
+        &program_source::Record {
+            path: fs_err::canonicalize(&source_path).map_err(Error::IoError)?,
+            checksum: program_source::checksum(&source),
+        },
+    )?;
+    completion_cache::invalidate(&state_base);
+    println!(
+        "Updated the program of task {} from {}",
+        params.name,
+        source_path.display()
+    );
+    let selection = |program: &Program| {
+        program
+            .statements
+            .iter()
+            .filter(|s| {
+                !matches!(
+                    s,
+                    GlobalStatement::ForWorkspace(_) | GlobalStatement::ForCrate(_)
+                )
+            })
+            .cloned()
+            .collect::<Vec<_>>()
+    };
+    if selection(&old_program) != selection(&new_program) {
+        println!(
+            "Note: the select statements changed, but the task keeps its original targets; \
+             run `cargo-for-each task refresh --name {}` to select targets again",
+            params.name
+        );
+    }
+    Ok(())
+}
+
+/// Prints a table with the status of each target in each of several tasks,
+/// or with every executed step of one task.
+///
+/// # Errors
+///
+/// Returns an error if a task cannot be loaded or the report cannot be
+/// written.
+#[instrument]
+#[expect(clippy::print_stdout, reason = "This is part of the UI, not logging")]
+pub async fn task_report_command(
+    params: ReportParameters,
+    environment: crate::Environment,
+    run_options: crate::RunOptions,
+) -> Result<(), Error> {
+    let format = report::Format::for_command(run_options.format, "task report")?;
+    let content = if let Some(name) = &params.name {
+        let steps = report::step_rows(name, &environment)?;
+        report::render_steps(name, &steps, format, params.style)
+    } else {
+        let statuses = params
+            .tasks
+            .iter()
+            .map(|task| report::target_statuses(task, &environment))
+            .collect::<Result<Vec<_>, _>>()?;
+        let approvals = params
+            .tasks
+            .iter()
+            .map(|task| report::target_approvals(task, &environment))
+            .collect::<Result<Vec<_>, _>>()?
+            .concat();
+        report::render(
+            &params.tasks,
+            &report::rows(&statuses),
+            &approvals,
+            format,
+            params.style,
+        )
+    };
+    match params.output {
+        Some(path) => {
+            fs_err::write(&path, content).map_err(|e| Error::CouldNotWriteReport(path, e))?;
+        }
+        None => print!("{content}"),
+    }
+    Ok(())
+}
+
+/// Prints the progress of each target of a task in each of its top-level
+/// statements.
+///
+/// # Errors
+///
+/// Returns an error if the task cannot be loaded or its status cannot be
+/// printed in the chosen format.
+#[instrument]
+#[expect(clippy::print_stdout, reason = "This is part of the UI, not logging")]
+pub async fn task_status_command(
+    params: StatusTaskParameters,
+    environment: crate::Environment,
+    run_options: crate::RunOptions,
+) -> Result<(), Error> {
+    let (program, resolved) = load_task_data(&params.name, &environment)?;
+    let state_base = state_dir_for_task(&params.name, &environment)?;
+    if porcelain::is_json(run_options.format, None, "task status")? {
+        let view = status::ProgressView {
+            name: params.name,
+            running: run_lock::read(&state_base).filter(run_lock::Holder::may_be_alive),
+            workspaces: status::views(&status::workspace_matrix(
+                first_workspace_stmts(&program),
+                &resolved,
+                &state_base,
+            )),
+            crates: status::views(&status::crate_matrix(
+                first_crate_stmts(&program),
+                &resolved,
+                &state_base,
+            )),
+        };
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&view).map_err(Error::CouldNotSerializeListOutput)?
+        );
+        return Ok(());
+    }
+    println!("Task: {}", params.name);
+    if let Some(holder) = run_lock::read(&state_base).filter(run_lock::Holder::may_be_alive) {
+        println!(
+            "Running since {} (process {} on {})",
+            params.style.timestamp(&holder.started),
+            holder.pid,
+            holder.host
+        );
+    }
+    if !resolved.workspace_executions.is_empty() {
+        let matrix =
+            status::workspace_matrix(first_workspace_stmts(&program), &resolved, &state_base);
+        print!("\nWorkspaces:\n{}", status::render(&matrix));
+    }
+    if !resolved.crate_executions.is_empty() {
+        let matrix = status::crate_matrix(first_crate_stmts(&program), &resolved, &state_base);
+        print!("\nStandalone crates:\n{}", status::render(&matrix));
+    }
+    println!("\n✓ done  ✗ failed  w waiting  ~ started  · pending");
+    let steps = report::step_rows(&params.name, &environment)?;
+    let times = report::target_times(&steps);
+    if let Some(wall_clock) = report::wall_clock(&steps) {
+        print!(
+            "\nTime by target ({} wall-clock in total):\n{}",
+            params.style.duration(wall_clock),
+            report::render_times(&times, params.style)
+        );
+    }
+    if params.verbose {
+        let workspaces = resolved
+            .workspace_executions
+            .iter()
+            .enumerate()
+            .map(|(i, w)| (CursorSegment::WorkspaceIteration(i), &w.manifest_dir));
+        let crates = resolved
+            .crate_executions
+            .iter()
+            .enumerate()
+            .map(|(i, c)| (CursorSegment::CrateIteration(i), &c.manifest_dir));
+        for (segment, manifest_dir) in workspaces.chain(crates) {
+            let prefix = ProgramCursor::new().with(segment);
+            let invocations = invocation::all_below(&state_base.join(prefix.to_path()));
+            if invocations.is_empty() {
+                continue;
+            }
+            println!("\n{}:", manifest_dir.display());
+            for (path, invocation) in invocations {
+                let Some((command, args)) = invocation.argv.split_first() else {
+                    continue;
+                };
+                let state_dir = state_base.join(prefix.to_path()).join(&path);
+                println!(
+                    "  {}{}/  {}",
+                    prefix.to_path_string(),
+                    path.display(),
+                    shell_command_line(command, args)
+                );
+                let sandboxed = if invocation.sandboxed {
+                    " (sandboxed)"
+                } else {
+                    ""
+                };
+                println!("      in {}{sandboxed}", invocation.working_dir.display());
+                for (name, value) in &invocation.env {
+                    println!("      {name}={value}");
+                }
+                if let Some(duration) = step_duration::read(&state_dir) {
+                    println!("      ran for {}", params.style.duration(duration));
+                }
+                let sizes: Vec<String> = [step_logs::STDOUT_FILE, step_logs::STDERR_FILE]
+                    .iter()
+                    .filter_map(|file| {
+                        let size = fs_err::metadata(state_dir.join(file)).ok()?.len();
+                        Some(format!("{file} {}", crate::formatting::bytes(size)))
+                    })
+                    .collect();
+                if !sizes.is_empty() {
+                    println!("      output: {}", sizes.join(", "));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Resolves the task's program against the current configuration and prints
+/// how the result differs from the targets the task was created with.
+///
+/// # Errors
+///
+/// Returns an error if the task cannot be loaded, if the configuration cannot
+/// be loaded, if the program cannot be resolved or if the changes cannot be
+/// printed in the chosen format.
+#[instrument]
+#[expect(clippy::print_stdout, reason = "This is part of the UI, not logging")]
+pub async fn task_diff_targets_command(
+    params: DiffTargetsParameters,
+    environment: crate::Environment,
+    run_options: crate::RunOptions,
+) -> Result<(), Error> {
+    let (program, stored) = load_task_data(&params.name, &environment)?;
+    let config = Config::load(&environment)?;
+    let current =
+        crate::program::resolve::resolve_program(&program, &config, &Cached::new(Subprocess))?;
+    let changes = target_changes::compare(&stored, &current);
+    if porcelain::is_json(run_options.format, params.porcelain, "task diff-targets")? {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&target_changes::ChangesView::from(&changes))
+                .map_err(Error::CouldNotSerializeListOutput)?
+        );
+        return Ok(());
+    }
+
+    let targets = [
+        ("workspace", "added", &changes.added_workspaces),
+        ("workspace", "removed", &changes.removed_workspaces),
+        ("crate", "added", &changes.added_crates),
+        ("crate", "removed", &changes.removed_crates),
+    ];
+    let dependencies = [
+        ("added", &changes.added_dependencies),
+        ("removed", &changes.removed_dependencies),
+    ];
+    if params.porcelain.is_some() {
+        for (kind, change, dirs) in targets {
+            for dir in dirs {
+                println!(
+                    "{}",
+                    porcelain::record(&[kind, change, &dir.display().to_string()])
+                );
+            }
+        }
+        for (change, edges) in dependencies {
+            for (dependent, dependency) in edges {
+                println!(
+                    "{}",
+                    porcelain::record(&[
+                        "dependency",
+                        change,
+                        &dependent.display().to_string(),
+                        &dependency.display().to_string(),
+                    ])
+                );
+            }
+        }
+        return Ok(());
+    }
+
+    if changes.is_empty() {
+        println!(
+            "Resolving the program of task {} again selects the same targets",
+            params.name
+        );
+        return Ok(());
+    }
+    for line in target_changes::describe(&changes) {
+        println!("{line}");
+    }
+    println!("Run task refresh or task run --refresh-targets to pick up these changes");
+    Ok(())
+}
+
+/// Copies the directory `from` with all its contents to `to`.
+///
+/// # Errors
+///
+/// Returns an error if a directory cannot be read or created or if a file
+/// cannot be copied.
+fn copy_dir(from: &Path, to: &Path) -> Result<(), Error> {
+    fs_err::create_dir_all(to).map_err(|e| Error::CouldNotCreateTaskDir(to.to_path_buf(), e))?;
+    for entry in fs_err::read_dir(from).map_err(Error::IoError)? {
+        let entry = entry.map_err(Error::IoError)?;
+        let source = entry.path();
+        let target = to.join(entry.file_name());
+        if source.is_dir() {
+            copy_dir(&source, &target)?;
+        } else {
+            fs_err::copy(&source, &target)
+                .map_err(|e| Error::CouldNotCopyFile(source.clone(), target.clone(), e))?;
+        }
+    }
+    Ok(())
+}
+
+/// Copies a task under a new name, with its program, its targets (or the
+/// targets the program resolves to now) and, unless reset, its state.
+///
+/// # Errors
+///
+/// Returns an error if the source task does not exist, if the new task already
+/// exists, if resolving the program again fails or if files cannot be copied.
+#[instrument]
+pub async fn task_clone_command(
+    params: CloneTaskParameters,
+    environment: crate::Environment,
+) -> Result<(), Error> {
+    let (program, _resolved) = load_task_data(&params.from, &environment)?;
+    let from_dir = named_dir_path(&params.from, &environment)?;
+    let to_dir = named_dir_path(&params.to, &environment)?;
+    if to_dir.exists() {
+        return Err(Error::AlreadyExists(format!("task {}", params.to)));
+    }
+    copy_dir(&from_dir, &to_dir)?;
+    if params.resolve_again {
+        let resolved = resolve_from_config(&program, &environment, false)?;
+        write_resolved_program(&to_dir, &resolved)?;
+        resolution_inputs::write(
+            &to_dir,
+            &resolution_inputs::collect(&resolved, &environment)?,
+        )?;
+    }
+
+    let to_state = state_dir_for_task(&params.to, &environment)?;
+    if to_state.exists() {
+        fs_err::remove_dir_all(&to_state)
+            .map_err(|e| Error::CouldNotRemoveTaskDir(to_state.clone(), e))?;
+    }
+    completion_cache::invalidate(&to_state);
+    let from_state = state_dir_for_task(&params.from, &environment)?;
+    if !params.reset_state && from_state.exists() {
+        copy_dir(&from_state, &to_state)?;
+    }
+    Ok(())
+}
+
+/// Releases a wait barrier so execution can continue past it.
+///
+/// # Errors
+///
+/// Returns an error if the cursor string cannot be parsed or if the state files
+/// cannot be written.
+#[instrument]
+pub async fn release_wait_barrier_command(
+    params: ContinueBarrierParameters,
+    environment: crate::Environment,
+) -> Result<(), Error> {
+    use crate::program::cursor::ProgramCursor;
+
+    let cursor = ProgramCursor::from_path_string(&params.cursor)
+        .map_err(|e| Error::InvalidCursorString(params.cursor.clone(), e.to_string()))?;
+    let state_base = state_dir_for_task(&params.name, &environment)?;
+    let state_dir = state_base.join(cursor.to_path());
+    if !state_dir.exists() {
+        fs_err::create_dir_all(&state_dir)
+            .map_err(|e| Error::CouldNotCreateStateDir(state_dir.clone(), e))?;
+    }
+    let release_file = state_dir.join("barrier_released");
+    fs_err::write(&release_file, "")
+        .map_err(|e| Error::CouldNotWriteStateFile(release_file.clone(), e))?;
+    approval::write(&state_dir, &approval::Approval::now(true))?;
+    println!(
+        "Barrier at {} released. Execution can continue.",
+        cursor.to_path_string()
+    );
     Ok(())
 }
 
-/// Lists all tasks found in the tasks configuration directory.
+/// Removes the lock a `task run` of the task holds, e.g. after it was killed
+/// on another host.
 ///
 /// # Errors
 ///
-/// Returns an error if the tasks directory cannot be read.
+/// Returns an error if the state directory cannot be determined or the lock
+/// cannot be removed.
 #[instrument]
 #[expect(clippy::print_stdout, reason = "This is part of the UI, not logging")]
-pub async fn task_list_command(environment: crate::Environment) -> Result<(), Error> {
-    let tasks_dir = dir_path(&environment)?;
-
-    if !tasks_dir.exists() {
-        println!("No tasks found.");
+pub async fn task_unlock_command(
+    params: UnlockParameters,
+    environment: crate::Environment,
+) -> Result<(), Error> {
+    let state_base = state_dir_for_task(&params.name, &environment)?;
+    let holder = run_lock::read(&state_base);
+    if !run_lock::remove(&state_base)? {
+        println!("Task {} is not locked", params.name);
         return Ok(());
     }
-
-    println!("Existing tasks:");
-    for entry in fs_err::read_dir(&tasks_dir)
-        .map_err(|e| Error::CouldNotReadTasksDir(tasks_dir.clone(), e))?
-    {
-        let entry = entry.map_err(|e| Error::CouldNotReadTasksDir(tasks_dir.clone(), e))?;
-        let path = entry.path();
-        if path.is_dir()
-            && let Some(task_name) = path.file_name().and_then(|s| s.to_str())
-        {
-            println!("- {task_name}");
+    match holder {
+        Some(holder) => {
+            println!(
+                "Removed the lock of process {} on {} (started {})",
+                holder.pid, holder.host, holder.started
+            );
+            if holder.may_be_alive() {
+                println!("Warning: that process may still be running the task");
+            }
         }
+        None => println!("Removed the unreadable lock of task {}", params.name),
     }
     Ok(())
 }
 
-/// Dispatches the `task` subcommand.
+/// Prints the standard output and error of the `run` steps of one target,
+/// with a header per log if there is more than one.
 ///
 /// # Errors
 ///
-/// Propagates errors from the chosen subcommand.
+/// Returns an error if the task cannot be loaded, if the target is not part
+/// of the task, if no output was recorded for it or if the logs cannot be
+/// printed in the chosen format.
 #[instrument]
-pub async fn task_command(
-    task_parameters: TaskParameters,
+#[expect(clippy::print_stdout, reason = "This is part of the UI, not logging")]
+pub async fn task_logs_command(
+    params: LogsParameters,
     environment: crate::Environment,
+    run_options: crate::RunOptions,
 ) -> Result<(), Error> {
-    match task_parameters.sub_command {
-        TaskSubCommand::Create(params) => {
-            task_create_command(params, environment).await?;
-        }
-        TaskSubCommand::Remove(params) => {
-            let task_dir = named_dir_path(&params.name, &environment)?;
-            fs_err::remove_dir_all(&task_dir)
-                .map_err(|e| Error::CouldNotRemoveTaskDir(task_dir.clone(), e))?;
-        }
-        TaskSubCommand::Run(params) => {
-            task_run_command(params, environment).await?;
-        }
-        TaskSubCommand::List => {
-            task_list_command(environment).await?;
+    let (_program, resolved) = load_task_data(&params.name, &environment)?;
+    let state_base = state_dir_for_task(&params.name, &environment)?;
+    let manifest_dir = crate::targets::canonical_manifest_dir(params.target)?;
+    let mut prefix = reset::targets(&resolved, Some(&manifest_dir))?
+        .into_iter()
+        .next()
+        .unwrap_or_default();
+    if let Some(step) = params.step {
+        prefix = prefix.with(CursorSegment::Statement(step));
+    }
+    let base = state_base.join(prefix.to_path());
+    let mut logs = Vec::new();
+    for relative in step_logs::all_below(&base) {
+        let mut cursor = prefix.to_path_string();
+        for component in relative.components() {
+            cursor.push_str(&component.as_os_str().to_string_lossy());
+            cursor.push('/');
         }
-        TaskSubCommand::Describe(params) => {
-            task_describe_command(params, environment).await?;
+        for file in params.stream.files() {
+            let path = base.join(&relative).join(file);
+            if path.exists() {
+                logs.push((cursor.clone(), *file, path));
+            }
         }
-        TaskSubCommand::Rewind(params) => {
-            task_rewind_command(params, environment).await?;
+    }
+    if logs.is_empty() {
+        return Err(Error::NoStepLogs(manifest_dir));
+    }
+    let json = porcelain::is_json(run_options.format, None, "task logs")?;
+    let headers = logs.len() > 1;
+    let mut views = Vec::new();
+    for (cursor, file, path) in &logs {
+        let content = fs_err::read_to_string(path).map_err(Error::IoError)?;
+        let content = match params.tail {
+            Some(count) => step_logs::tail(&content, count),
+            None => &content,
+        };
+        if json {
+            views.push(step_logs::LogView {
+                cursor: cursor.clone(),
+                file: (*file).to_owned(),
+                content: content.to_owned(),
+            });
+            continue;
         }
-        TaskSubCommand::Continue(params) => {
-            release_wait_barrier_command(params, environment).await?;
+        if headers {
+            println!("==> {cursor} {file} <==");
         }
+        print!("{content}");
+    }
+    if json {
+        println!("{}", porcelain::json(&views)?);
     }
     Ok(())
 }
 
-/// Releases a wait barrier so execution can continue past it.
+/// Records who is responsible for the manual step at a cursor.
+///
+/// The assignment is stored in the step's state directory and takes precedence
+/// over the `assignee` given in the program.
 ///
 /// # Errors
 ///
-/// Returns an error if the cursor string cannot be parsed or if the state files
-/// cannot be written.
+/// Returns an error if the task cannot be loaded, if the cursor string cannot
+/// be parsed or does not point at a manual step, or if the state file cannot
+/// be written.
 #[instrument]
-pub async fn release_wait_barrier_command(
-    params: ContinueBarrierParameters,
+pub async fn task_handoff_command(
+    params: HandoffParameters,
     environment: crate::Environment,
 ) -> Result<(), Error> {
-    use crate::program::cursor::ProgramCursor;
-
+    let (program, _resolved) = load_task_data(&params.name, &environment)?;
     let cursor = ProgramCursor::from_path_string(&params.cursor)
         .map_err(|e| Error::InvalidCursorString(params.cursor.clone(), e.to_string()))?;
+    if !is_manual_step_cursor(&program, &cursor) {
+        return Err(Error::NotAManualStep(params.cursor));
+    }
     let state_base = state_dir_for_task(&params.name, &environment)?;
     let state_dir = state_base.join(cursor.to_path());
-    if !state_dir.exists() {
-        fs_err::create_dir_all(&state_dir)
-            .map_err(|e| Error::CouldNotCreateStateDir(state_dir.clone(), e))?;
-    }
-    let release_file = state_dir.join("barrier_released");
-    fs_err::write(&release_file, "")
-        .map_err(|e| Error::CouldNotWriteStateFile(release_file.clone(), e))?;
+    fs_err::create_dir_all(&state_dir)
+        .map_err(|e| Error::CouldNotCreateStateDir(state_dir.clone(), e))?;
+    let assignee_file = state_dir.join("assignee");
+    fs_err::write(&assignee_file, &params.to)
+        .map_err(|e| Error::CouldNotWriteStateFile(assignee_file.clone(), e))?;
     println!(
-        "Barrier at {} released. Execution can continue.",
-        cursor.to_path_string()
+        "Manual step at {} handed off to {}.",
+        cursor.to_path_string(),
+        params.to
     );
     Ok(())
 }
 
+/// Returns `true` if `cursor` points at a `manual_step` statement of `program`.
+///
+/// Iteration segments (`w{N}`, `c{N}`) select a target rather than a statement
+/// and are skipped; the remaining segments are followed through `if` branches,
+/// `with_env_file` bodies and `for crate in workspace` blocks.
+fn is_manual_step_cursor(program: &Program, cursor: &ProgramCursor) -> bool {
+    let mut segments = cursor.segments().iter().copied();
+    match segments.next() {
+        Some(CursorSegment::WorkspaceIteration(_)) => {
+            is_manual_step_in_workspace_stmts(first_workspace_stmts(program), &mut segments)
+        }
+        Some(CursorSegment::CrateIteration(_)) => {
+            is_manual_step_in_crate_stmts(first_crate_stmts(program), &mut segments)
+        }
+        _ => false,
+    }
+}
+
+/// Follows `segments` through workspace statements, see [`is_manual_step_cursor`].
+fn is_manual_step_in_workspace_stmts(
+    stmts: &[WorkspaceStatement],
+    segments: &mut impl Iterator<Item = CursorSegment>,
+) -> bool {
+    let Some(CursorSegment::Statement(i)) = segments.next() else {
+        return false;
+    };
+    match (stmts.get(i), segments.next()) {
+        (Some(WorkspaceStatement::ManualStep(_)), None) => true,
+        (Some(WorkspaceStatement::If(block)), Some(CursorSegment::IfBranch(b))) => block
+            .branches
+            .get(b)
+            .is_some_and(|branch| is_manual_step_in_workspace_stmts(&branch.statements, segments)),
+        (Some(WorkspaceStatement::If(block)), Some(CursorSegment::ElseBranch)) => {
+            is_manual_step_in_workspace_stmts(&block.else_statements, segments)
+        }
+        (Some(WorkspaceStatement::WithEnvFile(block)), Some(CursorSegment::WithEnvFile)) => {
+            is_manual_step_in_workspace_stmts(&block.statements, segments)
+        }
+        (
+            Some(WorkspaceStatement::ForCrateInWorkspace(block)),
+            Some(CursorSegment::CrateIteration(_)),
+        ) => is_manual_step_in_crate_stmts(&block.statements, segments),
+        _ => false,
+    }
+}
+
+/// Follows `segments` through crate statements, see [`is_manual_step_cursor`].
+fn is_manual_step_in_crate_stmts(
+    stmts: &[CrateStatement],
+    segments: &mut impl Iterator<Item = CursorSegment>,
+) -> bool {
+    let Some(CursorSegment::Statement(i)) = segments.next() else {
+        return false;
+    };
+    match (stmts.get(i), segments.next()) {
+        (Some(CrateStatement::ManualStep(_)), None) => true,
+        (Some(CrateStatement::If(block)), Some(CursorSegment::IfBranch(b))) => block
+            .branches
+            .get(b)
+            .is_some_and(|branch| is_manual_step_in_crate_stmts(&branch.statements, segments)),
+        (Some(CrateStatement::If(block)), Some(CursorSegment::ElseBranch)) => {
+            is_manual_step_in_crate_stmts(&block.else_statements, segments)
+        }
+        (Some(CrateStatement::WithEnvFile(block)), Some(CursorSegment::WithEnvFile)) => {
+            is_manual_step_in_crate_stmts(&block.statements, segments)
+        }
+        _ => false,
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use std::collections::BTreeMap;
     use std::path::{Path, PathBuf};
 
     use pretty_assertions::assert_eq;
     use tempfile::tempdir;
 
-    use super::{find_next_statement, is_crate_stmt_completed, is_run_completed};
+    use super::{
+        execute_run_step, execute_wait_until_step, expand_interpolations, find_next_statement,
+        is_crate_stmt_completed, is_manual_step_cursor, is_run_completed, is_run_failed,
+        is_wait_until_completed, is_wait_until_timed_out, step_duration, step_logs, step_timeout,
+    };
     use crate::Environment;
-    use crate::program::ast::common::RunStep;
+    use crate::program::ast::common::{Retry, RunStep, WaitUntilNode};
     use crate::program::ast::crate_ctx::CrateStatement;
     use crate::program::ast::crate_ctx::ForCrateBlock;
     use crate::program::ast::workspace_ctx::ForWorkspaceBlock;
@@ -3045,6 +6481,7 @@ mod tests {
             state_dir: temp_dir.path().join("state"),
             paths: vec![],
             suppress_subprocess_output: true,
+            local_root: None,
         }
     }
 
@@ -3083,6 +6520,7 @@ mod tests {
             crate_executions: vec![ResolvedCrateExecution {
                 manifest_dir,
                 dependencies: vec![],
+                package: None,
             }],
         }
     }
@@ -3163,6 +6601,14 @@ mod tests {
         let stmt = CrateStatement::Run(RunStep {
             command: "echo".to_owned(),
             args: vec![],
+            stdin: None,
+            rate_limit: None,
+            exclusive: vec![],
+            elevation: None,
+            env: BTreeMap::new(),
+            cwd_relative: None,
+            timeout_seconds: None,
+            retry: None,
         });
         assert!(is_crate_stmt_completed(&stmt, &cursor, temp.path()));
         Ok(())
@@ -3178,6 +6624,14 @@ mod tests {
         let stmt = CrateStatement::Run(RunStep {
             command: "echo".to_owned(),
             args: vec![],
+            stdin: None,
+            rate_limit: None,
+            exclusive: vec![],
+            elevation: None,
+            env: BTreeMap::new(),
+            cwd_relative: None,
+            timeout_seconds: None,
+            retry: None,
         });
         assert!(!is_crate_stmt_completed(&stmt, &cursor, temp.path()));
         Ok(())
@@ -3200,6 +6654,14 @@ mod tests {
         let program = crate_program(vec![CrateStatement::Run(RunStep {
             command: "echo".to_owned(),
             args: vec![],
+            stdin: None,
+            rate_limit: None,
+            exclusive: vec![],
+            elevation: None,
+            env: BTreeMap::new(),
+            cwd_relative: None,
+            timeout_seconds: None,
+            retry: None,
         })]);
         let resolved = resolved_with_one_crate(dir);
         assert!(find_next_statement(&program, &resolved, &state_base).is_none());
@@ -3216,6 +6678,14 @@ mod tests {
         let program = crate_program(vec![CrateStatement::Run(RunStep {
             command: "echo".to_owned(),
             args: vec![],
+            stdin: None,
+            rate_limit: None,
+            exclusive: vec![],
+            elevation: None,
+            env: BTreeMap::new(),
+            cwd_relative: None,
+            timeout_seconds: None,
+            retry: None,
         })]);
         let resolved = resolved_with_one_crate(dir);
         let next = find_next_statement(&program, &resolved, &state_base);
@@ -3248,10 +6718,26 @@ mod tests {
             CrateStatement::Run(RunStep {
                 command: "echo".to_owned(),
                 args: vec!["a".to_owned()],
+                stdin: None,
+                rate_limit: None,
+                exclusive: vec![],
+                elevation: None,
+                env: BTreeMap::new(),
+                cwd_relative: None,
+                timeout_seconds: None,
+                retry: None,
             }),
             CrateStatement::Run(RunStep {
                 command: "echo".to_owned(),
                 args: vec!["b".to_owned()],
+                stdin: None,
+                rate_limit: None,
+                exclusive: vec![],
+                elevation: None,
+                env: BTreeMap::new(),
+                cwd_relative: None,
+                timeout_seconds: None,
+                retry: None,
             }),
         ]);
         let resolved = resolved_with_one_crate(dir);
@@ -3283,6 +6769,14 @@ mod tests {
         let program = crate_program(vec![CrateStatement::Run(RunStep {
             command: "echo".to_owned(),
             args: vec![],
+            stdin: None,
+            rate_limit: None,
+            exclusive: vec![],
+            elevation: None,
+            env: BTreeMap::new(),
+            cwd_relative: None,
+            timeout_seconds: None,
+            retry: None,
         })]);
         let resolved = resolved_with_one_crate(dir);
         let next = find_next_statement(&program, &resolved, &state_base);
@@ -3303,6 +6797,14 @@ mod tests {
         let program = workspace_program(vec![WorkspaceStatement::Run(RunStep {
             command: "cargo".to_owned(),
             args: vec!["build".to_owned()],
+            stdin: None,
+            rate_limit: None,
+            exclusive: vec![],
+            elevation: None,
+            env: BTreeMap::new(),
+            cwd_relative: None,
+            timeout_seconds: None,
+            retry: None,
         })]);
         let resolved = resolved_with_one_workspace(dir);
         let next = find_next_statement(&program, &resolved, &state_base);
@@ -3316,4 +6818,483 @@ mod tests {
         );
         Ok(())
     }
+
+    // ── expand_interpolations ─────────────────────────────────────────────────
+
+    #[test]
+    fn expand_interpolations_with_filters() -> TestResult {
+        let temp = tempdir()?;
+        let state_base = temp.path().join("state");
+        let manifest_dir = temp.path().join("my-crate");
+        let snapshot_dir = state_base.join("snapshots").join("meta");
+        fs_err::create_dir_all(&snapshot_dir)?;
+        let snapshot = serde_json::json!({
+            "packages": [{
+                "name": "my-crate",
+                "version": "0.4.1",
+                "manifest_path": manifest_dir.join("Cargo.toml"),
+            }]
+        });
+        fs_err::write(snapshot_dir.join("latest.json"), snapshot.to_string())?;
+
+        assert_eq!(
+            expand_interpolations(
+                "${meta.name|snake_case} v${meta.version|next_minor}",
+                &manifest_dir,
+                &state_base
+            )?,
+            "my_crate v0.5.0"
+        );
+        assert_eq!(
+            expand_interpolations(
+                "${meta.name | snake_case | uppercase}",
+                &manifest_dir,
+                &state_base
+            )?,
+            "MY_CRATE"
+        );
+        assert!(
+            expand_interpolations("${meta.name|bogus}", &manifest_dir, &state_base).is_err(),
+            "unknown filters should be rejected"
+        );
+        Ok(())
+    }
+
+    // ── is_manual_step_cursor ─────────────────────────────────────────────────
+
+    #[test]
+    fn manual_step_cursor_detection() -> TestResult {
+        let program = crate::program::parser::parse(
+            r#"for workspace {
+                run "cargo" "build";
+                if working_directory_clean {
+                    manual_step "Review" "Check it.";
+                }
+                for crate in workspace {
+                    manual_step "Announce" "Tell everyone." assignee "alice";
+                }
+            }"#,
+            "test.cfe",
+        )
+        .map_err(|_errors| "parse failed")?;
+        let cursor = |s: &str| ProgramCursor::from_path_string(s);
+        assert!(is_manual_step_cursor(&program, &cursor("w0/s1/if0/s0/")?));
+        assert!(is_manual_step_cursor(&program, &cursor("w1/s2/c3/s0/")?));
+        assert!(!is_manual_step_cursor(&program, &cursor("w0/s0/")?));
+        assert!(!is_manual_step_cursor(&program, &cursor("w0/s1/else/s0/")?));
+        assert!(!is_manual_step_cursor(&program, &cursor("c0/s0/")?));
+        Ok(())
+    }
+
+    // ── execute_wait_until_step ───────────────────────────────────────────────
+
+    #[tokio::test]
+    async fn wait_until_records_success_and_timeout() -> TestResult {
+        let temp = tempdir()?;
+        let env = Environment::mock(&temp)?;
+        let run_options = crate::RunOptions::default();
+        let state_base = env.state_dir.join("cargo-for-each").join("tasks").join("t");
+        let succeed = ProgramCursor::new()
+            .with(CursorSegment::CrateIteration(0))
+            .with(CursorSegment::Statement(0));
+        let fail = ProgramCursor::new()
+            .with(CursorSegment::CrateIteration(0))
+            .with(CursorSegment::Statement(1));
+        let node = |command: &str| WaitUntilNode {
+            command: command.to_owned(),
+            args: vec![],
+            interval_seconds: 1,
+            timeout_seconds: Some(0),
+        };
+
+        execute_wait_until_step(
+            &node("true"),
+            &succeed,
+            temp.path(),
+            &state_base,
+            &env,
+            &run_options,
+            &[],
+        )
+        .await?;
+        assert!(is_wait_until_completed(&state_base.join(succeed.to_path())));
+
+        let result = execute_wait_until_step(
+            &node("false"),
+            &fail,
+            temp.path(),
+            &state_base,
+            &env,
+            &run_options,
+            &[],
+        )
+        .await;
+        assert!(
+            matches!(result, Err(crate::error::Error::WaitUntilTimedOut(..))),
+            "expected a timeout, got {result:?}"
+        );
+        let fail_dir = state_base.join(fail.to_path());
+        assert!(!is_wait_until_completed(&fail_dir));
+        assert!(is_wait_until_timed_out(&fail_dir));
+        Ok(())
+    }
+
+    // ── execute_run_step ──────────────────────────────────────────────────────
+
+    #[tokio::test]
+    async fn run_steps_are_retried_and_killed_after_their_timeout() -> TestResult {
+        let temp = tempdir()?;
+        let env = Environment::mock(&temp)?;
+        let run_options = crate::RunOptions {
+            recorder: super::recorder::Recorder::None,
+            ..crate::RunOptions::default()
+        };
+        let state_base = env.state_dir.join("cargo-for-each").join("tasks").join("t");
+        let cursor = |i| {
+            ProgramCursor::new()
+                .with(CursorSegment::CrateIteration(0))
+                .with(CursorSegment::Statement(i))
+        };
+        let step = |script: &str, timeout_seconds, retry| RunStep {
+            command: "sh".to_owned(),
+            args: vec!["-c".to_owned(), script.to_owned()],
+            stdin: None,
+            rate_limit: None,
+            exclusive: vec![],
+            elevation: None,
+            env: BTreeMap::new(),
+            cwd_relative: None,
+            timeout_seconds,
+            retry,
+        };
+        let retry = Some(Retry {
+            retries: 2,
+            delay_seconds: 0,
+        });
+
+        // fails on the first attempt only
+        let flaky = step(
+            "test -e attempted && exit 0; touch attempted; exit 1",
+            None,
+            retry,
+        );
+        execute_run_step(
+            &flaky,
+            &cursor(0),
+            temp.path(),
+            &state_base,
+            &env,
+            &run_options,
+            &[],
+        )
+        .await?;
+        let flaky_dir = state_base.join(cursor(0).to_path());
+        assert!(is_run_completed(&flaky_dir), "the second attempt succeeds");
+        assert_eq!(fs_err::read_to_string(flaky_dir.join("attempts"))?, "2");
+        assert!(
+            flaky_dir.join(step_logs::STDOUT_FILE).exists(),
+            "the output of the step is kept"
+        );
+        assert!(
+            step_duration::read(&flaky_dir).is_some(),
+            "the duration of the step is recorded"
+        );
+
+        let hanging = step("sleep 60", Some(1), retry);
+        let result = execute_run_step(
+            &hanging,
+            &cursor(1),
+            temp.path(),
+            &state_base,
+            &env,
+            &run_options,
+            &[],
+        )
+        .await;
+        assert!(
+            matches!(result, Err(crate::error::Error::StepTimedOut(_, _, 1))),
+            "expected a timeout, got {result:?}"
+        );
+        let hanging_dir = state_base.join(cursor(1).to_path());
+        assert!(is_run_failed(&hanging_dir), "a killed step failed");
+        assert_eq!(step_timeout::read(&hanging_dir), Some(1));
+        assert_eq!(fs_err::read_to_string(hanging_dir.join("attempts"))?, "3");
+        Ok(())
+    }
+
+    // ── task list ─────────────────────────────────────────────────────────────
+
+    #[test]
+    fn task_list_summarizes_progress() -> TestResult {
+        let temp = tempdir()?;
+        let env = make_environment(&temp);
+        let task_dir = super::named_dir_path("release", &env)?;
+        fs_err::create_dir_all(&task_dir)?;
+        fs_err::write(
+            task_dir.join("program.cfe"),
+            "for crate { run \"true\"; }\n",
+        )?;
+        let resolved = ResolvedProgram {
+            workspace_executions: vec![],
+            crate_executions: ["a", "b"]
+                .into_iter()
+                .map(|dir| ResolvedCrateExecution {
+                    manifest_dir: temp.path().join(dir),
+                    dependencies: vec![],
+                    package: None,
+                })
+                .collect(),
+        };
+        super::write_resolved_program(&task_dir, &resolved)?;
+        let step = ProgramCursor::new()
+            .with(CursorSegment::CrateIteration(0))
+            .with(CursorSegment::Statement(0));
+        let state_dir = make_cursor_state_dir(&super::state_dir_for_task("release", &env)?, &step)?;
+        fs_err::write(state_dir.join("exit_status"), "0")?;
+
+        let view = super::TaskView::summarize("release".to_owned(), &env);
+        assert_eq!(view.program, None);
+        assert_eq!(
+            view.targets.into_iter().collect::<Vec<_>>(),
+            [("done", 1), ("pending", 1)]
+        );
+        let missing = super::TaskView::summarize("missing".to_owned(), &env);
+        assert!(
+            missing.targets.is_empty(),
+            "a task that cannot be loaded has no targets"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn task_description_contains_all_statement_fields() -> TestResult {
+        let program = crate::program::parser::parse(
+            r#"record script; for crate { run "cargo" "publish" rate_limit 1 per minute exclusive "registry-token" timeout 600 retries 2 delay 30; }"#,
+            "test.cfe",
+        )
+        .map_err(|errors| format!("{errors:?}"))?;
+        let resolved = resolved_with_one_crate(PathBuf::from("/src/a"));
+        let description = super::TaskDescription {
+            schema_version: super::DESCRIPTION_SCHEMA_VERSION,
+            name: "release",
+            source: None,
+            program: &program,
+            targets: &resolved,
+        };
+        let json = serde_json::to_value(&description)?;
+        let field = |pointer: &str| json.pointer(pointer).cloned();
+        assert_eq!(field("/schema_version"), Some(serde_json::json!(1)));
+        assert_eq!(
+            field("/program/statements/0/record"),
+            Some(serde_json::json!("script"))
+        );
+        assert_eq!(
+            field("/program/statements/1/for_crate/statements/0/run"),
+            Some(serde_json::json!({
+                "command": "cargo",
+                "args": ["publish"],
+                "stdin": null,
+                "rate_limit": {"max": 1, "period_seconds": 60},
+                "exclusive": ["registry-token"],
+                "elevation": null,
+                "env": {},
+                "cwd_relative": null,
+                "timeout_seconds": 600,
+                "retry": {"retries": 2, "delay_seconds": 30},
+            }))
+        );
+        assert_eq!(
+            field("/targets/crate_executions/0/manifest_dir"),
+            Some(serde_json::json!("/src/a"))
+        );
+        assert!(
+            json.get("source").is_none(),
+            "tasks without a recorded source have no source field"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn per_workspace_task_names_skip_standalone_crates() -> TestResult {
+        let workspace = |dir: &str, is_standalone| crate::Workspace {
+            manifest_dir: PathBuf::from(dir),
+            is_standalone,
+            repository: None,
+        };
+        let names = super::per_workspace_task_names(
+            &[
+                workspace("/src/b", false),
+                workspace("/src/single", true),
+                workspace("/src/a", false),
+            ],
+            "ws-",
+        )?;
+        assert_eq!(
+            names.into_iter().collect::<Vec<_>>(),
+            [
+                ("ws-a".to_owned(), PathBuf::from("/src/a")),
+                ("ws-b".to_owned(), PathBuf::from("/src/b")),
+            ]
+        );
+        assert!(
+            super::per_workspace_task_names(
+                &[workspace("/src/a", false), workspace("/vendor/a", false)],
+                "ws-",
+            )
+            .is_err(),
+            "workspaces in directories with the same name are ambiguous"
+        );
+        Ok(())
+    }
+
+    // ── task clone ────────────────────────────────────────────────────────────
+
+    #[tokio::test]
+    async fn clone_copies_task_with_or_without_state() -> TestResult {
+        let temp = tempdir()?;
+        let env = make_environment(&temp);
+        let task_dir = super::named_dir_path("release-1", &env)?;
+        fs_err::create_dir_all(&task_dir)?;
+        fs_err::write(
+            task_dir.join("program.cfe"),
+            "for crate { run \"true\"; }\n",
+        )?;
+        super::write_resolved_program(&task_dir, &resolved_with_one_crate(temp.path().into()))?;
+        let step = ProgramCursor::new()
+            .with(CursorSegment::CrateIteration(0))
+            .with(CursorSegment::Statement(0));
+        let state_dir =
+            make_cursor_state_dir(&super::state_dir_for_task("release-1", &env)?, &step)?;
+        fs_err::write(state_dir.join("exit_status"), "0")?;
+
+        let clone = |to: &str, reset_state: bool| super::CloneTaskParameters {
+            from: "release-1".to_owned(),
+            to: to.to_owned(),
+            reset_state,
+            resolve_again: false,
+        };
+        super::task_clone_command(clone("release-2", false), env.clone()).await?;
+        super::task_clone_command(clone("release-3", true), env.clone()).await?;
+
+        for (name, completed) in [("release-2", true), ("release-3", false)] {
+            let (program, resolved) = super::load_task_data(name, &env)?;
+            assert_eq!(resolved.crate_executions.len(), 1, "targets of {name}");
+            let state_base = super::state_dir_for_task(name, &env)?;
+            assert_eq!(
+                find_next_statement(&program, &resolved, &state_base).is_none(),
+                completed,
+                "state of {name}"
+            );
+        }
+        let result = super::task_clone_command(clone("release-2", true), env).await;
+        assert!(
+            matches!(result, Err(crate::error::Error::AlreadyExists(_))),
+            "expected an existing task to be rejected, got {result:?}"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn create_reads_only_one_input_from_stdin() -> TestResult {
+        let temp = tempdir()?;
+        let env = make_environment(&temp);
+        assert_eq!(
+            super::parse_target_list("# release train\n/src/a\n\n  /src/b  \n"),
+            [PathBuf::from("/src/a"), PathBuf::from("/src/b")]
+        );
+        let result = super::task_create_command(
+            super::CreateTaskParameters {
+                name: "release".to_owned(),
+                program: PathBuf::from("-"),
+                workspaces: vec![],
+                crates: vec![],
+                workspaces_from: None,
+                crates_from: Some(PathBuf::from("-")),
+                skip_command_check: true,
+                register_path_dependencies: false,
+                include_dependents: false,
+            },
+            env,
+        )
+        .await;
+        assert!(
+            matches!(result, Err(crate::error::Error::StdinUsedTwice)),
+            "expected two stdin inputs to be rejected, got {result:?}"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn only_changing_subcommands_are_journalled() -> TestResult {
+        use clap::Parser as _;
+
+        let journalled = |args: &[&str]| -> Result<bool, clap::Error> {
+            let parameters = super::TaskParameters::try_parse_from(
+                std::iter::once("task").chain(args.iter().copied()),
+            )?;
+            Ok(parameters.sub_command.is_journalled())
+        };
+        assert!(!journalled(&["list"])?, "listing tasks is not journalled");
+        assert!(
+            !journalled(&["status", "--name", "release"])?,
+            "showing the status is not journalled"
+        );
+        assert!(
+            journalled(&["run", "all-targets", "--name", "release"])?,
+            "runs are journalled"
+        );
+        assert!(
+            !journalled(&["run", "--dry-run", "all-targets", "--name", "release"])?,
+            "dry runs are not journalled"
+        );
+        assert!(
+            journalled(&["reset", "--name", "release"])?,
+            "resetting a task is journalled"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn dependents_of_skipped_targets_are_reported() -> TestResult {
+        use clap::Parser as _;
+
+        let temp_dir = tempdir()?;
+        let a = temp_dir.path().join("a");
+        let b = temp_dir.path().join("b");
+        let c = temp_dir.path().join("c");
+        for dir in [&a, &b, &c] {
+            fs_err::create_dir_all(dir)?;
+        }
+        fs_err::write(a.join(super::skip_marker::FILE_NAME), "broken")?;
+        let targets = vec![
+            (a.clone(), vec![]),
+            (b.clone(), vec![a.clone()]),
+            (c.clone(), vec![]),
+        ];
+        let params =
+            super::RunAllTargetsParameters::try_parse_from(["all-targets", "--name", "release"])?;
+        let mut started = Vec::new();
+        let not_run = super::run_in_dependency_order(
+            &targets,
+            &params,
+            &[],
+            &mut 0,
+            "Crate execution",
+            &crate::tasks::cancellation::Token::default(),
+            |_idx, manifest_dir| {
+                started.push(manifest_dir);
+                std::future::ready(Ok(()))
+            },
+        )
+        .await?;
+        assert_eq!(started, vec![c]);
+        assert_eq!(
+            not_run,
+            super::NotRun {
+                blocked: vec![],
+                skipped: vec![a, b],
+            }
+        );
+        Ok(())
+    }
 }
@@ -0,0 +1,150 @@
+//! Who approved manual steps and released barriers, and when.
+//!
+//! Answering the question after a `manual_step` and releasing a
+//! `wait_for_continue` barrier with `task continue` are the points where a
+//! person signs off on a change.  Next to the answer itself, the operator
+//! (taken from `CARGO_FOR_EACH_OPERATOR`, falling back to the login name in
+//! `USER`, `LOGNAME` or `USERNAME`) and the time are stored as
+//! `approval.toml` in the step's state directory.  `task describe` shows them
+//! next to the step and `task report` lists all approvals of the reported
+//! tasks.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::invocation::records_below;
+use crate::error::Error;
+
+/// Name of the file inside the step's state directory.
+const FILE_NAME: &str = "approval.toml";
+
+/// Variables naming the operator, in order of preference.
+const OPERATOR_VARIABLES: &[&str] = &["CARGO_FOR_EACH_OPERATOR", "USER", "LOGNAME", "USERNAME"];
+
+/// The answer given for a step requiring confirmation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Approval {
+    /// The person who answered.
+    pub operator: String,
+    /// When the answer was given, in RFC 3339 format.
+    pub at: String,
+    /// Whether the step was confirmed.
+    pub approved: bool,
+}
+
+impl Approval {
+    /// Describes an answer given by the current operator now.
+    #[must_use]
+    pub fn now(approved: bool) -> Self {
+        Self {
+            operator: operator(),
+            at: humantime::format_rfc3339_seconds(std::time::SystemTime::now()).to_string(),
+            approved,
+        }
+    }
+
+    /// Returns a short description with the time formatted in `style`, e.g.
+    /// `approved by alice at 2024-05-01 12:00:00 UTC`.
+    #[must_use]
+    pub fn summary(&self, style: crate::formatting::Style) -> String {
+        let verdict = if self.approved {
+            "approved"
+        } else {
+            "rejected"
+        };
+        format!(
+            "{verdict} by {} at {}",
+            self.operator,
+            style.timestamp(&self.at)
+        )
+    }
+}
+
+/// Returns the name of the current operator from the environment, or
+/// `unknown`.
+#[must_use]
+pub fn operator() -> String {
+    OPERATOR_VARIABLES
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.trim().is_empty())
+        .unwrap_or_else(|| "unknown".to_owned())
+}
+
+/// Writes `approval` to the step's `state_dir`.
+///
+/// # Errors
+///
+/// Returns an error if the approval cannot be serialized or written.
+pub fn write(state_dir: &Path, approval: &Approval) -> Result<(), Error> {
+    let path = state_dir.join(FILE_NAME);
+    let content = toml::to_string(approval).map_err(Error::CouldNotSerializeApproval)?;
+    fs_err::write(&path, content).map_err(|e| Error::CouldNotWriteStateFile(path, e))
+}
+
+/// Reads the approval recorded in the step's `state_dir`, if there is one.
+#[must_use]
+pub fn read(state_dir: &Path) -> Option<Approval> {
+    let content = fs_err::read_to_string(state_dir.join(FILE_NAME)).ok()?;
+    toml::from_str(&content).ok()
+}
+
+/// Returns all approvals recorded in `dir` and below it, with the path of
+/// their state directory relative to `dir`, in program order.
+#[must_use]
+pub fn all_below(dir: &Path) -> Vec<(PathBuf, Approval)> {
+    records_below(dir, read)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use pretty_assertions::assert_eq;
+
+    use super::{Approval, all_below, read, write};
+
+    #[test]
+    fn approvals_are_recorded_per_step() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempfile::tempdir()?;
+        let target_dir = temp_dir.path().join("w0");
+        let first = target_dir.join("s2");
+        let second = target_dir.join("s10");
+        fs_err::create_dir_all(&first)?;
+        fs_err::create_dir_all(&second)?;
+        assert_eq!(read(&first), None);
+
+        let rejected = Approval {
+            operator: "alice".to_owned(),
+            at: "2024-05-01T12:00:00Z".to_owned(),
+            approved: false,
+        };
+        let approved = Approval {
+            approved: true,
+            ..rejected.clone()
+        };
+        write(&second, &rejected)?;
+        write(&first, &approved)?;
+        assert_eq!(read(&first).as_ref(), Some(&approved));
+        assert_eq!(
+            all_below(&target_dir),
+            [
+                (PathBuf::from("s2"), approved.clone()),
+                (PathBuf::from("s10"), rejected.clone()),
+            ]
+        );
+        assert_eq!(
+            rejected.summary(crate::formatting::Style {
+                utc: true,
+                iso8601: false
+            }),
+            "rejected by alice at 2024-05-01 12:00:00 UTC"
+        );
+        assert!(
+            !Approval::now(true).operator.is_empty(),
+            "the operator always has a name"
+        );
+        Ok(())
+    }
+}
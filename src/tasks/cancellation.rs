@@ -0,0 +1,169 @@
+//! Cancelling the running steps of a run.
+//!
+//! Once a target of `task run all-targets` fails without `--keep-going` (or
+//! `--max-failures` targets failed), the run fails whatever the other targets
+//! do.  Instead of letting the targets running at the same time finish, the
+//! [`Token`] of the run is cancelled: the commands of their running `run`
+//! steps are killed together with everything they started (see
+//! [`super::step_timeout`]) and no further steps are started.  An
+//! interrupted step is recorded like a failed one, with an `interrupted`
+//! file in its state directory, so the next run starts it over.
+//!
+//! While `task run all-targets` runs, an [`InterruptHandler`] cancels the run
+//! the same way when the user presses Ctrl+C, instead of leaving the
+//! recorders and cargo processes of the running steps behind.  The run then
+//! prints which targets are not completed yet and can be resumed later.
+//! Pressing Ctrl+C a second time exits immediately.
+
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::error::Error;
+
+/// Name of the file marking an interrupted step in its state directory.
+const INTERRUPTED_FILE: &str = "interrupted";
+
+/// Cancels the steps of a run; clones share the same state.
+#[derive(Debug, Clone, Default)]
+pub struct Token(Arc<tokio::sync::watch::Sender<bool>>);
+
+impl Token {
+    /// Cancels the run.
+    pub fn cancel(&self) {
+        self.0.send_replace(true);
+    }
+
+    /// Returns whether the run was cancelled.
+    #[must_use]
+    pub fn is_cancelled(&self) -> bool {
+        *self.0.borrow()
+    }
+
+    /// Waits until the run is cancelled.
+    pub async fn cancelled(&self) {
+        let mut receiver = self.0.subscribe();
+        // the sender is kept alive by `self`, so waiting cannot fail
+        let _ignored = receiver.wait_for(|cancelled| *cancelled).await;
+    }
+}
+
+/// Cancels a run when the user presses Ctrl+C, until it is dropped.
+#[derive(Debug)]
+pub struct InterruptHandler(tokio::task::JoinHandle<()>);
+
+impl InterruptHandler {
+    /// Starts cancelling `token` on Ctrl+C.
+    #[must_use]
+    pub fn install(token: &Token) -> Self {
+        let token = token.clone();
+        Self(tokio::spawn(async move {
+            if let Err(e) = tokio::signal::ctrl_c().await {
+                tracing::warn!("Could not listen for Ctrl+C: {e}");
+                return;
+            }
+            tracing::warn!(
+                "Interrupting the running targets; press Ctrl+C again to exit immediately"
+            );
+            token.cancel();
+            if tokio::signal::ctrl_c().await.is_ok() {
+                exit_interrupted();
+            }
+        }))
+    }
+}
+
+impl Drop for InterruptHandler {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// Exits with the status of a process killed by SIGINT.
+#[expect(clippy::exit, reason = "a second Ctrl+C exits without cleaning up")]
+fn exit_interrupted() -> ! {
+    std::process::exit(130)
+}
+
+/// Records in `state_dir` that the step was interrupted.
+///
+/// # Errors
+///
+/// Returns [`Error::CouldNotWriteStateFile`] if the file cannot be written.
+pub fn mark(state_dir: &Path) -> Result<(), Error> {
+    let path = state_dir.join(INTERRUPTED_FILE);
+    fs_err::write(&path, "").map_err(|e| Error::CouldNotWriteStateFile(path, e))
+}
+
+/// Removes the record of an earlier interruption from `state_dir`.
+///
+/// # Errors
+///
+/// Returns [`Error::CouldNotRemoveStateFile`] if the file exists but cannot
+/// be removed.
+pub fn clear(state_dir: &Path) -> Result<(), Error> {
+    let path = state_dir.join(INTERRUPTED_FILE);
+    match fs_err::remove_file(&path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            Err(Error::CouldNotRemoveStateFile(path, e))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Returns whether the step in `state_dir` was interrupted.
+#[must_use]
+pub fn was_interrupted(state_dir: &Path) -> bool {
+    state_dir.join(INTERRUPTED_FILE).exists()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{Token, clear, mark, was_interrupted};
+    use crate::tasks::step_timeout::{Outcome, execute};
+
+    #[tokio::test]
+    async fn cancelling_kills_running_commands() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempfile::tempdir()?;
+        let environment = crate::Environment::mock(&temp_dir)?;
+        let cancellation = Token::default();
+        let mut hanging = std::process::Command::new("sh");
+        hanging.args(["-c", "sleep 60"]);
+        let started = Instant::now();
+        let (outcome, ()) = tokio::join!(
+            execute(hanging, &environment, &cancellation, temp_dir.path(), None),
+            async {
+                tokio::time::sleep(Duration::from_millis(200)).await;
+                cancellation.clone().cancel();
+            }
+        );
+        assert!(
+            matches!(outcome?, Outcome::Interrupted),
+            "the command is interrupted"
+        );
+        assert!(
+            started.elapsed().as_secs() < 30,
+            "the command is killed when the run is cancelled"
+        );
+        assert!(cancellation.is_cancelled(), "clones share the cancellation");
+        assert!(
+            !Token::default().is_cancelled(),
+            "other runs are not cancelled"
+        );
+
+        assert!(
+            !was_interrupted(temp_dir.path()),
+            "nothing was interrupted yet"
+        );
+        mark(temp_dir.path())?;
+        assert!(
+            was_interrupted(temp_dir.path()),
+            "the interruption is recorded"
+        );
+        clear(temp_dir.path())?;
+        clear(temp_dir.path())?;
+        assert!(!was_interrupted(temp_dir.path()), "the record is removed");
+        Ok(())
+    }
+}
@@ -0,0 +1,162 @@
+//! Refusing to start targets whose git working tree is dirty.
+//!
+//! Mechanical edits applied across a fleet mix badly with uncommitted local
+//! work: the edit ends up in the same commit, or a `git stash` in a statement
+//! loses it.  With the `require_clean_git;` program statement or `task run
+//! --require-clean-git`, `git status --porcelain` is checked in the manifest
+//! directory of each workspace and standalone crate before its first statement
+//! runs.  A target with changes is not run; it is marked as blocked by writing
+//! the reason to `blocked` in its state directory, which `task describe` and
+//! `task report` show.  Blocked targets do not count as failed, and the check
+//! is repeated on the next run.  Once a statement of a target ran, the check
+//! is skipped, since its own statements are expected to change the tree.
+
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use crate::error::Error;
+use crate::program::{GlobalStatement, Program};
+
+/// Name of the file recording why a target is blocked.
+const FILE_NAME: &str = "blocked";
+
+/// Returns `true` if `program` contains a `require_clean_git` statement.
+#[must_use]
+pub fn is_required(program: &Program) -> bool {
+    program
+        .statements
+        .iter()
+        .any(|stmt| matches!(stmt, GlobalStatement::RequireCleanGit))
+}
+
+/// Returns `true` if a statement of the target with state in `target_dir`
+/// already ran.
+fn has_started(target_dir: &Path) -> bool {
+    fs_err::read_dir(target_dir).is_ok_and(|entries| {
+        entries
+            .filter_map(Result::ok)
+            .any(|entry| entry.path().is_dir())
+    })
+}
+
+/// Returns why the git working tree in `manifest_dir` is not clean, or `None`
+/// if it is.
+///
+/// # Errors
+///
+/// Returns an error if `git` cannot be run.
+pub fn dirty_reason(manifest_dir: &Path) -> Result<Option<String>, Error> {
+    let output = Command::new("git")
+        .args(["status", "--porcelain"])
+        .current_dir(manifest_dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .map_err(|e| {
+            Error::CommandExecutionFailed(
+                "git status --porcelain".to_owned(),
+                manifest_dir.to_path_buf(),
+                e,
+            )
+        })?;
+    if !output.status.success() {
+        return Ok(Some(format!(
+            "git status failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    let changes = String::from_utf8_lossy(&output.stdout);
+    let changed: Vec<&str> = changes.lines().collect();
+    Ok(match changed.as_slice() {
+        [] => None,
+        [first, rest @ ..] => Some(format!(
+            "uncommitted changes ({}{})",
+            first.trim(),
+            if rest.is_empty() {
+                String::new()
+            } else {
+                format!(" and {} more", rest.len())
+            }
+        )),
+    })
+}
+
+/// Checks the target in `manifest_dir`, with state in `target_dir`, before
+/// its statements run, if `required`.
+///
+/// # Errors
+///
+/// Returns [`Error::TargetBlocked`] if the working tree is not clean, or an
+/// error if `git` cannot be run or the state cannot be written.
+pub fn check(manifest_dir: &Path, target_dir: &Path, required: bool) -> Result<(), Error> {
+    if !required || has_started(target_dir) {
+        return Ok(());
+    }
+    let path = target_dir.join(FILE_NAME);
+    match dirty_reason(manifest_dir)? {
+        Some(reason) => {
+            fs_err::create_dir_all(target_dir)
+                .map_err(|e| Error::CouldNotCreateStateDir(target_dir.to_path_buf(), e))?;
+            fs_err::write(&path, &reason)
+                .map_err(|e| Error::CouldNotWriteStateFile(path.clone(), e))?;
+            Err(Error::TargetBlocked(manifest_dir.to_path_buf(), reason))
+        }
+        None => {
+            if path.exists() {
+                fs_err::remove_file(&path).map_err(|e| Error::CouldNotRemoveStateFile(path, e))?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Returns why the target with state in `target_dir` is blocked, if it is.
+#[must_use]
+pub fn blocked_reason(target_dir: &Path) -> Option<String> {
+    fs_err::read_to_string(target_dir.join(FILE_NAME)).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+
+    use pretty_assertions::assert_eq;
+
+    use super::{blocked_reason, check};
+
+    #[test]
+    fn dirty_trees_block_targets_until_clean() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempfile::tempdir()?;
+        let repo = temp_dir.path().join("repo");
+        let target_dir = temp_dir.path().join("state").join("c0");
+        fs_err::create_dir_all(&repo)?;
+        let status = Command::new("git")
+            .args(["init", "--quiet"])
+            .current_dir(&repo)
+            .status()?;
+        assert!(status.success(), "git init failed");
+
+        check(&repo, &target_dir, true)?;
+        fs_err::write(repo.join("notes.txt"), "local work")?;
+        check(&repo, &target_dir, false)?;
+        let result = check(&repo, &target_dir, true);
+        assert!(
+            matches!(result, Err(crate::error::Error::TargetBlocked(..))),
+            "expected a dirty tree to block the target, got {result:?}"
+        );
+        assert_eq!(
+            blocked_reason(&target_dir).as_deref(),
+            Some("uncommitted changes (?? notes.txt)")
+        );
+
+        fs_err::remove_file(repo.join("notes.txt"))?;
+        check(&repo, &target_dir, true)?;
+        assert_eq!(blocked_reason(&target_dir), None);
+
+        fs_err::create_dir_all(target_dir.join("s0"))?;
+        fs_err::write(repo.join("Cargo.toml"), "changed by a statement")?;
+        check(&repo, &target_dir, true)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,60 @@
+//! In-memory cache of completed targets.
+//!
+//! Whether a target is completed is determined by reading the state files of
+//! all its statements.  The scheduler asks this for every target (and for the
+//! dependencies of every target) on every pass, which gets slow for tasks
+//! with hundreds of targets.  A completed target stays completed while the
+//! task runs, because the runner never removes state, so positive answers are
+//! remembered for the lifetime of the process.  Commands that do remove state
+//! (rewind, remove and sync-program) call [`invalidate`] for the task.
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// State directories of targets known to be completed.
+static COMPLETED: Mutex<BTreeSet<PathBuf>> = Mutex::new(BTreeSet::new());
+
+/// Returns `true` if the target with state directory `target_dir` is known to
+/// be completed.
+#[must_use]
+pub fn contains(target_dir: &Path) -> bool {
+    COMPLETED
+        .lock()
+        .is_ok_and(|completed| completed.contains(target_dir))
+}
+
+/// Records that the target with state directory `target_dir` is completed.
+pub fn insert(target_dir: PathBuf) {
+    if let Ok(mut completed) = COMPLETED.lock() {
+        completed.insert(target_dir);
+    }
+}
+
+/// Forgets all targets of the task with state directory `state_base`.
+pub fn invalidate(state_base: &Path) {
+    if let Ok(mut completed) = COMPLETED.lock() {
+        completed.retain(|target_dir| !target_dir.starts_with(state_base));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{contains, insert, invalidate};
+
+    #[test]
+    fn invalidate_forgets_targets_of_one_task() {
+        let a = Path::new("/completion-cache-test/tasks/a");
+        let b = Path::new("/completion-cache-test/tasks/b");
+        insert(a.join("w0"));
+        insert(b.join("c1"));
+        assert!(contains(&a.join("w0")), "a/w0 was recorded");
+        assert!(!contains(&a.join("w1")), "a/w1 was not recorded");
+        invalidate(a);
+        assert!(!contains(&a.join("w0")), "a/w0 was invalidated");
+        assert!(contains(&b.join("c1")), "b/c1 belongs to another task");
+        invalidate(b);
+    }
+}
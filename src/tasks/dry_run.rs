@@ -0,0 +1,251 @@
+//! Showing what `task run` would do without doing it.
+//!
+//! Before a destructive task like publishing or tagging runs across dozens of
+//! repositories, `task run --dry-run` lists the statements the subcommand
+//! would execute, in execution order: the next one for `single-step`, those
+//! of the first target with pending work for `single-target` and all pending
+//! ones for `all-targets`.  `run` and `cargo` statements are shown with the
+//! command line after interpolation and `elevate`/`run_as` wrapping, their
+//! working directory and the environment they would get from
+//! `with_env_file`, the crate's settings and `env`, with secret values
+//! redacted as in `invocation.toml`.
+//!
+//! Nothing is written to the state directory and no lock is taken, but
+//! interpolating variables may run `cargo metadata`.  The statements of an
+//! `if` block are only known once its conditions were evaluated, so the block
+//! is shown as a single step, and interpolations referring to snapshots that
+//! were not taken yet are shown as written.  `--until-phase` and
+//! `--only-phase` are not applied.
+
+use std::fmt::Write as _;
+use std::path::Path;
+
+use super::{
+    NextStatement, StatementAction, TaskRunSubCommand, cargo_label, crate_env, elevation,
+    expand_interpolations, feature_powerset_label, find_next_statement, invocation,
+    load_env_vars_from_files, load_task_data, pending, run_label, shell_command_line,
+    state_dir_for_task, wait_until_label,
+};
+use crate::Config;
+use crate::error::Error;
+use crate::program::ast::common::RunStep;
+use crate::program::cursor::CursorSegment;
+
+/// Returns the statement of `action` in program syntax.
+fn label(action: &StatementAction<'_>) -> String {
+    match action {
+        StatementAction::RunCommand(step) => run_label(step),
+        StatementAction::Cargo(step) => cargo_label(step),
+        StatementAction::FeaturePowerset(node) => feature_powerset_label(node),
+        StatementAction::ManualStep(node) => format!("manual_step \"{}\"", node.title),
+        StatementAction::EvaluateWorkspaceIf(_) | StatementAction::EvaluateCrateIf(_) => {
+            String::from("if ... (evaluates its conditions, then runs the chosen branch)")
+        }
+        StatementAction::SnapshotMetadata(node) => format!("snapshot_metadata \"{}\"", node.name),
+        StatementAction::WaitForContinue(node) => {
+            format!("wait_for_continue \"{}\"", node.description)
+        }
+        StatementAction::WaitUntil(node) => wait_until_label(node),
+    }
+}
+
+/// Appends the command line, working directory and environment `step` would
+/// run with in `manifest_dir` to `out`.
+///
+/// # Errors
+///
+/// Returns an error if an interpolation cannot be expanded yet.
+fn describe_run_step(
+    out: &mut String,
+    step: &RunStep,
+    manifest_dir: &Path,
+    state_base: &Path,
+    extra_env: &[(String, String)],
+) -> Result<(), Error> {
+    let command = expand_interpolations(&step.command, manifest_dir, state_base)?;
+    let args = step
+        .args
+        .iter()
+        .map(|a| expand_interpolations(a, manifest_dir, state_base))
+        .collect::<Result<Vec<_>, _>>()?;
+    let working_dir = match &step.cwd_relative {
+        Some(dir) => manifest_dir.join(expand_interpolations(dir, manifest_dir, state_base)?),
+        None => manifest_dir.to_path_buf(),
+    };
+    let mut env = extra_env.to_vec();
+    for (name, value) in &step.env {
+        env.push((
+            name.clone(),
+            expand_interpolations(value, manifest_dir, state_base)?,
+        ));
+    }
+    let (command, args) = match &step.elevation {
+        Some(elevation) => elevation::wrap(elevation, &command, &args),
+        None => (command, args),
+    };
+    let invocation = invocation::Invocation::new(&command, &args, &working_dir, false, &env);
+    let _ignored = writeln!(out, "    command: {}", shell_command_line(&command, &args));
+    let _ignored = writeln!(out, "    cwd: {}", invocation.working_dir.display());
+    for (name, value) in &invocation.env {
+        let _ignored = writeln!(out, "    env: {name}={value}");
+    }
+    Ok(())
+}
+
+/// Renders the statements in `steps` as they would run.
+#[must_use]
+pub fn render(steps: &[&NextStatement<'_>], config: &Config, state_base: &Path) -> String {
+    let mut out = String::new();
+    for step in steps {
+        let _ignored = writeln!(
+            out,
+            "{}  {}\n    {}",
+            step.cursor,
+            step.manifest_dir.display(),
+            label(&step.action)
+        );
+        let run_step = match step.action {
+            StatementAction::RunCommand(run) => Some(run.clone()),
+            StatementAction::Cargo(cargo) => Some(cargo.to_run_step()),
+            _ => None,
+        };
+        let Some(run_step) = run_step else {
+            continue;
+        };
+        let in_crate = step
+            .cursor
+            .segments()
+            .iter()
+            .any(|segment| matches!(segment, CursorSegment::CrateIteration(_)));
+        let described = load_env_vars_from_files(&step.env_file_paths, step.manifest_dir)
+            .map(|extra_env| {
+                if in_crate {
+                    crate_env(config, step.manifest_dir, &extra_env)
+                } else {
+                    extra_env
+                }
+            })
+            .and_then(|extra_env| {
+                describe_run_step(
+                    &mut out,
+                    &run_step,
+                    step.manifest_dir,
+                    state_base,
+                    &extra_env,
+                )
+            });
+        if let Err(e) = described {
+            let _ignored = writeln!(out, "    (not known before earlier steps ran: {e})");
+        }
+    }
+    out
+}
+
+/// Prints what `sub_command` would execute, without executing anything.
+///
+/// # Errors
+///
+/// Returns an error if the task or the configuration cannot be loaded.
+#[expect(clippy::print_stdout, reason = "This is part of the UI, not logging")]
+pub fn command(
+    sub_command: &TaskRunSubCommand,
+    environment: &crate::Environment,
+) -> Result<(), Error> {
+    let task_name = match sub_command {
+        TaskRunSubCommand::SingleStep(p) => &p.name,
+        TaskRunSubCommand::SingleTarget(p) => &p.name,
+        TaskRunSubCommand::AllTargets(p) => &p.name,
+    };
+    let (program, resolved) = load_task_data(task_name, environment)?;
+    let config = Config::load(environment)?;
+    let state_base = state_dir_for_task(task_name, environment)?;
+    let work = pending::compute(&program, &resolved, &state_base);
+    let next;
+    let steps: Vec<&NextStatement<'_>> = match sub_command {
+        TaskRunSubCommand::SingleStep(_) => {
+            next = find_next_statement(&program, &resolved, &state_base);
+            next.iter().collect()
+        }
+        TaskRunSubCommand::SingleTarget(_) => work
+            .targets
+            .first()
+            .map(|target| target.steps.iter().map(|step| &step.statement).collect())
+            .unwrap_or_default(),
+        TaskRunSubCommand::AllTargets(_) => work.steps().map(|step| &step.statement).collect(),
+    };
+    if steps.is_empty() {
+        println!("Nothing to run for task {task_name}");
+        return Ok(());
+    }
+    println!("Dry run of task {task_name}; nothing is executed:");
+    print!("{}", render(&steps, &config, &state_base));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::render;
+    use crate::program::resolve::{ResolvedCrateExecution, ResolvedProgram};
+    use crate::tasks::{parse_program, pending};
+
+    #[test]
+    fn pending_statements_are_shown_as_they_would_run() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempfile::tempdir()?;
+        let crate_dir = temp_dir.path().join("a");
+        fs_err::create_dir_all(&crate_dir)?;
+        fs_err::write(
+            crate_dir.join("release.env"),
+            "CARGO_REGISTRY_TOKEN=secret\n",
+        )?;
+        let program = parse_program(
+            r#"
+            select crates;
+            for crate {
+                with_env_file "release.env" {
+                    run "cargo" "publish" env "MODE=fast" cwd "sub";
+                }
+                manual_step "Announce" "Post the release notes";
+            }
+            "#,
+            "<test>",
+        )?;
+        let resolved = ResolvedProgram {
+            workspace_executions: vec![],
+            crate_executions: vec![ResolvedCrateExecution {
+                manifest_dir: crate_dir.clone(),
+                dependencies: vec![],
+                package: None,
+            }],
+        };
+        let state_base = temp_dir.path().join("state");
+        let config = crate::Config {
+            sort_on_save: false,
+            shared_repository: None,
+            notifications: vec![],
+            target_sets: std::collections::BTreeMap::new(),
+            tags: std::collections::BTreeMap::new(),
+            workspaces: vec![],
+            crates: vec![],
+        };
+        let work = pending::compute(&program, &resolved, &state_base);
+        let steps: Vec<_> = work.steps().map(|step| &step.statement).collect();
+        assert_eq!(
+            render(&steps, &config, &state_base),
+            format!(
+                "c0/s0/env/s0/  {dir}\n    \
+                 run \"cargo\" \"publish\" env \"MODE=fast\" cwd \"sub\"\n    \
+                 command: cargo \"publish\"\n    \
+                 cwd: {dir}/sub\n    \
+                 env: CARGO_REGISTRY_TOKEN=<redacted>\n    \
+                 env: MODE=fast\n\
+                 c0/s1/  {dir}\n    \
+                 manual_step \"Announce\"\n",
+                dir = crate_dir.display()
+            )
+        );
+        assert!(!state_base.exists(), "a dry run writes no state");
+        Ok(())
+    }
+}
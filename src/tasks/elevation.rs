@@ -0,0 +1,188 @@
+//! Running `run` statements as another user via `sudo`.
+//!
+//! Statements with an `elevate` or `run_as "user"` clause are prefixed with
+//! `sudo -n`, which fails instead of prompting for a password.  A prompt in
+//! the middle of a run would be hidden in an asciinema recording, or several
+//! prompts would compete for the terminal with `--jobs`, so before a task with
+//! such statements runs, [`preflight`] makes sure `sudo` has cached
+//! credentials, prompting once up front when that is possible, and
+//! [`keep_alive`] refreshes them while the task runs.
+
+use std::io::IsTerminal as _;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use crate::error::Error;
+use crate::program::ast::common::Elevation;
+use crate::program::ast::crate_ctx::CrateStatement;
+use crate::program::ast::workspace_ctx::WorkspaceStatement;
+use crate::program::{GlobalStatement, Program};
+
+/// How often [`keep_alive`] refreshes the cached credentials; well below the
+/// default `sudo` timeout of five minutes.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Returns the command and arguments that run `command` with `args` as the
+/// user given by `elevation`.
+#[must_use]
+pub fn wrap(elevation: &Elevation, command: &str, args: &[String]) -> (String, Vec<String>) {
+    let mut sudo_args = vec!["-n".to_owned()];
+    if let Elevation::User(user) = elevation {
+        sudo_args.push("-u".to_owned());
+        sudo_args.push(user.clone());
+    }
+    sudo_args.push("--".to_owned());
+    sudo_args.push(command.to_owned());
+    sudo_args.extend(args.iter().cloned());
+    ("sudo".to_owned(), sudo_args)
+}
+
+/// Returns `true` if any of the crate statements runs as another user.
+fn crate_stmts_elevated(stmts: &[CrateStatement]) -> bool {
+    stmts.iter().any(|stmt| match stmt {
+        CrateStatement::Run(step) => step.elevation.is_some(),
+        CrateStatement::If(block) => {
+            block
+                .branches
+                .iter()
+                .any(|branch| crate_stmts_elevated(&branch.statements))
+                || crate_stmts_elevated(&block.else_statements)
+        }
+        CrateStatement::WithEnvFile(block) => crate_stmts_elevated(&block.statements),
+        CrateStatement::Cargo(_)
+        | CrateStatement::FeaturePowerset(_)
+        | CrateStatement::ManualStep(_)
+        | CrateStatement::SnapshotMetadata(_)
+        | CrateStatement::WaitForContinue(_)
+        | CrateStatement::WaitUntil(_)
+        | CrateStatement::Phase(_) => false,
+    })
+}
+
+/// Returns `true` if any of the workspace statements runs as another user.
+fn workspace_stmts_elevated(stmts: &[WorkspaceStatement]) -> bool {
+    stmts.iter().any(|stmt| match stmt {
+        WorkspaceStatement::Run(step) => step.elevation.is_some(),
+        WorkspaceStatement::If(block) => {
+            block
+                .branches
+                .iter()
+                .any(|branch| workspace_stmts_elevated(&branch.statements))
+                || workspace_stmts_elevated(&block.else_statements)
+        }
+        WorkspaceStatement::WithEnvFile(block) => workspace_stmts_elevated(&block.statements),
+        WorkspaceStatement::ForCrateInWorkspace(block) => crate_stmts_elevated(&block.statements),
+        WorkspaceStatement::Cargo(_)
+        | WorkspaceStatement::ManualStep(_)
+        | WorkspaceStatement::SnapshotMetadata(_)
+        | WorkspaceStatement::WaitForContinue(_)
+        | WorkspaceStatement::WaitUntil(_)
+        | WorkspaceStatement::Phase(_) => false,
+    })
+}
+
+/// Returns `true` if any `run` statement of `program` runs as another user.
+#[must_use]
+pub fn is_used(program: &Program) -> bool {
+    program.statements.iter().any(|stmt| match stmt {
+        GlobalStatement::ForWorkspace(block) => workspace_stmts_elevated(&block.statements),
+        GlobalStatement::ForCrate(block) => crate_stmts_elevated(&block.statements),
+        _ => false,
+    })
+}
+
+/// Makes sure `sudo` can run without asking for a password.
+///
+/// If the credentials are not cached and standard input is a terminal,
+/// `sudo -v` is run to ask for the password once.
+///
+/// # Errors
+///
+/// Returns an error if `sudo` is not installed, or if the credentials are not
+/// cached and could not be refreshed.
+pub fn preflight(environment: &crate::Environment) -> Result<(), Error> {
+    if !crate::utils::command_is_executable("sudo", environment) {
+        return Err(Error::CommandNotFound("sudo".to_owned()));
+    }
+    let cached = Command::new("sudo")
+        .args(["-n", "true"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success());
+    if cached {
+        return Ok(());
+    }
+    if std::io::stdin().is_terminal()
+        && Command::new("sudo")
+            .arg("-v")
+            .status()
+            .is_ok_and(|status| status.success())
+    {
+        return Ok(());
+    }
+    Err(Error::SudoCredentialsNotCached)
+}
+
+/// Refreshes the cached `sudo` credentials in the background until the
+/// returned task is aborted, so long runs do not fail halfway through.
+#[must_use]
+pub fn keep_alive() -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async {
+        loop {
+            tokio::time::sleep(REFRESH_INTERVAL).await;
+            let refreshed = tokio::process::Command::new("sudo")
+                .args(["-n", "-v"])
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status()
+                .await;
+            if !refreshed.is_ok_and(|status| status.success()) {
+                tracing::warn!("Could not refresh the cached sudo credentials");
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::{is_used, wrap};
+    use crate::program::ast::common::Elevation;
+    use crate::program::parser::parse;
+
+    #[test]
+    fn wrap_prefixes_non_interactive_sudo() {
+        let args = vec!["--path".to_owned(), ".".to_owned()];
+        assert_eq!(
+            wrap(&Elevation::User("deploy".to_owned()), "cargo", &args),
+            (
+                "sudo".to_owned(),
+                ["-n", "-u", "deploy", "--", "cargo", "--path", "."]
+                    .map(str::to_owned)
+                    .to_vec()
+            )
+        );
+        assert_eq!(
+            wrap(&Elevation::Root, "make", &[]).1,
+            ["-n", "--", "make"].map(str::to_owned).to_vec()
+        );
+    }
+
+    #[test]
+    fn is_used_finds_nested_elevated_steps() -> Result<(), Box<dyn std::error::Error>> {
+        let nested =
+            r#"for workspace { for crate in workspace { if standalone { run "x" elevate; } } }"#;
+        let plain = r#"for workspace { run "x"; } for crate { run "y"; }"#;
+        let parse_ok = |src: &str| parse(src, "test.cfe").map_err(|_errors| "parse failed");
+        assert!(
+            is_used(&parse_ok(nested)?),
+            "elevated step inside an if block"
+        );
+        assert!(!is_used(&parse_ok(plain)?), "no elevated steps");
+        Ok(())
+    }
+}
@@ -0,0 +1,580 @@
+//! Export of a task as a standalone POSIX shell script.
+//!
+//! The generated script runs the `run` statements of the task for every target
+//! in dependency order, without requiring cargo-for-each on the machine that
+//! executes it.  Statements that need the interactive runner are rendered as
+//! comments: manual steps and wait barriers pause the script until the operator
+//! presses Enter, and `if` blocks are emitted as commented-out branches because
+//! their conditions are not evaluated by the script.
+
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+use tracing::instrument;
+
+use super::feature_powerset;
+use super::scheduling::{cycle_error, topological_order};
+use super::{
+    ExportScriptParameters, expand_interpolations, first_crate_stmts, first_workspace_stmts,
+    load_task_data, shell_command_line, state_dir_for_task,
+};
+use crate::error::Error;
+use crate::program::Program;
+use crate::program::ast::common::{Elevation, ManualStepNode, RunStep, StdinSource, WaitUntilNode};
+use crate::program::ast::crate_ctx::CrateStatement;
+use crate::program::ast::workspace_ctx::WorkspaceStatement;
+use crate::program::resolve::{ResolvedCrateExecution, ResolvedProgram};
+
+/// Accumulates the lines of the generated script with the current nesting.
+struct ScriptWriter<'a> {
+    /// The script text generated so far.
+    out: String,
+    /// Current indentation depth (four spaces per level).
+    depth: usize,
+    /// Number of enclosing commented-out `if` branches.
+    commented: usize,
+    /// Task state directory used to resolve `${name.field}` interpolations
+    /// from snapshots that were already captured.
+    state_base: &'a Path,
+}
+
+impl ScriptWriter<'_> {
+    /// Appends a single line at the current indentation.
+    fn line(&mut self, text: &str) -> Result<(), Error> {
+        let indent = "    ".repeat(self.depth);
+        if self.commented > 0 {
+            writeln!(self.out, "{indent}# {text}")?;
+        } else if text.is_empty() {
+            writeln!(self.out)?;
+        } else {
+            writeln!(self.out, "{indent}{text}")?;
+        }
+        Ok(())
+    }
+
+    /// Appends a comment line at the current indentation.
+    fn comment(&mut self, text: &str) -> Result<(), Error> {
+        self.line(&format!("# {text}"))
+    }
+
+    /// Opens a subshell that changes into `dir`.
+    fn open_target(&mut self, kind: &str, dir: &Path) -> Result<(), Error> {
+        self.comment(&format!("── {kind} {} ──", dir.display()))?;
+        self.line("(")?;
+        self.depth = self.depth.saturating_add(1);
+        self.line(&format!("cd {}", single_quote(&dir.to_string_lossy())))
+    }
+
+    /// Closes the subshell opened by [`Self::open_target`] or [`Self::open_env_file`].
+    fn close_subshell(&mut self) -> Result<(), Error> {
+        self.depth = self.depth.saturating_sub(1);
+        self.line(")")
+    }
+
+    /// Expands interpolations in `s`, falling back to the raw text (with a
+    /// warning comment) when the referenced snapshot has not been captured yet.
+    fn expand(&mut self, s: &str, manifest_dir: &Path) -> Result<String, Error> {
+        match expand_interpolations(s, manifest_dir, self.state_base) {
+            Ok(expanded) => Ok(expanded),
+            Err(e) => {
+                self.comment(&format!("WARNING: interpolation left unresolved: {e}"))?;
+                Ok(s.to_owned())
+            }
+        }
+    }
+
+    /// Renders a `run` statement.
+    fn run(&mut self, step: &RunStep, manifest_dir: &Path) -> Result<(), Error> {
+        let command = self.expand(&step.command, manifest_dir)?;
+        let mut args = Vec::with_capacity(step.args.len());
+        for arg in &step.args {
+            args.push(self.expand(arg, manifest_dir)?);
+        }
+        let command_line = shell_command_line(&command, &args);
+        // The script runs interactively, so sudo may ask for a password.
+        let command_line = match &step.elevation {
+            None => command_line,
+            Some(Elevation::Root) => format!("sudo -- {command_line}"),
+            Some(Elevation::User(user)) => {
+                format!("sudo -u {} -- {command_line}", single_quote(user))
+            }
+        };
+        let mut command_line = match step.timeout_seconds {
+            None => command_line,
+            Some(seconds) => format!("timeout {seconds} {command_line}"),
+        };
+        for (name, value) in step.env.iter().rev() {
+            let value = self.expand(value, manifest_dir)?;
+            command_line = format!("{name}={} {command_line}", single_quote(&value));
+        }
+        if let Some(limit) = step.rate_limit {
+            self.comment(&format!(
+                "NOTE: rate limit of {} per {}s is not enforced by this script",
+                limit.max, limit.period_seconds
+            ))?;
+        }
+        if let Some(retry) = step.retry {
+            self.comment(&format!(
+                "NOTE: {} retries after failures are not performed by this script",
+                retry.retries
+            ))?;
+        }
+        let line = match &step.stdin {
+            None => command_line,
+            Some(StdinSource::File(file)) => {
+                let file = self.expand(file, manifest_dir)?;
+                // the file is relative to the target even with `cwd`
+                let file = if step.cwd_relative.is_some() {
+                    manifest_dir.join(file).to_string_lossy().into_owned()
+                } else {
+                    file
+                };
+                format!("{command_line} < {}", single_quote(&file))
+            }
+            Some(StdinSource::Content(content)) => {
+                // `printf '%b'` keeps the content on one line of the script,
+                // which matters inside commented-out branches.
+                let content = self
+                    .expand(content, manifest_dir)?
+                    .replace('\\', "\\\\")
+                    .replace('\n', "\\n");
+                format!("printf '%b' {} | {command_line}", single_quote(&content))
+            }
+        };
+        match &step.cwd_relative {
+            None => self.line(&line),
+            Some(dir) => {
+                let dir = self.expand(dir, manifest_dir)?;
+                self.line(&format!("(cd {} && {line})", single_quote(&dir)))
+            }
+        }
+    }
+
+    /// Renders a `wait_until` statement as an `until` loop that polls the
+    /// command and gives up after the timeout.
+    fn wait_until(&mut self, step: &WaitUntilNode, manifest_dir: &Path) -> Result<(), Error> {
+        let command = self.expand(&step.command, manifest_dir)?;
+        let mut args = Vec::with_capacity(step.args.len());
+        for arg in &step.args {
+            args.push(self.expand(arg, manifest_dir)?);
+        }
+        let command_line = shell_command_line(&command, &args);
+        self.line("wait_start=$(date +%s)")?;
+        self.line(&format!("until {command_line}; do"))?;
+        self.depth = self.depth.saturating_add(1);
+        if let Some(timeout) = step.timeout_seconds {
+            self.line(&format!(
+                "if [ $(( $(date +%s) - wait_start )) -ge {timeout} ]; then"
+            ))?;
+            self.depth = self.depth.saturating_add(1);
+            self.line(&format!(
+                "printf '%s\\n' {} >&2",
+                single_quote(&format!("gave up waiting for {command_line}"))
+            ))?;
+            self.line("exit 1")?;
+            self.depth = self.depth.saturating_sub(1);
+            self.line("fi")?;
+        }
+        self.line(&format!("sleep {}", step.interval_seconds))?;
+        self.depth = self.depth.saturating_sub(1);
+        self.line("done")
+    }
+
+    /// Renders a `manual_step` as a comment block followed by a pause.
+    fn manual_step(&mut self, step: &ManualStepNode, manifest_dir: &Path) -> Result<(), Error> {
+        let title = self.expand(&step.title, manifest_dir)?;
+        let instructions = self.expand(&step.instructions, manifest_dir)?;
+        self.comment(&format!("MANUAL STEP: {title}"))?;
+        if let Some(assignee) = &step.assignee {
+            self.comment(&format!("  Assignee: {assignee}"))?;
+        }
+        for instruction in instructions.lines() {
+            self.comment(&format!("  {instruction}"))?;
+        }
+        self.pause(&format!("Manual step: {title}"))
+    }
+
+    /// Renders a pause that waits for the operator to press Enter.
+    fn pause(&mut self, message: &str) -> Result<(), Error> {
+        self.line(&format!(
+            "printf '%s\\n' {}",
+            single_quote(&format!("{message} (press Enter to continue)"))
+        ))?;
+        self.line("read -r _ < /dev/tty")
+    }
+
+    /// Opens a subshell that exports the variables from `env_file`.
+    fn open_env_file(&mut self, env_file: &str) -> Result<(), Error> {
+        self.line("(")?;
+        self.depth = self.depth.saturating_add(1);
+        self.line("set -a")?;
+        self.line(&format!(". ./{}", single_quote(env_file)))?;
+        self.line("set +a")
+    }
+
+    /// Renders the header comment of one `if`/`else if`/`else` branch and
+    /// enters commented mode for its body.
+    fn open_branch(&mut self, header: &str) -> Result<(), Error> {
+        self.line(header)?;
+        self.commented = self.commented.saturating_add(1);
+        self.depth = self.depth.saturating_add(1);
+        Ok(())
+    }
+
+    /// Leaves the branch opened by [`Self::open_branch`].
+    const fn close_branch(&mut self) {
+        self.commented = self.commented.saturating_sub(1);
+        self.depth = self.depth.saturating_sub(1);
+    }
+
+    /// Renders a list of crate-context statements for the crate at `manifest_dir`.
+    fn crate_stmts(&mut self, stmts: &[CrateStatement], manifest_dir: &Path) -> Result<(), Error> {
+        for stmt in stmts {
+            match stmt {
+                CrateStatement::Run(step) => self.run(step, manifest_dir)?,
+                CrateStatement::Cargo(step) => self.run(&step.to_run_step(), manifest_dir)?,
+                CrateStatement::FeaturePowerset(node) => {
+                    self.run(&feature_powerset::run_step(node), manifest_dir)?;
+                }
+                CrateStatement::ManualStep(step) => self.manual_step(step, manifest_dir)?,
+                CrateStatement::WaitUntil(step) => self.wait_until(step, manifest_dir)?,
+                CrateStatement::SnapshotMetadata(node) => self.comment(&format!(
+                    "snapshot_metadata {:?} (captured by cargo-for-each only)",
+                    node.name
+                ))?,
+                CrateStatement::Phase(node) => {
+                    self.comment(&format!("── phase {:?} ──", node.name))?;
+                }
+                CrateStatement::WaitForContinue(node) => {
+                    self.comment(&format!("WAIT FOR CONTINUE: {}", node.description))?;
+                    self.pause(&node.description)?;
+                }
+                CrateStatement::WithEnvFile(block) => {
+                    self.open_env_file(&block.env_file)?;
+                    self.crate_stmts(&block.statements, manifest_dir)?;
+                    self.close_subshell()?;
+                }
+                CrateStatement::If(block) => {
+                    self.comment(
+                        "NOTE: conditions are not evaluated by this script; uncomment the branch that applies",
+                    )?;
+                    for (i, branch) in block.branches.iter().enumerate() {
+                        let keyword = if i == 0 { "if" } else { "else if" };
+                        self.open_branch(&format!("# {keyword} {}:", branch.condition))?;
+                        self.crate_stmts(&branch.statements, manifest_dir)?;
+                        self.close_branch();
+                    }
+                    if !block.else_statements.is_empty() {
+                        self.open_branch("# else:")?;
+                        self.crate_stmts(&block.else_statements, manifest_dir)?;
+                        self.close_branch();
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Renders a list of workspace-context statements for the workspace at
+    /// `manifest_dir` with the given member crates.
+    fn workspace_stmts(
+        &mut self,
+        stmts: &[WorkspaceStatement],
+        manifest_dir: &Path,
+        member_crates: &[ResolvedCrateExecution],
+    ) -> Result<(), Error> {
+        for stmt in stmts {
+            match stmt {
+                WorkspaceStatement::Run(step) => self.run(step, manifest_dir)?,
+                WorkspaceStatement::Cargo(step) => self.run(&step.to_run_step(), manifest_dir)?,
+                WorkspaceStatement::ManualStep(step) => self.manual_step(step, manifest_dir)?,
+                WorkspaceStatement::WaitUntil(step) => self.wait_until(step, manifest_dir)?,
+                WorkspaceStatement::SnapshotMetadata(node) => self.comment(&format!(
+                    "snapshot_metadata {:?} (captured by cargo-for-each only)",
+                    node.name
+                ))?,
+                WorkspaceStatement::Phase(node) => {
+                    self.comment(&format!("── phase {:?} ──", node.name))?;
+                }
+                WorkspaceStatement::WaitForContinue(node) => {
+                    self.comment(&format!("WAIT FOR CONTINUE: {}", node.description))?;
+                    self.pause(&node.description)?;
+                }
+                WorkspaceStatement::WithEnvFile(block) => {
+                    self.open_env_file(&block.env_file)?;
+                    self.workspace_stmts(&block.statements, manifest_dir, member_crates)?;
+                    self.close_subshell()?;
+                }
+                WorkspaceStatement::ForCrateInWorkspace(block) => {
+                    // Member crates are already in intra-workspace dependency order.
+                    for member in member_crates {
+                        self.open_target("crate", &member.manifest_dir)?;
+                        self.crate_stmts(&block.statements, &member.manifest_dir)?;
+                        self.close_subshell()?;
+                    }
+                }
+                WorkspaceStatement::If(block) => {
+                    self.comment(
+                        "NOTE: conditions are not evaluated by this script; uncomment the branch that applies",
+                    )?;
+                    for (i, branch) in block.branches.iter().enumerate() {
+                        let keyword = if i == 0 { "if" } else { "else if" };
+                        self.open_branch(&format!("# {keyword} {}:", branch.condition))?;
+                        self.workspace_stmts(&branch.statements, manifest_dir, member_crates)?;
+                        self.close_branch();
+                    }
+                    if !block.else_statements.is_empty() {
+                        self.open_branch("# else:")?;
+                        self.workspace_stmts(&block.else_statements, manifest_dir, member_crates)?;
+                        self.close_branch();
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Quotes `s` for the shell using single quotes.
+fn single_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+/// Renders the shell script for a task.
+///
+/// Workspaces are emitted first, followed by standalone crates, each in
+/// dependency order.  Each target runs in its own subshell so that directory
+/// changes and env files do not leak between targets.
+///
+/// # Errors
+///
+/// Returns [`Error::CircularDependency`] if the targets cannot be ordered, or
+/// an error if writing to the script buffer fails.
+pub fn render_script(
+    task_name: &str,
+    program: &Program,
+    resolved: &ResolvedProgram,
+    state_base: &Path,
+) -> Result<String, Error> {
+    let mut writer = ScriptWriter {
+        out: String::new(),
+        depth: 0,
+        commented: 0,
+        state_base,
+    };
+    writer.line("#!/bin/sh")?;
+    writer.comment(&format!(
+        "Generated by `cargo-for-each task export-script --name {task_name}`."
+    ))?;
+    writer.comment(
+        "Targets are processed in dependency order; the script stops at the first failure.",
+    )?;
+    writer.line("set -e")?;
+
+    let ws_stmts = first_workspace_stmts(program);
+    let ws_targets: Vec<(PathBuf, Vec<PathBuf>)> = resolved
+        .workspace_executions
+        .iter()
+        .map(|w| (w.manifest_dir.clone(), w.dependencies.clone()))
+        .collect();
+    let ws_order =
+        topological_order(&ws_targets).map_err(|stuck| cycle_error(&ws_targets, &stuck))?;
+    if !ws_stmts.is_empty() {
+        for idx in ws_order {
+            if let Some(ws_exec) = resolved.workspace_executions.get(idx) {
+                writer.line("")?;
+                writer.open_target("workspace", &ws_exec.manifest_dir)?;
+                writer.workspace_stmts(ws_stmts, &ws_exec.manifest_dir, &ws_exec.member_crates)?;
+                writer.close_subshell()?;
+            }
+        }
+    }
+
+    let crate_stmts = first_crate_stmts(program);
+    let crate_targets: Vec<(PathBuf, Vec<PathBuf>)> = resolved
+        .crate_executions
+        .iter()
+        .map(|c| (c.manifest_dir.clone(), c.dependencies.clone()))
+        .collect();
+    let crate_order =
+        topological_order(&crate_targets).map_err(|stuck| cycle_error(&crate_targets, &stuck))?;
+    if !crate_stmts.is_empty() {
+        for idx in crate_order {
+            if let Some(crate_exec) = resolved.crate_executions.get(idx) {
+                writer.line("")?;
+                writer.open_target("crate", &crate_exec.manifest_dir)?;
+                writer.crate_stmts(crate_stmts, &crate_exec.manifest_dir)?;
+                writer.close_subshell()?;
+            }
+        }
+    }
+
+    Ok(writer.out)
+}
+
+/// Writes a task as a standalone shell script to a file or stdout.
+///
+/// # Errors
+///
+/// Returns an error if the task cannot be loaded, if the targets cannot be
+/// ordered, or if the output file cannot be written.
+#[instrument]
+#[expect(clippy::print_stdout, reason = "This is part of the UI, not logging")]
+pub async fn task_export_script_command(
+    params: ExportScriptParameters,
+    environment: crate::Environment,
+) -> Result<(), Error> {
+    let (program, resolved) = load_task_data(&params.name, &environment)?;
+    let state_base = state_dir_for_task(&params.name, &environment)?;
+    let script = render_script(&params.name, &program, &resolved, &state_base)?;
+    match params.output {
+        Some(path) => {
+            fs_err::write(&path, &script).map_err(Error::IoError)?;
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt as _;
+                let perms = std::fs::Permissions::from_mode(0o755);
+                fs_err::set_permissions(&path, perms).map_err(Error::IoError)?;
+            }
+        }
+        None => print!("{script}"),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::path::PathBuf;
+
+    use pretty_assertions::assert_eq;
+    use tempfile::tempdir;
+
+    use super::render_script;
+    use crate::program::ast::common::{IfBlock, ManualStepNode, Retry, RunStep};
+    use crate::program::ast::crate_ctx::{CrateCondition, CrateStatement, ForCrateBlock};
+    use crate::program::resolve::{ResolvedCrateExecution, ResolvedProgram};
+    use crate::program::{GlobalStatement, Program};
+
+    #[test]
+    fn renders_crates_in_dependency_order_with_manual_and_if()
+    -> Result<(), Box<dyn std::error::Error>> {
+        let temp = tempdir()?;
+        let program = Program {
+            statements: vec![GlobalStatement::ForCrate(ForCrateBlock {
+                statements: vec![
+                    CrateStatement::Run(RunStep {
+                        command: "cargo".to_owned(),
+                        args: vec!["build".to_owned()],
+                        stdin: None,
+                        rate_limit: None,
+                        exclusive: vec![],
+                        elevation: None,
+                        env: BTreeMap::new(),
+                        cwd_relative: None,
+                        timeout_seconds: None,
+                        retry: None,
+                    }),
+                    CrateStatement::ManualStep(ManualStepNode {
+                        title: "Check".to_owned(),
+                        instructions: "Look at it".to_owned(),
+                        assignee: None,
+                    }),
+                    CrateStatement::If(IfBlock {
+                        branches: vec![crate::program::ast::common::Branch {
+                            condition: CrateCondition::Standalone,
+                            statements: vec![CrateStatement::Run(RunStep {
+                                command: "echo".to_owned(),
+                                args: vec!["it's standalone".to_owned()],
+                                stdin: None,
+                                rate_limit: None,
+                                exclusive: vec![],
+                                elevation: None,
+                                env: BTreeMap::new(),
+                                cwd_relative: None,
+                                timeout_seconds: None,
+                                retry: None,
+                            })],
+                        }],
+                        else_statements: vec![],
+                    }),
+                ],
+            })],
+        };
+        let resolved = ResolvedProgram {
+            workspace_executions: vec![],
+            crate_executions: vec![
+                ResolvedCrateExecution {
+                    manifest_dir: PathBuf::from("/src/b"),
+                    dependencies: vec![PathBuf::from("/src/a")],
+                    package: None,
+                },
+                ResolvedCrateExecution {
+                    manifest_dir: PathBuf::from("/src/a"),
+                    dependencies: vec![],
+                    package: None,
+                },
+            ],
+        };
+        let script = render_script("demo", &program, &resolved, temp.path())?;
+        let crate_block = |dir: &str| {
+            format!(
+                "\n# ── crate {dir} ──\n(\n    cd '{dir}'\n    cargo \"build\"\n    # MANUAL STEP: Check\n    #   Look at it\n    printf '%s\\n' 'Manual step: Check (press Enter to continue)'\n    read -r _ < /dev/tty\n    # NOTE: conditions are not evaluated by this script; uncomment the branch that applies\n    # if standalone:\n        # echo \"it's standalone\"\n)\n"
+            )
+        };
+        let expected = format!(
+            "#!/bin/sh\n# Generated by `cargo-for-each task export-script --name demo`.\n# Targets are processed in dependency order; the script stops at the first failure.\nset -e\n{}{}",
+            crate_block("/src/a"),
+            crate_block("/src/b")
+        );
+        assert_eq!(script, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn renders_env_cwd_and_timeout_of_run_steps() -> Result<(), Box<dyn std::error::Error>> {
+        let temp = tempdir()?;
+        let program = Program {
+            statements: vec![GlobalStatement::ForCrate(ForCrateBlock {
+                statements: vec![CrateStatement::Run(RunStep {
+                    command: "cargo".to_owned(),
+                    args: vec!["build".to_owned()],
+                    stdin: None,
+                    rate_limit: None,
+                    exclusive: vec![],
+                    elevation: None,
+                    env: BTreeMap::from([
+                        ("CARGO_TARGET_DIR".to_owned(), "/tmp/t".to_owned()),
+                        ("RUSTFLAGS".to_owned(), "-D warnings".to_owned()),
+                    ]),
+                    cwd_relative: Some("fuzz".to_owned()),
+                    timeout_seconds: Some(600),
+                    retry: Some(Retry {
+                        retries: 2,
+                        delay_seconds: 10,
+                    }),
+                })],
+            })],
+        };
+        let resolved = ResolvedProgram {
+            workspace_executions: vec![],
+            crate_executions: vec![ResolvedCrateExecution {
+                manifest_dir: PathBuf::from("/src/a"),
+                dependencies: vec![],
+                package: None,
+            }],
+        };
+        let script = render_script("demo", &program, &resolved, temp.path())?;
+        assert!(
+            script.contains(
+                "    (cd 'fuzz' && CARGO_TARGET_DIR='/tmp/t' RUSTFLAGS='-D warnings' timeout 600 cargo \"build\")\n"
+            ),
+            "variables, directory and timeout of the step: {script}"
+        );
+        assert!(
+            script.contains("# NOTE: 2 retries after failures are not performed by this script"),
+            "retries are not exported: {script}"
+        );
+        Ok(())
+    }
+}
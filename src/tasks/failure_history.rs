@@ -0,0 +1,167 @@
+//! Targets that failed in earlier runs, for `--fail-fast-order`.
+//!
+//! When a task invocation fails, the journal entry of the invocation lists the
+//! manifest directories of the task's targets that have a failed `run` step or
+//! a timed out `wait_until` step.  With `task run all-targets
+//! --fail-fast-order`, ready targets that failed more recently, in any task,
+//! are started before the others, so feedback on a fix arrives at the
+//! beginning of a long run instead of at its end.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use super::{is_run_failed, is_wait_until_timed_out, load_task_data, state_dir_for_task};
+use crate::journal::Entry;
+use crate::program::cursor::{CursorSegment, ProgramCursor};
+use crate::program::resolve::ResolvedProgram;
+
+/// Returns `true` if a statement in `dir` or below it failed.
+#[must_use]
+pub fn failed_below(dir: &Path) -> bool {
+    if is_run_failed(dir) || is_wait_until_timed_out(dir) {
+        return true;
+    }
+    fs_err::read_dir(dir).is_ok_and(|entries| {
+        entries
+            .filter_map(Result::ok)
+            .any(|entry| entry.path().is_dir() && failed_below(&entry.path()))
+    })
+}
+
+/// Returns the manifest directories of the targets in `resolved` that have a
+/// failed statement in `state_base`.
+#[must_use]
+pub fn failed_in(resolved: &ResolvedProgram, state_base: &Path) -> Vec<PathBuf> {
+    let workspaces = resolved
+        .workspace_executions
+        .iter()
+        .enumerate()
+        .map(|(i, w)| (CursorSegment::WorkspaceIteration(i), &w.manifest_dir));
+    let crates = resolved
+        .crate_executions
+        .iter()
+        .enumerate()
+        .map(|(i, c)| (CursorSegment::CrateIteration(i), &c.manifest_dir));
+    workspaces
+        .chain(crates)
+        .filter(|(segment, _)| {
+            failed_below(&state_base.join(ProgramCursor::new().with(*segment).to_path()))
+        })
+        .map(|(_, manifest_dir)| manifest_dir.clone())
+        .collect()
+}
+
+/// Returns the manifest directories of the targets of the task `task_name`
+/// that have a failed statement; empty if the task cannot be loaded.
+#[must_use]
+pub fn failed_targets(task_name: &str, environment: &crate::Environment) -> Vec<PathBuf> {
+    let Ok((_program, resolved)) = load_task_data(task_name, environment) else {
+        return Vec::new();
+    };
+    let Ok(state_base) = state_dir_for_task(task_name, environment) else {
+        return Vec::new();
+    };
+    failed_in(&resolved, &state_base)
+}
+
+/// Computes a priority for each target from the journal `entries`, oldest
+/// first; higher values are scheduled first.
+///
+/// A target that failed in an invocation gets the (one-based) position of the
+/// latest such entry, a target that never failed gets 0.  The returned vector
+/// is indexed like `targets`, as in
+/// [`target_priorities`](super::scheduling::target_priorities).
+#[must_use]
+pub fn priorities(targets: &[(PathBuf, Vec<PathBuf>)], entries: &[Entry]) -> Vec<usize> {
+    let mut last_failure: HashMap<&Path, usize> = HashMap::new();
+    for (position, entry) in (1_usize..).zip(entries) {
+        for target in &entry.failed_targets {
+            last_failure.insert(target, position);
+        }
+    }
+    targets
+        .iter()
+        .map(|(manifest_dir, _)| {
+            last_failure
+                .get(manifest_dir.as_path())
+                .copied()
+                .unwrap_or(0)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use pretty_assertions::assert_eq;
+
+    use super::{failed_in, priorities};
+    use crate::journal::Entry;
+    use crate::program::resolve::{
+        ResolvedCrateExecution, ResolvedProgram, ResolvedWorkspaceExecution,
+    };
+
+    /// Builds a journal entry listing `failed_targets`.
+    fn entry(failed_targets: &[&str]) -> Entry {
+        Entry {
+            timestamp: "2026-01-01T00:00:00Z".to_owned(),
+            user: "alice".to_owned(),
+            host: "box".to_owned(),
+            task: Some("release".to_owned()),
+            arguments: vec![],
+            outcome: "failed: some steps failed".to_owned(),
+            failed_targets: failed_targets.iter().map(PathBuf::from).collect(),
+        }
+    }
+
+    #[test]
+    fn failed_targets_are_found_in_nested_state() -> Result<(), Box<dyn std::error::Error>> {
+        let resolved = ResolvedProgram {
+            workspace_executions: vec![ResolvedWorkspaceExecution {
+                manifest_dir: PathBuf::from("/ws"),
+                dependencies: vec![],
+                member_crates: vec![],
+            }],
+            crate_executions: vec![
+                ResolvedCrateExecution {
+                    manifest_dir: PathBuf::from("/ok"),
+                    dependencies: vec![],
+                    package: None,
+                },
+                ResolvedCrateExecution {
+                    manifest_dir: PathBuf::from("/broken"),
+                    dependencies: vec![],
+                    package: None,
+                },
+            ],
+        };
+        let temp_dir = tempfile::tempdir()?;
+        let nested = temp_dir.path().join("w0").join("s1").join("c0").join("s0");
+        fs_err::create_dir_all(&nested)?;
+        fs_err::write(nested.join("exit_status"), "101")?;
+        let ok = temp_dir.path().join("c0").join("s0");
+        fs_err::create_dir_all(&ok)?;
+        fs_err::write(ok.join("exit_status"), "0")?;
+        let timed_out = temp_dir.path().join("c1").join("s0");
+        fs_err::create_dir_all(&timed_out)?;
+        fs_err::write(timed_out.join("wait_until_timed_out"), "")?;
+
+        assert_eq!(
+            failed_in(&resolved, temp_dir.path()),
+            [PathBuf::from("/ws"), PathBuf::from("/broken")]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn recent_failures_get_higher_priorities() {
+        let targets: Vec<(PathBuf, Vec<PathBuf>)> = ["/a", "/b", "/c"]
+            .into_iter()
+            .map(|dir| (PathBuf::from(dir), vec![]))
+            .collect();
+        let entries = [entry(&["/b", "/c"]), entry(&[]), entry(&["/a", "/gone"])];
+        assert_eq!(priorities(&targets, &entries), [3, 1, 1]);
+        assert_eq!(priorities(&targets, &[]), [0, 0, 0]);
+    }
+}
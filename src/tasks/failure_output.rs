@@ -0,0 +1,178 @@
+//! The last lines of output of failed `run` steps.
+//!
+//! `run` steps are recorded with asciinema or `script` (see [`super::recorder`]).
+//! When a step fails, the tail of its terminal output is extracted from the
+//! recording and stored as
+//! `failure_output` in the step's state directory, so `task describe` can
+//! show the actual compiler error next to the exit code without having to
+//! replay each recording.
+
+use std::path::Path;
+
+use crate::error::Error;
+
+/// Name of the file holding the output tail inside a step's state directory.
+const FILE_NAME: &str = "failure_output";
+
+/// Number of lines kept from the output of a failed step.
+pub const LINES: usize = 20;
+
+/// Extracts the last `count` non-empty lines of terminal output from an
+/// asciinema recording (format version 2 or 3).
+///
+/// Escape sequences are removed, and of a line that was rewritten with a
+/// carriage return (e.g. a progress bar) only the final text is kept.  Lines
+/// that cannot be parsed as events are ignored.
+#[must_use]
+pub fn tail_from_cast(cast: &str, count: usize) -> Vec<String> {
+    let output = cast
+        .lines()
+        .skip(1)
+        .filter_map(|line| serde_json::from_str::<serde_json::Value>(line).ok())
+        .filter_map(|event| match event.as_array()?.as_slice() {
+            [_, kind, data] if kind.as_str() == Some("o") => data.as_str().map(str::to_owned),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .concat();
+    tail_from_output(&output, count)
+}
+
+/// Extracts the last `count` non-empty lines of terminal output from a
+/// typescript written by util-linux `script`, without its `Script started`
+/// and `Script done` lines.
+#[must_use]
+pub fn tail_from_typescript(typescript: &str, count: usize) -> Vec<String> {
+    let output = typescript
+        .strip_prefix("Script started on ")
+        .and_then(|rest| rest.split_once('\n'))
+        .map_or(typescript, |(_, rest)| rest);
+    let output = output
+        .rfind("Script done on ")
+        .and_then(|end| output.get(..end))
+        .unwrap_or(output);
+    tail_from_output(output, count)
+}
+
+/// Returns the last `count` non-empty lines of the terminal `output`.
+///
+/// Escape sequences are removed, and of a line that was rewritten with a
+/// carriage return only the final text is kept.
+fn tail_from_output(output: &str, count: usize) -> Vec<String> {
+    let lines: Vec<String> = strip_escape_sequences(output)
+        .split('\n')
+        .map(|line| {
+            line.trim_end_matches('\r')
+                .rsplit('\r')
+                .next()
+                .unwrap_or_default()
+                .trim_end()
+                .to_owned()
+        })
+        .filter(|line| !line.is_empty())
+        .collect();
+    let skip = lines.len().saturating_sub(count);
+    lines.into_iter().skip(skip).collect()
+}
+
+/// Removes terminal escape sequences (CSI, OSC and two-character sequences)
+/// from `s`.
+fn strip_escape_sequences(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\u{1b}' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            // CSI: parameters and intermediates up to a final byte in @..~
+            Some('[') => {
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            // OSC: terminated by BEL or ESC \
+            Some(']') => {
+                while let Some(c) = chars.next() {
+                    if c == '\u{7}' {
+                        break;
+                    }
+                    if c == '\u{1b}' && chars.peek() == Some(&'\\') {
+                        chars.next();
+                        break;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    result
+}
+
+/// Stores `lines` as the output tail of the failed step in `state_dir`.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be written.
+pub fn write(state_dir: &Path, lines: &[String]) -> Result<(), Error> {
+    let path = state_dir.join(FILE_NAME);
+    let mut content = lines.join("\n");
+    content.push('\n');
+    fs_err::write(&path, content).map_err(|e| Error::CouldNotWriteStateFile(path, e))
+}
+
+/// Reads the output tail stored for the step in `state_dir`, if any.
+#[must_use]
+pub fn read(state_dir: &Path) -> Option<Vec<String>> {
+    fs_err::read_to_string(state_dir.join(FILE_NAME))
+        .ok()
+        .map(|content| content.lines().map(str::to_owned).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::{tail_from_cast, tail_from_typescript};
+
+    #[test]
+    fn extracts_last_output_lines_from_cast() {
+        let cast = concat!(
+            r#"{"version": 2, "width": 80, "height": 24}"#,
+            "\n",
+            r#"[0.1, "o", "   Compiling foo v0.1.0\r\n"]"#,
+            "\n",
+            r#"[0.2, "i", "ignored input"]"#,
+            "\n",
+            r#"[0.3, "o", "\u001b[1m\u001b[31merror[E0425]\u001b[0m: cannot find value `x`\r\n  --> src/lib.rs:1:1"]"#,
+            "\n",
+            r#"[0.4, "o", "\r\n\r\nBuilding [=>  ] 1/2\rBuilding [===] 2/2\r\n"]"#,
+            "\n",
+        );
+        assert_eq!(
+            tail_from_cast(cast, 3),
+            vec![
+                "error[E0425]: cannot find value `x`",
+                "  --> src/lib.rs:1:1",
+                "Building [===] 2/2",
+            ]
+        );
+        assert_eq!(tail_from_cast(cast, 10).len(), 4);
+    }
+
+    #[test]
+    fn extracts_last_output_lines_from_typescript() {
+        let typescript = "Script started on 2026-01-01 00:00:00+00:00 [COMMAND=\"./run_wrapper.sh\"]\n\
+                          hello\r\n\
+                          \u{1b}[31merror\u{1b}[0m: failed\r\n\
+                          \n\
+                          Script done on 2026-01-01 00:00:01+00:00 [COMMAND_EXIT_CODE=\"1\"]\n";
+        assert_eq!(
+            tail_from_typescript(typescript, 10),
+            vec!["hello", "error: failed"]
+        );
+    }
+}
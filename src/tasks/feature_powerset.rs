@@ -0,0 +1,201 @@
+//! Checking every feature combination of a crate with `cargo hack`.
+//!
+//! A `feature_powerset` statement runs `cargo hack check --feature-powerset`
+//! like a `run` statement, recorded with asciinema.  cargo hack announces each
+//! check with a line like
+//! ``info: running `cargo check --no-default-features --features a,b` on foo (3/8)``;
+//! the feature arguments of these lines are stored with their result as
+//! `feature_sets` in the step's state directory, so `task describe` can show
+//! which combination broke.  cargo hack stops at the first failing check, so
+//! all announced checks but the last one passed, and the last one passed only
+//! if the whole step succeeded.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::Path;
+
+use crate::error::Error;
+use crate::program::ast::common::RunStep;
+use crate::program::ast::crate_ctx::FeaturePowersetNode;
+
+/// Name of the file holding the results inside a step's state directory.
+const FILE_NAME: &str = "feature_sets";
+
+/// Label of the check that uses the crate's default features.
+const DEFAULT_FEATURES: &str = "(default features)";
+
+/// The result of checking one feature combination.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeatureSet {
+    /// The feature arguments of the check, e.g. `--no-default-features --features a,b`.
+    pub features: String,
+    /// Whether the check succeeded.
+    pub passed: bool,
+}
+
+/// Returns the `run` step performing the checks of `node`.
+#[must_use]
+pub fn run_step(node: &FeaturePowersetNode) -> RunStep {
+    let mut args = vec![
+        "hack".to_owned(),
+        "check".to_owned(),
+        "--feature-powerset".to_owned(),
+    ];
+    if let Some(depth) = node.depth {
+        args.push("--depth".to_owned());
+        args.push(depth.to_string());
+    }
+    if !node.exclude_features.is_empty() {
+        args.push("--exclude-features".to_owned());
+        args.push(node.exclude_features.join(","));
+    }
+    RunStep {
+        command: "cargo".to_owned(),
+        args,
+        stdin: None,
+        rate_limit: None,
+        exclusive: vec![],
+        elevation: None,
+        env: BTreeMap::new(),
+        cwd_relative: None,
+        timeout_seconds: None,
+        retry: None,
+    }
+}
+
+/// Returns the feature arguments of a cargo command line announced by
+/// cargo hack, without the manifest path.
+fn features_of(command: &str) -> String {
+    let mut words = command.split_whitespace().skip(2);
+    let mut features = Vec::new();
+    while let Some(word) = words.next() {
+        if word == "--manifest-path" {
+            words.next();
+        } else if !word.starts_with("--manifest-path=") {
+            features.push(word);
+        }
+    }
+    if features.is_empty() {
+        DEFAULT_FEATURES.to_owned()
+    } else {
+        features.join(" ")
+    }
+}
+
+/// Extracts the checked feature combinations from the output of cargo hack;
+/// `succeeded` tells whether cargo hack exited successfully.
+#[must_use]
+pub fn parse(lines: &[String], succeeded: bool) -> Vec<FeatureSet> {
+    let mut sets: Vec<FeatureSet> = lines
+        .iter()
+        .filter_map(|line| {
+            let (_, rest) = line.trim_start().split_once("running `")?;
+            let (command, _) = rest.split_once('`')?;
+            Some(FeatureSet {
+                features: features_of(command),
+                passed: true,
+            })
+        })
+        .collect();
+    if !succeeded && let Some(last) = sets.last_mut() {
+        last.passed = false;
+    }
+    sets
+}
+
+/// Stores `sets` in `state_dir`.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be written.
+pub fn write(state_dir: &Path, sets: &[FeatureSet]) -> Result<(), Error> {
+    let path = state_dir.join(FILE_NAME);
+    let mut content = String::new();
+    for set in sets {
+        let result = if set.passed { "passed" } else { "failed" };
+        let _ignored = writeln!(content, "{result}\t{}", set.features);
+    }
+    fs_err::write(&path, content).map_err(|e| Error::CouldNotWriteStateFile(path, e))
+}
+
+/// Reads the results stored in `state_dir`, if any.
+#[must_use]
+pub fn read(state_dir: &Path) -> Option<Vec<FeatureSet>> {
+    let content = fs_err::read_to_string(state_dir.join(FILE_NAME)).ok()?;
+    Some(
+        content
+            .lines()
+            .filter_map(|line| {
+                let (result, features) = line.split_once('\t')?;
+                Some(FeatureSet {
+                    features: features.to_owned(),
+                    passed: result == "passed",
+                })
+            })
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::{FeatureSet, parse, read, run_step, write};
+    use crate::program::ast::crate_ctx::FeaturePowersetNode;
+
+    #[test]
+    fn run_step_passes_limits_to_cargo_hack() {
+        let step = run_step(&FeaturePowersetNode {
+            depth: Some(2),
+            exclude_features: vec!["nightly".to_owned(), "docs".to_owned()],
+        });
+        assert_eq!(step.command, "cargo");
+        assert_eq!(
+            step.args,
+            [
+                "hack",
+                "check",
+                "--feature-powerset",
+                "--depth",
+                "2",
+                "--exclude-features",
+                "nightly,docs"
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_marks_last_check_of_failed_run() -> Result<(), Box<dyn std::error::Error>> {
+        let output = [
+            "info: running `cargo check --manifest-path Cargo.toml` on foo (1/3)",
+            "    Finished `dev` profile [unoptimized + debuginfo] target(s) in 0.50s",
+            "info: running `cargo check --manifest-path Cargo.toml --no-default-features` on foo (2/3)",
+            "info: running `cargo check --manifest-path Cargo.toml --no-default-features --features a,b` on foo (3/3)",
+            "error[E0425]: cannot find value `x` in this scope",
+        ]
+        .map(str::to_owned);
+        let sets = parse(&output, false);
+        let set = |features: &str, passed| FeatureSet {
+            features: features.to_owned(),
+            passed,
+        };
+        assert_eq!(
+            sets,
+            [
+                set("(default features)", true),
+                set("--no-default-features", true),
+                set("--no-default-features --features a,b", false),
+            ]
+        );
+        assert!(
+            parse(&output, true).iter().all(|set| set.passed),
+            "all checks of a successful run passed"
+        );
+
+        let temp_dir = tempfile::tempdir()?;
+        assert_eq!(read(temp_dir.path()), None);
+        write(temp_dir.path(), &sets)?;
+        assert_eq!(read(temp_dir.path()), Some(sets));
+        Ok(())
+    }
+}
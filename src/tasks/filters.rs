@@ -0,0 +1,235 @@
+//! Filters for `${name.field|filter}` interpolations.
+//!
+//! A filter transforms the value looked up from a metadata snapshot before it
+//! is substituted into a `run` argument or `manual_step` text, so common
+//! transformations like case conversion or version bumps do not have to be
+//! done in a shell inside the step.  Filters can be chained, e.g.
+//! `${meta.name|snake_case|uppercase}`, and are applied left to right.
+
+use std::path::Path;
+
+use crate::error::Error;
+
+/// The names of all supported filters, in the order they are documented.
+pub const NAMES: &[&str] = &[
+    "snake_case",
+    "kebab_case",
+    "lowercase",
+    "uppercase",
+    "next_major",
+    "next_minor",
+    "next_patch",
+    "file_name",
+    "parent",
+    "relative_to_home",
+];
+
+/// Applies the filter called `name` to `value`.
+///
+/// # Errors
+///
+/// Returns an error if `name` is not a known filter or if the filter cannot be
+/// applied to `value` (e.g. a version filter on a value that is not a
+/// `major.minor.patch` version).
+pub fn apply(name: &str, value: &str) -> Result<String, Error> {
+    match name {
+        "snake_case" => Ok(join_words(value, '_')),
+        "kebab_case" => Ok(join_words(value, '-')),
+        "lowercase" => Ok(value.to_lowercase()),
+        "uppercase" => Ok(value.to_uppercase()),
+        "next_major" => bump_version(value, VersionPart::Major),
+        "next_minor" => bump_version(value, VersionPart::Minor),
+        "next_patch" => bump_version(value, VersionPart::Patch),
+        "file_name" => Path::new(value)
+            .file_name()
+            .map(|f| f.to_string_lossy().into_owned())
+            .ok_or_else(|| not_applicable(name, value)),
+        "parent" => Path::new(value)
+            .parent()
+            .map(|p| p.to_string_lossy().into_owned())
+            .ok_or_else(|| not_applicable(name, value)),
+        "relative_to_home" => Ok(relative_to_home(value, dirs::home_dir().as_deref())),
+        _ => Err(Error::UnknownInterpolationFilter(name.to_owned())),
+    }
+}
+
+/// Checks that all `${...|filter}` interpolations in the program `source`
+/// use known filters, so a typo fails when the program is parsed rather than
+/// when the step runs.
+///
+/// # Errors
+///
+/// Returns [`Error::UnknownInterpolationFilter`] for the first unknown filter.
+pub fn check_names(source: &str) -> Result<(), Error> {
+    for part in source.split("${").skip(1) {
+        let Some((reference, _)) = part.split_once('}') else {
+            continue;
+        };
+        for filter in reference.split('|').skip(1).map(str::trim) {
+            if !NAMES.contains(&filter) {
+                return Err(Error::UnknownInterpolationFilter(filter.to_owned()));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Builds the error for a filter that cannot be applied to `value`.
+fn not_applicable(filter: &str, value: &str) -> Error {
+    Error::InterpolationFilterNotApplicable(filter.to_owned(), value.to_owned())
+}
+
+/// Lowercases `value` and joins its words with `separator`; words are
+/// separated by `-`, `_`, spaces and a lowercase letter or digit followed by
+/// an uppercase letter, so `FooBar` and `Foo-Bar` both become `foo_bar`.
+fn join_words(value: &str, separator: char) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut previous: Option<char> = None;
+    for c in value.chars() {
+        if matches!(c, '-' | '_' | ' ') {
+            result.push(separator);
+        } else {
+            if c.is_uppercase() && previous.is_some_and(|p| p.is_lowercase() || p.is_ascii_digit())
+            {
+                result.push(separator);
+            }
+            result.extend(c.to_lowercase());
+        }
+        previous = Some(c);
+    }
+    result
+}
+
+/// The component of a version to increment.
+#[derive(Debug, Clone, Copy)]
+enum VersionPart {
+    /// `1.2.3` becomes `2.0.0`
+    Major,
+    /// `1.2.3` becomes `1.3.0`
+    Minor,
+    /// `1.2.3` becomes `1.2.4`
+    Patch,
+}
+
+/// Increments `part` of the `major.minor.patch` version in `value` and resets
+/// the less significant components.
+///
+/// Pre-release and build metadata suffixes are dropped.
+fn bump_version(value: &str, part: VersionPart) -> Result<String, Error> {
+    let filter = match part {
+        VersionPart::Major => "next_major",
+        VersionPart::Minor => "next_minor",
+        VersionPart::Patch => "next_patch",
+    };
+    let core = value
+        .split(['-', '+'])
+        .next()
+        .ok_or_else(|| not_applicable(filter, value))?;
+    let numbers = core
+        .split('.')
+        .map(str::parse::<u64>)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_parse_error| not_applicable(filter, value))?;
+    let [major, minor, patch] = numbers[..] else {
+        return Err(not_applicable(filter, value));
+    };
+    let overflow = || not_applicable(filter, value);
+    let (major, minor, patch) = match part {
+        VersionPart::Major => (major.checked_add(1).ok_or_else(overflow)?, 0, 0),
+        VersionPart::Minor => (major, minor.checked_add(1).ok_or_else(overflow)?, 0),
+        VersionPart::Patch => (major, minor, patch.checked_add(1).ok_or_else(overflow)?),
+    };
+    Ok(format!("{major}.{minor}.{patch}"))
+}
+
+/// Rewrites `value` to start with `~` if it lies within `home`.
+///
+/// Values outside the home directory (or without a known home directory) are
+/// returned unchanged.
+fn relative_to_home(value: &str, home: Option<&Path>) -> String {
+    home.and_then(|home| Path::new(value).strip_prefix(home).ok())
+        .map_or_else(
+            || value.to_owned(),
+            |rest| {
+                if rest.as_os_str().is_empty() {
+                    "~".to_owned()
+                } else {
+                    format!("~/{}", rest.display())
+                }
+            },
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use pretty_assertions::assert_eq;
+
+    use super::{apply, check_names, relative_to_home};
+    use crate::error::Error;
+
+    #[test]
+    fn case_filters() -> Result<(), Box<dyn std::error::Error>> {
+        assert_eq!(apply("snake_case", "my-crate-name")?, "my_crate_name");
+        assert_eq!(apply("kebab_case", "my_crate")?, "my-crate");
+        assert_eq!(apply("snake_case", "FooBar")?, "foo_bar");
+        assert_eq!(apply("snake_case", "Foo-Bar")?, "foo_bar");
+        assert_eq!(apply("kebab_case", "HttpClient2Go")?, "http-client2-go");
+        assert_eq!(apply("uppercase", "my_crate")?, "MY_CRATE");
+        assert_eq!(apply("lowercase", "MyCrate")?, "mycrate");
+        Ok(())
+    }
+
+    #[test]
+    fn version_filters() -> Result<(), Box<dyn std::error::Error>> {
+        assert_eq!(apply("next_major", "1.2.3")?, "2.0.0");
+        assert_eq!(apply("next_minor", "1.2.3")?, "1.3.0");
+        assert_eq!(apply("next_patch", "1.2.3-rc.1+build")?, "1.2.4");
+        assert!(
+            matches!(
+                apply("next_minor", "1.2"),
+                Err(Error::InterpolationFilterNotApplicable(..))
+            ),
+            "a version without a patch component should be rejected"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn path_filters() -> Result<(), Box<dyn std::error::Error>> {
+        assert_eq!(apply("file_name", "/src/foo/Cargo.toml")?, "Cargo.toml");
+        assert_eq!(apply("parent", "/src/foo/Cargo.toml")?, "/src/foo");
+        let home = Path::new("/home/user");
+        assert_eq!(
+            relative_to_home("/home/user/src/foo", Some(home)),
+            "~/src/foo"
+        );
+        assert_eq!(relative_to_home("/home/user", Some(home)), "~");
+        assert_eq!(relative_to_home("/opt/foo", Some(home)), "/opt/foo");
+        assert_eq!(relative_to_home("/opt/foo", None), "/opt/foo");
+        Ok(())
+    }
+
+    #[test]
+    fn unknown_filter() {
+        assert!(
+            matches!(
+                apply("camel_case", "x"),
+                Err(Error::UnknownInterpolationFilter(name)) if name == "camel_case"
+            ),
+            "unknown filters should be reported by name"
+        );
+        assert!(
+            check_names("run \"echo\" \"${meta.name|snake_case|uppercase}\" \"${HOME}\";").is_ok(),
+            "known filters and references without filters are accepted"
+        );
+        assert!(
+            matches!(
+                check_names("run \"echo\" \"${meta.name| snake_cas }\";"),
+                Err(Error::UnknownInterpolationFilter(name)) if name == "snake_cas"
+            ),
+            "unknown filters are found before the program runs"
+        );
+    }
+}
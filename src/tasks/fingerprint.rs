@@ -0,0 +1,261 @@
+//! The environment the steps of a task ran in.
+//!
+//! A task interrupted halfway and resumed after a toolchain update would leave
+//! a fleet in which some targets were built by one compiler and the rest by
+//! another.  After the command of a `run` step exits, a fingerprint of its
+//! environment is stored as `fingerprint.toml` in the step's state directory:
+//! the output of `rustc --version` and `cargo --version` in its working
+//! directory, a checksum of the task's program and the values of the
+//! environment variables in [`VARIABLES`].
+//!
+//! The same fingerprint, taken in the manifest directory of the target, is
+//! stored in the target's state directory (`w0/`, `c1/`) when `task run`
+//! starts the target.  When the target is started again, e.g. to resume it
+//! after a failure, the stored fingerprint is compared with the current one.
+//! If they differ, the target is blocked like a dirty one with
+//! `require_clean_git`: it is not run, other targets continue, and the
+//! differences are printed.  Running again with `--accept-environment-change`
+//! records the new environment and continues; rewinding or resetting the
+//! target starts it over in the new environment instead.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use serde::{Deserialize, Serialize};
+
+use super::named_dir_path;
+use super::program_source::checksum;
+use crate::error::Error;
+
+/// Name of the file holding the fingerprint inside a state directory.
+const FILE_NAME: &str = "fingerprint.toml";
+
+/// The environment variables recorded in fingerprints, because they change
+/// what cargo builds.
+pub const VARIABLES: &[&str] = &[
+    "RUSTUP_TOOLCHAIN",
+    "RUSTFLAGS",
+    "RUSTDOCFLAGS",
+    "CARGO_ENCODED_RUSTFLAGS",
+    "CARGO_BUILD_TARGET",
+    "CARGO_TARGET_DIR",
+];
+
+/// The environment a step ran in.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Fingerprint {
+    /// The output of `rustc --version`, if it could be run.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rustc: Option<String>,
+    /// The output of `cargo --version`, if it could be run.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cargo: Option<String>,
+    /// The checksum of the task's program.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub program: Option<String>,
+    /// The values of the set variables in [`VARIABLES`].
+    #[serde(default)]
+    pub variables: BTreeMap<String, String>,
+}
+
+/// Returns the trimmed output of `program --version` run in `dir` with `env`,
+/// or `None` if it fails.
+fn version(program: &str, dir: &Path, env: &[(String, String)]) -> Option<String> {
+    let output = Command::new(program)
+        .arg("--version")
+        .current_dir(dir)
+        .envs(env.iter().map(|(name, value)| (name, value)))
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_owned())
+}
+
+/// Returns the fingerprint of the environment in `dir`, where `env` is set in
+/// addition to the variables of this process, for the program with the
+/// checksum `program`.
+#[must_use]
+pub fn current(dir: &Path, env: &[(String, String)], program: Option<&str>) -> Fingerprint {
+    let variables = VARIABLES
+        .iter()
+        .filter_map(|name| {
+            let value = env
+                .iter()
+                .rev()
+                .find(|(set, _)| set == name)
+                .map(|(_, value)| value.clone())
+                .or_else(|| std::env::var(name).ok())?;
+            Some(((*name).to_owned(), value))
+        })
+        .collect();
+    Fingerprint {
+        rustc: version("rustc", dir, env),
+        cargo: version("cargo", dir, env),
+        program: program.map(str::to_owned),
+        variables,
+    }
+}
+
+/// Returns the checksum of the program of the task `task_name`.
+///
+/// # Errors
+///
+/// Returns an error if the task's copy of its program cannot be read.
+pub fn program_checksum(
+    task_name: &str,
+    environment: &crate::Environment,
+) -> Result<String, Error> {
+    let path = named_dir_path(task_name, environment)?.join("program.cfe");
+    let source = fs_err::read_to_string(&path).map_err(Error::CouldNotReadProgramFile)?;
+    Ok(checksum(&source))
+}
+
+/// Describes how `current` differs from `recorded`.  The program is only
+/// compared if both fingerprints have a checksum for it.
+#[must_use]
+pub fn changes(recorded: &Fingerprint, current: &Fingerprint) -> Vec<String> {
+    let show = |value: Option<&String>| value.map_or_else(|| "unknown".to_owned(), Clone::clone);
+    let mut changes = Vec::new();
+    for (name, before, after) in [
+        ("rustc", &recorded.rustc, &current.rustc),
+        ("cargo", &recorded.cargo, &current.cargo),
+    ] {
+        if before != after {
+            changes.push(format!(
+                "{name} changed from {} to {}",
+                show(before.as_ref()),
+                show(after.as_ref())
+            ));
+        }
+    }
+    if let (Some(before), Some(after)) = (&recorded.program, &current.program)
+        && before != after
+    {
+        changes.push("the program changed".to_owned());
+    }
+    for name in VARIABLES {
+        match (recorded.variables.get(*name), current.variables.get(*name)) {
+            (Some(before), Some(after)) if before != after => {
+                changes.push(format!("{name} changed from {before:?} to {after:?}"));
+            }
+            (Some(before), None) => changes.push(format!("{name} was {before:?} and is unset")),
+            (None, Some(after)) => changes.push(format!("{name} was unset and is {after:?}")),
+            _ => {}
+        }
+    }
+    changes
+}
+
+/// Stores `fingerprint` in the state directory `dir`.
+///
+/// # Errors
+///
+/// Returns an error if the fingerprint cannot be serialized or written.
+pub fn write(dir: &Path, fingerprint: &Fingerprint) -> Result<(), Error> {
+    fs_err::create_dir_all(dir).map_err(|e| Error::CouldNotCreateStateDir(dir.to_path_buf(), e))?;
+    let path = dir.join(FILE_NAME);
+    let content = toml::to_string(fingerprint).map_err(Error::CouldNotSerializeFingerprint)?;
+    fs_err::write(&path, content).map_err(|e| Error::CouldNotWriteStateFile(path, e))
+}
+
+/// What happens to a target whose environment changed since its earlier steps
+/// ran.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OnChange {
+    /// The target is blocked.
+    #[default]
+    Block,
+    /// The new environment is recorded and the target continues, with
+    /// `--accept-environment-change`.
+    Continue,
+}
+
+/// Reads the fingerprint stored in the state directory `dir`, if any.
+#[must_use]
+pub fn read(dir: &Path) -> Option<Fingerprint> {
+    let content = fs_err::read_to_string(dir.join(FILE_NAME)).ok()?;
+    toml::from_str(&content).ok()
+}
+
+/// Compares the environment of the target in `manifest_dir`, with state in
+/// `target_dir`, with the one its earlier steps ran in, and records it.
+///
+/// # Errors
+///
+/// Returns [`Error::TargetBlocked`] if the environment changed and
+/// `--accept-environment-change` was not given, or an error if the
+/// fingerprint cannot be written.
+pub fn check(
+    manifest_dir: &Path,
+    target_dir: &Path,
+    run_options: &crate::RunOptions,
+) -> Result<(), Error> {
+    let current = current(manifest_dir, &[], run_options.program_checksum.as_deref());
+    if let Some(recorded) = read(target_dir) {
+        let changes = changes(&recorded, &current);
+        if changes.is_empty() {
+            return Ok(());
+        }
+        if run_options.environment_change == OnChange::Block {
+            return Err(Error::TargetBlocked(
+                manifest_dir.to_path_buf(),
+                format!(
+                    "the environment changed since its earlier steps ran ({}); run with --accept-environment-change or rewind the target",
+                    changes.join(", ")
+                ),
+            ));
+        }
+        tracing::warn!(
+            "Continuing {} in a changed environment: {}",
+            manifest_dir.display(),
+            changes.join(", ")
+        );
+    }
+    write(target_dir, &current)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use pretty_assertions::assert_eq;
+
+    use super::{Fingerprint, changes, read, write};
+
+    #[test]
+    fn fingerprints_are_compared() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempfile::tempdir()?;
+        assert_eq!(read(temp_dir.path()), None);
+        let recorded = Fingerprint {
+            rustc: Some("rustc 1.89.0 (29483883e 2025-08-04)".to_owned()),
+            cargo: Some("cargo 1.89.0 (c24e10642 2025-06-23)".to_owned()),
+            program: Some("abc".to_owned()),
+            variables: BTreeMap::from([("RUSTFLAGS".to_owned(), "-Dwarnings".to_owned())]),
+        };
+        write(temp_dir.path(), &recorded)?;
+        assert_eq!(read(temp_dir.path()), Some(recorded.clone()));
+        assert_eq!(changes(&recorded, &recorded), Vec::<String>::new());
+
+        let current = Fingerprint {
+            rustc: Some("rustc 1.90.0 (1159e78c4 2025-09-14)".to_owned()),
+            program: None,
+            variables: BTreeMap::from([("CARGO_TARGET_DIR".to_owned(), "/tmp/target".to_owned())]),
+            ..recorded.clone()
+        };
+        assert_eq!(
+            changes(&recorded, &current),
+            vec![
+                "rustc changed from rustc 1.89.0 (29483883e 2025-08-04) to rustc 1.90.0 (1159e78c4 2025-09-14)"
+                    .to_owned(),
+                "RUSTFLAGS was \"-Dwarnings\" and is unset".to_owned(),
+                "CARGO_TARGET_DIR was unset and is \"/tmp/target\"".to_owned(),
+            ]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,220 @@
+//! The effective command line of each executed `run` step.
+//!
+//! The program only contains the command with its interpolations, and the
+//! environment of a step depends on `with_env_file` blocks and the crate's
+//! settings at the time it ran.  Before a `run` (or `feature_powerset`) step
+//! starts, the fully substituted argv (including a `sudo` wrapper of `elevate`
+//! and `run_as`), the working directory, whether it ran in the sandbox and
+//! the injected environment variables are stored as `invocation.toml` in the
+//! step's state directory.  Values of variables whose names suggest a secret
+//! are replaced by `<redacted>`.  `task status --verbose` lists the recorded
+//! invocations of every target.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+
+/// Name of the file inside the step's state directory.
+const FILE_NAME: &str = "invocation.toml";
+
+/// Parts of variable names that mark their values as secret.
+const SECRET_MARKERS: &[&str] = &[
+    "TOKEN",
+    "SECRET",
+    "PASSWORD",
+    "PASSWD",
+    "CREDENTIAL",
+    "AUTH",
+    "KEY",
+];
+
+/// Replacement for the values of secret variables.
+const REDACTED: &str = "<redacted>";
+
+/// How a step's command was started.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Invocation {
+    /// The command and its arguments after interpolation.
+    pub argv: Vec<String>,
+    /// The directory the command ran in.
+    pub working_dir: PathBuf,
+    /// Whether the command ran in the bubblewrap sandbox.
+    #[serde(default)]
+    pub sandboxed: bool,
+    /// The environment variables set for the command in addition to the
+    /// inherited ones, with secret values redacted.
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+}
+
+impl Invocation {
+    /// Describes the invocation of `command` with `args` in `working_dir`,
+    /// redacting the values of secret variables in `env`.
+    #[must_use]
+    pub fn new(
+        command: &str,
+        args: &[String],
+        working_dir: &Path,
+        sandboxed: bool,
+        env: &[(String, String)],
+    ) -> Self {
+        Self {
+            argv: std::iter::once(command.to_owned())
+                .chain(args.iter().cloned())
+                .collect(),
+            working_dir: working_dir.to_path_buf(),
+            sandboxed,
+            env: env
+                .iter()
+                .map(|(name, value)| {
+                    let value = if is_secret(name) {
+                        REDACTED.to_owned()
+                    } else {
+                        value.clone()
+                    };
+                    (name.clone(), value)
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Returns `true` if the variable `name` likely holds a secret.
+#[must_use]
+pub fn is_secret(name: &str) -> bool {
+    let name = name.to_ascii_uppercase();
+    SECRET_MARKERS.iter().any(|marker| name.contains(marker))
+}
+
+/// Writes `invocation` to the step's `state_dir`.
+///
+/// # Errors
+///
+/// Returns an error if the invocation cannot be serialized or written.
+pub fn write(state_dir: &Path, invocation: &Invocation) -> Result<(), Error> {
+    let path = state_dir.join(FILE_NAME);
+    let content = toml::to_string(invocation).map_err(Error::CouldNotSerializeInvocation)?;
+    fs_err::write(&path, content).map_err(|e| Error::CouldNotWriteStateFile(path, e))
+}
+
+/// Reads the invocation recorded in the step's `state_dir`, if there is one.
+#[must_use]
+pub fn read(state_dir: &Path) -> Option<Invocation> {
+    let content = fs_err::read_to_string(state_dir.join(FILE_NAME)).ok()?;
+    toml::from_str(&content).ok()
+}
+
+/// Returns a key that sorts cursor path components like `s2` before `s10`.
+fn component_key(name: &str) -> (String, usize) {
+    let digits = name.trim_start_matches(|c: char| !c.is_ascii_digit());
+    let prefix = name.get(..name.len().saturating_sub(digits.len()));
+    (
+        prefix.unwrap_or(name).to_owned(),
+        digits.parse().unwrap_or(usize::MAX),
+    )
+}
+
+/// Returns all invocations recorded in `dir` and below it, with the path of
+/// their state directory relative to `dir`, in program order.
+#[must_use]
+pub fn all_below(dir: &Path) -> Vec<(PathBuf, Invocation)> {
+    records_below(dir, read)
+}
+
+/// Returns the records `read` finds in `dir` and the state directories below
+/// it, with the path of their state directory relative to `dir`, in program
+/// order.
+#[must_use]
+pub fn records_below<T>(dir: &Path, read: fn(&Path) -> Option<T>) -> Vec<(PathBuf, T)> {
+    let mut found = Vec::new();
+    collect_below(dir, Path::new(""), read, &mut found);
+    found
+}
+
+/// Adds the records in `dir`, which is `relative` below the starting
+/// directory, and its subdirectories to `found`.
+fn collect_below<T>(
+    dir: &Path,
+    relative: &Path,
+    read: fn(&Path) -> Option<T>,
+    found: &mut Vec<(PathBuf, T)>,
+) {
+    if let Some(record) = read(dir) {
+        found.push((relative.to_path_buf(), record));
+    }
+    let Ok(entries) = fs_err::read_dir(dir) else {
+        return;
+    };
+    let mut subdirs: Vec<PathBuf> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .collect();
+    subdirs
+        .sort_by_key(|path| component_key(&path.file_name().unwrap_or_default().to_string_lossy()));
+    for subdir in subdirs {
+        if let Some(name) = subdir.file_name() {
+            collect_below(&subdir, &relative.join(name), read, found);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use pretty_assertions::assert_eq;
+
+    use super::{Invocation, all_below, write};
+
+    #[test]
+    fn invocations_are_recorded_without_secrets() -> Result<(), Box<dyn std::error::Error>> {
+        let env = [
+            ("RUSTFLAGS".to_owned(), "-D warnings".to_owned()),
+            ("CARGO_REGISTRY_TOKEN".to_owned(), "hunter2".to_owned()),
+            ("aws_secret_access_key".to_owned(), "xyz".to_owned()),
+        ];
+        let invocation = Invocation::new(
+            "cargo",
+            &["build".to_owned(), "--release".to_owned()],
+            Path::new("/src/a"),
+            false,
+            &env,
+        );
+        assert_eq!(invocation.argv, ["cargo", "build", "--release"]);
+        assert_eq!(
+            invocation.env.into_iter().collect::<Vec<_>>(),
+            [
+                ("CARGO_REGISTRY_TOKEN".to_owned(), "<redacted>".to_owned()),
+                ("RUSTFLAGS".to_owned(), "-D warnings".to_owned()),
+                ("aws_secret_access_key".to_owned(), "<redacted>".to_owned()),
+            ]
+        );
+
+        let temp_dir = tempfile::tempdir()?;
+        for (path, command) in [("s10", "ten"), ("s2", "two"), ("s2/c0/s0", "nested")] {
+            let dir = temp_dir.path().join(path);
+            fs_err::create_dir_all(&dir)?;
+            write(
+                &dir,
+                &Invocation::new(command, &[], Path::new("/src/a"), true, &[]),
+            )?;
+        }
+        let found: Vec<(PathBuf, String)> = all_below(temp_dir.path())
+            .into_iter()
+            .map(|(path, invocation)| (path, invocation.argv.join(" ")))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (PathBuf::from("s2"), "two".to_owned()),
+                (PathBuf::from("s2/c0/s0"), "nested".to_owned()),
+                (PathBuf::from("s10"), "ten".to_owned()),
+            ]
+        );
+        Ok(())
+    }
+}
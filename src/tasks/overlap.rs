@@ -0,0 +1,266 @@
+//! Keeping runs of different tasks out of each other's checkouts.
+//!
+//! [`super::run_lock`] keeps two runs of the same task apart, but two
+//! different tasks may still select the same workspaces, e.g. a release task
+//! and a dependency update, and interleave their edits in the same checkout.
+//! Before `task run` starts, the locks of all other tasks are checked: if one
+//! of them is held by a live process and its targets share a directory with
+//! the targets of this task (the same directory, or one inside the other, as
+//! for a workspace and one of its members), the run is refused.  `task run
+//! --force` runs anyway and only prints a warning for each such task.
+
+use std::path::{Path, PathBuf};
+
+use super::{load_task_data, run_lock, state_dir_for_task};
+use crate::error::Error;
+use crate::program::resolve::ResolvedProgram;
+
+/// Another task running on some of the same targets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Overlap {
+    /// The name of the other task.
+    pub task: String,
+    /// The process running the other task.
+    pub holder: run_lock::Holder,
+    /// The targets of this task the other task works on as well.
+    pub shared: Vec<PathBuf>,
+}
+
+/// Returns the manifest directories of all targets in `resolved`, including
+/// the member crates of workspaces.
+#[must_use]
+pub fn target_dirs(resolved: &ResolvedProgram) -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    for workspace in &resolved.workspace_executions {
+        dirs.push(workspace.manifest_dir.clone());
+        dirs.extend(
+            workspace
+                .member_crates
+                .iter()
+                .map(|member| member.manifest_dir.clone()),
+        );
+    }
+    dirs.extend(
+        resolved
+            .crate_executions
+            .iter()
+            .map(|krate| krate.manifest_dir.clone()),
+    );
+    dirs.sort();
+    dirs.dedup();
+    dirs
+}
+
+/// Returns the directories of `ours` that are equal to, inside of or contain
+/// one of `theirs`.
+#[must_use]
+pub fn shared_dirs(ours: &[PathBuf], theirs: &[PathBuf]) -> Vec<PathBuf> {
+    ours.iter()
+        .filter(|dir| {
+            theirs
+                .iter()
+                .any(|other| dir.starts_with(other) || other.starts_with(dir))
+        })
+        .cloned()
+        .collect()
+}
+
+/// Returns the other tasks with a live run sharing targets with `resolved`,
+/// the targets of the task `task_name`, sorted by task name.  Tasks that
+/// cannot be loaded are skipped with a warning.
+///
+/// # Errors
+///
+/// Returns an error if the state directory cannot be determined or read.
+pub fn find(
+    task_name: &str,
+    resolved: &ResolvedProgram,
+    environment: &crate::Environment,
+) -> Result<Vec<Overlap>, Error> {
+    let own_state = state_dir_for_task(task_name, environment)?;
+    let Some(states_dir) = own_state.parent().filter(|dir| dir.exists()) else {
+        return Ok(Vec::new());
+    };
+    let ours = target_dirs(resolved);
+    let mut overlaps = Vec::new();
+    for entry in fs_err::read_dir(states_dir)
+        .map_err(|e| Error::CouldNotReadTasksDir(states_dir.to_path_buf(), e))?
+    {
+        let entry = entry.map_err(|e| Error::CouldNotReadTasksDir(states_dir.to_path_buf(), e))?;
+        let path = entry.path();
+        let Some(other) = path.file_name().and_then(|s| s.to_str()) else {
+            continue;
+        };
+        if other == task_name {
+            continue;
+        }
+        let Some(holder) = running(&path) else {
+            continue;
+        };
+        let theirs = match load_task_data(other, environment) {
+            Ok((_program, other_resolved)) => target_dirs(&other_resolved),
+            Err(e) => {
+                tracing::warn!("Could not check the targets of the running task {other}: {e}");
+                continue;
+            }
+        };
+        let shared = shared_dirs(&ours, &theirs);
+        if !shared.is_empty() {
+            overlaps.push(Overlap {
+                task: other.to_owned(),
+                holder,
+                shared,
+            });
+        }
+    }
+    overlaps.sort_by(|a, b| a.task.cmp(&b.task));
+    Ok(overlaps)
+}
+
+/// Returns the holder of the lock in `state_base` if it may still be running.
+fn running(state_base: &Path) -> Option<run_lock::Holder> {
+    run_lock::read(state_base).filter(run_lock::Holder::may_be_alive)
+}
+
+/// Refuses to run the task `task_name` while another task runs on some of
+/// its targets, or only warns about it with `force`.
+///
+/// # Errors
+///
+/// Returns [`Error::TaskRunOverlaps`] for the first overlapping task without
+/// `force`, or an error if the other tasks cannot be checked.
+#[expect(clippy::print_stdout, reason = "This is part of the UI, not logging")]
+pub fn check(
+    task_name: &str,
+    resolved: &ResolvedProgram,
+    environment: &crate::Environment,
+    force: bool,
+) -> Result<(), Error> {
+    for overlap in find(task_name, resolved, environment)? {
+        let Some(first) = overlap.shared.first() else {
+            continue;
+        };
+        if !force {
+            return Err(Error::TaskRunOverlaps(
+                overlap.task,
+                overlap.holder.pid,
+                overlap.holder.host,
+                first.clone(),
+            ));
+        }
+        println!(
+            "Warning: task {} (process {} on {}) is running on the same targets:",
+            overlap.task, overlap.holder.pid, overlap.holder.host
+        );
+        for dir in &overlap.shared {
+            println!("  {}", dir.display());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use pretty_assertions::assert_eq;
+
+    use super::{check, find, shared_dirs};
+    use crate::error::Error;
+    use crate::program::resolve::{
+        ResolvedCrateExecution, ResolvedProgram, ResolvedWorkspaceExecution,
+    };
+    use crate::tasks::{run_lock, state_dir_for_task, store_task};
+
+    /// A program running in the workspace `/ws` with its member `/ws/a`.
+    fn workspace() -> ResolvedProgram {
+        ResolvedProgram {
+            workspace_executions: vec![ResolvedWorkspaceExecution {
+                manifest_dir: PathBuf::from("/ws"),
+                dependencies: vec![],
+                member_crates: vec![ResolvedCrateExecution {
+                    manifest_dir: PathBuf::from("/ws/a"),
+                    dependencies: vec![],
+                    package: None,
+                }],
+            }],
+            crate_executions: vec![],
+        }
+    }
+
+    /// A program running in the standalone crates `dirs`.
+    fn crates(dirs: &[&str]) -> ResolvedProgram {
+        ResolvedProgram {
+            workspace_executions: vec![],
+            crate_executions: dirs
+                .iter()
+                .map(|dir| ResolvedCrateExecution {
+                    manifest_dir: PathBuf::from(dir),
+                    dependencies: vec![],
+                    package: None,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn nested_directories_are_shared() {
+        let ours = [PathBuf::from("/ws/a"), PathBuf::from("/solo")];
+        assert_eq!(
+            shared_dirs(&ours, &[PathBuf::from("/ws")]),
+            [PathBuf::from("/ws/a")]
+        );
+        assert_eq!(
+            shared_dirs(&[PathBuf::from("/ws")], &ours),
+            [PathBuf::from("/ws")]
+        );
+        assert!(
+            shared_dirs(&ours, &[PathBuf::from("/ws/ab")]).is_empty(),
+            "directories are compared by components"
+        );
+    }
+
+    #[test]
+    fn running_tasks_on_the_same_targets_are_found() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempfile::tempdir()?;
+        let environment = crate::Environment::mock(&temp_dir)?;
+        let source = "select workspaces;\n";
+        store_task("release", None, source, &workspace(), &environment)?;
+        store_task(
+            "update",
+            None,
+            source,
+            &crates(&["/ws/a", "/solo"]),
+            &environment,
+        )?;
+        store_task(
+            "other",
+            None,
+            source,
+            &crates(&["/elsewhere"]),
+            &environment,
+        )?;
+        assert_eq!(
+            find("update", &crates(&["/ws/a", "/solo"]), &environment)?,
+            []
+        );
+
+        let _release = run_lock::acquire("release", &state_dir_for_task("release", &environment)?)?;
+        let _other = run_lock::acquire("other", &state_dir_for_task("other", &environment)?)?;
+        let overlaps = find("update", &crates(&["/ws/a", "/solo"]), &environment)?;
+        assert_eq!(
+            overlaps
+                .iter()
+                .map(|o| (o.task.as_str(), o.shared.clone()))
+                .collect::<Vec<_>>(),
+            [("release", vec![PathBuf::from("/ws/a")])]
+        );
+        let result = check("update", &crates(&["/ws/a", "/solo"]), &environment, false);
+        assert!(
+            matches!(result, Err(Error::TaskRunOverlaps(ref task, _, _, _)) if task == "release"),
+            "the run is refused: {result:?}"
+        );
+        check("update", &crates(&["/ws/a", "/solo"]), &environment, true)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,517 @@
+//! The complete set of pending work of a task as a dependency graph.
+//!
+//! [`find_next_statement`](super::find_next_statement) only answers what to
+//! run next.  [`compute`] lists every pending statement of every target in
+//! execution order together with what it waits for, so callers can show the
+//! remaining work or run it with their own scheduler.  It only reads the
+//! state directory and changes nothing.
+//!
+//! A statement waits for the cursors in [`Step::waits_for`]; such a cursor
+//! stands for all pending steps at or below it, e.g. `w1/` for every step of
+//! workspace 1 or `w0/s2/c3/` for crate 3 of a `for crate in workspace` block.
+//! Statements inside an `if` block are only known once its condition has been
+//! evaluated, so an unevaluated `if` is a single step that the following
+//! statements wait for.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use super::{
+    NextStatement, StatementAction, first_crate_stmts, first_workspace_stmts,
+    is_crate_stmt_completed, is_crate_stmts_completed, is_wait_barrier_waiting,
+    is_workspace_stmt_completed, is_workspace_stmts_completed,
+};
+use crate::program::Program;
+use crate::program::ast::crate_ctx::CrateStatement;
+use crate::program::ast::workspace_ctx::WorkspaceStatement;
+use crate::program::cursor::{CursorSegment, ProgramCursor};
+use crate::program::resolve::{ResolvedCrateExecution, ResolvedProgram};
+
+/// A pending statement and what has to finish before it can run.
+#[derive(Debug)]
+pub struct Step<'a> {
+    /// The statement and how to run it.
+    pub statement: NextStatement<'a>,
+    /// Cursors whose pending steps have to finish first.
+    pub waits_for: Vec<ProgramCursor>,
+    /// Whether this is a `wait_for_continue` barrier that was reached and
+    /// waits for `task continue`.
+    pub suspended: bool,
+}
+
+/// A workspace or standalone crate with pending statements.
+#[derive(Debug)]
+pub struct Target<'a> {
+    /// Cursor of the target (`w0/`, `c1/`).
+    pub cursor: ProgramCursor,
+    /// The directory of the workspace or crate.
+    pub manifest_dir: &'a Path,
+    /// Targets it depends on that have pending steps.
+    pub waits_for: Vec<ProgramCursor>,
+    /// Its pending statements in execution order.
+    pub steps: Vec<Step<'a>>,
+}
+
+/// All pending work of a task.
+#[derive(Debug, Default)]
+pub struct Work<'a> {
+    /// Targets with pending statements, workspaces first.
+    pub targets: Vec<Target<'a>>,
+}
+
+impl Work<'_> {
+    /// Returns `true` if no statement is pending.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.targets.is_empty()
+    }
+
+    /// Returns all pending steps in execution order.
+    pub fn steps(&self) -> impl Iterator<Item = &Step<'_>> {
+        self.targets.iter().flat_map(|target| target.steps.iter())
+    }
+
+    /// Returns `true` if a step at or below `cursor` is pending.
+    #[must_use]
+    pub fn is_pending(&self, cursor: &ProgramCursor) -> bool {
+        self.steps()
+            .any(|step| step.statement.cursor.starts_with(cursor))
+    }
+
+    /// Returns the steps that do not wait for anything pending and can run
+    /// now.
+    pub fn ready(&self) -> impl Iterator<Item = &Step<'_>> {
+        self.steps().filter(|step| {
+            !step.suspended && !step.waits_for.iter().any(|cursor| self.is_pending(cursor))
+        })
+    }
+}
+
+/// Appends the pending steps of crate statements to `steps`.
+///
+/// `after` are the cursors the first pending statement waits for.  Returns
+/// `true` if a step was added.
+fn collect_crate<'a>(
+    stmts: &'a [CrateStatement],
+    prefix: &ProgramCursor,
+    manifest_dir: &'a Path,
+    state_base: &Path,
+    env_file_paths: &[String],
+    mut after: Vec<ProgramCursor>,
+    steps: &mut Vec<Step<'a>>,
+) -> bool {
+    let before = steps.len();
+    for (i, stmt) in stmts.iter().enumerate() {
+        let cursor = prefix.clone().with(CursorSegment::Statement(i));
+        if is_crate_stmt_completed(stmt, &cursor, state_base) {
+            continue;
+        }
+        let stmt_is_barrier = matches!(stmt, CrateStatement::WaitForContinue(_));
+        let action = match stmt {
+            CrateStatement::Run(step) => StatementAction::RunCommand(step),
+            CrateStatement::Cargo(step) => StatementAction::Cargo(step),
+            CrateStatement::FeaturePowerset(node) => StatementAction::FeaturePowerset(node),
+            CrateStatement::ManualStep(step) => StatementAction::ManualStep(step),
+            CrateStatement::SnapshotMetadata(step) => StatementAction::SnapshotMetadata(step),
+            CrateStatement::WaitForContinue(node) => StatementAction::WaitForContinue(node),
+            CrateStatement::WaitUntil(node) => StatementAction::WaitUntil(node),
+            CrateStatement::Phase(_) => continue,
+            CrateStatement::If(block) => {
+                let Some(branch) = chosen_branch(&cursor, state_base) else {
+                    steps.push(Step {
+                        statement: NextStatement {
+                            cursor: cursor.clone(),
+                            manifest_dir,
+                            action: StatementAction::EvaluateCrateIf(block),
+                            env_file_paths: env_file_paths.to_vec(),
+                        },
+                        waits_for: after,
+                        suspended: false,
+                    });
+                    after = vec![cursor];
+                    continue;
+                };
+                let statements = match branch {
+                    CursorSegment::IfBranch(n) => block
+                        .branches
+                        .get(n)
+                        .map_or(&[][..], |b| b.statements.as_slice()),
+                    _ => &block.else_statements,
+                };
+                if collect_crate(
+                    statements,
+                    &cursor.clone().with(branch),
+                    manifest_dir,
+                    state_base,
+                    env_file_paths,
+                    after.clone(),
+                    steps,
+                ) {
+                    after = vec![cursor];
+                }
+                continue;
+            }
+            CrateStatement::WithEnvFile(block) => {
+                let mut inner_env_files = env_file_paths.to_vec();
+                inner_env_files.push(block.env_file.clone());
+                if collect_crate(
+                    &block.statements,
+                    &cursor.clone().with(CursorSegment::WithEnvFile),
+                    manifest_dir,
+                    state_base,
+                    &inner_env_files,
+                    after.clone(),
+                    steps,
+                ) {
+                    after = vec![cursor];
+                }
+                continue;
+            }
+        };
+        steps.push(Step {
+            statement: NextStatement {
+                cursor: cursor.clone(),
+                manifest_dir,
+                action,
+                env_file_paths: env_file_paths.to_vec(),
+            },
+            waits_for: after,
+            suspended: suspended(stmt_is_barrier, &cursor, state_base),
+        });
+        after = vec![cursor];
+    }
+    steps.len() > before
+}
+
+/// Appends the pending steps of workspace statements to `steps`.
+///
+/// `after` are the cursors the first pending statement waits for.  Returns
+/// `true` if a step was added.
+#[expect(
+    clippy::too_many_arguments,
+    reason = "mirrors collect_crate plus the member crates"
+)]
+fn collect_workspace<'a>(
+    stmts: &'a [WorkspaceStatement],
+    prefix: &ProgramCursor,
+    manifest_dir: &'a Path,
+    member_crates: &'a [ResolvedCrateExecution],
+    state_base: &Path,
+    env_file_paths: &[String],
+    mut after: Vec<ProgramCursor>,
+    steps: &mut Vec<Step<'a>>,
+) -> bool {
+    let before = steps.len();
+    for (i, stmt) in stmts.iter().enumerate() {
+        let cursor = prefix.clone().with(CursorSegment::Statement(i));
+        if is_workspace_stmt_completed(stmt, &cursor, member_crates, state_base) {
+            continue;
+        }
+        let stmt_is_barrier = matches!(stmt, WorkspaceStatement::WaitForContinue(_));
+        let action = match stmt {
+            WorkspaceStatement::Run(step) => StatementAction::RunCommand(step),
+            WorkspaceStatement::Cargo(step) => StatementAction::Cargo(step),
+            WorkspaceStatement::ManualStep(step) => StatementAction::ManualStep(step),
+            WorkspaceStatement::SnapshotMetadata(step) => StatementAction::SnapshotMetadata(step),
+            WorkspaceStatement::WaitForContinue(node) => StatementAction::WaitForContinue(node),
+            WorkspaceStatement::WaitUntil(node) => StatementAction::WaitUntil(node),
+            WorkspaceStatement::Phase(_) => continue,
+            WorkspaceStatement::If(block) => {
+                let Some(branch) = chosen_branch(&cursor, state_base) else {
+                    steps.push(Step {
+                        statement: NextStatement {
+                            cursor: cursor.clone(),
+                            manifest_dir,
+                            action: StatementAction::EvaluateWorkspaceIf(block),
+                            env_file_paths: env_file_paths.to_vec(),
+                        },
+                        waits_for: after,
+                        suspended: false,
+                    });
+                    after = vec![cursor];
+                    continue;
+                };
+                let statements = match branch {
+                    CursorSegment::IfBranch(n) => block
+                        .branches
+                        .get(n)
+                        .map_or(&[][..], |b| b.statements.as_slice()),
+                    _ => &block.else_statements,
+                };
+                if collect_workspace(
+                    statements,
+                    &cursor.clone().with(branch),
+                    manifest_dir,
+                    member_crates,
+                    state_base,
+                    env_file_paths,
+                    after.clone(),
+                    steps,
+                ) {
+                    after = vec![cursor];
+                }
+                continue;
+            }
+            WorkspaceStatement::WithEnvFile(block) => {
+                let mut inner_env_files = env_file_paths.to_vec();
+                inner_env_files.push(block.env_file.clone());
+                if collect_workspace(
+                    &block.statements,
+                    &cursor.clone().with(CursorSegment::WithEnvFile),
+                    manifest_dir,
+                    member_crates,
+                    state_base,
+                    &inner_env_files,
+                    after.clone(),
+                    steps,
+                ) {
+                    after = vec![cursor];
+                }
+                continue;
+            }
+            WorkspaceStatement::ForCrateInWorkspace(block) => {
+                let crate_map = index_by_dir(member_crates, |c| &c.manifest_dir);
+                let mut added = false;
+                for (c_idx, crate_exec) in member_crates.iter().enumerate() {
+                    let c_prefix = cursor.clone().with(CursorSegment::CrateIteration(c_idx));
+                    let mut crate_after = after.clone();
+                    crate_after.extend(pending_deps(&crate_exec.dependencies, &crate_map, |dep| {
+                        let dep_prefix = cursor.clone().with(CursorSegment::CrateIteration(dep));
+                        (!is_crate_stmts_completed(&block.statements, &dep_prefix, state_base))
+                            .then_some(dep_prefix)
+                    }));
+                    added |= collect_crate(
+                        &block.statements,
+                        &c_prefix,
+                        &crate_exec.manifest_dir,
+                        state_base,
+                        env_file_paths,
+                        crate_after,
+                        steps,
+                    );
+                }
+                if added {
+                    after = vec![cursor];
+                }
+                continue;
+            }
+        };
+        steps.push(Step {
+            statement: NextStatement {
+                cursor: cursor.clone(),
+                manifest_dir,
+                action,
+                env_file_paths: env_file_paths.to_vec(),
+            },
+            waits_for: after,
+            suspended: suspended(stmt_is_barrier, &cursor, state_base),
+        });
+        after = vec![cursor];
+    }
+    steps.len() > before
+}
+
+/// Returns the branch chosen for the `if` block at `cursor`, or `None` if its
+/// condition has not been evaluated yet.
+fn chosen_branch(cursor: &ProgramCursor, state_base: &Path) -> Option<CursorSegment> {
+    let chosen =
+        fs_err::read_to_string(state_base.join(cursor.to_path()).join("chosen_branch")).ok()?;
+    match chosen.trim() {
+        "else" => Some(CursorSegment::ElseBranch),
+        s => s.parse().ok().map(CursorSegment::IfBranch),
+    }
+}
+
+/// Returns `true` if the statement at `cursor` is a `wait_for_continue`
+/// barrier that waits for `task continue`.
+fn suspended(is_barrier: bool, cursor: &ProgramCursor, state_base: &Path) -> bool {
+    is_barrier && is_wait_barrier_waiting(&state_base.join(cursor.to_path()))
+}
+
+/// Maps each manifest directory to its index in `targets`.
+fn index_by_dir<'a, T: 'a>(
+    targets: impl IntoIterator<Item = &'a T>,
+    dir: impl Fn(&T) -> &PathBuf,
+) -> HashMap<PathBuf, usize> {
+    targets
+        .into_iter()
+        .enumerate()
+        .map(|(i, target)| (dir(target).clone(), i))
+        .collect()
+}
+
+/// Returns the cursors `pending` yields for those `dependencies` that are in
+/// `map`.
+fn pending_deps(
+    dependencies: &[PathBuf],
+    map: &HashMap<PathBuf, usize>,
+    pending: impl Fn(usize) -> Option<ProgramCursor>,
+) -> Vec<ProgramCursor> {
+    dependencies
+        .iter()
+        .filter_map(|dep| map.get(dep).copied())
+        .filter_map(pending)
+        .collect()
+}
+
+/// Computes all pending work of the task with `program` and `resolved`, whose
+/// state is stored in `state_base`.
+///
+/// Targets only wait for the targets they depend on; `task run all-targets`
+/// additionally runs standalone crates after all workspaces.
+#[must_use]
+pub fn compute<'a>(
+    program: &'a Program,
+    resolved: &'a ResolvedProgram,
+    state_base: &Path,
+) -> Work<'a> {
+    let mut work = Work::default();
+
+    let ws_stmts = first_workspace_stmts(program);
+    let ws_map = index_by_dir(&resolved.workspace_executions, |w| &w.manifest_dir);
+    for (ws_idx, ws_exec) in resolved.workspace_executions.iter().enumerate() {
+        let cursor = ProgramCursor::new().with(CursorSegment::WorkspaceIteration(ws_idx));
+        let waits_for = pending_deps(&ws_exec.dependencies, &ws_map, |dep| {
+            let dep_cursor = ProgramCursor::new().with(CursorSegment::WorkspaceIteration(dep));
+            let dep_exec = resolved.workspace_executions.get(dep)?;
+            (!is_workspace_stmts_completed(
+                ws_stmts,
+                &dep_cursor,
+                &dep_exec.member_crates,
+                state_base,
+            ))
+            .then_some(dep_cursor)
+        });
+        let mut steps = Vec::new();
+        if collect_workspace(
+            ws_stmts,
+            &cursor,
+            &ws_exec.manifest_dir,
+            &ws_exec.member_crates,
+            state_base,
+            &[],
+            waits_for.clone(),
+            &mut steps,
+        ) {
+            work.targets.push(Target {
+                cursor,
+                manifest_dir: &ws_exec.manifest_dir,
+                waits_for,
+                steps,
+            });
+        }
+    }
+
+    let crate_stmts = first_crate_stmts(program);
+    let crate_map = index_by_dir(&resolved.crate_executions, |c| &c.manifest_dir);
+    for (c_idx, crate_exec) in resolved.crate_executions.iter().enumerate() {
+        let cursor = ProgramCursor::new().with(CursorSegment::CrateIteration(c_idx));
+        let waits_for = pending_deps(&crate_exec.dependencies, &crate_map, |dep| {
+            let dep_cursor = ProgramCursor::new().with(CursorSegment::CrateIteration(dep));
+            (!is_crate_stmts_completed(crate_stmts, &dep_cursor, state_base)).then_some(dep_cursor)
+        });
+        let mut steps = Vec::new();
+        if collect_crate(
+            crate_stmts,
+            &cursor,
+            &crate_exec.manifest_dir,
+            state_base,
+            &[],
+            waits_for.clone(),
+            &mut steps,
+        ) {
+            work.targets.push(Target {
+                cursor,
+                manifest_dir: &crate_exec.manifest_dir,
+                waits_for,
+                steps,
+            });
+        }
+    }
+
+    work
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use pretty_assertions::assert_eq;
+
+    use super::compute;
+    use crate::program::parser::parse;
+    use crate::program::resolve::{
+        ResolvedCrateExecution, ResolvedProgram, ResolvedWorkspaceExecution,
+    };
+
+    #[test]
+    fn pending_steps_with_their_dependencies() -> Result<(), Box<dyn std::error::Error>> {
+        let src = r#"
+            for workspace { run "a"; for crate in workspace { run "b"; } run "c"; }
+            for crate { run "d"; }
+        "#;
+        let program = parse(src, "test.cfe").map_err(|_errors| "parse failed")?;
+        let member = |dir: &str, dependencies: Vec<PathBuf>| ResolvedCrateExecution {
+            manifest_dir: PathBuf::from(dir),
+            dependencies,
+            package: None,
+        };
+        let resolved = ResolvedProgram {
+            workspace_executions: vec![
+                ResolvedWorkspaceExecution {
+                    manifest_dir: PathBuf::from("/ws"),
+                    dependencies: vec![],
+                    member_crates: vec![
+                        member("/ws/x", vec![]),
+                        member("/ws/y", vec![PathBuf::from("/ws/x")]),
+                    ],
+                },
+                ResolvedWorkspaceExecution {
+                    manifest_dir: PathBuf::from("/other"),
+                    dependencies: vec![PathBuf::from("/ws")],
+                    member_crates: vec![],
+                },
+            ],
+            crate_executions: vec![member("/solo", vec![])],
+        };
+        let temp_dir = tempfile::tempdir()?;
+        let done = temp_dir.path().join("w0").join("s0");
+        fs_err::create_dir_all(&done)?;
+        fs_err::write(done.join("exit_status"), "0")?;
+
+        let work = compute(&program, &resolved, temp_dir.path());
+        let steps: Vec<(String, Vec<String>)> = work
+            .steps()
+            .map(|step| {
+                (
+                    step.statement.cursor.to_string(),
+                    step.waits_for.iter().map(ToString::to_string).collect(),
+                )
+            })
+            .collect();
+        let expected: Vec<(String, Vec<String>)> = [
+            ("w0/s1/c0/s0/", vec![]),
+            ("w0/s1/c1/s0/", vec!["w0/s1/c0/"]),
+            ("w0/s2/", vec!["w0/s1/"]),
+            ("w1/s0/", vec!["w0/"]),
+            ("w1/s2/", vec!["w1/s0/"]),
+            ("c0/s0/", vec![]),
+        ]
+        .into_iter()
+        .map(|(cursor, waits_for)| {
+            (
+                cursor.to_owned(),
+                waits_for.into_iter().map(str::to_owned).collect(),
+            )
+        })
+        .collect();
+        assert_eq!(steps, expected);
+
+        let ready: Vec<String> = work
+            .ready()
+            .map(|step| step.statement.cursor.to_string())
+            .collect();
+        assert_eq!(ready, ["w0/s1/c0/s0/", "c0/s0/"]);
+        Ok(())
+    }
+}
@@ -0,0 +1,186 @@
+//! Named phases of a program and the phases selected for a run.
+//!
+//! `phase "name";` markers split a block into phases, e.g. `prepare`,
+//! `verify` and `release`.  `task run all-targets --until-phase verify` runs
+//! every target up to the end of `verify`, so the results can be reviewed
+//! before `--only-phase release` continues with the irreversible part.
+//! Statements before the first marker belong to no phase and are treated as
+//! part of the first one.
+
+use std::collections::BTreeSet;
+
+use crate::error::Error;
+use crate::program::ast::crate_ctx::CrateStatement;
+use crate::program::ast::workspace_ctx::WorkspaceStatement;
+
+/// The phases a run is limited to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Selection {
+    /// Names of the selected phases.
+    names: BTreeSet<String>,
+    /// Whether statements outside of any phase are selected.
+    unnamed: bool,
+}
+
+impl Selection {
+    /// Selects the phases up to and including `last`, in the order given by
+    /// `phases`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `last` is not one of `phases`.
+    pub fn until(phases: &[String], last: &str) -> Result<Self, Error> {
+        let end = phases
+            .iter()
+            .position(|name| name == last)
+            .ok_or_else(|| unknown_phase(phases, last))?;
+        Ok(Self {
+            names: phases.iter().take(end.saturating_add(1)).cloned().collect(),
+            unnamed: true,
+        })
+    }
+
+    /// Selects only the phase `only`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `only` is not one of `phases`.
+    pub fn only(phases: &[String], only: &str) -> Result<Self, Error> {
+        if !phases.iter().any(|name| name == only) {
+            return Err(unknown_phase(phases, only));
+        }
+        Ok(Self {
+            names: BTreeSet::from([only.to_owned()]),
+            unnamed: false,
+        })
+    }
+
+    /// Returns `true` if statements in `phase` (`None` outside of any phase)
+    /// may run.
+    #[must_use]
+    pub fn contains(&self, phase: Option<&str>) -> bool {
+        phase.map_or(self.unnamed, |name| self.names.contains(name))
+    }
+}
+
+/// Returns the error for a phase name that does not occur in the program.
+fn unknown_phase(phases: &[String], name: &str) -> Error {
+    Error::UnknownPhase(name.to_owned(), phases.join(", "))
+}
+
+/// Appends `name` to `phases` unless it is already there.
+fn push_unique(phases: &mut Vec<String>, name: &str) {
+    if !phases.iter().any(|known| known == name) {
+        phases.push(name.to_owned());
+    }
+}
+
+/// Collects the phase names of crate statements in order of appearance.
+fn collect_crate(stmts: &[CrateStatement], phases: &mut Vec<String>) {
+    for stmt in stmts {
+        match stmt {
+            CrateStatement::Phase(node) => push_unique(phases, &node.name),
+            CrateStatement::If(block) => {
+                for branch in &block.branches {
+                    collect_crate(&branch.statements, phases);
+                }
+                collect_crate(&block.else_statements, phases);
+            }
+            CrateStatement::WithEnvFile(block) => collect_crate(&block.statements, phases),
+            CrateStatement::Run(_)
+            | CrateStatement::Cargo(_)
+            | CrateStatement::FeaturePowerset(_)
+            | CrateStatement::ManualStep(_)
+            | CrateStatement::SnapshotMetadata(_)
+            | CrateStatement::WaitForContinue(_)
+            | CrateStatement::WaitUntil(_) => {}
+        }
+    }
+}
+
+/// Collects the phase names of workspace statements in order of appearance.
+fn collect_workspace(stmts: &[WorkspaceStatement], phases: &mut Vec<String>) {
+    for stmt in stmts {
+        match stmt {
+            WorkspaceStatement::Phase(node) => push_unique(phases, &node.name),
+            WorkspaceStatement::If(block) => {
+                for branch in &block.branches {
+                    collect_workspace(&branch.statements, phases);
+                }
+                collect_workspace(&block.else_statements, phases);
+            }
+            WorkspaceStatement::WithEnvFile(block) => collect_workspace(&block.statements, phases),
+            WorkspaceStatement::ForCrateInWorkspace(block) => {
+                collect_crate(&block.statements, phases);
+            }
+            WorkspaceStatement::Run(_)
+            | WorkspaceStatement::Cargo(_)
+            | WorkspaceStatement::ManualStep(_)
+            | WorkspaceStatement::SnapshotMetadata(_)
+            | WorkspaceStatement::WaitForContinue(_)
+            | WorkspaceStatement::WaitUntil(_) => {}
+        }
+    }
+}
+
+/// Returns the names of all phases in the workspace and crate statements, in
+/// order of first appearance.
+#[must_use]
+pub fn names(ws_stmts: &[WorkspaceStatement], crate_stmts: &[CrateStatement]) -> Vec<String> {
+    let mut phases = Vec::new();
+    collect_workspace(ws_stmts, &mut phases);
+    collect_crate(crate_stmts, &mut phases);
+    phases
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::{Selection, names};
+    use crate::program::GlobalStatement;
+    use crate::program::parser::parse;
+
+    #[test]
+    fn names_and_selection() -> Result<(), Box<dyn std::error::Error>> {
+        let src = r#"
+            for workspace {
+                run "fetch";
+                phase "prepare";
+                for crate in workspace { phase "verify"; run "test"; }
+                phase "release";
+                run "publish";
+            }
+            for crate { phase "verify"; run "test"; phase "cleanup"; }
+        "#;
+        let program = parse(src, "test.cfe").map_err(|_errors| "parse failed")?;
+        let mut ws_stmts = &[][..];
+        let mut crate_stmts = &[][..];
+        for stmt in &program.statements {
+            match stmt {
+                GlobalStatement::ForWorkspace(block) => ws_stmts = &block.statements,
+                GlobalStatement::ForCrate(block) => crate_stmts = &block.statements,
+                _ => {}
+            }
+        }
+        let phases = names(ws_stmts, crate_stmts);
+        assert_eq!(phases, ["prepare", "verify", "release", "cleanup"]);
+
+        let until = Selection::until(&phases, "verify")?;
+        assert!(until.contains(None), "statements before the first phase");
+        assert!(until.contains(Some("prepare")), "earlier phase");
+        assert!(until.contains(Some("verify")), "last selected phase");
+        assert!(!until.contains(Some("release")), "later phase");
+
+        let only = Selection::only(&phases, "release")?;
+        assert!(!only.contains(None), "statements before the first phase");
+        assert!(!only.contains(Some("verify")), "other phase");
+        assert!(only.contains(Some("release")), "selected phase");
+
+        assert!(
+            Selection::until(&phases, "deploy").is_err(),
+            "unknown phase"
+        );
+        Ok(())
+    }
+}
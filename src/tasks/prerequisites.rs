@@ -0,0 +1,203 @@
+//! Commands a program needs, checked when a task is created.
+//!
+//! `task run` only notices a missing executable when it reaches the first
+//! statement using it, which may be on the 37th target of a long run.
+//! `task create` therefore collects the commands of all `run` and
+//! `wait_until` statements and `run` conditions, plus `sudo` for statements
+//! with `elevate` or `run_as` and `cargo-hack` for `feature_powerset`, and fails with the complete list of commands
+//! that cannot be found.  Commands containing an interpolation are only known
+//! at run time and are not checked.
+
+use std::collections::BTreeSet;
+
+use crate::program::ast::common::CommonCondition;
+use crate::program::ast::crate_ctx::{CrateCondition, CrateStatement};
+use crate::program::ast::workspace_ctx::{WorkspaceCondition, WorkspaceStatement};
+use crate::program::{GlobalStatement, Program};
+
+/// Adds `command` to `commands` unless it is only known at run time.
+fn add(commands: &mut BTreeSet<String>, command: &str) {
+    if !command.contains("${") {
+        commands.insert(command.to_owned());
+    }
+}
+
+/// Collects the commands of `run` conditions in `cond`.
+fn common_condition(cond: &CommonCondition, commands: &mut BTreeSet<String>) {
+    match cond {
+        CommonCondition::RunCommand { command, .. } => add(commands, command),
+        CommonCondition::Not(inner) => common_condition(inner, commands),
+        CommonCondition::And(conditions) | CommonCondition::Or(conditions) => {
+            for c in conditions {
+                common_condition(c, commands);
+            }
+        }
+        CommonCondition::AskUser(_)
+        | CommonCondition::FileExists(_)
+        | CommonCondition::WorkingDirectoryClean
+        | CommonCondition::GitConfigEquals { .. } => {}
+    }
+}
+
+/// Collects the commands of `run` conditions in a crate condition.
+fn crate_condition(cond: &CrateCondition, commands: &mut BTreeSet<String>) {
+    match cond {
+        CrateCondition::Common(inner) => common_condition(inner, commands),
+        CrateCondition::Not(inner) => crate_condition(inner, commands),
+        CrateCondition::And(conditions) | CrateCondition::Or(conditions) => {
+            for c in conditions {
+                crate_condition(c, commands);
+            }
+        }
+        CrateCondition::CrateType(_)
+        | CrateCondition::Standalone
+        | CrateCondition::DefaultMember
+        | CrateCondition::PublishesTo(_)
+        | CrateCondition::Tagged(_)
+        | CrateCondition::InSet(_) => {}
+    }
+}
+
+/// Collects the commands of `run` conditions in a workspace condition.
+fn workspace_condition(cond: &WorkspaceCondition, commands: &mut BTreeSet<String>) {
+    match cond {
+        WorkspaceCondition::Common(inner) => common_condition(inner, commands),
+        WorkspaceCondition::Not(inner) => workspace_condition(inner, commands),
+        WorkspaceCondition::And(conditions) | WorkspaceCondition::Or(conditions) => {
+            for c in conditions {
+                workspace_condition(c, commands);
+            }
+        }
+        WorkspaceCondition::Standalone
+        | WorkspaceCondition::HasMembers
+        | WorkspaceCondition::Virtual
+        | WorkspaceCondition::InSet(_)
+        | WorkspaceCondition::Tagged(_) => {}
+    }
+}
+
+/// Collects the commands used by crate statements.
+fn crate_stmts(stmts: &[CrateStatement], commands: &mut BTreeSet<String>) {
+    for stmt in stmts {
+        match stmt {
+            CrateStatement::Run(step) => {
+                add(commands, &step.command);
+                if step.elevation.is_some() {
+                    add(commands, "sudo");
+                }
+            }
+            CrateStatement::Cargo(_) => add(commands, "cargo"),
+            CrateStatement::FeaturePowerset(_) => {
+                add(commands, "cargo");
+                add(commands, "cargo-hack");
+            }
+            CrateStatement::WaitUntil(step) => add(commands, &step.command),
+            CrateStatement::If(block) => {
+                for branch in &block.branches {
+                    crate_condition(&branch.condition, commands);
+                    crate_stmts(&branch.statements, commands);
+                }
+                crate_stmts(&block.else_statements, commands);
+            }
+            CrateStatement::WithEnvFile(block) => crate_stmts(&block.statements, commands),
+            CrateStatement::ManualStep(_)
+            | CrateStatement::SnapshotMetadata(_)
+            | CrateStatement::WaitForContinue(_)
+            | CrateStatement::Phase(_) => {}
+        }
+    }
+}
+
+/// Collects the commands used by workspace statements.
+fn workspace_stmts(stmts: &[WorkspaceStatement], commands: &mut BTreeSet<String>) {
+    for stmt in stmts {
+        match stmt {
+            WorkspaceStatement::Run(step) => {
+                add(commands, &step.command);
+                if step.elevation.is_some() {
+                    add(commands, "sudo");
+                }
+            }
+            WorkspaceStatement::Cargo(_) => add(commands, "cargo"),
+            WorkspaceStatement::WaitUntil(step) => add(commands, &step.command),
+            WorkspaceStatement::If(block) => {
+                for branch in &block.branches {
+                    workspace_condition(&branch.condition, commands);
+                    workspace_stmts(&branch.statements, commands);
+                }
+                workspace_stmts(&block.else_statements, commands);
+            }
+            WorkspaceStatement::WithEnvFile(block) => workspace_stmts(&block.statements, commands),
+            WorkspaceStatement::ForCrateInWorkspace(block) => {
+                crate_stmts(&block.statements, commands);
+            }
+            WorkspaceStatement::ManualStep(_)
+            | WorkspaceStatement::SnapshotMetadata(_)
+            | WorkspaceStatement::WaitForContinue(_)
+            | WorkspaceStatement::Phase(_) => {}
+        }
+    }
+}
+
+/// Returns all commands `program` runs, sorted and without duplicates.
+#[must_use]
+pub fn commands(program: &Program) -> BTreeSet<String> {
+    let mut commands = BTreeSet::new();
+    for stmt in &program.statements {
+        match stmt {
+            GlobalStatement::ForWorkspace(block) => {
+                workspace_stmts(&block.statements, &mut commands);
+            }
+            GlobalStatement::ForCrate(block) => crate_stmts(&block.statements, &mut commands),
+            _ => {}
+        }
+    }
+    commands
+}
+
+/// Returns the commands of `program` that are not executable in
+/// `environment`.
+#[must_use]
+pub fn missing_commands(program: &Program, environment: &crate::Environment) -> Vec<String> {
+    commands(program)
+        .into_iter()
+        .filter(|command| !crate::utils::command_is_executable(command, environment))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::{commands, missing_commands};
+    use crate::program::parser::parse;
+
+    #[test]
+    fn commands_are_collected_from_steps_and_conditions() -> Result<(), Box<dyn std::error::Error>>
+    {
+        let src = r#"
+            for workspace {
+                if run "git" "diff" "--quiet" { run "cargo" "fmt"; }
+                for crate in workspace { run "make" "install" elevate; }
+                wait_until "curl" "-f" "https://example.com";
+            }
+            for crate { run "${meta.name}-setup"; run "cargo" "test"; }
+        "#;
+        let program = parse(src, "test.cfe").map_err(|_errors| "parse failed")?;
+        assert_eq!(
+            commands(&program).into_iter().collect::<Vec<_>>(),
+            ["cargo", "curl", "git", "make", "sudo"]
+        );
+
+        let temp_dir = tempfile::tempdir()?;
+        let environment = crate::Environment {
+            paths: vec![temp_dir.path().to_path_buf()],
+            ..crate::Environment::mock(&temp_dir)?
+        };
+        assert_eq!(
+            missing_commands(&program, &environment),
+            ["cargo", "curl", "git", "make", "sudo"]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,149 @@
+//! Tracking of the `.cfe` file a task was created from.
+//!
+//! `task create` copies the program into the task directory, so later edits
+//! of the original file do not affect the task.  To make that divergence
+//! visible, the path and a SHA-256 checksum of the original are recorded in
+//! `source.toml` next to the copy.  `task run` and `task describe` compare the
+//! checksum against the current file, and `task sync-program` replaces the
+//! copy with the current file if the recorded state is still meaningful for
+//! the new program.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha256};
+
+use crate::error::Error;
+use crate::program::Program;
+
+/// Name of the metadata file inside the task directory.
+const FILE_NAME: &str = "source.toml";
+
+/// Where a task's program came from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Record {
+    /// Absolute path of the `.cfe` file the task was created from.
+    pub path: PathBuf,
+    /// Hex-encoded SHA-256 checksum of that file at the time it was copied.
+    pub checksum: String,
+}
+
+/// How the original program file relates to the task's copy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    /// The file still has the recorded checksum.
+    Unchanged,
+    /// The file has been edited since it was copied.
+    Changed,
+    /// The file no longer exists or cannot be read.
+    Missing,
+}
+
+/// Returns the hex-encoded SHA-256 checksum of `content`.
+#[must_use]
+pub fn checksum(content: &str) -> String {
+    Sha256::digest(content.as_bytes())
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect::<Vec<_>>()
+        .concat()
+}
+
+/// Writes the source record for the task in `task_dir`.
+///
+/// # Errors
+///
+/// Returns an error if the record cannot be serialized or written.
+pub fn write(task_dir: &Path, record: &Record) -> Result<(), Error> {
+    let path = task_dir.join(FILE_NAME);
+    let content = toml::to_string(record).map_err(Error::CouldNotSerializeProgramSource)?;
+    crate::utils::write_atomically(&path, content)
+        .map_err(|e| Error::CouldNotWriteProgramSource(path, e))
+}
+
+/// Reads the source record for the task in `task_dir`.
+///
+/// Returns `None` for tasks created before sources were recorded.
+///
+/// # Errors
+///
+/// Returns an error if the record exists but cannot be read or parsed.
+pub fn read(task_dir: &Path) -> Result<Option<Record>, Error> {
+    let path = task_dir.join(FILE_NAME);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs_err::read_to_string(&path)
+        .map_err(|e| Error::CouldNotReadProgramSource(path.clone(), e))?;
+    toml::from_str(&content)
+        .map(Some)
+        .map_err(|e| Error::CouldNotParseProgramSource(path, e))
+}
+
+/// Compares the original program file against `record`.
+#[must_use]
+pub fn status(record: &Record) -> Status {
+    match fs_err::read_to_string(&record.path) {
+        Ok(content) if checksum(&content) == record.checksum => Status::Unchanged,
+        Ok(_) => Status::Changed,
+        Err(_) => Status::Missing,
+    }
+}
+
+/// Returns true if execution state recorded for `old` stays meaningful when
+/// the task switches to `new`.
+///
+/// State is keyed by statement position, so this holds as long as the
+/// `for workspace` and `for crate` bodies of `old` are prefixes of those of
+/// `new`, i.e. statements were only appended.
+#[must_use]
+pub fn is_state_compatible(old: &Program, new: &Program) -> bool {
+    super::first_workspace_stmts(new).starts_with(super::first_workspace_stmts(old))
+        && super::first_crate_stmts(new).starts_with(super::first_crate_stmts(old))
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use tempfile::tempdir;
+
+    use super::{Record, Status, checksum, is_state_compatible, read, status, write};
+    use crate::program::parser::parse;
+
+    #[test]
+    fn record_round_trip_and_status() -> Result<(), Box<dyn std::error::Error>> {
+        let temp = tempdir()?;
+        let program_path = temp.path().join("release.cfe");
+        fs_err::write(&program_path, "select crates;\n")?;
+        assert_eq!(read(temp.path())?, None);
+
+        let record = Record {
+            path: program_path.clone(),
+            checksum: checksum("select crates;\n"),
+        };
+        write(temp.path(), &record)?;
+        assert_eq!(read(temp.path())?, Some(record.clone()));
+        assert_eq!(status(&record), Status::Unchanged);
+
+        fs_err::write(&program_path, "select workspaces;\n")?;
+        assert_eq!(status(&record), Status::Changed);
+
+        fs_err::remove_file(&program_path)?;
+        assert_eq!(status(&record), Status::Missing);
+        Ok(())
+    }
+
+    #[test]
+    fn appended_statements_are_compatible() -> Result<(), Box<dyn std::error::Error>> {
+        let parse_ok = |src: &str| parse(src, "test.cfe").map_err(|_errors| "parse failed");
+        let old = parse_ok(r#"for crate { run "cargo" "build"; }"#)?;
+        let appended = parse_ok(r#"for crate { run "cargo" "build"; run "cargo" "test"; }"#)?;
+        let changed = parse_ok(r#"for crate { run "cargo" "check"; run "cargo" "build"; }"#)?;
+        assert!(is_state_compatible(&old, &old), "identical programs");
+        assert!(is_state_compatible(&old, &appended), "appended statement");
+        assert!(!is_state_compatible(&old, &changed), "inserted statement");
+        assert!(!is_state_compatible(&appended, &old), "removed statement");
+        Ok(())
+    }
+}
@@ -0,0 +1,163 @@
+//! Progress of parallel runs.
+//!
+//! With `task run all-targets --jobs N` and `N > 1` the targets run
+//! concurrently and anything printed while running a statement would be
+//! interleaved on the terminal.  Each target therefore runs in a [`scope`]:
+//! inside of it, [`say`] sends the messages of statements (`Running: ...`,
+//! retries, barriers) as events to the target's log file (see
+//! [`super::target_log`]) instead of printing them.
+//!
+//! If the output is a terminal, [`Bars`] additionally shows a line for each
+//! running target with the time it has been running and the statement it
+//! currently runs (see [`step`]).  When the target ends its line is replaced
+//! by one saying how it ended.  Without a terminal, e.g. in CI, only the
+//! start and end of each target are printed.
+//!
+//! With `--prefix-output` the output is shown as it happens instead, each
+//! line prefixed with the name of its target like `docker compose` does (see
+//! [`Target::Prefixed`] and [`crate::utils::execute_command`]).
+
+use std::fmt::Display;
+use std::io::IsTerminal as _;
+use std::path::Path;
+use std::time::Duration;
+
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+
+/// The layout of the line of a running target.
+const TEMPLATE: &str = "{spinner} [{elapsed_precise}] {prefix}: {wide_msg}";
+
+/// How often the spinner and the elapsed time are redrawn.
+const TICK: Duration = Duration::from_millis(200);
+
+tokio::task_local! {
+    /// How the output of the target the current future runs is shown.
+    static TARGET: Target;
+}
+
+/// How the output of a target of a parallel run is shown.
+#[derive(Debug, Clone)]
+pub enum Target {
+    /// Written to the target's log file, with its progress on the bar, if
+    /// there is one.
+    Logged(Option<ProgressBar>),
+    /// Printed as it happens, each line prefixed with the given name.
+    Prefixed(String),
+}
+
+/// The progress bars of the running targets.
+#[derive(Debug, Clone)]
+pub struct Bars(MultiProgress);
+
+impl Bars {
+    /// Returns the progress bars, if standard output is a terminal.
+    #[must_use]
+    pub fn new() -> Option<Self> {
+        std::io::stdout()
+            .is_terminal()
+            .then(|| Self(MultiProgress::new()))
+    }
+
+    /// Adds the line of the target in `manifest_dir` and returns its bar.
+    #[must_use]
+    pub fn start(&self, manifest_dir: &Path) -> ProgressBar {
+        let bar = self.0.add(ProgressBar::new_spinner());
+        if let Ok(style) = ProgressStyle::with_template(TEMPLATE) {
+            bar.set_style(style);
+        }
+        bar.set_prefix(manifest_dir.display().to_string());
+        bar.set_message("starting");
+        bar.enable_steady_tick(TICK);
+        bar
+    }
+
+    /// Replaces the line of the target of `bar` by `line`.
+    #[expect(clippy::print_stdout, reason = "This is part of the UI, not logging")]
+    pub fn finish(&self, bar: &ProgressBar, line: &str) {
+        bar.finish_and_clear();
+        self.0.remove(bar);
+        if self.0.println(line).is_err() {
+            println!("{line}");
+        }
+    }
+}
+
+/// Runs `future` as a target of a parallel run whose output is shown as
+/// described by `target`.
+pub async fn scope<F: Future>(target: Target, future: F) -> F::Output {
+    TARGET.scope(target, future).await
+}
+
+/// Returns the name to prefix the output lines of the current target with,
+/// if they are shown as they happen.
+#[must_use]
+pub fn prefix() -> Option<String> {
+    TARGET
+        .try_with(|target| match target {
+            Target::Prefixed(name) => Some(name.clone()),
+            Target::Logged(_) => None,
+        })
+        .ok()
+        .flatten()
+}
+
+/// Returns `line` prefixed with the name `prefix`, without its line break.
+#[must_use]
+pub fn prefixed_line(prefix: &str, line: &str) -> String {
+    format!("{prefix} | {}", line.trim_end_matches(['\n', '\r']))
+}
+
+/// Shows `statement` as the statement the current target runs, if it has a
+/// progress bar.
+pub fn step(statement: impl Display) {
+    let _ignored = TARGET.try_with(|target| {
+        if let Target::Logged(Some(bar)) = target {
+            bar.set_message(statement.to_string());
+        }
+    });
+}
+
+/// Prints `message` about a statement, prefixed with the name of the current
+/// target in a [`scope`] of [`Target::Prefixed`], or writes it to the log of
+/// the current target in a scope of [`Target::Logged`].
+#[expect(clippy::print_stdout, reason = "This is part of the UI, not logging")]
+pub fn say(message: impl Display) {
+    match TARGET.try_with(Clone::clone) {
+        Ok(Target::Logged(_)) => tracing::info!("{message}"),
+        Ok(Target::Prefixed(name)) => println!("{}", prefixed_line(&name, &message.to_string())),
+        Err(_) => println!("{message}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indicatif::ProgressBar;
+    use pretty_assertions::assert_eq;
+
+    use super::{Target, prefix, prefixed_line, scope, step};
+
+    #[tokio::test]
+    async fn steps_are_shown_on_the_bar_of_their_target() {
+        let first = ProgressBar::hidden();
+        let second = ProgressBar::hidden();
+        step("outside of any target");
+        let ((), (), (), prefixed) = futures::join!(
+            scope(Target::Logged(Some(first.clone())), async {
+                step("Running: cargo build");
+            }),
+            scope(Target::Logged(Some(second.clone())), async {
+                step("Running: cargo test");
+            }),
+            scope(Target::Logged(None), async { step("Running: cargo doc") }),
+            scope(Target::Prefixed("api ".to_owned()), async { prefix() }),
+        );
+        assert_eq!(first.message(), "Running: cargo build");
+        assert_eq!(second.message(), "Running: cargo test");
+        assert_eq!(prefixed, Some("api ".to_owned()));
+        assert_eq!(prefix(), None);
+        assert_eq!(
+            prefixed_line("api ", "Compiling api v0.1.0\r\n"),
+            "api  | Compiling api v0.1.0"
+        );
+    }
+}
@@ -0,0 +1,176 @@
+//! Throttling of `run` statements with a `rate_limit` clause.
+//!
+//! The start times of a rate-limited statement are recorded per statement (not
+//! per target) in the task's state directory, so the limit holds across all
+//! targets, independently of `--jobs`, and across separate invocations such as
+//! repeated `task run single-target`.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::error::Error;
+use crate::program::ast::common::RateLimit;
+use crate::program::cursor::{CursorSegment, ProgramCursor};
+
+/// Serializes the check-and-record step between the targets of one process.
+static LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+/// Returns the name of the file recording the start times of the statement
+/// at `cursor`.
+///
+/// Iteration indices are dropped, so the statement shares the file across all
+/// workspaces or crates, e.g. both `w0/c1/s2/` and `w3/c0/s2/` map to
+/// `w-c-s2`.
+#[must_use]
+pub fn key(cursor: &ProgramCursor) -> String {
+    cursor
+        .segments()
+        .iter()
+        .map(|segment| match segment {
+            CursorSegment::WorkspaceIteration(_) => "w".to_owned(),
+            CursorSegment::CrateIteration(_) => "c".to_owned(),
+            other => other.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Returns how long to wait before another start is allowed, given the start
+/// times (milliseconds since the epoch) that lie within the current period.
+///
+/// Returns `None` if a start is allowed right away.
+#[must_use]
+pub fn delay(starts: &[u64], now: u64, limit: RateLimit) -> Option<Duration> {
+    let period = limit.period_seconds.saturating_mul(1000);
+    let mut recent: Vec<u64> = starts
+        .iter()
+        .copied()
+        .filter(|&start| now.saturating_sub(start) < period)
+        .collect();
+    if u64::try_from(recent.len()).is_ok_and(|n| n < limit.max) {
+        return None;
+    }
+    recent.sort_unstable();
+    let excess = recent
+        .len()
+        .saturating_sub(usize::try_from(limit.max).unwrap_or(usize::MAX));
+    let oldest_blocking = recent.get(excess).copied().unwrap_or(now);
+    Some(Duration::from_millis(
+        oldest_blocking.saturating_add(period).saturating_sub(now),
+    ))
+}
+
+/// Returns the current time in milliseconds since the epoch.
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| u64::try_from(d.as_millis()).unwrap_or(u64::MAX))
+}
+
+/// Returns the path of the file recording the starts of the statement at
+/// `cursor`.
+fn file_path(state_base: &Path, cursor: &ProgramCursor) -> PathBuf {
+    state_base.join("rate_limit").join(key(cursor))
+}
+
+/// Reads the recorded start times from `path`; a missing file means none.
+fn read_starts(path: &Path) -> Vec<u64> {
+    fs_err::read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| line.trim().parse().ok())
+        .collect()
+}
+
+/// Waits until the statement at `cursor` may start again under `limit`, then
+/// records the start.
+///
+/// # Errors
+///
+/// Returns an error if the start times cannot be written.
+#[expect(clippy::print_stdout, reason = "This is part of the UI, not logging")]
+pub async fn acquire(
+    limit: RateLimit,
+    cursor: &ProgramCursor,
+    state_base: &Path,
+) -> Result<(), Error> {
+    let path = file_path(state_base, cursor);
+    loop {
+        let guard = LOCK.lock().await;
+        let now = now_millis();
+        let starts = read_starts(&path);
+        match delay(&starts, now, limit) {
+            None => {
+                let period = limit.period_seconds.saturating_mul(1000);
+                let mut kept: Vec<String> = starts
+                    .into_iter()
+                    .filter(|&start| now.saturating_sub(start) < period)
+                    .map(|start| start.to_string())
+                    .collect();
+                kept.push(now.to_string());
+                if let Some(parent) = path.parent() {
+                    fs_err::create_dir_all(parent)
+                        .map_err(|e| Error::CouldNotCreateStateDir(parent.to_path_buf(), e))?;
+                }
+                fs_err::write(&path, kept.join("\n"))
+                    .map_err(|e| Error::CouldNotWriteStateFile(path.clone(), e))?;
+                drop(guard);
+                return Ok(());
+            }
+            Some(wait) => {
+                drop(guard);
+                println!(
+                    "Rate limit of {} per {}s reached for {}, waiting {}s",
+                    limit.max,
+                    limit.period_seconds,
+                    cursor.to_path_string(),
+                    wait.as_secs().saturating_add(1)
+                );
+                tokio::time::sleep(wait).await;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use pretty_assertions::assert_eq;
+
+    use super::{delay, key};
+    use crate::program::ast::common::RateLimit;
+    use crate::program::cursor::{CursorSegment, ProgramCursor};
+
+    #[test]
+    fn key_ignores_iteration_indices() {
+        let cursor = |w, c| {
+            ProgramCursor::new()
+                .with(CursorSegment::WorkspaceIteration(w))
+                .with(CursorSegment::Statement(1))
+                .with(CursorSegment::CrateIteration(c))
+                .with(CursorSegment::Statement(2))
+        };
+        assert_eq!(key(&cursor(0, 1)), "w-s1-c-s2");
+        assert_eq!(key(&cursor(0, 1)), key(&cursor(3, 0)));
+    }
+
+    #[test]
+    fn delay_until_oldest_start_leaves_period() {
+        let limit = RateLimit {
+            max: 2,
+            period_seconds: 60,
+        };
+        assert_eq!(delay(&[], 100_000, limit), None);
+        assert_eq!(delay(&[90_000], 100_000, limit), None);
+        assert_eq!(
+            delay(&[10_000, 30_000, 90_000], 100_000, limit),
+            None,
+            "starts older than the period do not count"
+        );
+        assert_eq!(
+            delay(&[90_000, 50_000], 100_000, limit),
+            Some(Duration::from_millis(10_000))
+        );
+    }
+}
@@ -0,0 +1,200 @@
+//! Recording the terminal output of `run` and `manual_step` steps.
+//!
+//! By default each step runs inside `asciinema record`, which keeps a
+//! replayable recording and lets `task describe` show the tail of the output
+//! of failed steps.  On machines without asciinema, util-linux `script` is
+//! used instead, which writes a plain typescript; without either, steps run
+//! unrecorded and failed steps are shown with their exit code only.
+//!
+//! The `record <recorder>;` program statement picks a recorder for a task
+//! (`auto`, the default, detects the first available one at run time), and
+//! `task run --no-record` turns recording off for one invocation.  Explicitly
+//! choosing a recorder that is not installed is an error.
+
+use std::path::Path;
+use std::process::Command;
+
+use super::failure_output;
+use crate::error::Error;
+use crate::program::{GlobalStatement, Program};
+
+/// Name of the asciinema recording inside a step's state directory.
+const CAST_FILE: &str = "asciinema.cast";
+
+/// Name of the `script` typescript inside a step's state directory.
+const TYPESCRIPT_FILE: &str = "typescript";
+
+/// How the terminal output of steps is recorded.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Recorder {
+    /// Use asciinema if it is installed, otherwise `script`, otherwise nothing.
+    #[default]
+    Auto,
+    /// Record with `asciinema record`.
+    Asciinema,
+    /// Record with util-linux `script`.
+    Script,
+    /// Do not record.
+    None,
+}
+
+impl Recorder {
+    /// Returns the executable of the recorder, if it has one.
+    #[must_use]
+    pub const fn executable(self) -> Option<&'static str> {
+        match self {
+            Self::Asciinema => Some("asciinema"),
+            Self::Script => Some("script"),
+            Self::Auto | Self::None => None,
+        }
+    }
+
+    /// Returns the recorder to use in `environment`: the first installed one
+    /// for [`Recorder::Auto`], or `self` if it is installed.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::RecorderNotAvailable`] if `self` is a recorder that
+    /// is not installed.
+    pub fn detect(self, environment: &crate::Environment) -> Result<Self, Error> {
+        let installed = |recorder: Self| {
+            recorder
+                .executable()
+                .is_none_or(|exe| crate::utils::command_is_executable(exe, environment))
+        };
+        match self {
+            Self::Auto => Ok([Self::Asciinema, Self::Script]
+                .into_iter()
+                .find(|recorder| installed(*recorder))
+                .unwrap_or(Self::None)),
+            recorder if installed(recorder) => Ok(recorder),
+            recorder => Err(Error::RecorderNotAvailable(
+                recorder.executable().unwrap_or_default().to_owned(),
+            )),
+        }
+    }
+
+    /// Builds the command running the script `wrapper` for a `run` step
+    /// with state in `state_dir`.  With `headless`, asciinema does not
+    /// expect a terminal.
+    #[must_use]
+    pub fn run_command(self, wrapper: &Path, state_dir: &Path, headless: bool) -> Command {
+        match self {
+            Self::Asciinema => {
+                let mut cmd = Command::new("asciinema");
+                cmd.arg("record").arg("--overwrite");
+                if headless {
+                    cmd.arg("--headless");
+                }
+                cmd.arg("-q")
+                    .arg("-c")
+                    .arg(wrapper)
+                    .arg(state_dir.join(CAST_FILE));
+                cmd
+            }
+            Self::Script => {
+                let mut cmd = Command::new("script");
+                cmd.arg("-q")
+                    .arg("-e")
+                    .arg("-c")
+                    .arg(wrapper)
+                    .arg(state_dir.join(TYPESCRIPT_FILE));
+                cmd
+            }
+            Self::Auto | Self::None => Command::new(wrapper),
+        }
+    }
+
+    /// Builds the command starting the interactive shell of a `manual_step`
+    /// with state in `state_dir`.
+    #[must_use]
+    pub fn shell_command(self, state_dir: &Path, headless: bool) -> Command {
+        match self {
+            Self::Asciinema => {
+                let mut cmd = Command::new("asciinema");
+                cmd.arg("record");
+                if headless {
+                    cmd.arg("--headless");
+                }
+                cmd.arg("-q").arg(state_dir.join(CAST_FILE));
+                cmd
+            }
+            Self::Script => {
+                let mut cmd = Command::new("script");
+                cmd.arg("-q").arg(state_dir.join(TYPESCRIPT_FILE));
+                cmd
+            }
+            Self::Auto | Self::None => {
+                Command::new(std::env::var_os("SHELL").unwrap_or_else(|| "sh".into()))
+            }
+        }
+    }
+}
+
+/// Returns the recorder chosen by the last `record` statement of `program`,
+/// or [`Recorder::Auto`].
+#[must_use]
+pub fn from_program(program: &Program) -> Recorder {
+    program
+        .statements
+        .iter()
+        .rev()
+        .find_map(|stmt| match stmt {
+            GlobalStatement::Record(recorder) => Some(*recorder),
+            _ => None,
+        })
+        .unwrap_or_default()
+}
+
+/// Returns the recording of the step in `state_dir`, if it was recorded.
+#[must_use]
+pub fn recording(state_dir: &Path) -> Option<std::path::PathBuf> {
+    [CAST_FILE, TYPESCRIPT_FILE]
+        .into_iter()
+        .map(|file| state_dir.join(file))
+        .find(|path| path.exists())
+}
+
+/// Returns the last `count` lines of output recorded for the step in
+/// `state_dir`, or `None` if it was not recorded.
+#[must_use]
+pub fn output_tail(state_dir: &Path, count: usize) -> Option<Vec<String>> {
+    if let Ok(cast) = fs_err::read_to_string(state_dir.join(CAST_FILE)) {
+        return Some(failure_output::tail_from_cast(&cast, count));
+    }
+    fs_err::read_to_string(state_dir.join(TYPESCRIPT_FILE))
+        .ok()
+        .map(|typescript| failure_output::tail_from_typescript(&typescript, count))
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::Recorder;
+
+    #[test]
+    fn recorders_are_detected_on_the_path() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempfile::tempdir()?;
+        let mut environment = crate::Environment::mock(&temp_dir)?;
+        let bin_dir = temp_dir.path().join("bin");
+        environment.paths = vec![bin_dir.clone()];
+        assert_eq!(Recorder::Auto.detect(&environment)?, Recorder::None);
+
+        let script = bin_dir.join("script");
+        fs_err::write(&script, "#!/bin/sh\n")?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt as _;
+            fs_err::set_permissions(&script, std::fs::Permissions::from_mode(0o755))?;
+        }
+        assert_eq!(Recorder::Auto.detect(&environment)?, Recorder::Script);
+        assert_eq!(Recorder::None.detect(&environment)?, Recorder::None);
+        assert!(
+            Recorder::Asciinema.detect(&environment).is_err(),
+            "an explicitly chosen recorder has to be installed"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,121 @@
+//! Guard rails for removing things tasks depend on.
+//!
+//! A task keeps the targets it was resolved against, so removing a
+//! registered workspace or crate does not change the tasks running on it;
+//! they only become stale.  `target remove` therefore lists the tasks using
+//! the targets it is about to remove and only goes ahead with `--force`, with
+//! `--cascade` (which removes these tasks as well), or after the operator
+//! confirmed it on the terminal.  `task remove` likewise shows what it
+//! removes and refuses to remove a task while it is being run.
+
+use std::io::{self, IsTerminal as _, Write as _};
+use std::path::PathBuf;
+
+use super::{load_task_data, overlap, task_names};
+use crate::error::Error;
+
+/// A task running on some of the targets about to be removed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reference {
+    /// The name of the task.
+    pub task: String,
+    /// The targets of the task that would be removed.
+    pub targets: Vec<PathBuf>,
+}
+
+/// Returns the tasks whose targets include some of `dirs`, sorted by name.
+/// Tasks that cannot be loaded are skipped with a warning.
+///
+/// # Errors
+///
+/// Returns an error if the tasks cannot be listed.
+pub fn tasks_using(
+    dirs: &[PathBuf],
+    environment: &crate::Environment,
+) -> Result<Vec<Reference>, Error> {
+    let mut references = Vec::new();
+    for task in task_names(environment)? {
+        let resolved = match load_task_data(&task, environment) {
+            Ok((_program, resolved)) => resolved,
+            Err(e) => {
+                tracing::warn!("Could not check the targets of task {task}: {e}");
+                continue;
+            }
+        };
+        let theirs = overlap::target_dirs(&resolved);
+        let targets: Vec<PathBuf> = dirs
+            .iter()
+            .filter(|dir| theirs.contains(dir))
+            .cloned()
+            .collect();
+        if !targets.is_empty() {
+            references.push(Reference { task, targets });
+        }
+    }
+    Ok(references)
+}
+
+/// Asks `question` on the terminal and returns whether it was answered with
+/// yes.  Without a terminal to ask on, the answer is no.
+///
+/// # Errors
+///
+/// Returns an error if the terminal cannot be read or written.
+#[expect(clippy::print_stdout, reason = "This is part of the UI, not logging")]
+pub fn confirm(question: &str) -> Result<bool, Error> {
+    if !io::stdin().is_terminal() {
+        return Ok(false);
+    }
+    print!("{question} (y/N) ");
+    io::stdout().flush().map_err(Error::IoError)?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer).map_err(Error::IoError)?;
+    let answer = answer.trim();
+    Ok(answer.eq_ignore_ascii_case("y") || answer.eq_ignore_ascii_case("yes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use pretty_assertions::assert_eq;
+
+    use super::{Reference, tasks_using};
+    use crate::program::resolve::{ResolvedCrateExecution, ResolvedProgram};
+    use crate::tasks::store_task;
+
+    #[test]
+    fn tasks_using_removed_targets_are_found() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempfile::tempdir()?;
+        let environment = crate::Environment::mock(&temp_dir)?;
+        let crates = |dirs: &[&str]| ResolvedProgram {
+            workspace_executions: vec![],
+            crate_executions: dirs
+                .iter()
+                .map(|dir| ResolvedCrateExecution {
+                    manifest_dir: PathBuf::from(dir),
+                    dependencies: vec![],
+                    package: None,
+                })
+                .collect(),
+        };
+        let source = "select crates;\n";
+        store_task(
+            "release",
+            None,
+            source,
+            &crates(&["/a", "/b"]),
+            &environment,
+        )?;
+        store_task("update", None, source, &crates(&["/c"]), &environment)?;
+        assert_eq!(
+            tasks_using(&[PathBuf::from("/b"), PathBuf::from("/d")], &environment)?,
+            [Reference {
+                task: "release".to_owned(),
+                targets: vec![PathBuf::from("/b")],
+            }]
+        );
+        assert_eq!(tasks_using(&[PathBuf::from("/d")], &environment)?, []);
+        Ok(())
+    }
+}
@@ -0,0 +1,1003 @@
+//! One document summarizing the results of several tasks.
+//!
+//! Release trains are often split into separate tasks, e.g. one preparing the
+//! crates, one publishing them and one announcing the release.  `task report
+//! --tasks prepare,publish,announce` determines the status of every target of
+//! each task and prints a single table with one row per target, sorted by
+//! manifest directory, and one column per task, as plain text, Markdown or
+//! HTML.  Below the table, every recorded approval of a manual step or
+//! barrier is listed with the operator who gave it and when.
+//!
+//! `task report --name <task>` goes into detail on a single task instead:
+//! every executed `run` step of every target gets a row with its result,
+//! how long it took, how many attempts it needed, the command line it ran and
+//! links to its recording and output logs, so a finished run can be shared
+//! as one file.  Below the steps, the targets are listed by the wall-clock
+//! time their steps took, longest first, to show which of them dominate the
+//! run.
+
+use std::borrow::Borrow;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use super::approval::{self, Approval};
+use super::clean_git::blocked_reason;
+use super::failure_history::failed_below;
+use super::invocation::{self, records_below};
+use super::{
+    cancellation, first_crate_stmts, first_workspace_stmts, is_standalone_crate_completed,
+    is_workspace_completed, load_task_data, recorder, state_dir_for_task, step_duration, step_logs,
+    step_timeout,
+};
+use crate::error::Error;
+use crate::formatting::Style;
+use crate::porcelain::OutputFormat;
+use crate::program::cursor::{CursorSegment, ProgramCursor};
+
+/// Output format of a report.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Format {
+    /// Aligned columns for the terminal.
+    #[default]
+    Text,
+    /// A Markdown table, e.g. for an issue or a pull request.
+    Markdown,
+    /// A standalone HTML document.
+    Html,
+}
+
+impl Format {
+    /// Returns the report format for the output format `format` chosen with
+    /// the global `--format` option.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::UnsupportedOutputFormat`] for JSON and `dot`, since
+    /// `command` only renders reports for people.
+    pub fn for_command(format: OutputFormat, command: &str) -> Result<Self, Error> {
+        match format {
+            OutputFormat::Text => Ok(Self::Text),
+            OutputFormat::Markdown => Ok(Self::Markdown),
+            OutputFormat::Html => Ok(Self::Html),
+            OutputFormat::Json | OutputFormat::Dot => {
+                Err(Error::UnsupportedOutputFormat(format, command.to_owned()))
+            }
+        }
+    }
+}
+
+/// Status of one target in one task.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    /// All statements for the target are completed.
+    Done,
+    /// A `run` step failed or a `wait_until` step timed out.
+    Failed,
+    /// The target was not started because its git working tree is not clean.
+    Blocked,
+    /// Some statements ran, but not all of them.
+    Started,
+    /// No statement ran yet.
+    Pending,
+}
+
+impl Status {
+    /// All statuses, from the most to the least advanced.
+    pub const ALL: [Self; 5] = [
+        Self::Done,
+        Self::Failed,
+        Self::Blocked,
+        Self::Started,
+        Self::Pending,
+    ];
+
+    /// Returns the label shown in reports.
+    #[must_use]
+    pub const fn label(self) -> &'static str {
+        match self {
+            Self::Done => "done",
+            Self::Failed => "failed",
+            Self::Blocked => "blocked",
+            Self::Started => "started",
+            Self::Pending => "pending",
+        }
+    }
+}
+
+/// The statuses of one target in each task of a report.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Row {
+    /// The manifest directory of the target.
+    pub manifest_dir: PathBuf,
+    /// The status in each task, in the order of the tasks; `None` if the
+    /// target is not part of the task.
+    pub statuses: Vec<Option<Status>>,
+}
+
+/// An approval given in one of the reported tasks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApprovalRow {
+    /// The task the approved step belongs to.
+    pub task: String,
+    /// The manifest directory of the target.
+    pub manifest_dir: PathBuf,
+    /// The cursor of the approved step.
+    pub cursor: String,
+    /// Who answered and when.
+    pub approval: Approval,
+}
+
+/// One executed `run` step of a task.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StepRow {
+    /// The manifest directory of the target.
+    pub manifest_dir: PathBuf,
+    /// The cursor of the step.
+    pub cursor: String,
+    /// The command line the step ran, if it was recorded.
+    pub command: String,
+    /// `ok`, the exit code, or why the step did not finish.
+    pub result: String,
+    /// Whether the step failed.
+    pub failed: bool,
+    /// How long the last attempt ran, if that was recorded.
+    pub duration: Option<Duration>,
+    /// When the last attempt started and finished, if that was recorded.
+    pub times: Option<(SystemTime, SystemTime)>,
+    /// How many attempts the step needed, for steps with `retries`.
+    pub attempts: Option<u32>,
+    /// The recording and output logs of the step.
+    pub files: Vec<PathBuf>,
+}
+
+/// The time the steps of one target took.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TargetTime {
+    /// The manifest directory of the target.
+    pub manifest_dir: PathBuf,
+    /// From the start of its first step to the end of its last one,
+    /// including the time between the steps.
+    pub wall_clock: Duration,
+    /// The sum of the durations of its steps.
+    pub step_time: Duration,
+    /// The number of its steps.
+    pub steps: usize,
+}
+
+/// Returns the time from the start of the first of `steps` to the end of the
+/// last one, if their times were recorded.
+#[must_use]
+pub fn wall_clock<S: Borrow<StepRow>>(steps: impl IntoIterator<Item = S>) -> Option<Duration> {
+    let (start, end) = steps
+        .into_iter()
+        .filter_map(|step| step.borrow().times)
+        .reduce(|(start, end), (started, finished)| (start.min(started), end.max(finished)))?;
+    Some(end.duration_since(start).unwrap_or_default())
+}
+
+/// Adds up the times of `steps` by target, the targets with the longest
+/// wall-clock time first.  Targets whose steps have no recorded times are
+/// left out.
+#[must_use]
+pub fn target_times(steps: &[StepRow]) -> Vec<TargetTime> {
+    let mut by_target: BTreeMap<&Path, Vec<&StepRow>> = BTreeMap::new();
+    for step in steps {
+        by_target.entry(&step.manifest_dir).or_default().push(step);
+    }
+    let mut times: Vec<TargetTime> = by_target
+        .into_iter()
+        .filter_map(|(manifest_dir, steps)| {
+            Some(TargetTime {
+                manifest_dir: manifest_dir.to_path_buf(),
+                wall_clock: wall_clock(steps.iter().copied())?,
+                step_time: steps.iter().filter_map(|step| step.duration).sum(),
+                steps: steps.len(),
+            })
+        })
+        .collect();
+    times.sort_by_key(|time| std::cmp::Reverse(time.wall_clock));
+    times
+}
+
+/// Renders `times` as an aligned plain text table with durations in `style`.
+#[must_use]
+pub fn render_times(times: &[TargetTime], style: Style) -> String {
+    let mut lines = vec![
+        ["target", "wall-clock", "in steps", "steps"]
+            .map(str::to_owned)
+            .to_vec(),
+    ];
+    lines.extend(times.iter().map(|time| time_cells(time, style).to_vec()));
+    aligned(&lines)
+}
+
+/// Returns the cells of the target, wall-clock time, step time and number of
+/// steps of `time`.
+fn time_cells(time: &TargetTime, style: Style) -> [String; 4] {
+    [
+        time.manifest_dir.display().to_string(),
+        style.duration(time.wall_clock),
+        style.duration(time.step_time),
+        time.steps.to_string(),
+    ]
+}
+
+impl StepRow {
+    /// Reads the state of the step at `cursor` from its `state_dir`.
+    fn read(manifest_dir: &Path, cursor: String, state_dir: &Path) -> Self {
+        let exit_status = fs_err::read_to_string(state_dir.join("exit_status")).unwrap_or_default();
+        let (result, failed) = match exit_status.trim() {
+            "0" => ("ok".to_owned(), false),
+            "" => match step_timeout::read(state_dir) {
+                Some(seconds) => (format!("killed after {seconds}s"), true),
+                None if cancellation::was_interrupted(state_dir) => {
+                    ("interrupted".to_owned(), true)
+                }
+                None => ("error".to_owned(), true),
+            },
+            code => (format!("exit {code}"), true),
+        };
+        let files = recorder::recording(state_dir)
+            .into_iter()
+            .chain(
+                step_logs::Stream::Both
+                    .files()
+                    .iter()
+                    .map(|file| state_dir.join(file))
+                    .filter(|path| path.exists()),
+            )
+            .collect();
+        Self {
+            manifest_dir: manifest_dir.to_path_buf(),
+            cursor,
+            command: invocation::read(state_dir)
+                .map(|invocation| invocation.argv.join(" "))
+                .unwrap_or_default(),
+            result,
+            failed,
+            duration: step_duration::read(state_dir),
+            times: step_duration::read_times(state_dir),
+            attempts: fs_err::read_to_string(state_dir.join("attempts"))
+                .ok()
+                .and_then(|attempts| attempts.trim().parse().ok()),
+            files,
+        }
+    }
+}
+
+/// Returns the status of each target of the task `task_name`.
+///
+/// # Errors
+///
+/// Returns an error if the task cannot be loaded.
+pub fn target_statuses(
+    task_name: &str,
+    environment: &crate::Environment,
+) -> Result<Vec<(PathBuf, Status)>, Error> {
+    let (program, resolved) = load_task_data(task_name, environment)?;
+    let state_base = state_dir_for_task(task_name, environment)?;
+    let status = |segment: CursorSegment, done: bool| {
+        let target_dir = state_base.join(ProgramCursor::new().with(segment).to_path());
+        if done {
+            Status::Done
+        } else if failed_below(&target_dir) {
+            Status::Failed
+        } else if blocked_reason(&target_dir).is_some() {
+            Status::Blocked
+        } else if target_dir.exists() {
+            Status::Started
+        } else {
+            Status::Pending
+        }
+    };
+    let ws_stmts = first_workspace_stmts(&program);
+    let workspaces = resolved
+        .workspace_executions
+        .iter()
+        .enumerate()
+        .map(|(i, w)| {
+            let done = is_workspace_completed(i, w, ws_stmts, &state_base);
+            (
+                w.manifest_dir.clone(),
+                status(CursorSegment::WorkspaceIteration(i), done),
+            )
+        });
+    let crate_stmts = first_crate_stmts(&program);
+    let crates = resolved.crate_executions.iter().enumerate().map(|(i, c)| {
+        let done = is_standalone_crate_completed(i, crate_stmts, &state_base);
+        (
+            c.manifest_dir.clone(),
+            status(CursorSegment::CrateIteration(i), done),
+        )
+    });
+    Ok(workspaces.chain(crates).collect())
+}
+
+/// Returns the approvals recorded for the targets of the task `task_name`,
+/// in the order of the targets and the program.
+///
+/// # Errors
+///
+/// Returns an error if the task cannot be loaded.
+pub fn target_approvals(
+    task_name: &str,
+    environment: &crate::Environment,
+) -> Result<Vec<ApprovalRow>, Error> {
+    let (_program, resolved) = load_task_data(task_name, environment)?;
+    let state_base = state_dir_for_task(task_name, environment)?;
+    let workspaces = resolved
+        .workspace_executions
+        .iter()
+        .enumerate()
+        .map(|(i, w)| (CursorSegment::WorkspaceIteration(i), &w.manifest_dir));
+    let crates = resolved
+        .crate_executions
+        .iter()
+        .enumerate()
+        .map(|(i, c)| (CursorSegment::CrateIteration(i), &c.manifest_dir));
+    let mut approvals = Vec::new();
+    for (segment, manifest_dir) in workspaces.chain(crates) {
+        let prefix = ProgramCursor::new().with(segment);
+        for (path, approval) in approval::all_below(&state_base.join(prefix.to_path())) {
+            approvals.push(ApprovalRow {
+                task: task_name.to_owned(),
+                manifest_dir: manifest_dir.clone(),
+                cursor: format!("{}{}", prefix.to_path_string(), path.display()),
+                approval,
+            });
+        }
+    }
+    Ok(approvals)
+}
+
+/// Marks the state directory of a step that ran a command.
+fn executed(state_dir: &Path) -> Option<()> {
+    state_dir.join("exit_status").exists().then_some(())
+}
+
+/// Returns the executed `run` steps of the task `task_name`, in the order of
+/// the targets and the program.
+///
+/// # Errors
+///
+/// Returns an error if the task cannot be loaded.
+pub fn step_rows(task_name: &str, environment: &crate::Environment) -> Result<Vec<StepRow>, Error> {
+    let (_program, resolved) = load_task_data(task_name, environment)?;
+    let state_base = state_dir_for_task(task_name, environment)?;
+    let workspaces = resolved
+        .workspace_executions
+        .iter()
+        .enumerate()
+        .map(|(i, w)| (CursorSegment::WorkspaceIteration(i), &w.manifest_dir));
+    let crates = resolved
+        .crate_executions
+        .iter()
+        .enumerate()
+        .map(|(i, c)| (CursorSegment::CrateIteration(i), &c.manifest_dir));
+    let mut steps = Vec::new();
+    for (segment, manifest_dir) in workspaces.chain(crates) {
+        let prefix = ProgramCursor::new().with(segment);
+        let target_dir = state_base.join(prefix.to_path());
+        for (path, ()) in records_below(&target_dir, executed) {
+            steps.push(StepRow::read(
+                manifest_dir,
+                format!("{}{}", prefix.to_path_string(), path.display()),
+                &target_dir.join(&path),
+            ));
+        }
+    }
+    Ok(steps)
+}
+
+/// Counts the targets in each status, in the order of [`Status::ALL`],
+/// leaving out statuses no target has.
+#[must_use]
+pub fn counts(targets: &[(PathBuf, Status)]) -> Vec<(Status, usize)> {
+    Status::ALL
+        .into_iter()
+        .map(|status| {
+            let count = targets.iter().filter(|(_, s)| *s == status).count();
+            (status, count)
+        })
+        .filter(|(_, count)| *count > 0)
+        .collect()
+}
+
+/// Merges the target statuses of several tasks into rows, one per target.
+#[must_use]
+pub fn rows(tasks: &[Vec<(PathBuf, Status)>]) -> Vec<Row> {
+    let mut by_target: BTreeMap<PathBuf, Vec<Option<Status>>> = BTreeMap::new();
+    for (i, targets) in tasks.iter().enumerate() {
+        for (manifest_dir, status) in targets {
+            let statuses = by_target
+                .entry(manifest_dir.clone())
+                .or_insert_with(|| vec![None; tasks.len()]);
+            if let Some(slot) = statuses.get_mut(i) {
+                *slot = Some(*status);
+            }
+        }
+    }
+    by_target
+        .into_iter()
+        .map(|(manifest_dir, statuses)| Row {
+            manifest_dir,
+            statuses,
+        })
+        .collect()
+}
+
+/// Renders `lines` as columns separated by two spaces, each as wide as its
+/// widest cell.
+fn aligned(lines: &[Vec<String>]) -> String {
+    let columns = lines.iter().map(Vec::len).max().unwrap_or(0);
+    let widths: Vec<usize> = (0..columns)
+        .map(|column| {
+            lines
+                .iter()
+                .filter_map(|line| line.get(column))
+                .map(|cell| cell.chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    let mut out = String::new();
+    for line in lines {
+        let cells: Vec<String> = line
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{cell:width$}"))
+            .collect();
+        let _ignored = writeln!(out, "{}", cells.join("  ").trim_end());
+    }
+    out
+}
+
+/// Escapes `text` for use in HTML.
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Returns the answer of an approval as shown in reports.
+const fn verdict(approval: &Approval) -> &'static str {
+    if approval.approved {
+        "approved"
+    } else {
+        "rejected"
+    }
+}
+
+/// Renders a report on `tasks` with `rows` and `approvals` in `format`, with
+/// the times of the approvals in `style`.
+#[must_use]
+pub fn render(
+    tasks: &[String],
+    rows: &[Row],
+    approvals: &[ApprovalRow],
+    format: Format,
+    style: Style,
+) -> String {
+    let label = |status: &Option<Status>| status.map_or("-", Status::label);
+    let mut out = String::new();
+    match format {
+        Format::Text => {
+            let mut header = vec!["target".to_owned()];
+            header.extend(tasks.iter().cloned());
+            let mut lines = vec![header];
+            for row in rows {
+                let mut line = vec![row.manifest_dir.display().to_string()];
+                line.extend(row.statuses.iter().map(|s| label(s).to_owned()));
+                lines.push(line);
+            }
+            out.push_str(&aligned(&lines));
+            if !approvals.is_empty() {
+                let _ignored = writeln!(out, "\napprovals:");
+            }
+            for row in approvals {
+                let _ignored = writeln!(
+                    out,
+                    "  {} {} {}: {}",
+                    row.task,
+                    row.manifest_dir.display(),
+                    row.cursor,
+                    row.approval.summary(style)
+                );
+            }
+        }
+        Format::Markdown => {
+            let _ignored = writeln!(out, "| Target | {} |", tasks.join(" | "));
+            let _ignored = writeln!(out, "|---|{}", "---|".repeat(tasks.len()));
+            for row in rows {
+                let statuses: Vec<&str> = row.statuses.iter().map(label).collect();
+                let _ignored = writeln!(
+                    out,
+                    "| `{}` | {} |",
+                    row.manifest_dir.display(),
+                    statuses.join(" | ")
+                );
+            }
+            if !approvals.is_empty() {
+                let _ignored = writeln!(
+                    out,
+                    "\n| Task | Target | Step | Answer | Operator | Time |\n|---|---|---|---|---|---|"
+                );
+            }
+            for row in approvals {
+                let _ignored = writeln!(
+                    out,
+                    "| {} | `{}` | `{}` | {} | {} | {} |",
+                    row.task,
+                    row.manifest_dir.display(),
+                    row.cursor,
+                    verdict(&row.approval),
+                    row.approval.operator,
+                    style.timestamp(&row.approval.at)
+                );
+            }
+        }
+        Format::Html => {
+            let title = escape_html(&format!("Tasks {}", tasks.join(", ")));
+            let _ignored = writeln!(
+                out,
+                "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n\
+                 <style>\ntable {{ border-collapse: collapse; }}\n\
+                 th, td {{ border: 1px solid #ccc; padding: 0.2em 0.6em; text-align: left; }}\n\
+                 .done {{ background: #d4f4d4; }}\n.failed {{ background: #f8d0d0; }}\n\
+                 .blocked {{ background: #e0e0f0; }}\n\
+                 .started {{ background: #fff0c0; }}\n</style>\n</head>\n<body>\n<h1>{title}</h1>\n\
+                 <table>\n<tr><th>Target</th>"
+            );
+            for task in tasks {
+                let _ignored = write!(out, "<th>{}</th>", escape_html(task));
+            }
+            let _ignored = writeln!(out, "</tr>");
+            for row in rows {
+                let _ignored = write!(
+                    out,
+                    "<tr><td>{}</td>",
+                    escape_html(&row.manifest_dir.display().to_string())
+                );
+                for status in &row.statuses {
+                    let class = status.map_or("none", Status::label);
+                    let _ignored = write!(out, "<td class=\"{class}\">{}</td>", label(status));
+                }
+                let _ignored = writeln!(out, "</tr>");
+            }
+            let _ignored = writeln!(out, "</table>");
+            if !approvals.is_empty() {
+                let _ignored = writeln!(
+                    out,
+                    "<h2>Approvals</h2>\n<table>\n<tr><th>Task</th><th>Target</th><th>Step</th>\
+                     <th>Answer</th><th>Operator</th><th>Time</th></tr>"
+                );
+                for row in approvals {
+                    let cells = [
+                        row.task.clone(),
+                        row.manifest_dir.display().to_string(),
+                        row.cursor.clone(),
+                        verdict(&row.approval).to_owned(),
+                        row.approval.operator.clone(),
+                        style.timestamp(&row.approval.at),
+                    ];
+                    let _ignored = write!(out, "<tr>");
+                    for cell in cells {
+                        let _ignored = write!(out, "<td>{}</td>", escape_html(&cell));
+                    }
+                    let _ignored = writeln!(out, "</tr>");
+                }
+                let _ignored = writeln!(out, "</table>");
+            }
+            let _ignored = writeln!(out, "</body>\n</html>");
+        }
+    }
+    out
+}
+
+/// Returns the cells of the result, duration and attempts of `step`.
+fn step_cells(step: &StepRow, style: Style) -> [String; 3] {
+    [
+        step.result.clone(),
+        step.duration
+            .map(|duration| style.duration(duration))
+            .unwrap_or_default(),
+        step.attempts.map(|n| n.to_string()).unwrap_or_default(),
+    ]
+}
+
+/// Returns the line summarizing `steps` below a step report.
+fn steps_summary(steps: &[StepRow], style: Style) -> String {
+    let failed = steps.iter().filter(|step| step.failed).count();
+    let total: Duration = steps.iter().filter_map(|step| step.duration).sum();
+    let wall_clock = wall_clock(steps)
+        .map(|wall_clock| format!(", {} wall-clock", style.duration(wall_clock)))
+        .unwrap_or_default();
+    format!(
+        "{} steps, {failed} failed, {} in total{wall_clock}",
+        steps.len(),
+        style.duration(total)
+    )
+}
+
+/// Renders a report on the executed steps of the task `task` in `format`,
+/// with durations in `style`.
+#[must_use]
+pub fn render_steps(task: &str, steps: &[StepRow], format: Format, style: Style) -> String {
+    let file_name = |path: &PathBuf| {
+        path.file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    };
+    let times = target_times(steps);
+    let mut out = String::new();
+    match format {
+        Format::Text => {
+            let mut lines = vec![
+                [
+                    "target", "step", "result", "duration", "attempts", "command",
+                ]
+                .map(str::to_owned)
+                .to_vec(),
+            ];
+            for step in steps {
+                let mut line = vec![step.manifest_dir.display().to_string(), step.cursor.clone()];
+                line.extend(step_cells(step, style));
+                line.push(step.command.clone());
+                lines.push(line);
+            }
+            out.push_str(&aligned(&lines));
+            let _ignored = writeln!(out, "\n{}", steps_summary(steps, style));
+            if !times.is_empty() {
+                let _ignored = write!(out, "\ntime by target:\n{}", render_times(&times, style));
+            }
+        }
+        Format::Markdown => {
+            let _ignored = writeln!(
+                out,
+                "# Task {task}\n\n\
+                 | Target | Step | Result | Duration | Attempts | Command | Files |\n\
+                 |---|---|---|---|---|---|---|"
+            );
+            for step in steps {
+                let [result, duration, attempts] = step_cells(step, style);
+                let files: Vec<String> = step
+                    .files
+                    .iter()
+                    .map(|path| format!("[{}](<{}>)", file_name(path), path.display()))
+                    .collect();
+                let _ignored = writeln!(
+                    out,
+                    "| `{}` | `{}` | {result} | {duration} | {attempts} | `{}` | {} |",
+                    step.manifest_dir.display(),
+                    step.cursor,
+                    step.command.replace('|', "\\|"),
+                    files.join(" ")
+                );
+            }
+            let _ignored = writeln!(out, "\n{}", steps_summary(steps, style));
+            if !times.is_empty() {
+                let _ignored = writeln!(
+                    out,
+                    "\n## Time by Target\n\n\
+                     | Target | Wall-clock | In steps | Steps |\n|---|---|---|---|"
+                );
+            }
+            for time in &times {
+                let [target, wall_clock, step_time, count] = time_cells(time, style);
+                let _ignored =
+                    writeln!(out, "| `{target}` | {wall_clock} | {step_time} | {count} |");
+            }
+        }
+        Format::Html => {
+            let title = escape_html(&format!("Task {task}"));
+            let _ignored = writeln!(
+                out,
+                "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n\
+                 <style>\ntable {{ border-collapse: collapse; }}\n\
+                 th, td {{ border: 1px solid #ccc; padding: 0.2em 0.6em; text-align: left; }}\n\
+                 .ok {{ background: #d4f4d4; }}\n.failed {{ background: #f8d0d0; }}\n\
+                 </style>\n</head>\n<body>\n<h1>{title}</h1>\n<table>\n<tr><th>Target</th>\
+                 <th>Step</th><th>Result</th><th>Duration</th><th>Attempts</th><th>Command</th>\
+                 <th>Files</th></tr>"
+            );
+            for step in steps {
+                let class = if step.failed { "failed" } else { "ok" };
+                let [result, duration, attempts] = step_cells(step, style);
+                let _ignored = write!(
+                    out,
+                    "<tr><td>{}</td><td>{}</td><td class=\"{class}\">{}</td><td>{}</td>\
+                     <td>{}</td><td><code>{}</code></td><td>",
+                    escape_html(&step.manifest_dir.display().to_string()),
+                    escape_html(&step.cursor),
+                    escape_html(&result),
+                    escape_html(&duration),
+                    escape_html(&attempts),
+                    escape_html(&step.command)
+                );
+                for path in &step.files {
+                    let _ignored = write!(
+                        out,
+                        "<a href=\"file://{}\">{}</a> ",
+                        escape_html(&path.display().to_string()),
+                        escape_html(&file_name(path))
+                    );
+                }
+                let _ignored = writeln!(out, "</td></tr>");
+            }
+            let _ignored = writeln!(
+                out,
+                "</table>\n<p>{}</p>",
+                escape_html(&steps_summary(steps, style))
+            );
+            if !times.is_empty() {
+                let _ignored = writeln!(
+                    out,
+                    "<h2>Time by Target</h2>\n<table>\n<tr><th>Target</th><th>Wall-clock</th>\
+                     <th>In steps</th><th>Steps</th></tr>"
+                );
+                for time in &times {
+                    let _ignored = write!(out, "<tr>");
+                    for cell in time_cells(time, style) {
+                        let _ignored = write!(out, "<td>{}</td>", escape_html(&cell));
+                    }
+                    let _ignored = writeln!(out, "</tr>");
+                }
+                let _ignored = writeln!(out, "</table>");
+            }
+            let _ignored = writeln!(out, "</body>\n</html>");
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    use pretty_assertions::assert_eq;
+
+    use super::{
+        ApprovalRow, Format, Row, Status, StepRow, TargetTime, counts, render, render_steps, rows,
+        step_rows, target_times,
+    };
+    use crate::formatting::Style;
+    use crate::program::resolve::{ResolvedCrateExecution, ResolvedProgram};
+    use crate::tasks::approval::Approval;
+    use crate::tasks::{state_dir_for_task, store_task};
+
+    #[test]
+    fn tasks_are_merged_by_target() {
+        let prepare = vec![
+            (PathBuf::from("/src/b"), Status::Done),
+            (PathBuf::from("/src/a"), Status::Done),
+        ];
+        let publish = vec![
+            (PathBuf::from("/src/a"), Status::Failed),
+            (PathBuf::from("/src/c"), Status::Pending),
+        ];
+        assert_eq!(
+            counts(&publish),
+            [(Status::Failed, 1), (Status::Pending, 1)]
+        );
+        let merged = rows(&[prepare, publish]);
+        assert_eq!(
+            merged,
+            [
+                Row {
+                    manifest_dir: PathBuf::from("/src/a"),
+                    statuses: vec![Some(Status::Done), Some(Status::Failed)],
+                },
+                Row {
+                    manifest_dir: PathBuf::from("/src/b"),
+                    statuses: vec![Some(Status::Done), None],
+                },
+                Row {
+                    manifest_dir: PathBuf::from("/src/c"),
+                    statuses: vec![None, Some(Status::Pending)],
+                },
+            ]
+        );
+
+        let tasks = ["prepare".to_owned(), "publish".to_owned()];
+        assert_eq!(
+            render(&tasks, &merged, &[], Format::Text, Style::default()),
+            "target  prepare  publish\n\
+             /src/a  done     failed\n\
+             /src/b  done     -\n\
+             /src/c  -        pending\n"
+        );
+        assert_eq!(
+            render(&tasks, &merged, &[], Format::Markdown, Style::default()),
+            "| Target | prepare | publish |\n\
+             |---|---|---|\n\
+             | `/src/a` | done | failed |\n\
+             | `/src/b` | done | - |\n\
+             | `/src/c` | - | pending |\n"
+        );
+        let single = Row {
+            manifest_dir: PathBuf::from("/src/a"),
+            statuses: vec![Some(Status::Done)],
+        };
+        let html = render(
+            &["a<b".to_owned()],
+            &[single],
+            &[],
+            Format::Html,
+            Style::default(),
+        );
+        assert!(
+            html.contains("<th>a&lt;b</th>")
+                && html.contains("<tr><td>/src/a</td><td class=\"done\">done</td></tr>"),
+            "escaped header and status cells: {html}"
+        );
+    }
+
+    #[test]
+    fn approvals_are_listed_below_the_table() {
+        let tasks = ["publish".to_owned()];
+        let merged = [Row {
+            manifest_dir: PathBuf::from("/src/a"),
+            statuses: vec![Some(Status::Done)],
+        }];
+        let approvals = [ApprovalRow {
+            task: "publish".to_owned(),
+            manifest_dir: PathBuf::from("/src/a"),
+            cursor: "w0/s3".to_owned(),
+            approval: Approval {
+                operator: "alice".to_owned(),
+                at: "2024-05-01T12:00:00Z".to_owned(),
+                approved: true,
+            },
+        }];
+        let utc = Style {
+            utc: true,
+            iso8601: false,
+        };
+        assert_eq!(
+            render(&tasks, &merged, &approvals, Format::Text, utc),
+            "target  publish\n\
+             /src/a  done\n\
+             \n\
+             approvals:\n  \
+             publish /src/a w0/s3: approved by alice at 2024-05-01 12:00:00 UTC\n"
+        );
+        let markdown = render(
+            &tasks,
+            &merged,
+            &approvals,
+            Format::Markdown,
+            Style {
+                utc: true,
+                iso8601: true,
+            },
+        );
+        assert!(
+            markdown.ends_with(
+                "| publish | `/src/a` | `w0/s3` | approved | alice | 2024-05-01T12:00:00Z |\n"
+            ),
+            "approval row in Markdown: {markdown}"
+        );
+        let html = render(&tasks, &merged, &approvals, Format::Html, utc);
+        assert!(
+            html.contains("<td>approved</td><td>alice</td>"),
+            "approval row in HTML: {html}"
+        );
+    }
+
+    #[test]
+    fn executed_steps_are_reported() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempfile::tempdir()?;
+        let environment = crate::Environment::mock(&temp_dir)?;
+        let resolved = ResolvedProgram {
+            workspace_executions: vec![],
+            crate_executions: vec![ResolvedCrateExecution {
+                manifest_dir: PathBuf::from("/src/a"),
+                dependencies: vec![],
+                package: None,
+            }],
+        };
+        store_task("release", None, "select crates;\n", &resolved, &environment)?;
+        let target_dir = state_dir_for_task("release", &environment)?.join("c0");
+        let built = target_dir.join("s0");
+        let hanging = target_dir.join("s1");
+        for dir in [&built, &hanging, &target_dir.join("s2")] {
+            fs_err::create_dir_all(dir)?;
+        }
+        fs_err::write(built.join("exit_status"), "0")?;
+        fs_err::write(built.join("duration_ms"), "61500")?;
+        fs_err::write(built.join("stdout.log"), "built\n")?;
+        fs_err::write(hanging.join("exit_status"), "")?;
+        fs_err::write(hanging.join("timed_out"), "60")?;
+        fs_err::write(hanging.join("attempts"), "3")?;
+
+        let steps = step_rows("release", &environment)?;
+        assert_eq!(
+            steps
+                .iter()
+                .map(|step| (step.cursor.as_str(), step.failed))
+                .collect::<Vec<_>>(),
+            [("c0/s0", false), ("c0/s1", true)],
+            "steps that never ran are left out"
+        );
+        assert_eq!(
+            render_steps("release", &steps, Format::Text, Style::default()),
+            "target  step   result            duration  attempts  command\n\
+             /src/a  c0/s0  ok                1m 1s\n\
+             /src/a  c0/s1  killed after 60s            3\n\
+             \n\
+             2 steps, 1 failed, 1m 1s in total\n"
+        );
+        let markdown = render_steps("release", &steps, Format::Markdown, Style::default());
+        assert!(
+            markdown.contains(&format!(
+                "| `/src/a` | `c0/s0` | ok | 1m 1s |  | `` | [stdout.log](<{}>) |",
+                built.join("stdout.log").display()
+            )),
+            "steps link to their logs: {markdown}"
+        );
+        let html = render_steps("release", &steps, Format::Html, Style::default());
+        assert!(
+            html.contains("<td class=\"failed\">killed after 60s</td>"),
+            "failed steps are highlighted: {html}"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn targets_are_listed_by_wall_clock_time() -> Result<(), Box<dyn std::error::Error>> {
+        let at = |time: &str| humantime::parse_rfc3339(time);
+        let step = |manifest_dir: &str, seconds: u64, started: &str, finished: &str| {
+            Ok::<_, humantime::TimestampError>(StepRow {
+                manifest_dir: PathBuf::from(manifest_dir),
+                cursor: "c0/s0".to_owned(),
+                command: "cargo build".to_owned(),
+                result: "ok".to_owned(),
+                failed: false,
+                duration: Some(Duration::from_secs(seconds)),
+                times: Some((at(started)?, at(finished)?)),
+                attempts: None,
+                files: vec![],
+            })
+        };
+        let steps = [
+            step("/src/a", 60, "2024-05-01T12:00:00Z", "2024-05-01T12:01:00Z")?,
+            step("/src/b", 30, "2024-05-01T12:00:00Z", "2024-05-01T12:00:30Z")?,
+            // the time between the steps counts towards the wall-clock time
+            step("/src/b", 60, "2024-05-01T12:02:00Z", "2024-05-01T12:03:00Z")?,
+        ];
+        assert_eq!(
+            target_times(&steps),
+            [
+                TargetTime {
+                    manifest_dir: PathBuf::from("/src/b"),
+                    wall_clock: Duration::from_secs(180),
+                    step_time: Duration::from_secs(90),
+                    steps: 2,
+                },
+                TargetTime {
+                    manifest_dir: PathBuf::from("/src/a"),
+                    wall_clock: Duration::from_secs(60),
+                    step_time: Duration::from_secs(60),
+                    steps: 1,
+                },
+            ]
+        );
+        let text = render_steps("release", &steps, Format::Text, Style::default());
+        assert!(
+            text.ends_with(
+                "3 steps, 0 failed, 2m 30s in total, 3m wall-clock\n\
+                 \n\
+                 time by target:\n\
+                 target  wall-clock  in steps  steps\n\
+                 /src/b  3m          1m 30s    2\n\
+                 /src/a  1m          1m        1\n"
+            ),
+            "targets are listed by their wall-clock time: {text}"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,148 @@
+//! Clearing the state of selected targets and statements of a task.
+//!
+//! `task rewind` undoes the most recent progress in execution order.  `task
+//! reset` instead clears the state of any target, optionally only of one of
+//! its top-level statements (`--step <n>`, the `s<n>` of its cursor) and
+//! optionally only of the steps that failed (`--only-failed`), so a task can
+//! be re-run from scratch or from a failed step while keeping the targets it
+//! was created with.
+
+use std::path::{Path, PathBuf};
+
+use super::{is_run_failed, is_wait_until_timed_out};
+use crate::error::Error;
+use crate::program::cursor::{CursorSegment, ProgramCursor};
+use crate::program::resolve::ResolvedProgram;
+
+/// Returns the cursor prefixes of the targets of `resolved`, or only of the
+/// one in `manifest_dir` if given.
+///
+/// # Errors
+///
+/// Returns [`Error::TargetNotInTask`] if no target is in `manifest_dir`.
+pub fn targets(
+    resolved: &ResolvedProgram,
+    manifest_dir: Option<&Path>,
+) -> Result<Vec<ProgramCursor>, Error> {
+    let workspaces = resolved
+        .workspace_executions
+        .iter()
+        .enumerate()
+        .map(|(i, w)| (CursorSegment::WorkspaceIteration(i), &w.manifest_dir));
+    let crates = resolved
+        .crate_executions
+        .iter()
+        .enumerate()
+        .map(|(i, c)| (CursorSegment::CrateIteration(i), &c.manifest_dir));
+    let selected: Vec<ProgramCursor> = workspaces
+        .chain(crates)
+        .filter(|(_, dir)| manifest_dir.is_none_or(|wanted| dir.as_path() == wanted))
+        .map(|(segment, _)| ProgramCursor::new().with(segment))
+        .collect();
+    match manifest_dir {
+        Some(dir) if selected.is_empty() => Err(Error::TargetNotInTask(dir.to_path_buf())),
+        _ => Ok(selected),
+    }
+}
+
+/// Returns the state directories of failed steps in `dir` and below it.
+fn failed_dirs(dir: &Path) -> Vec<PathBuf> {
+    if is_run_failed(dir) || is_wait_until_timed_out(dir) {
+        return vec![dir.to_path_buf()];
+    }
+    let Ok(entries) = fs_err::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .flat_map(|path| failed_dirs(&path))
+        .collect()
+}
+
+/// Removes the state of the target in `target_dir`, or only of its top-level
+/// statement `step`, or only of the failed steps in it, and returns the
+/// removed directories.
+///
+/// # Errors
+///
+/// Returns an error if a directory cannot be removed.
+pub fn clear(
+    target_dir: &Path,
+    step: Option<usize>,
+    only_failed: bool,
+) -> Result<Vec<PathBuf>, Error> {
+    let base = match step {
+        Some(n) => target_dir.join(format!("s{n}")),
+        None => target_dir.to_path_buf(),
+    };
+    let dirs = if only_failed {
+        failed_dirs(&base)
+    } else if base.exists() {
+        vec![base]
+    } else {
+        Vec::new()
+    };
+    for dir in &dirs {
+        fs_err::remove_dir_all(dir)
+            .map_err(|e| Error::CouldNotRemoveTaskStateDir(dir.clone(), e))?;
+    }
+    Ok(dirs)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use pretty_assertions::assert_eq;
+
+    use super::{clear, targets};
+    use crate::program::resolve::{ResolvedCrateExecution, ResolvedProgram};
+
+    #[test]
+    fn failed_steps_of_one_target_are_cleared() -> Result<(), Box<dyn std::error::Error>> {
+        let resolved = ResolvedProgram {
+            workspace_executions: vec![],
+            crate_executions: ["/a", "/b"]
+                .into_iter()
+                .map(|dir| ResolvedCrateExecution {
+                    manifest_dir: PathBuf::from(dir),
+                    dependencies: vec![],
+                    package: None,
+                })
+                .collect(),
+        };
+        let selected = targets(&resolved, Some(Path::new("/b")))?;
+        assert_eq!(
+            selected
+                .iter()
+                .map(|cursor| cursor.to_path_string())
+                .collect::<Vec<_>>(),
+            ["c1/"]
+        );
+        assert_eq!(targets(&resolved, None)?.len(), 2);
+        assert!(
+            targets(&resolved, Some(Path::new("/c"))).is_err(),
+            "an unknown target is rejected"
+        );
+
+        let temp_dir = tempfile::tempdir()?;
+        let target = temp_dir.path().join("c1");
+        for (step, status) in [("s0", "0"), ("s1", "1"), ("s2/c0/s0", "101")] {
+            fs_err::create_dir_all(target.join(step))?;
+            fs_err::write(target.join(step).join("exit_status"), status)?;
+        }
+        assert_eq!(
+            clear(&target, Some(2), true)?,
+            [target.join("s2").join("c0").join("s0")]
+        );
+        let mut removed = clear(&target, None, true)?;
+        removed.sort();
+        assert_eq!(removed, [target.join("s1")]);
+        assert!(target.join("s0").exists(), "successful steps are kept");
+        assert_eq!(clear(&target, None, false)?, std::slice::from_ref(&target));
+        assert!(!target.exists(), "the whole target state is removed");
+        Ok(())
+    }
+}
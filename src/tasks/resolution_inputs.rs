@@ -0,0 +1,168 @@
+//! Detection of stale target resolutions.
+//!
+//! A task keeps the targets its program selected when it was created (see
+//! [`target_changes`](super::target_changes)).  To notice when that selection
+//! is likely outdated without resolving the program again, `task create`
+//! records checksums of the inputs of the resolution in `resolution.toml`:
+//! the configuration file, and the `Cargo.toml` and `Cargo.lock` files of the
+//! selected targets and workspace members.  `task run` compares them with the
+//! current files and warns, or fails with `--strict`, if any of them changed.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::program_source::checksum;
+use crate::error::Error;
+use crate::program::resolve::ResolvedProgram;
+
+/// Name of the file inside the task directory.
+const FILE_NAME: &str = "resolution.toml";
+
+/// Checksums of the files a resolution depended on.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Inputs {
+    /// Checksum of the configuration file, if there was one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    config: Option<String>,
+    /// Checksums of the manifests and lockfiles of the targets, keyed by
+    /// path; files that did not exist are left out.
+    #[serde(default)]
+    files: BTreeMap<PathBuf, String>,
+}
+
+/// Returns the checksum of the file at `path`, if it can be read.
+fn file_checksum(path: &Path) -> Option<String> {
+    fs_err::read_to_string(path)
+        .ok()
+        .map(|content| checksum(&content))
+}
+
+/// Collects the current checksums of the inputs of `resolved`.
+///
+/// # Errors
+///
+/// Returns an error if the path of the configuration file cannot be
+/// determined.
+pub fn collect(
+    resolved: &ResolvedProgram,
+    environment: &crate::Environment,
+) -> Result<Inputs, Error> {
+    let workspace_dirs = resolved.workspace_executions.iter().flat_map(|w| {
+        std::iter::once(&w.manifest_dir).chain(w.member_crates.iter().map(|c| &c.manifest_dir))
+    });
+    let crate_dirs = resolved.crate_executions.iter().map(|c| &c.manifest_dir);
+    let files = workspace_dirs
+        .chain(crate_dirs)
+        .flat_map(|dir| [dir.join("Cargo.toml"), dir.join("Cargo.lock")])
+        .filter_map(|path| file_checksum(&path).map(|sum| (path, sum)))
+        .collect();
+    Ok(Inputs {
+        config: file_checksum(&crate::config_file(environment)?),
+        files,
+    })
+}
+
+/// Describes the inputs that differ between `recorded` and `current`.
+#[must_use]
+pub fn changes(recorded: &Inputs, current: &Inputs) -> Vec<String> {
+    let mut changes = Vec::new();
+    if recorded.config != current.config {
+        changes.push("the configuration file changed".to_owned());
+    }
+    for (path, sum) in &recorded.files {
+        match current.files.get(path) {
+            None => changes.push(format!("{} was removed", path.display())),
+            Some(current_sum) if current_sum != sum => {
+                changes.push(format!("{} changed", path.display()));
+            }
+            Some(_) => {}
+        }
+    }
+    for path in current.files.keys() {
+        if !recorded.files.contains_key(path) {
+            changes.push(format!("{} was created", path.display()));
+        }
+    }
+    changes
+}
+
+/// Writes the inputs of the task's resolution in `task_dir`.
+///
+/// # Errors
+///
+/// Returns an error if the inputs cannot be serialized or written.
+pub fn write(task_dir: &Path, inputs: &Inputs) -> Result<(), Error> {
+    let path = task_dir.join(FILE_NAME);
+    let content = toml::to_string(inputs).map_err(Error::CouldNotSerializeResolutionInputs)?;
+    crate::utils::write_atomically(&path, content)
+        .map_err(|e| Error::CouldNotWriteResolutionInputs(path, e))
+}
+
+/// Reads the inputs of the task's resolution in `task_dir`.
+///
+/// Returns `None` for tasks created before the inputs were recorded.
+///
+/// # Errors
+///
+/// Returns an error if the file exists but cannot be read or parsed.
+pub fn read(task_dir: &Path) -> Result<Option<Inputs>, Error> {
+    let path = task_dir.join(FILE_NAME);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs_err::read_to_string(&path)
+        .map_err(|e| Error::CouldNotReadResolutionInputs(path.clone(), e))?;
+    toml::from_str(&content)
+        .map(Some)
+        .map_err(|e| Error::CouldNotParseResolutionInputs(path, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use pretty_assertions::assert_eq;
+
+    use super::{changes, collect, read, write};
+    use crate::program::resolve::{ResolvedCrateExecution, ResolvedProgram};
+
+    #[test]
+    fn changed_manifests_and_config_are_reported() -> Result<(), Box<dyn std::error::Error>> {
+        let temp = tempfile::tempdir()?;
+        let environment = crate::Environment::mock(&temp)?;
+        let crate_dir = temp.path().join("solo");
+        fs_err::create_dir_all(&crate_dir)?;
+        fs_err::write(crate_dir.join("Cargo.toml"), "[package]\nname = \"solo\"\n")?;
+        let resolved = ResolvedProgram {
+            workspace_executions: vec![],
+            crate_executions: vec![ResolvedCrateExecution {
+                manifest_dir: crate_dir.clone(),
+                dependencies: vec![],
+                package: None,
+            }],
+        };
+
+        let recorded = collect(&resolved, &environment)?;
+        assert_eq!(read(temp.path())?, None);
+        write(temp.path(), &recorded)?;
+        assert_eq!(read(temp.path())?, Some(recorded.clone()));
+        assert_eq!(
+            changes(&recorded, &collect(&resolved, &environment)?),
+            Vec::<String>::new()
+        );
+
+        fs_err::write(crate_dir.join("Cargo.lock"), "version = 4\n")?;
+        crate::Config::default().save(&environment)?;
+        let lockfile = PathBuf::from(&crate_dir).join("Cargo.lock");
+        assert_eq!(
+            changes(&recorded, &collect(&resolved, &environment)?),
+            [
+                "the configuration file changed".to_owned(),
+                format!("{} was created", lockfile.display())
+            ]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,180 @@
+//! Exclusive resources of `run` statements.
+//!
+//! Some commands must not run concurrently even for different targets, e.g.
+//! two `cargo login` calls writing the same credentials file or two package
+//! manager invocations competing for its lock.  A `run` statement lists the
+//! named resources it needs with `exclusive "registry-token" "apt"`; while it
+//! runs, no other statement needing one of them starts in this process, so
+//! with `--jobs` the statements are serialized across targets while everything
+//! else keeps running in parallel.
+//!
+//! All statements take their resources in one global order, sorted by name,
+//! and only after the task's run lock, so statements sharing several
+//! resources cannot deadlock.  Waiting statements get a resource in the order
+//! they asked for it, so none of them starves.  With `task run
+//! --explain-blocking` every wait is printed with the target holding the
+//! resource, the resources the waiting target already holds and the targets
+//! queued before it.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use tokio::sync::OwnedMutexGuard;
+
+/// A resource used so far.
+#[derive(Debug, Default)]
+struct Resource {
+    /// The lock of the resource.
+    lock: Arc<tokio::sync::Mutex<()>>,
+    /// The target holding the resource, if any.
+    holder: Option<String>,
+    /// The targets waiting for the resource, in the order they asked for it.
+    waiting: Vec<String>,
+}
+
+/// Each resource used so far, by name.
+static RESOURCES: Mutex<BTreeMap<String, Resource>> = Mutex::new(BTreeMap::new());
+
+/// Runs `f` on the resource `name`.
+fn with_resource<T>(name: &str, f: impl FnOnce(&mut Resource) -> T) -> T {
+    let mut resources = RESOURCES
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    f(resources.entry(name.to_owned()).or_default())
+}
+
+/// Returns the resources in `names` in the order they are taken.
+#[must_use]
+pub fn lock_order(names: &[String]) -> Vec<&String> {
+    let mut sorted: Vec<&String> = names.iter().collect();
+    sorted.sort();
+    sorted.dedup();
+    sorted
+}
+
+/// Holds the resources of a running statement until dropped.
+#[derive(Debug)]
+pub struct Held {
+    /// The names of the resources, in acquisition order.
+    names: Vec<String>,
+    /// The guards of the resources, in acquisition order.
+    _guards: Vec<OwnedMutexGuard<()>>,
+}
+
+impl Drop for Held {
+    fn drop(&mut self) {
+        for name in &self.names {
+            with_resource(name, |resource| resource.holder = None);
+        }
+    }
+}
+
+/// Waits until all resources in `names` are free and takes them for the
+/// target in `manifest_dir`.  With `explain`, a wait is printed with what
+/// blocks it.
+#[expect(clippy::print_stdout, reason = "This is part of the UI, not logging")]
+pub async fn acquire(names: &[String], manifest_dir: &Path, explain: bool) -> Held {
+    let target = manifest_dir.display().to_string();
+    let mut taken: Vec<String> = Vec::new();
+    let mut guards = Vec::new();
+    for name in lock_order(names) {
+        let lock = with_resource(name, |resource| Arc::clone(&resource.lock));
+        let guard = match Arc::clone(&lock).try_lock_owned() {
+            Ok(guard) => guard,
+            Err(_busy) => {
+                let (holder, queued) = with_resource(name, |resource| {
+                    let queued = resource.waiting.clone();
+                    resource.waiting.push(target.clone());
+                    (resource.holder.clone(), queued)
+                });
+                if explain {
+                    println!(
+                        "{target} waits for exclusive resource \"{name}\" held by {}",
+                        holder.as_deref().unwrap_or("a statement finishing")
+                    );
+                    if !taken.is_empty() {
+                        println!("  it already holds \"{}\"", taken.join("\", \""));
+                    }
+                    if !queued.is_empty() {
+                        println!("  queued before it: {}", queued.join(", "));
+                    }
+                } else {
+                    println!("Waiting for exclusive resource \"{name}\"");
+                }
+                let start = Instant::now();
+                let guard = lock.lock_owned().await;
+                with_resource(name, |resource| {
+                    if let Some(position) = resource.waiting.iter().position(|t| t == &target) {
+                        resource.waiting.remove(position);
+                    }
+                });
+                if explain {
+                    println!(
+                        "{target} took exclusive resource \"{name}\" after waiting {:.1}s",
+                        start.elapsed().as_secs_f64()
+                    );
+                }
+                guard
+            }
+        };
+        with_resource(name, |resource| resource.holder = Some(target.clone()));
+        taken.push(name.clone());
+        guards.push(guard);
+    }
+    Held {
+        names: taken,
+        _guards: guards,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    use pretty_assertions::assert_eq;
+
+    use super::{acquire, lock_order};
+
+    #[tokio::test]
+    async fn statements_sharing_a_resource_do_not_overlap() {
+        let running = Arc::new(AtomicUsize::new(0));
+        let most = Arc::new(AtomicUsize::new(0));
+        let statements = (0..4).map(|i| {
+            let running = Arc::clone(&running);
+            let most = Arc::clone(&most);
+            // every statement shares "test-a"; the order differs to check
+            // that several resources are taken without deadlocking
+            let names = if i % 2 == 0 {
+                vec!["test-a".to_owned(), "test-b".to_owned()]
+            } else {
+                vec![
+                    "test-b".to_owned(),
+                    "test-a".to_owned(),
+                    "test-a".to_owned(),
+                ]
+            };
+            tokio::spawn(async move {
+                let _held = acquire(&names, Path::new("/t"), true).await;
+                let now = running.fetch_add(1, Ordering::SeqCst).saturating_add(1);
+                most.fetch_max(now, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                running.fetch_sub(1, Ordering::SeqCst);
+            })
+        });
+        for statement in statements.collect::<Vec<_>>() {
+            assert!(statement.await.is_ok(), "a statement panicked");
+        }
+        assert_eq!(most.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn resources_are_taken_in_one_global_order() {
+        let names = ["b".to_owned(), "a".to_owned(), "b".to_owned()];
+        assert_eq!(lock_order(&names), vec!["a", "b"]);
+    }
+}
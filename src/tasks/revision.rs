@@ -0,0 +1,134 @@
+//! The git commit each target was at when its steps ran.
+//!
+//! Before a `run` step starts, the commit of the target's checkout (and
+//! whether it had uncommitted changes) is stored as `git_commit` in the step's
+//! state directory.  After the step, the commit is also stored in the target's
+//! state directory (`w0/`, `c1/`), so the next step can warn when the checkout
+//! was moved to another commit in between, e.g. when a failed step is re-run
+//! after the branch was switched.  Commits created by the steps themselves do
+//! not trigger the warning.  `task describe` shows the target's commit.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::error::Error;
+use crate::program::cursor::ProgramCursor;
+
+/// Name of the file holding the revision inside a state directory.
+const FILE_NAME: &str = "git_commit";
+
+/// The state of a git checkout.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Revision {
+    /// The full hash of the checked out commit.
+    pub commit: String,
+    /// Whether the working tree had uncommitted changes.
+    pub dirty: bool,
+}
+
+impl fmt::Display for Revision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.commit.get(..12).unwrap_or(&self.commit))?;
+        if self.dirty {
+            write!(f, " (dirty)")?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs `git` with `args` in `dir` and returns its trimmed output, or `None`
+/// if it fails.
+#[must_use]
+pub fn git_output(dir: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_owned())
+}
+
+/// Returns the revision of the checkout containing `dir`, or `None` if it is
+/// not in a git repository.
+#[must_use]
+pub fn current(dir: &Path) -> Option<Revision> {
+    let commit = git_output(dir, &["rev-parse", "HEAD"])?;
+    let status = git_output(dir, &["status", "--porcelain", "--", "."])?;
+    Some(Revision {
+        commit,
+        dirty: !status.is_empty(),
+    })
+}
+
+/// Stores `revision` in `dir`.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be written.
+pub fn write(dir: &Path, revision: &Revision) -> Result<(), Error> {
+    fs_err::create_dir_all(dir).map_err(|e| Error::CouldNotCreateStateDir(dir.to_path_buf(), e))?;
+    let path = dir.join(FILE_NAME);
+    let mut content = revision.commit.clone();
+    if revision.dirty {
+        content.push_str(" dirty");
+    }
+    content.push('\n');
+    fs_err::write(&path, content).map_err(|e| Error::CouldNotWriteStateFile(path, e))
+}
+
+/// Reads the revision stored in `dir`, if any.
+#[must_use]
+pub fn read(dir: &Path) -> Option<Revision> {
+    let content = fs_err::read_to_string(dir.join(FILE_NAME)).ok()?;
+    let mut fields = content.split_whitespace();
+    let commit = fields.next()?.to_owned();
+    Some(Revision {
+        commit,
+        dirty: fields.next() == Some("dirty"),
+    })
+}
+
+/// Returns the state directory of the target (workspace or standalone crate)
+/// the statement at `cursor` belongs to.
+#[must_use]
+pub fn target_dir(state_base: &Path, cursor: &ProgramCursor) -> PathBuf {
+    cursor.segments().first().map_or_else(
+        || state_base.to_path_buf(),
+        |segment| state_base.join(ProgramCursor::new().with(*segment).to_path()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::{Revision, current, read, write};
+
+    #[test]
+    fn revision_round_trip() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempfile::tempdir()?;
+        assert_eq!(read(temp_dir.path()), None);
+        let revision = Revision {
+            commit: "0123456789abcdef0123456789abcdef01234567".to_owned(),
+            dirty: true,
+        };
+        write(temp_dir.path(), &revision)?;
+        assert_eq!(read(temp_dir.path()), Some(revision.clone()));
+        assert_eq!(revision.to_string(), "0123456789ab (dirty)");
+        Ok(())
+    }
+
+    #[test]
+    fn current_is_none_outside_git() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempfile::tempdir()?;
+        assert_eq!(current(temp_dir.path()), None);
+        Ok(())
+    }
+}
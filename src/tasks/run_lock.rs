@@ -0,0 +1,306 @@
+//! Keeping two `task run` invocations of the same task apart.
+//!
+//! While `task run` executes a task, `run.lock` in the task's state directory
+//! names the process, its host and when it started.  Another `task run` of
+//! the same task refuses to start while that process is alive, since both
+//! would pick the same next steps.  `task rewind`, `task reset` and `task
+//! sync-program` take the lock as well, so they do not change the state of a
+//! run under its feet.  If the process is gone (or a zombie) the
+//! earlier run crashed or was killed: the lock is stale, so it is removed
+//! with a warning, inline `stdin` content the interrupted steps left behind is
+//! deleted and the steps that never recorded an exit status are listed, as
+//! they run again.  Locks of other hosts cannot be checked;
+//! `task unlock --name <task>` removes a lock by hand.
+
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::invocation::records_below;
+use crate::error::Error;
+
+/// Name of the lock file inside the task's state directory.
+const FILE_NAME: &str = "run.lock";
+
+/// The process holding the lock of a task.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Holder {
+    /// The process id of the `task run` invocation.
+    pub pid: u32,
+    /// The host the process runs on.
+    pub host: String,
+    /// When the run started, in RFC 3339 format.
+    pub started: String,
+}
+
+impl Holder {
+    /// Describes the current process.
+    #[must_use]
+    pub fn current() -> Self {
+        Self {
+            pid: std::process::id(),
+            host: host_name(),
+            started: humantime::format_rfc3339_seconds(std::time::SystemTime::now()).to_string(),
+        }
+    }
+
+    /// Returns `true` if the holder may still be running: it runs on another
+    /// host, where it cannot be checked, or its process is alive.
+    #[must_use]
+    pub fn may_be_alive(&self) -> bool {
+        self.host != host_name() || is_alive(self.pid)
+    }
+}
+
+/// Removes the lock when dropped, i.e. when the run ends.
+#[derive(Debug)]
+pub struct Guard {
+    /// The lock file.
+    path: PathBuf,
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        if let Err(e) = fs_err::remove_file(&self.path) {
+            tracing::warn!("Could not remove the lock {}: {e}", self.path.display());
+        }
+    }
+}
+
+/// Returns the name of this host, or `unknown`.
+fn host_name() -> String {
+    fs_err::read_to_string("/proc/sys/kernel/hostname")
+        .ok()
+        .or_else(|| std::env::var("HOSTNAME").ok())
+        .map(|name| name.trim().to_owned())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "unknown".to_owned())
+}
+
+/// Returns `true` if the process `pid` exists and is not a zombie.
+#[cfg(target_os = "linux")]
+#[must_use]
+pub fn is_alive(pid: u32) -> bool {
+    // the state follows the command name in parentheses, which may contain
+    // spaces and parentheses itself
+    fs_err::read_to_string(format!("/proc/{pid}/stat"))
+        .ok()
+        .and_then(|stat| {
+            let (_, rest) = stat.rsplit_once(')')?;
+            rest.split_whitespace().next().map(str::to_owned)
+        })
+        .is_some_and(|state| state != "Z" && state != "X")
+}
+
+/// Returns `true` if the process `pid` exists.
+#[cfg(not(target_os = "linux"))]
+#[must_use]
+pub fn is_alive(pid: u32) -> bool {
+    std::process::Command::new("kill")
+        .arg("-0")
+        .arg(pid.to_string())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+/// Reads the lock of the task with state in `state_base`, if there is one.
+#[must_use]
+pub fn read(state_base: &Path) -> Option<Holder> {
+    let content = fs_err::read_to_string(state_base.join(FILE_NAME)).ok()?;
+    toml::from_str(&content).ok()
+}
+
+/// Removes the lock of the task with state in `state_base` and returns
+/// whether it was locked.
+///
+/// # Errors
+///
+/// Returns an error if the lock exists but cannot be removed.
+pub fn remove(state_base: &Path) -> Result<bool, Error> {
+    let path = state_base.join(FILE_NAME);
+    if !path.exists() {
+        return Ok(false);
+    }
+    fs_err::remove_file(&path).map_err(|e| Error::CouldNotRemoveRunLock(path, e))?;
+    Ok(true)
+}
+
+/// Checks that the task `task_name` with state in `state_base` is not being
+/// run, for commands that remove its whole state directory and so cannot
+/// hold the lock themselves.
+///
+/// # Errors
+///
+/// Returns [`Error::TaskIsRunning`] if a process that may still be alive
+/// holds the lock.
+pub fn ensure_not_running(task_name: &str, state_base: &Path) -> Result<(), Error> {
+    match read(state_base).filter(Holder::may_be_alive) {
+        Some(holder) => Err(Error::TaskIsRunning(
+            task_name.to_owned(),
+            holder.pid,
+            holder.host,
+            holder.started,
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Takes the lock of the task `task_name` with state in `state_base`,
+/// removing a stale lock of a crashed run first.
+///
+/// # Errors
+///
+/// Returns [`Error::TaskIsRunning`] if another process holds the lock, or an
+/// error if the lock file cannot be written.
+#[expect(clippy::print_stdout, reason = "This is part of the UI, not logging")]
+pub fn acquire(task_name: &str, state_base: &Path) -> Result<Guard, Error> {
+    fs_err::create_dir_all(state_base)
+        .map_err(|e| Error::CouldNotCreateStateDir(state_base.to_path_buf(), e))?;
+    let path = state_base.join(FILE_NAME);
+    if let Some(holder) = read(state_base) {
+        if holder.may_be_alive() {
+            return Err(Error::TaskIsRunning(
+                task_name.to_owned(),
+                holder.pid,
+                holder.host,
+                holder.started,
+            ));
+        }
+        println!(
+            "Warning: removing the stale lock of process {} (started {}), which is no longer running",
+            holder.pid, holder.started
+        );
+        fs_err::remove_file(&path).map_err(|e| Error::CouldNotRemoveRunLock(path.clone(), e))?;
+        for step in clean_up_interrupted(state_base) {
+            println!(
+                "Warning: the step at {}/ was interrupted and runs again",
+                step.display()
+            );
+        }
+    }
+    let content = toml::to_string(&Holder::current()).map_err(Error::CouldNotSerializeRunLock)?;
+    let mut file = fs_err::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&path)
+        .map_err(|e| match read(state_base) {
+            // another run took the lock since it was checked
+            Some(holder) if e.kind() == std::io::ErrorKind::AlreadyExists => Error::TaskIsRunning(
+                task_name.to_owned(),
+                holder.pid,
+                holder.host,
+                holder.started,
+            ),
+            _ => Error::CouldNotWriteStateFile(path.clone(), e),
+        })?;
+    file.write_all(content.as_bytes())
+        .map_err(|e| Error::CouldNotWriteStateFile(path.clone(), e))?;
+    Ok(Guard { path })
+}
+
+/// Marks a step directory that started a command but never recorded its
+/// exit status.
+fn interrupted(state_dir: &Path) -> Option<()> {
+    (state_dir.join("run_wrapper.sh").exists() && !state_dir.join("exit_status").exists())
+        .then_some(())
+}
+
+/// Deletes the inline `stdin` content left behind by interrupted steps
+/// below `state_base`, which may contain secrets, and returns the cursor
+/// paths of those steps.
+fn clean_up_interrupted(state_base: &Path) -> Vec<PathBuf> {
+    records_below(state_base, interrupted)
+        .into_iter()
+        .map(|(step, ())| {
+            let stdin = state_base.join(&step).join("stdin");
+            if stdin.exists()
+                && let Err(e) = fs_err::remove_file(&stdin)
+            {
+                tracing::warn!("Could not remove {}: {e}", stdin.display());
+            }
+            step
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use pretty_assertions::assert_eq;
+
+    use super::{Holder, acquire, clean_up_interrupted, is_alive, read, remove};
+    use crate::error::Error;
+
+    #[test]
+    fn runs_of_one_task_exclude_each_other() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempfile::tempdir()?;
+        let state_base = temp_dir.path().join("release");
+        assert!(is_alive(std::process::id()), "this process is alive");
+
+        let guard = acquire("release", &state_base)?;
+        assert_eq!(
+            read(&state_base).map(|holder| holder.pid),
+            Some(std::process::id())
+        );
+        assert!(
+            matches!(
+                acquire("release", &state_base),
+                Err(Error::TaskIsRunning(ref name, _, _, _)) if name == "release"
+            ),
+            "a second run is refused while the first holds the lock"
+        );
+        drop(guard);
+        assert_eq!(read(&state_base), None, "the lock is released");
+        assert!(!remove(&state_base)?, "nothing to unlock");
+        Ok(())
+    }
+
+    #[test]
+    fn stale_locks_are_removed() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempfile::tempdir()?;
+        let state_base = temp_dir.path().join("release");
+        let interrupted = state_base.join("w0").join("s1");
+        let finished = state_base.join("w0").join("s0");
+        for dir in [&interrupted, &finished] {
+            fs_err::create_dir_all(dir)?;
+            fs_err::write(dir.join("run_wrapper.sh"), "")?;
+            fs_err::write(dir.join("stdin"), "token")?;
+        }
+        fs_err::write(finished.join("exit_status"), "0")?;
+        assert_eq!(
+            clean_up_interrupted(&state_base),
+            [PathBuf::from("w0/s1")],
+            "only steps without exit status were interrupted"
+        );
+        assert!(
+            !interrupted.join("stdin").exists(),
+            "inline stdin of an interrupted step is removed"
+        );
+
+        let dead = Holder {
+            // larger than any pid the kernel hands out
+            pid: u32::MAX,
+            ..Holder::current()
+        };
+        assert!(!dead.may_be_alive(), "the process does not exist");
+        fs_err::write(state_base.join("run.lock"), toml::to_string(&dead)?)?;
+        let _guard = acquire("release", &state_base)?;
+        assert_eq!(
+            read(&state_base).map(|holder| holder.pid),
+            Some(std::process::id())
+        );
+
+        let elsewhere = Holder {
+            host: "some-other-host".to_owned(),
+            ..dead
+        };
+        assert!(
+            elsewhere.may_be_alive(),
+            "processes on other hosts cannot be checked"
+        );
+        Ok(())
+    }
+}
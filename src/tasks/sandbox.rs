@@ -0,0 +1,126 @@
+//! Sandboxed execution of `run` steps.
+//!
+//! With `task run --sandbox`, the command of each `run` step is started
+//! through [bubblewrap](https://github.com/containers/bubblewrap) with the
+//! whole filesystem mounted read-only, except for the target's directory, the
+//! cargo target directory of its workspace and `CARGO_HOME`.  `/tmp` is
+//! replaced by an empty private tmpfs.  This keeps commands applied across a
+//! whole fleet of checkouts from accidentally modifying unrelated ones.
+
+use std::path::{Path, PathBuf};
+
+use cargo_metadata::MetadataCommand;
+
+use crate::Environment;
+use crate::error::Error;
+
+/// The bubblewrap executable used to sandbox commands.
+const BWRAP: &str = "bwrap";
+
+/// Returns the directories a sandboxed step for the target in `manifest_dir`
+/// may write to.
+///
+/// These are `manifest_dir` itself, the cargo target directory of its
+/// workspace (if it lies outside `manifest_dir`) and `CARGO_HOME`.
+#[must_use]
+pub fn writable_dirs(manifest_dir: &Path) -> Vec<PathBuf> {
+    let mut dirs = vec![manifest_dir.to_path_buf()];
+    if let Ok(metadata) = MetadataCommand::new()
+        .current_dir(manifest_dir)
+        .no_deps()
+        .exec()
+    {
+        let target_dir = metadata.target_directory.into_std_path_buf();
+        if !target_dir.starts_with(manifest_dir) {
+            dirs.push(target_dir);
+        }
+    }
+    let cargo_home = std::env::var_os("CARGO_HOME")
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|home| home.join(".cargo")));
+    if let Some(cargo_home) = cargo_home {
+        dirs.push(cargo_home);
+    }
+    dirs
+}
+
+/// Builds the bubblewrap arguments that restrict writes to `writable`.
+///
+/// The directories in `writable` must exist, since bubblewrap can only bind
+/// existing paths; [`wrap_command_line`] creates them before calling this.
+#[must_use]
+pub fn bwrap_arguments(writable: &[PathBuf]) -> Vec<String> {
+    let mut args = [
+        "--ro-bind",
+        "/",
+        "/",
+        "--dev-bind",
+        "/dev",
+        "/dev",
+        "--proc",
+        "/proc",
+        "--tmpfs",
+        "/tmp",
+        "--die-with-parent",
+    ]
+    .map(str::to_owned)
+    .to_vec();
+    for dir in writable {
+        let dir = dir.to_string_lossy().into_owned();
+        args.extend(["--bind".to_owned(), dir.clone(), dir]);
+    }
+    args.push("--".to_owned());
+    args
+}
+
+/// Prefixes `command_line` with a bubblewrap invocation that only allows
+/// writes to the directories relevant for the target in `manifest_dir`.
+///
+/// # Errors
+///
+/// Returns an error if bubblewrap is not installed or if one of the writable
+/// directories cannot be created.
+pub fn wrap_command_line(
+    command_line: &str,
+    manifest_dir: &Path,
+    environment: &Environment,
+) -> Result<String, Error> {
+    if !crate::utils::command_is_executable(BWRAP, environment) {
+        return Err(Error::SandboxUnavailable(BWRAP.to_owned()));
+    }
+    let writable = writable_dirs(manifest_dir);
+    for dir in &writable {
+        fs_err::create_dir_all(dir).map_err(Error::IoError)?;
+    }
+    Ok(format!(
+        "{} {command_line}",
+        super::shell_command_line(BWRAP, &bwrap_arguments(&writable))
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use pretty_assertions::assert_eq;
+
+    use super::bwrap_arguments;
+
+    #[test]
+    fn binds_writable_dirs_after_read_only_root() {
+        let args = bwrap_arguments(&[PathBuf::from("/src/foo"), PathBuf::from("/home/u/.cargo")]);
+        assert_eq!(args.first().map(String::as_str), Some("--ro-bind"));
+        assert_eq!(
+            args.iter().rev().take(7).rev().collect::<Vec<_>>(),
+            vec![
+                "--bind",
+                "/src/foo",
+                "/src/foo",
+                "--bind",
+                "/home/u/.cargo",
+                "/home/u/.cargo",
+                "--"
+            ]
+        );
+    }
+}
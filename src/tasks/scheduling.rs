@@ -0,0 +1,479 @@
+//! Prioritization of ready targets for the all-targets scheduler.
+//!
+//! When more targets are ready to run than there are job slots, the order in
+//! which they are started determines how soon their dependents become ready.
+//! The strategies in this module compute a priority for every target from the
+//! dependency graph of the resolved program so that the scheduler can start
+//! the most important targets first.
+//!
+//! [`Scheduler`] tracks how many dependencies of each target are still
+//! outstanding, so that a target becomes ready the moment its last dependency
+//! completes instead of the scheduler polling for ready targets.  Dependency
+//! cycles are rejected before anything runs, naming the targets on the cycle.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crate::error::Error;
+
+/// Strategy used to order targets that are ready to run at the same time.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ScheduleStrategy {
+    /// Start ready targets in the order they appear in the resolved program.
+    #[default]
+    Fifo,
+    /// Start targets with the longest chain of downstream dependents first.
+    CriticalPath,
+    /// Start targets with the largest number of (transitive) dependents first.
+    MostDependents,
+}
+
+/// Computes a priority for each target; higher values are scheduled first.
+///
+/// `targets` lists each target's manifest directory together with the manifest
+/// directories it depends on.  Dependencies that are not themselves part of
+/// `targets` are ignored.  The returned vector is indexed like `targets`.
+#[must_use]
+pub fn target_priorities(
+    targets: &[(PathBuf, Vec<PathBuf>)],
+    strategy: ScheduleStrategy,
+) -> Vec<usize> {
+    let dependents = dependents_by_index(targets);
+    match strategy {
+        ScheduleStrategy::Fifo => vec![0; targets.len()],
+        ScheduleStrategy::CriticalPath => {
+            let mut memo = vec![None; targets.len()];
+            (0..targets.len())
+                .map(|idx| {
+                    let mut visiting = vec![false; targets.len()];
+                    critical_path_length(idx, &dependents, &mut memo, &mut visiting)
+                })
+                .collect()
+        }
+        ScheduleStrategy::MostDependents => (0..targets.len())
+            .map(|idx| transitive_dependent_count(idx, &dependents))
+            .collect(),
+    }
+}
+
+/// Sorts `ready` so that entries with a higher priority come first.
+///
+/// The sort is stable, so entries with equal priority keep their relative
+/// order.  `index_of` extracts the target index used to look up `priorities`.
+pub fn sort_by_priority<T>(ready: &mut [T], priorities: &[usize], index_of: impl Fn(&T) -> usize) {
+    ready.sort_by_key(|entry| {
+        std::cmp::Reverse(priorities.get(index_of(entry)).copied().unwrap_or(0))
+    });
+}
+
+/// Orders targets so that every target comes after its dependencies.
+///
+/// Targets are emitted level by level: first all targets without pending
+/// dependencies in program order, then those whose dependencies are all in
+/// the first level, and so on.  This mirrors the order in which the
+/// all-targets scheduler starts targets when running with a single job.
+///
+/// # Errors
+///
+/// Returns the indices of the targets that could not be ordered because they
+/// are part of, or depend on, a dependency cycle.
+pub fn topological_order(targets: &[(PathBuf, Vec<PathBuf>)]) -> Result<Vec<usize>, Vec<usize>> {
+    let index_by_dir: HashMap<&PathBuf, usize> = targets
+        .iter()
+        .enumerate()
+        .map(|(idx, (dir, _))| (dir, idx))
+        .collect();
+    let mut placed = vec![false; targets.len()];
+    let mut order = Vec::with_capacity(targets.len());
+    loop {
+        let level: Vec<usize> = targets
+            .iter()
+            .enumerate()
+            .filter(|(idx, (_, dependencies))| {
+                !placed.get(*idx).copied().unwrap_or(true)
+                    && dependencies.iter().all(|dep| {
+                        index_by_dir
+                            .get(dep)
+                            .is_none_or(|dep_idx| placed.get(*dep_idx).copied().unwrap_or(false))
+                    })
+            })
+            .map(|(idx, _)| idx)
+            .collect();
+        if level.is_empty() {
+            break;
+        }
+        for idx in level {
+            if let Some(slot) = placed.get_mut(idx) {
+                *slot = true;
+            }
+            order.push(idx);
+        }
+    }
+    if order.len() == targets.len() {
+        Ok(order)
+    } else {
+        Err((0..targets.len())
+            .filter(|idx| !placed.get(*idx).copied().unwrap_or(false))
+            .collect())
+    }
+}
+
+/// Returns the targets of one dependency cycle among `stuck`, the targets
+/// [`topological_order`] could not order, in dependency order and starting
+/// with the first one on the cycle.
+///
+/// Every stuck target has a stuck dependency, so following stuck
+/// dependencies from any of them has to return to a target seen before.
+#[must_use]
+pub fn find_cycle(targets: &[(PathBuf, Vec<PathBuf>)], stuck: &[usize]) -> Vec<usize> {
+    let index_by_dir: HashMap<&PathBuf, usize> = targets
+        .iter()
+        .enumerate()
+        .map(|(idx, (dir, _))| (dir, idx))
+        .collect();
+    let mut path: Vec<usize> = Vec::new();
+    let mut current = stuck.first().copied();
+    while let Some(idx) = current {
+        if let Some(start) = path.iter().position(|&seen| seen == idx) {
+            return path.split_off(start);
+        }
+        path.push(idx);
+        current = targets.get(idx).and_then(|(_, dependencies)| {
+            dependencies
+                .iter()
+                .filter_map(|dep| index_by_dir.get(dep).copied())
+                .find(|dep_idx| stuck.contains(dep_idx))
+        });
+    }
+    path
+}
+
+/// Builds the [`Error::CircularDependency`] for the targets that
+/// [`topological_order`] could not order.
+#[must_use]
+pub fn cycle_error(targets: &[(PathBuf, Vec<PathBuf>)], stuck: &[usize]) -> Error {
+    let cycle = find_cycle(targets, stuck);
+    let names: Vec<String> = cycle
+        .iter()
+        .chain(cycle.first())
+        .filter_map(|idx| targets.get(*idx))
+        .map(|(dir, _)| dir.display().to_string())
+        .collect();
+    Error::CircularDependency(names.join(" -> "))
+}
+
+/// Hands out targets as soon as all of their dependencies completed.
+#[derive(Debug, Clone)]
+pub struct Scheduler {
+    /// For each target, the targets that depend on it directly.
+    dependents: Vec<Vec<usize>>,
+    /// For each target, the number of its dependencies that did not complete.
+    outstanding: Vec<usize>,
+    /// Targets that became ready and were not taken yet, in program order.
+    ready: Vec<usize>,
+    /// Targets that will not run, see [`Scheduler::abandon`].
+    abandoned: Vec<bool>,
+}
+
+impl Scheduler {
+    /// Creates a scheduler for `targets`, where only the targets without
+    /// dependencies among `targets` are ready.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::CircularDependency`] naming the targets on a cycle if
+    /// the targets cannot be ordered.
+    pub fn new(targets: &[(PathBuf, Vec<PathBuf>)]) -> Result<Self, Error> {
+        topological_order(targets).map_err(|stuck| cycle_error(targets, &stuck))?;
+        let dependents = dependents_by_index(targets);
+        let mut outstanding = vec![0_usize; targets.len()];
+        for list in &dependents {
+            for &dependent in list {
+                if let Some(count) = outstanding.get_mut(dependent) {
+                    *count = count.saturating_add(1);
+                }
+            }
+        }
+        let ready = (0..targets.len())
+            .filter(|idx| outstanding.get(*idx) == Some(&0))
+            .collect();
+        Ok(Self {
+            dependents,
+            outstanding,
+            ready,
+            abandoned: vec![false; targets.len()],
+        })
+    }
+
+    /// Returns the targets that became ready since the last call.
+    pub fn take_ready(&mut self) -> Vec<usize> {
+        std::mem::take(&mut self.ready)
+    }
+
+    /// Records that `idx` completed; dependents whose last outstanding
+    /// dependency it was become ready.  Targets that fail or are blocked are
+    /// simply never completed, which keeps their dependents from running.
+    pub fn complete(&mut self, idx: usize) {
+        let Some(dependents) = self.dependents.get(idx) else {
+            return;
+        };
+        for &dependent in dependents {
+            if let Some(count) = self.outstanding.get_mut(dependent) {
+                *count = count.saturating_sub(1);
+                if *count == 0 {
+                    self.ready.push(dependent);
+                }
+            }
+        }
+    }
+
+    /// Records that `idx` will not complete because it failed, was blocked
+    /// or skipped, and returns the targets depending on it, directly or
+    /// transitively, in program order.  Those never become ready; targets
+    /// already returned for another abandoned target are not returned again.
+    pub fn abandon(&mut self, idx: usize) -> Vec<usize> {
+        if let Some(slot) = self.abandoned.get_mut(idx) {
+            *slot = true;
+        }
+        let mut unreached = Vec::new();
+        let mut stack: Vec<usize> = self.dependents.get(idx).cloned().unwrap_or_default();
+        while let Some(current) = stack.pop() {
+            if let Some(slot) = self.abandoned.get_mut(current)
+                && !*slot
+            {
+                *slot = true;
+                unreached.push(current);
+                if let Some(next) = self.dependents.get(current) {
+                    stack.extend(next.iter().copied());
+                }
+            }
+        }
+        unreached.sort_unstable();
+        unreached
+    }
+}
+
+/// Builds the reverse dependency graph: for each target, the indices of the
+/// targets that depend on it directly.
+fn dependents_by_index(targets: &[(PathBuf, Vec<PathBuf>)]) -> Vec<Vec<usize>> {
+    let index_by_dir: HashMap<&PathBuf, usize> = targets
+        .iter()
+        .enumerate()
+        .map(|(idx, (dir, _))| (dir, idx))
+        .collect();
+    let mut dependents = vec![Vec::new(); targets.len()];
+    for (idx, (_, dependencies)) in targets.iter().enumerate() {
+        for dep in dependencies {
+            if let Some(dep_idx) = index_by_dir.get(dep)
+                && let Some(list) = dependents.get_mut(*dep_idx)
+            {
+                list.push(idx);
+            }
+        }
+    }
+    dependents
+}
+
+/// Returns the number of targets on the longest chain of dependents starting
+/// at `idx` (including `idx` itself).
+///
+/// Cycles are broken by treating a target that is already being visited as
+/// contributing nothing; the scheduler reports cycles separately.
+fn critical_path_length(
+    idx: usize,
+    dependents: &[Vec<usize>],
+    memo: &mut [Option<usize>],
+    visiting: &mut [bool],
+) -> usize {
+    if let Some(Some(length)) = memo.get(idx) {
+        return *length;
+    }
+    if visiting.get(idx).copied().unwrap_or(true) {
+        return 0;
+    }
+    if let Some(slot) = visiting.get_mut(idx) {
+        *slot = true;
+    }
+    let longest_downstream = dependents.get(idx).map_or(0, |list| {
+        list.iter()
+            .map(|&dependent| critical_path_length(dependent, dependents, memo, visiting))
+            .max()
+            .unwrap_or(0)
+    });
+    if let Some(slot) = visiting.get_mut(idx) {
+        *slot = false;
+    }
+    let length = longest_downstream.saturating_add(1);
+    if let Some(slot) = memo.get_mut(idx) {
+        *slot = Some(length);
+    }
+    length
+}
+
+/// Returns the number of distinct targets that depend on `idx`, directly or
+/// transitively.
+fn transitive_dependent_count(idx: usize, dependents: &[Vec<usize>]) -> usize {
+    let mut seen = vec![false; dependents.len()];
+    let mut stack: Vec<usize> = dependents.get(idx).cloned().unwrap_or_default();
+    let mut count: usize = 0;
+    while let Some(current) = stack.pop() {
+        if current == idx {
+            continue;
+        }
+        if let Some(slot) = seen.get_mut(current)
+            && !*slot
+        {
+            *slot = true;
+            count = count.saturating_add(1);
+            if let Some(next) = dependents.get(current) {
+                stack.extend(next.iter().copied());
+            }
+        }
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use pretty_assertions::assert_eq;
+
+    use super::{
+        ScheduleStrategy, Scheduler, cycle_error, sort_by_priority, target_priorities,
+        topological_order,
+    };
+
+    /// Builds a target list from `(name, dependencies)` pairs.
+    fn targets(spec: &[(&str, &[&str])]) -> Vec<(PathBuf, Vec<PathBuf>)> {
+        spec.iter()
+            .map(|(name, deps)| {
+                (
+                    PathBuf::from(name),
+                    deps.iter().map(PathBuf::from).collect(),
+                )
+            })
+            .collect()
+    }
+
+    /// `a` has a long chain of dependents (`b` → `c` → `d`) while `e` has four
+    /// direct dependents (`f`, `g`, `h`, `i`) but no longer chain.
+    fn sample() -> Vec<(PathBuf, Vec<PathBuf>)> {
+        targets(&[
+            ("a", &[]),
+            ("e", &[]),
+            ("b", &["a"]),
+            ("c", &["b"]),
+            ("d", &["c"]),
+            ("f", &["e"]),
+            ("g", &["e"]),
+            ("h", &["e"]),
+            ("i", &["e"]),
+        ])
+    }
+
+    #[test]
+    fn fifo_priorities_are_all_equal() {
+        assert_eq!(
+            target_priorities(&sample(), ScheduleStrategy::Fifo),
+            vec![0; 9]
+        );
+    }
+
+    #[test]
+    fn critical_path_counts_longest_downstream_chain() {
+        assert_eq!(
+            target_priorities(&sample(), ScheduleStrategy::CriticalPath),
+            vec![4, 2, 3, 2, 1, 1, 1, 1, 1]
+        );
+    }
+
+    #[test]
+    fn most_dependents_counts_transitive_dependents() {
+        assert_eq!(
+            target_priorities(&sample(), ScheduleStrategy::MostDependents),
+            vec![3, 4, 2, 1, 0, 0, 0, 0, 0]
+        );
+    }
+
+    #[test]
+    fn cycles_do_not_loop_forever() {
+        let cyclic = targets(&[("a", &["b"]), ("b", &["a"])]);
+        assert_eq!(
+            target_priorities(&cyclic, ScheduleStrategy::CriticalPath),
+            vec![2, 1]
+        );
+        assert_eq!(
+            target_priorities(&cyclic, ScheduleStrategy::MostDependents),
+            vec![1, 1]
+        );
+    }
+
+    #[test]
+    fn sort_by_priority_is_stable() {
+        let mut ready = vec![0_usize, 1, 2, 3];
+        sort_by_priority(&mut ready, &[1, 5, 1, 5], |idx| *idx);
+        assert_eq!(ready, vec![1, 3, 0, 2]);
+    }
+
+    #[test]
+    fn topological_order_places_dependencies_first() {
+        assert_eq!(
+            topological_order(&sample()),
+            Ok(vec![0, 1, 2, 5, 6, 7, 8, 3, 4])
+        );
+    }
+
+    #[test]
+    fn topological_order_reports_cycle_members() {
+        let cyclic = targets(&[("a", &[]), ("b", &["c"]), ("c", &["b"]), ("d", &["b"])]);
+        assert_eq!(topological_order(&cyclic), Err(vec![1, 2, 3]));
+    }
+
+    #[test]
+    fn cycle_error_names_only_the_cycle() {
+        let cyclic = targets(&[("a", &[]), ("b", &["c"]), ("c", &["b"]), ("d", &["b"])]);
+        assert_eq!(
+            cycle_error(&cyclic, &[1, 2, 3]).to_string(),
+            "circular dependency between targets: b -> c -> b"
+        );
+        let through_dependent = targets(&[("x", &["y"]), ("y", &["z"]), ("z", &["y"])]);
+        assert_eq!(
+            cycle_error(&through_dependent, &[0, 1, 2]).to_string(),
+            "circular dependency between targets: y -> z -> y"
+        );
+    }
+
+    #[test]
+    fn scheduler_releases_targets_as_dependencies_complete()
+    -> Result<(), Box<dyn std::error::Error>> {
+        let mut scheduler = Scheduler::new(&sample())?;
+        assert_eq!(scheduler.take_ready(), [0, 1]);
+        assert_eq!(scheduler.take_ready(), Vec::<usize>::new());
+        scheduler.complete(1);
+        assert_eq!(scheduler.take_ready(), [5, 6, 7, 8]);
+        scheduler.complete(0);
+        assert_eq!(scheduler.take_ready(), [2]);
+        // `c` never becomes ready while `b` does not complete, e.g. because
+        // it failed
+        scheduler.complete(5);
+        assert_eq!(scheduler.take_ready(), Vec::<usize>::new());
+        assert!(
+            Scheduler::new(&targets(&[("a", &["a"])])).is_err(),
+            "a target depending on itself is a cycle"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn abandoned_targets_return_their_dependents_once() -> Result<(), Box<dyn std::error::Error>> {
+        let mut scheduler = Scheduler::new(&sample())?;
+        assert_eq!(scheduler.take_ready(), [0, 1]);
+        assert_eq!(scheduler.abandon(0), [2, 3, 4]);
+        // `b` was already returned for `a`, and so were its dependents
+        assert_eq!(scheduler.abandon(2), Vec::<usize>::new());
+        scheduler.complete(1);
+        assert_eq!(scheduler.take_ready(), [5, 6, 7, 8]);
+        Ok(())
+    }
+}
@@ -0,0 +1,63 @@
+//! Letting a crate opt out of fleet operations for a while.
+//!
+//! When a crate is temporarily broken, nobody wants to edit the registered
+//! targets or every task to leave it out.  Creating a `.cargo-for-each-skip`
+//! file in its manifest directory is enough: `task run` (and everything built
+//! on it, like `hygiene`) does not start a workspace, standalone crate or
+//! member crate with such a marker and logs it as skipped by marker.  The
+//! first line of the file, if any, is shown as the reason.
+//!
+//! Like a target skipped with `--interactive`, a skipped target is left as it
+//! is, so the targets depending on it do not run either, and a workspace with
+//! a skipped member stops after its `for crate in workspace` loop.  Once the
+//! marker is removed, running the task again picks the target up.  Crates
+//! that should be left out of tasks for good set `skip = true` in their
+//! package metadata instead.
+
+use std::path::Path;
+
+/// Name of the marker file.
+pub const FILE_NAME: &str = ".cargo-for-each-skip";
+
+/// Returns why the target in `manifest_dir` is skipped if it contains a
+/// marker, e.g. `skipped by marker: waiting for a fix upstream`, or `None` if
+/// it does not.
+#[must_use]
+pub fn reason(manifest_dir: &Path) -> Option<String> {
+    let marker = manifest_dir.join(FILE_NAME);
+    if !marker.is_file() {
+        return None;
+    }
+    let content = fs_err::read_to_string(&marker).unwrap_or_default();
+    Some(
+        match content.lines().map(str::trim).find(|line| !line.is_empty()) {
+            Some(line) => format!("skipped by marker: {line}"),
+            None => "skipped by marker".to_owned(),
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::{FILE_NAME, reason};
+
+    #[test]
+    fn markers_give_the_reason() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempfile::tempdir()?;
+        let dir = temp_dir.path();
+        assert_eq!(reason(dir), None);
+        fs_err::write(dir.join(FILE_NAME), "")?;
+        assert_eq!(reason(dir).as_deref(), Some("skipped by marker"));
+        fs_err::write(
+            dir.join(FILE_NAME),
+            "\nwaiting for a fix upstream\nsee #12\n",
+        )?;
+        assert_eq!(
+            reason(dir).as_deref(),
+            Some("skipped by marker: waiting for a fix upstream")
+        );
+        Ok(())
+    }
+}
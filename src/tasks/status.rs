@@ -0,0 +1,371 @@
+//! A compact progress matrix of a task.
+//!
+//! `task describe` prints every statement of every target as a tree, which
+//! gets long for tasks with many targets.  `task status --name <task>` prints
+//! one row per target and one column per top-level statement of the
+//! `for workspace` or `for crate` block instead, with a single marker per
+//! cell, followed by the labels of the columns.  Nested statements (`if`
+//! branches, `with_env_file` bodies and `for crate in workspace` members) are
+//! summarized in the cell of the statement containing them.  `phase` markers
+//! have no state and get no column.
+
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+use super::failure_history::failed_below;
+use super::{
+    crate_stmt_label, is_crate_stmt_completed, is_wait_barrier_waiting,
+    is_workspace_stmt_completed, workspace_stmt_label,
+};
+use crate::program::ast::crate_ctx::CrateStatement;
+use crate::program::ast::workspace_ctx::WorkspaceStatement;
+use crate::program::cursor::{CursorSegment, ProgramCursor};
+use crate::program::resolve::ResolvedProgram;
+
+/// The progress of one statement for one target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Cell {
+    /// The statement and everything nested in it completed.
+    Done,
+    /// A `run` step in it failed or a `wait_until` step timed out.
+    Failed,
+    /// It is a `wait_for_continue` barrier that was reached.
+    Waiting,
+    /// Some of its nested statements ran, but not all of them.
+    Started,
+    /// It did not run yet.
+    Pending,
+}
+
+impl Cell {
+    /// Returns the marker shown in the matrix.
+    #[must_use]
+    pub const fn marker(self) -> char {
+        match self {
+            Self::Done => '✓',
+            Self::Failed => '✗',
+            Self::Waiting => 'w',
+            Self::Started => '~',
+            Self::Pending => '·',
+        }
+    }
+
+    /// Determines the cell of a statement with state in `state_dir`.
+    fn of(completed: bool, is_barrier: bool, state_dir: &Path) -> Self {
+        if completed {
+            Self::Done
+        } else if failed_below(state_dir) {
+            Self::Failed
+        } else if is_barrier && is_wait_barrier_waiting(state_dir) {
+            Self::Waiting
+        } else if state_dir.exists() {
+            Self::Started
+        } else {
+            Self::Pending
+        }
+    }
+}
+
+/// The progress of the targets of one kind.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Matrix {
+    /// The index and label of each statement with a column.
+    pub columns: Vec<(usize, String)>,
+    /// The manifest directory of each target and its cell in each column.
+    pub rows: Vec<(PathBuf, Vec<Cell>)>,
+}
+
+/// Computes the matrix of the workspaces of `resolved` running `stmts`.
+#[must_use]
+pub fn workspace_matrix(
+    stmts: &[WorkspaceStatement],
+    resolved: &ResolvedProgram,
+    state_base: &Path,
+) -> Matrix {
+    let columns: Vec<(usize, &WorkspaceStatement)> = stmts
+        .iter()
+        .enumerate()
+        .filter(|(_, stmt)| !matches!(stmt, WorkspaceStatement::Phase(_)))
+        .collect();
+    let rows = resolved
+        .workspace_executions
+        .iter()
+        .enumerate()
+        .map(|(ws_idx, ws_exec)| {
+            let prefix = ProgramCursor::new().with(CursorSegment::WorkspaceIteration(ws_idx));
+            let cells = columns
+                .iter()
+                .map(|(i, stmt)| {
+                    let cursor = prefix.clone().with(CursorSegment::Statement(*i));
+                    Cell::of(
+                        is_workspace_stmt_completed(
+                            stmt,
+                            &cursor,
+                            &ws_exec.member_crates,
+                            state_base,
+                        ),
+                        matches!(stmt, WorkspaceStatement::WaitForContinue(_)),
+                        &state_base.join(cursor.to_path()),
+                    )
+                })
+                .collect();
+            (ws_exec.manifest_dir.clone(), cells)
+        })
+        .collect();
+    Matrix {
+        columns: columns
+            .into_iter()
+            .map(|(i, stmt)| (i, workspace_stmt_label(stmt)))
+            .collect(),
+        rows,
+    }
+}
+
+/// Computes the matrix of the standalone crates of `resolved` running
+/// `stmts`.
+#[must_use]
+pub fn crate_matrix(
+    stmts: &[CrateStatement],
+    resolved: &ResolvedProgram,
+    state_base: &Path,
+) -> Matrix {
+    let columns: Vec<(usize, &CrateStatement)> = stmts
+        .iter()
+        .enumerate()
+        .filter(|(_, stmt)| !matches!(stmt, CrateStatement::Phase(_)))
+        .collect();
+    let rows = resolved
+        .crate_executions
+        .iter()
+        .enumerate()
+        .map(|(c_idx, crate_exec)| {
+            let prefix = ProgramCursor::new().with(CursorSegment::CrateIteration(c_idx));
+            let cells = columns
+                .iter()
+                .map(|(i, stmt)| {
+                    let cursor = prefix.clone().with(CursorSegment::Statement(*i));
+                    Cell::of(
+                        is_crate_stmt_completed(stmt, &cursor, state_base),
+                        matches!(stmt, CrateStatement::WaitForContinue(_)),
+                        &state_base.join(cursor.to_path()),
+                    )
+                })
+                .collect();
+            (crate_exec.manifest_dir.clone(), cells)
+        })
+        .collect();
+    Matrix {
+        columns: columns
+            .into_iter()
+            .map(|(i, stmt)| (i, crate_stmt_label(stmt)))
+            .collect(),
+        rows,
+    }
+}
+
+/// The JSON output of `task status`.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct ProgressView {
+    /// The name of the task.
+    pub name: String,
+    /// The process running the task, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub running: Option<super::run_lock::Holder>,
+    /// The progress of the workspaces.
+    pub workspaces: Vec<TargetView>,
+    /// The progress of the standalone crates.
+    pub crates: Vec<TargetView>,
+}
+
+/// A target in the JSON output of `task status`.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct TargetView {
+    /// The directory that contains the target's `Cargo.toml`.
+    pub manifest_dir: PathBuf,
+    /// The progress in each top-level statement with a column.
+    pub statements: Vec<StatementView>,
+}
+
+/// The progress of a target in one statement, see [`TargetView`].
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct StatementView {
+    /// The index of the statement, the `s<n>` of its cursor.
+    pub index: usize,
+    /// The label of the statement.
+    pub label: String,
+    /// The progress of the target in it.
+    pub state: Cell,
+}
+
+/// Returns the rows of `matrix` as views for the JSON output.
+#[must_use]
+pub fn views(matrix: &Matrix) -> Vec<TargetView> {
+    matrix
+        .rows
+        .iter()
+        .map(|(dir, cells)| TargetView {
+            manifest_dir: dir.clone(),
+            statements: matrix
+                .columns
+                .iter()
+                .zip(cells)
+                .map(|((index, label), cell)| StatementView {
+                    index: *index,
+                    label: label.clone(),
+                    state: *cell,
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+/// Renders `matrix` as aligned text, followed by the labels of the columns.
+#[must_use]
+pub fn render(matrix: &Matrix) -> String {
+    let headers: Vec<String> = matrix
+        .columns
+        .iter()
+        .map(|(i, _)| format!("s{i}"))
+        .collect();
+    let target_width = matrix
+        .rows
+        .iter()
+        .map(|(dir, _)| dir.display().to_string().chars().count())
+        .chain(std::iter::once("target".len()))
+        .max()
+        .unwrap_or(0);
+    let mut out = String::new();
+    let _ignored = write!(out, "{:target_width$}", "target");
+    for header in &headers {
+        let _ignored = write!(out, "  {header}");
+    }
+    let _ignored = writeln!(out);
+    for (dir, cells) in &matrix.rows {
+        let mut line = format!("{:target_width$}", dir.display().to_string());
+        for (header, cell) in headers.iter().zip(cells) {
+            let width = header.len();
+            let _ignored = write!(line, "  {:width$}", cell.marker());
+        }
+        let _ignored = writeln!(out, "{}", line.trim_end());
+    }
+    for ((_, label), header) in matrix.columns.iter().zip(&headers) {
+        let _ignored = writeln!(out, "  {header:<4} {label}");
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use pretty_assertions::assert_eq;
+
+    use super::{Cell, Matrix, StatementView, TargetView, crate_matrix, render, views};
+    use crate::program::GlobalStatement;
+    use crate::program::parser::parse;
+    use crate::program::resolve::{ResolvedCrateExecution, ResolvedProgram};
+
+    #[test]
+    fn cells_follow_the_state_directory() -> Result<(), Box<dyn std::error::Error>> {
+        let src = r#"
+            select crates;
+            for crate { run "true"; phase "check"; run "false"; wait_for_continue "review"; }
+        "#;
+        let program = parse(src, "test.cfe").map_err(|_errors| "parse failed")?;
+        let Some(GlobalStatement::ForCrate(block)) = program.statements.get(1) else {
+            return Err("expected a for crate block".into());
+        };
+        let resolved = ResolvedProgram {
+            workspace_executions: vec![],
+            crate_executions: ["/a", "/b"]
+                .into_iter()
+                .map(|dir| ResolvedCrateExecution {
+                    manifest_dir: PathBuf::from(dir),
+                    dependencies: vec![],
+                    package: None,
+                })
+                .collect(),
+        };
+        let temp_dir = tempfile::tempdir()?;
+        let ok = temp_dir.path().join("c0").join("s0");
+        fs_err::create_dir_all(&ok)?;
+        fs_err::write(ok.join("exit_status"), "0")?;
+        let failed = temp_dir.path().join("c0").join("s2");
+        fs_err::create_dir_all(&failed)?;
+        fs_err::write(failed.join("exit_status"), "1")?;
+        fs_err::create_dir_all(temp_dir.path().join("c0").join("s3"))?;
+
+        let matrix = crate_matrix(&block.statements, &resolved, temp_dir.path());
+        assert_eq!(
+            matrix.rows,
+            [
+                (
+                    PathBuf::from("/a"),
+                    vec![Cell::Done, Cell::Failed, Cell::Waiting]
+                ),
+                (
+                    PathBuf::from("/b"),
+                    vec![Cell::Pending, Cell::Pending, Cell::Pending]
+                ),
+            ]
+        );
+        let labels: Vec<usize> = matrix.columns.iter().map(|(i, _)| *i).collect();
+        assert_eq!(labels, [0, 2, 3]);
+        Ok(())
+    }
+
+    #[test]
+    fn matrix_is_rendered_with_column_labels() {
+        let matrix = Matrix {
+            columns: vec![(0, "run \"a\"".to_owned()), (2, "run \"b\"".to_owned())],
+            rows: vec![
+                (PathBuf::from("/src/long"), vec![Cell::Done, Cell::Started]),
+                (PathBuf::from("/x"), vec![Cell::Failed, Cell::Pending]),
+            ],
+        };
+        assert_eq!(
+            render(&matrix),
+            "target     s0  s2\n\
+             /src/long  ✓   ~\n\
+             /x         ✗   ·\n  \
+             s0   run \"a\"\n  \
+             s2   run \"b\"\n"
+        );
+    }
+
+    #[test]
+    fn views_name_the_state_of_each_statement() -> Result<(), Box<dyn std::error::Error>> {
+        let matrix = Matrix {
+            columns: vec![(0, "run \"a\"".to_owned()), (2, "run \"b\"".to_owned())],
+            rows: vec![(PathBuf::from("/x"), vec![Cell::Done, Cell::Waiting])],
+        };
+        let statement = |index, label: &str, state| StatementView {
+            index,
+            label: label.to_owned(),
+            state,
+        };
+        let targets = views(&matrix);
+        assert_eq!(
+            targets,
+            [TargetView {
+                manifest_dir: PathBuf::from("/x"),
+                statements: vec![
+                    statement(0, "run \"a\"", Cell::Done),
+                    statement(2, "run \"b\"", Cell::Waiting),
+                ],
+            }]
+        );
+        assert_eq!(
+            serde_json::to_value(&targets)?,
+            serde_json::json!([{
+                "manifest_dir": "/x",
+                "statements": [
+                    { "index": 0, "label": "run \"a\"", "state": "done" },
+                    { "index": 2, "label": "run \"b\"", "state": "waiting" },
+                ],
+            }])
+        );
+        Ok(())
+    }
+}
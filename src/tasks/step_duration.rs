@@ -0,0 +1,103 @@
+//! How long `run` steps took.
+//!
+//! After the command of a `run` step exits (or is killed), the time it ran
+//! is written to the step's `duration_ms` file in milliseconds.  Waiting for
+//! an `exclusive` resource or a `rate_limit` is not included, and a retried
+//! step keeps the duration of its last attempt.  `task report --name` shows
+//! the durations of all steps of a task and `task status --verbose` those of
+//! the executed `run` steps.
+//!
+//! When the last attempt started and ended is written to `started_at` and
+//! `finished_at` as RFC 3339 timestamps.  From these `task report --name` and
+//! `task status` add up the wall-clock time of each target, from the start
+//! of its first step to the end of its last, to show which targets dominate
+//! a run.
+
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use crate::error::Error;
+
+/// Name of the file recording the duration in a step's state directory.
+const FILE_NAME: &str = "duration_ms";
+
+/// Name of the file recording when the step started.
+const STARTED_FILE: &str = "started_at";
+
+/// Name of the file recording when the step finished.
+const FINISHED_FILE: &str = "finished_at";
+
+/// Records in `state_dir` that the step ran for `duration`.
+///
+/// # Errors
+///
+/// Returns [`Error::CouldNotWriteStateFile`] if the file cannot be written.
+pub fn write(state_dir: &Path, duration: Duration) -> Result<(), Error> {
+    let path = state_dir.join(FILE_NAME);
+    fs_err::write(&path, duration.as_millis().to_string())
+        .map_err(|e| Error::CouldNotWriteStateFile(path, e))
+}
+
+/// Returns how long the step in `state_dir` ran, if that was recorded.
+#[must_use]
+pub fn read(state_dir: &Path) -> Option<Duration> {
+    fs_err::read_to_string(state_dir.join(FILE_NAME))
+        .ok()
+        .and_then(|millis| millis.trim().parse().ok())
+        .map(Duration::from_millis)
+}
+
+/// Records in `state_dir` that the step ran from `started` to `finished`.
+///
+/// # Errors
+///
+/// Returns [`Error::CouldNotWriteStateFile`] if a file cannot be written.
+pub fn write_times(
+    state_dir: &Path,
+    started: SystemTime,
+    finished: SystemTime,
+) -> Result<(), Error> {
+    for (file, time) in [(STARTED_FILE, started), (FINISHED_FILE, finished)] {
+        let path = state_dir.join(file);
+        fs_err::write(&path, humantime::format_rfc3339_millis(time).to_string())
+            .map_err(|e| Error::CouldNotWriteStateFile(path, e))?;
+    }
+    Ok(())
+}
+
+/// Returns when the step in `state_dir` started and finished, if that was
+/// recorded.
+#[must_use]
+pub fn read_times(state_dir: &Path) -> Option<(SystemTime, SystemTime)> {
+    let read = |file| {
+        let time = fs_err::read_to_string(state_dir.join(file)).ok()?;
+        humantime::parse_rfc3339(time.trim()).ok()
+    };
+    Some((read(STARTED_FILE)?, read(FINISHED_FILE)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use pretty_assertions::assert_eq;
+
+    use super::{read, read_times, write, write_times};
+
+    #[test]
+    fn durations_are_recorded() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempfile::tempdir()?;
+        assert_eq!(read(temp_dir.path()), None);
+        write(temp_dir.path(), Duration::from_millis(65_432))?;
+        assert_eq!(read(temp_dir.path()), Some(Duration::from_millis(65_432)));
+
+        assert_eq!(read_times(temp_dir.path()), None);
+        let started = humantime::parse_rfc3339("2024-05-01T12:00:00.250Z")?;
+        let finished = started
+            .checked_add(Duration::from_millis(65_432))
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+        write_times(temp_dir.path(), started, finished)?;
+        assert_eq!(read_times(temp_dir.path()), Some((started, finished)));
+        Ok(())
+    }
+}
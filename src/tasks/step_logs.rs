@@ -0,0 +1,169 @@
+//! The raw output of `run` steps.
+//!
+//! Recordings keep what the terminal showed, including escape sequences and
+//! progress bars, which makes them awkward to search.  The wrapper script of
+//! a `run` step therefore also copies the standard output and error of the
+//! command to `stdout.log` and `stderr.log` in the step's state directory,
+//! while still passing them on to the terminal (and the recording).  `task
+//! logs` prints these files for a target, optionally only for one of its
+//! top-level statements or only their last lines.
+//!
+//! The copies are made with `tee` reading from named pipes, so the command
+//! writes to pipes instead of a terminal and tools that check for one print
+//! without colors or progress bars.
+
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+/// Name of the copy of the standard output in a step's state directory.
+pub const STDOUT_FILE: &str = "stdout.log";
+
+/// Name of the copy of the standard error in a step's state directory.
+pub const STDERR_FILE: &str = "stderr.log";
+
+/// A log in the JSON output of `task logs`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LogView {
+    /// The cursor of the step that wrote the log.
+    pub cursor: String,
+    /// The name of the log file, [`STDOUT_FILE`] or [`STDERR_FILE`].
+    pub file: String,
+    /// The content of the log, or its last lines with `--tail`.
+    pub content: String,
+}
+
+/// Environment variable passing the path of the standard output copy to the
+/// wrapper script.
+pub const STDOUT_VARIABLE: &str = "CARGO_FOR_EACH_STDOUT_LOG";
+
+/// Environment variable passing the path of the standard error copy to the
+/// wrapper script.
+pub const STDERR_VARIABLE: &str = "CARGO_FOR_EACH_STDERR_LOG";
+
+/// Which output of a step to show.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Stream {
+    /// Standard output followed by standard error.
+    #[default]
+    Both,
+    /// Only standard output.
+    Stdout,
+    /// Only standard error.
+    Stderr,
+}
+
+impl Stream {
+    /// Returns the names of the log files of this stream.
+    #[must_use]
+    pub const fn files(self) -> &'static [&'static str] {
+        match self {
+            Self::Both => &[STDOUT_FILE, STDERR_FILE],
+            Self::Stdout => &[STDOUT_FILE],
+            Self::Stderr => &[STDERR_FILE],
+        }
+    }
+}
+
+/// Returns the shell commands running `script_line` with its output copied
+/// to the files named by [`STDOUT_VARIABLE`] and [`STDERR_VARIABLE`].  The
+/// exit status of the command is left in `$rc`.
+#[must_use]
+pub fn capture(script_line: &str) -> String {
+    let out = format!("\"${STDOUT_VARIABLE}\"");
+    let err = format!("\"${STDERR_VARIABLE}\"");
+    let out_fifo = format!("\"${STDOUT_VARIABLE}.fifo\"");
+    let err_fifo = format!("\"${STDERR_VARIABLE}.fifo\"");
+    format!(
+        "rm -f {out_fifo} {err_fifo}\n\
+         mkfifo {out_fifo} {err_fifo}\n\
+         tee {out} < {out_fifo} &\n\
+         tee {err} < {err_fifo} >&2 &\n\
+         {script_line} > {out_fifo} 2> {err_fifo}\n\
+         rc=$?\n\
+         wait\n\
+         rm -f {out_fifo} {err_fifo}"
+    )
+}
+
+/// Returns `Some` if a log was written for the step in `state_dir`.
+#[must_use]
+pub fn find(state_dir: &Path) -> Option<()> {
+    (state_dir.join(STDOUT_FILE).exists() || state_dir.join(STDERR_FILE).exists()).then_some(())
+}
+
+/// Returns the state directories with logs in `dir` and below it, relative
+/// to `dir`, in program order.
+#[must_use]
+pub fn all_below(dir: &Path) -> Vec<PathBuf> {
+    super::invocation::records_below(dir, find)
+        .into_iter()
+        .map(|(path, ())| path)
+        .collect()
+}
+
+/// Returns the last `count` lines of `content`.
+#[must_use]
+pub fn tail(content: &str, count: usize) -> &str {
+    if count == 0 {
+        return "";
+    }
+    let trimmed = content.strip_suffix('\n').unwrap_or(content);
+    let start = trimmed
+        .rmatch_indices('\n')
+        .nth(count.saturating_sub(1))
+        .map_or(0, |(i, _)| i.saturating_add(1));
+    content.get(start..).unwrap_or(content)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+
+    use pretty_assertions::assert_eq;
+
+    use super::{
+        STDERR_FILE, STDERR_VARIABLE, STDOUT_FILE, STDOUT_VARIABLE, all_below, capture, tail,
+    };
+
+    #[test]
+    fn output_is_copied_to_the_logs() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempfile::tempdir()?;
+        let state_dir = temp_dir.path().join("c0").join("s1");
+        fs_err::create_dir_all(&state_dir)?;
+        let script = format!(
+            "{}\nexit \"$rc\"\n",
+            capture("sh -c 'echo out; echo err >&2; exit 3'")
+        );
+        let output = Command::new("sh")
+            .arg("-c")
+            .arg(script)
+            .env(STDOUT_VARIABLE, state_dir.join(STDOUT_FILE))
+            .env(STDERR_VARIABLE, state_dir.join(STDERR_FILE))
+            .output()?;
+        assert_eq!(output.status.code(), Some(3));
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "out\n");
+        assert_eq!(String::from_utf8_lossy(&output.stderr), "err\n");
+        assert_eq!(
+            fs_err::read_to_string(state_dir.join(STDOUT_FILE))?,
+            "out\n"
+        );
+        assert_eq!(
+            fs_err::read_to_string(state_dir.join(STDERR_FILE))?,
+            "err\n"
+        );
+        assert_eq!(
+            all_below(temp_dir.path()),
+            [std::path::Path::new("c0").join("s1")]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn tails_are_the_last_lines() {
+        assert_eq!(tail("a\nb\nc\n", 2), "b\nc\n");
+        assert_eq!(tail("a\nb\nc", 2), "b\nc");
+        assert_eq!(tail("a\nb\n", 5), "a\nb\n");
+        assert_eq!(tail("a\nb\n", 0), "");
+    }
+}
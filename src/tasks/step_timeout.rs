@@ -0,0 +1,303 @@
+//! Time limits of `run` statements.
+//!
+//! A `run` statement with `timeout <seconds>` is killed once it has run for
+//! that long, so a hanging `cargo test` fails its target instead of blocking
+//! the whole task forever.  Killing only the recorder or the wrapper script
+//! would leave the actual command running, so every process started below it
+//! is killed as well.  The limit that was exceeded is written to the step's
+//! `timed_out` file, which `task describe` shows with the failure and which
+//! is removed when the step runs again.
+//!
+//! Commands are also killed this way when the run is cancelled (see
+//! [`super::cancellation`]).
+
+use std::path::Path;
+use std::process::{Output, Stdio};
+use std::time::Duration;
+
+use tokio::io::{AsyncBufReadExt as _, AsyncRead, AsyncReadExt as _};
+
+use crate::error::Error;
+
+/// Name of the file recording the exceeded limit in a step's state directory.
+const TIMED_OUT_FILE: &str = "timed_out";
+
+/// How a command started by [`execute`] ended.
+#[derive(Debug)]
+pub enum Outcome {
+    /// The command exited by itself.
+    Exited(Output),
+    /// The command was killed after running for the given number of seconds.
+    TimedOut(u64),
+    /// The command was killed because the run was cancelled.
+    Interrupted,
+}
+
+/// Runs `command` like [`crate::utils::execute_command`], but kills it and
+/// all processes it started after `seconds`, if given, or once the run is
+/// cancelled with `cancellation`.
+///
+/// # Errors
+///
+/// Returns [`Error::CommandExecutionFailed`] if the command cannot be
+/// started or waited for.
+pub async fn execute(
+    command: std::process::Command,
+    environment: &crate::Environment,
+    cancellation: &super::cancellation::Token,
+    cwd: &Path,
+    seconds: Option<u64>,
+) -> Result<Outcome, Error> {
+    let mut command = tokio::process::Command::from(command);
+    if environment.suppress_subprocess_output {
+        command
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+    } else {
+        command
+            .stdin(Stdio::inherit())
+            .stdout(Stdio::inherit())
+            .stderr(Stdio::inherit());
+    }
+    let description = format!("{command:?}");
+    let failed = |e: std::io::Error| {
+        Error::CommandExecutionFailed(description.clone(), cwd.to_path_buf(), e)
+    };
+    let mut child = command.spawn().map_err(failed)?;
+    let prefix = super::progress::prefix();
+    let stdout = tokio::spawn(read_all(child.stdout.take(), prefix.clone(), false));
+    let stderr = tokio::spawn(read_all(child.stderr.take(), prefix, true));
+
+    let limit = async {
+        match seconds {
+            Some(seconds) => tokio::time::sleep(Duration::from_secs(seconds)).await,
+            None => std::future::pending().await,
+        }
+    };
+    // `Err` holds why the command has to be killed
+    let exited = tokio::select! {
+        status = child.wait() => Ok(status.map_err(failed)?),
+        () = limit => Err(Outcome::TimedOut(seconds.unwrap_or_default())),
+        () = cancellation.cancelled() => Err(Outcome::Interrupted),
+    };
+    let status = match exited {
+        Ok(status) => status,
+        Err(outcome) => {
+            if let Some(pid) = child.id() {
+                kill_tree(pid);
+            }
+            // reap the killed process; its exit status does not matter
+            if let Err(e) = child.wait().await {
+                tracing::warn!("Could not wait for the killed command: {e}");
+            }
+            return Ok(outcome);
+        }
+    };
+    let output = Output {
+        status,
+        stdout: stdout.await.unwrap_or_default(),
+        stderr: stderr.await.unwrap_or_default(),
+    };
+    tracing::trace!(
+        "Command stdout: {}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    tracing::trace!(
+        "Command stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    Ok(Outcome::Exited(output))
+}
+
+/// Reads `pipe` to the end, if there is one; with a `prefix` each line is
+/// printed as it arrives (see [`crate::utils::print_prefixed`]).
+async fn read_all<R: AsyncRead + Unpin>(
+    pipe: Option<R>,
+    prefix: Option<String>,
+    stderr: bool,
+) -> Vec<u8> {
+    let mut buf = Vec::new();
+    let Some(mut pipe) = pipe else {
+        return buf;
+    };
+    let result = match prefix {
+        None => pipe.read_to_end(&mut buf).await.map(drop),
+        Some(prefix) => {
+            let mut reader = tokio::io::BufReader::new(pipe);
+            loop {
+                let start = buf.len();
+                match reader.read_until(b'\n', &mut buf).await {
+                    Ok(0) => break Ok(()),
+                    Ok(_) => crate::utils::print_prefixed(
+                        &prefix,
+                        buf.get(start..).unwrap_or_default(),
+                        stderr,
+                    ),
+                    Err(e) => break Err(e),
+                }
+            }
+        }
+    };
+    if let Err(e) = result {
+        tracing::warn!("Could not read the output of the command: {e}");
+    }
+    buf
+}
+
+/// Returns `root` and all of its descendants according to `ps`, parents
+/// before their children.
+fn process_tree(root: u32) -> Vec<u32> {
+    let parents: Vec<(u32, u32)> = std::process::Command::new("ps")
+        .args(["-A", "-o", "pid=", "-o", "ppid="])
+        .output()
+        .map(|output| {
+            String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .filter_map(|line| {
+                    let mut fields = line.split_whitespace();
+                    Some((fields.next()?.parse().ok()?, fields.next()?.parse().ok()?))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    let mut tree = vec![root];
+    let mut next = 0;
+    while let Some(&parent) = tree.get(next) {
+        for (pid, ppid) in &parents {
+            if *ppid == parent && !tree.contains(pid) {
+                tree.push(*pid);
+            }
+        }
+        next = next.saturating_add(1);
+    }
+    tree
+}
+
+/// Kills the process `pid` and everything it started.
+fn kill_tree(pid: u32) {
+    let pids: Vec<String> = process_tree(pid).iter().map(u32::to_string).collect();
+    let killed = std::process::Command::new("kill")
+        .arg("-KILL")
+        .args(&pids)
+        .stderr(Stdio::null())
+        .status();
+    if let Err(e) = killed {
+        tracing::warn!("Could not kill process {pid}: {e}");
+    }
+}
+
+/// Records in `state_dir` that the step was killed after `seconds`.
+///
+/// # Errors
+///
+/// Returns [`Error::CouldNotWriteStateFile`] if the file cannot be written.
+pub fn mark(state_dir: &Path, seconds: u64) -> Result<(), Error> {
+    let path = state_dir.join(TIMED_OUT_FILE);
+    fs_err::write(&path, seconds.to_string()).map_err(|e| Error::CouldNotWriteStateFile(path, e))
+}
+
+/// Removes the record of an earlier timeout from `state_dir`.
+///
+/// # Errors
+///
+/// Returns [`Error::CouldNotRemoveStateFile`] if the file exists but cannot
+/// be removed.
+pub fn clear(state_dir: &Path) -> Result<(), Error> {
+    let path = state_dir.join(TIMED_OUT_FILE);
+    match fs_err::remove_file(&path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            Err(Error::CouldNotRemoveStateFile(path, e))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Returns the limit the step in `state_dir` exceeded when it was killed, if
+/// it was.
+#[must_use]
+pub fn read(state_dir: &Path) -> Option<u64> {
+    fs_err::read_to_string(state_dir.join(TIMED_OUT_FILE))
+        .ok()
+        .and_then(|seconds| seconds.trim().parse().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use pretty_assertions::assert_eq;
+
+    use super::{Outcome, clear, execute, mark, read};
+
+    #[tokio::test]
+    async fn commands_are_killed_with_their_children() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempfile::tempdir()?;
+        let environment = crate::Environment::mock(&temp_dir)?;
+        let cancellation = crate::tasks::cancellation::Token::default();
+        let mut quick = std::process::Command::new("sh");
+        quick.args(["-c", "echo done"]);
+        let Outcome::Exited(output) = execute(
+            quick,
+            &environment,
+            &cancellation,
+            temp_dir.path(),
+            Some(30),
+        )
+        .await?
+        else {
+            return Err("the quick command was killed".into());
+        };
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "done\n");
+
+        let started = Instant::now();
+        let pid_file = temp_dir.path().join("pid");
+        let mut hanging = std::process::Command::new("sh");
+        hanging
+            .arg("-c")
+            .arg(r#"sleep 60 & echo $! > "$1"; wait"#)
+            .arg("sh")
+            .arg(&pid_file);
+        let outcome = execute(
+            hanging,
+            &environment,
+            &cancellation,
+            temp_dir.path(),
+            Some(1),
+        )
+        .await?;
+        assert!(
+            matches!(outcome, Outcome::TimedOut(1)),
+            "the command is killed"
+        );
+        assert!(
+            started.elapsed().as_secs() < 30,
+            "the command is killed after its timeout"
+        );
+        let sleep_pid: u32 = fs_err::read_to_string(&pid_file)?.trim().parse()?;
+        // the signal takes a moment to be delivered
+        for _ in 0..50 {
+            if !crate::tasks::run_lock::is_alive(sleep_pid) {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        }
+        assert!(
+            !crate::tasks::run_lock::is_alive(sleep_pid),
+            "the children of the command are killed as well"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn timeouts_are_recorded() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempfile::tempdir()?;
+        assert_eq!(read(temp_dir.path()), None);
+        mark(temp_dir.path(), 600)?;
+        assert_eq!(read(temp_dir.path()), Some(600));
+        clear(temp_dir.path())?;
+        clear(temp_dir.path())?;
+        assert_eq!(read(temp_dir.path()), None);
+        Ok(())
+    }
+}
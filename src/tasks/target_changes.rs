@@ -0,0 +1,257 @@
+//! Comparison of a task's stored target resolution with a fresh one.
+//!
+//! A task keeps the targets its program selected when it was created.  When
+//! targets are registered or removed, or their dependencies change, resolving
+//! the program again would yield a different set; [`compare`] lists the
+//! differences so they can be reviewed before recreating the task.
+
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+
+use crate::program::resolve::{ResolvedCrateExecution, ResolvedProgram};
+
+/// Differences between two resolutions of the same program.
+///
+/// Dependency edges are `(dependent, dependency)` pairs of manifest
+/// directories.  All lists are sorted.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Changes {
+    /// Workspaces only selected by the new resolution.
+    pub added_workspaces: Vec<PathBuf>,
+    /// Workspaces only selected by the old resolution.
+    pub removed_workspaces: Vec<PathBuf>,
+    /// Crates (standalone or workspace members) only in the new resolution.
+    pub added_crates: Vec<PathBuf>,
+    /// Crates (standalone or workspace members) only in the old resolution.
+    pub removed_crates: Vec<PathBuf>,
+    /// Dependency edges only in the new resolution.
+    pub added_dependencies: Vec<(PathBuf, PathBuf)>,
+    /// Dependency edges only in the old resolution.
+    pub removed_dependencies: Vec<(PathBuf, PathBuf)>,
+}
+
+impl Changes {
+    /// Returns `true` if both resolutions select the same targets with the
+    /// same dependencies.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
+/// The JSON output of `task diff-targets`, with the same lists as
+/// [`Changes`].
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct ChangesView {
+    /// Workspaces only selected by the new resolution.
+    pub added_workspaces: Vec<PathBuf>,
+    /// Workspaces only selected by the old resolution.
+    pub removed_workspaces: Vec<PathBuf>,
+    /// Crates only in the new resolution.
+    pub added_crates: Vec<PathBuf>,
+    /// Crates only in the old resolution.
+    pub removed_crates: Vec<PathBuf>,
+    /// Dependency edges only in the new resolution.
+    pub added_dependencies: Vec<DependencyView>,
+    /// Dependency edges only in the old resolution.
+    pub removed_dependencies: Vec<DependencyView>,
+}
+
+/// A dependency edge in a [`ChangesView`].
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct DependencyView {
+    /// The manifest directory of the dependent target.
+    pub dependent: PathBuf,
+    /// The manifest directory of the target it depends on.
+    pub dependency: PathBuf,
+}
+
+impl From<&Changes> for ChangesView {
+    fn from(changes: &Changes) -> Self {
+        let edges = |edges: &[(PathBuf, PathBuf)]| {
+            edges
+                .iter()
+                .map(|(dependent, dependency)| DependencyView {
+                    dependent: dependent.clone(),
+                    dependency: dependency.clone(),
+                })
+                .collect()
+        };
+        Self {
+            added_workspaces: changes.added_workspaces.clone(),
+            removed_workspaces: changes.removed_workspaces.clone(),
+            added_crates: changes.added_crates.clone(),
+            removed_crates: changes.removed_crates.clone(),
+            added_dependencies: edges(&changes.added_dependencies),
+            removed_dependencies: edges(&changes.removed_dependencies),
+        }
+    }
+}
+
+/// Returns the manifest directories of all workspaces in `resolved`.
+fn workspaces(resolved: &ResolvedProgram) -> BTreeSet<PathBuf> {
+    resolved
+        .workspace_executions
+        .iter()
+        .map(|ws| ws.manifest_dir.clone())
+        .collect()
+}
+
+/// Returns all crates in `resolved`, both standalone and workspace members.
+fn crates(resolved: &ResolvedProgram) -> impl Iterator<Item = &ResolvedCrateExecution> {
+    resolved
+        .workspace_executions
+        .iter()
+        .flat_map(|ws| ws.member_crates.iter())
+        .chain(resolved.crate_executions.iter())
+}
+
+/// Returns all `(dependent, dependency)` edges in `resolved`.
+fn dependencies(resolved: &ResolvedProgram) -> BTreeSet<(PathBuf, PathBuf)> {
+    let workspace_edges = resolved.workspace_executions.iter().flat_map(|ws| {
+        ws.dependencies
+            .iter()
+            .map(|dep| (ws.manifest_dir.clone(), dep.clone()))
+    });
+    let crate_edges = crates(resolved).flat_map(|krate| {
+        krate
+            .dependencies
+            .iter()
+            .map(|dep| (krate.manifest_dir.clone(), dep.clone()))
+    });
+    workspace_edges.chain(crate_edges).collect()
+}
+
+/// Returns the elements of `a` missing from `b`, in order.
+fn missing<T: Ord + Clone>(a: &BTreeSet<T>, b: &BTreeSet<T>) -> Vec<T> {
+    a.difference(b).cloned().collect()
+}
+
+/// Lists the differences between the `old` and the `new` resolution.
+#[must_use]
+pub fn compare(old: &ResolvedProgram, new: &ResolvedProgram) -> Changes {
+    let (old_workspaces, new_workspaces) = (workspaces(old), workspaces(new));
+    let old_crates: BTreeSet<PathBuf> = crates(old).map(|c| c.manifest_dir.clone()).collect();
+    let new_crates: BTreeSet<PathBuf> = crates(new).map(|c| c.manifest_dir.clone()).collect();
+    let (old_dependencies, new_dependencies) = (dependencies(old), dependencies(new));
+    Changes {
+        added_workspaces: missing(&new_workspaces, &old_workspaces),
+        removed_workspaces: missing(&old_workspaces, &new_workspaces),
+        added_crates: missing(&new_crates, &old_crates),
+        removed_crates: missing(&old_crates, &new_crates),
+        added_dependencies: missing(&new_dependencies, &old_dependencies),
+        removed_dependencies: missing(&old_dependencies, &new_dependencies),
+    }
+}
+
+/// Describes `changes` with one line per target or dependency edge, prefixed
+/// with `+` if it was added and `-` if it was removed.
+#[must_use]
+pub fn describe(changes: &Changes) -> Vec<String> {
+    let targets = [
+        ('+', "workspace", &changes.added_workspaces),
+        ('-', "workspace", &changes.removed_workspaces),
+        ('+', "crate", &changes.added_crates),
+        ('-', "crate", &changes.removed_crates),
+    ];
+    let dependencies = [
+        ('+', &changes.added_dependencies),
+        ('-', &changes.removed_dependencies),
+    ];
+    let mut lines = Vec::new();
+    for (sign, kind, dirs) in targets {
+        lines.extend(
+            dirs.iter()
+                .map(|dir| format!("{sign} {kind} {}", dir.display())),
+        );
+    }
+    for (sign, edges) in dependencies {
+        lines.extend(edges.iter().map(|(dependent, dependency)| {
+            format!(
+                "{sign} dependency {} -> {}",
+                dependent.display(),
+                dependency.display()
+            )
+        }));
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use pretty_assertions::assert_eq;
+
+    use super::{Changes, ChangesView, compare};
+    use crate::program::resolve::{
+        ResolvedCrateExecution, ResolvedProgram, ResolvedWorkspaceExecution,
+    };
+
+    fn krate(dir: &str, dependencies: &[&str]) -> ResolvedCrateExecution {
+        ResolvedCrateExecution {
+            manifest_dir: PathBuf::from(dir),
+            dependencies: dependencies.iter().map(PathBuf::from).collect(),
+            package: None,
+        }
+    }
+
+    #[test]
+    fn compare_lists_added_and_removed_targets_and_edges() {
+        let old = ResolvedProgram {
+            workspace_executions: vec![ResolvedWorkspaceExecution {
+                manifest_dir: PathBuf::from("/ws"),
+                dependencies: vec![],
+                member_crates: vec![krate("/ws/a", &[]), krate("/ws/b", &["/ws/a"])],
+            }],
+            crate_executions: vec![krate("/old", &[])],
+        };
+        let new = ResolvedProgram {
+            workspace_executions: vec![
+                ResolvedWorkspaceExecution {
+                    manifest_dir: PathBuf::from("/ws"),
+                    dependencies: vec![PathBuf::from("/ws2")],
+                    member_crates: vec![krate("/ws/a", &[]), krate("/ws/b", &[])],
+                },
+                ResolvedWorkspaceExecution {
+                    manifest_dir: PathBuf::from("/ws2"),
+                    dependencies: vec![],
+                    member_crates: vec![krate("/ws2", &[])],
+                },
+            ],
+            crate_executions: vec![],
+        };
+        assert_eq!(
+            compare(&old, &new),
+            Changes {
+                added_workspaces: vec![PathBuf::from("/ws2")],
+                removed_workspaces: vec![],
+                added_crates: vec![PathBuf::from("/ws2")],
+                removed_crates: vec![PathBuf::from("/old")],
+                added_dependencies: vec![(PathBuf::from("/ws"), PathBuf::from("/ws2"))],
+                removed_dependencies: vec![(PathBuf::from("/ws/b"), PathBuf::from("/ws/a"))],
+            }
+        );
+        assert!(compare(&new, &new).is_empty(), "a resolution equals itself");
+    }
+
+    #[test]
+    fn views_name_both_ends_of_dependency_edges() -> Result<(), Box<dyn std::error::Error>> {
+        let changes = Changes {
+            added_dependencies: vec![(PathBuf::from("/ws"), PathBuf::from("/ws2"))],
+            ..Changes::default()
+        };
+        assert_eq!(
+            serde_json::to_value(ChangesView::from(&changes))?,
+            serde_json::json!({
+                "added_workspaces": [],
+                "removed_workspaces": [],
+                "added_crates": [],
+                "removed_crates": [],
+                "added_dependencies": [{ "dependent": "/ws", "dependency": "/ws2" }],
+                "removed_dependencies": [],
+            })
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,83 @@
+//! Confirming each target of `task run all-targets`.
+//!
+//! Release trains are often semi-automated: somebody wants to look at the
+//! published crate or the CI of one repository before the next one is
+//! touched.  With `--interactive` (or `--confirm-each-target`) the run asks
+//! before starting each target whether to continue with it, skip it or abort
+//! the whole run.  Since it only runs one target at a time, this is right
+//! after the previous target finished.
+//!
+//! A skipped target is left as it is, so targets depending on it do not run
+//! either; running the task again offers it again.  Aborting keeps the state of
+//! the targets that already ran.
+
+use std::io::{self, BufRead as _, Write as _};
+use std::path::Path;
+
+use crate::error::Error;
+
+/// What to do with the next target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Answer {
+    /// Run the target.
+    Continue,
+    /// Leave the target and its dependents alone and go on with the others.
+    Skip,
+    /// Start no further targets.
+    Abort,
+}
+
+/// Parses an answer to the prompt; an empty answer continues.
+#[must_use]
+pub fn parse(input: &str) -> Option<Answer> {
+    match input.trim().to_ascii_lowercase().as_str() {
+        "" | "c" | "continue" | "y" | "yes" => Some(Answer::Continue),
+        "s" | "skip" => Some(Answer::Skip),
+        "a" | "abort" | "q" | "quit" => Some(Answer::Abort),
+        _ => None,
+    }
+}
+
+/// Asks on the terminal what to do with the target in `manifest_dir`,
+/// repeating the question until it is answered.  The end of the input
+/// aborts.
+///
+/// # Errors
+///
+/// Returns an error if the terminal cannot be read or written.
+#[expect(clippy::print_stdout, reason = "This is part of the UI, not logging")]
+pub fn ask(manifest_dir: &Path) -> Result<Answer, Error> {
+    let stdin = io::stdin();
+    loop {
+        print!(
+            "Next target: {}. [C]ontinue, [s]kip or [a]bort? ",
+            manifest_dir.display()
+        );
+        io::stdout().flush().map_err(Error::IoError)?;
+        let mut input = String::new();
+        if stdin.lock().read_line(&mut input).map_err(Error::IoError)? == 0 {
+            println!();
+            return Ok(Answer::Abort);
+        }
+        if let Some(answer) = parse(&input) {
+            return Ok(answer);
+        }
+        println!("Please answer c, s or a.");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::{Answer, parse};
+
+    #[test]
+    fn answers_are_parsed() {
+        assert_eq!(parse("\n"), Some(Answer::Continue));
+        assert_eq!(parse("C\n"), Some(Answer::Continue));
+        assert_eq!(parse(" skip "), Some(Answer::Skip));
+        assert_eq!(parse("a"), Some(Answer::Abort));
+        assert_eq!(parse("later"), None);
+    }
+}
@@ -0,0 +1,201 @@
+//! Per-target log files for parallel runs.
+//!
+//! With `task run all-targets --jobs N` and `N > 1`, the output of several
+//! targets would be interleaved on the terminal and in the log file given by
+//! `CARGO_FOR_EACH_LOG_DIR`.  Instead, each target runs inside a `target` span
+//! carrying the path of a `log` file in the target's state directory (`w0/`,
+//! `c1/`), and [`Layer`] appends every event inside that span to the file.
+//! The output of `run` statements is captured rather than shown, so it ends up
+//! in the same file, and the terminal only shows the progress of the targets
+//! (see [`super::progress`]).
+
+use std::fmt::Write as _;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+
+use crate::program::cursor::ProgramCursor;
+
+/// Name of the log file inside a target's state directory.
+const FILE_NAME: &str = "log";
+
+/// Name of the span field holding the path of the log file.
+const LOG_FIELD: &str = "log";
+
+/// Returns the path of the log file of the target `cursor` belongs to.
+#[must_use]
+pub fn path(state_base: &Path, cursor: &ProgramCursor) -> PathBuf {
+    super::revision::target_dir(state_base, cursor).join(FILE_NAME)
+}
+
+/// Returns the span to run the target in `manifest_dir` in; with `log_file`,
+/// events inside the span are also written to that file by [`Layer`].
+#[must_use]
+pub fn span(manifest_dir: &Path, log_file: Option<&Path>) -> tracing::Span {
+    match log_file {
+        Some(log_file) => tracing::info_span!(
+            "target",
+            dir = %manifest_dir.display(),
+            log = %log_file.display()
+        ),
+        None => tracing::info_span!("target", dir = %manifest_dir.display()),
+    }
+}
+
+/// The open log file of a `target` span, stored in the span's extensions.
+struct LogFile(Mutex<fs_err::File>);
+
+/// Collects the `log` field of a span.
+#[derive(Default)]
+struct LogFieldVisitor(Option<PathBuf>);
+
+impl Visit for LogFieldVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == LOG_FIELD {
+            self.0 = Some(PathBuf::from(format!("{value:?}")));
+        }
+    }
+}
+
+/// Formats the fields of an event as a single line, message first.
+#[derive(Default)]
+struct LineVisitor {
+    /// The `message` field.
+    message: String,
+    /// All other fields as `name=value`.
+    fields: String,
+}
+
+impl Visit for LineVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            self.message = format!("{value:?}");
+        } else {
+            let _ignored = write!(self.fields, " {}={value:?}", field.name());
+        }
+    }
+}
+
+/// Tracing layer writing the events of each `target` span to its log file.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Layer;
+
+impl<S> tracing_subscriber::Layer<S> for Layer
+where
+    S: Subscriber + for<'lookup> LookupSpan<'lookup>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let mut visitor = LogFieldVisitor::default();
+        attrs.record(&mut visitor);
+        let Some(log_file) = visitor.0 else {
+            return;
+        };
+        if let Some(parent) = log_file.parent()
+            && let Err(e) = fs_err::create_dir_all(parent)
+        {
+            eprintln_failure(&e);
+            return;
+        }
+        let file = match fs_err::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&log_file)
+        {
+            Ok(file) => file,
+            Err(e) => {
+                eprintln_failure(&e);
+                return;
+            }
+        };
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(LogFile(Mutex::new(file)));
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let Some(span) = ctx
+            .event_scope(event)
+            .and_then(|mut scope| scope.find(|span| span.extensions().get::<LogFile>().is_some()))
+        else {
+            return;
+        };
+        let mut visitor = LineVisitor::default();
+        event.record(&mut visitor);
+        let metadata = event.metadata();
+        let line = format!(
+            "{} {} {}: {}{}\n",
+            humantime::format_rfc3339_seconds(std::time::SystemTime::now()),
+            metadata.level(),
+            metadata.target(),
+            visitor.message,
+            visitor.fields
+        );
+        if let Some(LogFile(file)) = span.extensions().get::<LogFile>()
+            && let Ok(mut file) = file.lock()
+            && let Err(e) = file.write_all(line.as_bytes())
+        {
+            eprintln_failure(&e);
+        }
+    }
+}
+
+/// Reports that a log file could not be written; logging it with tracing
+/// would recurse into [`Layer`].
+#[expect(
+    clippy::print_stderr,
+    reason = "tracing cannot be used from inside a tracing layer"
+)]
+fn eprintln_failure(error: &std::io::Error) {
+    eprintln!("Could not write target log: {error}");
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing_subscriber::layer::SubscriberExt as _;
+
+    use super::{Layer, span};
+
+    #[test]
+    fn events_inside_target_span_go_to_its_log() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempfile::tempdir()?;
+        let first = temp_dir.path().join("w0").join("log");
+        let second = temp_dir.path().join("w1").join("log");
+        let subscriber = tracing_subscriber::registry().with(Layer);
+        tracing::subscriber::with_default(subscriber, || {
+            span(temp_dir.path(), Some(&first)).in_scope(|| {
+                tracing::info!(step = 1, "first target");
+            });
+            span(temp_dir.path(), Some(&second)).in_scope(|| {
+                tracing::info_span!("statement").in_scope(|| tracing::trace!("second target"));
+            });
+            span(temp_dir.path(), None).in_scope(|| tracing::info!("not logged"));
+            tracing::info!("outside of any target");
+        });
+
+        let first_log = fs_err::read_to_string(&first)?;
+        assert!(
+            first_log.contains("INFO") && first_log.contains("first target step=1"),
+            "event with its fields: {first_log}"
+        );
+        assert!(
+            !first_log.contains("second target"),
+            "only events of its own target: {first_log}"
+        );
+        let second_log = fs_err::read_to_string(&second)?;
+        assert!(
+            second_log.contains("TRACE") && second_log.contains("second target"),
+            "event in a nested span: {second_log}"
+        );
+        assert!(
+            !second_log.contains("not logged") && !second_log.contains("outside"),
+            "events of other spans: {second_log}"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,404 @@
+//! Refreshing the targets of a task.
+//!
+//! A task keeps the targets its program selected when it was created, so
+//! after pulling new commits or registering new targets the task would have
+//! to be recreated, losing its progress.  `task refresh` resolves the program
+//! again instead, prints the added and removed targets and stores the new
+//! resolution; with `--refresh-workspaces` the registered workspaces the task
+//! runs on are refreshed first.  `task run --refresh-targets` does both right
+//! before running.
+//!
+//! Workspaces and crates the task was created with explicitly (`--workspace`,
+//! `--crate` and the lists read from files, or one workspace per task with
+//! `task create-per-workspace`) are recorded in `explicit-targets.toml` in the
+//! task directory and stay the targets; only their members and dependencies
+//! are resolved again.  Tasks created before this record existed are resolved
+//! like `task diff-targets` does, from the program alone.
+//!
+//! The execution state of a target is stored under its position in the
+//! resolution, so it is moved along when targets are added or removed before
+//! it.  The state of targets no longer selected is removed.
+
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+
+use tracing::instrument;
+
+use super::{
+    RefreshTaskParameters, completion_cache, load_task_data, named_dir_path, resolution_inputs,
+    resolve_targets, run_lock, state_dir_for_task, target_changes, write_resolved_program,
+};
+use crate::error::Error;
+use crate::program::Program;
+use crate::program::cursor::CursorSegment;
+use crate::program::resolve::{ResolvedCrateExecution, ResolvedProgram};
+
+/// Name of the file recording the explicitly given targets of a task.
+const FILE_NAME: &str = "explicit-targets.toml";
+
+/// Where the targets of a task come from; `None` means the program selects
+/// them.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Selection {
+    /// The explicitly given workspaces.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workspaces: Option<Vec<PathBuf>>,
+    /// The explicitly given standalone crates.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub crates: Option<Vec<PathBuf>>,
+}
+
+impl Selection {
+    /// Records the selection in `task_dir`, unless the program selects all
+    /// targets.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written.
+    pub fn write(&self, task_dir: &Path) -> Result<(), Error> {
+        if self == &Self::default() {
+            return Ok(());
+        }
+        let path = task_dir.join(FILE_NAME);
+        let content = toml::to_string(self).map_err(Error::CouldNotSerializeResolvedProgram)?;
+        crate::utils::write_atomically(&path, content).map_err(Error::IoError)
+    }
+
+    /// Reads the selection recorded in `task_dir`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file exists but cannot be read or parsed.
+    pub fn read(task_dir: &Path) -> Result<Self, Error> {
+        let path = task_dir.join(FILE_NAME);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs_err::read_to_string(&path).map_err(Error::IoError)?;
+        toml::from_str(&content).map_err(|e| Error::CouldNotParseExplicitTargets(path, e))
+    }
+}
+
+/// Returns the new name for each `name(index)` of `old` targets: the one at
+/// their position in `new`, or `None` if they are gone.
+fn renames(
+    old: &[PathBuf],
+    new: &[PathBuf],
+    name: fn(usize) -> CursorSegment,
+) -> HashMap<String, Option<String>> {
+    old.iter()
+        .enumerate()
+        .map(|(old_idx, dir)| {
+            (
+                name(old_idx).to_string(),
+                new.iter()
+                    .position(|d| d == dir)
+                    .map(|new_idx| name(new_idx).to_string()),
+            )
+        })
+        .collect()
+}
+
+/// Returns the manifest directories of `crates`.
+fn dirs(crates: &[ResolvedCrateExecution]) -> Vec<PathBuf> {
+    crates.iter().map(|c| c.manifest_dir.clone()).collect()
+}
+
+/// Renames the state directories of member crates below `dir` according to
+/// `renames`, removing those without a new name.
+///
+/// # Errors
+///
+/// Returns an error if a directory cannot be read, renamed or removed.
+fn rename_members(dir: &Path, renames: &HashMap<String, Option<String>>) -> Result<(), Error> {
+    let mut moved = Vec::new();
+    for entry in fs_err::read_dir(dir).map_err(Error::IoError)? {
+        let path = entry.map_err(Error::IoError)?.path();
+        if !path.is_dir() {
+            continue;
+        }
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        match renames.get(&name) {
+            Some(Some(new_name)) => {
+                let staged = dir.join(format!(".{new_name}"));
+                fs_err::rename(&path, &staged).map_err(Error::IoError)?;
+                moved.push((staged, dir.join(new_name)));
+            }
+            Some(None) => fs_err::remove_dir_all(&path).map_err(Error::IoError)?,
+            None => rename_members(&path, renames)?,
+        }
+    }
+    for (staged, target) in moved {
+        fs_err::rename(&staged, &target).map_err(Error::IoError)?;
+    }
+    Ok(())
+}
+
+/// Moves the execution state in `state_base` from the positions of the
+/// targets in `old` to their positions in `new`, and removes the state of
+/// targets (and workspace members) that are not in `new`.
+///
+/// # Errors
+///
+/// Returns an error if a state directory cannot be moved or removed.
+pub fn remap_state(
+    state_base: &Path,
+    old: &ResolvedProgram,
+    new: &ResolvedProgram,
+) -> Result<(), Error> {
+    if !state_base.exists() {
+        return Ok(());
+    }
+    let staging = state_base.join(".refresh-targets");
+    if staging.exists() {
+        fs_err::remove_dir_all(&staging).map_err(Error::IoError)?;
+    }
+    fs_err::create_dir_all(&staging).map_err(Error::IoError)?;
+    let new_workspaces: Vec<PathBuf> = new
+        .workspace_executions
+        .iter()
+        .map(|w| w.manifest_dir.clone())
+        .collect();
+    let new_crates = dirs(&new.crate_executions);
+    for (old_idx, workspace) in old.workspace_executions.iter().enumerate() {
+        let from = state_base.join(CursorSegment::WorkspaceIteration(old_idx).to_string());
+        if !from.exists() {
+            continue;
+        }
+        let Some(new_idx) = new_workspaces
+            .iter()
+            .position(|dir| dir == &workspace.manifest_dir)
+        else {
+            tracing::warn!(
+                "Removing the state of {}, which is no longer a target",
+                workspace.manifest_dir.display()
+            );
+            fs_err::remove_dir_all(&from).map_err(Error::IoError)?;
+            continue;
+        };
+        let to = staging.join(CursorSegment::WorkspaceIteration(new_idx).to_string());
+        fs_err::rename(&from, &to).map_err(Error::IoError)?;
+        let old_members = dirs(&workspace.member_crates);
+        let new_members = new
+            .workspace_executions
+            .get(new_idx)
+            .map(|w| dirs(&w.member_crates))
+            .unwrap_or_default();
+        if old_members != new_members {
+            rename_members(
+                &to,
+                &renames(&old_members, &new_members, CursorSegment::CrateIteration),
+            )?;
+        }
+    }
+    let crate_renames = renames(
+        &dirs(&old.crate_executions),
+        &new_crates,
+        CursorSegment::CrateIteration,
+    );
+    for (old_idx, krate) in old.crate_executions.iter().enumerate() {
+        let old_name = CursorSegment::CrateIteration(old_idx).to_string();
+        let from = state_base.join(&old_name);
+        if !from.exists() {
+            continue;
+        }
+        match crate_renames.get(&old_name) {
+            Some(Some(new_name)) => {
+                fs_err::rename(&from, staging.join(new_name)).map_err(Error::IoError)?;
+            }
+            _ => {
+                tracing::warn!(
+                    "Removing the state of {}, which is no longer a target",
+                    krate.manifest_dir.display()
+                );
+                fs_err::remove_dir_all(&from).map_err(Error::IoError)?;
+            }
+        }
+    }
+    for entry in fs_err::read_dir(&staging).map_err(Error::IoError)? {
+        let entry = entry.map_err(Error::IoError)?;
+        fs_err::rename(entry.path(), state_base.join(entry.file_name())).map_err(Error::IoError)?;
+    }
+    fs_err::remove_dir(&staging).map_err(Error::IoError)?;
+    completion_cache::invalidate(state_base);
+    Ok(())
+}
+
+/// Resolves the `program` of the task `task_name` with the targets `old`
+/// again and stores the result, moving the execution state along.  With
+/// `refresh_workspaces` the registered workspaces the task runs on are
+/// refreshed first.  Returns the new resolution.
+///
+/// # Errors
+///
+/// Returns an error if the configuration cannot be loaded, the
+/// workspaces cannot be refreshed, the program cannot be resolved or the
+/// task files cannot be written.
+#[expect(clippy::print_stdout, reason = "This is part of the UI, not logging")]
+pub async fn refresh(
+    task_name: &str,
+    program: &Program,
+    old: &ResolvedProgram,
+    environment: &crate::Environment,
+    refresh_workspaces: bool,
+) -> Result<ResolvedProgram, Error> {
+    if refresh_workspaces {
+        refresh_registered_workspaces(old, environment).await?;
+    }
+    let task_dir = named_dir_path(task_name, environment)?;
+    let new = resolve_targets(program, &Selection::read(&task_dir)?, environment, false)?;
+    let changes = target_changes::compare(old, &new);
+    if changes.is_empty() {
+        println!("The targets of task {task_name} did not change");
+    } else {
+        println!("The targets of task {task_name} changed:");
+        for line in target_changes::describe(&changes) {
+            println!("  {line}");
+        }
+    }
+    remap_state(&state_dir_for_task(task_name, environment)?, old, &new)?;
+    write_resolved_program(&task_dir, &new)?;
+    resolution_inputs::write(&task_dir, &resolution_inputs::collect(&new, environment)?)?;
+    Ok(new)
+}
+
+/// Refreshes the registered workspaces the targets `resolved` belong to.
+///
+/// # Errors
+///
+/// Returns an error if the configuration cannot be loaded or the
+/// workspaces cannot be refreshed.
+async fn refresh_registered_workspaces(
+    resolved: &ResolvedProgram,
+    environment: &crate::Environment,
+) -> Result<(), Error> {
+    let config = crate::Config::load(environment)?;
+    let mut workspaces: BTreeSet<PathBuf> = resolved
+        .workspace_executions
+        .iter()
+        .map(|w| w.manifest_dir.clone())
+        .collect();
+    for krate in &resolved.crate_executions {
+        workspaces.insert(
+            config
+                .crates
+                .iter()
+                .find(|c| c.manifest_dir == krate.manifest_dir)
+                .map_or_else(
+                    || krate.manifest_dir.clone(),
+                    |c| c.workspace_manifest_dir.clone(),
+                ),
+        );
+    }
+    if !workspaces.is_empty() {
+        crate::targets::refresh_command(
+            crate::targets::RefreshParameters {
+                shallow: false,
+                deep: false,
+                workspaces: workspaces.into_iter().collect(),
+            },
+            environment.clone(),
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// Resolves the targets of a task again, for `task refresh`.
+///
+/// # Errors
+///
+/// Returns an error if the task cannot be loaded, is being run, or cannot
+/// be refreshed.
+#[instrument]
+pub async fn task_refresh_command(
+    params: RefreshTaskParameters,
+    environment: crate::Environment,
+) -> Result<(), Error> {
+    let (program, resolved) = load_task_data(&params.name, &environment)?;
+    let _lock = run_lock::acquire(
+        &params.name,
+        &state_dir_for_task(&params.name, &environment)?,
+    )?;
+    refresh(
+        &params.name,
+        &program,
+        &resolved,
+        &environment,
+        params.refresh_workspaces,
+    )
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use pretty_assertions::assert_eq;
+
+    use super::remap_state;
+    use crate::program::resolve::{
+        ResolvedCrateExecution, ResolvedProgram, ResolvedWorkspaceExecution,
+    };
+
+    fn krate(dir: &str) -> ResolvedCrateExecution {
+        ResolvedCrateExecution {
+            manifest_dir: PathBuf::from(dir),
+            dependencies: vec![],
+            package: None,
+        }
+    }
+
+    fn workspace(dir: &str, members: &[&str]) -> ResolvedWorkspaceExecution {
+        ResolvedWorkspaceExecution {
+            manifest_dir: PathBuf::from(dir),
+            dependencies: vec![],
+            member_crates: members.iter().map(|m| krate(m)).collect(),
+        }
+    }
+
+    /// Creates a state directory `path` below `base` holding a file naming
+    /// the target it belongs to.
+    fn state(base: &Path, path: &str, target: &str) -> Result<(), Box<dyn std::error::Error>> {
+        fs_err::create_dir_all(base.join(path))?;
+        fs_err::write(base.join(path).join("target"), target)?;
+        Ok(())
+    }
+
+    #[test]
+    fn state_follows_the_targets() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempfile::tempdir()?;
+        let base = temp_dir.path();
+        let old = ResolvedProgram {
+            workspace_executions: vec![workspace("/b", &["/b/x", "/b/y"]), workspace("/gone", &[])],
+            crate_executions: vec![krate("/s")],
+        };
+        let new = ResolvedProgram {
+            workspace_executions: vec![workspace("/a", &[]), workspace("/b", &["/b/new", "/b/y"])],
+            crate_executions: vec![krate("/t"), krate("/s")],
+        };
+        state(base, "w0/s0", "/b")?;
+        state(base, "w0/s1/c0", "/b/x")?;
+        state(base, "w0/s1/c1", "/b/y")?;
+        state(base, "w1/s0", "/gone")?;
+        state(base, "c0/s0", "/s")?;
+
+        remap_state(base, &old, &new)?;
+        let read = |path: &str| fs_err::read_to_string(base.join(path).join("target")).ok();
+        assert_eq!(read("w1/s0").as_deref(), Some("/b"));
+        assert_eq!(read("w1/s1/c1").as_deref(), Some("/b/y"));
+        assert_eq!(
+            read("w1/s1/c0"),
+            None,
+            "the state of removed members is gone"
+        );
+        assert_eq!(read("w0/s0"), None, "the state of removed targets is gone");
+        assert_eq!(read("c1/s0").as_deref(), Some("/s"));
+        assert!(!base.join("c0").exists(), "new targets start without state");
+        Ok(())
+    }
+}
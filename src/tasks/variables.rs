@@ -0,0 +1,159 @@
+//! Built-in `${variable}` interpolations describing the current target.
+//!
+//! Snapshot references (`${name.field}`) need a `snapshot_metadata` statement
+//! first.  The most common values are available without one: a reference
+//! without a `.` names one of the variables below, which are looked up when
+//! the step runs, so e.g. `${crate_version}` reflects a version bumped by an
+//! earlier step.  `cargo metadata` is only run if a variable needs it.
+//!
+//! Tools that have to run at the workspace root but act on one member, like
+//! `cargo build -p ${crate_name}` with `cwd "${workspace_dir}"`, use the
+//! workspace variables, and `${relative_manifest_dir}` gives the member's
+//! directory relative to the root (`.` for the root itself).
+
+use std::path::Path;
+use std::sync::Arc;
+
+use cargo_metadata::Metadata;
+
+use crate::error::Error;
+use crate::metadata::{MetadataProvider as _, Subprocess};
+
+/// The names of all built-in variables, in the order they are documented.
+pub const NAMES: &[&str] = &[
+    "crate_name",
+    "crate_version",
+    "manifest_dir",
+    "relative_manifest_dir",
+    "workspace_root",
+    "workspace_dir",
+];
+
+/// Looks up variables for one target, running `cargo metadata` at most once.
+#[derive(Debug)]
+pub struct Lookup<'a> {
+    /// The directory of the target.
+    manifest_dir: &'a Path,
+    /// The metadata of the target, once it was needed.
+    metadata: Option<Arc<Metadata>>,
+}
+
+impl<'a> Lookup<'a> {
+    /// Creates a lookup for the target in `manifest_dir`.
+    #[must_use]
+    pub const fn new(manifest_dir: &'a Path) -> Self {
+        Self {
+            manifest_dir,
+            metadata: None,
+        }
+    }
+
+    /// Returns the metadata of the target, running `cargo metadata` on the
+    /// first call.
+    fn metadata(&mut self) -> Result<Arc<Metadata>, Error> {
+        if let Some(metadata) = &self.metadata {
+            return Ok(Arc::clone(metadata));
+        }
+        let metadata = Subprocess.metadata(self.manifest_dir)?;
+        self.metadata = Some(Arc::clone(&metadata));
+        Ok(metadata)
+    }
+
+    /// Returns a field of the package whose manifest is in the target's
+    /// directory.
+    fn package_field(
+        &mut self,
+        name: &str,
+        field: fn(&cargo_metadata::Package) -> String,
+    ) -> Result<String, Error> {
+        let metadata = self.metadata()?;
+        let manifest_path = self.manifest_dir.join("Cargo.toml");
+        metadata
+            .packages
+            .iter()
+            .find(|package| package.manifest_path.as_std_path() == manifest_path)
+            .map(field)
+            .ok_or_else(|| {
+                Error::NoPackageForVariable(name.to_owned(), self.manifest_dir.to_path_buf())
+            })
+    }
+
+    /// Returns the value of the variable `name`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `name` is not a known variable, if `cargo
+    /// metadata` fails, or if a package variable is used for a virtual
+    /// workspace.
+    pub fn get(&mut self, name: &str) -> Result<String, Error> {
+        match name {
+            // the name is read from the manifest directly, without `cargo metadata`
+            "crate_name" => match crate::targets::package_name(self.manifest_dir) {
+                Some(crate_name) => Ok(crate_name),
+                None => self.package_field(name, |package| package.name.to_string()),
+            },
+            "crate_version" => self.package_field(name, |package| package.version.to_string()),
+            "manifest_dir" => Ok(self.manifest_dir.display().to_string()),
+            "relative_manifest_dir" => {
+                let metadata = self.metadata()?;
+                let relative = self
+                    .manifest_dir
+                    .strip_prefix(metadata.workspace_root.as_std_path())
+                    .unwrap_or(self.manifest_dir);
+                if relative.as_os_str().is_empty() {
+                    Ok(".".to_owned())
+                } else {
+                    Ok(relative.display().to_string())
+                }
+            }
+            "workspace_root" | "workspace_dir" => Ok(self.metadata()?.workspace_root.to_string()),
+            _ => Err(Error::UnknownVariable(name.to_owned())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::Lookup;
+    use crate::error::Error;
+
+    #[test]
+    fn variables_describe_the_target() -> Result<(), Box<dyn std::error::Error>> {
+        let temp = tempfile::tempdir()?;
+        let root = fs_err::canonicalize(temp.path())?;
+        fs_err::write(
+            root.join("Cargo.toml"),
+            "[workspace]\nmembers = [\"member\"]\nresolver = \"2\"\n",
+        )?;
+        let member = root.join("member");
+        fs_err::create_dir_all(member.join("src"))?;
+        fs_err::write(
+            member.join("Cargo.toml"),
+            "[package]\nname = \"member\"\nversion = \"1.4.0\"\nedition = \"2021\"\n",
+        )?;
+        fs_err::write(member.join("src").join("lib.rs"), "")?;
+
+        let mut lookup = Lookup::new(&member);
+        assert_eq!(lookup.get("crate_name")?, "member");
+        assert_eq!(lookup.get("crate_version")?, "1.4.0");
+        assert_eq!(lookup.get("manifest_dir")?, member.display().to_string());
+        assert_eq!(lookup.get("workspace_root")?, root.display().to_string());
+        assert_eq!(lookup.get("workspace_dir")?, root.display().to_string());
+        assert_eq!(lookup.get("relative_manifest_dir")?, "member");
+        assert_eq!(Lookup::new(&root).get("relative_manifest_dir")?, ".");
+        assert!(
+            matches!(lookup.get("bogus"), Err(Error::UnknownVariable(_))),
+            "unknown variables are rejected"
+        );
+        assert!(
+            matches!(
+                Lookup::new(&root).get("crate_name"),
+                Err(Error::NoPackageForVariable(_, _))
+            ),
+            "a virtual workspace has no package"
+        );
+        Ok(())
+    }
+}
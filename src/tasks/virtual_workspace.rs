@@ -0,0 +1,120 @@
+//! `run` statements that need a package, in virtual workspaces.
+//!
+//! A workspace root with a virtual manifest has no package of its own, so
+//! cargo commands that work on exactly one package, like `cargo publish`,
+//! fail there with a message about the manifest that does not mention the
+//! task.  Before such a command starts in a workspace root, `task run` checks
+//! whether the root is virtual and fails with an error naming the command and
+//! the ways to run it for the members instead.
+
+use std::path::Path;
+
+use crate::error::Error;
+
+/// Cargo subcommands that operate on a single package and fail in a virtual
+/// workspace root unless a package is selected.
+const PACKAGE_SUBCOMMANDS: &[&str] = &["add", "package", "publish", "remove"];
+
+/// Arguments that select the package(s) a cargo subcommand works on.
+const PACKAGE_SELECTORS: &[&str] = &["-p", "--package", "--workspace", "--manifest-path"];
+
+/// Returns the cargo subcommand of `command` with `args` if it needs a single
+/// package and none is selected by the arguments.
+#[must_use]
+pub fn unselected_package_subcommand<'a>(command: &str, args: &'a [String]) -> Option<&'a str> {
+    if command != "cargo" {
+        return None;
+    }
+    let args: Vec<&str> = args
+        .iter()
+        .map(String::as_str)
+        .take_while(|arg| *arg != "--")
+        .collect();
+    let subcommand = args
+        .iter()
+        .find(|arg| !arg.starts_with('-') && !arg.starts_with('+'))?;
+    let selected = args.iter().any(|arg| {
+        PACKAGE_SELECTORS.iter().any(|selector| {
+            arg == selector
+                || arg
+                    .strip_prefix(selector)
+                    .is_some_and(|rest| rest.starts_with('=') || *selector == "-p")
+        })
+    });
+    (PACKAGE_SUBCOMMANDS.contains(subcommand) && !selected).then_some(*subcommand)
+}
+
+/// Fails if `command` with `args` needs a single package but `manifest_dir`
+/// is the root of a virtual workspace.
+///
+/// # Errors
+///
+/// Returns [`Error::NeedsPackageContext`] in that case.
+pub fn check(command: &str, args: &[String], manifest_dir: &Path) -> Result<(), Error> {
+    match unselected_package_subcommand(command, args) {
+        Some(subcommand) if crate::targets::is_virtual_manifest(manifest_dir) => Err(
+            Error::NeedsPackageContext(subcommand.to_owned(), manifest_dir.to_path_buf()),
+        ),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::{check, unselected_package_subcommand};
+
+    /// Converts `args` to owned strings.
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|&arg| arg.to_owned()).collect()
+    }
+
+    #[test]
+    fn package_subcommands_without_selection_are_detected() {
+        for (command, arguments, expected) in [
+            ("cargo", &["publish"][..], Some("publish")),
+            (
+                "cargo",
+                &["+nightly", "--locked", "package"][..],
+                Some("package"),
+            ),
+            ("cargo", &["publish", "-p", "foo"][..], None),
+            ("cargo", &["publish", "-pfoo"][..], None),
+            ("cargo", &["add", "--package=foo", "serde"][..], None),
+            ("cargo", &["publish", "--workspace"][..], None),
+            ("cargo", &["build"][..], None),
+            ("cargo", &["run", "--", "publish"][..], None),
+            ("git", &["publish"][..], None),
+        ] {
+            assert_eq!(
+                unselected_package_subcommand(command, &args(arguments)),
+                expected,
+                "{command} {arguments:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn only_virtual_roots_are_rejected() -> Result<(), Box<dyn std::error::Error>> {
+        let temp_dir = tempfile::tempdir()?;
+        let root = temp_dir.path().join("virtual");
+        let package = temp_dir.path().join("package");
+        fs_err::create_dir_all(&root)?;
+        fs_err::create_dir_all(&package)?;
+        fs_err::write(root.join("Cargo.toml"), "[workspace]\nmembers = [\"a\"]\n")?;
+        fs_err::write(
+            package.join("Cargo.toml"),
+            "[package]\nname = \"b\"\n\n[workspace]\n",
+        )?;
+
+        let result = check("cargo", &args(&["publish"]), &root);
+        assert!(
+            matches!(result, Err(crate::error::Error::NeedsPackageContext(ref subcommand, _)) if subcommand == "publish"),
+            "expected publish in a virtual root to be rejected, got {result:?}"
+        );
+        check("cargo", &args(&["publish"]), &package)?;
+        check("cargo", &args(&["publish", "--workspace"]), &root)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,183 @@
+//! Reviewing the changes a task made to its targets.
+//!
+//! Tasks that edit files (a dependency bump, a codemod, a formatting pass)
+//! usually have a step that commits or pushes afterwards.  `task diff` shows
+//! what the task changed in all of its workspaces and standalone crates in one
+//! place before that step runs: `git diff --stat HEAD` for each target with
+//! changes, staged or not, followed by files git does not track yet, and a
+//! total over all targets.  With `--full` the complete diffs are shown
+//! instead of the statistics.
+//!
+//! Each target is diffed in its manifest directory only (`-- .`), so
+//! workspaces and crates sharing a repository each show their own changes.
+
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use tracing::instrument;
+
+use super::{DiffParameters, load_task_data};
+use crate::error::Error;
+
+/// The sizes of the changes of one or more targets.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Totals {
+    /// The number of changed files.
+    pub files: usize,
+    /// The number of added lines.
+    pub insertions: u64,
+    /// The number of removed lines.
+    pub deletions: u64,
+}
+
+impl Totals {
+    /// Adds `other` to these totals.
+    pub const fn add(&mut self, other: Self) {
+        self.files = self.files.saturating_add(other.files);
+        self.insertions = self.insertions.saturating_add(other.insertions);
+        self.deletions = self.deletions.saturating_add(other.deletions);
+    }
+}
+
+impl std::fmt::Display for Totals {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} file(s) changed, {} insertion(s)(+), {} deletion(s)(-)",
+            self.files, self.insertions, self.deletions
+        )
+    }
+}
+
+/// Sums up the output of `git diff --numstat`; binary files count as
+/// changed files without lines.
+#[must_use]
+pub fn parse_numstat(output: &str) -> Totals {
+    let mut totals = Totals::default();
+    for line in output.lines().filter(|line| !line.trim().is_empty()) {
+        let mut fields = line.split('\t');
+        let insertions = fields.next().and_then(|n| n.parse().ok()).unwrap_or(0);
+        let deletions = fields.next().and_then(|n| n.parse().ok()).unwrap_or(0);
+        totals.add(Totals {
+            files: 1,
+            insertions,
+            deletions,
+        });
+    }
+    totals
+}
+
+/// Runs `git` with `args` in `dir` and returns its output, or `None` if it
+/// fails, e.g. because `dir` is not in a repository.
+///
+/// # Errors
+///
+/// Returns an error if `git` cannot be run.
+fn git(dir: &Path, args: &[&str]) -> Result<Option<String>, Error> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .map_err(|e| {
+            Error::CommandExecutionFailed(format!("git {}", args.join(" ")), dir.to_path_buf(), e)
+        })?;
+    if !output.status.success() {
+        tracing::warn!(
+            "git {} failed in {}: {}",
+            args.join(" "),
+            dir.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+        return Ok(None);
+    }
+    Ok(Some(String::from_utf8_lossy(&output.stdout).into_owned()))
+}
+
+/// Prints the changes in the working trees of the targets of a task and
+/// their total.
+///
+/// # Errors
+///
+/// Returns an error if the task cannot be loaded or `git` cannot be run.
+#[instrument]
+#[expect(clippy::print_stdout, reason = "This is part of the UI, not logging")]
+pub async fn task_diff_command(
+    params: DiffParameters,
+    environment: crate::Environment,
+) -> Result<(), Error> {
+    let (_program, resolved) = load_task_data(&params.name, &environment)?;
+    let dirs: Vec<&Path> = resolved
+        .workspace_executions
+        .iter()
+        .map(|w| w.manifest_dir.as_path())
+        .chain(
+            resolved
+                .crate_executions
+                .iter()
+                .map(|c| c.manifest_dir.as_path()),
+        )
+        .collect();
+    let mut totals = Totals::default();
+    let mut changed: usize = 0;
+    let mut untracked_files: usize = 0;
+    for dir in &dirs {
+        let Some(numstat) = git(dir, &["diff", "--numstat", "HEAD", "--", "."])? else {
+            continue;
+        };
+        let untracked = git(
+            dir,
+            &["ls-files", "--others", "--exclude-standard", "--", "."],
+        )?
+        .unwrap_or_default();
+        let target_totals = parse_numstat(&numstat);
+        if target_totals == Totals::default() && untracked.trim().is_empty() {
+            continue;
+        }
+        changed = changed.saturating_add(1);
+        totals.add(target_totals);
+        println!("==> {} <==", dir.display());
+        if target_totals != Totals::default() {
+            let args: &[&str] = if params.full {
+                &["diff", "HEAD", "--", "."]
+            } else {
+                &["diff", "--stat", "HEAD", "--", "."]
+            };
+            if let Some(diff) = git(dir, args)? {
+                print!("{diff}");
+            }
+        }
+        for file in untracked.lines() {
+            untracked_files = untracked_files.saturating_add(1);
+            println!(" untracked: {file}");
+        }
+        println!();
+    }
+    println!("{changed} of {} target(s) changed: {totals}", dirs.len());
+    if untracked_files > 0 {
+        println!("{untracked_files} untracked file(s)");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::{Totals, parse_numstat};
+
+    #[test]
+    fn numstat_output_is_summed_up() {
+        assert_eq!(
+            parse_numstat("3\t1\tCargo.toml\n10\t0\tsrc/lib.rs\n-\t-\tlogo.png\n"),
+            Totals {
+                files: 3,
+                insertions: 13,
+                deletions: 1,
+            }
+        );
+        assert_eq!(parse_numstat(""), Totals::default());
+    }
+}
@@ -25,9 +25,7 @@ pub fn command_is_executable(command: &str, environment: &crate::Environment) ->
 #[must_use]
 pub fn is_executable(path: &std::path::Path) -> bool {
     use std::os::unix::fs::PermissionsExt as _;
-    fs_err::metadata(path)
-        .map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
-        .unwrap_or(false)
+    fs_err::metadata(path).is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
 }
 
 /// checks if the given path is an executable file
@@ -75,8 +73,10 @@ use std::process::{Command, Output, Stdio};
 /// Executes a command, optionally suppressing its stdout/stderr and tracing them instead.
 ///
 /// If `environment.suppress_subprocess_output` is `true`, the command's stdout and stderr
-/// are captured and logged at `tracing::trace` level. Otherwise, they are inherited
-/// from the parent process.
+/// are captured and logged at `tracing::trace` level; for a target of a parallel
+/// run with `--prefix-output` they are also printed line by line as they
+/// arrive, each line prefixed with the name of the target. Otherwise, they are
+/// inherited from the parent process.
 ///
 /// # Arguments
 ///
@@ -98,9 +98,11 @@ pub fn execute_command(
             .stdout(Stdio::piped())
             .stderr(Stdio::piped());
 
-        let output = command.output().map_err(|e| {
-            Error::CommandExecutionFailed(format!("{command:?}"), cwd.to_path_buf(), e)
-        })?;
+        let output = match crate::tasks::progress::prefix() {
+            Some(prefix) => execute_prefixed(command, &prefix),
+            None => command.output(),
+        }
+        .map_err(|e| Error::CommandExecutionFailed(format!("{command:?}"), cwd.to_path_buf(), e))?;
 
         tracing::trace!(
             "Command stdout: {}",
@@ -125,9 +127,111 @@ pub fn execute_command(
     }
 }
 
+/// prints `line` of the output of a command prefixed with `prefix`, to stderr
+/// if `stderr` is set and to stdout otherwise
+#[expect(clippy::print_stdout, reason = "This is part of the UI, not logging")]
+#[expect(clippy::print_stderr, reason = "This is part of the UI, not logging")]
+pub fn print_prefixed(prefix: &str, line: &[u8], stderr: bool) {
+    let line = crate::tasks::progress::prefixed_line(prefix, &String::from_utf8_lossy(line));
+    if stderr {
+        eprintln!("{line}");
+    } else {
+        println!("{line}");
+    }
+}
+
+/// reads `pipe` to the end, printing each line with [`print_prefixed`] as it
+/// arrives, and returns everything read
+fn stream_prefixed(pipe: impl std::io::Read, prefix: &str, stderr: bool) -> Vec<u8> {
+    use std::io::BufRead as _;
+    let mut reader = std::io::BufReader::new(pipe);
+    let mut buf = Vec::new();
+    loop {
+        let start = buf.len();
+        match reader.read_until(b'\n', &mut buf) {
+            Ok(0) => break,
+            Ok(_) => print_prefixed(prefix, buf.get(start..).unwrap_or_default(), stderr),
+            Err(e) => {
+                tracing::warn!("Could not read the output of the command: {e}");
+                break;
+            }
+        }
+    }
+    buf
+}
+
+/// runs `command`, whose stdout and stderr are piped, printing their lines
+/// prefixed with `prefix` as they arrive
+///
+/// # Errors
+///
+/// returns an error if the command cannot be started or waited for
+fn execute_prefixed(command: &mut Command, prefix: &str) -> Result<Output, std::io::Error> {
+    let mut child = command.spawn()?;
+    let stdout_pipe = child.stdout.take();
+    let stderr_pipe = child.stderr.take();
+    let (stdout, stderr) = std::thread::scope(|scope| {
+        let stdout = scope.spawn(|| {
+            stdout_pipe
+                .map(|pipe| stream_prefixed(pipe, prefix, false))
+                .unwrap_or_default()
+        });
+        let stderr = scope.spawn(|| {
+            stderr_pipe
+                .map(|pipe| stream_prefixed(pipe, prefix, true))
+                .unwrap_or_default()
+        });
+        (
+            stdout.join().unwrap_or_default(),
+            stderr.join().unwrap_or_default(),
+        )
+    });
+    Ok(Output {
+        status: child.wait()?,
+        stdout,
+        stderr,
+    })
+}
+
+/// writes `content` to `path` without ever leaving a partially written file
+///
+/// the content is written to a temporary file next to `path`, flushed to
+/// disk and then renamed over `path`, so after a crash `path` has either its
+/// old or its new content
+///
+/// # Errors
+///
+/// returns an error if the temporary file cannot be written or renamed
+pub fn write_atomically(
+    path: &std::path::Path,
+    content: impl AsRef<[u8]>,
+) -> Result<(), std::io::Error> {
+    use std::io::Write as _;
+    let file_name = path.file_name().map_or_else(
+        || std::ffi::OsString::from("file"),
+        std::ffi::OsStr::to_os_string,
+    );
+    let mut temp_name = std::ffi::OsString::from(".");
+    temp_name.push(&file_name);
+    temp_name.push(format!(".{}.tmp", std::process::id()));
+    let temp_path = path.with_file_name(temp_name);
+    let result = (|| {
+        let mut file = fs_err::File::create(&temp_path)?;
+        file.write_all(content.as_ref())?;
+        file.sync_all()?;
+        fs_err::rename(&temp_path, path)
+    })();
+    if result.is_err() {
+        let _ignored = fs_err::remove_file(&temp_path);
+    }
+    result
+}
+
 #[cfg(test)]
 mod tests {
-    use super::command_is_executable;
+    use pretty_assertions::assert_eq;
+
+    use super::{command_is_executable, execute_command, write_atomically};
     use crate::Environment;
     use tempfile::tempdir;
 
@@ -137,6 +241,7 @@ mod tests {
             state_dir: std::path::PathBuf::new(),
             paths,
             suppress_subprocess_output: true,
+            local_root: None,
         }
     }
 
@@ -207,4 +312,41 @@ mod tests {
             "absolute path to non-existent file should not be found"
         );
     }
+
+    /// An atomic write replaces the content and leaves no temporary file.
+    #[test]
+    fn test_write_atomically_replaces_the_file() -> Result<(), Box<dyn std::error::Error>> {
+        let temp = tempdir()?;
+        let path = temp.path().join("cargo-for-each.toml");
+        write_atomically(&path, "old")?;
+        write_atomically(&path, "new")?;
+        assert_eq!(fs_err::read_to_string(&path)?, "new");
+        assert_eq!(
+            fs_err::read_dir(temp.path())?.count(),
+            1,
+            "no temporary file is left behind"
+        );
+        assert!(
+            write_atomically(&temp.path().join("missing").join("file"), "x").is_err(),
+            "errors are reported"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_prefixed_output_is_still_captured() -> Result<(), Box<dyn std::error::Error>> {
+        let temp = tempdir()?;
+        let env = env_with_paths(vec![]);
+        let mut command = std::process::Command::new("sh");
+        command.args(["-c", "echo one; echo two >&2; printf three"]);
+        let output = crate::tasks::progress::scope(
+            crate::tasks::progress::Target::Prefixed("api".to_owned()),
+            async { execute_command(&mut command, &env, temp.path()) },
+        )
+        .await?;
+        assert!(output.status.success(), "the command succeeds");
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "one\nthree");
+        assert_eq!(String::from_utf8_lossy(&output.stderr), "two\n");
+        Ok(())
+    }
 }